@@ -3,6 +3,7 @@
 use std::fs::File;
 use std::io::Read;
 use std::path::PathBuf;
+use std::sync::OnceLock;
 
 use proc_macro::TokenStream;
 use proc_macro2::Span;
@@ -13,17 +14,15 @@ use syn::parse::Parser;
 use syn::punctuated::Punctuated;
 use syn::spanned::Spanned;
 use syn::{
-    parse, Attribute, Expr, ExprCast, ExprLit, Ident, Item, ItemEnum, ItemStruct, Lit, Meta, Path,
-    Token, Type, TypePath, Visibility,
+    parse, Attribute, Expr, ExprCast, ExprLit, ExprPath, Ident, Item, ItemEnum, ItemImpl,
+    ItemStruct, Lit, Meta, Path, Token, Type, TypePath, Visibility,
 };
 
-use once_cell::sync::OnceCell;
-
 struct GlobalState {
     declared_identifiers: Vec<String>,
 }
 
-static STATE: OnceCell<GlobalState> = OnceCell::new();
+static STATE: OnceLock<GlobalState> = OnceLock::new();
 
 fn get_state() -> &'static GlobalState {
     STATE.get_or_init(|| {
@@ -67,6 +66,8 @@ struct FieldArgs {
     netlink_type: Option<Path>,
     override_function_name: Option<String>,
     optional: bool,
+    stub_if_missing: bool,
+    required: bool,
 }
 
 fn parse_field_args(input: proc_macro2::TokenStream) -> Result<FieldArgs, Diagnostic> {
@@ -114,12 +115,47 @@ fn parse_field_args(input: proc_macro2::TokenStream) -> Result<FieldArgs, Diagno
                             return Err(namevalue.value.span().error("Expected a boolean"));
                         }
                     }
+                    "stub_if_missing" => {
+                        if let Expr::Lit(ExprLit {
+                            lit: Lit::Bool(boolean),
+                            ..
+                        }) = &namevalue.value
+                        {
+                            args.stub_if_missing = boolean.value;
+                        } else {
+                            return Err(namevalue.value.span().error("Expected a boolean"));
+                        }
+                    }
+                    "required" => {
+                        if let Expr::Lit(ExprLit {
+                            lit: Lit::Bool(boolean),
+                            ..
+                        }) = &namevalue.value
+                        {
+                            args.required = boolean.value;
+                        } else {
+                            return Err(namevalue.value.span().error("Expected a boolean"));
+                        }
+                    }
                     _ => return Err(arg.span().error("Unsupported macro parameter")),
                 }
             }
             _ => return Err(arg.span().error("Unrecognized argument")),
         }
     }
+    if args.stub_if_missing && !args.optional {
+        return Err(Diagnostic::new(
+            Level::Error,
+            "stub_if_missing only applies to optional fields".to_string(),
+        ));
+    }
+    // the builders could never be built on the kernels lacking the attribute
+    if args.required && args.optional {
+        return Err(Diagnostic::new(
+            Level::Error,
+            "optional fields cannot be required".to_string(),
+        ));
+    }
     Ok(args)
 }
 
@@ -127,6 +163,7 @@ struct StructArgs {
     nested: bool,
     derive_decoder: bool,
     derive_deserialize: bool,
+    builder: Option<Ident>,
 }
 
 impl Default for StructArgs {
@@ -135,6 +172,7 @@ impl Default for StructArgs {
             nested: false,
             derive_decoder: true,
             derive_deserialize: true,
+            builder: None,
         }
     }
 }
@@ -152,6 +190,17 @@ fn parse_struct_args(input: TokenStream) -> Result<StructArgs, Diagnostic> {
                 .get_ident()
                 .expect("the macro parameter is not an ident?")
                 .to_string();
+            if key == "builder" {
+                if let Expr::Lit(ExprLit {
+                    lit: Lit::Str(val), ..
+                }) = &namevalue.value
+                {
+                    args.builder = Some(Ident::new(&val.value(), val.span()));
+                    continue;
+                } else {
+                    return Err(namevalue.value.span().error("Expected a string literal"));
+                }
+            }
             if let Expr::Lit(ExprLit {
                 lit: Lit::Bool(boolean),
                 ..
@@ -179,6 +228,154 @@ fn parse_struct_args(input: TokenStream) -> Result<StructArgs, Diagnostic> {
     Ok(args)
 }
 
+/// Returns the name of a generated method, e.g. `get_<name>`, taking the name override of the
+/// field into account.
+fn function_name(prefix: &str, field_name: &Ident, args: &FieldArgs) -> Ident {
+    let field_str = args
+        .override_function_name
+        .clone()
+        .unwrap_or_else(|| field_name.to_string());
+    Ident::new(&format!("{}{}", prefix, field_str), field_name.span())
+}
+
+/// Whether `ty` is one of the integer types encoded in network byte order by the attributes.
+fn is_integer_type(ty: &Type) -> bool {
+    match ty {
+        Type::Path(TypePath { qself: None, path }) => path.get_ident().is_some_and(|ident| {
+            ["u8", "u16", "u32", "u64", "i32"].contains(&ident.to_string().as_str())
+        }),
+        _ => false,
+    }
+}
+
+/// Converts the CamelCase identifier `name` to snake_case.
+fn snake_case(name: &Ident) -> String {
+    let mut res = String::new();
+    for (i, c) in name.to_string().chars().enumerate() {
+        if c.is_uppercase() {
+            if i != 0 {
+                res.push('_');
+            }
+            res.extend(c.to_lowercase());
+        } else {
+            res.push(c);
+        }
+    }
+    res
+}
+
+/// Generates the typestate builder `builder` of the structure `name`, with one type parameter per
+/// required field, which records whether the field was set: `build()` is only implemented once
+/// all of them are `AttributePresent`.
+fn generate_builder(
+    name: &Ident,
+    builder: &Ident,
+    vis: &Visibility,
+    fields: &[Field],
+) -> proc_macro2::TokenStream {
+    let required: Vec<&Field> = fields.iter().filter(|f| f.args.required).collect();
+    let state_params: Vec<Ident> = required
+        .iter()
+        .map(|field| {
+            let camel_case: String = field
+                .name
+                .to_string()
+                .split('_')
+                .map(|word| {
+                    let mut chars = word.chars();
+                    chars
+                        .next()
+                        .map(|c| c.to_uppercase().chain(chars).collect::<String>())
+                        .unwrap_or_default()
+                })
+                .collect();
+            Ident::new(&format!("{}State", camel_case), field.name.span())
+        })
+        .collect();
+    let present = required
+        .iter()
+        .map(|_| quote!(crate::nlmsg::AttributePresent));
+
+    let setters = fields.iter().map(|field| {
+        let field_name = field.name;
+        let field_type = field.ty;
+        let setter_name = function_name("with_", field_name, &field.args);
+        match required.iter().position(|f| f.name == field_name) {
+            Some(position) => {
+                let next_state = state_params.iter().enumerate().map(|(i, param)| {
+                    if i == position {
+                        quote!(crate::nlmsg::AttributePresent)
+                    } else {
+                        quote!(#param)
+                    }
+                });
+                quote!(
+                    pub fn #setter_name(
+                        mut self,
+                        val: impl Into<#field_type>,
+                    ) -> #builder<#(#next_state),*> {
+                        self.inner.#field_name = Some(val.into());
+                        #builder {
+                            inner: self.inner,
+                            state: std::marker::PhantomData,
+                        }
+                    }
+                )
+            }
+            None => quote!(
+                pub fn #setter_name(mut self, val: impl Into<#field_type>) -> Self {
+                    self.inner.#field_name = Some(val.into());
+                    self
+                }
+            ),
+        }
+    });
+
+    let required_names = required
+        .iter()
+        .map(|field| format!("`{}`", field.name))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let builder_doc = format!(
+        "Builds a [`{}`] whose required attributes ({}) are checked at compile time: \
+         [`build`]({}::build) is only available once they are all set.",
+        name, required_names, builder
+    );
+    let constructor_doc = format!(
+        "Starts building a [`{}`] with a [`{}`], which requires the attributes {} to be set.",
+        name, builder, required_names
+    );
+    quote!(
+        #[doc = #builder_doc]
+        #[derive(Debug)]
+        #vis struct #builder<#(#state_params = crate::nlmsg::AttributeMissing),*> {
+            inner: #name,
+            state: std::marker::PhantomData<fn() -> (#(#state_params,)*)>,
+        }
+
+        impl #name {
+            #[doc = #constructor_doc]
+            pub fn builder() -> #builder {
+                #builder {
+                    inner: Default::default(),
+                    state: std::marker::PhantomData,
+                }
+            }
+        }
+
+        #[allow(dead_code)]
+        impl<#(#state_params),*> #builder<#(#state_params),*> {
+            #(#setters)*
+        }
+
+        impl #builder<#(#present),*> {
+            pub fn build(self) -> #name {
+                self.inner
+            }
+        }
+    )
+}
+
 fn nfnetlink_struct_inner(
     attrs: TokenStream,
     item: TokenStream,
@@ -194,6 +391,7 @@ fn nfnetlink_struct_inner(
     let state = get_state();
 
     let mut fields = Vec::with_capacity(ast.fields.len());
+    let mut missing_fields = Vec::new();
     let mut identical_fields = Vec::new();
 
     'out: for field in ast.fields.iter() {
@@ -224,11 +422,26 @@ fn nfnetlink_struct_inner(
                                 .ident
                                 .to_string();
                             if !state.declared_identifiers.contains(&netlink_type_ident) {
-                                // reject the optional identifier
+                                // reject the optional identifier, but keep its stubs if requested
+                                if field_args.stub_if_missing {
+                                    missing_fields.push((field, field_args, netlink_type_ident));
+                                }
                                 continue 'out;
                             }
                         }
 
+                        // two fields with the same attribute type would make the attribute
+                        // order ambiguous, and the decoder ignore the second field
+                        let type_str = quote!(#netlink_type).to_string();
+                        if fields.iter().any(|f: &Field| {
+                            let other = &f.netlink_type;
+                            quote!(#other).to_string() == type_str
+                        }) {
+                            return Err(attr
+                                .span()
+                                .error("Another field has the same netlink attribute type"));
+                        }
+
                         fields.push(Field {
                             name: field.ident.as_ref().expect("Should be a names struct"),
                             ty: &field.ty,
@@ -254,27 +467,41 @@ fn nfnetlink_struct_inner(
 
     let getters_and_setters = fields.iter().map(|field| {
         let field_name = field.name;
-        // use the name override if any
-        let field_str = field_name.to_string();
-        let field_str = field
-            .args
-            .override_function_name
-            .as_ref()
-            .map(|x| x.as_str())
-            .unwrap_or(field_str.as_str());
         let field_type = field.ty;
 
-        let getter_name = format!("get_{}", field_str);
-        let getter_name = Ident::new(&getter_name, field.name.span());
+        let getter_name = function_name("get_", field_name, &field.args);
+        let muttable_getter_name = function_name("get_mut_", field_name, &field.args);
+        let setter_name = function_name("set_", field_name, &field.args);
+        let in_place_edit_name = function_name("with_", field_name, &field.args);
 
-        let muttable_getter_name = format!("get_mut_{}", field_str);
-        let muttable_getter_name = Ident::new(&muttable_getter_name, field.name.span());
+        let fallible_setters = if field.args.stub_if_missing {
+            let try_setter_name = function_name("try_set_", field_name, &field.args);
+            let try_in_place_edit_name = function_name("try_with_", field_name, &field.args);
+            quote!(
+                /// Sets the attribute. Builds whose kernel headers lack the attribute fail with
+                /// `BuilderError::UnsupportedOnThisBuild` instead.
+                pub fn #try_setter_name(
+                    &mut self,
+                    val: impl Into<#field_type>,
+                ) -> Result<(), crate::error::BuilderError> {
+                    self.#field_name = Some(val.into());
+                    Ok(())
+                }
 
-        let setter_name = format!("set_{}", field_str);
-        let setter_name = Ident::new(&setter_name, field.name.span());
+                /// Sets the attribute. Builds whose kernel headers lack the attribute fail with
+                /// `BuilderError::UnsupportedOnThisBuild` instead.
+                pub fn #try_in_place_edit_name(
+                    mut self,
+                    val: impl Into<#field_type>,
+                ) -> Result<Self, crate::error::BuilderError> {
+                    self.#field_name = Some(val.into());
+                    Ok(self)
+                }
+            )
+        } else {
+            proc_macro2::TokenStream::new()
+        };
 
-        let in_place_edit_name = format!("with_{}", field_str);
-        let in_place_edit_name = Ident::new(&in_place_edit_name, field.name.span());
         quote!(
             #[allow(dead_code)]
             impl #name {
@@ -294,9 +521,59 @@ fn nfnetlink_struct_inner(
                 self.#field_name = Some(val.into());
                 self
             }
+
+            #fallible_setters
         })
     });
 
+    // the attributes missing from the kernel headers of this build keep fallible setters, so that
+    // their users can degrade gracefully instead of checking the build themselves
+    let stubs = missing_fields
+        .iter()
+        .map(|(field, field_args, netlink_type)| {
+            let field_name = field.ident.as_ref().expect("Should be a names struct");
+            let field_type = &field.ty;
+
+            let getter_name = function_name("get_", field_name, field_args);
+            let muttable_getter_name = function_name("get_mut_", field_name, field_args);
+            let try_setter_name = function_name("try_set_", field_name, field_args);
+            let try_in_place_edit_name = function_name("try_with_", field_name, field_args);
+            quote!(
+                #[allow(dead_code)]
+                impl #name {
+                /// Always returns `None`: the attribute is not supported on this build.
+                pub fn #getter_name(&self) -> Option<&#field_type> {
+                    None
+                }
+
+                /// Always returns `None`: the attribute is not supported on this build.
+                pub fn #muttable_getter_name(&mut self) -> Option<&mut #field_type> {
+                    None
+                }
+
+                /// Always fails: the attribute is not supported on this build.
+                pub fn #try_setter_name(
+                    &mut self,
+                    _val: impl Into<#field_type>,
+                ) -> Result<(), crate::error::BuilderError> {
+                    Err(crate::error::BuilderError::UnsupportedOnThisBuild(#netlink_type))
+                }
+
+                /// Always fails: the attribute is not supported on this build.
+                pub fn #try_in_place_edit_name(
+                    self,
+                    _val: impl Into<#field_type>,
+                ) -> Result<Self, crate::error::BuilderError> {
+                    Err(crate::error::BuilderError::UnsupportedOnThisBuild(#netlink_type))
+                }
+            })
+        });
+
+    let builder = match &args.builder {
+        Some(builder) => generate_builder(&name, builder, &ast.vis, &fields),
+        None => proc_macro2::TokenStream::new(),
+    };
+
     let decoder = if args.derive_decoder {
         let match_entries = fields.iter().map(|field| {
             let field_name = field.name;
@@ -331,6 +608,45 @@ fn nfnetlink_struct_inner(
         proc_macro2::TokenStream::new()
     };
 
+    // the test of each integer attribute is generated, so that no field escapes it
+    let integer_fields: Vec<&Field> = fields.iter().filter(|f| is_integer_type(f.ty)).collect();
+    let integer_field_test = if args.derive_decoder && !integer_fields.is_empty() {
+        let test_name = Ident::new(
+            &format!("{}_integer_fields_round_trip", snake_case(&name)),
+            Span::call_site(),
+        );
+        let field_names: Vec<&Ident> = fields.iter().map(|field| field.name).collect();
+        // the other fields take their default value
+        let rest = if identical_fields.is_empty() {
+            proc_macro2::TokenStream::new()
+        } else {
+            quote!(..Default::default())
+        };
+        let checks = integer_fields.iter().map(|field| {
+            let field_name = field.name;
+            let field_type = field.ty;
+            let field_str = field_name.to_string();
+            let in_place_edit_name = function_name("with_", field_name, &field.args);
+            quote!(
+                crate::tests::check_integer_field::<#name, #field_type>(
+                    #field_str,
+                    |val| #name { #(#field_names: None,)* #rest }
+                        .#in_place_edit_name(val),
+                    |obj| obj.#field_name,
+                );
+            )
+        });
+        quote!(
+            #[cfg(test)]
+            #[test]
+            fn #test_name() {
+                #(#checks)*
+            }
+        )
+    } else {
+        proc_macro2::TokenStream::new()
+    };
+
     let nfnetlinkattribute_impl = {
         let size_entries = fields.iter().map(|field| {
             let field_name = field.name;
@@ -342,12 +658,12 @@ fn nfnetlink_struct_inner(
                 }
             )
         });
-        let write_entries = fields.iter().map(|field| {
+        let write_entries = fields.iter().enumerate().map(|(index, field)| {
             let field_name = field.name;
             let field_str = field_name.to_string();
             let netlink_value = &field.netlink_type;
             quote!(
-                if let Some(val) = &self.#field_name {
+                #index => if let Some(val) = &self.#field_name {
                     debug!("writing attribute {} - {:?}", #field_str, val);
 
                     crate::parser::write_attribute(#netlink_value, val, addr);
@@ -361,6 +677,9 @@ fn nfnetlink_struct_inner(
                 }
             )
         });
+        let nb_fields = fields.len();
+        let netlink_types = fields.iter().map(|field| &field.netlink_type);
+        let indexes = 0..fields.len();
         let nested = args.nested;
         quote!(
             impl crate::nlmsg::NfNetlinkAttribute for #name {
@@ -379,7 +698,17 @@ fn nfnetlink_struct_inner(
                 fn write_payload(&self, mut addr: &mut [u8]) {
                     use crate::nlmsg::NfNetlinkAttribute;
 
-                    #(#write_entries) *
+                    let mut order: [(crate::nlmsg::NetlinkType, usize); #nb_fields] =
+                        [#((#netlink_types, #indexes)),*];
+                    if crate::parser::attribute_order() == crate::AttributeOrder::ByType {
+                        order.sort_by_key(|(netlink_type, _)| *netlink_type);
+                    }
+                    for (_, index) in order {
+                        match index {
+                            #(#write_entries) *
+                            _ => unreachable!(),
+                        }
+                    }
                 }
             }
         )
@@ -413,8 +742,14 @@ fn nfnetlink_struct_inner(
 
         #(#getters_and_setters) *
 
+        #(#stubs) *
+
+        #builder
+
         #decoder
 
+        #integer_field_test
+
         #nfnetlinkattribute_impl
 
         #nfnetlinkdeserialize_impl
@@ -439,6 +774,11 @@ fn nfnetlink_struct_inner(
 ///   implementation for the structure
 /// - `derive_deserialize` (defaults to `true`): derive a [`rustables::nlmsg::NfNetlinkDeserializable`]
 ///   implementation for the structure
+/// - `builder` (not defined by default): the name of a typestate builder to generate, along with
+///   a `builder()` constructor on the structure (which must implement `Default`). The builder
+///   has a `with_<name>` method per attribute, and its `build()` method only exists once all the
+///   fields marked `required` were set, so that forgetting one of them fails at compile time
+///   instead of the kernel refusing the object with `EINVAL`.
 ///
 /// # Example use
 /// ```ignore
@@ -471,7 +811,7 @@ fn nfnetlink_struct_inner(
 ///   so the struct may represent objects where that attribute is not set.
 ///
 /// # `#[field]` parameters
-/// The `#[field]` attribute can be parametrized through two options:
+/// The `#[field]` attribute can be parametrized through four options:
 /// - `optional` (defaults to `false`): if the netlink attribute type (here `NFTA_CHAIN_USERDATA`)
 ///   does not exist, do not generate methods and ignore this attribute if encountered
 ///   while deserializing a nftables object.
@@ -479,10 +819,28 @@ fn nfnetlink_struct_inner(
 ///   older kernels.
 ///   Support for an attribute is detected according to the existence of that attribute in the kernel
 ///   headers.
+/// - `stub_if_missing` (defaults to `false`, only for `optional` fields): also generate the
+///   fallible setters `try_set_<name>` and `try_with_<name>`. When the attribute type does not
+///   exist, these setters still exist and fail with `BuilderError::UnsupportedOnThisBuild`, and
+///   `get_<name>` always returns `None`, so that library users can degrade gracefully at runtime
+///   instead of checking the kernel headers of the build.
+/// - `required` (defaults to `false`, not for `optional` fields): the attribute must be set
+///   before the `builder` of the structure can build it.
 /// - `name_in_functions` (not defined by default): overwrite the `<name`> in the name of the methods
 ///   `get_<name>`, `set_<name>` and `with_<name>`.
 ///   Here, this means that even though the field is called `chain_type`, users can query it with
 ///   the method `get_type` instead of `get_chain_type`.
+///
+/// # Attribute order
+/// The attributes are written in the order of declaration of the `#[field]` fields, unless the
+/// current thread writes them by increasing attribute type (see
+/// [`rustables::with_attribute_order`]). Two fields cannot share an attribute type, so that
+/// both orders are fully determined.
+///
+/// # Tests
+/// When the decoder is derived, the macro also generates a `<name>_integer_fields_round_trip`
+/// test for the structures with integer fields (`u8`, `u16`, `u32`, `u64` and `i32`), checking
+/// that each of them is written in network byte order and decoded back to the same value.
 #[proc_macro_attribute]
 pub fn nfnetlink_struct(attrs: TokenStream, item: TokenStream) -> TokenStream {
     match nfnetlink_struct_inner(attrs, item) {
@@ -654,3 +1012,155 @@ pub fn nfnetlink_enum(attrs: TokenStream, item: TokenStream) -> TokenStream {
         Err(diag) => diag.emit_as_item_tokens().into(),
     }
 }
+
+#[derive(Default)]
+struct ObjectArgs {
+    add: Option<Path>,
+    del: Option<Path>,
+    family_field: Option<Ident>,
+}
+
+fn parse_object_args(input: TokenStream) -> Result<ObjectArgs, Diagnostic> {
+    let mut args = ObjectArgs::default();
+    let parser = Punctuated::<Meta, Token![,]>::parse_terminated;
+    let attribute_args = parser
+        .parse(input)
+        .map_err(|e| Diagnostic::new(Level::Error, e.to_string()))?;
+    for arg in attribute_args.iter() {
+        let namevalue = match arg {
+            Meta::NameValue(namevalue) => namevalue,
+            _ => return Err(arg.span().error("Unrecognized argument")),
+        };
+        let path = match &namevalue.value {
+            Expr::Path(ExprPath { path, .. }) => path.clone(),
+            _ => return Err(namevalue.value.span().error("Expected a path")),
+        };
+        let key = namevalue
+            .path
+            .get_ident()
+            .expect("the macro parameter is not an ident?")
+            .to_string();
+        match key.as_str() {
+            "add" => args.add = Some(path),
+            "del" => args.del = Some(path),
+            "family_field" => match path.get_ident() {
+                Some(ident) => args.family_field = Some(ident.clone()),
+                None => return Err(path.span().error("Expected a field name")),
+            },
+            _ => return Err(arg.span().error("Unsupported macro parameter")),
+        }
+    }
+    Ok(args)
+}
+
+fn nfnetlink_object_inner(
+    attrs: TokenStream,
+    item: TokenStream,
+) -> Result<TokenStream, Diagnostic> {
+    let args = parse_object_args(attrs)?;
+    let add = args
+        .add
+        .ok_or_else(|| Span::call_site().error("The message type adding the object is missing"))?;
+    let del = args.del.ok_or_else(|| {
+        Span::call_site().error("The message type deleting the object is missing")
+    })?;
+
+    // without a family field, the object is family-agnostic, which is the default of the trait
+    let family_methods = match args.family_field {
+        Some(field) => quote!(
+            fn get_family(&self) -> crate::ProtocolFamily {
+                self.#field
+            }
+
+            fn set_family(&mut self, family: crate::ProtocolFamily) {
+                self.#field = family;
+            }
+        ),
+        None => quote!(
+            fn get_family(&self) -> crate::ProtocolFamily {
+                crate::ProtocolFamily::Unspec
+            }
+        ),
+    };
+    let generated = quote!(
+        const MSG_TYPE_ADD: u32 = #add;
+        const MSG_TYPE_DEL: u32 = #del;
+
+        #family_methods
+    );
+
+    let res = match parse::<Item>(item) {
+        Ok(Item::Struct(ast)) => {
+            let name = &ast.ident;
+            quote!(
+                #ast
+
+                impl crate::nlmsg::NfNetlinkObject for #name {
+                    #generated
+                }
+            )
+        }
+        Ok(Item::Impl(ast)) if ast.trait_.is_some() => {
+            let ItemImpl {
+                attrs,
+                unsafety,
+                generics,
+                trait_,
+                self_ty,
+                items,
+                ..
+            } = ast;
+            let (_, trait_path, _) = trait_.unwrap();
+            let where_clause = &generics.where_clause;
+            quote!(
+                #(#attrs) *
+                #unsafety impl #generics #trait_path for #self_ty #where_clause {
+                    #generated
+
+                    #(#items) *
+                }
+            )
+        }
+        Ok(item) => {
+            return Err(item
+                .span()
+                .error("Expected a struct or an implementation of NfNetlinkObject"))
+        }
+        Err(e) => return Err(Diagnostic::new(Level::Error, e.to_string())),
+    };
+
+    Ok(res.into())
+}
+
+/// `nfnetlink_object` implements [`rustables::nlmsg::NfNetlinkObject`] for the top-level
+/// nftables objects, which are sent to (and received from) the kernel in their own messages.
+///
+/// The macro is placed either on the structure describing the object, or on an implementation
+/// of `NfNetlinkObject` whose other methods (e.g. `missing_attributes`) are written by hand.
+/// It fills in the message types and the accessors of the family of the object.
+///
+/// The objects are deserialized from whole netlink messages, so their structure must be declared
+/// with `#[nfnetlink_struct(derive_deserialize = false)]`.
+///
+/// # Parameters
+/// - `add`: the message type adding the object (e.g. `NFT_MSG_NEWTABLE`).
+/// - `del`: the message type deleting the object (e.g. `NFT_MSG_DELTABLE`).
+/// - `family_field` (optional): the field holding the [`rustables::ProtocolFamily`] of the
+///   object. Objects without this field are written with the `Unspec` family.
+///
+/// # Example use
+/// ```ignore
+/// #[nfnetlink_object(add = NFT_MSG_NEWTABLE, del = NFT_MSG_DELTABLE, family_field = family)]
+/// impl NfNetlinkObject for Table {
+///     fn missing_attributes(&self, _msg_type: MsgType) -> Vec<&'static str> {
+///         ...
+///     }
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn nfnetlink_object(attrs: TokenStream, item: TokenStream) -> TokenStream {
+    match nfnetlink_object_inner(attrs, item) {
+        Ok(tokens) => tokens,
+        Err(diag) => diag.emit_as_item_tokens().into(),
+    }
+}
@@ -11,11 +11,13 @@ use std::path::PathBuf;
 const SYS_HEADER_FILE: &str = "include/wrapper.h";
 
 fn main() {
-    generate_sys();
+    let sys = generate_sys();
+    generate_constant_list(&sys);
 }
 
-/// `bindgen`erate a rust sys file from the C kernel headers of the nf_tables capabilities.
-fn generate_sys() {
+/// `bindgen`erate a rust sys file from the C kernel headers of the nf_tables capabilities, and
+/// return its content.
+fn generate_sys() -> String {
     // Tell cargo to invalidate the built crate whenever the headers change.
     println!("cargo:rerun-if-changed={}", SYS_HEADER_FILE);
 
@@ -36,7 +38,7 @@ fn generate_sys() {
 
     // Add newlines because in alpine bindgen doesn't add them after statements.
     let s = bindings.to_string().replace(" ; ", ";\n");
-    let s = reformat_units(&s);
+    let s = reformat_units(&s).into_owned();
 
     // Write the bindings to the rust header file.
     let out_path = PathBuf::from(env::var("OUT_DIR").unwrap()).join("sys.rs");
@@ -44,6 +46,42 @@ fn generate_sys() {
         .expect("Error: could not create rust header file.")
         .write_all(&s.as_bytes())
         .expect("Error: could not write to the rust header file.");
+
+    s
+}
+
+/// List the `NFT_*` and `NFTA_*` constants found in the rust sys file `sys`, both as a
+/// machine-readable `constants.txt` file (one `NAME VALUE` pair per line) and as a rust array
+/// exposed through `sys::supported_attributes()`.
+fn generate_constant_list(sys: &str) {
+    let re = Regex::new(r"pub const (NFTA?_[A-Za-z0-9_]+): [A-Za-z0-9_]+ = (-?[0-9]+);").unwrap();
+    let constants: Vec<(&str, &str)> = re
+        .captures_iter(sys)
+        .map(|captures| {
+            (
+                captures.get(1).unwrap().as_str(),
+                captures.get(2).unwrap().as_str(),
+            )
+        })
+        .collect();
+
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+
+    let mut list = File::create(out_dir.join("constants.txt"))
+        .expect("Error: could not create the constant list.");
+    for (name, value) in &constants {
+        writeln!(list, "{} {}", name, value).expect("Error: could not write the constant list.");
+    }
+
+    let mut array = File::create(out_dir.join("constants.rs"))
+        .expect("Error: could not create the rust constant array.");
+    writeln!(array, "const SUPPORTED_ATTRIBUTES: &[(&str, i64)] = &[")
+        .expect("Error: could not write the rust constant array.");
+    for (name, value) in &constants {
+        writeln!(array, "    (\"{}\", {}),", name, value)
+            .expect("Error: could not write the rust constant array.");
+    }
+    writeln!(array, "];").expect("Error: could not write the rust constant array.");
 }
 
 /// Recast nft_*_attributes from u32 to u16 in header string `header`.
@@ -38,12 +38,13 @@
 
 use ipnetwork::{IpNetwork, Ipv4Network};
 use rustables::{
-    data_type::ip_to_vec,
+    data_type::IpOperand,
     expr::{
-        Bitwise, Cmp, CmpOp, Counter, HighLevelPayload, ICMPv6HeaderField, IPv4HeaderField,
-        IcmpCode, Immediate, Meta, MetaType, NetworkHeaderField, TransportHeaderField, VerdictKind,
+        Bitwise, Cmp, CmpOp, Counter, HighLevelPayload, IPv4HeaderField, Immediate, Meta, MetaType,
+        NetworkHeaderField, VerdictKind,
     },
-    iface_index, Batch, Chain, ChainPolicy, Hook, HookClass, MsgType, ProtocolFamily, Rule, Table,
+    iface_index, Batch, Chain, ChainPolicy, Hook, HookClass, Icmpv6Type, MsgType, ProtocolFamily,
+    Rule, Table,
 };
 use std::net::Ipv4Addr;
 
@@ -130,10 +131,10 @@ fn main() -> Result<(), Error> {
 
         // Mask out the part of the destination address that is not part of the network bits. The result
         // of this bitwise masking is stored back into the same netfilter register.
-        .with_expr(Bitwise::new(ip_to_vec(private_net.mask()), [0u8; 4])?)
+        .with_expr(Bitwise::new(IpOperand::from(private_net.mask()), [0u8; 4])?)
 
         // Compare the result of the masking with the IP of the network we are interested in.
-        .with_expr(Cmp::new(CmpOp::Eq, ip_to_vec(private_net.ip())))
+        .with_expr(Cmp::new_ip(CmpOp::Eq, private_net.ip()))
 
         // Add a packet counter to the rule. Shows how many packets have been evaluated against this
         // expression. Since expressions are evaluated from first to last, putting this counter before
@@ -153,21 +154,8 @@ fn main() -> Result<(), Error> {
     // === ADD A RULE ALLOWING ALL OUTGOING ICMPv6 PACKETS WITH TYPE 133 AND CODE 0 ===
 
     let allow_router_solicitation = Rule::new(&out_chain)?
-        // Check that the packet is IPv6 and ICMPv6
-        .with_expr(Meta::new(MetaType::NfProto))
-        .with_expr(Cmp::new(CmpOp::Eq, [libc::NFPROTO_IPV6 as u8]))
-        .with_expr(Meta::new(MetaType::L4Proto))
-        .with_expr(Cmp::new(CmpOp::Eq, [libc::IPPROTO_ICMPV6 as u8]))
-        .with_expr(
-            HighLevelPayload::Transport(TransportHeaderField::ICMPv6(ICMPv6HeaderField::Type))
-                .build(),
-        )
-        .with_expr(Cmp::new(CmpOp::Eq, [133u8]))
-        .with_expr(
-            HighLevelPayload::Transport(TransportHeaderField::ICMPv6(ICMPv6HeaderField::Code))
-                .build(),
-        )
-        .with_expr(Cmp::new(CmpOp::Eq, [IcmpCode::NoRoute as u8]))
+        // Check that the packet is ICMPv6, and that its type and code match
+        .icmpv6_type_code(Icmpv6Type::RouterSolicitation, 0)
         .with_expr(Immediate::new_verdict(VerdictKind::Accept));
 
     batch.add(&allow_router_solicitation, rustables::MsgType::Add);
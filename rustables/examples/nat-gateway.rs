@@ -0,0 +1,94 @@
+//! A home-router style NAT gateway: the hosts of the LAN reach the internet through the WAN
+//! interface, which masquerades their connections, while the internet can only answer them.
+//!
+//! This is the equivalent of the following nft ruleset:
+//!
+//! ```text
+//! table ip nat-gateway {
+//!     chain forward {
+//!         type filter hook forward priority 0; policy drop;
+//!         tcp flags syn tcp option maxseg size set rt mtu
+//!         ct state established,related accept
+//!         iifname "lan0" oifname "wan0" accept
+//!     }
+//!     chain postrouting {
+//!         type nat hook postrouting priority 100; policy accept;
+//!         oifname "wan0" meta l4proto tcp masquerade to :10000-65535
+//!         oifname "wan0" masquerade
+//!     }
+//! }
+//! ```
+use rustables::error::{BuilderError, QueryError};
+use rustables::{
+    Batch, Chain, ChainPolicy, ChainType, Hook, HookClass, MsgType, Protocol, ProtocolFamily, Rule,
+    Table,
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Error building a netlink object")]
+    BuildError(#[from] BuilderError),
+    #[error("Error applying batch")]
+    QueryError(#[from] QueryError),
+}
+
+const TABLE_NAME: &str = "nat-gateway";
+const LAN_IFACE: &str = "lan0";
+const WAN_IFACE: &str = "wan0";
+
+/// The source ports used for the masqueraded TCP connections, leaving the lower ports to the
+/// services of the gateway itself.
+const MASQUERADE_PORTS: std::ops::RangeInclusive<u16> = 10000..=65535;
+
+fn main() -> Result<(), Error> {
+    let mut batch = Batch::new();
+
+    // Start from a clean table: adding it first guarantees that the deletion succeeds.
+    let table = Table::new(ProtocolFamily::Ipv4).with_name(TABLE_NAME);
+    batch.add(&table, MsgType::Add);
+    batch.add(&table, MsgType::Del);
+    batch.add(&table, MsgType::Add);
+
+    // Only forward the connections initiated from the LAN, and their answers.
+    let forward = Chain::new(&table)
+        .with_name("forward")
+        .with_hook(Hook::new(HookClass::Forward, 0))
+        .with_type(ChainType::Filter)
+        .with_policy(ChainPolicy::Drop)
+        .add_to_batch(&mut batch);
+    // Some uplinks (e.g. PPPoE) have a smaller MTU than the LAN, and many hosts on the internet
+    // drop the ICMP errors that path MTU discovery relies on: advertise a MSS that fits.
+    Rule::new(&forward)?
+        .clamp_mss_to_pmtu()?
+        .add_to_batch(&mut batch);
+    Rule::new(&forward)?
+        .established_or_related()?
+        .accept()
+        .add_to_batch(&mut batch);
+    Rule::new(&forward)?
+        .iiface(LAN_IFACE)?
+        .oiface(WAN_IFACE)?
+        .accept()
+        .add_to_batch(&mut batch);
+
+    // Rewrite the source address of the connections leaving through the WAN interface.
+    let postrouting = Chain::new(&table)
+        .with_name("postrouting")
+        .with_hook(Hook::new(HookClass::PostRouting, 100))
+        .with_type(ChainType::Nat)
+        .with_policy(ChainPolicy::Accept)
+        .add_to_batch(&mut batch);
+    Rule::new(&postrouting)?
+        .oiface(WAN_IFACE)?
+        .protocol(Protocol::TCP)
+        .masquerade_to_ports(MASQUERADE_PORTS)?
+        .add_to_batch(&mut batch);
+    Rule::new(&postrouting)?
+        .oiface(WAN_IFACE)?
+        .masquerade()
+        .add_to_batch(&mut batch);
+
+    batch.send()?;
+    println!("table {} committed", TABLE_NAME);
+    Ok(())
+}
@@ -0,0 +1,94 @@
+//! An early-drop DDoS filter: the packets of a flood are dropped at the ingress hook of the
+//! interface, before they reach conntrack, routing or any socket.
+//!
+//! The chains of netdev tables see every frame received by their devices, whatever its network
+//! protocol, so the rules first check the ethertype of the packets. This also means that the
+//! `meta nfproto` matches of [`Rule::saddr`] and friends do not apply here.
+//!
+//! This is the equivalent of the following nft ruleset:
+//!
+//! ```text
+//! table netdev ddos-filter {
+//!     chain ingress {
+//!         type filter hook ingress device "eth0" priority -500; policy accept;
+//!         ip saddr 203.0.113.0/24 drop
+//!         ip protocol udp udp sport 11211 drop
+//!         ip protocol udp udp sport 19 drop
+//!     }
+//! }
+//! ```
+//!
+//! Everything created by this example can be removed by running
+//! ```bash
+//! # nft delete table netdev ddos-filter
+//! ```
+use std::net::Ipv4Addr;
+
+use rustables::error::{BuilderError, QueryError};
+use rustables::expr::{Bitwise, Cmp, CmpOp, HighLevelPayload, IPv4HeaderField, NetworkHeaderField};
+use rustables::{
+    Batch, Chain, ChainPolicy, ChainType, DeviceList, Hook, MsgType, Protocol, ProtocolFamily,
+    Rule, Table,
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Error building a netlink object")]
+    BuildError(#[from] BuilderError),
+    #[error("Error applying batch")]
+    QueryError(#[from] QueryError),
+}
+
+const TABLE_NAME: &str = "ddos-filter";
+const IFACE: &str = "eth0";
+
+/// A network sending us nothing but garbage.
+const BLOCKED_NETWORK: (Ipv4Addr, Ipv4Addr) = (
+    Ipv4Addr::new(203, 0, 113, 0),
+    Ipv4Addr::new(255, 255, 255, 0),
+);
+
+/// The source ports of the UDP services commonly abused for reflection attacks (memcached and
+/// chargen), which we never talk to.
+const REFLECTED_PORTS: [u16; 2] = [11211, 19];
+
+fn main() -> Result<(), Error> {
+    let mut batch = Batch::new();
+
+    let table = Table::new(ProtocolFamily::NetDev).with_name(TABLE_NAME);
+    batch.add(&table, MsgType::Add);
+
+    // The device list is mandatory on the hooks of netdev chains, and the priority puts the chain
+    // ahead of any other ingress chain of the interface.
+    let ingress = Chain::new(&table)
+        .with_name("ingress")
+        .with_hook(Hook::ingress(-500, DeviceList::new([IFACE])?))
+        .with_type(ChainType::Filter)
+        .with_policy(ChainPolicy::Accept);
+    // Catch the configurations the kernel would reject before sending anything.
+    ingress.validate()?;
+    let ingress = ingress.add_to_batch(&mut batch);
+
+    let (network, mask) = BLOCKED_NETWORK;
+    Rule::new(&ingress)?
+        .ether_type(libc::ETH_P_IP as u16)
+        .with_expr(
+            HighLevelPayload::Network(NetworkHeaderField::IPv4(IPv4HeaderField::Saddr)).build(),
+        )
+        .with_expr(Bitwise::new(mask.octets(), [0; 4])?)
+        .with_expr(Cmp::new_ip(CmpOp::Eq, network))
+        .drop()
+        .add_to_batch(&mut batch);
+
+    for port in REFLECTED_PORTS {
+        Rule::new(&ingress)?
+            .ether_type(libc::ETH_P_IP as u16)
+            .sport(port, Protocol::UDP)
+            .drop()
+            .add_to_batch(&mut batch);
+    }
+
+    batch.send()?;
+    println!("table {} committed", TABLE_NAME);
+    Ok(())
+}
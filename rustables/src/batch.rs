@@ -1,21 +1,298 @@
+use std::fmt;
+use std::rc::Rc;
+
 use libc;
 
 use thiserror::Error;
 
+#[cfg(feature = "socket")]
 use crate::error::QueryError;
-use crate::nlmsg::{NfNetlinkObject, NfNetlinkWriter};
-use crate::sys::{NFNL_SUBSYS_NFTABLES, NLM_F_ACK};
-use crate::{MsgType, ProtocolFamily};
-
-use nix::sys::socket::{
-    self, AddressFamily, MsgFlags, NetlinkAddr, SockAddr, SockFlag, SockProtocol, SockType,
+use crate::error::{BuilderError, DecodeError};
+use crate::expr::{Verdict, VerdictType};
+#[cfg(feature = "socket")]
+use crate::nlmsg::{get_operation_from_nlmsghdr_type, NfNetlinkDeserializable};
+use crate::nlmsg::{
+    pad_netlink_object, pad_netlink_object_with_variable_size, NfNetlinkObject, NfNetlinkWriter,
 };
+use crate::parser::{get_nlmsghdr, get_res_id};
+#[cfg(feature = "socket")]
+use crate::query::NfNetlinkSocket;
+#[cfg(feature = "socket")]
+use crate::sys::NLM_F_ECHO;
+use crate::sys::{
+    nfgenmsg, nlmsghdr, NFNETLINK_V0, NFNL_MSG_BATCH_BEGIN, NFNL_MSG_BATCH_END,
+    NFNL_SUBSYS_NFTABLES, NFT_MSG_DELCHAIN, NFT_MSG_DELFLOWTABLE, NFT_MSG_DELOBJ, NFT_MSG_DELRULE,
+    NFT_MSG_DELSET, NFT_MSG_DELSETELEM, NFT_MSG_DELTABLE, NFT_MSG_NEWCHAIN, NFT_MSG_NEWFLOWTABLE,
+    NFT_MSG_NEWOBJ, NFT_MSG_NEWRULE, NFT_MSG_NEWSET, NFT_MSG_NEWSETELEM, NFT_MSG_NEWTABLE,
+    NLM_F_ACK, NLM_F_APPEND, NLM_F_REPLACE,
+};
+use crate::zeroize::zeroize_if_enabled;
+#[cfg(feature = "socket")]
+use crate::zeroize::ZeroOnDrop;
+use crate::{Chain, MsgType, ProtocolFamily, Rule, Table};
+#[cfg(feature = "socket")]
+use crate::{Flowtable, Obj};
 
 /// Error while communicating with netlink.
 #[derive(Error, Debug)]
 #[error("Error while communicating with netlink")]
 pub struct NetlinkError(());
 
+/// An object added to a [`Batch`], identifying the message of the batch that the kernel refused
+/// in [`QueryError::BatchObjectRefused`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchObject {
+    /// The position of the object in the batch, starting from 0.
+    pub index: usize,
+    /// The sequence number of the message.
+    pub seq: u32,
+    /// The type of the message, e.g. `NFT_MSG_NEWRULE`.
+    pub msg_type: u16,
+    /// The netlink flags of the message, e.g. `NLM_F_REPLACE`.
+    pub flags: u16,
+    /// The name of the object, see [`NfNetlinkObject::object_name`].
+    pub name: Option<String>,
+}
+
+impl BatchObject {
+    /// The kind of object of the message, e.g. `"rule"` for `NFT_MSG_NEWRULE`.
+    pub fn kind(&self) -> &'static str {
+        object_kind(self.msg_type as u32)
+    }
+
+    /// The operation of the message on the object. Only the insertions of rules are told apart
+    /// from additions, as the other objects are inserted with the same message.
+    pub fn operation(&self) -> MsgType {
+        match self.msg_type as u32 {
+            NFT_MSG_DELTABLE | NFT_MSG_DELCHAIN | NFT_MSG_DELRULE | NFT_MSG_DELSET
+            | NFT_MSG_DELSETELEM | NFT_MSG_DELOBJ | NFT_MSG_DELFLOWTABLE => MsgType::Del,
+            _ if self.flags & NLM_F_REPLACE as u16 != 0 => MsgType::Replace,
+            NFT_MSG_NEWRULE if self.flags & NLM_F_APPEND as u16 == 0 => MsgType::Insert,
+            _ => MsgType::Add,
+        }
+    }
+}
+
+/// The kind of object of the messages of type `msg_type`, e.g. `"rule"` for `NFT_MSG_NEWRULE`.
+fn object_kind(msg_type: u32) -> &'static str {
+    match msg_type {
+        NFT_MSG_NEWTABLE | NFT_MSG_DELTABLE => "table",
+        NFT_MSG_NEWCHAIN | NFT_MSG_DELCHAIN => "chain",
+        NFT_MSG_NEWRULE | NFT_MSG_DELRULE => "rule",
+        NFT_MSG_NEWSET | NFT_MSG_DELSET => "set",
+        NFT_MSG_NEWSETELEM | NFT_MSG_DELSETELEM => "set elements",
+        NFT_MSG_NEWOBJ | NFT_MSG_DELOBJ => "object",
+        NFT_MSG_NEWFLOWTABLE | NFT_MSG_DELFLOWTABLE => "flowtable",
+        _ => "object",
+    }
+}
+
+/// An object created by a batch sent with [`Batch::send_echo`], as the kernel echoed it back
+/// after the commit.
+#[cfg(feature = "socket")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CreatedObject {
+    /// The object of the batch whose message created it.
+    pub object: BatchObject,
+    /// The handle assigned by the kernel to the rules, stateful objects and flowtables, which
+    /// designates them in later deletions or replacements.
+    pub handle: Option<u64>,
+    /// The echoed message, which can be decoded with [`NfNetlinkDeserializable::deserialize`].
+    ///
+    /// [`NfNetlinkDeserializable::deserialize`]: crate::nlmsg::NfNetlinkDeserializable::deserialize
+    pub message: Vec<u8>,
+}
+
+#[cfg(feature = "socket")]
+impl CreatedObject {
+    /// Matches a message echoed by the kernel with the object of `objects` whose message has
+    /// the same sequence number. Returns `None` for the echoes of the other operations, e.g.
+    /// the deletions.
+    pub(crate) fn from_echo(
+        message: &[u8],
+        objects: &[BatchObject],
+    ) -> Result<Option<Self>, DecodeError> {
+        let hdr = get_nlmsghdr(message)?;
+        let msg_type = get_operation_from_nlmsghdr_type(hdr.nlmsg_type) as u16;
+        let object = objects.iter().find(|object| {
+            object.seq == hdr.nlmsg_seq
+                && object.msg_type == msg_type
+                && object.operation() != MsgType::Del
+        });
+        let Some(object) = object else {
+            return Ok(None);
+        };
+        let handle = match msg_type as u32 {
+            NFT_MSG_NEWRULE => Rule::deserialize(message)?.0.get_handle().copied(),
+            NFT_MSG_NEWOBJ => Obj::deserialize(message)?.0.get_handle().copied(),
+            NFT_MSG_NEWFLOWTABLE => Flowtable::deserialize(message)?.0.get_handle().copied(),
+            _ => None,
+        };
+        Ok(Some(CreatedObject {
+            object: object.clone(),
+            handle,
+            message: message.to_vec(),
+        }))
+    }
+}
+
+/// Calls `f` on the header of every message of the finalized batch `buf`.
+fn for_each_header(buf: &mut [u8], mut f: impl FnMut(&mut nlmsghdr)) {
+    let mut offset = 0;
+    while let Ok(hdr) = get_nlmsghdr(&buf[offset..]) {
+        let len = pad_netlink_object_with_variable_size(hdr.nlmsg_len as usize);
+        f(unsafe { &mut *(buf[offset..].as_mut_ptr() as *mut nlmsghdr) });
+        offset += len;
+    }
+}
+
+/// Adds `NLM_F_ECHO` to the flags of the messages of the finalized batch `buf`, but the begin
+/// and end messages.
+#[cfg(feature = "socket")]
+pub(crate) fn set_echo_flags(buf: &mut [u8]) {
+    for_each_header(buf, |hdr| {
+        if hdr.nlmsg_type != NFNL_MSG_BATCH_BEGIN as u16
+            && hdr.nlmsg_type != NFNL_MSG_BATCH_END as u16
+        {
+            hdr.nlmsg_flags |= NLM_F_ECHO as u16;
+        }
+    });
+}
+
+impl fmt::Display for BatchObject {
+    /// Displays the object as e.g. `deletion of chain "input" (object 3 of the batch)`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let operation = match self.operation() {
+            MsgType::Add => "addition",
+            MsgType::Del => "deletion",
+            MsgType::Replace => "replacement",
+            MsgType::Insert => "insertion",
+        };
+        write!(f, "{} of {}", operation, self.kind())?;
+        if let Some(name) = &self.name {
+            write!(f, " {:?}", name)?;
+        }
+        write!(f, " (object {} of the batch)", self.index)
+    }
+}
+
+/// Whether a [`BatchMarker`] starts or ends a batch.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BatchMarkerKind {
+    /// `NFNL_MSG_BATCH_BEGIN`
+    Begin,
+    /// `NFNL_MSG_BATCH_END`
+    End,
+}
+
+/// One of the messages that delimit a batch, i.e. a transaction, in a stream of nfnetlink
+/// messages.
+///
+/// [`Batch`] writes them itself. They are exposed for the applications that frame the
+/// messages on their own, e.g. over a custom transport, or that need to recognize the
+/// transaction boundaries in the messages they receive.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BatchMarker {
+    pub kind: BatchMarkerKind,
+    /// The sequence number of the message.
+    pub seq: u32,
+    /// The resource id of the batch, i.e. the netfilter subsystem that processes its messages
+    /// (`NFNL_SUBSYS_NFTABLES` for nftables).
+    pub res_id: u16,
+}
+
+impl BatchMarker {
+    /// The message starting a batch, with the sequence number `seq`.
+    pub fn begin(seq: u32, res_id: u16) -> Self {
+        BatchMarker {
+            kind: BatchMarkerKind::Begin,
+            seq,
+            res_id,
+        }
+    }
+
+    /// The message ending a batch, with the sequence number `seq`.
+    pub fn end(seq: u32, res_id: u16) -> Self {
+        BatchMarker {
+            kind: BatchMarkerKind::End,
+            seq,
+            res_id,
+        }
+    }
+
+    /// Encodes the message, as [`Batch`] writes it.
+    pub fn to_message(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        self.write(&mut NfNetlinkWriter::new(&mut buf));
+        buf
+    }
+
+    fn write(&self, writer: &mut NfNetlinkWriter<'_>) {
+        // the kernel acknowledges the whole batch when asked to in its begin message
+        let (msg_type, flags) = match self.kind {
+            BatchMarkerKind::Begin => (NFNL_MSG_BATCH_BEGIN, NLM_F_ACK as u16),
+            BatchMarkerKind::End => (NFNL_MSG_BATCH_END, 0),
+        };
+        writer.write_header(
+            msg_type as u16,
+            ProtocolFamily::Unspec,
+            flags,
+            self.seq,
+            Some(self.res_id),
+        );
+        writer.finalize_writing_object();
+    }
+
+    /// Decodes the message at the start of `buf`, or returns `None` if it is not a batch begin
+    /// or end message. Unlike [`parse_nlmsg`](crate::parser::parse_nlmsg), the batches of every
+    /// subsystem are accepted.
+    pub fn parse(buf: &[u8]) -> Result<Option<Self>, DecodeError> {
+        let hdr = get_nlmsghdr(buf)?;
+        let kind = match hdr.nlmsg_type as u32 {
+            NFNL_MSG_BATCH_BEGIN => BatchMarkerKind::Begin,
+            NFNL_MSG_BATCH_END => BatchMarkerKind::End,
+            _ => return Ok(None),
+        };
+        let start = pad_netlink_object::<nlmsghdr>();
+        let end = start + std::mem::size_of::<nfgenmsg>();
+        if (hdr.nlmsg_len as usize) < end || buf.len() < end {
+            return Err(DecodeError::NlMsgTooSmall);
+        }
+        let nfgenmsg = unsafe { *(buf[start..end].as_ptr() as *const nfgenmsg) };
+        if nfgenmsg.version != NFNETLINK_V0 as u8 {
+            return Err(DecodeError::InvalidVersion(nfgenmsg.version));
+        }
+        Ok(Some(BatchMarker {
+            kind,
+            seq: hdr.nlmsg_seq,
+            res_id: get_res_id(&nfgenmsg),
+        }))
+    }
+}
+
+/// Which messages of a [`Batch`] ask the kernel for an acknowledgement, set with
+/// [`Batch::with_ack_mode`].
+///
+/// The kernel reports the errors of the messages that it refuses whatever the mode, so a refused
+/// object is identified in a [`QueryError::BatchObjectRefused`] in both modes.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum AckMode {
+    /// Every message asks for an acknowledgement (`NLM_F_ACK`), and the batch is known to be
+    /// committed once the last one is received. This is the behaviour of [`Batch::new`], and
+    /// the one of the `nft` tool.
+    #[default]
+    Strict,
+    /// Only the last message of the batch (before its end message) asks for an acknowledgement,
+    /// which the kernel sends once the whole batch is committed. This saves the kernel from
+    /// writing (and the socket from buffering) one acknowledgement per message, which matters for
+    /// batches of thousands of rules. The messages with custom flags keep `NLM_F_ACK` out of the
+    /// batch too.
+    ///
+    /// The end message itself is not acknowledged by the kernels before 6.10, which ignore the
+    /// flags of the begin and end messages.
+    LastMessage,
+}
+
 /// A batch of netfilter messages to be performed in one atomic operation.
 pub struct Batch {
     buf: Box<Vec<u8>>,
@@ -24,6 +301,11 @@ pub struct Batch {
     // the rest of the crate (let alone publicly).
     writer: NfNetlinkWriter<'static>,
     seq: u32,
+    res_id: u16,
+    next_rule_id: u32,
+    next_chain_id: u32,
+    objects: Vec<BatchObject>,
+    ack_mode: AckMode,
 }
 
 impl Batch {
@@ -31,35 +313,132 @@ impl Batch {
     ///
     /// [default page size]: fn.default_batch_page_size.html
     pub fn new() -> Self {
+        Batch::with_capacity(default_batch_page_size() as usize)
+    }
+
+    /// Creates a new batch whose buffer initially holds `capacity` bytes. The buffer grows
+    /// automatically when more messages are added, so the capacity only trades memory (e.g. on
+    /// embedded systems) against reallocations (e.g. when loading large rulesets).
+    pub fn with_capacity(capacity: usize) -> Self {
+        Batch::new_with(capacity, NFNL_SUBSYS_NFTABLES as u16)
+    }
+
+    /// Creates a new batch whose begin and end messages carry the resource id `res_id`, instead
+    /// of `NFNL_SUBSYS_NFTABLES`. The kernel uses the resource id of a batch to select the
+    /// netfilter subsystem that processes its messages.
+    pub fn with_res_id(res_id: u16) -> Self {
+        Batch::new_with(default_batch_page_size() as usize, res_id)
+    }
+
+    fn new_with(capacity: usize, res_id: u16) -> Self {
         // TODO: use a pinned Box ?
-        let mut buf = Box::new(Vec::with_capacity(default_batch_page_size() as usize));
+        // the writer points to the Vec, not to its content, so the Vec can be reallocated freely
+        let mut buf = Box::new(Vec::with_capacity(capacity));
         // Safe because we hold onto the buffer for as long as `writer` exists
         let mut writer = NfNetlinkWriter::new(unsafe {
             std::mem::transmute(Box::as_mut(&mut buf) as *mut Vec<u8>)
         });
         let seq = 0;
-        writer.write_header(
-            libc::NFNL_MSG_BATCH_BEGIN as u16,
-            ProtocolFamily::Unspec,
-            NLM_F_ACK as u16,
-            seq,
-            Some(libc::NFNL_SUBSYS_NFTABLES as u16),
-        );
-        writer.finalize_writing_object();
+        BatchMarker::begin(seq, res_id).write(&mut writer);
         Batch {
             buf,
             writer,
             seq: seq + 1,
+            res_id,
+            next_rule_id: 1,
+            next_chain_id: 1,
+            objects: Vec::new(),
+            ack_mode: AckMode::default(),
         }
     }
 
+    /// Selects which messages of the batch ask the kernel for an acknowledgement when it is
+    /// finalized, see [`AckMode`].
+    pub fn with_ack_mode(mut self, ack_mode: AckMode) -> Self {
+        self.set_ack_mode(ack_mode);
+        self
+    }
+
+    /// Same as [`Batch::with_ack_mode`], on a mutable reference.
+    pub fn set_ack_mode(&mut self, ack_mode: AckMode) {
+        self.ack_mode = ack_mode;
+    }
+
+    /// The [`AckMode`] of the batch.
+    pub fn get_ack_mode(&self) -> AckMode {
+        self.ack_mode
+    }
+
+    /// The number of messages added to the batch, without its begin and end messages.
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    /// Whether no message was added to the batch yet.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The number of bytes currently used by the messages of the batch.
+    pub fn size(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// The number of bytes the batch can hold before its buffer is reallocated.
+    pub fn capacity(&self) -> usize {
+        self.buf.capacity()
+    }
+
+    /// The resource id carried by the begin and end messages of this batch.
+    pub fn res_id(&self) -> u16 {
+        self.res_id
+    }
+
     /// Adds the given message to this batch.
     pub fn add<T: NfNetlinkObject>(&mut self, msg: &T, msg_type: MsgType) {
         trace!("Writing NlMsg with seq {} to batch", self.seq);
         msg.add_or_remove(&mut self.writer, msg_type, self.seq);
+        self.objects.push(BatchObject {
+            index: self.objects.len(),
+            seq: self.seq,
+            msg_type: match msg_type {
+                MsgType::Del => T::MSG_TYPE_DEL,
+                MsgType::Add | MsgType::Replace | MsgType::Insert => T::MSG_TYPE_ADD,
+            } as u16,
+            flags: msg.get_message_flags(msg_type),
+            name: msg.object_name(),
+        });
         self.seq += 1;
     }
 
+    /// The objects added to this batch, in the order of their messages.
+    pub fn objects(&self) -> &[BatchObject] {
+        &self.objects
+    }
+
+    /// Same as [`Batch::add`], but first checks that `msg` holds all the attributes the kernel
+    /// requires for `msg_type`. The missing attributes are all reported at once, instead of
+    /// the kernel failing the whole batch with `EINVAL`. The replacements of objects other than
+    /// rules, which the kernel refuses, are reported as well.
+    pub fn add_checked<T: NfNetlinkObject>(
+        &mut self,
+        msg: &T,
+        msg_type: MsgType,
+    ) -> Result<(), BuilderError> {
+        if msg_type == MsgType::Replace && T::MSG_TYPE_ADD != NFT_MSG_NEWRULE {
+            return Err(BuilderError::UnsupportedOperation(
+                msg_type,
+                object_kind(T::MSG_TYPE_ADD),
+            ));
+        }
+        let missing = msg.missing_attributes(msg_type);
+        if !missing.is_empty() {
+            return Err(BuilderError::MissingAttributes(missing));
+        }
+        self.add(msg, msg_type);
+        Ok(())
+    }
+
     /// Adds all the messages in the given iterator to this batch.
     pub fn add_iter<T: NfNetlinkObject, I: Iterator<Item = T>>(
         &mut self,
@@ -71,6 +450,145 @@ impl Batch {
         }
     }
 
+    /// Adds a message deleting every table of `family` along with all their objects (the
+    /// equivalent of `nft flush ruleset`), or the tables of every family with
+    /// [`ProtocolFamily::Unspec`].
+    ///
+    /// The objects added after it to the batch then replace the whole ruleset atomically, like a
+    /// file starting with `flush ruleset` loaded by `nft -f`: if the kernel rejects any message
+    /// of the batch, the previous ruleset is left untouched.
+    pub fn flush_ruleset(&mut self, family: ProtocolFamily) {
+        // a table deletion without a name nor a handle targets every table of the family
+        self.add(&Table::new(family), MsgType::Del);
+    }
+
+    /// Adds `chain`, and returns it with an id that identifies it in this batch. The rules jump
+    /// (or go) to the chains added in the same batch by their name as usual, e.g. with
+    /// [`Rule::jump`]; the verdicts returned by [`Batch::jump_to_added`] and
+    /// [`Batch::goto_to_added`] designate the returned chain by its id instead.
+    ///
+    /// Fails on builds whose kernel headers lack `NFTA_CHAIN_ID`.
+    pub fn add_chain(&mut self, chain: Chain) -> Result<Chain, BuilderError> {
+        let chain = chain.try_with_id(self.next_chain_id)?;
+        self.next_chain_id += 1;
+        self.add(&chain, MsgType::Add);
+        Ok(chain)
+    }
+
+    /// A jump to `chain`, returned by [`Batch::add_chain`], which designates it by its id in this
+    /// batch (`NFTA_VERDICT_CHAIN_ID`) rather than by its name, which the kernel resolves before
+    /// the chain is committed. Such verdicts are only meaningful in this batch, and are skipped by
+    /// [`Rule::jump_targets`].
+    ///
+    /// Fails if `chain` was not returned by [`Batch::add_chain`] on this batch.
+    pub fn jump_to_added(&self, chain: &Chain) -> Result<Verdict, BuilderError> {
+        Verdict::to_chain_id(VerdictType::Jump, self.added_chain_id(chain)?)
+    }
+
+    /// A goto to `chain`, designated by its id like in [`Batch::jump_to_added`].
+    pub fn goto_to_added(&self, chain: &Chain) -> Result<Verdict, BuilderError> {
+        Verdict::to_chain_id(VerdictType::Goto, self.added_chain_id(chain)?)
+    }
+
+    fn added_chain_id(&self, chain: &Chain) -> Result<u32, BuilderError> {
+        match chain.get_id() {
+            Some(&id) if id < self.next_chain_id => Ok(id),
+            _ => Err(BuilderError::ChainNotInBatch),
+        }
+    }
+
+    /// Adds `rule` at the end of its chain, and returns it with an id that identifies it in this
+    /// batch, so that other rules can be inserted after it with [`Batch::insert_after`] before
+    /// the batch is committed.
+    pub fn add_rule(&mut self, rule: Rule) -> Rule {
+        let rule = rule.with_id(self.next_rule_id);
+        self.next_rule_id += 1;
+        self.add(&rule, MsgType::Add);
+        rule
+    }
+
+    /// Adds `rule` right after `prev`, and returns it with its id in this batch, like
+    /// [`Batch::add_rule`].
+    ///
+    /// `prev` is either a rule returned by [`Batch::add_rule`], [`Batch::insert_after`] or
+    /// [`Batch::insert_before`], which the kernel finds by its id (`NFTA_RULE_POSITION_ID`)
+    /// although it is not committed yet, or a rule listed from the kernel, which is found by its
+    /// handle. It must belong to the same chain as `rule`.
+    ///
+    /// The ids are only unique in a batch, see [`Batch::append`].
+    pub fn insert_after(&mut self, prev: &Rule, rule: Rule) -> Result<Rule, BuilderError> {
+        if prev.get_table() != rule.get_table() || prev.get_chain() != rule.get_chain() {
+            return Err(BuilderError::RuleChainMismatch);
+        }
+        let rule = match (prev.get_id(), prev.get_handle()) {
+            (Some(&id), _) => rule.with_position_id(id),
+            (None, Some(&handle)) => rule.with_position(handle),
+            (None, None) => return Err(BuilderError::UnknownRulePosition),
+        };
+        Ok(self.add_rule(rule))
+    }
+
+    /// Inserts `rule` right before `next`, and returns it with its id in this batch, like
+    /// [`Batch::add_rule`]. `next` designates a rule as in [`Batch::insert_after`].
+    pub fn insert_before(&mut self, next: &Rule, rule: Rule) -> Result<Rule, BuilderError> {
+        if next.get_table() != rule.get_table() || next.get_chain() != rule.get_chain() {
+            return Err(BuilderError::RuleChainMismatch);
+        }
+        let rule = match (next.get_id(), next.get_handle()) {
+            (Some(&id), _) => rule.with_position_id(id),
+            (None, Some(&handle)) => rule.with_position(handle),
+            (None, None) => return Err(BuilderError::UnknownRulePosition),
+        };
+        let rule = rule.with_id(self.next_rule_id);
+        self.next_rule_id += 1;
+        self.add(&rule, MsgType::Insert);
+        Ok(rule)
+    }
+
+    /// Appends the messages of `other` after the ones already in this batch.
+    ///
+    /// Batches can be moved between threads, so large rulesets can be built in parallel, with
+    /// one batch per thread. Appending the batches in a fixed order then yields the same messages
+    /// as building them sequentially, before sending them in a single atomic operation.
+    ///
+    /// The rules and chains are only identified by their ids (see [`Batch::add_rule`] and
+    /// [`Batch::add_chain`]) in the batch that numbered them, so only one of the merged batches
+    /// may hold rules, and only one may hold chains, with such ids. Fails otherwise, leaving this
+    /// batch untouched; the ids given by this batch afterwards follow the ones of both batches.
+    pub fn append(&mut self, mut other: Batch) -> Result<(), BuilderError> {
+        if (self.next_rule_id > 1 && other.next_rule_id > 1)
+            || (self.next_chain_id > 1 && other.next_chain_id > 1)
+        {
+            return Err(BuilderError::ConflictingBatchIds);
+        }
+        self.next_rule_id = self.next_rule_id.max(other.next_rule_id);
+        self.next_chain_id = self.next_chain_id.max(other.next_chain_id);
+        let mut remaining = &other.buf[..];
+        // skip the batch begin message of `other`
+        if let Ok(hdr) = get_nlmsghdr(remaining) {
+            remaining = &remaining[pad_netlink_object_with_variable_size(hdr.nlmsg_len as usize)..];
+        }
+        let mut objects = std::mem::take(&mut other.objects).into_iter();
+        while let Ok(hdr) = get_nlmsghdr(remaining) {
+            let len = pad_netlink_object_with_variable_size(hdr.nlmsg_len as usize);
+            let msg = self.writer.add_data_zeroed(len);
+            msg.copy_from_slice(&remaining[..len]);
+            // renumber the message, as the sequence numbers must be unique in the batch
+            let hdr: &mut nlmsghdr = unsafe { &mut *(msg.as_mut_ptr() as *mut nlmsghdr) };
+            hdr.nlmsg_seq = self.seq;
+            if let Some(object) = objects.next() {
+                self.objects.push(BatchObject {
+                    index: self.objects.len(),
+                    seq: self.seq,
+                    ..object
+                });
+            }
+            self.seq += 1;
+            remaining = &remaining[len..];
+        }
+        Ok(())
+    }
+
     /// Adds the final end message to the batch and returns a [`FinalizedBatch`] that can be used
     /// to send the messages to netfilter.
     ///
@@ -78,45 +596,552 @@ impl Batch {
     ///
     /// [`FinalizedBatch`]: struct.FinalizedBatch.html
     pub fn finalize(mut self) -> Vec<u8> {
-        self.writer.write_header(
-            libc::NFNL_MSG_BATCH_END as u16,
-            ProtocolFamily::Unspec,
-            0,
-            self.seq,
-            Some(NFNL_SUBSYS_NFTABLES as u16),
-        );
-        self.writer.finalize_writing_object();
-        *self.buf
+        BatchMarker::end(self.seq, self.res_id).write(&mut self.writer);
+        let mut buf = std::mem::take(&mut *self.buf);
+        set_ack_flags(&mut buf, self.ack_mode);
+        buf
+    }
+
+    /// Returns the messages that [`finalize`](Batch::finalize) would return, while keeping the
+    /// batch around, e.g. to send it again once the cause of a failure is fixed.
+    pub(crate) fn finalized_copy(&self) -> Vec<u8> {
+        let mut buf = self.buf.as_ref().clone();
+        BatchMarker::end(self.seq, self.res_id).write(&mut NfNetlinkWriter::new(&mut buf));
+        set_ack_flags(&mut buf, self.ack_mode);
+        buf
+    }
+
+    /// The sequence number of the last message the kernel acknowledges once the batch is
+    /// finalized, after which no response to the batch is expected.
+    #[cfg(feature = "socket")]
+    fn last_acked_seq(&self) -> u32 {
+        self.seq - 1
     }
 
+    /// Deletes `chain`, after deleting the rules that jump (or go) to it, which would otherwise
+    /// make the kernel refuse the deletion with `EBUSY`. Returns the handles of the deleted rules.
+    ///
+    /// The rules are listed from the kernel when this function is called, so the deletion fails
+    /// if rules jumping to the chain are added before the batch is sent. The references from
+    /// verdict maps are not handled.
+    #[cfg(feature = "socket")]
+    pub fn delete_chain_cascade(&mut self, chain: &Chain) -> Result<Vec<u64>, QueryError> {
+        let rules = crate::rule::list_rules_jumping_to(chain)?;
+        let mut handles = Vec::with_capacity(rules.len());
+        for rule in &rules {
+            // a rule without a handle would delete every rule of its chain
+            let rule = rule.refresh_filter()?;
+            handles.extend(rule.get_handle());
+            self.add(&rule, MsgType::Del);
+        }
+        self.add(chain, MsgType::Del);
+        Ok(handles)
+    }
+
+    /// Dry run of [`Batch::delete_chain_cascade`]: returns the handles of the rules that would be
+    /// deleted along with `chain`, without adding anything to the batch.
+    #[cfg(feature = "socket")]
+    pub fn delete_chain_cascade_dry_run(&self, chain: &Chain) -> Result<Vec<u64>, QueryError> {
+        crate::rule::list_rules_jumping_to(chain)?
+            .iter()
+            .map(|rule| Ok(*rule.get_handle().ok_or(BuilderError::MissingRuleHandle)?))
+            .collect()
+    }
+
+    /// Sends the batch to netfilter on a new socket, and waits for the kernel to acknowledge it.
+    #[cfg(feature = "socket")]
     pub fn send(self) -> Result<(), QueryError> {
-        use crate::query::{recv_and_process, socket_close_wrapper};
+        use crate::query::socket_close_wrapper;
+
+        let sock = NfNetlinkSocket::new()?;
+        socket_close_wrapper(sock, move |sock| self.send_with_socket(sock))
+    }
+
+    /// Sends the batch to netfilter on the socket `sock`, and waits for the kernel to acknowledge
+    /// it.
+    #[cfg(feature = "socket")]
+    pub fn send_with_socket(mut self, sock: &NfNetlinkSocket) -> Result<(), QueryError> {
+        let max_seq = self.last_acked_seq();
+        let objects = std::mem::take(&mut self.objects);
+        Batch::send_finalized(sock, &ZeroOnDrop(self.finalize()), max_seq, &objects)
+    }
+
+    /// Sends the batch to netfilter on the socket `sock`, without waiting for the kernel to
+    /// acknowledge it. The acknowledgement is then received with [`PendingBatch::try_complete`]
+    /// when the socket becomes readable, like the responses of a [`PendingDump`].
+    ///
+    /// [`PendingDump`]: crate::query::PendingDump
+    #[cfg(feature = "socket")]
+    pub fn send_nonblocking(mut self, sock: &NfNetlinkSocket) -> Result<PendingBatch, QueryError> {
+        let max_seq = self.last_acked_seq();
+        let objects = std::mem::take(&mut self.objects);
+        sock.send(&ZeroOnDrop(self.finalize()))?;
+        Ok(PendingBatch {
+            buffer: crate::query::QueryBuffer::new(),
+            max_seq,
+            objects,
+        })
+    }
+
+    /// Same as [`Batch::send`], but awaits the acknowledgement of the kernel instead of blocking
+    /// the thread.
+    #[cfg(feature = "tokio")]
+    pub async fn send_async(self) -> Result<(), QueryError> {
+        let sock = NfNetlinkSocket::new()?.into_async()?;
+        let res = self.send_async_with_socket(&sock).await;
+        sock.into_inner().close()?;
+        res
+    }
+
+    /// Same as [`Batch::send_async`], on the socket `sock`, registered with the tokio runtime by
+    /// [`NfNetlinkSocket::into_async`].
+    #[cfg(feature = "tokio")]
+    pub async fn send_async_with_socket(
+        mut self,
+        sock: &tokio::io::unix::AsyncFd<NfNetlinkSocket>,
+    ) -> Result<(), QueryError> {
+        use crate::query::{recv_async, QueryBuffer};
+
+        let max_seq = self.last_acked_seq();
+        let objects = std::mem::take(&mut self.objects);
+        sock.get_ref().send(&ZeroOnDrop(self.finalize()))?;
+
+        recv_async(sock, &mut QueryBuffer::new(), Some(max_seq), |_| Ok(()))
+            .await
+            .map_err(|e| identify_refused_object(e, &objects))?;
+        crate::metrics::record_batch_committed();
+        Ok(())
+    }
+
+    /// Same as [`Batch::send`], with `NLM_F_ECHO` set on the messages so that the kernel echoes
+    /// the objects back once they are committed. Returns the created objects with the handles
+    /// the kernel assigned to them, e.g. to delete a rule later without listing its chain.
+    #[cfg(feature = "socket")]
+    pub fn send_echo(self) -> Result<Vec<CreatedObject>, QueryError> {
+        use crate::query::socket_close_wrapper;
+
+        let sock = NfNetlinkSocket::new()?;
+        let mut created = Vec::new();
+        socket_close_wrapper(sock, |sock| {
+            created = self.send_echo_with_socket(sock)?;
+            Ok::<(), QueryError>(())
+        })?;
+        Ok(created)
+    }
+
+    /// Same as [`Batch::send_echo`], on the socket `sock`.
+    #[cfg(feature = "socket")]
+    pub fn send_echo_with_socket(
+        mut self,
+        sock: &NfNetlinkSocket,
+    ) -> Result<Vec<CreatedObject>, QueryError> {
+        use crate::query::{recv_and_process, QueryBuffer};
+
+        let max_seq = self.last_acked_seq();
+        let objects = std::mem::take(&mut self.objects);
+        let mut to_send = ZeroOnDrop(self.finalize());
+        set_echo_flags(&mut to_send);
+        sock.send(&to_send)?;
 
-        let sock = socket::socket(
-            AddressFamily::Netlink,
-            SockType::Raw,
-            SockFlag::empty(),
-            SockProtocol::NetlinkNetFilter,
+        let mut created = Vec::new();
+        recv_and_process(
+            sock,
+            &mut QueryBuffer::new().with_echo(),
+            Some(max_seq),
+            Some(&|msg: &[u8], created: &mut Vec<CreatedObject>| {
+                created.extend(CreatedObject::from_echo(msg, &objects)?);
+                Ok(())
+            }),
+            &mut created,
         )
-        .map_err(QueryError::NetlinkOpenError)?;
+        .map_err(|e| identify_refused_object(e, &objects))?;
+        crate::metrics::record_batch_committed();
+        Ok(created)
+    }
+
+    #[cfg(feature = "socket")]
+    fn send_finalized(
+        sock: &NfNetlinkSocket,
+        to_send: &[u8],
+        max_seq: u32,
+        objects: &[BatchObject],
+    ) -> Result<(), QueryError> {
+        use crate::query::{recv_and_process, QueryBuffer};
+
+        sock.send(to_send)?;
+
+        recv_and_process(sock, &mut QueryBuffer::new(), Some(max_seq), None, &mut ())
+            .map_err(|e| identify_refused_object(e, objects))?;
+        crate::metrics::record_batch_committed();
+        Ok(())
+    }
+}
+
+impl Drop for Batch {
+    fn drop(&mut self) {
+        zeroize_if_enabled(&mut self.buf);
+    }
+}
+
+/// Keeps `NLM_F_ACK` only on the last message of the finalized batch `buf` before its end
+/// message, in the [`AckMode::LastMessage`] mode.
+fn set_ack_flags(buf: &mut [u8], ack_mode: AckMode) {
+    if ack_mode == AckMode::Strict {
+        return;
+    }
+    let mut last_seq = None;
+    for_each_header(buf, |hdr| {
+        if hdr.nlmsg_type != NFNL_MSG_BATCH_END as u16 {
+            last_seq = Some(hdr.nlmsg_seq);
+        }
+    });
+    for_each_header(buf, |hdr| {
+        if hdr.nlmsg_type != NFNL_MSG_BATCH_END as u16 && Some(hdr.nlmsg_seq) == last_seq {
+            hdr.nlmsg_flags |= NLM_F_ACK as u16;
+        } else {
+            hdr.nlmsg_flags &= !(NLM_F_ACK as u16);
+        }
+    });
+}
+
+/// Replaces an error of the kernel about one of the messages of a batch by a
+/// [`QueryError::BatchObjectRefused`] that designates the object of that message.
+#[cfg(feature = "socket")]
+pub(crate) fn identify_refused_object(error: QueryError, objects: &[BatchObject]) -> QueryError {
+    match error {
+        QueryError::NetlinkError(report) => {
+            match objects.iter().find(|object| object.seq == report.seq()) {
+                Some(object) => QueryError::BatchObjectRefused {
+                    object: object.clone(),
+                    report,
+                },
+                None => QueryError::NetlinkError(report),
+            }
+        }
+        error => error,
+    }
+}
+
+/// A batch sent by [`Batch::send_nonblocking`], whose acknowledgement has not been received yet.
+#[cfg(feature = "socket")]
+pub struct PendingBatch {
+    buffer: crate::query::QueryBuffer,
+    max_seq: u32,
+    objects: Vec<BatchObject>,
+}
+
+#[cfg(feature = "socket")]
+impl PendingBatch {
+    /// Processes the responses already received on `sock`. Returns whether the kernel
+    /// acknowledged the whole batch, or fails with the error of the kernel.
+    pub fn try_complete(&mut self, sock: &NfNetlinkSocket) -> Result<bool, QueryError> {
+        let done =
+            crate::query::recv_available(sock, &mut self.buffer, Some(self.max_seq), &mut |_| {
+                Ok(())
+            })
+            .map_err(|e| identify_refused_object(e, &self.objects))?;
+        if done {
+            crate::metrics::record_batch_committed();
+        }
+        Ok(done)
+    }
+}
+
+type RollbackOp = Rc<dyn Fn(&mut Batch)>;
+
+/// A [`Batch`] that keeps track of the inverse of every operation added to it, in order to be
+/// able to undo the whole transaction after it was committed.
+///
+/// This is useful when the application of the ruleset is only a step of a larger workflow: if a
+/// later (non-nftables) step fails, the [`Rollback`] returned by [`Transaction::send`] can be
+/// sent to revert the changes on a best-effort basis.
+///
+/// The inverse of adding an object is deleting it, and conversely. As a consequence, some care is
+/// needed for the rollback to restore the previous state exactly:
+/// - adding an object that already existed is not an error, but rolling it back deletes the
+///   pre-existing object;
+/// - deleting a table or a chain also deletes its content, but rolling it back only recreates the
+///   objects that were explicitly deleted in the transaction;
+/// - a [`Rule`] can only be deleted by handle, which is not known before the rule is committed,
+///   and deleting a rule without a handle removes every rule of its chain. The additions and
+///   insertions of rules are thus refused by [`Transaction::add`]: they must be added with
+///   [`Transaction::add_without_rollback`], and rolled back by deleting the chain or the table
+///   that contains them;
+/// - a replacement can only be rolled back when the previous object is known, so replacements
+///   are refused by [`Transaction::add`] and must be added with [`Transaction::replace`].
+///
+/// [`Rule`]: crate::Rule
+pub struct Transaction {
+    batch: Batch,
+    rollback: Vec<RollbackOp>,
+}
+
+impl Transaction {
+    pub fn new() -> Self {
+        Transaction {
+            batch: Batch::new(),
+            rollback: Vec::new(),
+        }
+    }
 
-        let max_seq = self.seq - 1;
+    /// Adds the given message to the transaction, and records its inverse operation.
+    ///
+    /// Fails with [`BuilderError::IrreversibleOperation`], without adding the message, when its
+    /// inverse cannot be built: for the additions and insertions of rules, whose handle is not
+    /// known yet, and for the replacements, whose previous object is not known (see
+    /// [`Transaction::replace`]).
+    pub fn add<T: NfNetlinkObject + Clone + 'static>(
+        &mut self,
+        msg: &T,
+        msg_type: MsgType,
+    ) -> Result<(), BuilderError> {
+        let inverse_type = match msg_type {
+            // deleting a rule without a handle would flush its chain
+            MsgType::Add | MsgType::Insert if T::MSG_TYPE_ADD == NFT_MSG_NEWRULE => {
+                return Err(BuilderError::IrreversibleOperation(
+                    msg_type,
+                    object_kind(T::MSG_TYPE_ADD),
+                ));
+            }
+            MsgType::Add | MsgType::Insert => MsgType::Del,
+            MsgType::Del => MsgType::Add,
+            MsgType::Replace => {
+                return Err(BuilderError::IrreversibleOperation(
+                    msg_type,
+                    object_kind(T::MSG_TYPE_ADD),
+                ));
+            }
+        };
+        self.batch.add(msg, msg_type);
+        let msg = msg.clone();
+        self.rollback.push(Rc::new(move |batch: &mut Batch| {
+            batch.add(&msg, inverse_type)
+        }));
+        Ok(())
+    }
+
+    /// Adds all the messages in the given iterator to this transaction. Stops at the first
+    /// message refused by [`Transaction::add`].
+    pub fn add_iter<T: NfNetlinkObject + Clone + 'static, I: Iterator<Item = T>>(
+        &mut self,
+        msg_iter: I,
+        msg_type: MsgType,
+    ) -> Result<(), BuilderError> {
+        for msg in msg_iter {
+            self.add(&msg, msg_type)?;
+        }
+        Ok(())
+    }
+
+    /// Replaces `previous` with `replacement`, and records the inverse replacement. `previous`
+    /// must be the object currently in the kernel (e.g. a rule listed from it), designated by the
+    /// same handle as `replacement`.
+    pub fn replace<T: NfNetlinkObject + Clone + 'static>(
+        &mut self,
+        previous: &T,
+        replacement: &T,
+    ) -> Result<(), BuilderError> {
+        let missing = previous.missing_attributes(MsgType::Replace);
+        if !missing.is_empty() {
+            return Err(BuilderError::MissingAttributes(missing));
+        }
+        self.batch.add_checked(replacement, MsgType::Replace)?;
+        let previous = previous.clone();
+        self.rollback.push(Rc::new(move |batch: &mut Batch| {
+            batch.add(&previous, MsgType::Replace)
+        }));
+        Ok(())
+    }
+
+    /// Adds the given message to the transaction without recording its inverse, e.g. for the
+    /// rules of a chain whose addition is rolled back, which deletes them along with it.
+    pub fn add_without_rollback<T: NfNetlinkObject>(&mut self, msg: &T, msg_type: MsgType) {
+        self.batch.add(msg, msg_type);
+    }
+
+    /// Returns the operations that undo this transaction.
+    pub fn rollback(&self) -> Rollback {
+        Rollback {
+            ops: self.rollback.clone(),
+        }
+    }
+
+    /// Sends the transaction to netfilter. On success, returns the operations that undo it.
+    #[cfg(feature = "socket")]
+    pub fn send(self) -> Result<Rollback, QueryError> {
+        let rollback = self.rollback();
+        self.batch.send()?;
+        Ok(rollback)
+    }
+}
+
+/// The state of a [`TransactionSequence`], passed to the progress callback of
+/// [`TransactionSequence::send`] after each committed transaction.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SequenceProgress {
+    /// The number of transactions of the sequence that were committed.
+    pub committed: usize,
+    /// The number of transactions of the sequence.
+    pub total: usize,
+}
 
-        let addr = SockAddr::Netlink(NetlinkAddr::new(0, 0));
-        // while this bind() is not strictly necessary, strace have trouble decoding the messages
-        // if we don't
-        socket::bind(sock, &addr).map_err(|_| QueryError::BindFailed)?;
+/// A configuration push that is too large for a single batch, split into several [`Transaction`]s
+/// that are committed one after the other.
+///
+/// Each transaction is atomic, but the sequence as a whole is not: when the kernel refuses a
+/// transaction, the previous ones remain applied. The sequence records the transactions that were
+/// committed (its savepoint), so that after a failure the caller can either fix the cause and
+/// call [`send`] again to resume the push from the refused transaction, or [`compensate`] by
+/// rolling back the committed transactions.
+///
+/// ```ignore
+/// let mut sequence = TransactionSequence::new();
+/// for chunk in ruleset.chunks(10_000) {
+///     sequence.push(build_transaction(chunk));
+/// }
+/// if let Err(e) = sequence.send(|p| println!("{}/{} batches applied", p.committed, p.total)) {
+///     eprintln!("{}, reverting", e);
+///     sequence.compensate()?;
+/// }
+/// ```
+///
+/// [`send`]: TransactionSequence::send
+/// [`compensate`]: TransactionSequence::compensate
+pub struct TransactionSequence {
+    transactions: Vec<Transaction>,
+    committed: Vec<Rollback>,
+}
 
-        let to_send = self.finalize();
-        let mut sent = 0;
-        while sent != to_send.len() {
-            sent += socket::send(sock, &to_send[sent..], MsgFlags::empty())
-                .map_err(QueryError::NetlinkSendError)?;
+impl TransactionSequence {
+    pub fn new() -> Self {
+        TransactionSequence {
+            transactions: Vec::new(),
+            committed: Vec::new(),
         }
+    }
+
+    /// Appends `transaction` to the sequence. It will be committed after all the transactions
+    /// already in the sequence.
+    pub fn push(&mut self, transaction: Transaction) {
+        self.transactions.push(transaction);
+    }
+
+    /// The number of transactions in the sequence.
+    pub fn len(&self) -> usize {
+        self.transactions.len()
+    }
+
+    /// Whether no transaction was pushed to the sequence yet.
+    pub fn is_empty(&self) -> bool {
+        self.transactions.is_empty()
+    }
+
+    /// The current state of the sequence. The first transaction that was not committed yet is
+    /// the one at index `committed`.
+    pub fn progress(&self) -> SequenceProgress {
+        SequenceProgress {
+            committed: self.committed.len(),
+            total: self.transactions.len(),
+        }
+    }
+
+    /// Whether all the transactions of the sequence were committed.
+    pub fn is_complete(&self) -> bool {
+        self.committed.len() == self.transactions.len()
+    }
+
+    /// Returns the operations that undo every committed transaction, from the last one to the
+    /// first one.
+    pub fn rollback(&self) -> Rollback {
+        Rollback {
+            ops: self
+                .committed
+                .iter()
+                .flat_map(|rollback| rollback.ops.iter().cloned())
+                .collect(),
+        }
+    }
+
+    /// Commits the transactions that were not committed yet, in order, and calls `on_progress`
+    /// after each of them.
+    ///
+    /// Sending stops at the first transaction refused by the kernel, which is reported in a
+    /// [`QueryError::TransactionSequenceFailed`]. The transactions that were committed before it
+    /// are recorded, so calling this function again resumes the sequence from the refused
+    /// transaction.
+    #[cfg(feature = "socket")]
+    pub fn send(
+        &mut self,
+        mut on_progress: impl FnMut(SequenceProgress),
+    ) -> Result<(), QueryError> {
+        use crate::query::socket_close_wrapper;
+
+        if self.is_complete() {
+            return Ok(());
+        }
+        let sock = NfNetlinkSocket::new()?;
+        socket_close_wrapper(sock, move |sock| {
+            while let Some(transaction) = self.transactions.get(self.committed.len()) {
+                let batch = &transaction.batch;
+                let to_send = ZeroOnDrop(batch.finalized_copy());
+                Batch::send_finalized(sock, &to_send, batch.last_acked_seq(), &batch.objects)
+                    .map_err(|e| QueryError::TransactionSequenceFailed {
+                        index: self.committed.len(),
+                        source: Box::new(e),
+                    })?;
+                self.committed.push(transaction.rollback());
+                on_progress(self.progress());
+            }
+            Ok::<(), QueryError>(())
+        })
+    }
+
+    /// Reverts the committed transactions in a single batch, and marks every transaction of the
+    /// sequence as pending again. See [`Transaction`] for the limits of the rollback.
+    #[cfg(feature = "socket")]
+    pub fn compensate(&mut self) -> Result<(), QueryError> {
+        if self.committed.is_empty() {
+            return Ok(());
+        }
+        self.rollback().send()?;
+        self.committed.clear();
+        Ok(())
+    }
+}
+
+impl Default for TransactionSequence {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Default for Transaction {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The compensating operations of a [`Transaction`], to be applied in reverse order.
+#[derive(Clone)]
+pub struct Rollback {
+    ops: Vec<RollbackOp>,
+}
+
+impl Rollback {
+    /// Creates a new batch containing the inverse of every operation of the transaction, from the
+    /// last one to the first one.
+    pub fn to_batch(&self) -> Batch {
+        let mut batch = Batch::new();
+        for op in self.ops.iter().rev() {
+            op(&mut batch);
+        }
+        batch
+    }
 
-        Ok(socket_close_wrapper(sock, move |sock| {
-            recv_and_process(sock, Some(max_seq), None, &mut ())
-        })?)
+    /// Sends the compensating batch to netfilter.
+    #[cfg(feature = "socket")]
+    pub fn send(&self) -> Result<(), QueryError> {
+        self.to_batch().send()
     }
 }
 
@@ -0,0 +1,267 @@
+//! Blocking of large lists of networks, e.g. the address blocks of whole countries taken from a
+//! GeoIP database, which easily reach hundreds of thousands of entries.
+//!
+//! The networks are merged into ranges, which are loaded in an interval set per address family
+//! (like the sets of nft with `flags interval; auto-merge`). A single rule per family then drops
+//! the packets whose source address is in the set, however long the list is:
+//!
+//! ```ignore
+//! let mut blocklist = Blocklist::new("geoip");
+//! for line in BufReader::new(File::open("cn.zone")?).lines() {
+//!     blocklist.add_network(line?.parse()?);
+//! }
+//! let mut sequence = blocklist.to_sequence(DEFAULT_RANGES_PER_TRANSACTION)?;
+//! sequence.send(|p| println!("{}/{} batches applied", p.committed, p.total))?;
+//! ```
+//!
+//! The elements do not fit in a single batch, so they are split into a [`TransactionSequence`]
+//! whose first transaction recreates the table with its chain, its sets and its rules. Loading
+//! the list again (e.g. after an update of the database) thus replaces the previous one, but the
+//! packets from the networks that are not loaded yet are accepted until the whole sequence is
+//! committed.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use ipnetwork::IpNetwork;
+
+use crate::data_type::DataType;
+use crate::error::BuilderError;
+use crate::expr::{
+    Cmp, CmpOp, Counter, HighLevelPayload, IPv4HeaderField, IPv6HeaderField, Lookup, Meta,
+    MetaType, NetworkHeaderField,
+};
+use crate::set::{Endianness, SetBuilder, SetElementList, SetUserdata};
+use crate::{
+    Chain, ChainPolicy, ChainType, Hook, HookClass, MsgType, ProtocolFamily, Rule, Set, SetFlags,
+    Table, Transaction, TransactionSequence,
+};
+
+/// The name of the set holding the IPv4 ranges.
+pub const IPV4_SET: &str = "ipv4_networks";
+/// The name of the set holding the IPv6 ranges.
+pub const IPV6_SET: &str = "ipv6_networks";
+
+/// A number of ranges per transaction that keeps the batches of both address families under the
+/// default size of the send buffer of netlink sockets, which otherwise fail with `EMSGSIZE`.
+pub const DEFAULT_RANGES_PER_TRANSACTION: usize = 2048;
+
+// the elements of a message are nested in a single attribute, whose length cannot exceed 64KiB
+const RANGES_PER_MESSAGE: usize = 512;
+
+/// A range of addresses, both ends included.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct AddressRange {
+    pub first: IpAddr,
+    pub last: IpAddr,
+}
+
+/// Merges the overlapping and adjacent `networks` into ranges, sorted by address, the IPv4
+/// ranges coming first. This is what nft does for the sets with `auto-merge`, as the kernel
+/// refuses the overlapping ranges.
+pub fn merge_networks(networks: impl IntoIterator<Item = IpNetwork>) -> Vec<AddressRange> {
+    let mut blocklist = Blocklist::new("");
+    blocklist.add_networks(networks);
+    blocklist.ranges()
+}
+
+/// An inet table dropping the packets coming from a list of networks, before connection tracking
+/// sees them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Blocklist {
+    table: Table,
+    ipv4: Vec<(u32, u32)>,
+    ipv6: Vec<(u128, u128)>,
+}
+
+impl Blocklist {
+    pub fn new(table_name: impl Into<String>) -> Self {
+        Blocklist {
+            table: Table::new(ProtocolFamily::Inet).with_name(table_name.into()),
+            ipv4: Vec::new(),
+            ipv6: Vec::new(),
+        }
+    }
+
+    pub fn get_table(&self) -> &Table {
+        &self.table
+    }
+
+    pub fn add_network(&mut self, network: IpNetwork) {
+        match network {
+            IpNetwork::V4(net) => self
+                .ipv4
+                .push((net.network().into(), net.broadcast().into())),
+            IpNetwork::V6(net) => self
+                .ipv6
+                .push((net.network().into(), net.broadcast().into())),
+        }
+    }
+
+    pub fn add_networks(&mut self, networks: impl IntoIterator<Item = IpNetwork>) {
+        for network in networks {
+            self.add_network(network);
+        }
+    }
+
+    pub fn with_networks(mut self, networks: impl IntoIterator<Item = IpNetwork>) -> Self {
+        self.add_networks(networks);
+        self
+    }
+
+    /// The ranges loaded in the sets, see [`merge_networks`].
+    pub fn ranges(&self) -> Vec<AddressRange> {
+        let ipv4 = merge(&self.ipv4)
+            .into_iter()
+            .map(|(first, last)| AddressRange {
+                first: Ipv4Addr::from(first).into(),
+                last: Ipv4Addr::from(last).into(),
+            });
+        let ipv6 = merge(&self.ipv6)
+            .into_iter()
+            .map(|(first, last)| AddressRange {
+                first: Ipv6Addr::from(first).into(),
+                last: Ipv6Addr::from(last).into(),
+            });
+        ipv4.chain(ipv6).collect()
+    }
+
+    /// The base chain holding the rules, on the prerouting hook with the priority of the `raw`
+    /// chains, so that the dropped packets do not create conntrack entries.
+    pub fn chain(&self) -> Chain {
+        Chain::new(&self.table)
+            .with_name("prerouting")
+            .with_hook(Hook::new(HookClass::PreRouting, -300))
+            .with_type(ChainType::Filter)
+            .with_policy(ChainPolicy::Accept)
+    }
+
+    /// The interval sets holding the IPv4 and the IPv6 ranges.
+    pub fn sets(&self) -> Result<(Set, Set), BuilderError> {
+        Ok((
+            interval_set::<Ipv4Addr>(IPV4_SET, &self.table)?,
+            interval_set::<Ipv6Addr>(IPV6_SET, &self.table)?,
+        ))
+    }
+
+    /// The rules dropping the packets whose source address is in one of the sets, with a counter
+    /// of the dropped packets.
+    pub fn rules(&self) -> Result<Vec<Rule>, BuilderError> {
+        let chain = self.chain();
+        let (ipv4, ipv6) = self.sets()?;
+        Ok(vec![
+            drop_rule(
+                &chain,
+                &ipv4,
+                libc::NFPROTO_IPV4,
+                NetworkHeaderField::IPv4(IPv4HeaderField::Saddr),
+            )?,
+            drop_rule(
+                &chain,
+                &ipv6,
+                libc::NFPROTO_IPV6,
+                NetworkHeaderField::IPv6(IPv6HeaderField::Saddr),
+            )?,
+        ])
+    }
+
+    /// Splits the blocklist into transactions: the first one recreates the table with its chain,
+    /// its sets and its rules, and each following one adds at most `ranges_per_transaction`
+    /// ranges to the sets (see [`DEFAULT_RANGES_PER_TRANSACTION`]).
+    pub fn to_sequence(
+        &self,
+        ranges_per_transaction: usize,
+    ) -> Result<TransactionSequence, BuilderError> {
+        let (ipv4, ipv6) = self.sets()?;
+        let mut setup = Transaction::new();
+        // deleting the table drops the ranges of a previous load, which would overlap the new ones
+        setup.add(&self.table, MsgType::Add)?;
+        setup.add(&self.table, MsgType::Del)?;
+        setup.add(&self.table, MsgType::Add)?;
+        // the rollback deletes the table, along with its content
+        setup.add_without_rollback(&self.chain(), MsgType::Add);
+        setup.add_without_rollback(&ipv4, MsgType::Add);
+        setup.add_without_rollback(&ipv6, MsgType::Add);
+        for rule in self.rules()? {
+            setup.add_without_rollback(&rule, MsgType::Add);
+        }
+
+        let mut sequence = TransactionSequence::new();
+        sequence.push(setup);
+        let ranges_per_transaction = ranges_per_transaction.max(1);
+        let ipv4_ranges: Vec<_> = merge(&self.ipv4)
+            .into_iter()
+            .map(|(first, last)| (Ipv4Addr::from(first), Ipv4Addr::from(last)))
+            .collect();
+        push_ranges(&mut sequence, &ipv4, &ipv4_ranges, ranges_per_transaction)?;
+        let ipv6_ranges: Vec<_> = merge(&self.ipv6)
+            .into_iter()
+            .map(|(first, last)| (Ipv6Addr::from(first), Ipv6Addr::from(last)))
+            .collect();
+        push_ranges(&mut sequence, &ipv6, &ipv6_ranges, ranges_per_transaction)?;
+        Ok(sequence)
+    }
+}
+
+fn merge<T: Ord + Copy + From<u8> + std::ops::Add<Output = T>>(ranges: &[(T, T)]) -> Vec<(T, T)> {
+    let mut ranges = ranges.to_vec();
+    ranges.sort_unstable();
+    let mut merged: Vec<(T, T)> = Vec::with_capacity(ranges.len());
+    for (first, last) in ranges {
+        match merged.last_mut() {
+            // the range overlaps the previous one, or follows it directly
+            Some(prev) if first <= prev.1 || first == prev.1 + T::from(1) => {
+                prev.1 = prev.1.max(last)
+            }
+            _ => merged.push((first, last)),
+        }
+    }
+    merged
+}
+
+fn interval_set<K: DataType>(name: &str, table: &Table) -> Result<Set, BuilderError> {
+    let userdata = SetUserdata {
+        key_byteorder: Some(Endianness::Big),
+        merge_elements: true,
+        ..Default::default()
+    };
+    Ok(SetBuilder::<K>::new(name, table)?
+        .add_flags(SetFlags::INTERVAL)
+        .with_userdata_tlvs(&userdata)?
+        .finish()
+        .0)
+}
+
+fn drop_rule(
+    chain: &Chain,
+    set: &Set,
+    nfproto: i32,
+    saddr: NetworkHeaderField,
+) -> Result<Rule, BuilderError> {
+    Ok(Rule::new(chain)?
+        .with_expr(Meta::new(MetaType::NfProto))
+        .with_expr(Cmp::new(CmpOp::Eq, [nfproto as u8]))
+        .with_expr(HighLevelPayload::Network(saddr).build())
+        .with_expr(Lookup::new(set)?)
+        .with_expr(Counter::new(0, 0))
+        .drop())
+}
+
+fn push_ranges<K: DataType>(
+    sequence: &mut TransactionSequence,
+    set: &Set,
+    ranges: &[(K, K)],
+    ranges_per_transaction: usize,
+) -> Result<(), BuilderError> {
+    for chunk in ranges.chunks(ranges_per_transaction) {
+        let mut transaction = Transaction::new();
+        for message in chunk.chunks(RANGES_PER_MESSAGE) {
+            let mut elements = SetElementList::for_set(set)?;
+            for (first, last) in message {
+                elements.add_range(first, last);
+            }
+            transaction.add(&elements, MsgType::Add)?;
+        }
+        sequence.push(transaction);
+    }
+    Ok(())
+}
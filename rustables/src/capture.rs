@@ -0,0 +1,157 @@
+//! Recording of the netlink messages received from the kernel, to attach them to bug reports.
+//!
+//! Once [`start_capture`] is called, every datagram received by the queries and monitors of this
+//! crate is appended to a capture file, until [`stop_capture`] is called. The file uses the
+//! pcap format with the `LINKTYPE_NETLINK` link type, so it can be opened with Wireshark, and
+//! [`read_capture`] reads it back, e.g. to replay the messages in a parser regression test:
+//!
+//! ```ignore
+//! rustables::capture::start_capture("/tmp/rules.pcap")?;
+//! let rules = list_rules_for_chain(&chain)?;
+//! rustables::capture::stop_capture()?;
+//!
+//! for msg in rustables::capture::read_capture("/tmp/rules.pcap")? {
+//!     println!("{:?}: {} bytes", msg.timestamp, msg.payload.len());
+//! }
+//! ```
+//!
+//! Failing to write to the capture file never fails the query: the error is logged, and the
+//! capture stops.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const PCAP_MAGIC: u32 = 0xa1b2_c3d4;
+const PCAP_VERSION: (u16, u16) = (2, 4);
+const PCAP_SNAPLEN: u32 = 0x0004_0000;
+const LINKTYPE_NETLINK: u32 = 253;
+
+/// Size of the "cooked" header preceding every message in `LINKTYPE_NETLINK` captures.
+const COOKED_HEADER_LEN: usize = 16;
+const ARPHRD_NETLINK: u16 = 824;
+/// `PACKET_HOST`: the messages are addressed to the capturing socket.
+const PACKET_HOST: u16 = 0;
+
+static CAPTURE: Mutex<Option<BufWriter<File>>> = Mutex::new(None);
+
+/// A datagram read back from a capture file by [`read_capture`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CapturedMessage {
+    /// When the datagram was received.
+    pub timestamp: SystemTime,
+    /// The datagram, which holds one or several netlink messages.
+    pub payload: Vec<u8>,
+}
+
+/// Starts recording the received datagrams to the file at `path`, which is truncated. Replaces
+/// the capture in progress, if any.
+pub fn start_capture(path: impl AsRef<Path>) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(&PCAP_MAGIC.to_ne_bytes())?;
+    writer.write_all(&PCAP_VERSION.0.to_ne_bytes())?;
+    writer.write_all(&PCAP_VERSION.1.to_ne_bytes())?;
+    // timezone offset and timestamp accuracy, always 0
+    writer.write_all(&[0; 8])?;
+    writer.write_all(&PCAP_SNAPLEN.to_ne_bytes())?;
+    writer.write_all(&LINKTYPE_NETLINK.to_ne_bytes())?;
+
+    let previous = lock().replace(writer);
+    if let Some(mut previous) = previous {
+        previous.flush()?;
+    }
+    Ok(())
+}
+
+/// Stops the capture in progress, if any, and flushes its file.
+pub fn stop_capture() -> io::Result<()> {
+    match lock().take() {
+        Some(mut writer) => writer.flush(),
+        None => Ok(()),
+    }
+}
+
+/// Whether a capture is in progress.
+pub fn is_capturing() -> bool {
+    lock().is_some()
+}
+
+fn lock() -> std::sync::MutexGuard<'static, Option<BufWriter<File>>> {
+    // a panic while holding the lock cannot leave the writer in an invalid state
+    CAPTURE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Appends `datagram` to the capture in progress, if any.
+#[cfg_attr(not(feature = "socket"), allow(dead_code))]
+pub(crate) fn record(datagram: &[u8]) {
+    let mut capture = lock();
+    if let Some(writer) = capture.as_mut() {
+        if let Err(e) = write_record(writer, datagram) {
+            warn!("Stopping the capture of the netlink messages: {}", e);
+            *capture = None;
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "socket"), allow(dead_code))]
+fn write_record(writer: &mut BufWriter<File>, datagram: &[u8]) -> io::Result<()> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let len = (COOKED_HEADER_LEN + datagram.len()) as u32;
+    writer.write_all(&(now.as_secs() as u32).to_ne_bytes())?;
+    writer.write_all(&now.subsec_micros().to_ne_bytes())?;
+    writer.write_all(&len.to_ne_bytes())?;
+    writer.write_all(&len.to_ne_bytes())?;
+
+    // the cooked header is in network byte order
+    writer.write_all(&PACKET_HOST.to_be_bytes())?;
+    writer.write_all(&ARPHRD_NETLINK.to_be_bytes())?;
+    // no link-layer address
+    writer.write_all(&[0; 10])?;
+    writer.write_all(&(libc::NETLINK_NETFILTER as u16).to_be_bytes())?;
+
+    writer.write_all(datagram)?;
+    // keep the file usable if the process is killed
+    writer.flush()
+}
+
+/// Reads the datagrams recorded in the capture file at `path`.
+pub fn read_capture(path: impl AsRef<Path>) -> io::Result<Vec<CapturedMessage>> {
+    let mut data = Vec::new();
+    File::open(path)?.read_to_end(&mut data)?;
+
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+    let read_u32 = |buf: &[u8], pos: usize| {
+        buf.get(pos..pos + 4)
+            .map(|b| u32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+            .ok_or_else(|| invalid("truncated capture"))
+    };
+
+    if read_u32(&data, 0)? != PCAP_MAGIC || read_u32(&data, 20)? != LINKTYPE_NETLINK {
+        return Err(invalid("not a netlink capture written by this crate"));
+    }
+
+    let mut messages = Vec::new();
+    let mut pos = 24;
+    while pos < data.len() {
+        let secs = read_u32(&data, pos)?;
+        let micros = read_u32(&data, pos + 4)?;
+        let len = read_u32(&data, pos + 8)? as usize;
+        pos += 16;
+        let record = data
+            .get(pos..pos + len)
+            .filter(|record| record.len() >= COOKED_HEADER_LEN)
+            .ok_or_else(|| invalid("truncated record"))?;
+        messages.push(CapturedMessage {
+            timestamp: UNIX_EPOCH
+                + Duration::from_secs(secs as u64)
+                + Duration::from_micros(micros as u64),
+            payload: record[COOKED_HEADER_LEN..].to_vec(),
+        });
+        pos += len;
+    }
+    Ok(messages)
+}
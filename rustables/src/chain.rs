@@ -1,19 +1,41 @@
 use libc::{NF_ACCEPT, NF_DROP};
-use rustables_macros::nfnetlink_struct;
+use rustables_macros::{nfnetlink_object, nfnetlink_struct};
 
-use crate::error::{DecodeError, QueryError};
-use crate::nlmsg::{NfNetlinkAttribute, NfNetlinkDeserializable, NfNetlinkObject};
+#[cfg(feature = "socket")]
+use crate::error::QueryError;
+use crate::error::{BuilderError, DecodeError};
+use crate::expr::{Counter, Limit, LogPrefix};
+use crate::nlmsg::{
+    without_use_count_and_handle, NfNetlinkAttribute, NfNetlinkDeserializable, NfNetlinkObject,
+};
 use crate::sys::{
-    NFTA_CHAIN_FLAGS, NFTA_CHAIN_HOOK, NFTA_CHAIN_NAME, NFTA_CHAIN_POLICY, NFTA_CHAIN_TABLE,
-    NFTA_CHAIN_TYPE, NFTA_HOOK_HOOKNUM, NFTA_HOOK_PRIORITY, NFT_MSG_DELCHAIN, NFT_MSG_NEWCHAIN,
+    NFTA_CHAIN_FLAGS, NFTA_CHAIN_HANDLE, NFTA_CHAIN_HOOK, NFTA_CHAIN_NAME, NFTA_CHAIN_POLICY,
+    NFTA_CHAIN_TABLE, NFTA_CHAIN_TYPE, NFTA_CHAIN_USE, NFTA_HOOK_DEVS, NFTA_HOOK_HOOKNUM,
+    NFTA_HOOK_PRIORITY, NFT_CHAIN_BASE, NFT_CHAIN_BINDING, NFT_CHAIN_HW_OFFLOAD, NFT_MSG_DELCHAIN,
+    NFT_MSG_NEWCHAIN,
 };
-use crate::{Batch, ProtocolFamily, Table};
+use crate::{Batch, DeviceList, MsgType, ProtocolFamily, Rule, SymbolicPriority, Table};
 use std::fmt::Debug;
 
 pub type ChainPriority = i32;
 
+bitflags::bitflags! {
+    /// The flags of a chain, as reported by the kernel.
+    pub struct ChainFlags: u32 {
+        /// The chain is a base chain, i.e. it is attached to a hook.
+        const BASE = NFT_CHAIN_BASE;
+        /// The rules of the chain are offloaded to the hardware.
+        const HW_OFFLOAD = NFT_CHAIN_HW_OFFLOAD;
+        /// The chain is bound to the rule that created it (an anonymous chain, e.g. created by a
+        /// verdict map), and is owned by that rule.
+        const BINDING = NFT_CHAIN_BINDING;
+    }
+}
+
 /// The netfilter event hooks a chain can register for.
 #[derive(Debug, Copy, Clone, Eq, PartialEq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 #[repr(i32)]
 pub enum HookClass {
     /// Hook into the pre-routing stage of netfilter. Corresponds to `NF_INET_PRE_ROUTING`.
@@ -30,12 +52,17 @@ pub enum HookClass {
 
 #[derive(Clone, PartialEq, Eq, Default, Debug)]
 #[nfnetlink_struct(nested = true)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Hook {
     /// Define the action netfilter will apply to packets processed by this chain, but that did not match any rules in it.
     #[field(NFTA_HOOK_HOOKNUM)]
     class: u32,
     #[field(NFTA_HOOK_PRIORITY)]
     priority: u32,
+    /// The devices the chain is bound to, for the chains of netdev tables (and the ingress hook
+    /// of inet tables).
+    #[field(NFTA_HOOK_DEVS)]
+    devices: DeviceList,
 }
 
 impl Hook {
@@ -44,11 +71,46 @@ impl Hook {
             .with_class(class as u32)
             .with_priority(priority as u32)
     }
+
+    /// Sets the priority of the hook from its nft notation (e.g. `mangle + 10`), for a chain in
+    /// a table of `family`. Fails if the standard priority is not available in that family, or
+    /// on the hook of `self` (e.g. `dstnat` on the `postrouting` hook).
+    pub fn with_symbolic_priority(
+        self,
+        priority: SymbolicPriority,
+        family: ProtocolFamily,
+    ) -> Result<Self, BuilderError> {
+        let priority = priority.resolve(family, self.get_class().copied())?;
+        Ok(self.with_priority(priority as u32))
+    }
+
+    /// A hook on the ingress path of `devices`, for the base chains of netdev tables.
+    ///
+    /// This is the earliest point where packets can be filtered, right after the driver hands
+    /// them to the network stack, which makes it the place of choice to drop unwanted traffic
+    /// (e.g. during a DDoS) before it costs anything more.
+    pub fn ingress(priority: ChainPriority, devices: DeviceList) -> Self {
+        Hook::default()
+            .with_class(libc::NF_NETDEV_INGRESS as u32)
+            .with_priority(priority as u32)
+            .with_devices(devices)
+    }
+
+    /// A hook on the egress path of `devices`, for the base chains of netdev tables (Linux 5.16
+    /// and later).
+    pub fn egress(priority: ChainPriority, devices: DeviceList) -> Self {
+        Hook::default()
+            .with_class(libc::NF_NETDEV_EGRESS as u32)
+            .with_priority(priority as u32)
+            .with_devices(devices)
+    }
 }
 
 /// A chain policy. Decides what to do with a packet that was processed by the chain but did not
 /// match any rules.
 #[derive(Debug, Copy, Clone, Eq, PartialEq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 #[repr(i32)]
 pub enum ChainPolicy {
     /// Accept the packet.
@@ -73,7 +135,7 @@ impl NfNetlinkDeserializable for ChainPolicy {
         Ok((
             match v {
                 NF_ACCEPT => ChainPolicy::Accept,
-                NF_DROP => ChainPolicy::Accept,
+                NF_DROP => ChainPolicy::Drop,
                 _ => return Err(DecodeError::UnknownChainPolicy),
             },
             remaining_data,
@@ -83,9 +145,11 @@ impl NfNetlinkDeserializable for ChainPolicy {
 
 /// Base chain type.
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum ChainType {
     /// Used to filter packets.
-    /// Supported protocols: ip, ip6, inet, arp, and bridge tables.
+    /// Supported protocols: ip, ip6, inet, arp, bridge, and netdev tables.
     Filter,
     /// Used to reroute packets if IP headers or packet marks are modified.
     /// Supported protocols: ip, and ip6 tables.
@@ -96,7 +160,7 @@ pub enum ChainType {
 }
 
 impl ChainType {
-    fn as_str(&self) -> &'static str {
+    pub(crate) fn as_str(&self) -> &'static str {
         match *self {
             ChainType::Filter => "filter",
             ChainType::Route => "route",
@@ -132,26 +196,74 @@ impl NfNetlinkDeserializable for ChainType {
 
 /// Abstraction over an nftable chain. Chains reside inside [`Table`]s and they hold [`Rule`]s.
 ///
+/// The policy of a chain is optional: regular (non-base) chains have no policy, and the kernel
+/// omits it when listing them, in which case `get_policy()` returns `None`. No policy attribute
+/// is sent to the kernel unless one was explicitly set. Note that a policy can only be applied to
+/// a base chain, i.e. a chain with a [`Hook`], see [`Chain::validate`].
+///
+/// Chains can also be created with [`Chain::builder`], which only builds them once their table
+/// and name are set:
+///
+/// ```
+/// use rustables::{Chain, ProtocolFamily};
+///
+/// let chain = Chain::builder()
+///     .with_family(ProtocolFamily::Inet)
+///     .with_table("filter")
+///     .with_name("input")
+///     .build();
+/// ```
+///
+/// ```compile_fail
+/// // the name of the chain is missing
+/// let chain = rustables::Chain::builder().with_table("filter").build();
+/// ```
+///
 /// [`Table`]: struct.Table.html
 /// [`Rule`]: struct.Rule.html
-#[nfnetlink_struct(derive_deserialize = false)]
-#[derive(PartialEq, Eq, Default, Debug)]
+#[nfnetlink_struct(derive_deserialize = false, builder = "ChainBuilder")]
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Chain {
     family: ProtocolFamily,
-    #[field(NFTA_CHAIN_TABLE)]
+    #[field(NFTA_CHAIN_TABLE, required = true)]
     table: String,
-    #[field(NFTA_CHAIN_NAME)]
+    #[field(NFTA_CHAIN_HANDLE)]
+    handle: u64,
+    #[field(NFTA_CHAIN_NAME, required = true)]
     name: String,
     #[field(NFTA_CHAIN_HOOK)]
     hook: Hook,
     #[field(NFTA_CHAIN_POLICY)]
     policy: ChainPolicy,
+    /// The number of rules and other chains referencing the chain, as dumped by the kernel.
+    #[field(NFTA_CHAIN_USE)]
+    use_count: u32,
     #[field(NFTA_CHAIN_TYPE, name_in_functions = "type")]
     chain_type: ChainType,
     #[field(NFTA_CHAIN_FLAGS)]
     flags: u32,
-    #[field(optional = true, crate::sys::NFTA_CHAIN_USERDATA)]
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, with = "crate::serde_helpers::option_hex")
+    )]
+    #[field(
+        optional = true,
+        stub_if_missing = true,
+        crate::sys::NFTA_CHAIN_USERDATA
+    )]
     userdata: Vec<u8>,
+    /// The id of the chain in the batch adding it, see [`Batch::add_chain`].
+    #[field(optional = true, stub_if_missing = true, crate::sys::NFTA_CHAIN_ID)]
+    id: u32,
+}
+
+impl<TableState, NameState> ChainBuilder<TableState, NameState> {
+    /// Sets the protocol family of the chain, which must be the one of its table.
+    pub fn with_family(mut self, family: ProtocolFamily) -> Self {
+        self.inner.family = family;
+        self
+    }
 }
 
 impl Chain {
@@ -169,26 +281,167 @@ impl Chain {
         chain
     }
 
+    /// The flags of the chain, ignoring the ones unknown to this library.
+    pub fn get_chain_flags(&self) -> Option<ChainFlags> {
+        self.get_flags().map(|f| ChainFlags::from_bits_truncate(*f))
+    }
+
+    /// The priority of the hook of the chain, as nft displays it (e.g. `filter + 5`). Returns
+    /// `None` if the chain is not a base chain.
+    pub fn symbolic_priority(&self) -> Option<SymbolicPriority> {
+        let hook = self.get_hook()?;
+        Some(SymbolicPriority::describe(
+            *hook.get_priority()? as ChainPriority,
+            self.family,
+            *hook.get_class()?,
+        ))
+    }
+
+    /// Whether the chain is bound to a rule, see [`ChainFlags::BINDING`].
+    pub fn is_bound(&self) -> bool {
+        self.get_chain_flags()
+            .is_some_and(|f| f.contains(ChainFlags::BINDING))
+    }
+
+    /// Whether the rules of the chain are offloaded to the hardware.
+    pub fn is_offloaded(&self) -> bool {
+        self.get_chain_flags()
+            .is_some_and(|f| f.contains(ChainFlags::HW_OFFLOAD))
+    }
+
+    /// Checks that the chain can be accepted by the kernel.
+    ///
+    /// Setting a policy on a chain without a hook is rejected by the kernel with `EOPNOTSUPP`,
+    /// which is not particularly helpful to track down the issue.
+    ///
+    /// In bridge tables, only `filter` chains are available, on the five hooks of
+    /// [`HookClass`].
+    ///
+    /// The base chains of netdev tables must be bound to at least one device, on either the
+    /// ingress or the egress hook (see [`Hook::ingress`] and [`Hook::egress`]), and can only be
+    /// `filter` chains. Conversely, the chains of the other families cannot be bound to devices,
+    /// save for the ingress hook of inet tables.
+    pub fn validate(&self) -> Result<(), BuilderError> {
+        if self.get_policy().is_some() && self.get_hook().is_none() {
+            return Err(BuilderError::ChainPolicyWithoutHook);
+        }
+        if let Some(hook) = self.get_hook() {
+            match hook.get_devices() {
+                Some(devices) if !devices.is_empty() => devices.validate()?,
+                _ if self.family == ProtocolFamily::NetDev => {
+                    return Err(BuilderError::MissingChainDevices)
+                }
+                _ => {}
+            }
+            let on_inet_ingress = self.family == ProtocolFamily::Inet
+                && hook.get_class() == Some(&(libc::NF_INET_INGRESS as u32));
+            if self.family != ProtocolFamily::NetDev
+                && !on_inet_ingress
+                && hook
+                    .get_devices()
+                    .is_some_and(|devices| !devices.is_empty())
+            {
+                return Err(BuilderError::UnexpectedChainDevices);
+            }
+        }
+        if self.family == ProtocolFamily::NetDev {
+            if let Some(&class) = self.get_hook().and_then(|hook| hook.get_class()) {
+                if class != libc::NF_NETDEV_INGRESS as u32 && class != libc::NF_NETDEV_EGRESS as u32
+                {
+                    return Err(BuilderError::UnsupportedHookForFamily(class));
+                }
+            }
+            if matches!(self.get_type(), Some(ChainType::Route | ChainType::Nat)) {
+                return Err(BuilderError::UnsupportedChainTypeForFamily);
+            }
+        }
+        if self.family == ProtocolFamily::Bridge {
+            if let Some(&class) = self.get_hook().and_then(|hook| hook.get_class()) {
+                if !(libc::NF_BR_PRE_ROUTING as u32..=libc::NF_BR_POST_ROUTING as u32)
+                    .contains(&class)
+                {
+                    return Err(BuilderError::UnsupportedHookForFamily(class));
+                }
+            }
+            if matches!(self.get_type(), Some(ChainType::Route | ChainType::Nat)) {
+                return Err(BuilderError::UnsupportedChainTypeForFamily);
+            }
+        }
+        Ok(())
+    }
+
     /// Appends this chain to `batch`
     pub fn add_to_batch(self, batch: &mut Batch) -> Self {
         batch.add(&self, crate::MsgType::Add);
         self
     }
+
+    /// Makes the chain drop the packets that none of its rules accepted: the chain is added to
+    /// `batch` again with a drop policy and, if `log` is set, followed by a final rule that counts
+    /// the dropped packets and logs them with the given prefix, at most at the given rate.
+    ///
+    /// Call this after adding the other rules of the chain to `batch`, as the logging rule must
+    /// come last to only see the packets about to be dropped. The counter is placed before the
+    /// limit, so that it counts all the dropped packets, including the ones that were not logged.
+    ///
+    /// Fails if the chain is not a base chain, as only those can have a policy.
+    pub fn drop_by_default(
+        self,
+        batch: &mut Batch,
+        log: Option<(LogPrefix, Limit)>,
+    ) -> Result<Self, BuilderError> {
+        let chain = self.with_policy(ChainPolicy::Drop);
+        chain.validate()?;
+        batch.add(&chain, MsgType::Add);
+        if let Some((prefix, rate)) = log {
+            Rule::new(&chain)?
+                .with_expr(Counter::default())
+                .log_limited(prefix, rate)?
+                .drop()
+                .add_to_batch(batch);
+        }
+        Ok(chain)
+    }
+
+    /// Adds to `batch` a message deleting every rule of the chain, while keeping the chain itself
+    /// (the equivalent of `nft flush chain`).
+    pub fn flush(&self, batch: &mut Batch) -> Result<(), BuilderError> {
+        batch.add(&Rule::new(self)?, MsgType::Del);
+        Ok(())
+    }
 }
 
+#[nfnetlink_object(add = NFT_MSG_NEWCHAIN, del = NFT_MSG_DELCHAIN, family_field = family)]
 impl NfNetlinkObject for Chain {
-    const MSG_TYPE_ADD: u32 = NFT_MSG_NEWCHAIN;
-    const MSG_TYPE_DEL: u32 = NFT_MSG_DELCHAIN;
+    fn object_name(&self) -> Option<String> {
+        self.name.clone()
+    }
 
-    fn get_family(&self) -> ProtocolFamily {
-        self.family
+    fn without_read_only_attributes(&self, msg_type: MsgType, _flags: u16) -> Option<Self> {
+        without_use_count_and_handle!(self, msg_type)
     }
 
-    fn set_family(&mut self, family: ProtocolFamily) {
-        self.family = family;
+    fn missing_attributes(&self, _msg_type: MsgType) -> Vec<&'static str> {
+        let mut missing = Vec::new();
+        if self.table.is_none() {
+            missing.push("NFTA_CHAIN_TABLE");
+        }
+        if self.name.is_none() {
+            missing.push("NFTA_CHAIN_NAME");
+        }
+        if let Some(hook) = &self.hook {
+            if hook.class.is_none() {
+                missing.push("NFTA_HOOK_HOOKNUM");
+            }
+            if hook.priority.is_none() {
+                missing.push("NFTA_HOOK_PRIORITY");
+            }
+        }
+        missing
     }
 }
 
+#[cfg(feature = "socket")]
 pub fn list_chains_for_table(table: &Table) -> Result<Vec<Chain>, QueryError> {
     let mut result = Vec::new();
     crate::query::list_objects_with_data(
@@ -205,8 +458,26 @@ pub fn list_chains_for_table(table: &Table) -> Result<Vec<Chain>, QueryError> {
             }
             Ok(())
         },
-        None,
+        // only retrieve the chains of the family of the table, the kernel ignores the table name
+        Some(&Chain::new(table)),
         &mut (&table, &mut result),
     )?;
     Ok(result)
 }
+
+/// Lists the chains of all the tables of `family`, or of all the families with
+/// [`ProtocolFamily::Unspec`].
+#[cfg(feature = "socket")]
+pub fn list_chains_for_family(family: ProtocolFamily) -> Result<Vec<Chain>, QueryError> {
+    let mut result = Vec::new();
+    crate::query::list_objects_with_data(
+        libc::NFT_MSG_GETCHAIN as u16,
+        &|chain: Chain, chains: &mut Vec<Chain>| {
+            chains.push(chain);
+            Ok(())
+        },
+        Some(&Chain::default().with_family(family)),
+        &mut result,
+    )?;
+    Ok(result)
+}
@@ -0,0 +1,261 @@
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+use crate::error::BuilderError;
+use crate::{ChainPriority, ProtocolFamily};
+
+/// The standard priorities nft knows by name. Their value depends on the family of the table.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum StandardPriority {
+    Raw,
+    Mangle,
+    DstNat,
+    Filter,
+    Security,
+    SrcNat,
+    /// Only available in bridge tables.
+    Out,
+}
+
+impl StandardPriority {
+    const ALL: [StandardPriority; 7] = [
+        StandardPriority::Raw,
+        StandardPriority::Mangle,
+        StandardPriority::DstNat,
+        StandardPriority::Filter,
+        StandardPriority::Security,
+        StandardPriority::SrcNat,
+        StandardPriority::Out,
+    ];
+
+    /// The value of the priority in the tables of `family`, or `None` if nft doesn't define it in
+    /// that family.
+    pub fn value(self, family: ProtocolFamily) -> Option<ChainPriority> {
+        match family {
+            ProtocolFamily::Ipv4 | ProtocolFamily::Ipv6 | ProtocolFamily::Inet => match self {
+                StandardPriority::Raw => Some(-300),
+                StandardPriority::Mangle => Some(-150),
+                StandardPriority::DstNat => Some(-100),
+                StandardPriority::Filter => Some(0),
+                StandardPriority::Security => Some(50),
+                StandardPriority::SrcNat => Some(100),
+                StandardPriority::Out => None,
+            },
+            ProtocolFamily::Bridge => match self {
+                StandardPriority::DstNat => Some(-300),
+                StandardPriority::Filter => Some(-200),
+                StandardPriority::Out => Some(100),
+                StandardPriority::SrcNat => Some(300),
+                _ => None,
+            },
+            ProtocolFamily::Arp | ProtocolFamily::NetDev => match self {
+                StandardPriority::Filter => Some(0),
+                _ => None,
+            },
+            ProtocolFamily::Unspec | ProtocolFamily::DecNet | ProtocolFamily::Other(_) => None,
+        }
+    }
+
+    /// Whether nft accepts the priority on the hook `hook` (e.g. `dstnat` is only meant for the
+    /// hooks where the destination of the packets is translated).
+    fn is_available_on_hook(self, family: ProtocolFamily, hook: u32) -> bool {
+        let hook = hook as i32;
+        let bridge = family == ProtocolFamily::Bridge;
+        match self {
+            StandardPriority::DstNat => {
+                hook == libc::NF_INET_PRE_ROUTING || (!bridge && hook == libc::NF_INET_LOCAL_OUT)
+            }
+            StandardPriority::SrcNat => {
+                hook == libc::NF_INET_POST_ROUTING || (!bridge && hook == libc::NF_INET_LOCAL_IN)
+            }
+            StandardPriority::Out => hook == libc::NF_BR_LOCAL_OUT,
+            _ => true,
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            StandardPriority::Raw => "raw",
+            StandardPriority::Mangle => "mangle",
+            StandardPriority::DstNat => "dstnat",
+            StandardPriority::Filter => "filter",
+            StandardPriority::Security => "security",
+            StandardPriority::SrcNat => "srcnat",
+            StandardPriority::Out => "out",
+        }
+    }
+}
+
+impl Display for StandardPriority {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for StandardPriority {
+    type Err = BuilderError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        StandardPriority::ALL
+            .into_iter()
+            .find(|priority| priority.as_str() == s)
+            .ok_or_else(|| BuilderError::InvalidPriority(s.to_string()))
+    }
+}
+
+/// A chain priority as written in nft: either a number, or a standard priority with an optional
+/// offset (e.g. `filter`, or `mangle + 10`).
+///
+/// The value of a standard priority depends on the family of the table, so symbolic priorities
+/// are resolved when they are set on a hook, with [`Hook::with_symbolic_priority`]. Conversely,
+/// [`Chain::symbolic_priority`] displays the priority of a chain like nft does.
+///
+/// With the `serde` feature, the priority is (de)serialized as a number or as a string.
+///
+/// [`Hook::with_symbolic_priority`]: crate::Hook::with_symbolic_priority
+/// [`Chain::symbolic_priority`]: crate::Chain::symbolic_priority
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Default)]
+pub struct SymbolicPriority {
+    base: Option<StandardPriority>,
+    offset: i32,
+}
+
+impl SymbolicPriority {
+    pub fn new(base: StandardPriority, offset: i32) -> Self {
+        SymbolicPriority {
+            base: Some(base),
+            offset,
+        }
+    }
+
+    pub fn get_base(&self) -> Option<StandardPriority> {
+        self.base
+    }
+
+    /// The offset relative to the standard priority, or the priority itself when there is no
+    /// standard priority.
+    pub fn get_offset(&self) -> i32 {
+        self.offset
+    }
+
+    /// Computes the value of the priority in the tables of `family`, for a chain on the hook
+    /// `hook` (if known). Fails if the standard priority is not available there.
+    pub fn resolve(
+        &self,
+        family: ProtocolFamily,
+        hook: Option<u32>,
+    ) -> Result<ChainPriority, BuilderError> {
+        let base = match self.base {
+            Some(base) => base,
+            None => return Ok(self.offset),
+        };
+        match base.value(family) {
+            Some(value)
+                if hook
+                    .into_iter()
+                    .all(|hook| base.is_available_on_hook(family, hook)) =>
+            {
+                value
+                    .checked_add(self.offset)
+                    .ok_or_else(|| BuilderError::InvalidPriority(self.to_string()))
+            }
+            _ => Err(BuilderError::UnsupportedPriority(self.to_string())),
+        }
+    }
+
+    /// Describes `priority` like nft does: relative to a standard priority when it is at most 10
+    /// away from one that is available on the hook, and as a number otherwise.
+    pub fn describe(priority: ChainPriority, family: ProtocolFamily, hook: u32) -> Self {
+        StandardPriority::ALL
+            .into_iter()
+            .filter(|base| base.is_available_on_hook(family, hook))
+            .find_map(|base| {
+                let offset = priority.checked_sub(base.value(family)?)?;
+                (offset.abs() <= 10).then(|| SymbolicPriority::new(base, offset))
+            })
+            .unwrap_or_else(|| SymbolicPriority::from(priority))
+    }
+}
+
+impl From<ChainPriority> for SymbolicPriority {
+    fn from(priority: ChainPriority) -> Self {
+        SymbolicPriority {
+            base: None,
+            offset: priority,
+        }
+    }
+}
+
+impl Display for SymbolicPriority {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.base {
+            None => write!(f, "{}", self.offset),
+            Some(base) if self.offset > 0 => write!(f, "{} + {}", base, self.offset),
+            Some(base) if self.offset < 0 => write!(f, "{} - {}", base, self.offset.unsigned_abs()),
+            Some(base) => Display::fmt(&base, f),
+        }
+    }
+}
+
+impl FromStr for SymbolicPriority {
+    type Err = BuilderError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || BuilderError::InvalidPriority(s.to_string());
+        let s = s.trim();
+        if let Ok(priority) = s.parse::<ChainPriority>() {
+            return Ok(SymbolicPriority::from(priority));
+        }
+
+        let name_len = s
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(s.len());
+        let base = s[..name_len].parse().map_err(|_| invalid())?;
+        let rest = s[name_len..].trim_start();
+        let offset = match rest.chars().next() {
+            None => 0,
+            Some(sign @ ('+' | '-')) => {
+                let digits = rest[1..].trim_start();
+                // reject a second sign, e.g. `filter + -5`
+                if !digits.starts_with(|c: char| c.is_ascii_digit()) {
+                    return Err(invalid());
+                }
+                let value = digits.parse::<i32>().map_err(|_| invalid())?;
+                if sign == '-' {
+                    -value
+                } else {
+                    value
+                }
+            }
+            Some(_) => return Err(invalid()),
+        };
+        Ok(SymbolicPriority::new(base, offset))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for SymbolicPriority {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.base {
+            None => serializer.serialize_i32(self.offset),
+            Some(_) => serializer.collect_str(self),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for SymbolicPriority {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(serde::Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Value(ChainPriority),
+            Symbol(String),
+        }
+
+        match Repr::deserialize(deserializer)? {
+            Repr::Value(priority) => Ok(SymbolicPriority::from(priority)),
+            Repr::Symbol(s) => s.parse().map_err(serde::de::Error::custom),
+        }
+    }
+}
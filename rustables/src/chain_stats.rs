@@ -0,0 +1,224 @@
+use std::time::{Duration, Instant};
+
+use crate::expr::Counter;
+use crate::Rule;
+
+#[cfg(feature = "socket")]
+use crate::{
+    error::QueryError,
+    query::{list_objects_with_socket, NfNetlinkSocket, QueryBuffer},
+    Chain,
+};
+
+/// The counters of a rule in a [`ChainSample`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct RuleSample {
+    pub handle: Option<u64>,
+    /// The traffic counted by the rule since the first sample, or since the creation of the rule
+    /// when its counters are not reset on read.
+    pub counter: Counter,
+    /// The traffic counted by the rule since the previous sample, or in total for the first
+    /// sample of the rule.
+    pub delta: Counter,
+    /// The average rates since the previous sample, in packets and bytes per second, or `None`
+    /// for the first sample.
+    pub rates: Option<(f64, f64)>,
+}
+
+/// The counters of the rules of a chain, read at once by a [`ChainStatsSampler`].
+///
+/// The rules without a counter expression are left out.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChainSample {
+    /// When the counters were read, on the monotonic clock, so that the rates are not affected by
+    /// changes of the system time.
+    pub taken_at: Instant,
+    /// The time elapsed since the previous sample, or `None` for the first sample.
+    pub elapsed: Option<Duration>,
+    pub rules: Vec<RuleSample>,
+}
+
+impl ChainSample {
+    /// Computes the sample of the counters of `rules`, read at `taken_at`, following `previous`.
+    ///
+    /// If `reset` is set, the counters of `rules` were reset when they were read, so they only
+    /// hold the traffic counted since `previous`.
+    #[cfg_attr(not(feature = "socket"), allow(dead_code))]
+    pub(crate) fn new(
+        rules: &[Rule],
+        taken_at: Instant,
+        previous: Option<&ChainSample>,
+        reset: bool,
+    ) -> Self {
+        let elapsed = previous
+            .and_then(|previous| taken_at.checked_duration_since(previous.taken_at))
+            .filter(|elapsed| !elapsed.is_zero());
+        let rules = rules
+            .iter()
+            .filter_map(|rule| {
+                let (bytes, packets) = rule.counters()?;
+                let read = Counter::new(packets, bytes);
+                let handle = rule.get_handle().copied();
+                // the rules without a handle cannot be told apart across samples
+                let last = previous.and_then(|previous| {
+                    previous
+                        .rules
+                        .iter()
+                        .find(|r| handle.is_some() && r.handle == handle)
+                });
+                let (counter, delta) = match (last, reset) {
+                    (Some(last), true) => (last.counter.merge(&read), read),
+                    (Some(last), false) => (read.clone(), read.delta_since(&last.counter)),
+                    (None, _) => (read.clone(), read),
+                };
+                let rates = elapsed.map(|elapsed| {
+                    let secs = elapsed.as_secs_f64();
+                    (delta.packets() as f64 / secs, delta.bytes() as f64 / secs)
+                });
+                Some(RuleSample {
+                    handle,
+                    counter,
+                    delta,
+                    rates,
+                })
+            })
+            .collect();
+        ChainSample {
+            taken_at,
+            elapsed,
+            rules,
+        }
+    }
+
+    /// The traffic counted by all the rules of the chain since the previous sample.
+    pub fn delta(&self) -> Counter {
+        self.rules.iter().map(|rule| &rule.delta).sum()
+    }
+
+    /// The average rates of all the rules of the chain since the previous sample, in packets and
+    /// bytes per second, or `None` for the first sample.
+    pub fn rates(&self) -> Option<(f64, f64)> {
+        let secs = self.elapsed?.as_secs_f64();
+        let delta = self.delta();
+        Some((delta.packets() as f64 / secs, delta.bytes() as f64 / secs))
+    }
+
+    /// The sample of the rule with `handle`.
+    pub fn rule(&self, handle: u64) -> Option<&RuleSample> {
+        self.rules.iter().find(|rule| rule.handle == Some(handle))
+    }
+}
+
+/// Reads the counters of the rules of a chain periodically, e.g. to feed a dashboard with the
+/// traffic rates of every rule:
+///
+/// ```ignore
+/// let mut sampler = ChainStatsSampler::new(&chain)?.with_reset(true);
+/// for sample in sampler.samples(Duration::from_secs(1)) {
+///     if let Some((pps, bps)) = sample?.rates() {
+///         println!("{:.0} packets/s, {:.0} bytes/s", pps, bps);
+///     }
+/// }
+/// ```
+///
+/// The sampler keeps its socket and its receive buffer across samples, so reading the counters
+/// every second does not open a socket or allocate a buffer of more than 128KB every time.
+#[cfg(feature = "socket")]
+pub struct ChainStatsSampler {
+    filter: Rule,
+    sock: NfNetlinkSocket,
+    buffer: QueryBuffer,
+    reset: bool,
+    last: Option<ChainSample>,
+}
+
+#[cfg(feature = "socket")]
+impl ChainStatsSampler {
+    /// Opens a socket to sample the counters of the rules of `chain`.
+    pub fn new(chain: &Chain) -> Result<Self, QueryError> {
+        Self::with_socket(chain, NfNetlinkSocket::new()?)
+    }
+
+    /// Samples the counters of the rules of `chain` on `sock`, which must not be used for other
+    /// queries in the meantime.
+    pub fn with_socket(chain: &Chain, sock: NfNetlinkSocket) -> Result<Self, QueryError> {
+        Ok(ChainStatsSampler {
+            filter: Rule::new(chain)?,
+            sock,
+            buffer: QueryBuffer::new(),
+            reset: false,
+            last: None,
+        })
+    }
+
+    /// Resets the counters of the rules every time they are read, see
+    /// [`list_rules_for_chain_with_reset`](crate::list_rules_for_chain_with_reset). This requires
+    /// Linux 6.3, and the counters then only hold the traffic since the previous sample, for all
+    /// of their readers.
+    pub fn with_reset(mut self, reset: bool) -> Self {
+        self.reset = reset;
+        self
+    }
+
+    /// The last sample, if any.
+    pub fn last(&self) -> Option<&ChainSample> {
+        self.last.as_ref()
+    }
+
+    /// Reads the counters of the rules now, and computes their rates since the previous sample.
+    pub fn sample(&mut self) -> Result<&ChainSample, QueryError> {
+        let msg_type = if self.reset {
+            crate::sys::NFT_MSG_GETRULE_RESET
+        } else {
+            libc::NFT_MSG_GETRULE as u32
+        };
+        let mut rules = Vec::new();
+        list_objects_with_socket(
+            &self.sock,
+            &mut self.buffer,
+            msg_type as u16,
+            &|rule: Rule, rules: &mut Vec<Rule>| {
+                rules.push(rule);
+                Ok(())
+            },
+            Some(&self.filter),
+            &mut rules,
+        )?;
+        let sample = ChainSample::new(&rules, Instant::now(), self.last.as_ref(), self.reset);
+        Ok(self.last.insert(sample))
+    }
+
+    /// Returns an endless iterator sampling the counters every `interval`, the first sample being
+    /// taken immediately. The iterator sleeps between the samples, and keeps a steady pace when
+    /// reading the counters takes time.
+    pub fn samples(&mut self, interval: Duration) -> Samples<'_> {
+        Samples {
+            sampler: self,
+            interval,
+            next: Instant::now(),
+        }
+    }
+}
+
+/// The iterator returned by [`ChainStatsSampler::samples`].
+#[cfg(feature = "socket")]
+pub struct Samples<'a> {
+    sampler: &'a mut ChainStatsSampler,
+    interval: Duration,
+    next: Instant,
+}
+
+#[cfg(feature = "socket")]
+impl Iterator for Samples<'_> {
+    type Item = Result<ChainSample, QueryError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let now = Instant::now();
+        if self.next > now {
+            std::thread::sleep(self.next - now);
+        }
+        // skip the deadlines missed while the consumer was busy, rather than sampling in bursts
+        self.next = std::cmp::max(self.next + self.interval, Instant::now());
+        Some(self.sampler.sample().cloned())
+    }
+}
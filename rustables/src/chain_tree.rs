@@ -0,0 +1,115 @@
+use std::collections::HashSet;
+
+use crate::error::BuilderError;
+use crate::expr::{Immediate, VerdictKind};
+use crate::nlmsg::NfNetlinkObject;
+use crate::{Batch, Chain, MsgType, Rule, Table};
+
+/// A hierarchy of chains, where each chain dispatches the packets to its sub-chains with jump
+/// rules.
+///
+/// This is the usual way to organize a large ruleset in zones (e.g. one sub-chain per interface
+/// or per network), where the rules of a zone are only evaluated for the packets of that zone:
+///
+/// ```ignore
+/// let tree = ChainTree::new(input_chain)
+///     .branch("lan", |rule| rule.iiface("eth0"))?
+///     .branch("wan", |rule| rule.iiface("eth1"))?;
+/// let mut batch = Batch::new();
+/// tree.add_to_batch(&mut batch);
+/// ```
+#[derive(Clone, Debug)]
+pub struct ChainTree {
+    chain: Chain,
+    branches: Vec<(Rule, ChainTree)>,
+}
+
+impl ChainTree {
+    /// Creates a tree whose root is `chain`, which is usually a base chain.
+    pub fn new(chain: Chain) -> Self {
+        ChainTree {
+            chain,
+            branches: Vec::new(),
+        }
+    }
+
+    /// The chain at the root of this tree.
+    pub fn chain(&self) -> &Chain {
+        &self.chain
+    }
+
+    /// Adds a regular chain named `name` under the root of the tree, and a rule to the root that
+    /// jumps to it. `matcher` receives the (empty) jump rule, and adds the matches selecting the
+    /// packets to dispatch to the new chain.
+    pub fn branch(
+        self,
+        name: &str,
+        matcher: impl FnOnce(Rule) -> Result<Rule, BuilderError>,
+    ) -> Result<Self, BuilderError> {
+        let table = Table::new(self.chain.get_family()).with_name(
+            self.chain
+                .get_table()
+                .ok_or(BuilderError::MissingChainInformationError)?,
+        );
+        let subtree = ChainTree::new(Chain::new(&table).with_name(name));
+        self.subtree(subtree, matcher)
+    }
+
+    /// Same as [`ChainTree::branch`], with a whole tree to graft under the root. The root of
+    /// `subtree` must be a regular chain of the same table.
+    pub fn subtree(
+        mut self,
+        subtree: ChainTree,
+        matcher: impl FnOnce(Rule) -> Result<Rule, BuilderError>,
+    ) -> Result<Self, BuilderError> {
+        if subtree.chain.get_table() != self.chain.get_table()
+            || subtree.chain.get_family() != self.chain.get_family()
+        {
+            return Err(BuilderError::ChainTreeTableMismatch);
+        }
+        let verdict = VerdictKind::jump_to(&subtree.chain)?;
+
+        let mut names = HashSet::new();
+        for chain in self.chains().into_iter().chain(subtree.chains()) {
+            if let Some(name) = chain.get_name() {
+                if !names.insert(name) {
+                    return Err(BuilderError::DuplicateChainName(name.clone()));
+                }
+            }
+        }
+
+        let rule = matcher(Rule::new(&self.chain)?)?.with_expr(Immediate::new_verdict(verdict));
+        self.branches.push((rule, subtree));
+        Ok(self)
+    }
+
+    /// All the chains of the tree, parents first.
+    pub fn chains(&self) -> Vec<&Chain> {
+        let mut chains = vec![&self.chain];
+        for (_, subtree) in &self.branches {
+            chains.extend(subtree.chains());
+        }
+        chains
+    }
+
+    /// All the jump rules of the tree.
+    pub fn rules(&self) -> Vec<&Rule> {
+        let mut rules = Vec::new();
+        for (rule, subtree) in &self.branches {
+            rules.push(rule);
+            rules.extend(subtree.rules());
+        }
+        rules
+    }
+
+    /// Adds every chain of the tree (including its root) to `batch`, followed by the jump rules,
+    /// as the target of a jump must exist when the rule is added.
+    pub fn add_to_batch(&self, batch: &mut Batch) {
+        for chain in self.chains() {
+            batch.add(chain, MsgType::Add);
+        }
+        for rule in self.rules() {
+            batch.add(rule, MsgType::Add);
+        }
+    }
+}
@@ -0,0 +1,157 @@
+//! Detection of the rules written by iptables-nft, which translates the iptables rules it cannot
+//! express natively into `match` and `target` expressions running the legacy xtables modules.
+//!
+//! Those rules are opaque to this crate (and to nft), and iptables-nft expects to own the tables
+//! holding them: tools managing the ruleset with rustables can use [`find_compat_usage`] (or
+//! [`list_compat_usage`] for the whole ruleset) to warn their users before interleaving their
+//! changes with legacy rules.
+//!
+//! ```ignore
+//! for usage in list_compat_usage()? {
+//!     eprintln!("warning: {}", usage);
+//! }
+//! ```
+
+use std::fmt::{self, Display, Formatter};
+
+use rustables_macros::nfnetlink_struct;
+
+#[cfg(feature = "socket")]
+use crate::error::QueryError;
+use crate::expr::ExpressionVariant;
+use crate::nlmsg::{NfNetlinkDeserializable, NfNetlinkObject};
+use crate::sys::{NFTA_RULE_COMPAT_FLAGS, NFTA_RULE_COMPAT_PROTO};
+use crate::{ProtocolFamily, Rule};
+
+/// The attributes of the `match` and `target` expressions, which share their numbering
+/// (`NFTA_MATCH_*` and `NFTA_TARGET_*` in `linux/netfilter/nf_tables_compat.h`).
+const NFTA_XT_NAME: u16 = 1;
+const NFTA_XT_REV: u16 = 2;
+const NFTA_XT_INFO: u16 = 3;
+
+/// The protocol iptables-nft attaches to the rules whose matches depend on it (e.g. `-p tcp -m
+/// tcp --dport 22`), so that the kernel can check the matches against it.
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+#[nfnetlink_struct(nested = true)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RuleCompat {
+    /// The layer 4 protocol of the rule, e.g. `IPPROTO_TCP`.
+    #[field(NFTA_RULE_COMPAT_PROTO)]
+    proto: u32,
+    /// `NFT_RULE_COMPAT_F_INV` if the protocol is inverted (`! -p tcp`).
+    #[field(NFTA_RULE_COMPAT_FLAGS)]
+    flags: u32,
+}
+
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+#[nfnetlink_struct(nested = true)]
+struct XtExpression {
+    #[field(NFTA_XT_NAME)]
+    name: String,
+    #[field(NFTA_XT_REV)]
+    rev: u32,
+    #[field(NFTA_XT_INFO)]
+    info: Vec<u8>,
+}
+
+/// A construct of iptables-nft found in a rule.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CompatConstruct {
+    /// A xtables match (e.g. `conntrack` for `-m conntrack`), with its name when it could be
+    /// decoded.
+    Match(Option<String>),
+    /// A xtables target (e.g. `REJECT`), with its name when it could be decoded.
+    Target(Option<String>),
+    /// The protocol information of the rule, see [`RuleCompat`].
+    RuleAttribute,
+}
+
+/// The location of an iptables-nft construct in the ruleset.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CompatUsage {
+    pub family: ProtocolFamily,
+    pub table: Option<String>,
+    pub chain: Option<String>,
+    /// The handle of the rule, if it was listed from the kernel.
+    pub handle: Option<u64>,
+    /// The index of the expression in the rule, or `None` for the attributes of the rule itself.
+    pub expression: Option<usize>,
+    pub construct: CompatConstruct,
+}
+
+impl Display for CompatUsage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let unknown = "?".to_string();
+        write!(
+            f,
+            "{} {} {}",
+            self.family,
+            self.table.as_ref().unwrap_or(&unknown),
+            self.chain.as_ref().unwrap_or(&unknown)
+        )?;
+        if let Some(handle) = self.handle {
+            write!(f, " handle {}", handle)?;
+        }
+        let name = |name: &Option<String>| name.clone().unwrap_or(unknown.clone());
+        match &self.construct {
+            CompatConstruct::Match(n) => write!(f, ": xtables match {}", name(n))?,
+            CompatConstruct::Target(n) => write!(f, ": xtables target {}", name(n))?,
+            CompatConstruct::RuleAttribute => f.write_str(": iptables-nft protocol attribute")?,
+        }
+        if let Some(index) = self.expression {
+            write!(f, " (expression {})", index)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the iptables-nft constructs of `rules`, in order.
+pub fn find_compat_usage<'a>(rules: impl IntoIterator<Item = &'a Rule>) -> Vec<CompatUsage> {
+    let mut res = Vec::new();
+    for rule in rules {
+        let usage = |expression, construct| CompatUsage {
+            family: rule.get_family(),
+            table: rule.get_table().cloned(),
+            chain: rule.get_chain().cloned(),
+            handle: rule.get_handle().copied(),
+            expression,
+            construct,
+        };
+        if rule.get_compat().is_some() {
+            res.push(usage(None, CompatConstruct::RuleAttribute));
+        }
+        let exprs = rule.get_expressions().into_iter().flat_map(|e| e.iter());
+        for (index, expr) in exprs.enumerate() {
+            let xt_name = || match expr.get_data() {
+                Some(ExpressionVariant::ExpressionRaw(raw)) => {
+                    XtExpression::deserialize(raw.as_bytes())
+                        .ok()
+                        .and_then(|(xt, _)| xt.name)
+                }
+                _ => None,
+            };
+            match expr.get_name().map(|name| name.as_str()) {
+                Some("match") => res.push(usage(Some(index), CompatConstruct::Match(xt_name()))),
+                Some("target") => res.push(usage(Some(index), CompatConstruct::Target(xt_name()))),
+                _ => {}
+            }
+        }
+    }
+    res
+}
+
+/// Lists the rules of all the tables, and returns their iptables-nft constructs.
+#[cfg(feature = "socket")]
+pub fn list_compat_usage() -> Result<Vec<CompatUsage>, QueryError> {
+    let mut rules = Vec::new();
+    crate::query::list_objects_with_data(
+        libc::NFT_MSG_GETRULE as u16,
+        &|rule: Rule, rules: &mut Vec<Rule>| {
+            rules.push(rule);
+            Ok(())
+        },
+        None,
+        &mut rules,
+    )?;
+    Ok(find_compat_usage(&rules))
+}
@@ -0,0 +1,219 @@
+//! Declarative description of tables, chains and rules.
+//!
+//! The structures in this module can be deserialized (with the `serde` feature) from any
+//! format supported by serde, and then be turned into a [`Batch`] that creates the described
+//! ruleset. Rules are expressed with the same high-level helpers as the ones available on
+//! [`Rule`] (e.g. [`Rule::dport`] or [`Rule::saddr`]).
+//!
+//! ```ignore
+//! let config: Config = serde_json::from_str(r#"{
+//!     "tables": [{
+//!         "name": "filter",
+//!         "family": "inet",
+//!         "chains": [{
+//!             "name": "input",
+//!             "hook": { "class": "in", "priority": "filter" },
+//!             "type": "filter",
+//!             "policy": "drop",
+//!             "rules": [
+//!                 { "established": true, "verdict": "accept" },
+//!                 { "protocol": "tcp", "dport": 22, "verdict": "accept" }
+//!             ]
+//!         }]
+//!     }]
+//! }"#)?;
+//! config.to_batch()?.send()?;
+//! ```
+//!
+//! [`Batch`]: crate::Batch
+//! [`Rule`]: crate::Rule
+
+use std::net::IpAddr;
+
+use ipnetwork::IpNetwork;
+
+use crate::error::BuilderError;
+use crate::expr::{Immediate, Log, LogPrefix, VerdictKind};
+use crate::nlmsg::NfNetlinkObject;
+use crate::{
+    Batch, Chain, ChainPolicy, ChainType, Hook, HookClass, MsgType, Protocol, ProtocolFamily, Rule,
+    SymbolicPriority, Table,
+};
+
+/// A full ruleset, made of several tables.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Config {
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub tables: Vec<TableConfig>,
+}
+
+impl Config {
+    /// Creates a new [`Batch`] and adds all the objects described in this configuration to it.
+    ///
+    /// [`Batch`]: crate::Batch
+    pub fn to_batch(&self) -> Result<Batch, BuilderError> {
+        let mut batch = Batch::new();
+        for table in &self.tables {
+            table.add_to_batch(&mut batch)?;
+        }
+        Ok(batch)
+    }
+}
+
+/// Description of a [`Table`] and of the chains it contains.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TableConfig {
+    pub name: String,
+    pub family: ProtocolFamily,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub chains: Vec<ChainConfig>,
+}
+
+impl TableConfig {
+    /// Adds the table, then its chains, then the rules of each chain to `batch`.
+    pub fn add_to_batch(&self, batch: &mut Batch) -> Result<(), BuilderError> {
+        let table = Table::new(self.family).with_name(&self.name);
+        batch.add(&table, MsgType::Add);
+
+        for chain in &self.chains {
+            chain.add_to_batch(&table, batch)?;
+        }
+        Ok(())
+    }
+}
+
+/// Hook of a base chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HookConfig {
+    pub class: HookClass,
+    /// Either a number or a priority in nft notation, e.g. `"mangle + 10"`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub priority: SymbolicPriority,
+}
+
+/// Description of a [`Chain`] and of the rules it contains.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChainConfig {
+    pub name: String,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub hook: Option<HookConfig>,
+    #[cfg_attr(feature = "serde", serde(default, rename = "type"))]
+    pub chain_type: Option<ChainType>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub policy: Option<ChainPolicy>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub rules: Vec<RuleConfig>,
+}
+
+impl ChainConfig {
+    /// Adds the chain and its rules to `batch`.
+    pub fn add_to_batch(&self, table: &Table, batch: &mut Batch) -> Result<(), BuilderError> {
+        let mut chain = Chain::new(table).with_name(&self.name);
+        if let Some(hook) = self.hook {
+            chain.set_hook(
+                Hook::new(hook.class, 0)
+                    .with_symbolic_priority(hook.priority, table.get_family())?,
+            );
+        }
+        if let Some(chain_type) = self.chain_type {
+            chain.set_type(chain_type);
+        }
+        if let Some(policy) = self.policy {
+            chain.set_policy(policy);
+        }
+        chain.validate()?;
+        batch.add(&chain, MsgType::Add);
+
+        for rule in &self.rules {
+            batch.add(&rule.to_rule(&chain)?, MsgType::Add);
+        }
+        Ok(())
+    }
+}
+
+/// Description of a [`Rule`]. Every match that is set must be satisfied for the rule to apply,
+/// in which case the (optional) log statement, masquerading and verdict are applied, in that
+/// order.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct RuleConfig {
+    /// Matches packets in an already established connection.
+    pub established: bool,
+    /// Matches ICMP packets.
+    pub icmp: bool,
+    /// Matches packets on `protocol`. Required when matching on ports.
+    pub protocol: Option<Protocol>,
+    pub sport: Option<u16>,
+    pub dport: Option<u16>,
+    pub saddr: Option<IpAddr>,
+    pub daddr: Option<IpAddr>,
+    pub snetwork: Option<IpNetwork>,
+    pub dnetwork: Option<IpNetwork>,
+    /// Input interface name.
+    pub iiface: Option<String>,
+    /// Output interface name.
+    pub oiface: Option<String>,
+    /// Logs the matching packets with the given prefix.
+    pub log_prefix: Option<LogPrefix>,
+    pub masquerade: bool,
+    pub verdict: Option<VerdictKind>,
+}
+
+impl RuleConfig {
+    /// Builds the rule described by this configuration in `chain`.
+    pub fn to_rule(&self, chain: &Chain) -> Result<Rule, BuilderError> {
+        let mut rule = Rule::new(chain)?;
+        if self.established {
+            rule = rule.established()?;
+        }
+        if self.icmp {
+            rule = rule.icmp();
+        }
+        if let Some(iface) = &self.iiface {
+            rule = rule.iiface(iface)?;
+        }
+        if let Some(iface) = &self.oiface {
+            rule = rule.oiface(iface)?;
+        }
+        if let Some(ip) = self.saddr {
+            rule = rule.saddr(ip);
+        }
+        if let Some(ip) = self.daddr {
+            rule = rule.daddr(ip);
+        }
+        if let Some(net) = self.snetwork {
+            rule = rule.snetwork(net)?;
+        }
+        if let Some(net) = self.dnetwork {
+            rule = rule.dnetwork(net)?;
+        }
+        match (self.protocol, self.sport, self.dport) {
+            (Some(protocol), None, None) => rule = rule.protocol(protocol),
+            (Some(protocol), sport, dport) => {
+                if let Some(port) = sport {
+                    rule = rule.sport(port, protocol);
+                }
+                if let Some(port) = dport {
+                    rule = rule.dport(port, protocol);
+                }
+            }
+            (None, None, None) => {}
+            (None, _, _) => return Err(BuilderError::MissingPortProtocol),
+        }
+        if let Some(prefix) = &self.log_prefix {
+            rule = rule.with_expr(Log::new(None, Some(prefix.clone()))?);
+        }
+        if self.masquerade {
+            rule = rule.masquerade();
+        }
+        if let Some(verdict) = &self.verdict {
+            rule = rule.with_expr(Immediate::new_verdict(verdict.clone()));
+        }
+        Ok(rule)
+    }
+}
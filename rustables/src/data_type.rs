@@ -1,4 +1,11 @@
+use std::fmt::{self, Display, Formatter};
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+
+use rustables_macros::nfnetlink_enum;
+
+use crate::error::{BuilderError, DecodeError};
+use crate::ProtocolFamily;
 
 pub trait DataType {
     const TYPE: u32;
@@ -7,8 +14,207 @@ pub trait DataType {
     fn data(&self) -> Vec<u8>;
 }
 
+// Identifiers of the data types of nft, as found in `enum datatypes` in nftables'
+// `include/datatype.h`. They are not part of the kernel ABI: the kernel stores them opaquely in
+// NFTA_SET_KEY_TYPE and NFTA_SET_DATA_TYPE, and only nft interprets them.
+const TYPE_VERDICT: u32 = 1;
+const TYPE_NFPROTO: u32 = 2;
+const TYPE_BITMASK: u32 = 3;
+const TYPE_INTEGER: u32 = 4;
+const TYPE_STRING: u32 = 5;
+const TYPE_LLADDR: u32 = 6;
+const TYPE_IPADDR: u32 = 7;
+const TYPE_IP6ADDR: u32 = 8;
+const TYPE_ETHERADDR: u32 = 9;
+const TYPE_ETHERTYPE: u32 = 10;
+const TYPE_ARPOP: u32 = 11;
+const TYPE_INET_PROTOCOL: u32 = 12;
+const TYPE_INET_SERVICE: u32 = 13;
+const TYPE_ICMP_TYPE: u32 = 14;
+const TYPE_TCP_FLAG: u32 = 15;
+const TYPE_DCCP_PKTTYPE: u32 = 16;
+const TYPE_MH_TYPE: u32 = 17;
+const TYPE_TIME: u32 = 18;
+const TYPE_MARK: u32 = 19;
+const TYPE_IFINDEX: u32 = 20;
+const TYPE_ARPHRD: u32 = 21;
+const TYPE_REALM: u32 = 22;
+const TYPE_CLASSID: u32 = 23;
+const TYPE_UID: u32 = 24;
+const TYPE_GID: u32 = 25;
+const TYPE_CT_STATE: u32 = 26;
+const TYPE_CT_DIR: u32 = 27;
+const TYPE_CT_STATUS: u32 = 28;
+const TYPE_ICMP6_TYPE: u32 = 29;
+const TYPE_CT_LABEL: u32 = 30;
+const TYPE_PKTTYPE: u32 = 31;
+const TYPE_ICMP_CODE: u32 = 32;
+const TYPE_ICMPV6_CODE: u32 = 33;
+const TYPE_ICMPX_CODE: u32 = 34;
+const TYPE_DEVGROUP: u32 = 35;
+const TYPE_DSCP: u32 = 36;
+const TYPE_ECN: u32 = 37;
+const TYPE_FIB_ADDR: u32 = 38;
+const TYPE_BOOLEAN: u32 = 39;
+const TYPE_CT_EVENTBIT: u32 = 40;
+const TYPE_IFNAME: u32 = 41;
+const TYPE_IGMP_TYPE: u32 = 42;
+const TYPE_TIME_DATE: u32 = 43;
+const TYPE_TIME_HOUR: u32 = 44;
+const TYPE_TIME_DAY: u32 = 45;
+const TYPE_CGROUPV2: u32 = 46;
+
+/// Number of bits used by nft to store each member of a concatenated data type.
+const TYPE_BITS: u32 = 6;
+
+/// The type of the keys (or data) of a set, as understood by nft. Creating sets with the proper
+/// type allows `nft list ruleset` to display their elements as addresses, ports, ... instead of
+/// raw integers.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[nfnetlink_enum(u32)]
+pub enum DataTypeId {
+    Verdict = TYPE_VERDICT,
+    NfProto = TYPE_NFPROTO,
+    Bitmask = TYPE_BITMASK,
+    Integer = TYPE_INTEGER,
+    String = TYPE_STRING,
+    LlAddr = TYPE_LLADDR,
+    IpAddr = TYPE_IPADDR,
+    Ip6Addr = TYPE_IP6ADDR,
+    EtherAddr = TYPE_ETHERADDR,
+    EtherType = TYPE_ETHERTYPE,
+    ArpOp = TYPE_ARPOP,
+    InetProtocol = TYPE_INET_PROTOCOL,
+    InetService = TYPE_INET_SERVICE,
+    IcmpType = TYPE_ICMP_TYPE,
+    TcpFlag = TYPE_TCP_FLAG,
+    DccpPktType = TYPE_DCCP_PKTTYPE,
+    MhType = TYPE_MH_TYPE,
+    Time = TYPE_TIME,
+    Mark = TYPE_MARK,
+    IfIndex = TYPE_IFINDEX,
+    ArpHrd = TYPE_ARPHRD,
+    Realm = TYPE_REALM,
+    ClassId = TYPE_CLASSID,
+    Uid = TYPE_UID,
+    Gid = TYPE_GID,
+    CtState = TYPE_CT_STATE,
+    CtDir = TYPE_CT_DIR,
+    CtStatus = TYPE_CT_STATUS,
+    Icmp6Type = TYPE_ICMP6_TYPE,
+    CtLabel = TYPE_CT_LABEL,
+    PktType = TYPE_PKTTYPE,
+    IcmpCode = TYPE_ICMP_CODE,
+    Icmpv6Code = TYPE_ICMPV6_CODE,
+    IcmpxCode = TYPE_ICMPX_CODE,
+    DevGroup = TYPE_DEVGROUP,
+    Dscp = TYPE_DSCP,
+    Ecn = TYPE_ECN,
+    FibAddr = TYPE_FIB_ADDR,
+    Boolean = TYPE_BOOLEAN,
+    CtEventBit = TYPE_CT_EVENTBIT,
+    IfName = TYPE_IFNAME,
+    IgmpType = TYPE_IGMP_TYPE,
+    TimeDate = TYPE_TIME_DATE,
+    TimeHour = TYPE_TIME_HOUR,
+    TimeDay = TYPE_TIME_DAY,
+    Cgroupv2 = TYPE_CGROUPV2,
+}
+
+impl DataTypeId {
+    /// The name of the type in the output of nft.
+    pub fn name(&self) -> &'static str {
+        match self {
+            DataTypeId::Verdict => "verdict",
+            DataTypeId::NfProto => "nf_proto",
+            DataTypeId::Bitmask => "bitmask",
+            DataTypeId::Integer => "integer",
+            DataTypeId::String => "string",
+            DataTypeId::LlAddr => "ll_addr",
+            DataTypeId::IpAddr => "ipv4_addr",
+            DataTypeId::Ip6Addr => "ipv6_addr",
+            DataTypeId::EtherAddr => "ether_addr",
+            DataTypeId::EtherType => "ether_type",
+            DataTypeId::ArpOp => "arp_op",
+            DataTypeId::InetProtocol => "inet_proto",
+            DataTypeId::InetService => "inet_service",
+            DataTypeId::IcmpType => "icmp_type",
+            DataTypeId::TcpFlag => "tcp_flag",
+            DataTypeId::DccpPktType => "dccp_pkttype",
+            DataTypeId::MhType => "mh_type",
+            DataTypeId::Time => "time",
+            DataTypeId::Mark => "mark",
+            DataTypeId::IfIndex => "iface_index",
+            DataTypeId::ArpHrd => "iface_type",
+            DataTypeId::Realm => "realm",
+            DataTypeId::ClassId => "classid",
+            DataTypeId::Uid => "uid",
+            DataTypeId::Gid => "gid",
+            DataTypeId::CtState => "ct_state",
+            DataTypeId::CtDir => "ct_dir",
+            DataTypeId::CtStatus => "ct_status",
+            DataTypeId::Icmp6Type => "icmpv6_type",
+            DataTypeId::CtLabel => "ct_label",
+            DataTypeId::PktType => "pkt_type",
+            DataTypeId::IcmpCode => "icmp_code",
+            DataTypeId::Icmpv6Code => "icmpv6_code",
+            DataTypeId::IcmpxCode => "icmpx_code",
+            DataTypeId::DevGroup => "devgroup",
+            DataTypeId::Dscp => "dscp",
+            DataTypeId::Ecn => "ecn",
+            DataTypeId::FibAddr => "fib_addrtype",
+            DataTypeId::Boolean => "boolean",
+            DataTypeId::CtEventBit => "ct_event",
+            DataTypeId::IfName => "ifname",
+            DataTypeId::IgmpType => "igmp_type",
+            DataTypeId::TimeDate => "time",
+            DataTypeId::TimeHour => "hour",
+            DataTypeId::TimeDay => "day",
+            DataTypeId::Cgroupv2 => "cgroupsv2",
+        }
+    }
+
+    /// The type named `name` in the output of nft, e.g. `ipv4_addr`.
+    pub fn from_name(name: &str) -> Option<DataTypeId> {
+        (0..=TYPE_CGROUPV2)
+            .filter_map(|id| DataTypeId::try_from(id).ok())
+            .find(|ty| ty.name() == name)
+    }
+
+    /// Combines several types into the type of a concatenation (e.g.
+    /// `ipv4_addr . inet_service`), in the same way as nft.
+    pub fn concat(types: &[DataTypeId]) -> u32 {
+        types
+            .iter()
+            .fold(0, |acc, ty| (acc << TYPE_BITS) | *ty as u32)
+    }
+
+    /// Splits a (possibly concatenated) type in its members. Fails if one of the members is not
+    /// known to this library.
+    pub fn split(mut value: u32) -> Result<Vec<DataTypeId>, DecodeError> {
+        let mask = (1 << TYPE_BITS) - 1;
+        let mut types = Vec::new();
+        loop {
+            types.push(DataTypeId::try_from(value & mask)?);
+            value >>= TYPE_BITS;
+            if value == 0 {
+                break;
+            }
+        }
+        types.reverse();
+        Ok(types)
+    }
+}
+
+impl Display for DataTypeId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
 impl DataType for Ipv4Addr {
-    const TYPE: u32 = 7;
+    const TYPE: u32 = DataTypeId::IpAddr as u32;
     const LEN: u32 = 4;
 
     fn data(&self) -> Vec<u8> {
@@ -17,7 +223,7 @@ impl DataType for Ipv4Addr {
 }
 
 impl DataType for Ipv6Addr {
-    const TYPE: u32 = 8;
+    const TYPE: u32 = DataTypeId::Ip6Addr as u32;
     const LEN: u32 = 16;
 
     fn data(&self) -> Vec<u8> {
@@ -26,7 +232,7 @@ impl DataType for Ipv6Addr {
 }
 
 impl<const N: usize> DataType for [u8; N] {
-    const TYPE: u32 = 5;
+    const TYPE: u32 = DataTypeId::String as u32;
     const LEN: u32 = N as u32;
 
     fn data(&self) -> Vec<u8> {
@@ -34,9 +240,194 @@ impl<const N: usize> DataType for [u8; N] {
     }
 }
 
-pub fn ip_to_vec(ip: IpAddr) -> Vec<u8> {
-    match ip {
-        IpAddr::V4(x) => x.octets().to_vec(),
-        IpAddr::V6(x) => x.octets().to_vec(),
+/// A TCP or UDP port, used as an operand in an expression or as a set key.
+///
+/// Ports are built from their number in host byte order, and are always written in network byte
+/// order, as they appear in the packet headers.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Port(u16);
+
+impl Port {
+    pub fn new(port: u16) -> Self {
+        Port(port)
+    }
+
+    /// The port number, in host byte order.
+    pub fn number(&self) -> u16 {
+        self.0
+    }
+
+    /// The port in network byte order.
+    pub fn to_bytes(&self) -> [u8; 2] {
+        self.0.to_be_bytes()
+    }
+}
+
+impl From<u16> for Port {
+    fn from(port: u16) -> Self {
+        Port(port)
+    }
+}
+
+impl From<Port> for Vec<u8> {
+    fn from(port: Port) -> Self {
+        port.to_bytes().to_vec()
+    }
+}
+
+impl DataType for Port {
+    const TYPE: u32 = DataTypeId::InetService as u32;
+    const LEN: u32 = 2;
+
+    fn data(&self) -> Vec<u8> {
+        self.to_bytes().to_vec()
+    }
+}
+
+/// A traffic control handle, identifying a qdisc or a class of tc, as a `major:minor` pair of
+/// 16 bits numbers. The packets whose priority (`meta priority`) is the handle of a class are
+/// classified into that class by the classful qdiscs (e.g. HTB).
+///
+/// Like in tc and nft, the numbers are written in hexadecimal: `1:20` is the handle `0x10020`.
+/// The special handles `none` (0) and `root` (`0xffffffff`) are written by name.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct TcHandle(u32);
+
+impl TcHandle {
+    /// The unspecified handle, `TC_H_UNSPEC`.
+    pub const NONE: TcHandle = TcHandle(0);
+    /// The handle of the root qdisc of an interface, `TC_H_ROOT`.
+    pub const ROOT: TcHandle = TcHandle(u32::MAX);
+
+    pub fn new(major: u16, minor: u16) -> Self {
+        TcHandle((major as u32) << 16 | minor as u32)
+    }
+
+    pub fn major(&self) -> u16 {
+        (self.0 >> 16) as u16
+    }
+
+    pub fn minor(&self) -> u16 {
+        self.0 as u16
     }
+
+    /// The handle as a single number, as stored in the priority of the packets.
+    pub fn value(&self) -> u32 {
+        self.0
+    }
+}
+
+impl From<u32> for TcHandle {
+    fn from(handle: u32) -> Self {
+        TcHandle(handle)
+    }
+}
+
+impl From<TcHandle> for u32 {
+    fn from(handle: TcHandle) -> Self {
+        handle.0
+    }
+}
+
+impl Display for TcHandle {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match *self {
+            TcHandle::NONE => f.write_str("none"),
+            TcHandle::ROOT => f.write_str("root"),
+            _ => write!(f, "{:x}:{:x}", self.major(), self.minor()),
+        }
+    }
+}
+
+impl FromStr for TcHandle {
+    type Err = BuilderError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || BuilderError::InvalidTcHandle(s.to_string());
+        match s.trim() {
+            "none" => Ok(TcHandle::NONE),
+            "root" => Ok(TcHandle::ROOT),
+            handle => {
+                let (major, minor) = handle.split_once(':').ok_or_else(invalid)?;
+                // tc reads `1:` as the qdisc `1:0`
+                let parse = |n: &str| match n {
+                    "" => Ok(0),
+                    n if n.starts_with('+') => Err(invalid()),
+                    n => u16::from_str_radix(n, 16).map_err(|_| invalid()),
+                };
+                Ok(TcHandle::new(parse(major)?, parse(minor)?))
+            }
+        }
+    }
+}
+
+impl DataType for TcHandle {
+    const TYPE: u32 = DataTypeId::ClassId as u32;
+    const LEN: u32 = 4;
+
+    fn data(&self) -> Vec<u8> {
+        // the priority of the packets is stored in host byte order
+        self.0.to_ne_bytes().to_vec()
+    }
+}
+
+/// An IPv4 or IPv6 address used as an operand in an expression or as a set key. Its network
+/// representation is 4 bytes long for IPv4 addresses, and 16 bytes long for IPv6 addresses.
+///
+/// All the builders that accept an address take an `impl Into<IpOperand>`, so any of
+/// [`IpAddr`], [`Ipv4Addr`] or [`Ipv6Addr`] can be supplied.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct IpOperand(IpAddr);
+
+impl IpOperand {
+    /// The address wrapped by this operand.
+    pub fn addr(&self) -> IpAddr {
+        self.0
+    }
+
+    /// The protocol family of the address, either [`ProtocolFamily::Ipv4`] or
+    /// [`ProtocolFamily::Ipv6`].
+    pub fn family(&self) -> ProtocolFamily {
+        match self.0 {
+            IpAddr::V4(_) => ProtocolFamily::Ipv4,
+            IpAddr::V6(_) => ProtocolFamily::Ipv6,
+        }
+    }
+
+    /// The address in network byte order.
+    pub fn to_vec(&self) -> Vec<u8> {
+        match self.0 {
+            IpAddr::V4(x) => x.octets().to_vec(),
+            IpAddr::V6(x) => x.octets().to_vec(),
+        }
+    }
+}
+
+impl From<IpAddr> for IpOperand {
+    fn from(ip: IpAddr) -> Self {
+        IpOperand(ip)
+    }
+}
+
+impl From<Ipv4Addr> for IpOperand {
+    fn from(ip: Ipv4Addr) -> Self {
+        IpOperand(IpAddr::V4(ip))
+    }
+}
+
+impl From<Ipv6Addr> for IpOperand {
+    fn from(ip: Ipv6Addr) -> Self {
+        IpOperand(IpAddr::V6(ip))
+    }
+}
+
+impl From<IpOperand> for Vec<u8> {
+    fn from(ip: IpOperand) -> Self {
+        ip.to_vec()
+    }
+}
+
+#[deprecated = "Use `IpOperand::to_vec` instead, or pass the address directly to the builders"]
+pub fn ip_to_vec(ip: IpAddr) -> Vec<u8> {
+    IpOperand::from(ip).to_vec()
 }
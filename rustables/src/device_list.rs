@@ -0,0 +1,129 @@
+use std::convert::TryFrom;
+
+use crate::error::{BuilderError, DecodeError};
+use crate::nlmsg::{NfNetlinkAttribute, NfNetlinkDeserializable};
+use crate::parser_impls::NfNetlinkList;
+
+/// The names of the network devices a hook is bound to, as used by netdev chains (see
+/// [`Hook::ingress`]) and flowtables.
+///
+/// The kernel reports a device list with an invalid or duplicated name as a generic `EINVAL` (or
+/// `EEXIST`) on the whole chain, so the names are checked when they are added to the list.
+///
+/// [`Hook::ingress`]: crate::Hook::ingress
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(try_from = "Vec<String>", into = "Vec<String>")
+)]
+pub struct DeviceList {
+    /// `NFTA_DEVICE_NAME` has the same value as `NFTA_LIST_ELEM`, so the devices are encoded as a
+    /// regular list.
+    devices: NfNetlinkList<String>,
+}
+
+impl DeviceList {
+    /// Creates a list holding the devices `names`, which must be valid and distinct.
+    pub fn new<S: Into<String>>(names: impl IntoIterator<Item = S>) -> Result<Self, BuilderError> {
+        let mut list = DeviceList::default();
+        for name in names {
+            list.add(name)?;
+        }
+        Ok(list)
+    }
+
+    /// Creates a list without checking the names, for the constructors that are not fallible.
+    /// Such lists are checked later with [`DeviceList::validate`].
+    pub(crate) fn new_unchecked<S: Into<String>>(names: impl IntoIterator<Item = S>) -> Self {
+        let mut devices = NfNetlinkList::default();
+        for name in names {
+            devices.add_value(name.into());
+        }
+        DeviceList { devices }
+    }
+
+    /// Appends the device `name` to the list.
+    pub fn add(&mut self, name: impl Into<String>) -> Result<(), BuilderError> {
+        let name = name.into();
+        check_device_name(&name)?;
+        if self.iter().any(|device| device == name) {
+            return Err(BuilderError::DuplicateDevice(name));
+        }
+        self.devices.add_value(name);
+        Ok(())
+    }
+
+    pub fn with_device(mut self, name: impl Into<String>) -> Result<Self, BuilderError> {
+        self.add(name)?;
+        Ok(self)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.devices.iter().map(|device| device.as_str())
+    }
+
+    pub fn len(&self) -> usize {
+        self.devices.iter().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.devices.iter().next().is_none()
+    }
+
+    /// Checks that the names of the devices are valid and distinct.
+    pub fn validate(&self) -> Result<(), BuilderError> {
+        for (i, name) in self.iter().enumerate() {
+            check_device_name(name)?;
+            if self.iter().take(i).any(|device| device == name) {
+                return Err(BuilderError::DuplicateDevice(name.to_string()));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn check_device_name(name: &str) -> Result<(), BuilderError> {
+    if name.is_empty() || name.contains('\0') {
+        return Err(BuilderError::InvalidDeviceName(name.to_string()));
+    }
+    if name.len() >= libc::IFNAMSIZ {
+        return Err(BuilderError::InterfaceNameTooLong);
+    }
+    Ok(())
+}
+
+impl TryFrom<Vec<String>> for DeviceList {
+    type Error = BuilderError;
+
+    fn try_from(names: Vec<String>) -> Result<Self, Self::Error> {
+        DeviceList::new(names)
+    }
+}
+
+impl From<DeviceList> for Vec<String> {
+    fn from(list: DeviceList) -> Self {
+        list.iter().map(String::from).collect()
+    }
+}
+
+impl NfNetlinkAttribute for DeviceList {
+    fn is_nested(&self) -> bool {
+        true
+    }
+
+    fn get_size(&self) -> usize {
+        self.devices.get_size()
+    }
+
+    fn write_payload(&self, addr: &mut [u8]) {
+        self.devices.write_payload(addr);
+    }
+}
+
+impl NfNetlinkDeserializable for DeviceList {
+    fn deserialize(buf: &[u8]) -> Result<(Self, &[u8]), DecodeError> {
+        let (devices, remaining) = NfNetlinkList::deserialize(buf)?;
+        Ok((DeviceList { devices }, remaining))
+    }
+}
@@ -1,9 +1,15 @@
+use std::fmt;
 use std::string::FromUtf8Error;
 
+#[cfg(feature = "socket")]
 use nix::errno::Errno;
 use thiserror::Error;
 
+use crate::set::SetFlags;
 use crate::sys::nlmsgerr;
+#[cfg(feature = "socket")]
+use crate::BatchObject;
+use crate::MsgType;
 
 #[derive(Error, Debug)]
 pub enum DecodeError {
@@ -22,6 +28,9 @@ pub enum DecodeError {
     #[error("Invalid version, expected NFNETLINK_V0")]
     InvalidVersion(u8),
 
+    #[error("Invalid resource id in a batch message, expected NFNL_SUBSYS_NFTABLES")]
+    InvalidResourceId(u16),
+
     #[error("Invalid port ID")]
     InvalidPortId(u32),
 
@@ -85,6 +94,9 @@ pub enum DecodeError {
     #[error("Unsupported value for an UDP header field")]
     UnknownUDPHeaderField(u32, u32),
 
+    #[error("Unsupported value for an ICMP header field")]
+    UnknownICMPHeaderField(u32, u32),
+
     #[error("Unsupported value for an ICMPv6 header field")]
     UnknownICMPv6HeaderField(u32, u32),
 
@@ -103,14 +115,47 @@ pub enum DecodeError {
     #[error("Unsupported attribute type")]
     UnsupportedAttributeType(u16),
 
+    #[error("Unknown attribute type {0} in {1}")]
+    UnknownAttribute(u16, &'static str),
+
+    #[error("Unknown expression {0:?}")]
+    UnknownExpression(String),
+
     #[error("Unexpected message type")]
     UnexpectedType(u16),
 
+    #[error("The kernel reported an error: {0}")]
+    ErrorMessage(NetlinkErrorReport),
+
     #[error("The decoded String is not UTF8 compliant")]
     StringDecodeFailure(#[from] FromUtf8Error),
 
-    #[error("Invalid value for a protocol family")]
-    UnknownProtocolFamily(i32),
+    #[error("Invalid type for a stateful object")]
+    UnknownObjectType(u32),
+
+    #[error("Unknown nft data type")]
+    UnknownDataTypeId(u32),
+
+    #[error("Unknown key for a Rt expression")]
+    UnknownRtKey(u32),
+
+    #[error("Unknown key for a Socket expression")]
+    UnknownSocketKey(u32),
+
+    #[error("Unknown type for a Hash expression")]
+    UnknownHashType(u32),
+
+    #[error("Unknown type for a Numgen expression")]
+    UnknownNumgenType(u32),
+
+    #[error("Unknown operation for a Byteorder expression")]
+    UnknownByteorderOp(u32),
+
+    #[error("Unknown operation for an Exthdr expression")]
+    UnknownExthdrOp(u32),
+
+    #[error("Unknown byte order in the userdata of a set")]
+    UnknownSetByteorder(u32),
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -124,16 +169,132 @@ pub enum BuilderError {
     #[error("Missing information in the chain to create a rule")]
     MissingChainInformationError,
 
+    #[error("A policy can only be set on a base chain, i.e. a chain with a hook")]
+    ChainPolicyWithoutHook,
+
+    #[error("The hook {0} is not available in the family of the chain")]
+    UnsupportedHookForFamily(u32),
+
+    #[error("The type of the chain is not available in its family")]
+    UnsupportedChainTypeForFamily,
+
+    #[error("Invalid chain priority {0:?}")]
+    InvalidPriority(String),
+
+    #[error("The priority {0} is not available in the family and hook of the chain")]
+    UnsupportedPriority(String),
+
+    #[error("Invalid traffic control handle {0:?}, expected major:minor in hexadecimal")]
+    InvalidTcHandle(String),
+
     #[error("Missing name for the set")]
     MissingSetName,
 
+    #[error("The set flags {0:?} cannot be combined")]
+    IncompatibleSetFlags(SetFlags),
+
+    #[error("The set is not a map holding packet marks")]
+    InvalidMarkMap,
+
+    #[error("The set is not a map of stateful objects")]
+    NotAnObjectMap,
+
+    #[error("The set is not a map of verdicts")]
+    NotAVerdictMap,
+
+    #[error("Cannot match against an empty list of values")]
+    EmptyValueList,
+
+    #[error("The port range is empty")]
+    EmptyPortRange,
+
+    #[error("The rule does not have a handle")]
+    MissingRuleHandle,
+
+    #[error("The operation {0:?} on a {1} cannot be rolled back")]
+    IrreversibleOperation(MsgType, &'static str),
+
+    #[error("The kernel does not support the operation {0:?} on a {1}")]
+    UnsupportedOperation(MsgType, &'static str),
+
+    #[error("The rule was neither added to the batch nor listed from the kernel")]
+    UnknownRulePosition,
+
+    #[error("The chain was not added by the batch")]
+    ChainNotInBatch,
+
+    #[error("Both batches designate rules or chains by their id in the batch")]
+    ConflictingBatchIds,
+
+    #[error("The rules belong to different chains")]
+    RuleChainMismatch,
+
+    #[error("The rule matches the network header, whose layout depends on the family of the rule")]
+    FamilyDependentRule,
+
+    #[error("Invalid expression {0} in the rule: {1}")]
+    InvalidRuleExpression(usize, &'static str),
+
+    #[error("The attribute {0} is not supported by the kernel headers this crate was built with")]
+    UnsupportedOnThisBuild(&'static str),
+
+    #[error("Missing mandatory attributes: {}", .0.join(", "))]
+    MissingAttributes(Vec<&'static str>),
+
     #[error("The interface name is too long to be written")]
     InterfaceNameTooLong,
 
     #[error("The log prefix string is more than 127 characters long")]
     TooLongLogPrefix,
+
+    #[error("The log prefix contains a NUL byte")]
+    NulInLogPrefix,
+
+    #[error("The value of the set userdata TLV {0} is longer than 255 bytes")]
+    TooLongUserdataValue(u8),
+
+    #[error("NFLOG group numbers are 16 bits long")]
+    InvalidLogGroup,
+
+    #[error("NFQUEUE queue numbers are 16 bits long")]
+    InvalidQueueNumber,
+
+    #[error("A port can only be matched when the protocol is specified")]
+    MissingPortProtocol,
+
+    #[error("A flowtable must be bound to at least one device")]
+    MissingFlowtableDevices,
+
+    #[error("A base chain of a netdev table must be bound to at least one device")]
+    MissingChainDevices,
+
+    #[error("Only the chains of netdev tables, and the ingress chains of inet tables, can be bound to devices")]
+    UnexpectedChainDevices,
+
+    #[error("Invalid device name {0:?}")]
+    InvalidDeviceName(String),
+
+    #[error("The device {0} appears several times in the device list")]
+    DuplicateDevice(String),
+
+    #[error("The chain {0} appears several times in the chain tree")]
+    DuplicateChainName(String),
+
+    #[error("The chains of a chain tree must all belong to the same table")]
+    ChainTreeTableMismatch,
+
+    #[error("The chain belongs to another table")]
+    ChainTableMismatch,
+}
+
+// allows the builders accepting `TryInto` arguments to be passed already built values
+impl From<std::convert::Infallible> for BuilderError {
+    fn from(e: std::convert::Infallible) -> Self {
+        match e {}
+    }
 }
 
+#[cfg(feature = "socket")]
 #[derive(thiserror::Error, Debug)]
 pub enum QueryError {
     #[error("Unable to open netlink socket to netfilter")]
@@ -151,8 +312,14 @@ pub enum QueryError {
     #[error("Error while building netlink objects in Rust")]
     BuilderError(#[from] BuilderError),
 
-    #[error("Error received from the kernel")]
-    NetlinkError(nlmsgerr),
+    #[error("Error received from the kernel: {0}")]
+    NetlinkError(NetlinkErrorReport),
+
+    #[error("The kernel refused the {object}: {report}")]
+    BatchObjectRefused {
+        object: BatchObject,
+        report: NetlinkErrorReport,
+    },
 
     #[error("Couldn't allocate a netlink object, out of memory ?")]
     NetlinkAllocationFailed,
@@ -174,4 +341,174 @@ pub enum QueryError {
 
     #[error("Couldn't bind the socket")]
     BindFailed,
+
+    #[error("Error while waiting for netlink messages")]
+    PollFailed(#[source] Errno),
+
+    #[error("Couldn't create or signal the shutdown handle of a monitor")]
+    MonitorShutdownError(#[source] Errno),
+
+    #[error("The kernel did not return the requested object")]
+    MissingObject,
+
+    #[error("The query was cancelled")]
+    Cancelled,
+
+    #[error("Transaction {index} of the sequence was refused, the previous ones were committed")]
+    TransactionSequenceFailed {
+        index: usize,
+        #[source]
+        source: Box<QueryError>,
+    },
+}
+
+#[cfg(feature = "socket")]
+impl QueryError {
+    /// The error returned by the kernel, whether it was attributed to an object of a batch or
+    /// not, e.g. to check its error code.
+    pub fn netlink_report(&self) -> Option<&NetlinkErrorReport> {
+        match self {
+            QueryError::NetlinkError(report) | QueryError::BatchObjectRefused { report, .. } => {
+                Some(report)
+            }
+            QueryError::TransactionSequenceFailed { source, .. } => source.netlink_report(),
+            _ => None,
+        }
+    }
+}
+
+/// An error while converting a ruleset from or to the JSON format of libnftables, see
+/// [`crate::json`].
+#[cfg(feature = "json")]
+#[derive(thiserror::Error, Debug)]
+pub enum JsonError {
+    #[error("Invalid JSON document")]
+    Syntax(#[from] serde_json::Error),
+
+    #[error("The JSON object {object:?} has no property {property:?}")]
+    MissingProperty {
+        object: &'static str,
+        property: &'static str,
+    },
+
+    #[error("Invalid value {value} for the property {property:?}")]
+    InvalidValue {
+        property: &'static str,
+        value: String,
+    },
+
+    #[error("Not supported by this crate: {0}")]
+    Unsupported(String),
+
+    #[error("The expression cannot be written in JSON: {0}")]
+    UntranslatedExpression(String),
+
+    #[error("Error while building the objects of the ruleset")]
+    BuilderError(#[from] BuilderError),
+}
+
+/// An error while translating the rules saved by `iptables-save`, see [`crate::iptables`].
+#[cfg(feature = "iptables")]
+#[derive(thiserror::Error, Debug)]
+pub enum IptablesError {
+    #[error("Line {line}: {error}")]
+    AtLine {
+        line: usize,
+        error: Box<IptablesError>,
+    },
+
+    #[error("Unexpected line {0:?}")]
+    UnexpectedLine(String),
+
+    #[error("The line is not in a table section, started by `*<table>`")]
+    OutsideTable,
+
+    #[error("The table {0:?} is not terminated by COMMIT")]
+    MissingCommit(String),
+
+    #[error("The chain {0:?} is not declared")]
+    UnknownChain(String),
+
+    #[error("The option {0} requires a value")]
+    MissingValue(String),
+
+    #[error("Invalid value {value:?} for the option {option}")]
+    InvalidValue { option: String, value: String },
+
+    #[error("Unterminated quoted argument")]
+    UnterminatedQuote,
+
+    #[error("Not supported by this crate: {0}")]
+    Unsupported(String),
+
+    #[error("Error while building the objects of the ruleset")]
+    BuilderError(#[from] BuilderError),
+}
+
+/// An error returned by the kernel in response to one of our messages.
+///
+/// Besides the error code, the kernel echoes the header of the offending message. When extended
+/// ACKs are supported (Linux 4.12 and later), it may also provide a textual description of the
+/// error and the offset of the attribute that caused it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetlinkErrorReport {
+    /// The raw error, holding the (positive) error code and the header of the offending message.
+    pub err: nlmsgerr,
+    /// The error message attached by the kernel.
+    pub message: Option<String>,
+    /// The offset of the faulty attribute, from the start of the offending message.
+    pub bad_attr_offset: Option<u32>,
+    /// The name of the top-level attribute that contains the faulty attribute, if it could be
+    /// resolved.
+    pub bad_attr_name: Option<&'static str>,
+}
+
+impl NetlinkErrorReport {
+    /// The error code returned by the kernel.
+    #[cfg(feature = "socket")]
+    pub fn errno(&self) -> Errno {
+        Errno::from_i32(self.err.error)
+    }
+
+    /// The type of the offending message.
+    pub fn msg_type(&self) -> u16 {
+        self.err.msg.nlmsg_type
+    }
+
+    /// The sequence number of the offending message.
+    pub fn seq(&self) -> u32 {
+        self.err.msg.nlmsg_seq
+    }
+}
+
+impl NetlinkErrorReport {
+    #[cfg(feature = "socket")]
+    fn description(&self) -> String {
+        self.errno().desc().to_string()
+    }
+
+    #[cfg(not(feature = "socket"))]
+    fn description(&self) -> String {
+        std::io::Error::from_raw_os_error(self.err.error).to_string()
+    }
+}
+
+impl fmt::Display for NetlinkErrorReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} (message type {:#x}, seq {})",
+            self.description(),
+            self.msg_type(),
+            self.seq()
+        )?;
+        if let Some(message) = &self.message {
+            write!(f, ": {}", message)?;
+        }
+        match (self.bad_attr_name, self.bad_attr_offset) {
+            (Some(name), Some(offset)) => write!(f, ", in attribute {} (offset {})", name, offset),
+            (None, Some(offset)) => write!(f, ", in the attribute at offset {}", offset),
+            _ => Ok(()),
+        }
+    }
 }
@@ -1,6 +1,8 @@
+use std::fmt::{self, Display, Formatter};
+
 use rustables_macros::nfnetlink_struct;
 
-use super::{Expression, Register};
+use super::{Expression, OptDisplay, Register};
 use crate::error::BuilderError;
 use crate::parser_impls::NfNetlinkData;
 use crate::sys::{
@@ -9,6 +11,7 @@ use crate::sys::{
 
 #[derive(Clone, PartialEq, Eq, Default, Debug)]
 #[nfnetlink_struct]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Bitwise {
     #[field(NFTA_BITWISE_SREG)]
     sreg: Register,
@@ -45,3 +48,16 @@ impl Bitwise {
             .with_mask(NfNetlinkData::default().with_value(mask)))
     }
 }
+
+impl Display for Bitwise {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "bitwise {} = ({} & {}) ^ {}",
+            OptDisplay(self.dreg.as_ref()),
+            OptDisplay(self.sreg.as_ref()),
+            OptDisplay(self.mask.as_ref()),
+            OptDisplay(self.xor.as_ref())
+        )
+    }
+}
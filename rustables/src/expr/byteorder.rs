@@ -0,0 +1,77 @@
+use std::fmt::{self, Display, Formatter};
+
+use rustables_macros::{nfnetlink_enum, nfnetlink_struct};
+
+use super::{Expression, OptDisplay, Register};
+use crate::sys::{
+    NFTA_BYTEORDER_DREG, NFTA_BYTEORDER_LEN, NFTA_BYTEORDER_OP, NFTA_BYTEORDER_SIZE,
+    NFTA_BYTEORDER_SREG, NFT_BYTEORDER_HTON, NFT_BYTEORDER_NTOH,
+};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+#[nfnetlink_enum(u32)]
+pub enum ByteorderOp {
+    /// Converts from network to host byte order.
+    Ntoh = NFT_BYTEORDER_NTOH,
+    /// Converts from host to network byte order.
+    Hton = NFT_BYTEORDER_HTON,
+}
+
+/// Converts the byte order of the `len` bytes of a register, by words of `word_size` bytes.
+///
+/// Some expressions (e.g. [`Rt`](super::Rt) or [`Meta`](super::Meta) with some keys) load their
+/// value in host byte order, which must be converted before being written to the packet.
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+#[nfnetlink_struct]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Byteorder {
+    #[field(NFTA_BYTEORDER_SREG)]
+    sreg: Register,
+    #[field(NFTA_BYTEORDER_DREG)]
+    dreg: Register,
+    #[field(NFTA_BYTEORDER_OP)]
+    op: ByteorderOp,
+    #[field(NFTA_BYTEORDER_LEN)]
+    len: u32,
+    /// Named `word_size` so that its getter doesn't shadow `NfNetlinkAttribute::get_size`.
+    #[field(NFTA_BYTEORDER_SIZE)]
+    word_size: u32,
+}
+
+impl Byteorder {
+    /// Converts a single value of `size` bytes held in [`Register::Reg1`], in place.
+    pub fn new(op: ByteorderOp, size: u32) -> Self {
+        Byteorder::default()
+            .with_sreg(Register::Reg1)
+            .with_dreg(Register::Reg1)
+            .with_op(op)
+            .with_len(size)
+            .with_word_size(size)
+    }
+}
+
+impl Expression for Byteorder {
+    fn get_name() -> &'static str {
+        "byteorder"
+    }
+}
+
+impl Display for Byteorder {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "byteorder {} = {}({}, {}, {})",
+            OptDisplay(self.dreg.as_ref()),
+            match self.op {
+                Some(ByteorderOp::Ntoh) => "ntoh",
+                Some(ByteorderOp::Hton) => "hton",
+                None => "?",
+            },
+            OptDisplay(self.sreg.as_ref()),
+            OptDisplay(self.word_size.as_ref()),
+            OptDisplay(self.len.as_ref()),
+        )
+    }
+}
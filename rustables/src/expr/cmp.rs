@@ -1,6 +1,10 @@
+use std::fmt::{self, Display, Formatter};
+
 use rustables_macros::{nfnetlink_enum, nfnetlink_struct};
 
+use super::OptDisplay;
 use crate::{
+    data_type::{IpOperand, Port},
     parser_impls::NfNetlinkData,
     sys::{
         NFTA_CMP_DATA, NFTA_CMP_OP, NFTA_CMP_SREG, NFT_CMP_EQ, NFT_CMP_GT, NFT_CMP_GTE, NFT_CMP_LT,
@@ -12,6 +16,8 @@ use super::{Expression, Register};
 
 /// Comparison operator.
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 #[nfnetlink_enum(u32, nested = true)]
 pub enum CmpOp {
     /// Equals.
@@ -31,6 +37,7 @@ pub enum CmpOp {
 /// Comparator expression. Allows comparing the content of the netfilter register with any value.
 #[derive(Default, Debug, Clone, PartialEq, Eq)]
 #[nfnetlink_struct]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Cmp {
     #[field(NFTA_CMP_SREG)]
     sreg: Register,
@@ -50,6 +57,18 @@ impl Cmp {
             data: Some(NfNetlinkData::default().with_value(data.into())),
         }
     }
+
+    /// Returns a new comparison expression comparing the value loaded in the register with the
+    /// IPv4 or IPv6 address `ip`.
+    pub fn new_ip(op: CmpOp, ip: impl Into<IpOperand>) -> Self {
+        Cmp::new(op, ip.into())
+    }
+
+    /// Returns a new comparison expression comparing the value loaded in the register with the
+    /// TCP or UDP port `port`, in network byte order.
+    pub fn new_port(op: CmpOp, port: impl Into<Port>) -> Self {
+        Cmp::new(op, port.into())
+    }
 }
 
 impl Expression for Cmp {
@@ -85,3 +104,28 @@ impl ToSlice for InterfaceName {
     }
 }
 */
+
+impl Display for CmpOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            CmpOp::Eq => "==",
+            CmpOp::Neq => "!=",
+            CmpOp::Lt => "<",
+            CmpOp::Lte => "<=",
+            CmpOp::Gt => ">",
+            CmpOp::Gte => ">=",
+        })
+    }
+}
+
+impl Display for Cmp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "cmp {} {} {}",
+            OptDisplay(self.sreg.as_ref()),
+            OptDisplay(self.op.as_ref()),
+            OptDisplay(self.data.as_ref())
+        )
+    }
+}
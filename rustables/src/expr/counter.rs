@@ -1,12 +1,18 @@
+use std::fmt::{self, Display, Formatter};
+use std::iter::Sum;
+use std::ops::Add;
+use std::time::{Duration, SystemTime};
+
 use rustables_macros::nfnetlink_struct;
 
-use super::Expression;
+use super::{Expression, OptDisplay};
 use crate::sys;
 
 /// A counter expression adds a counter to the rule that is incremented to count number of packets
 /// and number of bytes for all packets that have matched the rule.
 #[derive(Default, Clone, Debug, PartialEq, Eq)]
 #[nfnetlink_struct]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Counter {
     #[field(sys::NFTA_COUNTER_BYTES)]
     pub nb_bytes: u64,
@@ -14,8 +20,130 @@ pub struct Counter {
     pub nb_packets: u64,
 }
 
+impl Counter {
+    /// Creates a counter holding the given values.
+    pub fn new(nb_packets: u64, nb_bytes: u64) -> Self {
+        Counter::default()
+            .with_nb_packets(nb_packets)
+            .with_nb_bytes(nb_bytes)
+    }
+
+    /// The number of packets, or 0 if the counter doesn't hold it.
+    pub fn packets(&self) -> u64 {
+        self.nb_packets.unwrap_or(0)
+    }
+
+    /// The number of bytes, or 0 if the counter doesn't hold it.
+    pub fn bytes(&self) -> u64 {
+        self.nb_bytes.unwrap_or(0)
+    }
+
+    /// The sum of the two counters. The values saturate at `u64::MAX` instead of wrapping
+    /// around.
+    pub fn merge(&self, other: &Counter) -> Counter {
+        Counter::new(
+            self.packets().saturating_add(other.packets()),
+            self.bytes().saturating_add(other.bytes()),
+        )
+    }
+
+    /// The packets and bytes counted since `previous`, an earlier reading of the same counter.
+    ///
+    /// The counters of the kernel never wrap around in practice, so a value lower than in
+    /// `previous` means that the counter was reset in between (e.g. by a listing with reset, or
+    /// because the rule was recreated), and the whole current value is counted.
+    pub fn delta_since(&self, previous: &Counter) -> Counter {
+        let delta = |current: u64, previous: u64| {
+            if current >= previous {
+                current - previous
+            } else {
+                current
+            }
+        };
+        Counter::new(
+            delta(self.packets(), previous.packets()),
+            delta(self.bytes(), previous.bytes()),
+        )
+    }
+}
+
+impl Add for Counter {
+    type Output = Counter;
+
+    /// Same as [`Counter::merge`].
+    fn add(self, other: Counter) -> Counter {
+        self.merge(&other)
+    }
+}
+
+impl Sum for Counter {
+    fn sum<I: Iterator<Item = Counter>>(iter: I) -> Counter {
+        iter.fold(Counter::new(0, 0), |acc, counter| acc.merge(&counter))
+    }
+}
+
+impl<'a> Sum<&'a Counter> for Counter {
+    fn sum<I: Iterator<Item = &'a Counter>>(iter: I) -> Counter {
+        iter.fold(Counter::new(0, 0), |acc, counter| acc.merge(counter))
+    }
+}
+
+/// A reading of a [`Counter`], along with the time it was taken at, to compute rates between
+/// successive readings.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CounterSnapshot {
+    pub counter: Counter,
+    pub taken_at: SystemTime,
+}
+
+impl CounterSnapshot {
+    /// A snapshot of `counter`, taken now.
+    pub fn now(counter: Counter) -> Self {
+        CounterSnapshot {
+            counter,
+            taken_at: SystemTime::now(),
+        }
+    }
+
+    /// The time elapsed since `previous`, or `None` if `previous` is not older than this
+    /// snapshot (e.g. if the clock went backwards).
+    pub fn elapsed_since(&self, previous: &CounterSnapshot) -> Option<Duration> {
+        self.taken_at
+            .duration_since(previous.taken_at)
+            .ok()
+            .filter(|elapsed| !elapsed.is_zero())
+    }
+
+    /// The packets and bytes counted since `previous`, see [`Counter::delta_since`].
+    pub fn delta_since(&self, previous: &CounterSnapshot) -> Counter {
+        self.counter.delta_since(&previous.counter)
+    }
+
+    /// The average rates since `previous`, in packets and bytes per second. Returns `None` if
+    /// no time elapsed between the snapshots.
+    pub fn rates_since(&self, previous: &CounterSnapshot) -> Option<(f64, f64)> {
+        let elapsed = self.elapsed_since(previous)?.as_secs_f64();
+        let delta = self.delta_since(previous);
+        Some((
+            delta.packets() as f64 / elapsed,
+            delta.bytes() as f64 / elapsed,
+        ))
+    }
+}
+
 impl Expression for Counter {
     fn get_name() -> &'static str {
         "counter"
     }
 }
+
+impl Display for Counter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "counter packets {} bytes {}",
+            OptDisplay(self.nb_packets.as_ref()),
+            OptDisplay(self.nb_bytes.as_ref())
+        )
+    }
+}
@@ -1,22 +1,33 @@
+use std::fmt::{self, Display, Formatter};
+
 use rustables_macros::{nfnetlink_enum, nfnetlink_struct};
 
 use crate::sys::{
     NFTA_CT_DIRECTION, NFTA_CT_DREG, NFTA_CT_KEY, NFTA_CT_SREG, NFT_CT_MARK, NFT_CT_STATE,
 };
 
-use super::{Expression, Register};
+use super::{Expression, OptDisplay, Register};
 
 bitflags::bitflags! {
+    /// The connection tracking states of a packet, as matched by `ct state` in nft.
     pub struct ConnTrackState: u32 {
+        /// The packet is not associated with any known connection.
         const INVALID = 1;
+        /// The packet belongs to a connection that saw traffic in both directions.
         const ESTABLISHED = 2;
+        /// The packet starts a new connection related to an existing one (e.g. an FTP data
+        /// connection, or an ICMP error).
         const RELATED = 4;
+        /// The packet starts a new connection.
         const NEW = 8;
+        /// The packet was excluded from connection tracking (with a `notrack` statement).
         const UNTRACKED = 64;
     }
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 #[nfnetlink_enum(u32, nested = true)]
 pub enum ConntrackKey {
     State = NFT_CT_STATE,
@@ -25,6 +36,7 @@ pub enum ConntrackKey {
 
 #[derive(Default, Clone, Debug, PartialEq, Eq)]
 #[nfnetlink_struct(nested = true)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Conntrack {
     #[field(NFTA_CT_DREG)]
     pub dreg: Register,
@@ -67,3 +79,31 @@ impl Conntrack {
         self
     }
 }
+
+impl Display for ConntrackKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ConntrackKey::State => "state",
+            ConntrackKey::Mark => "mark",
+        })
+    }
+}
+
+impl Display for Conntrack {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "ct {}", OptDisplay(self.key.as_ref()))?;
+        match self.direction {
+            Some(0) => f.write_str(" original")?,
+            Some(1) => f.write_str(" reply")?,
+            Some(dir) => write!(f, " direction {}", dir)?,
+            None => {}
+        }
+        if let Some(dreg) = self.dreg {
+            write!(f, " -> {}", dreg)?;
+        }
+        if let Some(sreg) = self.sreg {
+            write!(f, " <- {}", sreg)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,109 @@
+use std::fmt::{self, Display, Formatter};
+
+use rustables_macros::{nfnetlink_enum, nfnetlink_struct};
+
+use super::{Expression, OptDisplay, Register};
+use crate::sys::{
+    NFTA_EXTHDR_DREG, NFTA_EXTHDR_FLAGS, NFTA_EXTHDR_LEN, NFTA_EXTHDR_OFFSET, NFTA_EXTHDR_OP,
+    NFTA_EXTHDR_SREG, NFTA_EXTHDR_TYPE, NFT_EXTHDR_OP_DCCP, NFT_EXTHDR_OP_IPV4, NFT_EXTHDR_OP_IPV6,
+    NFT_EXTHDR_OP_SCTP, NFT_EXTHDR_OP_TCPOPT,
+};
+
+/// The kind of TCP option holding the maximum segment size.
+pub const TCPOPT_MAXSEG: u8 = 2;
+
+/// The header whose extensions or options are read or written by an [`Exthdr`] expression.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+#[nfnetlink_enum(u32)]
+pub enum ExthdrOp {
+    /// IPv6 extension headers.
+    Ipv6 = NFT_EXTHDR_OP_IPV6,
+    /// TCP options.
+    TcpOpt = NFT_EXTHDR_OP_TCPOPT,
+    /// IPv4 options.
+    Ipv4 = NFT_EXTHDR_OP_IPV4,
+    /// SCTP chunks.
+    Sctp = NFT_EXTHDR_OP_SCTP,
+    /// DCCP options.
+    Dccp = NFT_EXTHDR_OP_DCCP,
+}
+
+/// Reads (with a destination register) or rewrites (with a source register) `len` bytes at
+/// `offset` in the extension header or option of type `exthdr_type`.
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+#[nfnetlink_struct]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Exthdr {
+    #[field(NFTA_EXTHDR_DREG)]
+    dreg: Register,
+    #[field(NFTA_EXTHDR_TYPE)]
+    exthdr_type: u8,
+    #[field(NFTA_EXTHDR_OFFSET)]
+    offset: u32,
+    #[field(NFTA_EXTHDR_LEN)]
+    len: u32,
+    #[field(NFTA_EXTHDR_FLAGS)]
+    flags: u32,
+    #[field(NFTA_EXTHDR_OP)]
+    op: ExthdrOp,
+    #[field(NFTA_EXTHDR_SREG)]
+    sreg: Register,
+}
+
+impl Exthdr {
+    /// Loads `len` bytes at `offset` in the TCP option of kind `kind` into [`Register::Reg1`].
+    pub fn tcp_option(kind: u8, offset: u32, len: u32) -> Self {
+        Exthdr::default()
+            .with_op(ExthdrOp::TcpOpt)
+            .with_exthdr_type(kind)
+            .with_offset(offset)
+            .with_len(len)
+            .with_dreg(Register::Reg1)
+    }
+
+    /// Turns the expression into a write of the content of `sreg` to the packet.
+    pub fn with_value_from(mut self, sreg: Register) -> Self {
+        self.dreg = None;
+        self.with_sreg(sreg)
+    }
+}
+
+impl Expression for Exthdr {
+    fn get_name() -> &'static str {
+        "exthdr"
+    }
+}
+
+impl Display for ExthdrOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ExthdrOp::Ipv6 => "ipv6",
+            ExthdrOp::TcpOpt => "tcpopt",
+            ExthdrOp::Ipv4 => "ipv4",
+            ExthdrOp::Sctp => "sctp",
+            ExthdrOp::Dccp => "dccp",
+        })
+    }
+}
+
+impl Display for Exthdr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "exthdr {} {} {}b @ {}",
+            OptDisplay(self.op.as_ref()),
+            OptDisplay(self.exthdr_type.as_ref()),
+            OptDisplay(self.len.as_ref()),
+            OptDisplay(self.offset.as_ref()),
+        )?;
+        if let Some(dreg) = self.dreg {
+            write!(f, " -> {}", dreg)?;
+        }
+        if let Some(sreg) = self.sreg {
+            write!(f, " <- {}", sreg)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,104 @@
+use std::fmt::{self, Display, Formatter};
+
+use rustables_macros::{nfnetlink_enum, nfnetlink_struct};
+
+use super::{Expression, Register};
+use crate::sys::{
+    NFTA_HASH_DREG, NFTA_HASH_LEN, NFTA_HASH_MODULUS, NFTA_HASH_OFFSET, NFTA_HASH_SEED,
+    NFTA_HASH_SREG, NFTA_HASH_TYPE, NFT_HASH_JENKINS, NFT_HASH_SYM,
+};
+
+/// The hash function of a [`Hash`] expression.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+#[nfnetlink_enum(u32)]
+pub enum HashType {
+    /// The Jenkins hash of the data in the source register.
+    Jenkins = NFT_HASH_JENKINS,
+    /// A hash of the flow of the packet that is the same in both directions, so that the replies
+    /// get the same value as the requests. It does not read any register.
+    Symmetric = NFT_HASH_SYM,
+}
+
+/// Loads a hash of the packet, modulo [`Hash::get_modulus`] and shifted by
+/// [`Hash::get_offset`], as a 32-bit integer in host byte order. Like with [`Numgen`], the value
+/// is usually looked up in a map to spread the packets across several backends, but the packets
+/// of a same flow always get the same value.
+///
+/// [`Numgen`]: super::Numgen
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+#[nfnetlink_struct]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Hash {
+    #[field(NFTA_HASH_SREG)]
+    sreg: Register,
+    #[field(NFTA_HASH_DREG)]
+    dreg: Register,
+    /// The number of bytes of the source register that are hashed.
+    #[field(NFTA_HASH_LEN)]
+    len: u32,
+    #[field(NFTA_HASH_MODULUS)]
+    modulus: u32,
+    #[field(NFTA_HASH_SEED)]
+    seed: u32,
+    #[field(NFTA_HASH_OFFSET)]
+    offset: u32,
+    #[field(NFTA_HASH_TYPE)]
+    hash_type: HashType,
+}
+
+impl Hash {
+    /// Hashes the `len` bytes loaded in `Reg1` (e.g. the source address of the packet, to keep
+    /// the clients on the same backend), and loads the result modulo `modulus` in `Reg1`.
+    pub fn jhash(len: u32, modulus: u32) -> Self {
+        Hash::default()
+            .with_hash_type(HashType::Jenkins)
+            .with_sreg(Register::Reg1)
+            .with_len(len)
+            .with_modulus(modulus)
+            .with_dreg(Register::Reg1)
+    }
+
+    /// Loads the symmetric hash of the flow of the packet modulo `modulus` in `Reg1`.
+    pub fn symhash(modulus: u32) -> Self {
+        Hash::default()
+            .with_hash_type(HashType::Symmetric)
+            .with_modulus(modulus)
+            .with_dreg(Register::Reg1)
+    }
+}
+
+impl Expression for Hash {
+    fn get_name() -> &'static str {
+        "hash"
+    }
+}
+
+impl Display for Hash {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self.hash_type {
+            Some(HashType::Symmetric) => "symhash",
+            _ => "jhash",
+        })?;
+        if let Some(sreg) = self.sreg {
+            write!(f, " {}", sreg)?;
+        }
+        if let Some(len) = self.len {
+            write!(f, " len {}", len)?;
+        }
+        if let Some(modulus) = self.modulus {
+            write!(f, " mod {}", modulus)?;
+        }
+        if let Some(seed) = self.seed {
+            write!(f, " seed {:#x}", seed)?;
+        }
+        if let Some(offset) = self.offset {
+            write!(f, " offset {}", offset)?;
+        }
+        if let Some(dreg) = self.dreg {
+            write!(f, " -> {}", dreg)?;
+        }
+        Ok(())
+    }
+}
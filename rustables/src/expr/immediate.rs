@@ -1,13 +1,17 @@
+use std::fmt::{self, Display, Formatter};
+
 use rustables_macros::nfnetlink_struct;
 
-use super::{Expression, Register, Verdict, VerdictKind, VerdictType};
+use super::{Expression, OptDisplay, Register, Verdict};
 use crate::{
+    data_type::IpOperand,
     parser_impls::NfNetlinkData,
     sys::{NFTA_IMMEDIATE_DATA, NFTA_IMMEDIATE_DREG},
 };
 
 #[derive(Clone, PartialEq, Eq, Default, Debug)]
 #[nfnetlink_struct]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Immediate {
     #[field(NFTA_IMMEDIATE_DREG)]
     dreg: Register,
@@ -22,24 +26,15 @@ impl Immediate {
             .with_data(NfNetlinkData::default().with_value(data))
     }
 
-    pub fn new_verdict(kind: VerdictKind) -> Self {
-        let code = match kind {
-            VerdictKind::Drop => VerdictType::Drop,
-            VerdictKind::Accept => VerdictType::Accept,
-            VerdictKind::Queue => VerdictType::Queue,
-            VerdictKind::Continue => VerdictType::Continue,
-            VerdictKind::Break => VerdictType::Break,
-            VerdictKind::Jump { .. } => VerdictType::Jump,
-            VerdictKind::Goto { .. } => VerdictType::Goto,
-            VerdictKind::Return => VerdictType::Return,
-        };
-        let mut data = Verdict::default().with_code(code);
-        if let VerdictKind::Jump { chain } | VerdictKind::Goto { chain } = kind {
-            data.set_chain(chain);
-        }
+    /// Loads the IPv4 or IPv6 address `ip` in `register`.
+    pub fn new_ip(ip: impl Into<IpOperand>, register: Register) -> Self {
+        Immediate::new_data(ip.into().to_vec(), register)
+    }
+
+    pub fn new_verdict(verdict: impl Into<Verdict>) -> Self {
         Immediate::default()
             .with_dreg(Register::Verdict)
-            .with_data(NfNetlinkData::default().with_verdict(data))
+            .with_data(NfNetlinkData::default().with_verdict(verdict))
     }
 }
 
@@ -48,3 +43,14 @@ impl Expression for Immediate {
         "immediate"
     }
 }
+
+impl Display for Immediate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "immediate {} -> {}",
+            OptDisplay(self.data.as_ref()),
+            OptDisplay(self.dreg.as_ref())
+        )
+    }
+}
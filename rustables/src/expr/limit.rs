@@ -0,0 +1,113 @@
+use std::fmt::{self, Display, Formatter};
+
+use rustables_macros::nfnetlink_struct;
+
+use super::{Expression, OptDisplay};
+use crate::sys::{
+    NFTA_LIMIT_BURST, NFTA_LIMIT_FLAGS, NFTA_LIMIT_RATE, NFTA_LIMIT_TYPE, NFTA_LIMIT_UNIT,
+    NFT_LIMIT_F_INV, NFT_LIMIT_PKTS, NFT_LIMIT_PKT_BYTES,
+};
+
+const MINUTE: u64 = 60;
+const HOUR: u64 = 60 * MINUTE;
+const DAY: u64 = 24 * HOUR;
+
+/// A limit expression matches packets until the given rate is reached, and stops matching them
+/// afterwards.
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+#[nfnetlink_struct]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Limit {
+    #[field(NFTA_LIMIT_RATE)]
+    rate: u64,
+    /// Length of the time period over which `rate` applies, in seconds.
+    #[field(NFTA_LIMIT_UNIT)]
+    unit: u64,
+    #[field(NFTA_LIMIT_BURST)]
+    burst: u32,
+    #[field(NFTA_LIMIT_TYPE)]
+    limit_type: u32,
+    #[field(NFTA_LIMIT_FLAGS)]
+    flags: u32,
+}
+
+impl Limit {
+    /// Creates a limit matching at most `rate` packets every `unit` seconds.
+    pub fn new(rate: u64, unit: u64) -> Limit {
+        Limit::default().with_rate(rate).with_unit(unit)
+    }
+
+    fn packets(rate: u64, unit: u64) -> Limit {
+        Limit::new(rate, unit).with_limit_type(NFT_LIMIT_PKTS)
+    }
+
+    /// Creates a limit matching at most `rate` packets per second, the equivalent of
+    /// `limit rate 10/second` in nft. Without [`Limit::burst`], the kernel lets bursts of 5
+    /// packets through.
+    pub fn per_second(rate: u64) -> Limit {
+        Limit::packets(rate, 1)
+    }
+
+    /// Creates a limit matching at most `rate` packets per minute.
+    pub fn per_minute(rate: u64) -> Limit {
+        Limit::packets(rate, MINUTE)
+    }
+
+    /// Creates a limit matching at most `rate` packets per hour.
+    pub fn per_hour(rate: u64) -> Limit {
+        Limit::packets(rate, HOUR)
+    }
+
+    /// Creates a limit matching at most `rate` packets per day.
+    pub fn per_day(rate: u64) -> Limit {
+        Limit::packets(rate, DAY)
+    }
+
+    /// Creates a limit matching packets until `rate` bytes per second are reached, the
+    /// equivalent of `limit rate 10 kbytes/second` in nft with a `rate` of 10240. The burst is
+    /// then a number of bytes.
+    pub fn bytes_per_second(rate: u64) -> Limit {
+        Limit::new(rate, 1).with_limit_type(NFT_LIMIT_PKT_BYTES)
+    }
+
+    /// Lets `burst` packets (or bytes) through above the rate, e.g. `limit rate 10/second burst
+    /// 20 packets` in nft.
+    pub fn burst(self, burst: u32) -> Self {
+        self.with_burst(burst)
+    }
+
+    /// Inverts the limit, which then matches the packets above the rate, the equivalent of
+    /// `limit rate over 10/second` in nft.
+    pub fn over(self) -> Self {
+        let flags = self.flags.unwrap_or(0);
+        self.with_flags(flags | NFT_LIMIT_F_INV)
+    }
+}
+
+impl Expression for Limit {
+    fn get_name() -> &'static str {
+        "limit"
+    }
+}
+
+impl Display for Limit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("limit rate ")?;
+        if self.flags.unwrap_or(0) & NFT_LIMIT_F_INV != 0 {
+            f.write_str("over ")?;
+        }
+        write!(
+            f,
+            "{}/{}s",
+            OptDisplay(self.rate.as_ref()),
+            OptDisplay(self.unit.as_ref())
+        )?;
+        if self.limit_type == Some(NFT_LIMIT_PKT_BYTES) {
+            f.write_str(" bytes")?;
+        }
+        if let Some(burst) = self.burst {
+            write!(f, " burst {}", burst)?;
+        }
+        Ok(())
+    }
+}
@@ -1,34 +1,122 @@
+use std::convert::{TryFrom, TryInto};
+use std::fmt::{self, Display, Formatter};
+
 use rustables_macros::nfnetlink_struct;
 
 use super::Expression;
 use crate::{
-    error::BuilderError,
+    error::{BuilderError, DecodeError},
+    groups::LogGroup,
+    nlmsg::{NfNetlinkAttribute, NfNetlinkDeserializable},
     sys::{NFTA_LOG_GROUP, NFTA_LOG_PREFIX},
 };
 
+/// The longest prefix the kernel accepts, in bytes.
+const MAX_LOG_PREFIX_LEN: usize = 127;
+
+/// The prefix of the packets logged by a [`Log`] expression.
+///
+/// The prefix is checked when it is built: it holds at most 127 bytes, and no NUL byte.
+#[derive(Clone, PartialEq, Eq, Default, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "String", into = "String"))]
+pub struct LogPrefix(String);
+
+impl LogPrefix {
+    pub fn new(prefix: impl Into<String>) -> Result<Self, BuilderError> {
+        let prefix = prefix.into();
+        if prefix.len() > MAX_LOG_PREFIX_LEN {
+            return Err(BuilderError::TooLongLogPrefix);
+        }
+        if prefix.contains('\0') {
+            return Err(BuilderError::NulInLogPrefix);
+        }
+        Ok(LogPrefix(prefix))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl TryFrom<&str> for LogPrefix {
+    type Error = BuilderError;
+
+    fn try_from(prefix: &str) -> Result<Self, Self::Error> {
+        LogPrefix::new(prefix)
+    }
+}
+
+impl TryFrom<String> for LogPrefix {
+    type Error = BuilderError;
+
+    fn try_from(prefix: String) -> Result<Self, Self::Error> {
+        LogPrefix::new(prefix)
+    }
+}
+
+impl From<LogPrefix> for String {
+    fn from(prefix: LogPrefix) -> Self {
+        prefix.0
+    }
+}
+
+impl AsRef<str> for LogPrefix {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for LogPrefix {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl NfNetlinkAttribute for LogPrefix {
+    fn get_size(&self) -> usize {
+        self.0.get_size()
+    }
+
+    fn write_payload(&self, addr: &mut [u8]) {
+        self.0.write_payload(addr)
+    }
+}
+
+impl NfNetlinkDeserializable for LogPrefix {
+    fn deserialize(buf: &[u8]) -> Result<(Self, &[u8]), DecodeError> {
+        // the kernel already enforces the constraints on the prefixes it stores
+        let (prefix, remaining) = String::deserialize(buf)?;
+        Ok((LogPrefix(prefix), remaining))
+    }
+}
+
 #[derive(Clone, PartialEq, Eq, Default, Debug)]
 #[nfnetlink_struct]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// A Log expression will log all packets that match the rule.
 pub struct Log {
     #[field(NFTA_LOG_GROUP)]
-    group: u16,
+    group: LogGroup,
     #[field(NFTA_LOG_PREFIX)]
-    prefix: String,
+    prefix: LogPrefix,
 }
 
 impl Log {
-    pub fn new(group: Option<u16>, prefix: Option<impl Into<String>>) -> Result<Log, BuilderError> {
+    /// Creates a log expression. The prefix is either a [`LogPrefix`] or a string, which is
+    /// checked the same way. Group numbers held in wider integers can be checked with
+    /// [`LogGroup::new`].
+    pub fn new<P>(group: Option<u16>, prefix: Option<P>) -> Result<Log, BuilderError>
+    where
+        P: TryInto<LogPrefix>,
+        BuilderError: From<P::Error>,
+    {
         let mut res = Log::default();
         if let Some(group) = group {
             res.set_group(group);
         }
         if let Some(prefix) = prefix {
-            let prefix = prefix.into();
-
-            if prefix.bytes().count() > 127 {
-                return Err(BuilderError::TooLongLogPrefix);
-            }
-            res.set_prefix(prefix);
+            res.set_prefix(prefix.try_into()?);
         }
         Ok(res)
     }
@@ -39,3 +127,16 @@ impl Expression for Log {
         "log"
     }
 }
+
+impl Display for Log {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("log")?;
+        if let Some(prefix) = &self.prefix {
+            write!(f, " prefix {:?}", prefix.as_str())?;
+        }
+        if let Some(group) = self.group {
+            write!(f, " group {}", group)?;
+        }
+        Ok(())
+    }
+}
@@ -1,12 +1,18 @@
+use std::fmt::{self, Display, Formatter};
+
 use rustables_macros::nfnetlink_struct;
 
-use super::{Expression, Register};
+use super::{Expression, OptDisplay, Register};
 use crate::error::BuilderError;
-use crate::sys::{NFTA_LOOKUP_DREG, NFTA_LOOKUP_SET, NFTA_LOOKUP_SET_ID, NFTA_LOOKUP_SREG};
+use crate::sys::{
+    NFTA_LOOKUP_DREG, NFTA_LOOKUP_FLAGS, NFTA_LOOKUP_SET, NFTA_LOOKUP_SET_ID, NFTA_LOOKUP_SREG,
+    NFT_LOOKUP_F_INV,
+};
 use crate::Set;
 
 #[derive(Clone, PartialEq, Eq, Default, Debug)]
 #[nfnetlink_struct]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Lookup {
     #[field(NFTA_LOOKUP_SET)]
     set: String,
@@ -16,6 +22,8 @@ pub struct Lookup {
     dreg: Register,
     #[field(NFTA_LOOKUP_SET_ID)]
     set_id: u32,
+    #[field(NFTA_LOOKUP_FLAGS)]
+    flags: u32,
 }
 
 impl Lookup {
@@ -31,6 +39,12 @@ impl Lookup {
 
         Ok(res)
     }
+
+    /// Inverts the lookup, which then matches the values that are not in the set.
+    pub fn inverted(self) -> Self {
+        let flags = self.flags.unwrap_or(0);
+        self.with_flags(flags | NFT_LOOKUP_F_INV)
+    }
 }
 
 impl Expression for Lookup {
@@ -38,3 +52,21 @@ impl Expression for Lookup {
         "lookup"
     }
 }
+
+impl Display for Lookup {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "lookup {} @{}",
+            OptDisplay(self.sreg.as_ref()),
+            OptDisplay(self.set.as_ref())
+        )?;
+        if let Some(dreg) = self.dreg {
+            write!(f, " -> {}", dreg)?;
+        }
+        if self.flags.unwrap_or(0) & NFT_LOOKUP_F_INV != 0 {
+            f.write_str(" inverted")?;
+        }
+        Ok(())
+    }
+}
@@ -1,15 +1,41 @@
+use std::fmt::{self, Display, Formatter};
+
 use rustables_macros::nfnetlink_struct;
 
-use super::Expression;
+use super::{Expression, NatFlags, Register};
+use crate::sys::{NFTA_MASQ_FLAGS, NFTA_MASQ_REG_PROTO_MAX, NFTA_MASQ_REG_PROTO_MIN};
 
 /// Sets the source IP to that of the output interface.
-#[derive(Default, Debug, PartialEq, Eq)]
+#[derive(Clone, Default, Debug, PartialEq, Eq)]
 #[nfnetlink_struct(nested = true)]
-pub struct Masquerade;
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Masquerade {
+    #[field(NFTA_MASQ_FLAGS)]
+    flags: u32,
+    #[field(NFTA_MASQ_REG_PROTO_MIN)]
+    port_min_register: Register,
+    #[field(NFTA_MASQ_REG_PROTO_MAX)]
+    port_max_register: Register,
+}
 
-impl Clone for Masquerade {
-    fn clone(&self) -> Self {
-        Masquerade {}
+impl Masquerade {
+    /// Restricts the source ports of the translated connections to the range whose bounds are
+    /// loaded (in network byte order) in `min` and `max`.
+    pub fn with_port_range(self, min: Register, max: Register) -> Self {
+        self.with_nat_flags(NatFlags::PROTO_SPECIFIED)
+            .with_port_min_register(min)
+            .with_port_max_register(max)
+    }
+
+    /// Adds `flags` to the flags of the masquerade, e.g. [`NatFlags::PROTO_RANDOM_FULLY`].
+    pub fn with_nat_flags(self, flags: NatFlags) -> Self {
+        let flags = self.flags.unwrap_or(0) | flags.bits();
+        self.with_flags(flags)
+    }
+
+    /// The flags of the masquerade, ignoring the ones unknown to this library.
+    pub fn get_nat_flags(&self) -> Option<NatFlags> {
+        self.flags.map(NatFlags::from_bits_truncate)
     }
 }
 
@@ -18,3 +44,16 @@ impl Expression for Masquerade {
         "masq"
     }
 }
+
+impl Display for Masquerade {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("masq")?;
+        if let Some(min) = self.port_min_register {
+            write!(f, " to :{}", min)?;
+            if let Some(max) = self.port_max_register {
+                write!(f, "-{}", max)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,10 +1,13 @@
+use std::fmt::{self, Display, Formatter};
+
 use rustables_macros::{nfnetlink_enum, nfnetlink_struct};
 
-use super::{Expression, Register};
+use super::{Expression, OptDisplay, Register};
 use crate::sys;
 
 /// A meta expression refers to meta data associated with a packet.
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[nfnetlink_enum(u32)]
 #[non_exhaustive]
 pub enum MetaType {
@@ -12,6 +15,9 @@ pub enum MetaType {
     Protocol = sys::NFT_META_PROTOCOL,
     /// Packet mark.
     Mark = sys::NFT_META_MARK,
+    /// Packet priority (skb->priority), which selects the tc class of the packet, see
+    /// [`TcHandle`](crate::data_type::TcHandle).
+    Priority = sys::NFT_META_PRIORITY,
     /// Packet input interface index (dev->ifindex).
     Iif = sys::NFT_META_IIF,
     /// Packet output interface index (dev->ifindex).
@@ -36,10 +42,15 @@ pub enum MetaType {
     Cgroup = sys::NFT_META_CGROUP,
     /// A 32bit pseudo-random number.
     PRandom = sys::NFT_META_PRANDOM,
+    /// Name of the bridge the packet was received on, in bridge tables.
+    BriIifName = sys::NFT_META_BRI_IIFNAME,
+    /// Name of the bridge the packet is sent through, in bridge tables.
+    BriOifName = sys::NFT_META_BRI_OIFNAME,
 }
 
 #[derive(Clone, PartialEq, Eq, Default, Debug)]
 #[nfnetlink_struct]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Meta {
     #[field(sys::NFTA_META_DREG)]
     dreg: Register,
@@ -60,3 +71,40 @@ impl Expression for Meta {
         "meta"
     }
 }
+
+impl Display for MetaType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            MetaType::Protocol => "protocol",
+            MetaType::Mark => "mark",
+            MetaType::Priority => "priority",
+            MetaType::Iif => "iif",
+            MetaType::Oif => "oif",
+            MetaType::IifName => "iifname",
+            MetaType::OifName => "oifname",
+            MetaType::IifType => "iiftype",
+            MetaType::OifType => "oiftype",
+            MetaType::SkUid => "skuid",
+            MetaType::SkGid => "skgid",
+            MetaType::NfProto => "nfproto",
+            MetaType::L4Proto => "l4proto",
+            MetaType::Cgroup => "cgroup",
+            MetaType::PRandom => "random",
+            MetaType::BriIifName => "ibrname",
+            MetaType::BriOifName => "obrname",
+        })
+    }
+}
+
+impl Display for Meta {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "meta {}", OptDisplay(self.key.as_ref()))?;
+        if let Some(dreg) = self.dreg {
+            write!(f, " -> {}", dreg)?;
+        }
+        if let Some(sreg) = self.sreg {
+            write!(f, " <- {}", sreg)?;
+        }
+        Ok(())
+    }
+}
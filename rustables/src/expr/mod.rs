@@ -3,7 +3,7 @@
 //!
 //! [`Rule`]: struct.Rule.html
 
-use std::fmt::Debug;
+use std::fmt::{self, Debug, Display, Formatter};
 
 use rustables_macros::nfnetlink_struct;
 
@@ -11,10 +11,14 @@ use crate::error::DecodeError;
 use crate::nlmsg::{NfNetlinkAttribute, NfNetlinkDeserializable};
 use crate::parser_impls::NfNetlinkList;
 use crate::sys::{self, NFTA_EXPR_DATA, NFTA_EXPR_NAME};
+use crate::RawAttributeTree;
 
 mod bitwise;
 pub use self::bitwise::*;
 
+mod byteorder;
+pub use self::byteorder::*;
+
 mod cmp;
 pub use self::cmp::*;
 
@@ -24,9 +28,18 @@ pub use self::counter::*;
 pub mod ct;
 pub use self::ct::*;
 
+mod exthdr;
+pub use self::exthdr::*;
+
+mod hash;
+pub use self::hash::*;
+
 mod immediate;
 pub use self::immediate::*;
 
+mod limit;
+pub use self::limit::*;
+
 mod log;
 pub use self::log::*;
 
@@ -42,24 +55,56 @@ pub use self::meta::*;
 mod nat;
 pub use self::nat::*;
 
+mod numgen;
+pub use self::numgen::*;
+
+mod objref;
+pub use self::objref::*;
+
 mod payload;
 pub use self::payload::*;
 
+mod queue;
+pub use self::queue::*;
+
 mod reject;
 pub use self::reject::{IcmpCode, Reject, RejectType};
+pub(crate) use self::reject::{ICMPV6_CODE_NAMES, ICMPX_CODE_NAMES, ICMP_CODE_NAMES};
 
 mod register;
 pub use self::register::Register;
 
+mod rt;
+pub use self::rt::*;
+
+mod socket;
+pub use self::socket::*;
+
+mod tproxy;
+pub use self::tproxy::*;
+
 mod verdict;
 pub use self::verdict::*;
 
+/// Displays an optional attribute of an expression, or `?` when it is missing.
+pub(crate) struct OptDisplay<'a, T>(pub Option<&'a T>);
+
+impl<T: Display> Display for OptDisplay<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(val) => Display::fmt(val, f),
+            None => f.write_str("?"),
+        }
+    }
+}
+
 pub trait Expression {
     fn get_name() -> &'static str;
 }
 
 #[derive(Clone, PartialEq, Eq, Default, Debug)]
 #[nfnetlink_struct(nested = true, derive_decoder = false)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RawExpression {
     #[field(NFTA_EXPR_NAME)]
     name: String,
@@ -67,6 +112,16 @@ pub struct RawExpression {
     data: ExpressionVariant,
 }
 
+impl Display for RawExpression {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match (&self.data, &self.name) {
+            (Some(data), _) => Display::fmt(data, f),
+            (None, Some(name)) => f.write_str(name),
+            (None, None) => f.write_str("?"),
+        }
+    }
+}
+
 impl<T> From<T> for RawExpression
 where
     T: Expression,
@@ -82,6 +137,7 @@ where
 macro_rules! create_expr_variant {
     ($enum:ident $(, [$name:ident, $type:ty])+) => {
         #[derive(Debug, Clone, PartialEq, Eq)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
         pub enum $enum {
             $(
                 $name($type),
@@ -110,6 +166,16 @@ macro_rules! create_expr_variant {
             }
         }
 
+        impl Display for $enum {
+            fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+                match self {
+                    $(
+                        $enum::$name(val) => Display::fmt(val, f),
+                    )+
+                }
+            }
+        }
+
         $(
             impl From<$type> for $enum {
                 fn from(val: $type) -> Self {
@@ -153,6 +219,9 @@ macro_rules! create_expr_variant {
                                 },
                             )+
                             name => {
+                                if $crate::parser::decode_mode() == $crate::DecodeMode::Strict {
+                                    return Err(DecodeError::UnknownExpression(name.to_string()));
+                                }
                                 info!("Unrecognized expression '{}', generating an ExpressionRaw", name);
                                 self.data = Some(ExpressionVariant::ExpressionRaw(ExpressionRaw::deserialize(buf)?.0));
                                 Ok(())
@@ -169,25 +238,108 @@ macro_rules! create_expr_variant {
 create_expr_variant!(
     ExpressionVariant,
     [Bitwise, Bitwise],
+    [Byteorder, Byteorder],
     [Cmp, Cmp],
     [Conntrack, Conntrack],
     [Counter, Counter],
     [ExpressionRaw, ExpressionRaw],
+    [Exthdr, Exthdr],
+    [Hash, Hash],
     [Immediate, Immediate],
+    [Limit, Limit],
     [Log, Log],
     [Lookup, Lookup],
     [Masquerade, Masquerade],
     [Meta, Meta],
     [Nat, Nat],
+    [Numgen, Numgen],
+    [Objref, Objref],
     [Payload, Payload],
-    [Reject, Reject]
+    [Queue, Queue],
+    [Reject, Reject],
+    [Rt, Rt],
+    [Socket, Socket],
+    [Tproxy, Tproxy]
 );
 
+impl ExpressionVariant {
+    /// The registers the expression reads, then the ones it loads, or `None` for the expressions
+    /// unknown to this crate.
+    pub(crate) fn registers(&self) -> Option<(Vec<Register>, Vec<Register>)> {
+        let (reads, loads) = match self {
+            ExpressionVariant::Bitwise(e) => (vec![e.get_sreg()], vec![e.get_dreg()]),
+            ExpressionVariant::Byteorder(e) => (vec![e.get_sreg()], vec![e.get_dreg()]),
+            ExpressionVariant::Cmp(e) => (vec![e.get_sreg()], vec![]),
+            ExpressionVariant::Conntrack(e) => (vec![e.get_sreg()], vec![e.get_dreg()]),
+            ExpressionVariant::Exthdr(e) => (vec![e.get_sreg()], vec![e.get_dreg()]),
+            ExpressionVariant::Hash(e) => (vec![e.get_sreg()], vec![e.get_dreg()]),
+            ExpressionVariant::Immediate(e) => (vec![], vec![e.get_dreg()]),
+            ExpressionVariant::Lookup(e) => (vec![e.get_sreg()], vec![e.get_dreg()]),
+            ExpressionVariant::Masquerade(e) => (
+                vec![e.get_port_min_register(), e.get_port_max_register()],
+                vec![],
+            ),
+            ExpressionVariant::Meta(e) => (vec![e.get_sreg()], vec![e.get_dreg()]),
+            ExpressionVariant::Nat(e) => (
+                vec![
+                    e.get_ip_register(),
+                    e.get_ip_max_register(),
+                    e.get_port_register(),
+                    e.get_port_max_register(),
+                ],
+                vec![],
+            ),
+            ExpressionVariant::Numgen(e) => (vec![], vec![e.get_dreg()]),
+            ExpressionVariant::Objref(e) => (vec![e.get_set_sreg()], vec![]),
+            ExpressionVariant::Payload(e) => (vec![e.get_sreg()], vec![e.get_dreg()]),
+            ExpressionVariant::Rt(e) => (vec![], vec![e.get_dreg()]),
+            ExpressionVariant::Socket(e) => (vec![], vec![e.get_dreg()]),
+            ExpressionVariant::Tproxy(e) => {
+                (vec![e.get_addr_register(), e.get_port_register()], vec![])
+            }
+            ExpressionVariant::Counter(_)
+            | ExpressionVariant::Limit(_)
+            | ExpressionVariant::Log(_)
+            | ExpressionVariant::Queue(_)
+            | ExpressionVariant::Reject(_) => (vec![], vec![]),
+            ExpressionVariant::ExpressionRaw(_) => return None,
+        };
+        Some((
+            reads.into_iter().flatten().copied().collect(),
+            loads.into_iter().flatten().copied().collect(),
+        ))
+    }
+}
+
 pub type ExpressionList = NfNetlinkList<RawExpression>;
 
+/// Builds an [`ExpressionList`] from expressions of different types, e.g. to set all the
+/// expressions of a rule at once:
+///
+/// ```
+/// use rustables::expr::{Cmp, CmpOp, Immediate, Meta, MetaType, VerdictKind};
+/// use rustables::exprs;
+///
+/// let exprs = exprs![
+///     Meta::new(MetaType::L4Proto),
+///     Cmp::new(CmpOp::Eq, [libc::IPPROTO_TCP as u8]),
+///     Immediate::new_verdict(VerdictKind::Accept),
+/// ];
+/// assert_eq!(exprs.iter().count(), 3);
+/// ```
+#[macro_export]
+macro_rules! exprs {
+    ($($expr:expr),* $(,)?) => {
+        $crate::expr::ExpressionList::default()$(.with_value($expr))*
+    };
+}
+
 // default type for expressions that we do not handle yet
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct ExpressionRaw(Vec<u8>);
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ExpressionRaw(
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_helpers::hex"))] Vec<u8>,
+);
 
 impl NfNetlinkAttribute for ExpressionRaw {
     fn get_size(&self) -> usize {
@@ -199,12 +351,34 @@ impl NfNetlinkAttribute for ExpressionRaw {
     }
 }
 
+impl ExpressionRaw {
+    /// The raw attributes of the expression.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// The attributes of the expression, decoded as a [`RawAttributeTree`] to inspect them.
+    pub fn to_attribute_tree(&self) -> Result<RawAttributeTree, DecodeError> {
+        RawAttributeTree::parse(&self.0)
+    }
+}
+
 impl NfNetlinkDeserializable for ExpressionRaw {
     fn deserialize(buf: &[u8]) -> Result<(Self, &[u8]), DecodeError> {
         Ok((ExpressionRaw(buf.to_vec()), &[]))
     }
 }
 
+impl Display for ExpressionRaw {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("unknown 0x")?;
+        for b in &self.0 {
+            write!(f, "{:02x}", b)?;
+        }
+        Ok(())
+    }
+}
+
 // Because we loose the name of the expression when parsing, this is the only expression
 // where deserializing a message and then reserializing it is invalid
 impl Expression for ExpressionRaw {
@@ -1,3 +1,5 @@
+use std::fmt::{self, Display, Formatter};
+
 use rustables_macros::{nfnetlink_enum, nfnetlink_struct};
 
 use super::{Expression, Register};
@@ -6,7 +8,26 @@ use crate::{
     ProtocolFamily,
 };
 
+bitflags::bitflags! {
+    /// The flags of a NAT range (`NF_NAT_RANGE_*` in the kernel), used by [`Nat`] and
+    /// [`Masquerade`](super::Masquerade).
+    pub struct NatFlags: u32 {
+        /// The range holds addresses.
+        const MAP_IPS = 1;
+        /// The range holds ports.
+        const PROTO_SPECIFIED = 2;
+        /// Picks the ports at random, seeded per destination.
+        const PROTO_RANDOM = 4;
+        /// Gives a client the same source address for every connection.
+        const PERSISTENT = 8;
+        /// Picks the ports fully at random.
+        const PROTO_RANDOM_FULLY = 16;
+    }
+}
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 #[nfnetlink_enum(i32)]
 pub enum NatType {
     /// Source NAT. Changes the source address of a packet.
@@ -19,6 +40,7 @@ pub enum NatType {
 /// port) of packets.
 #[derive(Default, Debug, Clone, PartialEq, Eq)]
 #[nfnetlink_struct(nested = true)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Nat {
     #[field(sys::NFTA_NAT_TYPE)]
     pub nat_type: NatType,
@@ -26,8 +48,42 @@ pub struct Nat {
     pub family: ProtocolFamily,
     #[field(sys::NFTA_NAT_REG_ADDR_MIN)]
     pub ip_register: Register,
+    #[field(sys::NFTA_NAT_REG_ADDR_MAX)]
+    pub ip_max_register: Register,
     #[field(sys::NFTA_NAT_REG_PROTO_MIN)]
     pub port_register: Register,
+    #[field(sys::NFTA_NAT_REG_PROTO_MAX)]
+    pub port_max_register: Register,
+    #[field(sys::NFTA_NAT_FLAGS)]
+    pub flags: u32,
+}
+
+impl Nat {
+    /// Translates the addresses to the range whose bounds are loaded in `min` and `max`.
+    pub fn with_address_range(self, min: Register, max: Register) -> Self {
+        self.with_nat_flags(NatFlags::MAP_IPS)
+            .with_ip_register(min)
+            .with_ip_max_register(max)
+    }
+
+    /// Translates the ports to the range whose bounds are loaded (in network byte order) in
+    /// `min` and `max`.
+    pub fn with_port_range(self, min: Register, max: Register) -> Self {
+        self.with_nat_flags(NatFlags::PROTO_SPECIFIED)
+            .with_port_register(min)
+            .with_port_max_register(max)
+    }
+
+    /// Adds `flags` to the flags of the translation, e.g. [`NatFlags::PERSISTENT`].
+    pub fn with_nat_flags(self, flags: NatFlags) -> Self {
+        let flags = self.flags.unwrap_or(0) | flags.bits();
+        self.with_flags(flags)
+    }
+
+    /// The flags of the translation, ignoring the ones unknown to this library.
+    pub fn get_nat_flags(&self) -> Option<NatFlags> {
+        self.flags.map(NatFlags::from_bits_truncate)
+    }
 }
 
 impl Expression for Nat {
@@ -35,3 +91,32 @@ impl Expression for Nat {
         "nat"
     }
 }
+
+impl Display for Nat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self.nat_type {
+            Some(NatType::SNat) => "snat",
+            Some(NatType::DNat) => "dnat",
+            None => "nat",
+        })?;
+        if let Some(family) = self.family {
+            write!(f, " {:?}", family)?;
+        }
+        if let Some(reg) = self.ip_register {
+            write!(f, " addr {}", reg)?;
+            if let Some(max) = self.ip_max_register {
+                write!(f, "-{}", max)?;
+            }
+        }
+        if let Some(reg) = self.port_register {
+            write!(f, " proto {}", reg)?;
+            if let Some(max) = self.port_max_register {
+                write!(f, "-{}", max)?;
+            }
+        }
+        if let Some(flags) = self.get_nat_flags() {
+            write!(f, " flags {:?}", flags)?;
+        }
+        Ok(())
+    }
+}
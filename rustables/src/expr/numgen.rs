@@ -0,0 +1,98 @@
+use std::fmt::{self, Display, Formatter};
+
+use rustables_macros::{nfnetlink_enum, nfnetlink_struct};
+
+use super::{Expression, Register};
+use crate::sys::{
+    NFTA_NG_DREG, NFTA_NG_MODULUS, NFTA_NG_OFFSET, NFTA_NG_TYPE, NFT_NG_INCREMENTAL, NFT_NG_RANDOM,
+};
+
+/// How a [`Numgen`] expression generates its numbers.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+#[nfnetlink_enum(u32)]
+pub enum NumgenType {
+    /// A counter incremented for every packet, for round-robin.
+    Incremental = NFT_NG_INCREMENTAL,
+    Random = NFT_NG_RANDOM,
+}
+
+/// Loads a number generated for every packet, modulo [`Numgen::get_modulus`] and shifted by
+/// [`Numgen::get_offset`], as a 32-bit integer in host byte order. Looking the number up in a map
+/// of addresses spreads the connections across several backends, e.g. to load balance with a
+/// `dnat` rule:
+///
+/// ```ignore
+/// let (backends, mut elements) = SetBuilder::<[u8; 4]>::new_anonymous(&table)?
+///     .with_key_type(DataTypeId::Integer)
+///     .map_to(DataTypeId::IpAddr, 4)
+///     .finish();
+/// elements.add_mapping(&0u32.to_ne_bytes(), Ipv4Addr::new(10, 0, 0, 1).octets());
+/// elements.add_mapping(&1u32.to_ne_bytes(), Ipv4Addr::new(10, 0, 0, 2).octets());
+/// let rule = Rule::new(&chain)?
+///     .with_expr(Numgen::incremental(2))
+///     .with_expr(Lookup::new(&backends)?.with_dreg(Register::Reg1))
+///     .with_expr(Nat::default().with_nat_type(NatType::DNat).with_family(ProtocolFamily::Ipv4)
+///         .with_ip_register(Register::Reg1));
+/// ```
+///
+/// Only the first packet of a connection goes through the `nat` chains, so the other packets
+/// follow it to the same backend.
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+#[nfnetlink_struct]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Numgen {
+    #[field(NFTA_NG_DREG)]
+    dreg: Register,
+    #[field(NFTA_NG_MODULUS)]
+    modulus: u32,
+    #[field(NFTA_NG_TYPE)]
+    ng_type: NumgenType,
+    #[field(NFTA_NG_OFFSET)]
+    offset: u32,
+}
+
+impl Numgen {
+    /// Loads 0, 1, ..., `modulus - 1`, 0, ... in `Reg1`, one number per packet.
+    pub fn incremental(modulus: u32) -> Self {
+        Numgen::default()
+            .with_ng_type(NumgenType::Incremental)
+            .with_modulus(modulus)
+            .with_dreg(Register::Reg1)
+    }
+
+    /// Loads a random number below `modulus` in `Reg1`.
+    pub fn random(modulus: u32) -> Self {
+        Numgen::default()
+            .with_ng_type(NumgenType::Random)
+            .with_modulus(modulus)
+            .with_dreg(Register::Reg1)
+    }
+}
+
+impl Expression for Numgen {
+    fn get_name() -> &'static str {
+        "numgen"
+    }
+}
+
+impl Display for Numgen {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self.ng_type {
+            Some(NumgenType::Incremental) => "numgen inc",
+            Some(NumgenType::Random) => "numgen random",
+            None => "numgen",
+        })?;
+        if let Some(modulus) = self.modulus {
+            write!(f, " mod {}", modulus)?;
+        }
+        if let Some(offset) = self.offset {
+            write!(f, " offset {}", offset)?;
+        }
+        if let Some(dreg) = self.dreg {
+            write!(f, " -> {}", dreg)?;
+        }
+        Ok(())
+    }
+}
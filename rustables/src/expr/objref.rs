@@ -0,0 +1,86 @@
+use std::fmt::{self, Display, Formatter};
+
+use rustables_macros::nfnetlink_struct;
+
+use super::{Expression, OptDisplay, Register};
+use crate::error::BuilderError;
+use crate::obj::ObjectType;
+use crate::sys::{
+    NFTA_OBJREF_IMM_NAME, NFTA_OBJREF_IMM_TYPE, NFTA_OBJREF_SET_ID, NFTA_OBJREF_SET_NAME,
+    NFTA_OBJREF_SET_SREG,
+};
+use crate::Set;
+
+/// Applies a stateful object (counter, quota, ...) of the table to the packet.
+///
+/// The object is either named directly, or looked up in an object map (see
+/// [`SetBuilder::map_to_objects`]) with the key loaded in a register, which lets each client use
+/// its own named counter with a single rule.
+///
+/// [`SetBuilder::map_to_objects`]: crate::set::SetBuilder::map_to_objects
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+#[nfnetlink_struct]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Objref {
+    #[field(NFTA_OBJREF_IMM_TYPE)]
+    imm_type: ObjectType,
+    #[field(NFTA_OBJREF_IMM_NAME)]
+    imm_name: String,
+    #[field(NFTA_OBJREF_SET_SREG)]
+    set_sreg: Register,
+    #[field(NFTA_OBJREF_SET_NAME)]
+    set_name: String,
+    #[field(NFTA_OBJREF_SET_ID)]
+    set_id: u32,
+}
+
+impl Objref {
+    /// References the object `name`, of type `obj_type`.
+    pub fn named(obj_type: ObjectType, name: impl Into<String>) -> Self {
+        Objref::default()
+            .with_imm_type(obj_type)
+            .with_imm_name(name.into())
+    }
+
+    /// References the object associated, in the object map `map`, to the key loaded in register
+    /// 1. Fails if `map` is not an object map.
+    pub fn from_map(map: &Set) -> Result<Self, BuilderError> {
+        if !map.is_object_map() {
+            return Err(BuilderError::NotAnObjectMap);
+        }
+        let mut res = Objref::default()
+            .with_set_name(map.get_name().ok_or(BuilderError::MissingSetName)?)
+            .with_set_sreg(Register::Reg1);
+
+        if let Some(id) = map.get_id() {
+            res.set_set_id(*id);
+        }
+
+        Ok(res)
+    }
+}
+
+impl Expression for Objref {
+    fn get_name() -> &'static str {
+        "objref"
+    }
+}
+
+impl Display for Objref {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.set_name {
+            Some(set) => write!(
+                f,
+                "objref {} map @{}",
+                OptDisplay(self.set_sreg.as_ref()),
+                set
+            ),
+            None => write!(
+                f,
+                "objref {} {:?}",
+                OptDisplay(self.imm_type.as_ref()),
+                self.imm_name.as_deref().unwrap_or("?")
+            ),
+        }
+    }
+}
@@ -1,6 +1,8 @@
+use std::fmt::{self, Display, Formatter};
+
 use rustables_macros::nfnetlink_struct;
 
-use super::{Expression, Register};
+use super::{Expression, OptDisplay, Register};
 use crate::{
     error::DecodeError,
     sys::{self, NFT_PAYLOAD_LL_HEADER, NFT_PAYLOAD_NETWORK_HEADER, NFT_PAYLOAD_TRANSPORT_HEADER},
@@ -9,6 +11,7 @@ use crate::{
 /// Payload expressions refer to data from the packet's payload.
 #[derive(Default, Debug, Copy, Clone, PartialEq, Eq)]
 #[nfnetlink_struct(nested = true)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Payload {
     #[field(sys::NFTA_PAYLOAD_DREG)]
     dreg: Register,
@@ -28,8 +31,37 @@ impl Expression for Payload {
     }
 }
 
+impl Display for Payload {
+    /// Displays the payload like the raw payload expressions of nft, with the offset and length in
+    /// bits, e.g. `payload @transport,16,16 -> reg1` for the TCP destination port.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("payload @")?;
+        match self.base {
+            Some(NFT_PAYLOAD_LL_HEADER) => f.write_str("ll")?,
+            Some(NFT_PAYLOAD_NETWORK_HEADER) => f.write_str("network")?,
+            Some(NFT_PAYLOAD_TRANSPORT_HEADER) => f.write_str("transport")?,
+            base => write!(f, "{}", OptDisplay(base.as_ref()))?,
+        }
+        write!(
+            f,
+            ",{},{}",
+            // in u64, as the offsets and lengths are u32 numbers of bytes
+            OptDisplay(self.offset.map(|o| u64::from(o) * 8).as_ref()),
+            OptDisplay(self.len.map(|l| u64::from(l) * 8).as_ref())
+        )?;
+        if let Some(dreg) = self.dreg {
+            write!(f, " -> {}", dreg)?;
+        }
+        if let Some(sreg) = self.sreg {
+            write!(f, " <- {}", sreg)?;
+        }
+        Ok(())
+    }
+}
+
 /// Payload expressions refer to data from the packet's payload.
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum HighLevelPayload {
     LinkLayer(LLHeaderField),
     Network(NetworkHeaderField),
@@ -58,6 +90,7 @@ impl HighLevelPayload {
 
 /// Payload expressions refer to data from the packet's payload.
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PayloadType {
     LinkLayer(LLHeaderField),
     Network,
@@ -94,6 +127,8 @@ pub trait HeaderField {
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 #[non_exhaustive]
 pub enum LLHeaderField {
     Daddr,
@@ -133,6 +168,7 @@ impl LLHeaderField {
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum NetworkHeaderField {
     IPv4(IPv4HeaderField),
     IPv6(IPv6HeaderField),
@@ -157,6 +193,8 @@ impl HeaderField for NetworkHeaderField {
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 #[non_exhaustive]
 pub enum IPv4HeaderField {
     Ttl,
@@ -200,6 +238,8 @@ impl IPv4HeaderField {
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 #[non_exhaustive]
 pub enum IPv6HeaderField {
     NextHeader,
@@ -243,10 +283,12 @@ impl IPv6HeaderField {
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[non_exhaustive]
 pub enum TransportHeaderField {
     Tcp(TCPHeaderField),
     Udp(UDPHeaderField),
+    ICMP(ICMPHeaderField),
     ICMPv6(ICMPv6HeaderField),
 }
 
@@ -256,6 +298,7 @@ impl HeaderField for TransportHeaderField {
         match *self {
             Tcp(ref f) => f.offset(),
             Udp(ref f) => f.offset(),
+            ICMP(ref f) => f.offset(),
             ICMPv6(ref f) => f.offset(),
         }
     }
@@ -265,16 +308,20 @@ impl HeaderField for TransportHeaderField {
         match *self {
             Tcp(ref f) => f.len(),
             Udp(ref f) => f.len(),
+            ICMP(ref f) => f.len(),
             ICMPv6(ref f) => f.len(),
         }
     }
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 #[non_exhaustive]
 pub enum TCPHeaderField {
     Sport,
     Dport,
+    Flags,
 }
 
 impl HeaderField for TCPHeaderField {
@@ -283,6 +330,7 @@ impl HeaderField for TCPHeaderField {
         match *self {
             Sport => 0,
             Dport => 2,
+            Flags => 13,
         }
     }
 
@@ -291,6 +339,7 @@ impl HeaderField for TCPHeaderField {
         match *self {
             Sport => 2,
             Dport => 2,
+            Flags => 1,
         }
     }
 }
@@ -300,12 +349,15 @@ impl TCPHeaderField {
         Ok(match (offset, len) {
             (0, 2) => Self::Sport,
             (2, 2) => Self::Dport,
+            (13, 1) => Self::Flags,
             _ => return Err(DecodeError::UnknownTCPHeaderField(offset, len)),
         })
     }
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 #[non_exhaustive]
 pub enum UDPHeaderField {
     Sport,
@@ -345,6 +397,49 @@ impl UDPHeaderField {
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+#[non_exhaustive]
+pub enum ICMPHeaderField {
+    Type,
+    Code,
+    Checksum,
+}
+
+impl HeaderField for ICMPHeaderField {
+    fn offset(&self) -> u32 {
+        use self::ICMPHeaderField::*;
+        match *self {
+            Type => 0,
+            Code => 1,
+            Checksum => 2,
+        }
+    }
+
+    fn len(&self) -> u32 {
+        use self::ICMPHeaderField::*;
+        match *self {
+            Type => 1,
+            Code => 1,
+            Checksum => 2,
+        }
+    }
+}
+
+impl ICMPHeaderField {
+    pub fn from_raw_data(offset: u32, len: u32) -> Result<Self, DecodeError> {
+        Ok(match (offset, len) {
+            (0, 1) => Self::Type,
+            (1, 1) => Self::Code,
+            (2, 2) => Self::Checksum,
+            _ => return Err(DecodeError::UnknownICMPHeaderField(offset, len)),
+        })
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 #[non_exhaustive]
 pub enum ICMPv6HeaderField {
     Type,
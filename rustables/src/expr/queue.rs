@@ -0,0 +1,91 @@
+use std::fmt::{self, Display, Formatter};
+
+use rustables_macros::nfnetlink_struct;
+
+use super::{Expression, OptDisplay};
+use crate::groups::QueueNum;
+use crate::sys::{
+    NFTA_QUEUE_FLAGS, NFTA_QUEUE_NUM, NFTA_QUEUE_TOTAL, NFT_QUEUE_FLAG_BYPASS,
+    NFT_QUEUE_FLAG_CPU_FANOUT,
+};
+
+bitflags::bitflags! {
+    /// The flags of a [`Queue`] expression.
+    pub struct QueueFlags: u16 {
+        /// Accepts the packets when no program listens on the queue, instead of dropping them.
+        const BYPASS = NFT_QUEUE_FLAG_BYPASS as u16;
+        /// Spreads the packets over the queues by the CPU handling them, instead of by flow.
+        const CPU_FANOUT = NFT_QUEUE_FLAG_CPU_FANOUT as u16;
+    }
+}
+
+/// A queue expression hands the packets over to a userspace program listening on an NFQUEUE
+/// queue, which then accepts or drops them. This is a verdict: the rule stops there.
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+#[nfnetlink_struct]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Queue {
+    #[field(NFTA_QUEUE_NUM)]
+    num: QueueNum,
+    /// The number of queues the packets are spread over, starting at `num`.
+    #[field(NFTA_QUEUE_TOTAL)]
+    total: u16,
+    #[field(NFTA_QUEUE_FLAGS)]
+    flags: u16,
+}
+
+impl Queue {
+    /// Sends the packets to the queue `num`, the equivalent of `queue to 3` in nft. Queue numbers
+    /// held in wider integers can be checked with [`QueueNum::new`].
+    pub fn new(num: impl Into<QueueNum>) -> Self {
+        Queue::default().with_num(num.into()).with_total(1u16)
+    }
+
+    /// Spreads the packets over the `total` queues starting at the one of the expression, e.g.
+    /// `queue to 3-6` in nft with a `total` of 4.
+    pub fn spread(self, total: u16) -> Self {
+        self.with_total(total)
+    }
+
+    /// Adds `flags` to the flags of the expression, e.g. [`QueueFlags::BYPASS`].
+    pub fn with_queue_flags(self, flags: QueueFlags) -> Self {
+        let flags = self.flags.unwrap_or(0) | flags.bits();
+        self.with_flags(flags)
+    }
+
+    /// The flags of the expression, ignoring the ones unknown to this library.
+    pub fn get_queue_flags(&self) -> Option<QueueFlags> {
+        self.flags.map(QueueFlags::from_bits_truncate)
+    }
+}
+
+impl Expression for Queue {
+    fn get_name() -> &'static str {
+        "queue"
+    }
+}
+
+impl Display for Queue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("queue")?;
+        let flags = self.get_queue_flags().unwrap_or_else(QueueFlags::empty);
+        let names: Vec<_> = [
+            (QueueFlags::BYPASS, "bypass"),
+            (QueueFlags::CPU_FANOUT, "fanout"),
+        ]
+        .into_iter()
+        .filter(|(flag, _)| flags.contains(*flag))
+        .map(|(_, name)| name)
+        .collect();
+        if !names.is_empty() {
+            write!(f, " flags {}", names.join(","))?;
+        }
+        write!(f, " to {}", OptDisplay(self.num.as_ref()))?;
+        match (self.num, self.total) {
+            (Some(num), Some(total)) if total > 1 => {
+                write!(f, "-{}", u32::from(num.number()) + u32::from(total) - 1)
+            }
+            _ => Ok(()),
+        }
+    }
+}
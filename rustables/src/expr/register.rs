@@ -1,4 +1,4 @@
-use std::fmt::Debug;
+use std::fmt::{self, Debug, Display, Formatter};
 
 use rustables_macros::nfnetlink_enum;
 
@@ -7,6 +7,8 @@ use crate::sys::{NFT_REG_1, NFT_REG_2, NFT_REG_3, NFT_REG_4, NFT_REG_VERDICT};
 /// A netfilter data register. The expressions store and read data to and from these when
 /// evaluating rule statements.
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 #[nfnetlink_enum(u32)]
 pub enum Register {
     Verdict = NFT_REG_VERDICT,
@@ -15,3 +17,15 @@ pub enum Register {
     Reg3 = NFT_REG_3,
     Reg4 = NFT_REG_4,
 }
+
+impl Display for Register {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Register::Verdict => "verdict",
+            Register::Reg1 => "reg1",
+            Register::Reg2 => "reg2",
+            Register::Reg3 => "reg3",
+            Register::Reg4 => "reg4",
+        })
+    }
+}
@@ -1,3 +1,5 @@
+use std::fmt::{self, Display, Formatter};
+
 use rustables_macros::{nfnetlink_enum, nfnetlink_struct};
 
 use crate::sys;
@@ -12,16 +14,89 @@ impl Expression for Reject {
 
 #[derive(Clone, PartialEq, Eq, Default, Debug)]
 #[nfnetlink_struct]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// A reject expression that defines the type of rejection message sent when discarding a packet.
 pub struct Reject {
     #[field(sys::NFTA_REJECT_TYPE, name_in_functions = "type")]
     reject_type: RejectType,
-    #[field(sys::NFTA_REJECT_ICMP_CODE)]
-    icmp_code: IcmpCode,
+    /// The code of the ICMP error: an [`IcmpCode`] for [`RejectType::IcmpxUnreach`], or an ICMP
+    /// (in the ip family) or ICMPv6 (in the ip6 family) code for [`RejectType::IcmpUnreach`].
+    #[field(sys::NFTA_REJECT_ICMP_CODE, name_in_functions = "code")]
+    icmp_code: u8,
+}
+
+impl Reject {
+    /// Rejects the packets with the ICMP or ICMPv6 error matching their family, e.g. `reject with
+    /// icmpx port-unreachable` in nft. This is the only ICMP rejection of the inet, bridge and
+    /// netdev families.
+    pub fn icmpx(code: IcmpCode) -> Self {
+        Reject::default()
+            .with_type(RejectType::IcmpxUnreach)
+            .with_icmp_code(code)
+    }
+
+    /// Rejects the packets with the ICMP error `code` in the ip family (e.g. 3, `reject with icmp
+    /// port-unreachable` in nft), or the ICMPv6 error `code` in the ip6 family (e.g. 4, `reject
+    /// with icmpv6 port-unreachable`). The kernel ignores this type in the other families.
+    pub fn icmp(code: u8) -> Self {
+        Reject::default()
+            .with_type(RejectType::IcmpUnreach)
+            .with_code(code)
+    }
+
+    /// Sets the code of an [`RejectType::IcmpxUnreach`] rejection.
+    pub fn set_icmp_code(&mut self, code: IcmpCode) {
+        self.set_code(code as u8);
+    }
+
+    /// Same as [`Reject::set_icmp_code`], by value.
+    pub fn with_icmp_code(mut self, code: IcmpCode) -> Self {
+        self.set_icmp_code(code);
+        self
+    }
+
+    /// The code of an [`RejectType::IcmpxUnreach`] rejection, or `None` for the other types.
+    pub fn get_icmp_code(&self) -> Option<IcmpCode> {
+        match self.reject_type {
+            Some(RejectType::IcmpxUnreach) => IcmpCode::try_from(*self.get_code()?).ok(),
+            _ => None,
+        }
+    }
 }
 
+/// The codes of the [`RejectType::IcmpxUnreach`] rejections, named like in nft.
+pub(crate) const ICMPX_CODE_NAMES: [(u8, &str); 4] = [
+    (IcmpCode::NoRoute as u8, "no-route"),
+    (IcmpCode::PortUnreach as u8, "port-unreachable"),
+    (IcmpCode::HostUnreach as u8, "host-unreachable"),
+    (IcmpCode::AdminProhibited as u8, "admin-prohibited"),
+];
+
+/// The ICMP codes of the rejections of the ip family, named like in nft.
+pub(crate) const ICMP_CODE_NAMES: [(u8, &str); 7] = [
+    (0, "net-unreachable"),
+    (1, "host-unreachable"),
+    (2, "prot-unreachable"),
+    (3, "port-unreachable"),
+    (9, "net-prohibited"),
+    (10, "host-prohibited"),
+    (13, "admin-prohibited"),
+];
+
+/// The ICMPv6 codes of the rejections of the ip6 family, named like in nft.
+pub(crate) const ICMPV6_CODE_NAMES: [(u8, &str); 6] = [
+    (0, "no-route"),
+    (1, "admin-prohibited"),
+    (3, "addr-unreachable"),
+    (4, "port-unreachable"),
+    (5, "policy-fail"),
+    (6, "reject-route"),
+];
+
 /// An ICMP reject code.
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 #[nfnetlink_enum(u32)]
 pub enum RejectType {
     IcmpUnreach = sys::NFT_REJECT_ICMP_UNREACH,
@@ -31,6 +106,8 @@ pub enum RejectType {
 
 /// An ICMP reject code.
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 #[nfnetlink_enum(u8)]
 pub enum IcmpCode {
     NoRoute = sys::NFT_REJECT_ICMPX_NO_ROUTE,
@@ -38,3 +115,24 @@ pub enum IcmpCode {
     HostUnreach = sys::NFT_REJECT_ICMPX_HOST_UNREACH,
     AdminProhibited = sys::NFT_REJECT_ICMPX_ADMIN_PROHIBITED,
 }
+
+impl Display for Reject {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self.reject_type {
+            Some(RejectType::IcmpUnreach) => "reject icmp",
+            Some(RejectType::TcpRst) => "reject tcp reset",
+            Some(RejectType::IcmpxUnreach) => "reject icmpx",
+            None => "reject",
+        })?;
+        match (self.get_icmp_code(), self.icmp_code) {
+            (Some(code), _) => f.write_str(match code {
+                IcmpCode::NoRoute => " no-route",
+                IcmpCode::PortUnreach => " port-unreachable",
+                IcmpCode::HostUnreach => " host-unreachable",
+                IcmpCode::AdminProhibited => " admin-prohibited",
+            }),
+            (None, Some(code)) => write!(f, " code {}", code),
+            (None, None) => Ok(()),
+        }
+    }
+}
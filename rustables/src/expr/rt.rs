@@ -0,0 +1,73 @@
+use std::fmt::{self, Display, Formatter};
+
+use rustables_macros::{nfnetlink_enum, nfnetlink_struct};
+
+use super::{Expression, OptDisplay, Register};
+use crate::sys::{
+    NFTA_RT_DREG, NFTA_RT_KEY, NFT_RT_CLASSID, NFT_RT_NEXTHOP4, NFT_RT_NEXTHOP6, NFT_RT_TCPMSS,
+    NFT_RT_XFRM,
+};
+
+/// The routing information retrieved by a [`Rt`] expression.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+#[nfnetlink_enum(u32)]
+pub enum RtKey {
+    /// The realm of the route.
+    ClassId = NFT_RT_CLASSID,
+    /// The IPv4 address of the next hop.
+    NextHop4 = NFT_RT_NEXTHOP4,
+    /// The IPv6 address of the next hop.
+    NextHop6 = NFT_RT_NEXTHOP6,
+    /// The TCP maximum segment size allowed by the MTU of the route, in host byte order.
+    TcpMss = NFT_RT_TCPMSS,
+    /// Whether the packet will be processed by IPsec.
+    Xfrm = NFT_RT_XFRM,
+}
+
+/// Loads information about the route of the packet, which is only known after the routing
+/// decision (i.e. in the `forward`, `output` and `postrouting` hooks).
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+#[nfnetlink_struct]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rt {
+    #[field(NFTA_RT_DREG)]
+    dreg: Register,
+    #[field(NFTA_RT_KEY)]
+    key: RtKey,
+}
+
+impl Rt {
+    pub fn new(key: RtKey) -> Self {
+        Rt::default().with_dreg(Register::Reg1).with_key(key)
+    }
+}
+
+impl Expression for Rt {
+    fn get_name() -> &'static str {
+        "rt"
+    }
+}
+
+impl Display for RtKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            RtKey::ClassId => "classid",
+            RtKey::NextHop4 => "nexthop4",
+            RtKey::NextHop6 => "nexthop6",
+            RtKey::TcpMss => "tcpmss",
+            RtKey::Xfrm => "ipsec",
+        })
+    }
+}
+
+impl Display for Rt {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "rt {}", OptDisplay(self.key.as_ref()))?;
+        if let Some(dreg) = self.dreg {
+            write!(f, " -> {}", dreg)?;
+        }
+        Ok(())
+    }
+}
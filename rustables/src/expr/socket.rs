@@ -0,0 +1,165 @@
+use std::fmt::{self, Display, Formatter};
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+
+use rustables_macros::{nfnetlink_enum, nfnetlink_struct};
+
+use super::{Expression, OptDisplay, Register};
+#[cfg(feature = "socket")]
+use crate::error::QueryError;
+use crate::sys::{
+    NFTA_SOCKET_DREG, NFTA_SOCKET_KEY, NFTA_SOCKET_LEVEL, NFT_SOCKET_CGROUPV2, NFT_SOCKET_MARK,
+    NFT_SOCKET_TRANSPARENT, NFT_SOCKET_WILDCARD,
+};
+
+/// The information about the local socket of the packet retrieved by a [`Socket`] expression.
+///
+/// The keys were added to the kernel over time, and older kernels reject the rules using the
+/// newer ones with `EOPNOTSUPP`: [`is_socket_key_supported`] tells whether the running kernel
+/// supports a key before building a ruleset around it.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+#[nfnetlink_enum(u32)]
+pub enum SocketKey {
+    /// Whether the socket has the `IP_TRANSPARENT` option, on one byte.
+    Transparent = NFT_SOCKET_TRANSPARENT,
+    /// The mark of the socket (`SO_MARK`), in host byte order.
+    Mark = NFT_SOCKET_MARK,
+    /// Whether the socket is bound to the wildcard address, on one byte.
+    Wildcard = NFT_SOCKET_WILDCARD,
+    /// The id of the cgroup v2 of the process owning the socket, or of its ancestor at the level
+    /// of the expression, on 8 bytes in host byte order. Needs Linux 5.13.
+    Cgroupv2 = NFT_SOCKET_CGROUPV2,
+}
+
+/// Loads information about the local socket the packet belongs to. Packets without a local socket
+/// (e.g. forwarded packets) do not match the rule.
+///
+/// The expression is only allowed in the `prerouting`, `input` and `output` hooks, and in the
+/// chains jumped to from them.
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+#[nfnetlink_struct]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Socket {
+    #[field(NFTA_SOCKET_KEY)]
+    key: SocketKey,
+    #[field(NFTA_SOCKET_DREG)]
+    dreg: Register,
+    /// The level of the ancestor cgroup loaded by [`SocketKey::Cgroupv2`], 0 being the root of
+    /// the hierarchy.
+    #[field(NFTA_SOCKET_LEVEL)]
+    level: u32,
+}
+
+impl Socket {
+    pub fn new(key: SocketKey) -> Self {
+        Socket::default().with_key(key).with_dreg(Register::Reg1)
+    }
+
+    /// Loads the id of the ancestor, at `level`, of the cgroup of the process owning the socket.
+    ///
+    /// The ancestor at level 1 of a process in `/sys/fs/cgroup/system.slice/sshd.service` is
+    /// `system.slice`, so the level of a cgroup is the number of components of its path below the
+    /// mount point of the hierarchy. The kernel does not support levels above 255.
+    pub fn cgroupv2(level: u8) -> Self {
+        Socket::new(SocketKey::Cgroupv2).with_level(level as u32)
+    }
+}
+
+impl Expression for Socket {
+    fn get_name() -> &'static str {
+        "socket"
+    }
+}
+
+impl Display for SocketKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SocketKey::Transparent => "transparent",
+            SocketKey::Mark => "mark",
+            SocketKey::Wildcard => "wildcard",
+            SocketKey::Cgroupv2 => "cgroupv2",
+        })
+    }
+}
+
+impl Display for Socket {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "socket {}", OptDisplay(self.key.as_ref()))?;
+        if let Some(level) = self.level {
+            write!(f, " level {}", level)?;
+        }
+        if let Some(dreg) = self.dreg {
+            write!(f, " -> {}", dreg)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the id of the cgroup v2 at `path` (e.g. `/sys/fs/cgroup/system.slice`), as loaded by
+/// [`Socket::cgroupv2`]. The id of a cgroup is the inode number of its directory in the cgroup2
+/// filesystem.
+pub fn cgroupv2_id(path: impl AsRef<Path>) -> std::io::Result<u64> {
+    Ok(std::fs::metadata(path)?.ino())
+}
+
+/// Checks whether the running kernel supports the socket expression with `key`.
+///
+/// This sends a batch adding a rule with the expression to a temporary table, then deleting the
+/// table: the kernel accepts or rejects the whole batch, so the ruleset is left untouched either
+/// way. This returns `Ok(false)` when the kernel does not know the key (`EOPNOTSUPP`) or the
+/// expression itself (`ENOENT`, when the `nft_socket` module is missing).
+///
+/// Tools that need cgroup scoping on kernels without [`SocketKey::Cgroupv2`] can fall back to the
+/// `net_cls` classid of the cgroup v1 hierarchy ([`MetaType::Cgroup`]), or skip their rules and
+/// warn the user rather than failing the whole ruleset.
+///
+/// [`MetaType::Cgroup`]: super::MetaType::Cgroup
+#[cfg(feature = "socket")]
+pub fn is_socket_key_supported(key: SocketKey) -> Result<bool, QueryError> {
+    use crate::query::{socket_close_wrapper, NfNetlinkSocket};
+
+    let mut supported = false;
+    socket_close_wrapper(NfNetlinkSocket::new()?, |sock| {
+        supported = is_socket_key_supported_with_socket(sock, key)?;
+        Ok::<(), QueryError>(())
+    })?;
+    Ok(supported)
+}
+
+/// Same as [`is_socket_key_supported`], on the socket `sock`.
+#[cfg(feature = "socket")]
+pub fn is_socket_key_supported_with_socket(
+    sock: &crate::query::NfNetlinkSocket,
+    key: SocketKey,
+) -> Result<bool, QueryError> {
+    use nix::errno::Errno;
+
+    use crate::{Batch, Chain, MsgType, ProtocolFamily, Rule, Table};
+
+    let table = Table::new(ProtocolFamily::Inet).with_name("rustables-socket-probe");
+    let chain = Chain::new(&table).with_name("probe");
+    let expr = match key {
+        SocketKey::Cgroupv2 => Socket::cgroupv2(1),
+        key => Socket::new(key),
+    };
+    let mut batch = Batch::new();
+    batch.add(&table, MsgType::Add);
+    batch.add(&chain, MsgType::Add);
+    batch.add(&Rule::new(&chain)?.with_expr(expr), MsgType::Add);
+    batch.add(&table, MsgType::Del);
+    match batch.send_with_socket(sock) {
+        Ok(()) => Ok(true),
+        // the error designates the rule of the batch, which the kernel refused
+        Err(e)
+            if matches!(
+                e.netlink_report().map(|report| report.errno()),
+                Some(Errno::EOPNOTSUPP | Errno::ENOENT)
+            ) =>
+        {
+            Ok(false)
+        }
+        Err(e) => Err(e),
+    }
+}
@@ -0,0 +1,48 @@
+use std::fmt::{self, Display, Formatter};
+
+use rustables_macros::nfnetlink_struct;
+
+use super::{Expression, Register};
+use crate::sys::{NFTA_TPROXY_FAMILY, NFTA_TPROXY_REG_ADDR, NFTA_TPROXY_REG_PORT};
+use crate::ProtocolFamily;
+
+/// Diverts the packets to a local socket without changing their destination, for transparent
+/// proxies. The socket listens on the address and the port loaded in the registers, or on the
+/// original ones when the registers are not set. Only allowed in the `prerouting` hook, on TCP and
+/// UDP packets.
+#[derive(Clone, Default, Debug, PartialEq, Eq)]
+#[nfnetlink_struct(nested = true)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Tproxy {
+    /// The family of the address, or [`ProtocolFamily::Unspec`] in inet tables when only the
+    /// port is set.
+    #[field(NFTA_TPROXY_FAMILY)]
+    family: ProtocolFamily,
+    #[field(NFTA_TPROXY_REG_ADDR)]
+    addr_register: Register,
+    /// The register holding the port, in network byte order.
+    #[field(NFTA_TPROXY_REG_PORT)]
+    port_register: Register,
+}
+
+impl Expression for Tproxy {
+    fn get_name() -> &'static str {
+        "tproxy"
+    }
+}
+
+impl Display for Tproxy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("tproxy")?;
+        if let Some(family) = self.family {
+            write!(f, " {:?}", family)?;
+        }
+        if let Some(reg) = self.addr_register {
+            write!(f, " addr {}", reg)?;
+        }
+        if let Some(reg) = self.port_register {
+            write!(f, " port {}", reg)?;
+        }
+        Ok(())
+    }
+}
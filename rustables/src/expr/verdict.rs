@@ -1,13 +1,18 @@
-use std::fmt::Debug;
+use std::fmt::{self, Debug, Display, Formatter};
 
 use libc::{NF_ACCEPT, NF_DROP, NF_QUEUE};
 use rustables_macros::{nfnetlink_enum, nfnetlink_struct};
 
+use super::OptDisplay;
+use crate::error::BuilderError;
 use crate::sys::{
     NFTA_VERDICT_CHAIN, NFTA_VERDICT_CODE, NFT_BREAK, NFT_CONTINUE, NFT_GOTO, NFT_JUMP, NFT_RETURN,
 };
+use crate::Chain;
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 #[nfnetlink_enum(i32)]
 pub enum VerdictType {
     Drop = NF_DROP,
@@ -22,16 +27,61 @@ pub enum VerdictType {
 
 #[nfnetlink_struct(nested = true)]
 #[derive(Clone, PartialEq, Eq, Default, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Verdict {
     #[field(NFTA_VERDICT_CODE)]
     code: VerdictType,
     #[field(NFTA_VERDICT_CHAIN)]
     chain: String,
-    #[field(optional = true, crate::sys::NFTA_VERDICT_CHAIN_ID)]
+    #[field(
+        optional = true,
+        stub_if_missing = true,
+        crate::sys::NFTA_VERDICT_CHAIN_ID
+    )]
     chain_id: u32,
 }
 
+impl Display for VerdictType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            VerdictType::Drop => "drop",
+            VerdictType::Accept => "accept",
+            VerdictType::Queue => "queue",
+            VerdictType::Continue => "continue",
+            VerdictType::Break => "break",
+            VerdictType::Jump => "jump",
+            VerdictType::Goto => "goto",
+            VerdictType::Return => "return",
+        })
+    }
+}
+
+impl Verdict {
+    /// A verdict of type `code` (a jump or a goto) to the chain whose id is `chain_id` in the
+    /// batch adding it, see [`Batch::jump_to_added`](crate::Batch::jump_to_added).
+    pub(crate) fn to_chain_id(code: VerdictType, chain_id: u32) -> Result<Self, BuilderError> {
+        // the kernel only looks the chain up by its id when the verdict has no chain name
+        let mut verdict = Verdict::default().with_code(code);
+        verdict.try_set_chain_id(chain_id)?;
+        Ok(verdict)
+    }
+}
+
+impl Display for Verdict {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", OptDisplay(self.code.as_ref()))?;
+        if let Some(chain) = &self.chain {
+            write!(f, " {}", chain)?;
+        } else if let Some(id) = self.get_chain_id() {
+            write!(f, " chain id {}", id)?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum VerdictKind {
     /// Silently drop the packet.
     Drop,
@@ -48,3 +98,52 @@ pub enum VerdictKind {
     },
     Return,
 }
+
+impl VerdictKind {
+    /// A jump to `chain`. Fails if the chain has no name.
+    ///
+    /// The target must belong to the table of the rule holding the verdict, which
+    /// [`Rule::jump`](crate::Rule::jump) checks.
+    pub fn jump_to(chain: &Chain) -> Result<Self, BuilderError> {
+        Ok(VerdictKind::Jump {
+            chain: Self::target_name(chain)?,
+        })
+    }
+
+    /// A goto to `chain`. Fails if the chain has no name.
+    ///
+    /// The target must belong to the table of the rule holding the verdict, which
+    /// [`Rule::goto`](crate::Rule::goto) checks.
+    pub fn goto_to(chain: &Chain) -> Result<Self, BuilderError> {
+        Ok(VerdictKind::Goto {
+            chain: Self::target_name(chain)?,
+        })
+    }
+
+    fn target_name(chain: &Chain) -> Result<String, BuilderError> {
+        chain
+            .get_name()
+            .cloned()
+            .ok_or(BuilderError::MissingChainInformationError)
+    }
+}
+
+impl From<VerdictKind> for Verdict {
+    fn from(kind: VerdictKind) -> Self {
+        let code = match kind {
+            VerdictKind::Drop => VerdictType::Drop,
+            VerdictKind::Accept => VerdictType::Accept,
+            VerdictKind::Queue => VerdictType::Queue,
+            VerdictKind::Continue => VerdictType::Continue,
+            VerdictKind::Break => VerdictType::Break,
+            VerdictKind::Jump { .. } => VerdictType::Jump,
+            VerdictKind::Goto { .. } => VerdictType::Goto,
+            VerdictKind::Return => VerdictType::Return,
+        };
+        let mut verdict = Verdict::default().with_code(code);
+        if let VerdictKind::Jump { chain } | VerdictKind::Goto { chain } = kind {
+            verdict.set_chain(chain);
+        }
+        verdict
+    }
+}
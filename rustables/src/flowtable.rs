@@ -0,0 +1,167 @@
+use rustables_macros::{nfnetlink_object, nfnetlink_struct};
+
+use crate::error::BuilderError;
+#[cfg(feature = "socket")]
+use crate::error::QueryError;
+use crate::nlmsg::{without_use_count_and_handle, NfNetlinkObject};
+use crate::sys::{
+    NFTA_FLOWTABLE_FLAGS, NFTA_FLOWTABLE_HANDLE, NFTA_FLOWTABLE_HOOK, NFTA_FLOWTABLE_HOOK_DEVS,
+    NFTA_FLOWTABLE_HOOK_NUM, NFTA_FLOWTABLE_HOOK_PRIORITY, NFTA_FLOWTABLE_NAME,
+    NFTA_FLOWTABLE_TABLE, NFTA_FLOWTABLE_USE, NFT_FLOWTABLE_COUNTER, NFT_FLOWTABLE_HW_OFFLOAD,
+    NFT_MSG_DELFLOWTABLE, NFT_MSG_NEWFLOWTABLE,
+};
+use crate::{Batch, ChainPriority, DeviceList, MsgType, ProtocolFamily, Table};
+
+bitflags::bitflags! {
+    pub struct FlowtableFlags: u32 {
+        /// Offload the flows to the hardware, if the network devices support it.
+        const HW_OFFLOAD = NFT_FLOWTABLE_HW_OFFLOAD;
+        /// Count the packets and bytes of every flow.
+        const COUNTER = NFT_FLOWTABLE_COUNTER;
+    }
+}
+
+/// The hook of a flowtable: the flowtable is attached to the ingress hook of every device in
+/// `devices`.
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+#[nfnetlink_struct(nested = true)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FlowtableHook {
+    #[field(NFTA_FLOWTABLE_HOOK_NUM)]
+    class: u32,
+    #[field(NFTA_FLOWTABLE_HOOK_PRIORITY)]
+    priority: ChainPriority,
+    /// Names of the network devices.
+    #[field(NFTA_FLOWTABLE_HOOK_DEVS)]
+    devices: DeviceList,
+}
+
+impl FlowtableHook {
+    pub fn new<S: Into<String>>(
+        priority: ChainPriority,
+        devices: impl IntoIterator<Item = S>,
+    ) -> Self {
+        // the device names are checked by `Flowtable::validate`
+        FlowtableHook::default()
+            .with_class(libc::NF_NETDEV_INGRESS as u32)
+            .with_priority(priority)
+            .with_devices(DeviceList::new_unchecked(devices))
+    }
+}
+
+/// Abstraction over an nftables flowtable. Flowtables reside inside [`Table`]s, and allow the
+/// established flows to bypass the classic forwarding path.
+///
+/// The kernel happily creates a flowtable that is not bound to any device, which then never
+/// offloads anything. [`Flowtable::validate`] guards against this.
+#[nfnetlink_struct(derive_deserialize = false)]
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Flowtable {
+    family: ProtocolFamily,
+    #[field(NFTA_FLOWTABLE_TABLE)]
+    table: String,
+    #[field(NFTA_FLOWTABLE_NAME)]
+    name: String,
+    #[field(NFTA_FLOWTABLE_HOOK)]
+    hook: FlowtableHook,
+    #[field(NFTA_FLOWTABLE_USE)]
+    use_count: u32,
+    #[field(NFTA_FLOWTABLE_HANDLE)]
+    handle: u64,
+    #[field(NFTA_FLOWTABLE_FLAGS)]
+    flags: u32,
+}
+
+impl Flowtable {
+    /// Creates a new flowtable instance inside the given [`Table`].
+    pub fn new(table: &Table) -> Flowtable {
+        let mut flowtable = Flowtable {
+            family: table.get_family(),
+            ..Default::default()
+        };
+
+        if let Some(table_name) = table.get_name() {
+            flowtable.set_table(table_name);
+        }
+
+        flowtable
+    }
+
+    /// The flags of the flowtable, ignoring the ones unknown to this library.
+    pub fn get_flowtable_flags(&self) -> Option<FlowtableFlags> {
+        self.get_flags()
+            .map(|f| FlowtableFlags::from_bits_truncate(*f))
+    }
+
+    /// Checks that the flowtable is bound to at least one device, and that the device names are
+    /// valid and distinct.
+    pub fn validate(&self) -> Result<(), BuilderError> {
+        let devices = self
+            .get_hook()
+            .and_then(|hook| hook.get_devices())
+            .ok_or(BuilderError::MissingFlowtableDevices)?;
+        if devices.is_empty() {
+            return Err(BuilderError::MissingFlowtableDevices);
+        }
+        devices.validate()
+    }
+
+    /// Appends this flowtable to `batch`, after checking it with [`Flowtable::validate`].
+    pub fn add_to_batch(self, batch: &mut Batch) -> Result<Self, BuilderError> {
+        self.validate()?;
+        batch.add(&self, crate::MsgType::Add);
+        Ok(self)
+    }
+}
+
+#[nfnetlink_object(add = NFT_MSG_NEWFLOWTABLE, del = NFT_MSG_DELFLOWTABLE, family_field = family)]
+impl NfNetlinkObject for Flowtable {
+    fn object_name(&self) -> Option<String> {
+        self.name.clone()
+    }
+
+    fn without_read_only_attributes(&self, msg_type: MsgType, _flags: u16) -> Option<Self> {
+        without_use_count_and_handle!(self, msg_type)
+    }
+
+    fn missing_attributes(&self, msg_type: MsgType) -> Vec<&'static str> {
+        let mut missing = Vec::new();
+        if self.table.is_none() {
+            missing.push("NFTA_FLOWTABLE_TABLE");
+        }
+        if self.name.is_none() {
+            missing.push("NFTA_FLOWTABLE_NAME");
+        }
+        if msg_type != MsgType::Del {
+            match &self.hook {
+                None => missing.push("NFTA_FLOWTABLE_HOOK"),
+                Some(hook) => {
+                    if hook.class.is_none() {
+                        missing.push("NFTA_FLOWTABLE_HOOK_NUM");
+                    }
+                    if hook.priority.is_none() {
+                        missing.push("NFTA_FLOWTABLE_HOOK_PRIORITY");
+                    }
+                }
+            }
+        }
+        missing
+    }
+}
+
+#[cfg(feature = "socket")]
+pub fn list_flowtables_for_table(table: &Table) -> Result<Vec<Flowtable>, QueryError> {
+    let mut result = Vec::new();
+    crate::query::list_objects_with_data(
+        crate::sys::NFT_MSG_GETFLOWTABLE as u16,
+        &|flowtable: Flowtable, flowtables: &mut Vec<Flowtable>| {
+            flowtables.push(flowtable);
+            Ok(())
+        },
+        // only retrieve the flowtables of the table
+        Some(&Flowtable::new(table)),
+        &mut result,
+    )?;
+    Ok(result)
+}
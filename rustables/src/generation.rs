@@ -0,0 +1,67 @@
+use std::fmt::{self, Display, Formatter};
+
+use rustables_macros::{nfnetlink_object, nfnetlink_struct};
+
+#[cfg(feature = "socket")]
+use crate::error::QueryError;
+use crate::sys::{NFTA_GEN_ID, NFTA_GEN_PROC_NAME, NFTA_GEN_PROC_PID, NFT_MSG_NEWGEN};
+
+/// A generation of the ruleset. The kernel bumps the generation id on every committed batch, and
+/// notifies the monitors with a `NFT_MSG_NEWGEN` message after the notifications of the changes
+/// of the batch (see [`RawEvent::generation`]).
+///
+/// Since Linux 5.1, the generation also identifies the process that committed the batch, which
+/// lets daemons attribute the changes they did not make themselves.
+///
+/// The generation cannot be created or deleted: both message types of the object are
+/// `NFT_MSG_NEWGEN`, the type of the notifications and of the responses to [`get_generation`].
+///
+/// [`RawEvent::generation`]: crate::monitor::RawEvent::generation
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+#[nfnetlink_object(add = NFT_MSG_NEWGEN, del = NFT_MSG_NEWGEN)]
+#[nfnetlink_struct(derive_deserialize = false)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Generation {
+    #[field(NFTA_GEN_ID)]
+    id: u32,
+    /// The process id of the process that committed the batch.
+    #[field(NFTA_GEN_PROC_PID)]
+    proc_pid: u32,
+    /// The name of the process that committed the batch, e.g. `nft`.
+    #[field(NFTA_GEN_PROC_NAME)]
+    proc_name: String,
+}
+
+impl Display for Generation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.id {
+            Some(id) => write!(f, "generation {}", id)?,
+            None => f.write_str("generation ?")?,
+        }
+        match (&self.proc_name, self.proc_pid) {
+            (Some(name), Some(pid)) => write!(f, " by {} (pid {})", name, pid),
+            (Some(name), None) => write!(f, " by {}", name),
+            (None, Some(pid)) => write!(f, " by pid {}", pid),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+/// Returns the current generation of the ruleset.
+#[cfg(feature = "socket")]
+pub fn get_generation() -> Result<Generation, QueryError> {
+    use crate::query::{get_object, socket_close_wrapper, NfNetlinkSocket, QueryBuffer};
+
+    let sock = NfNetlinkSocket::new()?;
+    let mut generation = None;
+    socket_close_wrapper(sock, |sock| {
+        generation = Some(get_object(
+            sock,
+            &mut QueryBuffer::new(),
+            crate::sys::NFT_MSG_GETGEN as u16,
+            &Generation::default(),
+        )?);
+        Ok::<(), QueryError>(())
+    })?;
+    generation.ok_or(QueryError::MissingObject)
+}
@@ -0,0 +1,101 @@
+//! The numbers identifying the NFLOG groups and the NFQUEUE queues, which connect the rules of the
+//! ruleset to the userspace programs receiving their packets (e.g. ulogd, suricata).
+//!
+//! Both are 16 bits long in the kernel. The wrappers of this module check the numbers coming from
+//! wider integers (e.g. a configuration file), instead of silently truncating them to another
+//! group or queue:
+//!
+//! ```
+//! use rustables::groups::LogGroup;
+//!
+//! assert_eq!(LogGroup::new(5u32).unwrap().number(), 5);
+//! assert!(LogGroup::new(70_000u32).is_err());
+//! ```
+
+use std::fmt::{self, Display, Formatter};
+
+use crate::error::{BuilderError, DecodeError};
+use crate::nlmsg::{NfNetlinkAttribute, NfNetlinkDeserializable};
+
+macro_rules! group_number {
+    ($(#[$meta:meta])* $name:ident, $error:ident) => {
+        $(#[$meta])*
+        #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+        #[cfg_attr(feature = "serde", serde(transparent))]
+        pub struct $name(u16);
+
+        impl $name {
+            /// Checks that `number` fits in 16 bits.
+            pub fn new(number: impl TryInto<u16>) -> Result<Self, BuilderError> {
+                number
+                    .try_into()
+                    .map($name)
+                    .map_err(|_| BuilderError::$error)
+            }
+
+            pub fn number(&self) -> u16 {
+                self.0
+            }
+        }
+
+        impl From<u16> for $name {
+            fn from(number: u16) -> Self {
+                $name(number)
+            }
+        }
+
+        impl From<$name> for u16 {
+            fn from(number: $name) -> Self {
+                number.0
+            }
+        }
+
+        impl Display for $name {
+            fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+                write!(f, "{}", self.0)
+            }
+        }
+
+        impl NfNetlinkAttribute for $name {
+            fn get_size(&self) -> usize {
+                self.0.get_size()
+            }
+
+            fn write_payload(&self, addr: &mut [u8]) {
+                self.0.write_payload(addr)
+            }
+        }
+
+        impl NfNetlinkDeserializable for $name {
+            fn deserialize(buf: &[u8]) -> Result<(Self, &[u8]), DecodeError> {
+                let (number, remaining) = u16::deserialize(buf)?;
+                Ok(($name(number), remaining))
+            }
+        }
+    };
+}
+
+group_number!(
+    /// The NFLOG group receiving the packets of a [`Log`](crate::expr::Log) expression.
+    LogGroup,
+    InvalidLogGroup
+);
+
+group_number!(
+    /// The NFQUEUE queue receiving the packets of a rule, to be accepted or dropped by a
+    /// userspace program.
+    QueueNum,
+    InvalidQueueNumber
+);
+
+impl LogGroup {
+    /// The group of the iptables `NFLOG` target when none is given, where ulogd listens by
+    /// default.
+    pub const DEFAULT: LogGroup = LogGroup(0);
+}
+
+impl QueueNum {
+    /// The queue that `nft` and `iptables` use when none is given.
+    pub const DEFAULT: QueueNum = QueueNum(0);
+}
@@ -0,0 +1,636 @@
+//! Translation of the rulesets saved by `iptables-save` (or `ip6tables-save`) into rustables
+//! objects, to migrate from iptables-based tooling:
+//!
+//! ```ignore
+//! let output = Command::new("iptables-save").output()?;
+//! let save = std::str::from_utf8(&output.stdout)?;
+//! let ruleset = IptablesRuleset::from_save(save, ProtocolFamily::Ipv4)?;
+//! let mut batch = Batch::new();
+//! ruleset.add_to_batch(&mut batch);
+//! ```
+//!
+//! Each `*table` section becomes a table of the same name, whose built-in chains are hooked
+//! with the priorities `iptables-nft` uses, and each `-A` line becomes a rule. Like in iptables,
+//! every rule counts the packets it matches.
+//!
+//! Only a constrained subset of the syntax is supported:
+//! - the `filter`, `nat`, `mangle` and `raw` tables;
+//! - the matches on the protocol (`-p tcp`, `udp` or `icmp`), the addresses (`-s`, `-d`), the
+//!   interfaces (`-i`, `-o`), the ports (`--sport`, `--dport`) and the conntrack states
+//!   (`-m conntrack --ctstate` or `-m state --state`);
+//! - the `ACCEPT`, `DROP`, `RETURN`, `REJECT`, `LOG`, `MASQUERADE`, `SNAT` and `DNAT` targets,
+//!   and the jumps (`-j`) and gotos (`-g`) to user-defined chains.
+//!
+//! Anything else, such as negations (`!`), port ranges or other match modules, fails with
+//! [`IptablesError::Unsupported`] rather than being silently dropped. The only exception are the
+//! comments (`-m comment --comment`), which are left out as this crate does not write the
+//! userdata of the rules.
+
+use std::net::IpAddr;
+
+use ipnetwork::IpNetwork;
+
+use crate::error::IptablesError;
+use crate::expr::{
+    ConnTrackState, Counter, IcmpCode, Immediate, Log, Reject, RejectType, VerdictKind,
+};
+use crate::nft_syntax::CT_STATE_NAMES;
+use crate::nlmsg::NfNetlinkObject;
+use crate::{
+    Batch, Chain, ChainPolicy, ChainPriority, ChainType, Hook, HookClass, MsgType, Protocol,
+    ProtocolFamily, Rule, Table,
+};
+
+/// An iptables table, with the hooks of its built-in chains.
+struct BuiltinTable {
+    name: &'static str,
+    chains: &'static [(&'static str, HookClass)],
+    priority: ChainPriority,
+}
+
+const BUILTIN_TABLES: [BuiltinTable; 4] = [
+    BuiltinTable {
+        name: "filter",
+        chains: &[
+            ("INPUT", HookClass::In),
+            ("FORWARD", HookClass::Forward),
+            ("OUTPUT", HookClass::Out),
+        ],
+        priority: 0,
+    },
+    BuiltinTable {
+        name: "nat",
+        chains: &[
+            ("PREROUTING", HookClass::PreRouting),
+            ("INPUT", HookClass::In),
+            ("OUTPUT", HookClass::Out),
+            ("POSTROUTING", HookClass::PostRouting),
+        ],
+        priority: -100,
+    },
+    BuiltinTable {
+        name: "mangle",
+        chains: &[
+            ("PREROUTING", HookClass::PreRouting),
+            ("INPUT", HookClass::In),
+            ("FORWARD", HookClass::Forward),
+            ("OUTPUT", HookClass::Out),
+            ("POSTROUTING", HookClass::PostRouting),
+        ],
+        priority: -150,
+    },
+    BuiltinTable {
+        name: "raw",
+        chains: &[
+            ("PREROUTING", HookClass::PreRouting),
+            ("OUTPUT", HookClass::Out),
+        ],
+        priority: -300,
+    },
+];
+
+/// The tables, chains and rules of a ruleset saved by `iptables-save`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IptablesRuleset {
+    pub tables: Vec<Table>,
+    pub chains: Vec<Chain>,
+    pub rules: Vec<Rule>,
+}
+
+impl IptablesRuleset {
+    /// Parses the output of `iptables-save`, whose tables are created in `family` (usually
+    /// [`ProtocolFamily::Ipv4`], or [`ProtocolFamily::Ipv6`] for `ip6tables-save`). The errors
+    /// are wrapped in [`IptablesError::AtLine`].
+    pub fn from_save(save: &str, family: ProtocolFamily) -> Result<Self, IptablesError> {
+        let mut ruleset = IptablesRuleset::default();
+        // the table of the section being read, until its COMMIT line
+        let mut current = None;
+        for (index, line) in save.lines().enumerate() {
+            ruleset
+                .parse_line(line.trim(), family, &mut current)
+                .map_err(|error| IptablesError::AtLine {
+                    line: index + 1,
+                    error: Box::new(error),
+                })?;
+        }
+        match current {
+            Some(table) => Err(IptablesError::MissingCommit(table_name(&table))),
+            None => Ok(ruleset),
+        }
+    }
+
+    /// Adds the objects of the ruleset to `batch`, tables first.
+    pub fn add_to_batch(&self, batch: &mut Batch) {
+        for table in &self.tables {
+            batch.add(table, MsgType::Add);
+        }
+        for chain in &self.chains {
+            batch.add(chain, MsgType::Add);
+        }
+        for rule in &self.rules {
+            batch.add(rule, MsgType::Add);
+        }
+    }
+
+    fn parse_line(
+        &mut self,
+        line: &str,
+        family: ProtocolFamily,
+        current: &mut Option<Table>,
+    ) -> Result<(), IptablesError> {
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(());
+        }
+        if let Some(name) = line.strip_prefix('*') {
+            if let Some(table) = current {
+                return Err(IptablesError::MissingCommit(table_name(table)));
+            }
+            if builtin_table(name).is_none() {
+                return Err(IptablesError::Unsupported(format!("the table {}", name)));
+            }
+            let table = Table::new(family).with_name(name);
+            self.tables.push(table.clone());
+            *current = Some(table);
+            return Ok(());
+        }
+        let Some(table) = current.as_ref() else {
+            return Err(IptablesError::OutsideTable);
+        };
+        if line == "COMMIT" {
+            *current = None;
+            return Ok(());
+        }
+        if let Some(declaration) = line.strip_prefix(':') {
+            // `:INPUT ACCEPT [0:0]`, with `-` as the policy of the user-defined chains
+            let mut fields = declaration.split_whitespace();
+            let (Some(name), Some(policy)) = (fields.next(), fields.next()) else {
+                return Err(IptablesError::UnexpectedLine(line.to_string()));
+            };
+            let chain = declare_chain(table, name, policy)?;
+            self.chains.push(chain);
+            return Ok(());
+        }
+
+        let mut tokens = tokenize(line)?;
+        // the counters saved by `iptables-save -c`, as `[packets:bytes]`
+        let counter = match tokens.first() {
+            Some(counters) if counters.starts_with('[') => {
+                let counter = parse_counters(counters)?;
+                tokens.remove(0);
+                Some(counter)
+            }
+            _ => None,
+        };
+        if tokens.first().map(String::as_str) != Some("-A") {
+            return Err(IptablesError::UnexpectedLine(line.to_string()));
+        }
+        let declared = |name: &str| {
+            self.chains.iter().any(|chain| {
+                chain.get_table() == table.get_name()
+                    && chain.get_name().map(String::as_str) == Some(name)
+            })
+        };
+        let rule = translate_rule(&tokens, table, counter, Some(&declared))?;
+        self.rules.push(rule);
+        Ok(())
+    }
+}
+
+/// Translates a single rule specification, as written by `iptables-save` (e.g.
+/// `-A INPUT -p tcp --dport 22 -j ACCEPT`), into a rule of `table`. The targets that are not
+/// built in are jumps to the chains of the same name in `table`.
+pub fn parse_rule(spec: &str, table: &Table) -> Result<Rule, IptablesError> {
+    translate_rule(&tokenize(spec)?, table, None, None)
+}
+
+fn table_name(table: &Table) -> String {
+    table.get_name().cloned().unwrap_or_default()
+}
+
+fn builtin_table(name: &str) -> Option<&'static BuiltinTable> {
+    BUILTIN_TABLES.iter().find(|table| table.name == name)
+}
+
+fn declare_chain(table: &Table, name: &str, policy: &str) -> Result<Chain, IptablesError> {
+    let chain = Chain::new(table).with_name(name);
+    let builtin = builtin_table(&table_name(table)).and_then(|builtin| {
+        builtin
+            .chains
+            .iter()
+            .find(|(chain, _)| *chain == name)
+            .map(|(_, class)| (*class, builtin.priority))
+    });
+    let Some((class, mut priority)) = builtin else {
+        if policy != "-" {
+            return Err(IptablesError::InvalidValue {
+                option: format!(":{}", name),
+                value: policy.to_string(),
+            });
+        }
+        return Ok(chain);
+    };
+
+    let mut chain_type = ChainType::Filter;
+    if table.get_name().map(String::as_str) == Some("nat") {
+        chain_type = ChainType::Nat;
+        // the source NAT happens after the filtering of the packets
+        if class == HookClass::In || class == HookClass::PostRouting {
+            priority = 100;
+        }
+    } else if table.get_name().map(String::as_str) == Some("mangle") && class == HookClass::Out {
+        // rerouted when their marks or addresses are changed, like iptables does
+        chain_type = ChainType::Route;
+    }
+    let policy = match policy {
+        "ACCEPT" => ChainPolicy::Accept,
+        "DROP" => ChainPolicy::Drop,
+        _ => {
+            return Err(IptablesError::InvalidValue {
+                option: format!(":{}", name),
+                value: policy.to_string(),
+            })
+        }
+    };
+    Ok(chain
+        .with_hook(Hook::new(class, priority))
+        .with_type(chain_type)
+        .with_policy(policy))
+}
+
+/// Splits a line into its arguments like a shell would, `iptables-save` quoting the ones with
+/// spaces, such as the comments.
+fn tokenize(line: &str) -> Result<Vec<String>, IptablesError> {
+    let mut tokens = Vec::new();
+    let mut chars = line.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        if chars.peek().is_none() {
+            return Ok(tokens);
+        }
+        let mut token = String::new();
+        let mut quoted = false;
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => quoted = !quoted,
+                '\\' if quoted => token.extend(chars.next()),
+                c if c.is_whitespace() && !quoted => break,
+                c => token.push(c),
+            }
+        }
+        if quoted {
+            return Err(IptablesError::UnterminatedQuote);
+        }
+        tokens.push(token);
+    }
+}
+
+fn parse_counters(counters: &str) -> Result<Counter, IptablesError> {
+    counters
+        .strip_prefix('[')
+        .and_then(|counters| counters.strip_suffix(']'))
+        .and_then(|counters| counters.split_once(':'))
+        .and_then(|(packets, bytes)| Some(Counter::new(packets.parse().ok()?, bytes.parse().ok()?)))
+        .ok_or_else(|| IptablesError::InvalidValue {
+            option: "-c".to_string(),
+            value: counters.to_string(),
+        })
+}
+
+/// The options of a rule, gathered before the rule is built as iptables does not care about
+/// their order.
+#[derive(Default)]
+struct RuleSpec<'a> {
+    chain: Option<&'a str>,
+    protocol: Option<&'a str>,
+    source: Option<&'a str>,
+    destination: Option<&'a str>,
+    in_iface: Option<&'a str>,
+    out_iface: Option<&'a str>,
+    sport: Option<&'a str>,
+    dport: Option<&'a str>,
+    ct_states: Option<&'a str>,
+    goto: Option<&'a str>,
+    target: Option<&'a str>,
+    target_options: Vec<(&'a str, &'a str)>,
+}
+
+fn next_value<'a>(
+    tokens: &mut impl Iterator<Item = &'a str>,
+    option: &str,
+) -> Result<&'a str, IptablesError> {
+    tokens
+        .next()
+        .ok_or_else(|| IptablesError::MissingValue(option.to_string()))
+}
+
+fn invalid(option: &str, value: &str) -> IptablesError {
+    IptablesError::InvalidValue {
+        option: option.to_string(),
+        value: value.to_string(),
+    }
+}
+
+fn translate_rule(
+    tokens: &[String],
+    table: &Table,
+    counter: Option<Counter>,
+    declared: Option<&dyn Fn(&str) -> bool>,
+) -> Result<Rule, IptablesError> {
+    let mut spec = RuleSpec::default();
+    let mut tokens = tokens.iter().map(String::as_str);
+    while let Some(option) = tokens.next() {
+        // the options of the targets follow them
+        if spec.target.is_some() {
+            let value = next_value(&mut tokens, option)?;
+            spec.target_options.push((option, value));
+            continue;
+        }
+        match option {
+            "-A" | "--append" => spec.chain = Some(next_value(&mut tokens, option)?),
+            "-p" | "--protocol" => spec.protocol = Some(next_value(&mut tokens, option)?),
+            "-s" | "--source" => spec.source = Some(next_value(&mut tokens, option)?),
+            "-d" | "--destination" => spec.destination = Some(next_value(&mut tokens, option)?),
+            "-i" | "--in-interface" => spec.in_iface = Some(next_value(&mut tokens, option)?),
+            "-o" | "--out-interface" => spec.out_iface = Some(next_value(&mut tokens, option)?),
+            "--sport" | "--source-port" => spec.sport = Some(next_value(&mut tokens, option)?),
+            "--dport" | "--destination-port" => spec.dport = Some(next_value(&mut tokens, option)?),
+            "--ctstate" | "--state" => spec.ct_states = Some(next_value(&mut tokens, option)?),
+            "--comment" => {
+                next_value(&mut tokens, option)?;
+            }
+            "-m" | "--match" => match next_value(&mut tokens, option)? {
+                "tcp" | "udp" | "icmp" | "conntrack" | "state" | "comment" => {}
+                module => {
+                    return Err(IptablesError::Unsupported(format!(
+                        "the match module {}",
+                        module
+                    )))
+                }
+            },
+            "-j" | "--jump" => spec.target = Some(next_value(&mut tokens, option)?),
+            "-g" | "--goto" => spec.goto = Some(next_value(&mut tokens, option)?),
+            "!" => return Err(IptablesError::Unsupported("negations".to_string())),
+            _ => return Err(IptablesError::Unsupported(format!("the option {}", option))),
+        }
+    }
+
+    let name = spec
+        .chain
+        .ok_or_else(|| IptablesError::MissingValue("-A".to_string()))?;
+    let chain_in_table = |name: &str| {
+        if declared.is_some_and(|declared| !declared(name)) {
+            return Err(IptablesError::UnknownChain(name.to_string()));
+        }
+        Ok(Chain::new(table).with_name(name))
+    };
+    let mut rule = Rule::new(&chain_in_table(name)?)?;
+
+    if let Some(iface) = spec.in_iface {
+        rule = rule.iiface(interface(iface)?)?;
+    }
+    if let Some(iface) = spec.out_iface {
+        rule = rule.oiface(interface(iface)?)?;
+    }
+    if let Some(source) = spec.source {
+        rule = match address(source, table.get_family(), "-s")? {
+            Ok(ip) => rule.saddr(ip),
+            Err(net) => rule.snetwork(net)?,
+        };
+    }
+    if let Some(destination) = spec.destination {
+        rule = match address(destination, table.get_family(), "-d")? {
+            Ok(ip) => rule.daddr(ip),
+            Err(net) => rule.dnetwork(net)?,
+        };
+    }
+    rule = match_protocol(rule, &spec)?;
+    if let Some(states) = spec.ct_states {
+        rule = rule.ct_states(ct_states(states)?, false)?;
+    }
+    rule.add_expr(counter.unwrap_or(Counter::new(0, 0)));
+
+    match (spec.target, spec.goto) {
+        (Some(_), Some(_)) => {
+            return Err(IptablesError::Unsupported(
+                "both a jump and a goto".to_string(),
+            ))
+        }
+        (None, Some(chain)) => rule = rule.goto(&chain_in_table(chain)?)?,
+        (Some(target), None) => {
+            rule = match translate_target(rule, target, &spec.target_options)? {
+                Ok(rule) => rule,
+                // the other targets are extensions, e.g. TCPMSS
+                Err(_) if declared.is_some_and(|declared| !declared(target)) => {
+                    return Err(IptablesError::Unsupported(format!("the target {}", target)))
+                }
+                Err(rule) => rule.jump(&chain_in_table(target)?)?,
+            }
+        }
+        (None, None) => {}
+    }
+    Ok(rule)
+}
+
+fn interface(name: &str) -> Result<&str, IptablesError> {
+    if name.ends_with('+') {
+        return Err(IptablesError::Unsupported(format!(
+            "the interface wildcard {}",
+            name
+        )));
+    }
+    Ok(name)
+}
+
+/// Parses an address or a network, returning the address alone when the whole address is
+/// matched, as `iptables-save` writes the addresses with a full-length prefix.
+fn address(
+    value: &str,
+    family: ProtocolFamily,
+    option: &str,
+) -> Result<Result<IpAddr, IpNetwork>, IptablesError> {
+    let net: IpNetwork = value.parse().map_err(|_| invalid(option, value))?;
+    let mismatch = match family {
+        ProtocolFamily::Ipv4 => !net.is_ipv4(),
+        ProtocolFamily::Ipv6 => !net.is_ipv6(),
+        _ => false,
+    };
+    if mismatch {
+        return Err(invalid(option, value));
+    }
+    let full_length = if net.is_ipv4() { 32 } else { 128 };
+    if net.prefix() == full_length {
+        Ok(Ok(net.ip()))
+    } else {
+        Ok(Err(net))
+    }
+}
+
+fn match_protocol(mut rule: Rule, spec: &RuleSpec) -> Result<Rule, IptablesError> {
+    let protocol = match spec.protocol {
+        Some("tcp") => Protocol::TCP,
+        Some("udp") => Protocol::UDP,
+        Some("icmp") if rule.get_family() == ProtocolFamily::Ipv4 => {
+            if spec.sport.is_some() || spec.dport.is_some() {
+                return Err(IptablesError::Unsupported("ports with ICMP".to_string()));
+            }
+            return Ok(rule.icmp());
+        }
+        Some("all") | None if spec.sport.is_none() && spec.dport.is_none() => return Ok(rule),
+        None => {
+            return Err(IptablesError::Unsupported(
+                "ports without `-p tcp` or `-p udp`".to_string(),
+            ))
+        }
+        Some(protocol) => return Err(invalid("-p", protocol)),
+    };
+    if spec.sport.is_none() && spec.dport.is_none() {
+        return Ok(rule.protocol(protocol));
+    }
+    if let Some(port) = spec.sport {
+        rule = rule.sport(port_number(port, "--sport")?, protocol);
+    }
+    if let Some(port) = spec.dport {
+        rule = rule.dport(port_number(port, "--dport")?, protocol);
+    }
+    Ok(rule)
+}
+
+fn port_number(value: &str, option: &str) -> Result<u16, IptablesError> {
+    if value.contains(':') {
+        return Err(IptablesError::Unsupported(format!(
+            "the port range {}",
+            value
+        )));
+    }
+    value.parse().map_err(|_| invalid(option, value))
+}
+
+fn ct_states(value: &str) -> Result<ConnTrackState, IptablesError> {
+    let mut states = ConnTrackState::empty();
+    for name in value.split(',') {
+        let (state, _) = CT_STATE_NAMES
+            .iter()
+            .find(|(_, state_name)| state_name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| invalid("--ctstate", name))?;
+        states |= *state;
+    }
+    Ok(states)
+}
+
+/// The rejection with the ICMP error `code`, as named by iptables (e.g. `icmp-host-prohibited`)
+/// or ip6tables (e.g. `icmp6-adm-prohibited`), in a table of `family`. The ip and ip6 families
+/// ignore the ICMPX rejections, so their packets are rejected with the ICMP or ICMPv6 code itself.
+fn reject_with_icmp(family: ProtocolFamily, code: &str) -> Result<Reject, IptablesError> {
+    let icmp_code = match (family, code) {
+        (ProtocolFamily::Ipv4, "icmp-net-unreachable") => 0,
+        (ProtocolFamily::Ipv4, "icmp-host-unreachable") => 1,
+        (ProtocolFamily::Ipv4, "icmp-proto-unreachable") => 2,
+        (ProtocolFamily::Ipv4, "icmp-port-unreachable") => 3,
+        (ProtocolFamily::Ipv4, "icmp-net-prohibited") => 9,
+        (ProtocolFamily::Ipv4, "icmp-host-prohibited") => 10,
+        (ProtocolFamily::Ipv4, "icmp-admin-prohibited") => 13,
+        (ProtocolFamily::Ipv6, "icmp6-no-route") => 0,
+        (ProtocolFamily::Ipv6, "icmp6-adm-prohibited") => 1,
+        (ProtocolFamily::Ipv6, "icmp6-addr-unreachable") => 3,
+        (ProtocolFamily::Ipv6, "icmp6-port-unreachable") => 4,
+        (ProtocolFamily::Ipv4 | ProtocolFamily::Ipv6, _) => {
+            return Err(invalid("--reject-with", code))
+        }
+        // the other families only have the codes common to ICMP and ICMPv6
+        (_, code) => {
+            return Ok(Reject::icmpx(match code {
+                "icmp-net-unreachable" | "icmp6-no-route" => IcmpCode::NoRoute,
+                "icmp-host-unreachable" | "icmp6-addr-unreachable" => IcmpCode::HostUnreach,
+                "icmp-port-unreachable" | "icmp6-port-unreachable" => IcmpCode::PortUnreach,
+                "icmp-net-prohibited"
+                | "icmp-host-prohibited"
+                | "icmp-admin-prohibited"
+                | "icmp6-adm-prohibited" => IcmpCode::AdminProhibited,
+                _ => return Err(invalid("--reject-with", code)),
+            }))
+        }
+    };
+    Ok(Reject::icmp(icmp_code))
+}
+
+/// Adds the built-in `target` to `rule`, or returns the rule untouched in `Err` when the target
+/// is a chain.
+fn translate_target(
+    mut rule: Rule,
+    target: &str,
+    options: &[(&str, &str)],
+) -> Result<Result<Rule, Rule>, IptablesError> {
+    let mut options = options.iter();
+    rule = match target {
+        "ACCEPT" => rule.accept(),
+        "DROP" => rule.drop(),
+        "RETURN" => {
+            rule.add_expr(Immediate::new_verdict(VerdictKind::Return));
+            rule
+        }
+        "REJECT" => {
+            // like in iptables, the packets are rejected with a port unreachable error by default
+            let family = rule.get_family();
+            let reject = match options.next() {
+                None if family == ProtocolFamily::Ipv6 => {
+                    reject_with_icmp(family, "icmp6-port-unreachable")?
+                }
+                None => reject_with_icmp(family, "icmp-port-unreachable")?,
+                Some(("--reject-with", "tcp-reset")) => {
+                    Reject::default().with_type(RejectType::TcpRst)
+                }
+                Some(("--reject-with", code)) => reject_with_icmp(family, code)?,
+                Some((option, _)) => return Err(unsupported_target_option(target, option)),
+            };
+            rule.add_expr(reject);
+            rule
+        }
+        "LOG" => {
+            let log = match options.next() {
+                None => Log::new::<&str>(None, None)?,
+                Some(("--log-prefix", prefix)) => Log::new(None, Some(*prefix))?,
+                Some((option, _)) => return Err(unsupported_target_option(target, option)),
+            };
+            rule.add_expr(log);
+            rule
+        }
+        "MASQUERADE" => match options.next() {
+            None => rule.masquerade(),
+            Some(("--to-ports", ports)) => {
+                let (first, last) = ports.split_once('-').unwrap_or((ports, ports));
+                let first = first.parse().map_err(|_| invalid("--to-ports", ports))?;
+                let last = last.parse().map_err(|_| invalid("--to-ports", ports))?;
+                rule.masquerade_to_ports(first..=last)?
+            }
+            Some((option, _)) => return Err(unsupported_target_option(target, option)),
+        },
+        "SNAT" | "DNAT" => {
+            let option = if target == "SNAT" {
+                "--to-source"
+            } else {
+                "--to-destination"
+            };
+            let ip: IpAddr = match options.next() {
+                Some((name, ip)) if *name == option => {
+                    ip.parse().map_err(|_| invalid(option, ip))?
+                }
+                Some((name, _)) => return Err(unsupported_target_option(target, name)),
+                None => return Err(IptablesError::MissingValue(option.to_string())),
+            };
+            if target == "SNAT" {
+                rule.snat(ip)
+            } else {
+                rule.dnat(ip)
+            }
+        }
+        _ => return Ok(Err(rule)),
+    };
+    if let Some((option, _)) = options.next() {
+        return Err(unsupported_target_option(target, option));
+    }
+    Ok(Ok(rule))
+}
+
+fn unsupported_target_option(target: &str, option: &str) -> IptablesError {
+    IptablesError::Unsupported(format!("the option {} of the target {}", option, target))
+}
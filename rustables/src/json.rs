@@ -0,0 +1,1166 @@
+//! Reading and writing rulesets in the JSON format of libnftables, as printed by
+//! `nft -j list ruleset` and loaded by `nft -j -f`, to exchange rulesets with the standard nft
+//! tooling and with other orchestrators:
+//!
+//! ```ignore
+//! let output = Command::new("nft").args(["-j", "list", "table", "inet", "filter"]).output()?;
+//! let ruleset = JsonRuleset::from_json(std::str::from_utf8(&output.stdout)?)?;
+//! let mut batch = Batch::new();
+//! ruleset.add_to_batch(&mut batch);
+//! ```
+//!
+//! The rules are translated from and to their expressions like [`Rule::to_nft_syntax`] does,
+//! and only the statements that this crate can translate are supported: matches on meta keys,
+//! header fields, conntrack states and marks, named sets and verdict maps, and the verdict,
+//! counter, log, limit, reject, masquerade and NAT statements. The elements of the sets, the
+//! stateful objects and the flowtables are not supported either. The conversions fail with
+//! [`JsonError::Unsupported`] or [`JsonError::UntranslatedExpression`] rather than silently
+//! dropping parts of the ruleset.
+
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use serde_json::{json, Map, Value};
+
+use crate::data_type::{DataTypeId, IpOperand};
+use crate::error::{BuilderError, JsonError};
+use crate::expr::{
+    Bitwise, Cmp, CmpOp, ConnTrackState, Conntrack, ConntrackKey, Counter, Immediate, Limit, Log,
+    Lookup, Masquerade, Meta, MetaType, Nat, NatType, Payload, Register, Reject, RejectType,
+    Tproxy, Verdict, VerdictKind, VerdictType, ICMPV6_CODE_NAMES, ICMPX_CODE_NAMES,
+    ICMP_CODE_NAMES,
+};
+use crate::nft_syntax::{
+    address, be_integer, ct_state_names, hook_name, hook_names, host_integer, interface_name,
+    meta_value_kind, Operand, RejectWith, RuleTranslator, Statement, Value as Right, ValueKind,
+    CT_STATE_NAMES, HEADER_FIELDS, L4PROTO_NAMES, LIMIT_UNITS, NFPROTO_NAMES, PAYLOAD_BASES,
+};
+use crate::nlmsg::NfNetlinkObject;
+use crate::set::{SetFlags, SetUserdata};
+use crate::sys::{
+    NFT_DATA_VERDICT, NFT_LIMIT_F_INV, NFT_LIMIT_PKTS, NFT_LIMIT_PKT_BYTES, NFT_TABLE_F_DORMANT,
+    NFT_TABLE_F_OWNER,
+};
+use crate::{
+    Batch, Chain, ChainPolicy, ChainType, DeviceList, Hook, MsgType, ProtocolFamily, Rule, Set,
+    Table, TableContents,
+};
+
+/// The version of the JSON schema written in the `metainfo` object.
+const JSON_SCHEMA_VERSION: u64 = 1;
+
+/// The tables, chains, sets and rules of a ruleset in the JSON format of libnftables.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct JsonRuleset {
+    pub tables: Vec<Table>,
+    pub chains: Vec<Chain>,
+    pub sets: Vec<Set>,
+    pub rules: Vec<Rule>,
+}
+
+impl JsonRuleset {
+    /// Parses a ruleset printed by `nft -j list`.
+    pub fn from_json(json: &str) -> Result<Self, JsonError> {
+        Self::from_value(&serde_json::from_str(json)?)
+    }
+
+    /// Parses a ruleset printed by `nft -j list`, already decoded as a JSON value.
+    pub fn from_value(value: &Value) -> Result<Self, JsonError> {
+        let objects =
+            value
+                .get("nftables")
+                .and_then(Value::as_array)
+                .ok_or(JsonError::MissingProperty {
+                    object: "ruleset",
+                    property: "nftables",
+                })?;
+        let mut ruleset = JsonRuleset::default();
+        for object in objects {
+            let Some((kind, properties)) = object.as_object().and_then(|o| o.iter().next()) else {
+                return Err(invalid("nftables", object));
+            };
+            let properties = properties
+                .as_object()
+                .ok_or_else(|| invalid("nftables", object))?;
+            match kind.as_str() {
+                "metainfo" => {}
+                "table" => ruleset.tables.push(parse_table(properties)?),
+                "chain" => ruleset.chains.push(parse_chain(properties)?),
+                "set" | "map" => ruleset.sets.push(parse_set(properties)?),
+                "rule" => ruleset.rules.push(parse_rule(properties)?),
+                kind => return Err(JsonError::Unsupported(format!("{} objects", kind))),
+            }
+        }
+        Ok(ruleset)
+    }
+
+    /// Writes the ruleset like `nft -j list ruleset`.
+    pub fn to_json(&self) -> Result<String, JsonError> {
+        Ok(self.to_value()?.to_string())
+    }
+
+    /// Writes the ruleset like `nft -j list ruleset`, as a JSON value.
+    pub fn to_value(&self) -> Result<Value, JsonError> {
+        let mut objects = vec![json!({
+            "metainfo": { "json_schema_version": JSON_SCHEMA_VERSION }
+        })];
+        for table in &self.tables {
+            objects.push(json!({ "table": table_to_json(table) }));
+        }
+        for chain in &self.chains {
+            objects.push(json!({ "chain": chain_to_json(chain) }));
+        }
+        for set in &self.sets {
+            let kind = if set.is_map() { "map" } else { "set" };
+            objects.push(json!({ kind: set_to_json(set)? }));
+        }
+        for rule in &self.rules {
+            objects.push(json!({ "rule": rule_to_json(rule)? }));
+        }
+        Ok(json!({ "nftables": objects }))
+    }
+
+    /// Adds the objects of the ruleset to `batch`, tables first.
+    pub fn add_to_batch(&self, batch: &mut Batch) {
+        for table in &self.tables {
+            batch.add(table, MsgType::Add);
+        }
+        for chain in &self.chains {
+            batch.add(chain, MsgType::Add);
+        }
+        for set in &self.sets {
+            batch.add(set, MsgType::Add);
+        }
+        for rule in &self.rules {
+            batch.add(rule, MsgType::Add);
+        }
+    }
+}
+
+impl From<TableContents> for JsonRuleset {
+    /// Takes the table with its chains, rules and named sets. The anonymous sets are written
+    /// inline in the rules by nft, and are left out.
+    fn from(contents: TableContents) -> Self {
+        JsonRuleset {
+            tables: vec![contents.table],
+            chains: contents.chains,
+            sets: contents
+                .sets
+                .into_iter()
+                .filter(|set| {
+                    !set.get_set_flags()
+                        .is_some_and(|f| f.contains(SetFlags::ANONYMOUS))
+                })
+                .collect(),
+            rules: contents.rules,
+        }
+    }
+}
+
+fn invalid(property: &'static str, value: &Value) -> JsonError {
+    JsonError::InvalidValue {
+        property,
+        value: value.to_string(),
+    }
+}
+
+fn get<'a>(
+    object: &'static str,
+    properties: &'a Map<String, Value>,
+    property: &'static str,
+) -> Result<&'a Value, JsonError> {
+    properties
+        .get(property)
+        .ok_or(JsonError::MissingProperty { object, property })
+}
+
+fn get_str<'a>(
+    object: &'static str,
+    properties: &'a Map<String, Value>,
+    property: &'static str,
+) -> Result<&'a str, JsonError> {
+    let value = get(object, properties, property)?;
+    value.as_str().ok_or_else(|| invalid(property, value))
+}
+
+fn get_u64(value: &Value, property: &'static str) -> Result<u64, JsonError> {
+    value.as_u64().ok_or_else(|| invalid(property, value))
+}
+
+/// The value of `property` as a list, nft writing a single value instead of a list of one.
+fn get_list<'a>(properties: &'a Map<String, Value>, property: &str) -> Vec<&'a Value> {
+    match properties.get(property) {
+        Some(Value::Array(values)) => values.iter().collect(),
+        Some(value) => vec![value],
+        None => Vec::new(),
+    }
+}
+
+/// A single value, or the list of `values` when there are several of them, like nft writes
+/// flags and devices.
+fn one_or_many(mut values: Vec<Value>) -> Value {
+    if values.len() == 1 {
+        values.remove(0)
+    } else {
+        Value::Array(values)
+    }
+}
+
+fn parse_family(properties: &Map<String, Value>) -> Result<ProtocolFamily, JsonError> {
+    let name = get_str("object", properties, "family")?;
+    [
+        ProtocolFamily::Inet,
+        ProtocolFamily::Ipv4,
+        ProtocolFamily::Ipv6,
+        ProtocolFamily::Arp,
+        ProtocolFamily::Bridge,
+        ProtocolFamily::NetDev,
+    ]
+    .into_iter()
+    .find(|family| family.to_string() == name)
+    .ok_or_else(|| invalid("family", &properties["family"]))
+}
+
+fn parse_table(properties: &Map<String, Value>) -> Result<Table, JsonError> {
+    let mut flags = 0;
+    for flag in get_list(properties, "flags") {
+        flags |= match flag.as_str() {
+            Some("dormant") => NFT_TABLE_F_DORMANT,
+            Some("owner") => NFT_TABLE_F_OWNER,
+            _ => return Err(invalid("flags", flag)),
+        };
+    }
+    Ok(Table::new(parse_family(properties)?)
+        .with_name(get_str("table", properties, "name")?)
+        .with_flags(flags))
+}
+
+fn table_to_json(table: &Table) -> Value {
+    let mut properties = Map::new();
+    properties.insert("family".into(), json!(table.get_family().to_string()));
+    properties.insert("name".into(), json!(table.get_name()));
+    let flags = table.get_flags().copied().unwrap_or(0);
+    let names: Vec<Value> = [
+        (NFT_TABLE_F_DORMANT, "dormant"),
+        (NFT_TABLE_F_OWNER, "owner"),
+    ]
+    .into_iter()
+    .filter(|(flag, _)| flags & flag != 0)
+    .map(|(_, name)| json!(name))
+    .collect();
+    if !names.is_empty() {
+        properties.insert("flags".into(), one_or_many(names));
+    }
+    Value::Object(properties)
+}
+
+/// The table named `table` in the properties of a chain, a set or a rule.
+fn parent_table(object: &'static str, properties: &Map<String, Value>) -> Result<Table, JsonError> {
+    Ok(Table::new(parse_family(properties)?).with_name(get_str(object, properties, "table")?))
+}
+
+fn parse_chain(properties: &Map<String, Value>) -> Result<Chain, JsonError> {
+    let table = parent_table("chain", properties)?;
+    let mut chain = Chain::new(&table).with_name(get_str("chain", properties, "name")?);
+    let Some(hook) = properties.get("hook") else {
+        return Ok(chain);
+    };
+    let class = hook_names(table.get_family())
+        .iter()
+        .find(|(_, name)| Some(*name) == hook.as_str())
+        .map(|(class, _)| *class as u32)
+        .ok_or_else(|| invalid("hook", hook))?;
+    let prio = get("chain", properties, "prio")?;
+    let priority = prio
+        .as_i64()
+        .and_then(|prio| i32::try_from(prio).ok())
+        .ok_or_else(|| invalid("prio", prio))?;
+    let mut hook = Hook::default()
+        .with_class(class)
+        .with_priority(priority as u32);
+    let devices = get_list(properties, "dev");
+    if !devices.is_empty() {
+        let names = devices
+            .iter()
+            .map(|dev| dev.as_str().ok_or_else(|| invalid("dev", dev)))
+            .collect::<Result<Vec<_>, _>>()?;
+        hook.set_devices(DeviceList::new(names)?);
+    }
+    let chain_type = match get_str("chain", properties, "type")? {
+        "filter" => ChainType::Filter,
+        "route" => ChainType::Route,
+        "nat" => ChainType::Nat,
+        _ => return Err(invalid("type", &properties["type"])),
+    };
+    chain = chain.with_hook(hook).with_type(chain_type);
+    match properties
+        .get("policy")
+        .map(|policy| (policy.as_str(), policy))
+    {
+        Some((Some("accept"), _)) => chain.set_policy(ChainPolicy::Accept),
+        Some((Some("drop"), _)) => chain.set_policy(ChainPolicy::Drop),
+        Some((_, policy)) => return Err(invalid("policy", policy)),
+        None => {}
+    }
+    Ok(chain)
+}
+
+fn chain_to_json(chain: &Chain) -> Value {
+    let mut properties = Map::new();
+    properties.insert("family".into(), json!(chain.get_family().to_string()));
+    properties.insert("table".into(), json!(chain.get_table()));
+    properties.insert("name".into(), json!(chain.get_name()));
+    if let Some(hook) = chain.get_hook() {
+        let chain_type = chain.get_type().map_or("filter", |t| t.as_str());
+        properties.insert("type".into(), json!(chain_type));
+        let class = hook.get_class().copied().unwrap_or(0);
+        properties.insert("hook".into(), json!(hook_name(chain.get_family(), class)));
+        let priority = hook.get_priority().copied().unwrap_or(0) as i32;
+        properties.insert("prio".into(), json!(priority));
+        if let Some(devices) = hook.get_devices().filter(|d| !d.is_empty()) {
+            let names = devices.iter().map(|d| json!(d)).collect();
+            properties.insert("dev".into(), one_or_many(names));
+        }
+        if let Some(policy) = chain.get_policy() {
+            let policy = match policy {
+                ChainPolicy::Accept => "accept",
+                ChainPolicy::Drop => "drop",
+            };
+            properties.insert("policy".into(), json!(policy));
+        }
+    }
+    Value::Object(properties)
+}
+
+/// The length of the values of `ty`, for the types whose values have a fixed length.
+fn type_len(ty: DataTypeId) -> Option<u32> {
+    Some(match ty {
+        DataTypeId::NfProto | DataTypeId::InetProtocol | DataTypeId::Dscp => 1,
+        DataTypeId::InetService | DataTypeId::EtherType => 2,
+        DataTypeId::IpAddr
+        | DataTypeId::Mark
+        | DataTypeId::IfIndex
+        | DataTypeId::Uid
+        | DataTypeId::Gid
+        | DataTypeId::CtState => 4,
+        DataTypeId::EtherAddr => 6,
+        DataTypeId::Ip6Addr | DataTypeId::IfName => 16,
+        _ => return None,
+    })
+}
+
+/// Parses a type of a set, which is a list of types for concatenations, and returns it with
+/// the length of its values.
+fn parse_set_type(value: &Value, property: &'static str) -> Result<(u32, u32), JsonError> {
+    let mut types = Vec::new();
+    let mut len = 0;
+    let members = get_list_value(value);
+    for name in &members {
+        let ty = name
+            .as_str()
+            .and_then(DataTypeId::from_name)
+            .ok_or_else(|| invalid(property, value))?;
+        let ty_len = type_len(ty).ok_or_else(|| {
+            JsonError::Unsupported(format!("sets of {} without a fixed length", ty))
+        })?;
+        // nft aligns the members of the concatenations on 4 bytes
+        len += if members.len() == 1 {
+            ty_len
+        } else {
+            ty_len.next_multiple_of(4)
+        };
+        types.push(ty);
+    }
+    Ok((DataTypeId::concat(&types), len))
+}
+
+fn get_list_value(value: &Value) -> Vec<&Value> {
+    match value {
+        Value::Array(values) => values.iter().collect(),
+        value => vec![value],
+    }
+}
+
+fn set_type_to_json(set_type: u32) -> Result<Value, JsonError> {
+    let types = DataTypeId::split(set_type)
+        .map_err(|_| JsonError::Unsupported(format!("the set type {:#x}", set_type)))?;
+    Ok(one_or_many(
+        types.iter().map(|ty| json!(ty.name())).collect(),
+    ))
+}
+
+const SET_FLAG_NAMES: [(SetFlags, &str); 4] = [
+    (SetFlags::CONSTANT, "constant"),
+    (SetFlags::INTERVAL, "interval"),
+    (SetFlags::TIMEOUT, "timeout"),
+    (SetFlags::EVAL, "dynamic"),
+];
+
+fn parse_set(properties: &Map<String, Value>) -> Result<Set, JsonError> {
+    if properties.contains_key("elem") {
+        return Err(JsonError::Unsupported("the elements of the sets".into()));
+    }
+    let table = parent_table("set", properties)?;
+    let (key_type, key_len) = parse_set_type(get("set", properties, "type")?, "type")?;
+    let mut set = Set::default()
+        .with_family(table.get_family())
+        .with_table(get_str("set", properties, "table")?)
+        .with_name(get_str("set", properties, "name")?)
+        .with_key_type(key_type)
+        .with_key_len(key_len);
+    let mut flags = SetFlags::empty();
+    for flag in get_list(properties, "flags") {
+        flags |= SET_FLAG_NAMES
+            .iter()
+            .find(|(_, name)| Some(*name) == flag.as_str())
+            .map(|(flag, _)| *flag)
+            .ok_or_else(|| invalid("flags", flag))?;
+    }
+    match properties.get("map") {
+        Some(Value::String(data)) if data == "verdict" => {
+            flags |= SetFlags::MAP;
+            set.set_data_type(NFT_DATA_VERDICT);
+        }
+        Some(data) => {
+            let (data_type, data_len) = parse_set_type(data, "map")?;
+            flags |= SetFlags::MAP;
+            set.set_data_type(data_type);
+            set.set_data_len(data_len);
+        }
+        None => {}
+    }
+    if let Some(timeout) = properties.get("timeout") {
+        set.set_timeout(get_u64(timeout, "timeout")? * 1000);
+    }
+    if let Some(comment) = properties.get("comment") {
+        let comment = comment
+            .as_str()
+            .ok_or_else(|| invalid("comment", comment))?;
+        set.set_userdata_tlvs(&SetUserdata {
+            comment: Some(comment.to_string()),
+            ..Default::default()
+        })?;
+    }
+    Ok(set.with_set_flags(flags))
+}
+
+fn set_to_json(set: &Set) -> Result<Value, JsonError> {
+    if set.is_object_map() {
+        return Err(JsonError::Unsupported("the object maps".into()));
+    }
+    let mut properties = Map::new();
+    properties.insert("family".into(), json!(set.get_family().to_string()));
+    properties.insert("name".into(), json!(set.get_name()));
+    properties.insert("table".into(), json!(set.get_table()));
+    let key_type = set.get_key_type().copied().unwrap_or(0);
+    properties.insert("type".into(), set_type_to_json(key_type)?);
+    if set.is_verdict_map() {
+        properties.insert("map".into(), json!("verdict"));
+    } else if set.is_map() {
+        let data_type = set.get_data_type().copied().unwrap_or(0);
+        properties.insert("map".into(), set_type_to_json(data_type)?);
+    }
+    let flags = set.get_set_flags().unwrap_or(SetFlags::empty());
+    let names: Vec<Value> = SET_FLAG_NAMES
+        .into_iter()
+        .filter(|(flag, _)| flags.contains(*flag))
+        .map(|(_, name)| json!(name))
+        .collect();
+    if !names.is_empty() {
+        properties.insert("flags".into(), one_or_many(names));
+    }
+    if let Some(timeout) = set.get_timeout() {
+        properties.insert("timeout".into(), json!(timeout / 1000));
+    }
+    if let Some(comment) = set.get_userdata_tlvs().ok().and_then(|u| u.comment) {
+        properties.insert("comment".into(), json!(comment));
+    }
+    Ok(Value::Object(properties))
+}
+
+fn parse_rule(properties: &Map<String, Value>) -> Result<Rule, JsonError> {
+    let table = parent_table("rule", properties)?;
+    let chain = Chain::new(&table).with_name(get_str("rule", properties, "chain")?);
+    let mut builder = RuleBuilder {
+        rule: Rule::new(&chain)?,
+        nfproto_matched: table.get_family() != ProtocolFamily::Inet,
+        l4proto_matched: false,
+    };
+    if let Some(handle) = properties.get("handle") {
+        builder.rule.set_handle(get_u64(handle, "handle")?);
+    }
+    let statements = get("rule", properties, "expr")?;
+    let statements = statements
+        .as_array()
+        .ok_or_else(|| invalid("expr", statements))?;
+    for statement in statements {
+        builder.statement(statement)?;
+    }
+    Ok(builder.rule)
+}
+
+fn rule_to_json(rule: &Rule) -> Result<Value, JsonError> {
+    let mut properties = Map::new();
+    properties.insert("family".into(), json!(rule.get_family().to_string()));
+    properties.insert("table".into(), json!(rule.get_table()));
+    properties.insert("chain".into(), json!(rule.get_chain()));
+    if let Some(handle) = rule.get_handle() {
+        properties.insert("handle".into(), json!(handle));
+    }
+    let statements = RuleTranslator::translate(rule)
+        .iter()
+        .map(statement_to_json)
+        .collect::<Result<Vec<_>, _>>()?;
+    properties.insert("expr".into(), Value::Array(statements));
+    Ok(Value::Object(properties))
+}
+
+fn operand_to_json(operand: &Operand) -> Value {
+    match operand {
+        Operand::Meta(key) => json!({ "meta": { "key": key.to_string() } }),
+        Operand::Field(field) => {
+            json!({ "payload": { "protocol": field.protocol, "field": field.field } })
+        }
+        Operand::Raw { base, offset, len } => {
+            json!({ "payload": { "base": base, "offset": offset, "len": len } })
+        }
+        Operand::Ct(key) => json!({ "ct": { "key": key.to_string() } }),
+    }
+}
+
+fn protocol_name(names: &[(i32, &str)], proto: u8) -> Value {
+    names
+        .iter()
+        .find(|(p, _)| *p == proto as i32)
+        .map_or_else(|| json!(proto), |(_, name)| json!(name))
+}
+
+fn value_to_json(kind: ValueKind, data: &[u8]) -> Option<Value> {
+    Some(match kind {
+        ValueKind::NfProto if data.len() == 1 => protocol_name(&NFPROTO_NAMES, data[0]),
+        ValueKind::L4Proto if data.len() == 1 => protocol_name(&L4PROTO_NAMES, data[0]),
+        ValueKind::Integer | ValueKind::NfProto | ValueKind::L4Proto | ValueKind::Raw => {
+            json!(be_integer(data)?)
+        }
+        ValueKind::HostInteger | ValueKind::Mark => json!(host_integer(data)?),
+        ValueKind::Interface => json!(interface_name(data)),
+        ValueKind::Address => json!(address(data)?.to_string()),
+        ValueKind::CtState => {
+            let states = ConnTrackState::from_bits_truncate(host_integer(data)?);
+            ct_states_to_json(states)
+        }
+    })
+}
+
+fn ct_states_to_json(states: ConnTrackState) -> Value {
+    one_or_many(
+        ct_state_names(states)
+            .into_iter()
+            .map(|s| json!(s))
+            .collect(),
+    )
+}
+
+fn statement_to_json(statement: &Statement) -> Result<Value, JsonError> {
+    let untranslated = || JsonError::UntranslatedExpression(statement.to_string());
+    Ok(match statement {
+        Statement::Match { left, op, right } => {
+            let (op, right) = match right {
+                Right::Bytes(data) => (
+                    op.to_string(),
+                    value_to_json(left.kind(), data).ok_or_else(untranslated)?,
+                ),
+                Right::Prefix(addr, len) => {
+                    let addr = address(addr).ok_or_else(untranslated)?.to_string();
+                    (
+                        op.to_string(),
+                        json!({ "prefix": { "addr": addr, "len": len } }),
+                    )
+                }
+                Right::CtStates(states) => {
+                    let op = match op {
+                        CmpOp::Eq => "in".to_string(),
+                        op => op.to_string(),
+                    };
+                    (op, ct_states_to_json(*states))
+                }
+                Right::Set(set) => (op.to_string(), json!(format!("@{}", set))),
+            };
+            json!({ "match": { "op": op, "left": operand_to_json(left), "right": right } })
+        }
+        Statement::VerdictMap { key, map } => {
+            json!({ "vmap": { "key": operand_to_json(key), "data": format!("@{}", map) } })
+        }
+        Statement::Verdict(verdict) => verdict_to_json(verdict).ok_or_else(untranslated)?,
+        Statement::Counter(counter) => json!({
+            "counter": { "packets": counter.packets(), "bytes": counter.bytes() }
+        }),
+        Statement::Log(log) => {
+            let mut properties = Map::new();
+            if let Some(prefix) = log.get_prefix() {
+                properties.insert("prefix".into(), json!(prefix.as_str()));
+            }
+            if let Some(group) = log.get_group() {
+                properties.insert("group".into(), json!(group.number()));
+            }
+            json!({ "log": Value::Object(properties) })
+        }
+        Statement::Limit(limit) => limit_to_json(limit).ok_or_else(untranslated)?,
+        Statement::Reject(with) => {
+            let properties = match with {
+                RejectWith::Default => Value::Null,
+                RejectWith::TcpReset => json!({ "type": "tcp reset" }),
+                RejectWith::Icmp(icmp_type, code) => json!({ "type": icmp_type, "expr": code }),
+            };
+            json!({ "reject": properties })
+        }
+        Statement::Masquerade => json!({ "masquerade": null }),
+        Statement::Nat {
+            nat_type,
+            family,
+            addr,
+            port,
+        } => {
+            let properties = destination_to_json(family, addr, port).ok_or_else(untranslated)?;
+            let kind = match nat_type {
+                NatType::SNat => "snat",
+                NatType::DNat => "dnat",
+            };
+            json!({ kind: Value::Object(properties) })
+        }
+        Statement::Tproxy { family, addr, port } => {
+            let properties = destination_to_json(family, addr, port).ok_or_else(untranslated)?;
+            json!({ "tproxy": Value::Object(properties) })
+        }
+        Statement::Untranslated(_) => return Err(untranslated()),
+    })
+}
+
+/// The properties of the NAT and tproxy statements.
+fn destination_to_json(
+    family: &Option<ProtocolFamily>,
+    addr: &Option<Vec<u8>>,
+    port: &Option<Vec<u8>>,
+) -> Option<Map<String, Value>> {
+    let mut properties = Map::new();
+    if let Some(family) = family {
+        properties.insert("family".into(), json!(family.to_string()));
+    }
+    if let Some(addr) = addr {
+        properties.insert("addr".into(), json!(address(addr)?.to_string()));
+    }
+    if let Some(port) = port {
+        properties.insert("port".into(), json!(be_integer(port)?));
+    }
+    Some(properties)
+}
+
+fn verdict_to_json(verdict: &Verdict) -> Option<Value> {
+    Some(match (verdict.get_code()?, verdict.get_chain()) {
+        (VerdictType::Jump, Some(chain)) => json!({ "jump": { "target": chain } }),
+        (VerdictType::Goto, Some(chain)) => json!({ "goto": { "target": chain } }),
+        (VerdictType::Accept, _) => json!({ "accept": null }),
+        (VerdictType::Drop, _) => json!({ "drop": null }),
+        (VerdictType::Continue, _) => json!({ "continue": null }),
+        (VerdictType::Return, _) => json!({ "return": null }),
+        _ => return None,
+    })
+}
+
+fn limit_to_json(limit: &Limit) -> Option<Value> {
+    let unit = limit.get_unit().copied().unwrap_or(1);
+    let (_, per) = LIMIT_UNITS.iter().find(|(u, _)| *u == unit)?;
+    let mut properties = Map::new();
+    properties.insert("rate".into(), json!(limit.get_rate().copied().unwrap_or(0)));
+    properties.insert("per".into(), json!(per));
+    if let Some(burst) = limit.get_burst().filter(|burst| **burst != 0) {
+        properties.insert("burst".into(), json!(burst));
+    }
+    if limit.get_limit_type() == Some(&NFT_LIMIT_PKT_BYTES) {
+        properties.insert("rate_unit".into(), json!("bytes"));
+        properties.insert("burst_unit".into(), json!("bytes"));
+    }
+    if limit.get_flags().copied().unwrap_or(0) & NFT_LIMIT_F_INV != 0 {
+        properties.insert("inv".into(), json!(true));
+    }
+    Some(json!({ "limit": Value::Object(properties) }))
+}
+
+/// Builds the expressions of a rule from its statements in JSON.
+struct RuleBuilder {
+    rule: Rule,
+    /// Whether the network protocol was matched, which nft leaves implicit in the fields of the
+    /// network headers (e.g. `ip saddr` in an inet table).
+    nfproto_matched: bool,
+    /// Whether the transport protocol was matched, which nft leaves implicit in the fields of
+    /// the transport headers (e.g. `tcp dport`).
+    l4proto_matched: bool,
+}
+
+impl RuleBuilder {
+    fn statement(&mut self, statement: &Value) -> Result<(), JsonError> {
+        let unsupported = || JsonError::Unsupported(format!("the statement {}", statement));
+        let (kind, properties) = statement
+            .as_object()
+            .and_then(|o| o.iter().next())
+            .ok_or_else(|| invalid("expr", statement))?;
+        let empty = Map::new();
+        let object = properties.as_object().unwrap_or(&empty);
+        let verdict = match kind.as_str() {
+            "accept" => Some(VerdictKind::Accept),
+            "drop" => Some(VerdictKind::Drop),
+            "continue" => Some(VerdictKind::Continue),
+            "return" => Some(VerdictKind::Return),
+            "jump" => Some(VerdictKind::Jump {
+                chain: get_str("jump", object, "target")?.to_string(),
+            }),
+            "goto" => Some(VerdictKind::Goto {
+                chain: get_str("goto", object, "target")?.to_string(),
+            }),
+            _ => None,
+        };
+        if let Some(verdict) = verdict {
+            self.rule.add_expr(Immediate::new_verdict(verdict));
+            return Ok(());
+        }
+        match kind.as_str() {
+            "match" => self.match_statement(object)?,
+            "vmap" => {
+                self.load(get("vmap", object, "key")?)?;
+                let set = set_reference(get("vmap", object, "data")?).ok_or_else(unsupported)?;
+                self.rule.add_expr(
+                    Lookup::default()
+                        .with_set(set)
+                        .with_sreg(Register::Reg1)
+                        .with_dreg(Register::Verdict),
+                );
+            }
+            "counter" => {
+                let count = |property| match object.get(property) {
+                    Some(value) => get_u64(value, property),
+                    None => Ok(0),
+                };
+                self.rule
+                    .add_expr(Counter::new(count("packets")?, count("bytes")?));
+            }
+            "log" => {
+                let group = match object.get("group") {
+                    Some(group) => Some(
+                        group
+                            .as_u64()
+                            .and_then(|g| u16::try_from(g).ok())
+                            .ok_or_else(|| invalid("group", group))?,
+                    ),
+                    None => None,
+                };
+                let prefix = match object.get("prefix") {
+                    Some(prefix) => Some(prefix.as_str().ok_or_else(|| invalid("prefix", prefix))?),
+                    None => None,
+                };
+                self.rule.add_expr(Log::new(group, prefix)?);
+            }
+            "limit" => self.rule.add_expr(parse_limit(object)?),
+            "reject" => self.rule.add_expr(parse_reject(object)?),
+            "masquerade" if object.is_empty() => self.rule.add_expr(Masquerade::default()),
+            "snat" | "dnat" => {
+                let nat_type = if kind == "snat" {
+                    NatType::SNat
+                } else {
+                    NatType::DNat
+                };
+                self.nat(nat_type, object)?;
+            }
+            "tproxy" => self.tproxy(object)?,
+            _ => return Err(unsupported()),
+        }
+        Ok(())
+    }
+
+    fn match_statement(&mut self, object: &Map<String, Value>) -> Result<(), JsonError> {
+        let op_value = get("match", object, "op")?;
+        let op = match op_value.as_str() {
+            Some("==") | Some("in") => CmpOp::Eq,
+            Some("!=") => CmpOp::Neq,
+            Some("<") => CmpOp::Lt,
+            Some("<=") => CmpOp::Lte,
+            Some(">") => CmpOp::Gt,
+            Some(">=") => CmpOp::Gte,
+            _ => return Err(invalid("op", op_value)),
+        };
+        let right = get("match", object, "right")?;
+        let (kind, len) = self.load(get("match", object, "left")?)?;
+        if let Some(set) = set_reference(right) {
+            let lookup = Lookup::default().with_set(set).with_sreg(Register::Reg1);
+            match op {
+                CmpOp::Eq => self.rule.add_expr(lookup),
+                CmpOp::Neq => self.rule.add_expr(lookup.inverted()),
+                _ => return Err(invalid("op", op_value)),
+            }
+            return Ok(());
+        }
+        match (kind, right.get("prefix")) {
+            (ValueKind::CtState, _) => {
+                let mut states = ConnTrackState::empty();
+                for state in get_list_value(right) {
+                    states |= CT_STATE_NAMES
+                        .iter()
+                        .find(|(_, name)| Some(*name) == state.as_str())
+                        .map(|(state, _)| *state)
+                        .ok_or_else(|| invalid("right", right))?;
+                }
+                // the states are matched like `Rule::ct_states` does, in host byte order
+                self.rule.add_expr(Bitwise::new(
+                    states.bits().to_ne_bytes(),
+                    0u32.to_be_bytes(),
+                )?);
+                let op = if op == CmpOp::Neq {
+                    CmpOp::Eq
+                } else {
+                    CmpOp::Neq
+                };
+                self.rule.add_expr(Cmp::new(op, 0u32.to_be_bytes()));
+            }
+            (ValueKind::Address, Some(prefix)) => {
+                let addr = prefix
+                    .get("addr")
+                    .and_then(Value::as_str)
+                    .and_then(|addr| IpAddr::from_str(addr).ok())
+                    .ok_or_else(|| invalid("prefix", prefix))?;
+                let prefix_len = prefix
+                    .get("len")
+                    .and_then(Value::as_u64)
+                    .and_then(|len| u8::try_from(len).ok())
+                    .ok_or_else(|| invalid("prefix", prefix))?;
+                let network = ipnetwork::IpNetwork::new(addr, prefix_len)
+                    .map_err(|_| invalid("prefix", prefix))?;
+                self.rule
+                    .add_expr(Bitwise::new(IpOperand::from(network.mask()), vec![0; len])?);
+                self.rule.add_expr(Cmp::new_ip(op, network.network()));
+            }
+            (kind, _) => {
+                let data = parse_value(kind, len, right)?;
+                if op == CmpOp::Eq && len == 1 {
+                    match kind {
+                        ValueKind::NfProto => self.nfproto_matched = true,
+                        ValueKind::L4Proto => self.l4proto_matched = true,
+                        _ => {}
+                    }
+                }
+                self.rule.add_expr(Cmp::new(op, data));
+            }
+        }
+        Ok(())
+    }
+
+    /// Loads `operand` in the first register, and returns how its values are written along
+    /// with their length.
+    fn load(&mut self, operand: &Value) -> Result<(ValueKind, usize), JsonError> {
+        let unsupported = || JsonError::Unsupported(format!("the expression {}", operand));
+        let (kind, properties) = operand
+            .as_object()
+            .and_then(|o| o.iter().next())
+            .ok_or_else(|| invalid("left", operand))?;
+        let empty = Map::new();
+        let properties = properties.as_object().unwrap_or(&empty);
+        match kind.as_str() {
+            "meta" => {
+                let name = get_str("meta", properties, "key")?;
+                let key = (0..=64)
+                    .filter_map(|key| MetaType::try_from(key).ok())
+                    .find(|key| key.to_string() == name)
+                    .ok_or_else(unsupported)?;
+                self.rule.add_expr(Meta::new(key));
+                let len = match key {
+                    MetaType::NfProto | MetaType::L4Proto => 1,
+                    MetaType::Protocol | MetaType::IifType | MetaType::OifType => 2,
+                    MetaType::IifName
+                    | MetaType::OifName
+                    | MetaType::BriIifName
+                    | MetaType::BriOifName => libc::IFNAMSIZ,
+                    _ => 4,
+                };
+                Ok((meta_value_kind(key), len))
+            }
+            "ct" => {
+                let key = match get_str("ct", properties, "key")? {
+                    "state" => ConntrackKey::State,
+                    "mark" => ConntrackKey::Mark,
+                    _ => return Err(unsupported()),
+                };
+                self.rule.add_expr(Conntrack::new(key));
+                Ok((Operand::Ct(key).kind(), 4))
+            }
+            "payload" if properties.contains_key("base") => {
+                let base = get_str("payload", properties, "base")?;
+                let base = PAYLOAD_BASES
+                    .iter()
+                    .find(|(_, name)| *name == base)
+                    .map(|(base, _)| *base)
+                    .ok_or_else(unsupported)?;
+                let offset = get_u64(get("payload", properties, "offset")?, "offset")?;
+                let len = get_u64(get("payload", properties, "len")?, "len")?;
+                if offset % 8 != 0 || len % 8 != 0 || len == 0 || len > 32 {
+                    return Err(unsupported());
+                }
+                let offset = u32::try_from(offset / 8).map_err(|_| unsupported())?;
+                self.rule.add_expr(
+                    Payload::default()
+                        .with_base(base)
+                        .with_offset(offset)
+                        .with_len(len as u32 / 8)
+                        .with_dreg(Register::Reg1),
+                );
+                Ok((ValueKind::Raw, len as usize / 8))
+            }
+            "payload" => {
+                let protocol = get_str("payload", properties, "protocol")?;
+                let name = get_str("payload", properties, "field")?;
+                let field = HEADER_FIELDS
+                    .iter()
+                    .find(|f| f.protocol == protocol && f.field == name)
+                    .ok_or_else(unsupported)?;
+                self.implicit_dependencies(protocol)?;
+                self.rule.add_expr(
+                    Payload::default()
+                        .with_base(field.base)
+                        .with_offset(field.offset)
+                        .with_len(field.len)
+                        .with_dreg(Register::Reg1),
+                );
+                if field.kind == ValueKind::L4Proto {
+                    // `ip protocol tcp` matches the transport protocol like `meta l4proto tcp`
+                    self.l4proto_matched = true;
+                }
+                Ok((field.kind, field.len as usize))
+            }
+            _ => Err(unsupported()),
+        }
+    }
+
+    /// Matches the protocols nft leaves implicit before the fields of `protocol`.
+    fn implicit_dependencies(&mut self, protocol: &str) -> Result<(), JsonError> {
+        let (key, proto, matched) = match protocol {
+            "ip" => (
+                MetaType::NfProto,
+                libc::NFPROTO_IPV4,
+                &mut self.nfproto_matched,
+            ),
+            "ip6" => (
+                MetaType::NfProto,
+                libc::NFPROTO_IPV6,
+                &mut self.nfproto_matched,
+            ),
+            "tcp" => (
+                MetaType::L4Proto,
+                libc::IPPROTO_TCP,
+                &mut self.l4proto_matched,
+            ),
+            "udp" => (
+                MetaType::L4Proto,
+                libc::IPPROTO_UDP,
+                &mut self.l4proto_matched,
+            ),
+            "icmp" => (
+                MetaType::L4Proto,
+                libc::IPPROTO_ICMP,
+                &mut self.l4proto_matched,
+            ),
+            "icmpv6" => (
+                MetaType::L4Proto,
+                libc::IPPROTO_ICMPV6,
+                &mut self.l4proto_matched,
+            ),
+            _ => return Ok(()),
+        };
+        if !*matched {
+            *matched = true;
+            self.rule.add_expr(Meta::new(key));
+            self.rule.add_expr(Cmp::new(CmpOp::Eq, [proto as u8]));
+        }
+        Ok(())
+    }
+
+    fn nat(&mut self, nat_type: NatType, object: &Map<String, Value>) -> Result<(), JsonError> {
+        let (addr, port, family) = self.load_destination(object)?;
+        let mut nat = Nat::default().with_nat_type(nat_type);
+        if let Some(reg) = addr {
+            nat.set_ip_register(reg);
+        }
+        if let Some(reg) = port {
+            nat.set_port_register(reg);
+        }
+        let family = family.unwrap_or_else(|| self.rule.get_family());
+        self.rule.add_expr(nat.with_family(family));
+        Ok(())
+    }
+
+    fn tproxy(&mut self, object: &Map<String, Value>) -> Result<(), JsonError> {
+        let (addr, port, family) = self.load_destination(object)?;
+        let mut tproxy = Tproxy::default();
+        if let Some(reg) = addr {
+            tproxy.set_addr_register(reg);
+        }
+        if let Some(reg) = port {
+            tproxy.set_port_register(reg);
+        }
+        // a port alone applies to both families in inet tables
+        let family = family.unwrap_or_else(|| match self.rule.get_family() {
+            ProtocolFamily::Inet => ProtocolFamily::Unspec,
+            family => family,
+        });
+        self.rule.add_expr(tproxy.with_family(family));
+        Ok(())
+    }
+
+    /// Loads the address and the port of a NAT or tproxy statement in the registers 1 and 2, and
+    /// returns the registers along with the family of the address, if it is known.
+    fn load_destination(
+        &mut self,
+        object: &Map<String, Value>,
+    ) -> Result<(Option<Register>, Option<Register>, Option<ProtocolFamily>), JsonError> {
+        let (mut addr_reg, mut port_reg, mut family) = (None, None, None);
+        if let Some(addr) = object.get("addr") {
+            let ip = addr
+                .as_str()
+                .and_then(|addr| IpAddr::from_str(addr).ok())
+                .ok_or_else(|| invalid("addr", addr))?;
+            self.rule.add_expr(Immediate::new_ip(ip, Register::Reg1));
+            addr_reg = Some(Register::Reg1);
+            family = Some(IpOperand::from(ip).family());
+        }
+        if let Some(port) = object.get("port") {
+            let port = port
+                .as_u64()
+                .and_then(|port| u16::try_from(port).ok())
+                .ok_or_else(|| invalid("port", port))?;
+            self.rule.add_expr(Immediate::new_data(
+                port.to_be_bytes().to_vec(),
+                Register::Reg2,
+            ));
+            port_reg = Some(Register::Reg2);
+        }
+        if let Some(name) = object.get("family") {
+            family = Some(match name.as_str() {
+                Some("ip") => ProtocolFamily::Ipv4,
+                Some("ip6") => ProtocolFamily::Ipv6,
+                _ => return Err(invalid("family", name)),
+            });
+        }
+        Ok((addr_reg, port_reg, family))
+    }
+}
+
+/// The name of the set referenced by `value`, e.g. `@blocked`.
+fn set_reference(value: &Value) -> Option<&str> {
+    value.as_str()?.strip_prefix('@')
+}
+
+/// Encodes `value` on `len` bytes, written as `kind`.
+fn parse_value(kind: ValueKind, len: usize, value: &Value) -> Result<Vec<u8>, JsonError> {
+    let invalid = || invalid("right", value);
+    let protocol = |names: &[(i32, &str)]| match value {
+        Value::String(name) => names
+            .iter()
+            .find(|(_, n)| n == name)
+            .map(|(proto, _)| vec![*proto as u8])
+            .ok_or_else(invalid),
+        value => Ok(vec![value
+            .as_u64()
+            .and_then(|v| u8::try_from(v).ok())
+            .ok_or_else(invalid)?]),
+    };
+    let number = || {
+        let max = if len >= 8 {
+            u64::MAX
+        } else {
+            (1 << (len * 8)) - 1
+        };
+        value.as_u64().filter(|v| *v <= max).ok_or_else(invalid)
+    };
+    match kind {
+        ValueKind::NfProto => protocol(&NFPROTO_NAMES),
+        ValueKind::L4Proto => protocol(&L4PROTO_NAMES),
+        ValueKind::Integer | ValueKind::Raw => {
+            Ok(number()?.to_be_bytes()[8 - len.min(8)..].to_vec())
+        }
+        ValueKind::HostInteger | ValueKind::Mark => Ok((number()? as u32).to_ne_bytes().to_vec()),
+        ValueKind::Interface => {
+            let name = value.as_str().ok_or_else(invalid)?;
+            if name.len() >= libc::IFNAMSIZ {
+                return Err(BuilderError::InterfaceNameTooLong.into());
+            }
+            let mut data = name.as_bytes().to_vec();
+            data.push(0);
+            Ok(data)
+        }
+        ValueKind::Address => {
+            let addr = value
+                .as_str()
+                .and_then(|addr| IpAddr::from_str(addr).ok())
+                .ok_or_else(invalid)?;
+            Ok(IpOperand::from(addr).to_vec())
+        }
+        ValueKind::CtState => Err(invalid()),
+    }
+}
+
+fn parse_limit(object: &Map<String, Value>) -> Result<Limit, JsonError> {
+    let rate = get_u64(get("limit", object, "rate")?, "rate")?;
+    let per = object
+        .get("per")
+        .and_then(Value::as_str)
+        .unwrap_or("second");
+    let (unit, _) = LIMIT_UNITS
+        .iter()
+        .find(|(_, name)| *name == per)
+        .ok_or_else(|| invalid("per", &object["per"]))?;
+    let limit_type = match object.get("rate_unit").and_then(Value::as_str) {
+        None | Some("packets") => NFT_LIMIT_PKTS,
+        Some("bytes") => NFT_LIMIT_PKT_BYTES,
+        Some(_) => {
+            return Err(JsonError::Unsupported(
+                "the limits in kbytes or mbytes".into(),
+            ))
+        }
+    };
+    let mut limit = Limit::new(rate, *unit).with_limit_type(limit_type);
+    if let Some(burst) = object.get("burst") {
+        let burst = burst
+            .as_u64()
+            .and_then(|burst| u32::try_from(burst).ok())
+            .ok_or_else(|| invalid("burst", burst))?;
+        limit = limit.burst(burst);
+    }
+    if object.get("inv").and_then(Value::as_bool) == Some(true) {
+        limit = limit.over();
+    }
+    Ok(limit)
+}
+
+fn parse_reject(object: &Map<String, Value>) -> Result<Reject, JsonError> {
+    let code = object.get("expr");
+    let named = |names: &[(u8, &str)]| {
+        let code = code.unwrap_or(&Value::Null);
+        names
+            .iter()
+            .find(|(_, name)| code.as_str() == Some(*name))
+            .map(|(value, _)| *value)
+            .ok_or_else(|| invalid("expr", code))
+    };
+    let reject = match (object.get("type").and_then(Value::as_str), code) {
+        (None, None) => Reject::default(),
+        (Some("tcp reset"), None) => Reject::default().with_type(RejectType::TcpRst),
+        (Some("icmpx"), Some(_)) => Reject::default()
+            .with_type(RejectType::IcmpxUnreach)
+            .with_code(named(&ICMPX_CODE_NAMES)?),
+        (Some("icmp"), Some(_)) => Reject::icmp(named(&ICMP_CODE_NAMES)?),
+        (Some("icmpv6"), Some(_)) => Reject::icmp(named(&ICMPV6_CODE_NAMES)?),
+        _ => {
+            return Err(JsonError::Unsupported(format!(
+                "the reject statement {:?}",
+                object
+            )))
+        }
+    };
+    Ok(reject)
+}
@@ -0,0 +1,219 @@
+//! Rule templates for the "killswitch" of VPN clients: a table that drops all the traffic,
+//! except for the few flows that must keep working when the tunnel is down (loopback, DHCP, the
+//! DNS servers, the VPN server itself) and the traffic of the tunnel.
+//!
+//! ```ignore
+//! Killswitch::new("killswitch")
+//!     .allow_loopback()
+//!     .allow_established()
+//!     .allow_dhcp(Some("eth0"))
+//!     .allow_dns([IpAddr::V4(Ipv4Addr::new(10, 64, 0, 1))])
+//!     .allow_endpoint(server_ip, 51820, Protocol::UDP)
+//!     .allow_interface("wg0")
+//!     .to_batch()?
+//!     .send()?;
+//! ```
+//!
+//! The table is flushed before the rules are added, so the same killswitch can be applied again
+//! (e.g. with other DNS servers) when the tunnel reconnects.
+
+use std::net::IpAddr;
+
+use crate::error::BuilderError;
+use crate::{
+    Batch, Chain, ChainPolicy, ChainType, Hook, HookClass, MsgType, Protocol, ProtocolFamily, Rule,
+    Table,
+};
+
+const DHCP_SERVER_PORT: u16 = 67;
+const DHCP_CLIENT_PORT: u16 = 68;
+const DNS_PORT: u16 = 53;
+
+/// A flow allowed through the killswitch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Template {
+    /// The traffic of the loopback interface.
+    Loopback,
+    /// The packets of the connections that were already accepted, in both directions.
+    Established,
+    /// The DHCPv4 exchanges, on the interface `iface` if set, or on any interface.
+    Dhcp { iface: Option<String> },
+    /// The DNS queries (over UDP and TCP) to `servers`.
+    Dns { servers: Vec<IpAddr> },
+    /// All the traffic of the interface, e.g. the tunnel.
+    Interface { name: String },
+    /// The traffic to a single endpoint, e.g. the VPN server.
+    Endpoint {
+        addr: IpAddr,
+        port: u16,
+        protocol: Protocol,
+    },
+}
+
+impl Template {
+    /// Adds the rules of the template to `batch`, in the base chains `input` and `output`.
+    pub fn add_rules(
+        &self,
+        input: &Chain,
+        output: &Chain,
+        batch: &mut Batch,
+    ) -> Result<(), BuilderError> {
+        let mut rules = Vec::new();
+        match self {
+            Template::Loopback => {
+                rules.push(Rule::new(input)?.iiface("lo")?.accept());
+                rules.push(Rule::new(output)?.oiface("lo")?.accept());
+            }
+            Template::Established => {
+                rules.push(Rule::new(input)?.established_or_related()?.accept());
+                rules.push(Rule::new(output)?.established_or_related()?.accept());
+            }
+            Template::Dhcp { iface } => {
+                let (mut request, mut response) = (Rule::new(output)?, Rule::new(input)?);
+                if let Some(iface) = iface {
+                    request = request.oiface(iface)?;
+                    response = response.iiface(iface)?;
+                }
+                rules.push(
+                    request
+                        .sport(DHCP_CLIENT_PORT, Protocol::UDP)
+                        .dport(DHCP_SERVER_PORT, Protocol::UDP)
+                        .accept(),
+                );
+                rules.push(
+                    response
+                        .sport(DHCP_SERVER_PORT, Protocol::UDP)
+                        .dport(DHCP_CLIENT_PORT, Protocol::UDP)
+                        .accept(),
+                );
+            }
+            Template::Dns { servers } => {
+                // the responses are accepted as part of the established connections
+                for &server in servers {
+                    for protocol in [Protocol::UDP, Protocol::TCP] {
+                        rules.push(
+                            Rule::new(output)?
+                                .daddr(server)
+                                .dport(DNS_PORT, protocol)
+                                .accept(),
+                        );
+                    }
+                }
+            }
+            Template::Interface { name } => {
+                rules.push(Rule::new(input)?.iiface(name)?.accept());
+                rules.push(Rule::new(output)?.oiface(name)?.accept());
+            }
+            Template::Endpoint {
+                addr,
+                port,
+                protocol,
+            } => {
+                rules.push(
+                    Rule::new(output)?
+                        .daddr(*addr)
+                        .dport(*port, *protocol)
+                        .accept(),
+                );
+            }
+        }
+        batch.add_iter(rules.into_iter(), MsgType::Add);
+        Ok(())
+    }
+}
+
+/// An inet table dropping all the traffic of the host, except for the flows allowed by its
+/// [`Template`]s. Only the `input` and `output` hooks are filtered: the forwarded traffic is left
+/// untouched.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Killswitch {
+    table: Table,
+    templates: Vec<Template>,
+}
+
+impl Killswitch {
+    pub fn new(table_name: impl Into<String>) -> Self {
+        Killswitch {
+            table: Table::new(ProtocolFamily::Inet).with_name(table_name.into()),
+            templates: Vec::new(),
+        }
+    }
+
+    pub fn get_table(&self) -> &Table {
+        &self.table
+    }
+
+    pub fn get_templates(&self) -> &[Template] {
+        &self.templates
+    }
+
+    pub fn with_template(mut self, template: Template) -> Self {
+        self.templates.push(template);
+        self
+    }
+
+    pub fn allow_loopback(self) -> Self {
+        self.with_template(Template::Loopback)
+    }
+
+    pub fn allow_established(self) -> Self {
+        self.with_template(Template::Established)
+    }
+
+    pub fn allow_dhcp(self, iface: Option<&str>) -> Self {
+        self.with_template(Template::Dhcp {
+            iface: iface.map(String::from),
+        })
+    }
+
+    pub fn allow_dns(self, servers: impl IntoIterator<Item = IpAddr>) -> Self {
+        self.with_template(Template::Dns {
+            servers: servers.into_iter().collect(),
+        })
+    }
+
+    pub fn allow_interface(self, name: impl Into<String>) -> Self {
+        self.with_template(Template::Interface { name: name.into() })
+    }
+
+    pub fn allow_endpoint(self, addr: IpAddr, port: u16, protocol: Protocol) -> Self {
+        self.with_template(Template::Endpoint {
+            addr,
+            port,
+            protocol,
+        })
+    }
+
+    /// Creates a new [`Batch`] holding the table, its chains and the rules of the templates.
+    pub fn to_batch(&self) -> Result<Batch, BuilderError> {
+        let mut batch = Batch::new();
+        self.add_to_batch(&mut batch)?;
+        Ok(batch)
+    }
+
+    /// Adds the table to `batch` and flushes it, then adds the `input` and `output` chains,
+    /// which drop the packets that are not accepted by the rules of the templates.
+    pub fn add_to_batch(&self, batch: &mut Batch) -> Result<(), BuilderError> {
+        batch.add(&self.table, MsgType::Add);
+        self.table.flush(batch)?;
+
+        let mut base_chain = |name: &str, class: HookClass| {
+            let chain = Chain::new(&self.table)
+                .with_name(name)
+                .with_hook(Hook::new(class, 0))
+                .with_type(ChainType::Filter)
+                .with_policy(ChainPolicy::Drop);
+            batch.add(&chain, MsgType::Add);
+            chain
+        };
+        let input = base_chain("input", HookClass::In);
+        let output = base_chain("output", HookClass::Out);
+
+        for template in &self.templates {
+            template.add_rules(&input, &output, batch)?;
+        }
+        Ok(())
+    }
+}
@@ -41,50 +41,188 @@
 //! Contributions are welcome!
 //!
 //! [`nftables`]: https://netfilter.org/projects/nftables/
+//!
+//! # Features
+//!
+//! - `serde`: implements `Serialize` and `Deserialize` for the objects and expressions.
+//! - `capture`: allows recording the messages received from the kernel to a file, see
+//!   [`capture`].
+//! - `json`: reads and writes rulesets in the JSON format of `nft -j`, see [`json`].
+//! - `iptables`: translates the rules saved by `iptables-save` into rules, see [`iptables`].
+//! - `socket` (enabled by default): everything that talks to the kernel (the [`query`] and
+//!   [`monitor`] modules, [`Batch::send`], the listing functions, ...), which depends on `nix`.
+//!   Without it (`default-features = false`), only the types that build and parse netlink
+//!   messages are left, for users that hand the messages over to their own privileged process.
+//! - `tokio`: async variants of the listings and of the batch sends, e.g.
+//!   `query::list_objects_with_data_async` and `Batch::send_async`, awaited on the tokio runtime
+//!   of the caller. Implies `socket`.
+//! - `zeroize`: overwrites the buffers of the crate holding netlink messages with zeros once they
+//!   are used, see [below](#ruleset-data-in-memory).
+//!
+//! # Ruleset data in memory
+//!
+//! The messages exchanged with the kernel describe the firewall, which may be sensitive (e.g.
+//! the addresses of an allow-list). With the `zeroize` feature, the buffers the crate allocates
+//! for them are overwritten with zeros, in a way the compiler cannot optimize out:
+//! - the messages of a [`Batch`] (and thus of a [`Transaction`] or a [`TransactionSequence`])
+//!   when it is dropped, and the finalized copies sent by [`Batch::send`] and its variants once
+//!   they are sent;
+//! - the responses received in a [`query::QueryBuffer`] after every query and when it is dropped,
+//!   which also applies to the buffers of [`ChainStatsSampler`] and of the batches sent;
+//! - the receive buffers of [`monitor::monitor_events`] and [`monitor::EventStream`].
+//!
+//! The feature does not cover:
+//! - the objects themselves ([`Table`], [`Chain`], [`Rule`], [`Set`], [`set::SetElementList`],
+//!   [`Obj`], the expressions, ...), nor the values derived from them ([`BatchObject`],
+//!   [`CreatedObject`], [`plan::Plan`], [`RawAttributeTree`], the errors of the kernel, ...),
+//!   which hold the ruleset for as long as the application keeps them;
+//! - the buffers handed over to the application, like the result of [`Batch::finalize`] or of
+//!   [`NfNetlinkObject::to_batch_message`];
+//! - the previous allocations of the buffers that grew: a batch reallocates its buffer when it
+//!   outgrows its capacity, which frees the previous one without wiping it, so batches holding
+//!   sensitive rulesets should be created with [`Batch::with_capacity`];
+//! - the copies made by the kernel and by the `capture` feature, which writes the responses to a
+//!   file.
+//!
+//! [`QueryBuffer::wipe`](query::QueryBuffer::wipe) wipes a buffer reused across queries
+//! explicitly, without the feature.
 
 #[macro_use]
 extern crate log;
 
 use libc;
 
-use rustables_macros::nfnetlink_enum;
-use std::convert::TryFrom;
+use error::DecodeError;
 
 mod batch;
-pub use batch::{default_batch_page_size, Batch};
+pub use batch::{
+    default_batch_page_size, AckMode, Batch, BatchMarker, BatchMarkerKind, BatchObject, Rollback,
+    SequenceProgress, Transaction, TransactionSequence,
+};
+#[cfg(feature = "socket")]
+pub use batch::{CreatedObject, PendingBatch};
+
+pub mod blocklist;
+
+#[cfg(feature = "capture")]
+pub mod capture;
+
+pub mod config;
 
 pub mod data_type;
 
+mod device_list;
+pub use device_list::DeviceList;
+
 mod table;
-pub use table::list_tables;
 pub use table::Table;
+#[cfg(feature = "socket")]
+pub use table::{list_tables, list_tables_for_family};
+
+mod table_contents;
+pub use table_contents::TableContents;
+
+mod table_scope;
+pub use table_scope::TableScope;
 
 mod chain;
-pub use chain::list_chains_for_table;
-pub use chain::{Chain, ChainPolicy, ChainPriority, ChainType, Hook, HookClass};
+#[cfg(feature = "socket")]
+pub use chain::{list_chains_for_family, list_chains_for_table};
+pub use chain::{
+    Chain, ChainBuilder, ChainFlags, ChainPolicy, ChainPriority, ChainType, Hook, HookClass,
+};
+
+mod chain_priority;
+pub use chain_priority::{StandardPriority, SymbolicPriority};
+
+mod chain_stats;
+pub use chain_stats::{ChainSample, RuleSample};
+#[cfg(feature = "socket")]
+pub use chain_stats::{ChainStatsSampler, Samples};
+
+mod chain_tree;
+pub use chain_tree::ChainTree;
 
 pub mod error;
 
+mod flowtable;
+#[cfg(feature = "socket")]
+pub use flowtable::list_flowtables_for_table;
+pub use flowtable::{Flowtable, FlowtableFlags, FlowtableHook};
+
+pub mod compat;
+
+mod generation;
+#[cfg(feature = "socket")]
+pub use generation::get_generation;
+pub use generation::Generation;
+
+pub mod groups;
+
+#[cfg(feature = "iptables")]
+pub mod iptables;
+
+#[cfg(feature = "json")]
+pub mod json;
+
+pub mod killswitch;
+
+mod metrics;
+pub use metrics::{metrics, Metrics};
+
+mod nft_syntax;
+
+#[cfg(feature = "socket")]
+pub mod monitor;
+
+#[cfg(feature = "socket")]
 pub mod query;
 
 pub(crate) mod nlmsg;
+pub use nlmsg::{
+    AttributeDecoder, AttributeMissing, AttributePresent, NetlinkType, NfNetlinkAttribute,
+    NfNetlinkDeserializable, NfNetlinkObject, NfNetlinkWriter,
+};
 pub(crate) mod parser;
+pub use parser::{
+    attribute_order, decode_mode, parse_response_stream, with_attribute_order, with_decode_mode,
+    AttributeOrder, DecodeMode, ResponseStream,
+};
 pub(crate) mod parser_impls;
 
+mod raw_attributes;
+pub use raw_attributes::{RawAttribute, RawAttributeTree, RawAttributeValue};
+
 mod rule;
-pub use rule::list_rules_for_chain;
-pub use rule::Rule;
+#[cfg(feature = "socket")]
+pub use rule::{
+    list_rules_for_chain, list_rules_for_chain_with_reset, list_rules_for_family,
+    list_rules_jumping_to,
+};
+pub use rule::{Rule, RuleSummary};
 
 pub mod expr;
 
 mod rule_methods;
-pub use rule_methods::{iface_index, Protocol};
+pub use rule_methods::{iface_index, IcmpType, Icmpv6Type, Protocol};
 
 pub mod set;
-pub use set::Set;
+pub use set::{Set, SetFlags};
+
+mod set_userdata;
+
+pub mod obj;
+pub use obj::Obj;
+
+pub mod plan;
 
 pub mod sys;
 
+mod zeroize;
+
+#[cfg(feature = "serde")]
+mod serde_helpers;
+
 #[cfg(test)]
 mod tests;
 
@@ -103,21 +241,86 @@ pub enum MsgType {
     Add,
     /// Remove the object from netfilter.
     Del,
+    /// Replace an existing rule with this one (`NLM_F_REPLACE`): a [`Rule`] replaces the rule
+    /// designated by its handle, see [`Rule::with_handle`]. The kernel refuses to replace the
+    /// other objects with `EOPNOTSUPP`, which [`Batch::add_checked`] reports before sending.
+    Replace,
+    /// Add the object in front of the existing ones: a [`Rule`] is inserted at the beginning of
+    /// its chain, or right before the rule designated by its position (see
+    /// [`Rule::with_position`]). The other objects are added as with `MsgType::Add`.
+    Insert,
 }
 
 /// Denotes a protocol. Used to specify which protocol a table or set belongs to.
+///
+/// The families unknown to this crate, e.g. introduced by a kernel more recent than it, are
+/// decoded as [`ProtocolFamily::Other`] rather than rejected, so that listing the ruleset does not
+/// fail as a whole. Use [`ProtocolFamily::from`] to convert a raw value, which never produces an
+/// `Other` variant holding a known family.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
-#[nfnetlink_enum(i32)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum ProtocolFamily {
-    Unspec = libc::NFPROTO_UNSPEC,
+    Unspec,
     /// Inet - Means both IPv4 and IPv6
-    Inet = libc::NFPROTO_INET,
-    Ipv4 = libc::NFPROTO_IPV4,
-    Arp = libc::NFPROTO_ARP,
-    NetDev = libc::NFPROTO_NETDEV,
-    Bridge = libc::NFPROTO_BRIDGE,
-    Ipv6 = libc::NFPROTO_IPV6,
-    DecNet = libc::NFPROTO_DECNET,
+    Inet,
+    Ipv4,
+    Arp,
+    NetDev,
+    Bridge,
+    Ipv6,
+    DecNet,
+    /// A family this crate does not know about, holding its raw `NFPROTO_*` value.
+    Other(i32),
+}
+
+impl From<i32> for ProtocolFamily {
+    fn from(value: i32) -> Self {
+        match value {
+            libc::NFPROTO_UNSPEC => ProtocolFamily::Unspec,
+            libc::NFPROTO_INET => ProtocolFamily::Inet,
+            libc::NFPROTO_IPV4 => ProtocolFamily::Ipv4,
+            libc::NFPROTO_ARP => ProtocolFamily::Arp,
+            libc::NFPROTO_NETDEV => ProtocolFamily::NetDev,
+            libc::NFPROTO_BRIDGE => ProtocolFamily::Bridge,
+            libc::NFPROTO_IPV6 => ProtocolFamily::Ipv6,
+            libc::NFPROTO_DECNET => ProtocolFamily::DecNet,
+            value => ProtocolFamily::Other(value),
+        }
+    }
+}
+
+impl From<ProtocolFamily> for i32 {
+    fn from(family: ProtocolFamily) -> Self {
+        match family {
+            ProtocolFamily::Unspec => libc::NFPROTO_UNSPEC,
+            ProtocolFamily::Inet => libc::NFPROTO_INET,
+            ProtocolFamily::Ipv4 => libc::NFPROTO_IPV4,
+            ProtocolFamily::Arp => libc::NFPROTO_ARP,
+            ProtocolFamily::NetDev => libc::NFPROTO_NETDEV,
+            ProtocolFamily::Bridge => libc::NFPROTO_BRIDGE,
+            ProtocolFamily::Ipv6 => libc::NFPROTO_IPV6,
+            ProtocolFamily::DecNet => libc::NFPROTO_DECNET,
+            ProtocolFamily::Other(value) => value,
+        }
+    }
+}
+
+impl NfNetlinkAttribute for ProtocolFamily {
+    fn get_size(&self) -> usize {
+        i32::from(*self).get_size()
+    }
+
+    fn write_payload(&self, addr: &mut [u8]) {
+        i32::from(*self).write_payload(addr);
+    }
+}
+
+impl NfNetlinkDeserializable for ProtocolFamily {
+    fn deserialize(buf: &[u8]) -> Result<(Self, &[u8]), DecodeError> {
+        let (v, remaining_data) = i32::deserialize(buf)?;
+        Ok((ProtocolFamily::from(v), remaining_data))
+    }
 }
 
 impl Default for ProtocolFamily {
@@ -125,3 +328,20 @@ impl Default for ProtocolFamily {
         ProtocolFamily::Unspec
     }
 }
+
+impl std::fmt::Display for ProtocolFamily {
+    /// Displays the family with the name nft uses for it, e.g. `ip6` for [`ProtocolFamily::Ipv6`].
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ProtocolFamily::Unspec => "unspec",
+            ProtocolFamily::Inet => "inet",
+            ProtocolFamily::Ipv4 => "ip",
+            ProtocolFamily::Arp => "arp",
+            ProtocolFamily::NetDev => "netdev",
+            ProtocolFamily::Bridge => "bridge",
+            ProtocolFamily::Ipv6 => "ip6",
+            ProtocolFamily::DecNet => "decnet",
+            ProtocolFamily::Other(value) => return write!(f, "family {}", value),
+        })
+    }
+}
@@ -0,0 +1,66 @@
+//! Process-wide counters of the exchanges with the kernel, e.g. to export the health of a
+//! long-running firewall daemon:
+//!
+//! ```ignore
+//! let metrics = rustables::metrics();
+//! println!(
+//!     "{} batches committed, {} decode errors",
+//!     metrics.batches_committed, metrics.decode_errors
+//! );
+//! ```
+//!
+//! The counters are updated by every socket of the crate, and are only reset when the process
+//! exits. They never decrease, so rates can be computed from successive snapshots.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+static MESSAGES_SENT: AtomicU64 = AtomicU64::new(0);
+static BATCHES_COMMITTED: AtomicU64 = AtomicU64::new(0);
+static DECODE_ERRORS: AtomicU64 = AtomicU64::new(0);
+static BUSY_ERRORS: AtomicU64 = AtomicU64::new(0);
+
+/// A snapshot of the counters, returned by [`metrics`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Metrics {
+    /// The netlink messages sent to the kernel, including the begin and end messages of the
+    /// batches.
+    pub messages_sent: u64,
+    /// The batches the kernel acknowledged.
+    pub batches_committed: u64,
+    /// The responses of the kernel that could not be decoded.
+    pub decode_errors: u64,
+    /// The requests the kernel refused with `EBUSY`, e.g. because a concurrent transaction was
+    /// in progress, or the deleted object was still in use. These requests are usually retried.
+    pub busy_errors: u64,
+}
+
+/// Returns the current value of the counters.
+pub fn metrics() -> Metrics {
+    Metrics {
+        messages_sent: MESSAGES_SENT.load(Ordering::Relaxed),
+        batches_committed: BATCHES_COMMITTED.load(Ordering::Relaxed),
+        decode_errors: DECODE_ERRORS.load(Ordering::Relaxed),
+        busy_errors: BUSY_ERRORS.load(Ordering::Relaxed),
+    }
+}
+
+#[cfg_attr(not(feature = "socket"), allow(dead_code))]
+pub(crate) fn record_messages_sent(count: u64) {
+    MESSAGES_SENT.fetch_add(count, Ordering::Relaxed);
+}
+
+#[cfg_attr(not(feature = "socket"), allow(dead_code))]
+pub(crate) fn record_batch_committed() {
+    BATCHES_COMMITTED.fetch_add(1, Ordering::Relaxed);
+}
+
+#[cfg_attr(not(feature = "socket"), allow(dead_code))]
+pub(crate) fn record_decode_error() {
+    DECODE_ERRORS.fetch_add(1, Ordering::Relaxed);
+}
+
+#[cfg_attr(not(feature = "socket"), allow(dead_code))]
+pub(crate) fn record_busy_error() {
+    BUSY_ERRORS.fetch_add(1, Ordering::Relaxed);
+}
@@ -0,0 +1,340 @@
+//! Blocking monitoring of the changes made to the ruleset.
+//!
+//! The kernel notifies the sockets subscribed to the `NFNLGRP_NFTABLES` multicast group of every
+//! change of the ruleset. [`monitor_events`] listens on such a socket and hands every
+//! notification to a callback, until it is stopped through a [`MonitorShutdown`] handle.
+//! [`EventStream`] yields the same notifications decoded into [`Event`]s.
+//!
+//! ```ignore
+//! let shutdown = MonitorShutdown::new()?;
+//! let stopper = shutdown.clone();
+//! std::thread::spawn(move || {
+//!     std::thread::sleep(std::time::Duration::from_secs(60));
+//!     stopper.stop().unwrap();
+//! });
+//! monitor_events(nftables_group(), &shutdown, |event| {
+//!     if event.msg_type() == libc::NFT_MSG_NEWTABLE as u8 {
+//!         println!("new table: {:?}", event.decode::<Table>()?);
+//!     }
+//!     if let Some(generation) = event.generation() {
+//!         println!("ruleset changed: {}", generation?);
+//!     }
+//!     Ok(())
+//! })?;
+//! ```
+
+use std::collections::VecDeque;
+use std::os::unix::prelude::{AsRawFd, RawFd};
+
+use nix::errno::Errno;
+use nix::fcntl::OFlag;
+use nix::poll::{poll, PollFd, PollFlags};
+use nix::sys::socket::{self, MsgFlags};
+
+use crate::error::{DecodeError, QueryError};
+use crate::nlmsg::{get_operation_from_nlmsghdr_type, nft_nlmsg_maxsize, NfNetlinkDeserializable};
+use crate::parser::{parse_nlmsgs, NlMsg};
+use crate::query::NfNetlinkSocket;
+use crate::set::SetElementList;
+use crate::sys::{
+    nfgenmsg, nlmsghdr, NFT_MSG_DELCHAIN, NFT_MSG_DELFLOWTABLE, NFT_MSG_DELOBJ, NFT_MSG_DELRULE,
+    NFT_MSG_DELSET, NFT_MSG_DELSETELEM, NFT_MSG_DELTABLE, NFT_MSG_NEWCHAIN, NFT_MSG_NEWFLOWTABLE,
+    NFT_MSG_NEWGEN, NFT_MSG_NEWOBJ, NFT_MSG_NEWRULE, NFT_MSG_NEWSET, NFT_MSG_NEWSETELEM,
+    NFT_MSG_NEWTABLE,
+};
+use crate::zeroize::ZeroOnDrop;
+use crate::{Chain, Flowtable, Generation, Obj, ProtocolFamily, Rule, Set, Table};
+
+/// The bitmask of the multicast groups to subscribe to in order to receive the nftables
+/// notifications.
+pub fn nftables_group() -> u32 {
+    1 << (libc::NFNLGRP_NFTABLES - 1)
+}
+
+/// A handle to stop [`monitor_events`] from another thread.
+///
+/// The handle is backed by a pipe: stopping it makes the pipe readable, which wakes up the
+/// monitoring loops waiting on it. A handle cannot be re-armed once stopped, and every loop
+/// started with it afterwards returns immediately. Clones share the same pipe.
+#[derive(Debug, Clone)]
+pub struct MonitorShutdown {
+    pipe: std::sync::Arc<Pipe>,
+}
+
+#[derive(Debug)]
+struct Pipe {
+    read: RawFd,
+    write: RawFd,
+}
+
+impl Drop for Pipe {
+    fn drop(&mut self) {
+        let _ = nix::unistd::close(self.read);
+        let _ = nix::unistd::close(self.write);
+    }
+}
+
+impl MonitorShutdown {
+    pub fn new() -> Result<Self, QueryError> {
+        let (read, write) =
+            nix::unistd::pipe2(OFlag::O_CLOEXEC).map_err(QueryError::MonitorShutdownError)?;
+        Ok(MonitorShutdown {
+            pipe: std::sync::Arc::new(Pipe { read, write }),
+        })
+    }
+
+    /// Stops the monitoring loops using this handle (or one of its clones).
+    pub fn stop(&self) -> Result<(), QueryError> {
+        loop {
+            match nix::unistd::write(self.pipe.write, &[0]) {
+                Err(Errno::EINTR) => continue,
+                Err(e) => return Err(QueryError::MonitorShutdownError(e)),
+                Ok(_) => return Ok(()),
+            }
+        }
+    }
+
+    /// Whether [`MonitorShutdown::stop`] was called on this handle or one of its clones.
+    pub fn is_stopped(&self) -> bool {
+        let mut fds = [PollFd::new(self.pipe.read, PollFlags::POLLIN)];
+        matches!(poll(&mut fds, 0), Ok(n) if n > 0)
+    }
+}
+
+impl AsRawFd for MonitorShutdown {
+    /// The read end of the pipe, which becomes readable when the handle is stopped. This allows
+    /// integrating the handle into an existing event loop.
+    fn as_raw_fd(&self) -> RawFd {
+        self.pipe.read
+    }
+}
+
+/// A notification received from the kernel, left undecoded.
+#[derive(Debug, Clone, Copy)]
+pub struct RawEvent<'a> {
+    header: nlmsghdr,
+    genmsg: nfgenmsg,
+    buf: &'a [u8],
+}
+
+impl<'a> RawEvent<'a> {
+    pub(crate) fn new(header: nlmsghdr, genmsg: nfgenmsg, buf: &'a [u8]) -> Self {
+        RawEvent {
+            header,
+            genmsg,
+            buf,
+        }
+    }
+
+    /// The type of the message, e.g. `NFT_MSG_NEWRULE`.
+    pub fn msg_type(&self) -> u8 {
+        get_operation_from_nlmsghdr_type(self.header.nlmsg_type)
+    }
+
+    /// The protocol family of the object the notification is about.
+    pub fn family(&self) -> ProtocolFamily {
+        ProtocolFamily::from(self.genmsg.nfgen_family as i32)
+    }
+
+    /// The port ID of the socket that caused the change, or 0 if it came from the kernel.
+    pub fn portid(&self) -> u32 {
+        self.header.nlmsg_pid
+    }
+
+    /// The raw netlink message, including its headers.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.buf
+    }
+
+    /// Decodes the generation carried by the `NFT_MSG_NEWGEN` notifications, which follow the
+    /// notifications of the changes of every committed batch. Returns `None` for the other
+    /// notifications.
+    ///
+    /// Comparing the process id of the generation with the one of the current process tells the
+    /// changes made by other programs apart.
+    pub fn generation(&self) -> Option<Result<Generation, DecodeError>> {
+        if self.msg_type() as u32 != NFT_MSG_NEWGEN {
+            return None;
+        }
+        Some(self.decode())
+    }
+
+    /// Decodes the object carried by the notification. The type of the object must match
+    /// [`RawEvent::msg_type`], e.g. a [`Rule`] for `NFT_MSG_NEWRULE` and `NFT_MSG_DELRULE`.
+    ///
+    /// [`Rule`]: crate::Rule
+    pub fn decode<T: NfNetlinkDeserializable>(&self) -> Result<T, DecodeError> {
+        T::deserialize(self.buf).map(|(obj, _)| obj)
+    }
+}
+
+/// Subscribes to the multicast groups in the `groups` bitmask (see [`nftables_group`]), and
+/// calls `callback` on every notification received, until `shutdown` is stopped.
+///
+/// The loop also ends when `callback` returns an error, which is then returned by this function.
+/// If the kernel drops notifications because the socket buffer is full, this function fails with
+/// a [`QueryError::NetlinkRecvError`] holding `ENOBUFS`, after which the state known by the
+/// caller should be refreshed with a full listing.
+pub fn monitor_events(
+    groups: u32,
+    shutdown: &MonitorShutdown,
+    mut callback: impl FnMut(RawEvent) -> Result<(), QueryError>,
+) -> Result<(), QueryError> {
+    let sock = NfNetlinkSocket::with_groups(groups)?;
+    let mut msg_buffer = ZeroOnDrop(vec![0; nft_nlmsg_maxsize() as usize]);
+
+    while recv_events(&sock, &mut msg_buffer, shutdown, &mut callback)? {}
+    sock.close()
+}
+
+/// Waits for the next datagram of notifications on `sock`, and calls `callback` on each of them.
+/// Returns `false` if `shutdown` was stopped instead.
+fn recv_events(
+    sock: &NfNetlinkSocket,
+    msg_buffer: &mut [u8],
+    shutdown: &MonitorShutdown,
+    callback: &mut impl FnMut(RawEvent) -> Result<(), QueryError>,
+) -> Result<bool, QueryError> {
+    loop {
+        let mut fds = [
+            PollFd::new(sock.as_raw_fd(), PollFlags::POLLIN),
+            PollFd::new(shutdown.as_raw_fd(), PollFlags::POLLIN),
+        ];
+        match poll(&mut fds, -1) {
+            Err(Errno::EINTR) => continue,
+            Err(e) => return Err(QueryError::PollFailed(e)),
+            Ok(_) => {}
+        }
+        if matches!(fds[1].revents(), Some(r) if r.contains(PollFlags::POLLIN)) {
+            return Ok(false);
+        }
+        if !matches!(fds[0].revents(), Some(r) if !r.is_empty()) {
+            continue;
+        }
+
+        let nb_recv = match socket::recv(sock.as_raw_fd(), msg_buffer, MsgFlags::empty()) {
+            Err(Errno::EINTR) => continue,
+            Err(e) => return Err(QueryError::NetlinkRecvError(e)),
+            Ok(n) => n,
+        };
+        #[cfg(feature = "capture")]
+        crate::capture::record(&msg_buffer[..nb_recv]);
+
+        // a datagram holds one or several complete messages
+        for res in parse_nlmsgs(&msg_buffer[..nb_recv]) {
+            let (header, msg, buf) = res?;
+            match msg {
+                NlMsg::NfGenMsg(genmsg, _) => callback(RawEvent::new(header, genmsg, buf))?,
+                NlMsg::Error(e) if e.err.error != 0 => return Err(QueryError::NetlinkError(e)),
+                _ => {}
+            }
+        }
+        return Ok(true);
+    }
+}
+
+/// A change of the ruleset, decoded from a notification by [`RawEvent::to_event`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    TableAdded(Table),
+    TableDeleted(Table),
+    ChainAdded(Chain),
+    ChainDeleted(Chain),
+    RuleAdded(Rule),
+    RuleDeleted(Rule),
+    SetAdded(Set),
+    SetDeleted(Set),
+    ElementsAdded(SetElementList),
+    ElementsDeleted(SetElementList),
+    ObjAdded(Obj),
+    ObjDeleted(Obj),
+    FlowtableAdded(Flowtable),
+    FlowtableDeleted(Flowtable),
+    /// The end of the notifications of a committed batch, see [`RawEvent::generation`].
+    Generation(Generation),
+    /// A notification of a type unknown to this crate, holding its message type.
+    Other(u8),
+}
+
+impl RawEvent<'_> {
+    /// Decodes the notification into the object it is about, according to its message type.
+    pub fn to_event(&self) -> Result<Event, DecodeError> {
+        Ok(match self.msg_type() as u32 {
+            NFT_MSG_NEWTABLE => Event::TableAdded(self.decode()?),
+            NFT_MSG_DELTABLE => Event::TableDeleted(self.decode()?),
+            NFT_MSG_NEWCHAIN => Event::ChainAdded(self.decode()?),
+            NFT_MSG_DELCHAIN => Event::ChainDeleted(self.decode()?),
+            NFT_MSG_NEWRULE => Event::RuleAdded(self.decode()?),
+            NFT_MSG_DELRULE => Event::RuleDeleted(self.decode()?),
+            NFT_MSG_NEWSET => Event::SetAdded(self.decode()?),
+            NFT_MSG_DELSET => Event::SetDeleted(self.decode()?),
+            NFT_MSG_NEWSETELEM => Event::ElementsAdded(self.decode()?),
+            NFT_MSG_DELSETELEM => Event::ElementsDeleted(self.decode()?),
+            NFT_MSG_NEWOBJ => Event::ObjAdded(self.decode()?),
+            NFT_MSG_DELOBJ => Event::ObjDeleted(self.decode()?),
+            NFT_MSG_NEWFLOWTABLE => Event::FlowtableAdded(self.decode()?),
+            NFT_MSG_DELFLOWTABLE => Event::FlowtableDeleted(self.decode()?),
+            NFT_MSG_NEWGEN => Event::Generation(self.decode()?),
+            msg_type => Event::Other(msg_type as u8),
+        })
+    }
+}
+
+/// The changes of the ruleset, as an iterator of [`Event`]s, for the daemons that react to the
+/// changes made by other programs:
+///
+/// ```ignore
+/// for event in EventStream::new(&shutdown)? {
+///     if let Event::RuleDeleted(rule) = event? {
+///         println!("rule {:?} deleted", rule.get_handle());
+///     }
+/// }
+/// ```
+///
+/// The iterator blocks until the next notification, and ends when `shutdown` is stopped. Like
+/// with [`monitor_events`], it yields a [`QueryError::NetlinkRecvError`] holding `ENOBUFS` when
+/// the kernel dropped notifications.
+pub struct EventStream {
+    sock: NfNetlinkSocket,
+    msg_buffer: ZeroOnDrop,
+    shutdown: MonitorShutdown,
+    pending: VecDeque<Result<Event, DecodeError>>,
+}
+
+impl EventStream {
+    /// Subscribes to the nftables notifications.
+    pub fn new(shutdown: &MonitorShutdown) -> Result<Self, QueryError> {
+        Ok(EventStream {
+            sock: NfNetlinkSocket::with_groups(nftables_group())?,
+            msg_buffer: ZeroOnDrop(vec![0; nft_nlmsg_maxsize() as usize]),
+            shutdown: shutdown.clone(),
+            pending: VecDeque::new(),
+        })
+    }
+}
+
+impl Iterator for EventStream {
+    type Item = Result<Event, QueryError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pending.is_empty() {
+            let pending = &mut self.pending;
+            match recv_events(
+                &self.sock,
+                &mut self.msg_buffer,
+                &self.shutdown,
+                &mut |event: RawEvent| {
+                    pending.push_back(event.to_event());
+                    Ok(())
+                },
+            ) {
+                Ok(true) => {}
+                Ok(false) => return None,
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        self.pending
+            .pop_front()
+            .map(|event| event.map_err(QueryError::from))
+    }
+}
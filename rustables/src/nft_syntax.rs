@@ -0,0 +1,1008 @@
+//! Rendering of the objects in the syntax of `nft list ruleset`, so that audit tools and tests
+//! can read a ruleset listed by this crate without shelling out to nft:
+//!
+//! ```text
+//! table inet filter {
+//!     set blocked {
+//!         type ipv4_addr
+//!         flags interval
+//!     }
+//!
+//!     chain input {
+//!         type filter hook input priority filter; policy drop;
+//!         ct state established,related accept
+//!         ip saddr @blocked counter packets 12 bytes 720 drop
+//!         meta l4proto tcp tcp dport 22 accept
+//!     }
+//! }
+//! ```
+//!
+//! The blocks are indented with tabs, like nft does. The rules are translated back from their
+//! expressions, without the simplifications of nft (e.g. nft drops the `meta l4proto tcp`
+//! implied by `tcp dport 22`). The sequences of expressions that nft would not produce itself,
+//! or that this crate cannot translate yet, are written like their `Display` implementation
+//! instead, which nft cannot parse: the output is meant to be read, and only loaded back by nft
+//! when the ruleset was built with the high-level methods of this crate.
+
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::chain_priority::SymbolicPriority;
+use crate::expr::{
+    Bitwise, Cmp, CmpOp, ConnTrackState, Conntrack, ConntrackKey, Counter, ExpressionVariant,
+    Immediate, Limit, Log, Lookup, Meta, MetaType, Nat, NatType, Payload, Register, Reject,
+    RejectType, Tproxy, Verdict, ICMPV6_CODE_NAMES, ICMPX_CODE_NAMES, ICMP_CODE_NAMES,
+};
+use crate::nlmsg::NfNetlinkObject;
+use crate::parser_impls::NfNetlinkData;
+use crate::set::SetFlags;
+use crate::sys::{
+    NFT_DATA_VERDICT, NFT_LIMIT_F_INV, NFT_LIMIT_PKT_BYTES, NFT_LOOKUP_F_INV,
+    NFT_PAYLOAD_LL_HEADER, NFT_PAYLOAD_NETWORK_HEADER as NETWORK,
+    NFT_PAYLOAD_TRANSPORT_HEADER as TRANSPORT, NFT_TABLE_F_DORMANT, NFT_TABLE_F_OWNER,
+};
+use crate::{
+    data_type::DataTypeId, Chain, ChainPolicy, ProtocolFamily, Rule, Set, Table, TableContents,
+};
+
+impl Table {
+    /// Writes the table like `nft list table`, without its contents (see
+    /// [`TableContents::to_nft_syntax`]).
+    pub fn to_nft_syntax(&self) -> String {
+        let mut out = String::new();
+        write_table(&mut out, self, |_| {});
+        out
+    }
+}
+
+impl Chain {
+    /// Writes the chain like `nft list chain`, without its rules (see
+    /// [`TableContents::to_nft_syntax`]).
+    pub fn to_nft_syntax(&self) -> String {
+        let mut out = String::new();
+        write_chain(&mut out, self, std::iter::empty(), "");
+        out
+    }
+}
+
+impl Set {
+    /// Writes the declaration of the set like `nft list set`, without its elements.
+    pub fn to_nft_syntax(&self) -> String {
+        let mut out = String::new();
+        write_set(&mut out, self, "");
+        out
+    }
+}
+
+impl Rule {
+    /// Writes the statements of the rule like they appear in the output of `nft list chain`,
+    /// e.g. `tcp dport 22 accept`.
+    pub fn to_nft_syntax(&self) -> String {
+        RuleTranslator::translate(self)
+            .iter()
+            .map(|statement| statement.to_string())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+impl TableContents {
+    /// Writes the table with its sets, chains and rules, like `nft list table`. The anonymous
+    /// sets are written inline in the rules by nft, and are left out.
+    pub fn to_nft_syntax(&self) -> String {
+        let mut out = String::new();
+        write_table(&mut out, &self.table, |out| {
+            let mut first = true;
+            let named_sets = self.sets.iter().filter(|set| {
+                !set.get_set_flags()
+                    .is_some_and(|f| f.contains(SetFlags::ANONYMOUS))
+            });
+            for set in named_sets {
+                if !first {
+                    out.push('\n');
+                }
+                first = false;
+                write_set(out, set, "\t");
+            }
+            for chain in &self.chains {
+                if !first {
+                    out.push('\n');
+                }
+                first = false;
+                write_chain(out, chain, self.rules_of(chain), "\t");
+            }
+        });
+        out
+    }
+}
+
+fn write_table(out: &mut String, table: &Table, contents: impl FnOnce(&mut String)) {
+    let name = table.get_name().map_or("?", |name| name.as_str());
+    let _ = writeln!(out, "table {} {} {{", table.get_family(), name);
+    let flags = table.get_flags().copied().unwrap_or(0);
+    let mut names = Vec::new();
+    if flags & NFT_TABLE_F_DORMANT != 0 {
+        names.push("dormant");
+    }
+    if flags & NFT_TABLE_F_OWNER != 0 {
+        names.push("owner");
+    }
+    if !names.is_empty() {
+        let _ = writeln!(out, "\tflags {}", names.join(","));
+    }
+    contents(out);
+    out.push_str("}\n");
+}
+
+fn write_chain<'a>(
+    out: &mut String,
+    chain: &Chain,
+    rules: impl Iterator<Item = &'a Rule>,
+    indent: &str,
+) {
+    let name = chain.get_name().map_or("?", |name| name.as_str());
+    let _ = writeln!(out, "{}chain {} {{", indent, name);
+    if let Some(hook) = chain.get_hook() {
+        let family = chain.get_family();
+        let class = hook.get_class().copied().unwrap_or(0);
+        let _ = write!(
+            out,
+            "{}\ttype {} hook {}",
+            indent,
+            chain.get_type().map_or("filter", |t| t.as_str()),
+            hook_name(family, class)
+        );
+        if let Some(devices) = hook.get_devices() {
+            let names: Vec<_> = devices.iter().map(|d| format!("\"{}\"", d)).collect();
+            if names.len() == 1 {
+                let _ = write!(out, " device {}", names[0]);
+            } else if !names.is_empty() {
+                let _ = write!(out, " devices = {{ {} }}", names.join(", "));
+            }
+        }
+        if let Some(priority) = hook.get_priority() {
+            let priority = SymbolicPriority::describe(*priority as i32, family, class);
+            let _ = write!(out, " priority {};", priority);
+        }
+        if let Some(policy) = chain.get_policy() {
+            let policy = match policy {
+                ChainPolicy::Accept => "accept",
+                ChainPolicy::Drop => "drop",
+            };
+            let _ = write!(out, " policy {};", policy);
+        }
+        out.push('\n');
+    }
+    for rule in rules {
+        let _ = writeln!(out, "{}\t{}", indent, rule.to_nft_syntax());
+    }
+    let _ = writeln!(out, "{}}}", indent);
+}
+
+/// The names of the hooks of the chains of `family` in nft, with their number.
+pub(crate) fn hook_names(family: ProtocolFamily) -> &'static [(i32, &'static str)] {
+    match family {
+        ProtocolFamily::NetDev => &[
+            (libc::NF_NETDEV_INGRESS, "ingress"),
+            (libc::NF_NETDEV_EGRESS, "egress"),
+        ],
+        _ => &[
+            (libc::NF_INET_PRE_ROUTING, "prerouting"),
+            (libc::NF_INET_LOCAL_IN, "input"),
+            (libc::NF_INET_FORWARD, "forward"),
+            (libc::NF_INET_LOCAL_OUT, "output"),
+            (libc::NF_INET_POST_ROUTING, "postrouting"),
+            (libc::NF_INET_INGRESS, "ingress"),
+        ],
+    }
+}
+
+/// The name of the hook `class` in nft, for a chain of `family`.
+pub(crate) fn hook_name(family: ProtocolFamily, class: u32) -> String {
+    hook_names(family)
+        .iter()
+        .find(|(hook, _)| *hook as u32 == class)
+        .map_or_else(|| class.to_string(), |(_, name)| name.to_string())
+}
+
+fn write_set(out: &mut String, set: &Set, indent: &str) {
+    let name = set.get_name().map_or("?", |name| name.as_str());
+    let kind = if set.is_map() { "map" } else { "set" };
+    let _ = writeln!(out, "{}{} {} {{", indent, kind, name);
+    let key_type = set.key_type_name().unwrap_or_else(|| "?".to_string());
+    let _ = write!(out, "{}\ttype {}", indent, key_type);
+    if set.is_verdict_map() {
+        out.push_str(" : verdict");
+    } else if set.is_object_map() {
+        if let Some(obj_type) = set.get_obj_type() {
+            let _ = write!(out, " : {}", obj_type);
+        }
+    } else if set.is_map() {
+        let data_type = match set.get_data_type() {
+            Some(&NFT_DATA_VERDICT) => "verdict".to_string(),
+            Some(&data_type) => match DataTypeId::split(data_type) {
+                Ok(types) => types
+                    .iter()
+                    .map(|ty| ty.name())
+                    .collect::<Vec<_>>()
+                    .join(" . "),
+                Err(_) => format!("{:#x}", data_type),
+            },
+            None => "?".to_string(),
+        };
+        let _ = write!(out, " : {}", data_type);
+    }
+    out.push('\n');
+    let flags = set.get_set_flags().unwrap_or(SetFlags::empty());
+    let names: Vec<_> = [
+        (SetFlags::CONSTANT, "constant"),
+        (SetFlags::INTERVAL, "interval"),
+        (SetFlags::TIMEOUT, "timeout"),
+        (SetFlags::EVAL, "dynamic"),
+    ]
+    .into_iter()
+    .filter(|(flag, _)| flags.contains(*flag))
+    .map(|(_, name)| name)
+    .collect();
+    if !names.is_empty() {
+        let _ = writeln!(out, "{}\tflags {}", indent, names.join(","));
+    }
+    if let Some(&timeout) = set.get_timeout() {
+        if timeout % 1000 == 0 {
+            let _ = writeln!(out, "{}\ttimeout {}s", indent, timeout / 1000);
+        } else {
+            let _ = writeln!(out, "{}\ttimeout {}ms", indent, timeout);
+        }
+    }
+    let _ = writeln!(out, "{}}}", indent);
+}
+
+/// How the values compared to an [`Operand`] are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ValueKind {
+    /// An integer in network byte order, e.g. a port.
+    Integer,
+    /// An integer in host byte order, e.g. a user id.
+    HostInteger,
+    /// A network protocol, e.g. `ipv4`.
+    NfProto,
+    /// A transport protocol, e.g. `tcp`.
+    L4Proto,
+    /// A mark, in hexadecimal and host byte order.
+    Mark,
+    /// A NUL-terminated interface name.
+    Interface,
+    /// An IPv4 or IPv6 address, according to its length.
+    Address,
+    /// The bitmask of the conntrack states.
+    CtState,
+    /// Anything else, written like a [`NfNetlinkData`].
+    Raw,
+}
+
+/// A header field known to nft by its name, e.g. `ip saddr`.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct HeaderField {
+    pub(crate) protocol: &'static str,
+    pub(crate) field: &'static str,
+    pub(crate) base: u32,
+    /// The offset of the field in its header, in bytes.
+    pub(crate) offset: u32,
+    pub(crate) len: u32,
+    pub(crate) kind: ValueKind,
+}
+
+const fn field(
+    protocol: &'static str,
+    field: &'static str,
+    base: u32,
+    offset: u32,
+    len: u32,
+    kind: ValueKind,
+) -> HeaderField {
+    HeaderField {
+        protocol,
+        field,
+        base,
+        offset,
+        len,
+        kind,
+    }
+}
+
+/// The header fields translated by name. The fields of the transport header are only named
+/// after their protocol (e.g. `tcp dport`) when the rule matched it before, and after the
+/// generic transport header (`th dport`) otherwise.
+pub(crate) const HEADER_FIELDS: &[HeaderField] = &[
+    field("ip", "protocol", NETWORK, 9, 1, ValueKind::L4Proto),
+    field("ip", "saddr", NETWORK, 12, 4, ValueKind::Address),
+    field("ip", "daddr", NETWORK, 16, 4, ValueKind::Address),
+    field("ip6", "nexthdr", NETWORK, 6, 1, ValueKind::L4Proto),
+    field("ip6", "saddr", NETWORK, 8, 16, ValueKind::Address),
+    field("ip6", "daddr", NETWORK, 24, 16, ValueKind::Address),
+    field("tcp", "sport", TRANSPORT, 0, 2, ValueKind::Integer),
+    field("tcp", "dport", TRANSPORT, 2, 2, ValueKind::Integer),
+    field("udp", "sport", TRANSPORT, 0, 2, ValueKind::Integer),
+    field("udp", "dport", TRANSPORT, 2, 2, ValueKind::Integer),
+    field("icmp", "type", TRANSPORT, 0, 1, ValueKind::Integer),
+    field("icmp", "code", TRANSPORT, 1, 1, ValueKind::Integer),
+    field("icmpv6", "type", TRANSPORT, 0, 1, ValueKind::Integer),
+    field("icmpv6", "code", TRANSPORT, 1, 1, ValueKind::Integer),
+    field("th", "sport", TRANSPORT, 0, 2, ValueKind::Integer),
+    field("th", "dport", TRANSPORT, 2, 2, ValueKind::Integer),
+];
+
+/// The names of the payload bases in the raw payload expressions of nft, e.g. `@nh,96,32`.
+pub(crate) const PAYLOAD_BASES: [(u32, &str); 3] = [
+    (NFT_PAYLOAD_LL_HEADER, "ll"),
+    (NETWORK, "nh"),
+    (TRANSPORT, "th"),
+];
+
+/// The names of the network protocols, as matched by `meta nfproto`.
+pub(crate) const NFPROTO_NAMES: [(i32, &str); 2] =
+    [(libc::NFPROTO_IPV4, "ipv4"), (libc::NFPROTO_IPV6, "ipv6")];
+
+/// The names of the transport protocols, as matched by `meta l4proto`.
+pub(crate) const L4PROTO_NAMES: [(i32, &str); 4] = [
+    (libc::IPPROTO_TCP, "tcp"),
+    (libc::IPPROTO_UDP, "udp"),
+    (libc::IPPROTO_ICMP, "icmp"),
+    (libc::IPPROTO_ICMPV6, "icmpv6"),
+];
+
+pub(crate) const CT_STATE_NAMES: [(ConnTrackState, &str); 5] = [
+    (ConnTrackState::INVALID, "invalid"),
+    (ConnTrackState::ESTABLISHED, "established"),
+    (ConnTrackState::RELATED, "related"),
+    (ConnTrackState::NEW, "new"),
+    (ConnTrackState::UNTRACKED, "untracked"),
+];
+
+/// The names of the units of the rates of the limits, with their length in seconds.
+pub(crate) const LIMIT_UNITS: [(u64, &str); 5] = [
+    (1, "second"),
+    (60, "minute"),
+    (3600, "hour"),
+    (86400, "day"),
+    (604800, "week"),
+];
+
+/// How the values of the meta keys are written.
+pub(crate) fn meta_value_kind(key: MetaType) -> ValueKind {
+    match key {
+        MetaType::Mark => ValueKind::Mark,
+        MetaType::IifName | MetaType::OifName | MetaType::BriIifName | MetaType::BriOifName => {
+            ValueKind::Interface
+        }
+        MetaType::NfProto => ValueKind::NfProto,
+        MetaType::L4Proto => ValueKind::L4Proto,
+        MetaType::Iif
+        | MetaType::Oif
+        | MetaType::SkUid
+        | MetaType::SkGid
+        | MetaType::Priority
+        | MetaType::Cgroup => ValueKind::HostInteger,
+        _ => ValueKind::Raw,
+    }
+}
+
+/// A value loaded in a register by an expression of a rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Operand {
+    Meta(MetaType),
+    Field(&'static HeaderField),
+    /// Bytes of a header, with their offset and their length in bits.
+    Raw {
+        base: &'static str,
+        offset: u64,
+        len: u64,
+    },
+    Ct(ConntrackKey),
+}
+
+impl Operand {
+    pub(crate) fn kind(&self) -> ValueKind {
+        match self {
+            Operand::Meta(key) => meta_value_kind(*key),
+            Operand::Field(field) => field.kind,
+            Operand::Raw { .. } => ValueKind::Raw,
+            Operand::Ct(ConntrackKey::State) => ValueKind::CtState,
+            Operand::Ct(ConntrackKey::Mark) => ValueKind::Mark,
+        }
+    }
+}
+
+impl Display for Operand {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Operand::Meta(key) => write!(f, "meta {}", key),
+            Operand::Field(field) => write!(f, "{} {}", field.protocol, field.field),
+            Operand::Raw { base, offset, len } => write!(f, "@{},{},{}", base, offset, len),
+            Operand::Ct(key) => write!(f, "ct {}", key),
+        }
+    }
+}
+
+/// The right-hand side of a [`Statement::Match`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Value {
+    /// Bytes compared to the operand, written according to its [`ValueKind`].
+    Bytes(Vec<u8>),
+    /// A network prefix, with the number of bits of the prefix.
+    Prefix(Vec<u8>, u32),
+    /// Conntrack states, any of which matches.
+    CtStates(ConnTrackState),
+    /// The elements of a named set.
+    Set(String),
+}
+
+/// A statement of a rule, translated from one or more expressions.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Statement {
+    Match {
+        left: Operand,
+        op: CmpOp,
+        right: Value,
+    },
+    VerdictMap {
+        key: Operand,
+        map: String,
+    },
+    Verdict(Verdict),
+    Counter(Counter),
+    Log(Log),
+    Limit(Limit),
+    Reject(RejectWith),
+    Masquerade,
+    Nat {
+        nat_type: NatType,
+        /// The family of the address, in inet tables only.
+        family: Option<ProtocolFamily>,
+        addr: Option<Vec<u8>>,
+        port: Option<Vec<u8>>,
+    },
+    Tproxy {
+        /// The family of the address, in inet tables only.
+        family: Option<ProtocolFamily>,
+        addr: Option<Vec<u8>>,
+        port: Option<Vec<u8>>,
+    },
+    /// An expression that could not be translated, written like its `Display` implementation.
+    Untranslated(String),
+}
+
+impl Display for Statement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Statement::Match {
+                left,
+                op,
+                right: Value::CtStates(states),
+            } => match op {
+                CmpOp::Eq => write!(f, "{} {}", left, ct_state_names(*states).join(",")),
+                op => write!(f, "{} {} {}", left, op, ct_state_names(*states).join(",")),
+            },
+            Statement::Match { left, op, right } => {
+                write!(f, "{}", left)?;
+                if *op != CmpOp::Eq {
+                    write!(f, " {}", op)?;
+                }
+                match right {
+                    Value::Bytes(data) => write!(f, " {}", format_value(left.kind(), data)),
+                    Value::Prefix(addr, len) => {
+                        write!(f, " {}/{}", format_value(ValueKind::Address, addr), len)
+                    }
+                    Value::Set(set) => write!(f, " @{}", set),
+                    Value::CtStates(_) => unreachable!(),
+                }
+            }
+            Statement::VerdictMap { key, map } => write!(f, "{} vmap @{}", key, map),
+            Statement::Verdict(verdict) => write!(f, "{}", verdict),
+            Statement::Counter(counter) => write!(f, "{}", counter),
+            Statement::Log(log) => write!(f, "{}", log),
+            Statement::Limit(limit) => write_limit(f, limit),
+            Statement::Reject(reject) => write_reject(f, reject),
+            Statement::Masquerade => f.write_str("masquerade"),
+            Statement::Nat {
+                nat_type,
+                family,
+                addr,
+                port,
+            } => {
+                f.write_str(match nat_type {
+                    NatType::SNat => "snat",
+                    NatType::DNat => "dnat",
+                })?;
+                if let Some(family) = family {
+                    write!(f, " {}", family)?;
+                }
+                write_destination(f, addr.as_deref(), port.as_deref())
+            }
+            Statement::Tproxy { family, addr, port } => {
+                f.write_str("tproxy")?;
+                if let Some(family) = family {
+                    write!(f, " {}", family)?;
+                }
+                write_destination(f, addr.as_deref(), port.as_deref())
+            }
+            Statement::Untranslated(expr) => f.write_str(expr),
+        }
+    }
+}
+
+/// Writes the ` to addr:port` part of the NAT and tproxy statements.
+fn write_destination(
+    f: &mut Formatter<'_>,
+    addr: Option<&[u8]>,
+    port: Option<&[u8]>,
+) -> fmt::Result {
+    let addr = addr.map(|addr| format_value(ValueKind::Address, addr));
+    let port = port.map(|port| format_value(ValueKind::Integer, port));
+    match (addr, port) {
+        (Some(addr), Some(port)) if addr.contains(':') => write!(f, " to [{}]:{}", addr, port),
+        (Some(addr), Some(port)) => write!(f, " to {}:{}", addr, port),
+        (Some(addr), None) => write!(f, " to {}", addr),
+        (None, Some(port)) => write!(f, " to :{}", port),
+        (None, None) => Ok(()),
+    }
+}
+
+/// A value loaded in a register by a previous expression of the rule.
+#[derive(Debug, Clone)]
+struct Loaded {
+    operand: Operand,
+    /// The mask applied by a [`Bitwise`] expression, if any.
+    mask: Option<Vec<u8>>,
+}
+
+/// The message of a reject statement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RejectWith {
+    /// `reject`, whose message depends on the family of the packets.
+    Default,
+    /// `reject with tcp reset`.
+    TcpReset,
+    /// `reject with <type> <code>`, e.g. `icmpx` and `port-unreachable`.
+    Icmp(&'static str, &'static str),
+}
+
+/// Translates the expressions of a rule into statements, like nft does.
+pub(crate) struct RuleTranslator {
+    family: ProtocolFamily,
+    registers: HashMap<Register, Loaded>,
+    /// The immediate values loaded in the registers, e.g. the address of a NAT statement.
+    immediates: HashMap<Register, Vec<u8>>,
+    /// The network protocol matched by the rule so far, to name the fields of inet rules.
+    nfproto: Option<u8>,
+    /// The transport protocol matched by the rule so far, e.g. `tcp` for `tcp dport`.
+    l4proto: Option<u8>,
+    statements: Vec<Statement>,
+}
+
+impl RuleTranslator {
+    pub(crate) fn translate(rule: &Rule) -> Vec<Statement> {
+        let family = rule.get_family();
+        let nfproto = match family {
+            ProtocolFamily::Ipv4 => Some(libc::NFPROTO_IPV4 as u8),
+            ProtocolFamily::Ipv6 => Some(libc::NFPROTO_IPV6 as u8),
+            _ => None,
+        };
+        let mut translator = RuleTranslator {
+            family,
+            registers: HashMap::new(),
+            immediates: HashMap::new(),
+            nfproto,
+            l4proto: None,
+            statements: Vec::new(),
+        };
+        for expr in rule.get_expressions().into_iter().flat_map(|e| e.iter()) {
+            if !translator.expression(expr.get_data()) {
+                translator
+                    .statements
+                    .push(Statement::Untranslated(expr.to_string()));
+            }
+        }
+        translator.statements
+    }
+
+    fn expression(&mut self, expr: Option<&ExpressionVariant>) -> bool {
+        let statement = match expr {
+            Some(ExpressionVariant::Meta(meta)) => return self.meta(meta),
+            Some(ExpressionVariant::Payload(payload)) => return self.payload(payload),
+            Some(ExpressionVariant::Conntrack(ct)) => return self.conntrack(ct),
+            Some(ExpressionVariant::Bitwise(bitwise)) => return self.bitwise(bitwise),
+            Some(ExpressionVariant::Cmp(cmp)) => return self.cmp(cmp),
+            Some(ExpressionVariant::Lookup(lookup)) => return self.lookup(lookup),
+            Some(ExpressionVariant::Immediate(immediate)) => return self.immediate(immediate),
+            Some(ExpressionVariant::Nat(nat)) => return self.nat(nat),
+            Some(ExpressionVariant::Tproxy(tproxy)) => return self.tproxy(tproxy),
+            Some(ExpressionVariant::Counter(counter)) => Statement::Counter(counter.clone()),
+            Some(ExpressionVariant::Log(log)) => Statement::Log(log.clone()),
+            Some(ExpressionVariant::Limit(limit)) => Statement::Limit(limit.clone()),
+            Some(ExpressionVariant::Reject(reject)) => match self.reject(reject) {
+                Some(with) => Statement::Reject(with),
+                None => return false,
+            },
+            // the port ranges are loaded in registers, which are not translated yet
+            Some(ExpressionVariant::Masquerade(masq))
+                if masq.get_port_min_register().is_none() && masq.get_nat_flags().is_none() =>
+            {
+                Statement::Masquerade
+            }
+            _ => return false,
+        };
+        self.statements.push(statement);
+        true
+    }
+
+    fn meta(&mut self, meta: &Meta) -> bool {
+        let (Some(&key), Some(&dreg)) = (meta.get_key(), meta.get_dreg()) else {
+            return false;
+        };
+        self.load(dreg, Operand::Meta(key));
+        true
+    }
+
+    fn payload(&mut self, payload: &Payload) -> bool {
+        let (Some(&base), Some(&offset), Some(&len), Some(&dreg)) = (
+            payload.get_base(),
+            payload.get_offset(),
+            payload.get_len(),
+            payload.get_dreg(),
+        ) else {
+            return false;
+        };
+        let l4proto = match self.l4proto.map(i32::from) {
+            Some(libc::IPPROTO_TCP) => "tcp",
+            Some(libc::IPPROTO_UDP) => "udp",
+            Some(libc::IPPROTO_ICMP) => "icmp",
+            Some(libc::IPPROTO_ICMPV6) => "icmpv6",
+            _ => "th",
+        };
+        let known = HEADER_FIELDS.iter().find(|field| {
+            let protocol_matched = match field.protocol {
+                "ip" => self.nfproto != Some(libc::NFPROTO_IPV6 as u8),
+                "ip6" => self.nfproto != Some(libc::NFPROTO_IPV4 as u8),
+                protocol => protocol == l4proto,
+            };
+            protocol_matched && (field.base, field.offset, field.len) == (base, offset, len)
+        });
+        let operand = match known {
+            Some(field) => Operand::Field(field),
+            None => {
+                let Some((_, base)) = PAYLOAD_BASES.iter().find(|(b, _)| *b == base) else {
+                    return false;
+                };
+                Operand::Raw {
+                    base,
+                    offset: u64::from(offset) * 8,
+                    len: u64::from(len) * 8,
+                }
+            }
+        };
+        self.load(dreg, operand);
+        true
+    }
+
+    fn conntrack(&mut self, ct: &Conntrack) -> bool {
+        let (Some(&key), Some(&dreg)) = (ct.get_key(), ct.get_dreg()) else {
+            return false;
+        };
+        self.load(dreg, Operand::Ct(key));
+        true
+    }
+
+    fn bitwise(&mut self, bitwise: &Bitwise) -> bool {
+        let (Some(sreg), Some(&dreg), Some(mask), Some(xor)) = (
+            bitwise.get_sreg(),
+            bitwise.get_dreg(),
+            bitwise.get_mask().and_then(NfNetlinkData::get_value),
+            bitwise.get_xor().and_then(NfNetlinkData::get_value),
+        ) else {
+            return false;
+        };
+        if xor.iter().any(|b| *b != 0) {
+            return false;
+        }
+        let Some(mut loaded) = self.registers.get(sreg).cloned() else {
+            return false;
+        };
+        loaded.mask = Some(mask.clone());
+        self.registers.insert(dreg, loaded);
+        true
+    }
+
+    fn cmp(&mut self, cmp: &Cmp) -> bool {
+        let (Some(sreg), Some(&op), Some(data)) = (
+            cmp.get_sreg(),
+            cmp.get_op(),
+            cmp.get_data().and_then(NfNetlinkData::get_value),
+        ) else {
+            return false;
+        };
+        let Some(loaded) = self.registers.get(sreg) else {
+            return false;
+        };
+        let (op, right) = match (loaded.operand.kind(), &loaded.mask) {
+            // ct state & mask != 0
+            (ValueKind::CtState, Some(mask)) if data.iter().all(|b| *b == 0) => {
+                let Ok(mask) = <[u8; 4]>::try_from(mask.as_slice()) else {
+                    return false;
+                };
+                let states = ConnTrackState::from_bits_truncate(u32::from_ne_bytes(mask));
+                match op {
+                    CmpOp::Neq => (CmpOp::Eq, Value::CtStates(states)),
+                    CmpOp::Eq => (CmpOp::Neq, Value::CtStates(states)),
+                    _ => return false,
+                }
+            }
+            // a network prefix
+            (ValueKind::Address, Some(mask)) => match prefix_len(mask) {
+                Some(len) => (op, Value::Prefix(data.clone(), len)),
+                None => return false,
+            },
+            (_, Some(_)) => return false,
+            (_, None) => (op, Value::Bytes(data.clone())),
+        };
+        // remember the protocols, to name the fields of the next expressions
+        if op == CmpOp::Eq && data.len() == 1 {
+            match loaded.operand.kind() {
+                ValueKind::NfProto => self.nfproto = Some(data[0]),
+                ValueKind::L4Proto => self.l4proto = Some(data[0]),
+                _ => {}
+            }
+        }
+        self.statements.push(Statement::Match {
+            left: loaded.operand.clone(),
+            op,
+            right,
+        });
+        true
+    }
+
+    fn lookup(&mut self, lookup: &Lookup) -> bool {
+        let (Some(sreg), Some(set)) = (lookup.get_sreg(), lookup.get_set()) else {
+            return false;
+        };
+        let Some(loaded) = self.registers.get(sreg) else {
+            return false;
+        };
+        if loaded.mask.is_some() {
+            return false;
+        }
+        let statement = match lookup.get_dreg() {
+            Some(Register::Verdict) => Statement::VerdictMap {
+                key: loaded.operand.clone(),
+                map: set.clone(),
+            },
+            Some(_) => return false,
+            None => {
+                let inverted = lookup.get_flags().copied().unwrap_or(0) & NFT_LOOKUP_F_INV != 0;
+                Statement::Match {
+                    left: loaded.operand.clone(),
+                    op: if inverted { CmpOp::Neq } else { CmpOp::Eq },
+                    right: Value::Set(set.clone()),
+                }
+            }
+        };
+        self.statements.push(statement);
+        true
+    }
+
+    fn immediate(&mut self, immediate: &Immediate) -> bool {
+        let (Some(&dreg), Some(data)) = (immediate.get_dreg(), immediate.get_data()) else {
+            return false;
+        };
+        match (dreg, data.get_verdict(), data.get_value()) {
+            (Register::Verdict, Some(verdict), _) => {
+                self.statements.push(Statement::Verdict(verdict.clone()));
+                true
+            }
+            (_, _, Some(value)) => {
+                self.immediates.insert(dreg, value.clone());
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn nat(&mut self, nat: &Nat) -> bool {
+        let Some(nat_type) = nat.nat_type else {
+            return false;
+        };
+        // neither are the ranges and the flags
+        if nat.ip_max_register.is_some() || nat.port_max_register.is_some() || nat.flags.is_some() {
+            return false;
+        }
+        let addr = nat.ip_register.map(|reg| self.immediates.get(&reg));
+        let port = nat.port_register.map(|reg| self.immediates.get(&reg));
+        // the registers must have been loaded by the rule
+        if matches!(addr, Some(None)) || matches!(port, Some(None)) {
+            return false;
+        }
+        let family = match self.family {
+            ProtocolFamily::Inet => nat.family,
+            _ => None,
+        };
+        self.statements.push(Statement::Nat {
+            nat_type,
+            family,
+            addr: addr.flatten().cloned(),
+            port: port.flatten().cloned(),
+        });
+        true
+    }
+
+    fn tproxy(&mut self, tproxy: &Tproxy) -> bool {
+        let addr = tproxy
+            .get_addr_register()
+            .map(|reg| self.immediates.get(reg));
+        let port = tproxy
+            .get_port_register()
+            .map(|reg| self.immediates.get(reg));
+        // the registers must have been loaded by the rule
+        if matches!(addr, Some(None)) || matches!(port, Some(None)) {
+            return false;
+        }
+        let family = match (self.family, tproxy.get_family()) {
+            (ProtocolFamily::Inet, Some(&family)) if family != ProtocolFamily::Unspec => {
+                Some(family)
+            }
+            _ => None,
+        };
+        self.statements.push(Statement::Tproxy {
+            family,
+            addr: addr.flatten().cloned(),
+            port: port.flatten().cloned(),
+        });
+        true
+    }
+
+    /// The message of `reject`, or `None` when nft could not name it, e.g. for the ICMP codes
+    /// of rules that do not match a network protocol.
+    fn reject(&self, reject: &Reject) -> Option<RejectWith> {
+        let (icmp_type, names) = match (reject.get_type(), reject.get_code()) {
+            (None, None) => return Some(RejectWith::Default),
+            (Some(RejectType::TcpRst), _) => return Some(RejectWith::TcpReset),
+            (Some(RejectType::IcmpxUnreach), _) => ("icmpx", &ICMPX_CODE_NAMES[..]),
+            // the ICMP codes depend on the network protocol
+            (Some(RejectType::IcmpUnreach), _) => match self.nfproto.map(i32::from) {
+                Some(libc::NFPROTO_IPV4) => ("icmp", &ICMP_CODE_NAMES[..]),
+                Some(libc::NFPROTO_IPV6) => ("icmpv6", &ICMPV6_CODE_NAMES[..]),
+                _ => return None,
+            },
+            _ => return None,
+        };
+        let code = reject.get_code()?;
+        let (_, name) = names.iter().find(|(value, _)| value == code)?;
+        Some(RejectWith::Icmp(icmp_type, name))
+    }
+
+    fn load(&mut self, reg: Register, operand: Operand) {
+        self.registers.insert(
+            reg,
+            Loaded {
+                operand,
+                mask: None,
+            },
+        );
+    }
+}
+
+fn format_value(kind: ValueKind, data: &[u8]) -> String {
+    let raw = || {
+        NfNetlinkData::default()
+            .with_value(data.to_vec())
+            .to_string()
+    };
+    let name = |names: &[(i32, &str)]| {
+        let proto = data[0] as i32;
+        names
+            .iter()
+            .find(|(p, _)| *p == proto)
+            .map_or_else(|| proto.to_string(), |(_, name)| name.to_string())
+    };
+    match kind {
+        ValueKind::NfProto if data.len() == 1 => name(&NFPROTO_NAMES),
+        ValueKind::L4Proto if data.len() == 1 => name(&L4PROTO_NAMES),
+        ValueKind::Integer | ValueKind::NfProto | ValueKind::L4Proto => {
+            be_integer(data).map_or_else(raw, |v| v.to_string())
+        }
+        ValueKind::HostInteger => host_integer(data).map_or_else(raw, |v| v.to_string()),
+        ValueKind::Mark => host_integer(data).map_or_else(raw, |v| format!("{:#010x}", v)),
+        ValueKind::Interface => format!("\"{}\"", interface_name(data)),
+        ValueKind::Address => address(data).map_or_else(raw, |addr| addr.to_string()),
+        ValueKind::CtState => host_integer(data).map_or_else(raw, |v| {
+            ct_state_names(ConnTrackState::from_bits_truncate(v)).join(",")
+        }),
+        ValueKind::Raw => raw(),
+    }
+}
+
+/// Decodes an integer of 1, 2 or 4 bytes in network byte order.
+pub(crate) fn be_integer(data: &[u8]) -> Option<u32> {
+    match *data {
+        [a] => Some(a as u32),
+        [a, b] => Some(u16::from_be_bytes([a, b]) as u32),
+        [a, b, c, d] => Some(u32::from_be_bytes([a, b, c, d])),
+        _ => None,
+    }
+}
+
+/// Decodes an integer of 1, 2 or 4 bytes in host byte order.
+pub(crate) fn host_integer(data: &[u8]) -> Option<u32> {
+    match *data {
+        [a] => Some(a as u32),
+        [a, b] => Some(u16::from_ne_bytes([a, b]) as u32),
+        [a, b, c, d] => Some(u32::from_ne_bytes([a, b, c, d])),
+        _ => None,
+    }
+}
+
+pub(crate) fn address(data: &[u8]) -> Option<IpAddr> {
+    if let Ok(octets) = <[u8; 4]>::try_from(data) {
+        Some(Ipv4Addr::from(octets).into())
+    } else if let Ok(octets) = <[u8; 16]>::try_from(data) {
+        Some(Ipv6Addr::from(octets).into())
+    } else {
+        None
+    }
+}
+
+/// The interface name in `data`, up to its NUL terminator.
+pub(crate) fn interface_name(data: &[u8]) -> std::borrow::Cow<'_, str> {
+    String::from_utf8_lossy(data.split(|b| *b == 0).next().unwrap_or_default())
+}
+
+pub(crate) fn ct_state_names(states: ConnTrackState) -> Vec<&'static str> {
+    CT_STATE_NAMES
+        .into_iter()
+        .filter(|(state, _)| states.contains(*state))
+        .map(|(_, name)| name)
+        .collect()
+}
+
+/// The length of the network prefix of `mask`, or `None` if `mask` is not a prefix mask.
+fn prefix_len(mask: &[u8]) -> Option<u32> {
+    if mask.is_empty() || mask.len() > 16 {
+        return None;
+    }
+    let bits = mask.iter().fold(0u128, |bits, b| bits << 8 | *b as u128);
+    let bits = bits << (128 - mask.len() * 8);
+    // the bits set must all come first
+    (bits.count_ones() == bits.leading_ones()).then_some(bits.leading_ones())
+}
+
+fn write_limit(f: &mut Formatter<'_>, limit: &Limit) -> fmt::Result {
+    f.write_str("limit rate ")?;
+    if limit.get_flags().copied().unwrap_or(0) & NFT_LIMIT_F_INV != 0 {
+        f.write_str("over ")?;
+    }
+    let rate = limit.get_rate().copied().unwrap_or(0);
+    let unit = limit.get_unit().copied().unwrap_or(1);
+    let bytes = limit.get_limit_type() == Some(&NFT_LIMIT_PKT_BYTES);
+    if bytes {
+        write!(f, "{} bytes/", rate)?;
+    } else {
+        write!(f, "{}/", rate)?;
+    }
+    match LIMIT_UNITS.iter().find(|(u, _)| *u == unit) {
+        Some((_, name)) => f.write_str(name)?,
+        None => write!(f, "{}s", unit)?,
+    }
+    if let Some(burst) = limit.get_burst().filter(|burst| **burst != 0) {
+        let unit = if bytes { "bytes" } else { "packets" };
+        write!(f, " burst {} {}", burst, unit)?;
+    }
+    Ok(())
+}
+
+fn write_reject(f: &mut Formatter<'_>, with: &RejectWith) -> fmt::Result {
+    match with {
+        RejectWith::Default => f.write_str("reject"),
+        RejectWith::TcpReset => f.write_str("reject with tcp reset"),
+        RejectWith::Icmp(icmp_type, code) => write!(f, "reject with {} {}", icmp_type, code),
+    }
+}
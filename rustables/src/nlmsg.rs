@@ -1,18 +1,29 @@
+//! Low-level serialization of nfnetlink messages.
+//!
+//! The traits of this module are implemented by every object of this crate, and can be
+//! implemented by downstream crates to send their own nfnetlink objects in a [`Batch`]: an object
+//! that implements [`NfNetlinkObject`] (and thus [`NfNetlinkAttribute`] for its payload,
+//! [`AttributeDecoder`] and [`NfNetlinkDeserializable`]) can be passed to [`Batch::add`].
+//!
+//! [`Batch`]: crate::Batch
+//! [`Batch::add`]: crate::Batch::add
+
 use std::{fmt::Debug, mem::size_of};
 
 use crate::{
     error::DecodeError,
     sys::{
         nfgenmsg, nlmsghdr, NFNETLINK_V0, NFNL_MSG_BATCH_BEGIN, NFNL_MSG_BATCH_END,
-        NFNL_SUBSYS_NFTABLES, NLMSG_ALIGNTO, NLM_F_ACK, NLM_F_CREATE,
+        NFNL_SUBSYS_NFTABLES, NLMSG_ALIGNTO, NLM_F_ACK, NLM_F_CREATE, NLM_F_REPLACE,
     },
-    MsgType, ProtocolFamily,
+    MsgType, ProtocolFamily, RawAttributeTree,
 };
 ///
 /// The largest nf_tables netlink message is the set element message, which contains the
 /// NFTA_SET_ELEM_LIST_ELEMENTS attribute. This attribute is a nest that describes the set
 /// elements. Given that the netlink attribute length (nla_len) is 16 bits, the largest message is
 /// a bit larger than 64 KBytes.
+#[cfg_attr(not(feature = "socket"), allow(dead_code))]
 pub fn nft_nlmsg_maxsize() -> u32 {
     u32::from(::std::u16::MAX) + unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as u32
 }
@@ -37,6 +48,12 @@ pub fn get_operation_from_nlmsghdr_type(x: u16) -> u8 {
     (x & 0x00ff) as u8
 }
 
+/// Writes netlink messages, made of a `nlmsghdr` header, a `nfgenmsg` header and a payload, in a
+/// buffer.
+///
+/// A message is started with [`NfNetlinkWriter::write_header`], its payload is then reserved
+/// with [`NfNetlinkWriter::add_data_zeroed`] (the length in the header is updated accordingly),
+/// and the message is closed with [`NfNetlinkWriter::finalize_writing_object`].
 pub struct NfNetlinkWriter<'a> {
     buf: &'a mut Vec<u8>,
     // hold the position of the nlmsghdr and nfgenmsg structures for the object currently being
@@ -45,10 +62,13 @@ pub struct NfNetlinkWriter<'a> {
 }
 
 impl<'a> NfNetlinkWriter<'a> {
+    /// Creates a writer appending the messages to `buf`.
     pub fn new(buf: &'a mut Vec<u8>) -> NfNetlinkWriter<'a> {
         NfNetlinkWriter { buf, headers: None }
     }
 
+    /// Appends `size` zeroed bytes (padded to the netlink alignment) to the buffer, and returns
+    /// them so that the caller can fill them in.
     pub fn add_data_zeroed<'b>(&'b mut self, size: usize) -> &'b mut [u8] {
         let padded_size = pad_netlink_object_with_variable_size(size);
         let start = self.buf.len();
@@ -65,6 +85,8 @@ impl<'a> NfNetlinkWriter<'a> {
         &mut self.buf[start..start + size]
     }
 
+    /// Starts a new message of type `msg_type` (e.g. `NFT_MSG_NEWTABLE`), for the objects of the
+    /// protocol family `family`. `NLM_F_REQUEST` is always added to `flags`.
     // rewrite of `__nftnl_nlmsg_build_hdr`
     pub fn write_header(
         &mut self,
@@ -98,9 +120,10 @@ impl<'a> NfNetlinkWriter<'a> {
         let nfgenmsg_buf = self.add_data_zeroed(nfgenmsg_len);
         let nfgenmsg: &mut nfgenmsg =
             unsafe { std::mem::transmute(nfgenmsg_buf.as_mut_ptr() as *mut nfgenmsg) };
-        nfgenmsg.nfgen_family = family as u8;
+        nfgenmsg.nfgen_family = i32::from(family) as u8;
         nfgenmsg.version = NFNETLINK_V0 as u8;
-        nfgenmsg.res_id = ressource_id.unwrap_or(0);
+        // the resource id is in network byte order
+        nfgenmsg.res_id = ressource_id.unwrap_or(0).to_be();
 
         self.headers = Some((
             self.buf.len() - (nlmsghdr_len + nfgenmsg_len),
@@ -108,48 +131,127 @@ impl<'a> NfNetlinkWriter<'a> {
         ));
     }
 
+    /// Closes the current message. The data added afterwards is not accounted in its length.
     pub fn finalize_writing_object(&mut self) {
         self.headers = None;
     }
 }
 
+/// The type of a netlink attribute, e.g. `NFTA_TABLE_NAME`.
 pub type NetlinkType = u16;
 
+/// Decodes the attributes of an object, one at a time.
 pub trait AttributeDecoder {
+    /// Updates the object with the attribute of type `attr_type`, whose payload is `buf`. Fails
+    /// with [`DecodeError::UnsupportedAttributeType`] for the attributes the object doesn't know.
     fn decode_attribute(&mut self, attr_type: NetlinkType, buf: &[u8]) -> Result<(), DecodeError>;
 }
 
+/// Deserializes an object from the beginning of a buffer.
 pub trait NfNetlinkDeserializable: Sized {
+    /// Returns the object and the remainder of `buf`, following the object.
     fn deserialize(buf: &[u8]) -> Result<(Self, &[u8]), DecodeError>;
 }
 
+/// The state of a required attribute that was not set yet, in the builders generated by the
+/// `builder` parameter of `nfnetlink_struct`, e.g. [`ChainBuilder`](crate::ChainBuilder).
+#[derive(Debug)]
+pub enum AttributeMissing {}
+
+/// The state of a required attribute that was set, in the builders generated by the `builder`
+/// parameter of `nfnetlink_struct`.
+#[derive(Debug)]
+pub enum AttributePresent {}
+
+/// Implements [`NfNetlinkObject::without_read_only_attributes`] for the objects `$obj` whose
+/// dumps hold a use count, which is maintained by the kernel, and a handle, which can only
+/// designate the object to delete: the use count is always stripped, and the handle is stripped
+/// unless `$msg_type` is [`MsgType::Del`].
+macro_rules! without_use_count_and_handle {
+    ($obj:expr, $msg_type:expr) => {{
+        let strip_handle = $msg_type != crate::MsgType::Del && $obj.handle.is_some();
+        if $obj.use_count.is_none() && !strip_handle {
+            None
+        } else {
+            let mut stripped = $obj.clone();
+            stripped.use_count = None;
+            if strip_handle {
+                stripped.handle = None;
+            }
+            Some(stripped)
+        }
+    }};
+}
+pub(crate) use without_use_count_and_handle;
+
+/// A top-level object of nf_tables (e.g. a table or a rule), that is sent in its own netlink
+/// message, whose type depends on whether the object is added or deleted.
 pub trait NfNetlinkObject:
     Sized + AttributeDecoder + NfNetlinkDeserializable + NfNetlinkAttribute
 {
+    /// The message type used to add the object, e.g. `NFT_MSG_NEWTABLE`.
     const MSG_TYPE_ADD: u32;
+    /// The message type used to delete the object, e.g. `NFT_MSG_DELTABLE`.
     const MSG_TYPE_DEL: u32;
 
+    /// Writes the message adding or deleting the object with `writer`.
     fn add_or_remove<'a>(&self, writer: &mut NfNetlinkWriter<'a>, msg_type: MsgType, seq: u32) {
+        self.write_message(writer, msg_type, seq, self.get_message_flags(msg_type));
+    }
+
+    /// Same as [`NfNetlinkObject::add_or_remove`], with the netlink flags `flags` (to which
+    /// `NLM_F_REQUEST` is added) instead of the ones of [`NfNetlinkObject::get_message_flags`].
+    fn write_message<'a>(
+        &self,
+        writer: &mut NfNetlinkWriter<'a>,
+        msg_type: MsgType,
+        seq: u32,
+        flags: u16,
+    ) {
         let raw_msg_type = match msg_type {
-            MsgType::Add => Self::MSG_TYPE_ADD,
             MsgType::Del => Self::MSG_TYPE_DEL,
+            MsgType::Add | MsgType::Replace | MsgType::Insert => Self::MSG_TYPE_ADD,
         } as u16;
-        writer.write_header(
-            raw_msg_type,
-            self.get_family(),
-            (if let MsgType::Add = msg_type {
-                self.get_add_flags()
-            } else {
-                self.get_del_flags()
-            } | NLM_F_ACK) as u16,
-            seq,
-            None,
-        );
-        let buf = writer.add_data_zeroed(self.get_size());
-        self.write_payload(buf);
+        let stripped = self.without_read_only_attributes(msg_type, flags);
+        let obj = stripped.as_ref().unwrap_or(self);
+        writer.write_header(raw_msg_type, obj.get_family(), flags, seq, None);
+        let buf = writer.add_data_zeroed(obj.get_size());
+        obj.write_payload(buf);
         writer.finalize_writing_object();
     }
 
+    /// Encodes the message adding or deleting the object, with the sequence number `seq`, as
+    /// it would be written in a [`Batch`]. This lets the messages be laid out freely, e.g. to
+    /// interleave them with the messages of other nfnetlink subsystems. The message must still
+    /// be sent between the begin and end messages of a batch.
+    ///
+    /// [`Batch`]: crate::Batch
+    fn to_batch_message(&self, msg_type: MsgType, seq: u32) -> Vec<u8> {
+        self.to_message_with_flags(msg_type, seq, self.get_message_flags(msg_type))
+    }
+
+    /// Same as [`NfNetlinkObject::to_batch_message`], with the netlink flags `flags` (to which
+    /// `NLM_F_REQUEST` is added), e.g. to add `NLM_F_EXCL` or to drop `NLM_F_ACK`.
+    fn to_message_with_flags(&self, msg_type: MsgType, seq: u32, flags: u16) -> Vec<u8> {
+        let mut buf = Vec::new();
+        self.write_message(&mut NfNetlinkWriter::new(&mut buf), msg_type, seq, flags);
+        buf
+    }
+
+    /// The netlink flags of the messages of type `msg_type` written in batches:
+    /// [`NfNetlinkObject::get_add_flags`], [`NfNetlinkObject::get_del_flags`],
+    /// [`NfNetlinkObject::get_replace_flags`] or [`NfNetlinkObject::get_insert_flags`], with
+    /// `NLM_F_ACK`.
+    fn get_message_flags(&self, msg_type: MsgType) -> u16 {
+        (match msg_type {
+            MsgType::Add => self.get_add_flags(),
+            MsgType::Del => self.get_del_flags(),
+            MsgType::Replace => self.get_replace_flags(),
+            MsgType::Insert => self.get_insert_flags(),
+        } | NLM_F_ACK) as u16
+    }
+
+    /// The protocol family of the object, written in the `nfgenmsg` header.
     fn get_family(&self) -> ProtocolFamily;
 
     fn set_family(&mut self, _family: ProtocolFamily) {
@@ -161,25 +263,76 @@ pub trait NfNetlinkObject:
         self
     }
 
+    /// The names of the attributes the kernel requires to add (or delete) the object, and that
+    /// are not set. Checked by [`Batch::add_checked`].
+    ///
+    /// [`Batch::add_checked`]: crate::Batch::add_checked
+    fn missing_attributes(&self, _msg_type: MsgType) -> Vec<&'static str> {
+        Vec::new()
+    }
+
+    /// The name identifying the object in the errors of the kernel, e.g. the name of a table or
+    /// of a chain. `None` for the objects without a name, like rules.
+    fn object_name(&self) -> Option<String> {
+        None
+    }
+
+    /// A copy of the object without the attributes the kernel fills in its dumps (e.g. handles
+    /// and use counts), but refuses in the messages of type `msg_type` with the flags `flags`.
+    /// Returns `None` when the object holds no such attribute, which is the default.
+    ///
+    /// The messages are written from the copy, so objects listed from the kernel can be modified
+    /// and sent back as is.
+    fn without_read_only_attributes(&self, _msg_type: MsgType, _flags: u16) -> Option<Self> {
+        None
+    }
+
+    /// The attributes of the object as they are written in its messages, for debugging: unlike
+    /// `Debug`, this shows the encoding of the attributes, to compare it with the messages of nft.
+    ///
+    /// Fails if the attributes cannot be parsed back, e.g. when the raw bytes of an expression
+    /// unknown to this crate are malformed.
+    fn to_attribute_tree(&self) -> Result<RawAttributeTree, DecodeError> {
+        let mut buf = vec![0; self.get_size()];
+        self.write_payload(&mut buf);
+        RawAttributeTree::parse(&buf)
+    }
+
+    /// The netlink flags of the messages adding the object, `NLM_F_CREATE` by default.
     fn get_add_flags(&self) -> u32 {
         NLM_F_CREATE
     }
 
+    /// The netlink flags of the messages deleting the object, none by default.
     fn get_del_flags(&self) -> u32 {
         0
     }
+
+    /// The netlink flags of the messages replacing the object, `NLM_F_REPLACE` by default.
+    fn get_replace_flags(&self) -> u32 {
+        NLM_F_REPLACE
+    }
+
+    /// The netlink flags of the messages inserting the object, the ones of
+    /// [`NfNetlinkObject::get_add_flags`] by default.
+    fn get_insert_flags(&self) -> u32 {
+        self.get_add_flags()
+    }
 }
 
+/// A value that can be serialized as the payload of a netlink attribute (or of a message).
 pub trait NfNetlinkAttribute: Debug + Sized {
     // is it a nested argument that must be marked with a NLA_F_NESTED flag?
     fn is_nested(&self) -> bool {
         false
     }
 
+    /// The size of the payload, before padding.
     fn get_size(&self) -> usize {
         size_of::<Self>()
     }
 
+    /// Writes the payload in `addr`, which is [`NfNetlinkAttribute::get_size`] bytes long.
     // example body: std::ptr::copy_nonoverlapping(self as *const Self as *const u8, addr.as_mut_ptr(), self.get_size());
     fn write_payload(&self, addr: &mut [u8]);
 }
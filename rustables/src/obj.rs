@@ -0,0 +1,168 @@
+use std::fmt::Debug;
+
+use rustables_macros::{nfnetlink_enum, nfnetlink_object, nfnetlink_struct};
+
+use crate::error::BuilderError;
+#[cfg(feature = "socket")]
+use crate::error::QueryError;
+use crate::expr::Counter;
+use crate::nlmsg::{without_use_count_and_handle, NfNetlinkDeserializable, NfNetlinkObject};
+#[cfg(feature = "socket")]
+use crate::query::list_objects_with_data;
+use crate::sys::{
+    NFTA_OBJ_DATA, NFTA_OBJ_HANDLE, NFTA_OBJ_NAME, NFTA_OBJ_TABLE, NFTA_OBJ_TYPE, NFTA_OBJ_USE,
+    NFT_MSG_DELOBJ, NFT_MSG_NEWOBJ, NFT_OBJECT_CONNLIMIT, NFT_OBJECT_COUNTER, NFT_OBJECT_CT_EXPECT,
+    NFT_OBJECT_CT_HELPER, NFT_OBJECT_CT_TIMEOUT, NFT_OBJECT_LIMIT, NFT_OBJECT_QUOTA,
+    NFT_OBJECT_SECMARK, NFT_OBJECT_SYNPROXY, NFT_OBJECT_TUNNEL,
+};
+use crate::{MsgType, ProtocolFamily, Table};
+
+/// The type of a stateful object.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+#[nfnetlink_enum(u32)]
+pub enum ObjectType {
+    Counter = NFT_OBJECT_COUNTER,
+    Quota = NFT_OBJECT_QUOTA,
+    CtHelper = NFT_OBJECT_CT_HELPER,
+    Limit = NFT_OBJECT_LIMIT,
+    Connlimit = NFT_OBJECT_CONNLIMIT,
+    Tunnel = NFT_OBJECT_TUNNEL,
+    CtTimeout = NFT_OBJECT_CT_TIMEOUT,
+    Secmark = NFT_OBJECT_SECMARK,
+    CtExpect = NFT_OBJECT_CT_EXPECT,
+    Synproxy = NFT_OBJECT_SYNPROXY,
+}
+
+impl std::fmt::Display for ObjectType {
+    /// Displays the type with the name nft uses for it, e.g. `ct helper`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ObjectType::Counter => "counter",
+            ObjectType::Quota => "quota",
+            ObjectType::CtHelper => "ct helper",
+            ObjectType::Limit => "limit",
+            ObjectType::Connlimit => "ct count",
+            ObjectType::Tunnel => "tunnel",
+            ObjectType::CtTimeout => "ct timeout",
+            ObjectType::Secmark => "secmark",
+            ObjectType::CtExpect => "ct expectation",
+            ObjectType::Synproxy => "synproxy",
+        })
+    }
+}
+
+/// A stateful object (counter, quota, limit, ...) that lives in a [`Table`] and can be
+/// referenced by name from several rules.
+///
+/// The content of `data` depends on the type of the object, and is left undecoded, with the
+/// exception of counters that can be read with [`Obj::counter`].
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+#[nfnetlink_struct(derive_deserialize = false)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Obj {
+    pub family: ProtocolFamily,
+    #[field(NFTA_OBJ_TABLE)]
+    pub table: String,
+    #[field(NFTA_OBJ_NAME)]
+    pub name: String,
+    #[field(NFTA_OBJ_TYPE)]
+    pub obj_type: ObjectType,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, with = "crate::serde_helpers::option_hex")
+    )]
+    #[field(NFTA_OBJ_DATA)]
+    pub data: Vec<u8>,
+    #[field(NFTA_OBJ_USE)]
+    pub use_count: u32,
+    #[field(NFTA_OBJ_HANDLE)]
+    pub handle: u64,
+}
+
+impl Obj {
+    pub fn new(table: &Table) -> Result<Self, BuilderError> {
+        let table_name = table.get_name().ok_or(BuilderError::MissingTableName)?;
+        let mut obj = Obj::default().with_table(table_name);
+        obj.family = table.get_family();
+        Ok(obj)
+    }
+
+    /// Decodes the content of a counter object. Returns `None` if this object is not a counter,
+    /// or if it doesn't hold any data.
+    pub fn counter(&self) -> Option<Counter> {
+        if self.obj_type != Some(ObjectType::Counter) {
+            return None;
+        }
+        let data = self.data.as_ref()?;
+        Counter::deserialize(data).ok().map(|(counter, _)| counter)
+    }
+}
+
+#[nfnetlink_object(add = NFT_MSG_NEWOBJ, del = NFT_MSG_DELOBJ, family_field = family)]
+impl NfNetlinkObject for Obj {
+    fn object_name(&self) -> Option<String> {
+        self.name.clone()
+    }
+
+    fn without_read_only_attributes(&self, msg_type: MsgType, _flags: u16) -> Option<Self> {
+        without_use_count_and_handle!(self, msg_type)
+    }
+
+    fn missing_attributes(&self, msg_type: MsgType) -> Vec<&'static str> {
+        let mut missing = Vec::new();
+        if self.table.is_none() {
+            missing.push("NFTA_OBJ_TABLE");
+        }
+        if self.name.is_none() {
+            missing.push("NFTA_OBJ_NAME");
+        }
+        if self.obj_type.is_none() {
+            missing.push("NFTA_OBJ_TYPE");
+        }
+        if msg_type != MsgType::Del && self.data.is_none() {
+            missing.push("NFTA_OBJ_DATA");
+        }
+        missing
+    }
+}
+
+/// Lists the stateful objects of `table`. If `obj_type` is set, only the objects of that type
+/// are returned, the filtering being done by the kernel.
+#[cfg(feature = "socket")]
+pub fn list_objects_for_table(
+    table: &Table,
+    obj_type: Option<ObjectType>,
+) -> Result<Vec<Obj>, QueryError> {
+    let mut filter = Obj::new(table)?;
+    filter.obj_type = obj_type;
+
+    let mut result = Vec::new();
+    list_objects_with_data(
+        crate::sys::NFT_MSG_GETOBJ as u16,
+        &|obj: Obj, objs: &mut Vec<Obj>| {
+            objs.push(obj);
+            Ok(())
+        },
+        Some(&filter),
+        &mut result,
+    )?;
+    Ok(result)
+}
+
+/// Reads the counter objects of `table`, returning a `(name, packets, bytes)` tuple for each.
+#[cfg(feature = "socket")]
+pub fn read_counter_objects(table: &Table) -> Result<Vec<(String, u64, u64)>, QueryError> {
+    Ok(list_objects_for_table(table, Some(ObjectType::Counter))?
+        .iter()
+        .filter_map(|obj| {
+            let counter = obj.counter()?;
+            Some((
+                obj.name.clone()?,
+                counter.nb_packets.unwrap_or(0),
+                counter.nb_bytes.unwrap_or(0),
+            ))
+        })
+        .collect())
+}
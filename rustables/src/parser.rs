@@ -1,21 +1,117 @@
 use std::{
+    cell::Cell,
     fmt::Debug,
+    marker::PhantomData,
     mem::{size_of, transmute},
 };
 
 use crate::{
-    error::DecodeError,
+    error::{DecodeError, NetlinkErrorReport},
     nlmsg::{
         get_operation_from_nlmsghdr_type, get_subsystem_from_nlmsghdr_type, pad_netlink_object,
         pad_netlink_object_with_variable_size, AttributeDecoder, NetlinkType, NfNetlinkAttribute,
+        NfNetlinkObject,
     },
     sys::{
-        nfgenmsg, nlattr, nlmsgerr, nlmsghdr, NFNETLINK_V0, NFNL_MSG_BATCH_BEGIN,
-        NFNL_MSG_BATCH_END, NFNL_SUBSYS_NFTABLES, NLA_F_NESTED, NLA_TYPE_MASK, NLMSG_DONE,
-        NLMSG_ERROR, NLMSG_MIN_TYPE, NLMSG_NOOP, NLM_F_DUMP_INTR,
+        self, nfgenmsg, nlattr, nlmsgerr, nlmsghdr, NFNETLINK_V0, NFNL_MSG_BATCH_BEGIN,
+        NFNL_MSG_BATCH_END, NFNL_SUBSYS_NFTABLES, NLA_F_NESTED, NLA_TYPE_MASK, NLMSGERR_ATTR_MSG,
+        NLMSGERR_ATTR_OFFS, NLMSG_DONE, NLMSG_ERROR, NLMSG_MIN_TYPE, NLMSG_NOOP, NLM_F_ACK_TLVS,
+        NLM_F_CAPPED, NLM_F_DUMP_INTR,
     },
 };
 
+/// How the decoders handle the data the crate doesn't know about.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum DecodeMode {
+    /// Unknown attributes are logged and skipped, and unknown expressions are kept as
+    /// [`ExpressionRaw`]. This lets the crate decode the objects of newer kernels.
+    ///
+    /// [`ExpressionRaw`]: crate::expr::ExpressionRaw
+    #[default]
+    Lenient,
+    /// Unknown attributes fail with [`DecodeError::UnknownAttribute`], and unknown expressions
+    /// with [`DecodeError::UnknownExpression`]. Meant for test environments, to detect when the
+    /// kernel starts sending data that would otherwise be dropped silently.
+    Strict,
+}
+
+thread_local! {
+    static DECODE_MODE: Cell<DecodeMode> = const { Cell::new(DecodeMode::Lenient) };
+}
+
+/// Runs `f` with the objects decoded in the mode `mode` on the current thread, e.g. to list the
+/// rules of a chain in strict mode:
+///
+/// ```ignore
+/// let rules = with_decode_mode(DecodeMode::Strict, || list_rules_for_chain(&chain))?;
+/// ```
+///
+/// The previous mode is restored when `f` returns, so calls can be nested, and a strict section
+/// can contain a lenient one.
+pub fn with_decode_mode<R>(mode: DecodeMode, f: impl FnOnce() -> R) -> R {
+    struct RestoreMode(DecodeMode);
+
+    impl Drop for RestoreMode {
+        fn drop(&mut self) {
+            DECODE_MODE.with(|m| m.set(self.0));
+        }
+    }
+
+    let _restore = RestoreMode(DECODE_MODE.with(|m| m.replace(mode)));
+    f()
+}
+
+/// The decode mode of the current thread, see [`with_decode_mode`].
+pub fn decode_mode() -> DecodeMode {
+    DECODE_MODE.with(|m| m.get())
+}
+
+/// The order in which the attributes of the objects are written.
+///
+/// The kernel does not depend on the order of the attributes, but byte-exact comparisons do (e.g.
+/// golden files of serialized batches).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum AttributeOrder {
+    /// The attributes are written in the order of declaration of their fields in the structure of
+    /// the object. This is stable across releases only as long as the fields are not reordered.
+    #[default]
+    Declaration,
+    /// The attributes are written by increasing attribute type (e.g. `NFTA_RULE_TABLE` before
+    /// `NFTA_RULE_CHAIN`), which only depends on the kernel headers. The elements of lists (e.g.
+    /// the expressions of a rule) keep their order, which is meaningful.
+    ByType,
+}
+
+thread_local! {
+    static ATTRIBUTE_ORDER: Cell<AttributeOrder> = const { Cell::new(AttributeOrder::Declaration) };
+}
+
+/// Runs `f` with the objects written in the attribute order `order` on the current thread, e.g.
+/// to compare a batch to a golden file that survives the refactorings of the crate:
+///
+/// ```ignore
+/// let buf = with_attribute_order(AttributeOrder::ByType, || batch.finalize());
+/// ```
+///
+/// The previous order is restored when `f` returns.
+pub fn with_attribute_order<R>(order: AttributeOrder, f: impl FnOnce() -> R) -> R {
+    struct RestoreOrder(AttributeOrder);
+
+    impl Drop for RestoreOrder {
+        fn drop(&mut self) {
+            ATTRIBUTE_ORDER.with(|o| o.set(self.0));
+        }
+    }
+
+    let _restore = RestoreOrder(ATTRIBUTE_ORDER.with(|o| o.replace(order)));
+    f()
+}
+
+/// The attribute order of the current thread, see [`with_attribute_order`].
+pub fn attribute_order() -> AttributeOrder {
+    ATTRIBUTE_ORDER.with(|o| o.get())
+}
+
 pub fn get_nlmsghdr(buf: &[u8]) -> Result<nlmsghdr, DecodeError> {
     let size_of_hdr = size_of::<nlmsghdr>();
 
@@ -37,11 +133,23 @@ pub fn get_nlmsghdr(buf: &[u8]) -> Result<nlmsghdr, DecodeError> {
     Ok(nlmsghdr)
 }
 
+/// Returns the resource id of `nfgenmsg`, in host byte order.
+///
+/// Like the kernel, this accepts the resource id of nftables batches written in host byte order,
+/// as older versions of nft (and of this library) did.
+pub fn get_res_id(nfgenmsg: &nfgenmsg) -> u16 {
+    if nfgenmsg.res_id == NFNL_SUBSYS_NFTABLES as u16 {
+        NFNL_SUBSYS_NFTABLES as u16
+    } else {
+        u16::from_be(nfgenmsg.res_id)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum NlMsg<'a> {
     Done,
     Noop,
-    Error(nlmsgerr),
+    Error(NetlinkErrorReport),
     NfGenMsg(nfgenmsg, &'a [u8]),
 }
 
@@ -67,7 +175,8 @@ pub fn parse_nlmsg<'a>(buf: &'a [u8]) -> Result<(nlmsghdr, NlMsg<'a>), DecodeErr
                 };
                 // some APIs return negative values, while other return positive values
                 err.error = err.error.abs();
-                return Ok((hdr, NlMsg::Error(err)));
+                let extra = &buf[size_of_hdr + size_of::<nlmsgerr>()..hdr.nlmsg_len as usize];
+                return Ok((hdr, NlMsg::Error(parse_error_report(&hdr, err, extra))));
             }
             x if x == NLMSG_DONE => return Ok((hdr, NlMsg::Done)),
             x => return Err(DecodeError::UnsupportedType(x as u16)),
@@ -98,11 +207,300 @@ pub fn parse_nlmsg<'a>(buf: &'a [u8]) -> Result<(nlmsghdr, NlMsg<'a>), DecodeErr
         return Err(DecodeError::InvalidVersion(nfgenmsg.version));
     }
 
+    // the resource id of batch messages designates the subsystem targeted by the batch
+    if hdr.nlmsg_type == NFNL_MSG_BATCH_BEGIN as u16 || hdr.nlmsg_type == NFNL_MSG_BATCH_END as u16
+    {
+        let res_id = get_res_id(&nfgenmsg);
+        if res_id != NFNL_SUBSYS_NFTABLES as u16 {
+            return Err(DecodeError::InvalidResourceId(res_id));
+        }
+    }
+
     let raw_value = &buf[size_of_hdr + size_of_nfgenmsg..hdr.nlmsg_len as usize];
 
     Ok((hdr, NlMsg::NfGenMsg(nfgenmsg, raw_value)))
 }
 
+/// An iterator over the netlink messages of a buffer, see [`parse_nlmsgs`].
+pub(crate) struct NlMsgs<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Iterator for NlMsgs<'a> {
+    /// A message, with its header and its bytes (header included).
+    type Item = Result<(nlmsghdr, NlMsg<'a>, &'a [u8]), DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.buf.is_empty() {
+            return None;
+        }
+        let res = parse_nlmsg(self.buf).and_then(|(hdr, msg)| {
+            // an empty message would never let the iteration advance
+            if (hdr.nlmsg_len as usize) < size_of::<nlmsghdr>() {
+                return Err(DecodeError::NlMsgTooSmall);
+            }
+            Ok((hdr, msg))
+        });
+        match res {
+            Ok((hdr, msg)) => {
+                let raw = &self.buf[..(hdr.nlmsg_len as usize).min(self.buf.len())];
+                let len = pad_netlink_object_with_variable_size(hdr.nlmsg_len as usize);
+                self.buf = &self.buf[len.min(self.buf.len())..];
+                Some(Ok((hdr, msg, raw)))
+            }
+            Err(e) => {
+                // the end of the current message is unknown, there is no way to resume
+                self.buf = &[];
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// Iterates over the netlink messages of `buf`, e.g. a datagram received from the kernel, which
+/// holds one or several complete messages. The iteration ends at the first invalid message.
+pub(crate) fn parse_nlmsgs(buf: &[u8]) -> NlMsgs<'_> {
+    NlMsgs { buf }
+}
+
+/// An iterator over the objects of a sequence of netlink messages, see
+/// [`parse_response_stream`].
+pub struct ResponseStream<'a, T> {
+    msgs: NlMsgs<'a>,
+    _phantom: PhantomData<T>,
+}
+
+impl<T: NfNetlinkObject> Iterator for ResponseStream<'_, T> {
+    type Item = Result<(nlmsghdr, T), DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (hdr, msg, raw) = match self.msgs.next()? {
+                Ok(x) => x,
+                Err(e) => return Some(Err(e)),
+            };
+            match msg {
+                NlMsg::Done => {
+                    self.msgs.buf = &[];
+                    return None;
+                }
+                NlMsg::Error(e) if e.err.error != 0 => {
+                    self.msgs.buf = &[];
+                    return Some(Err(DecodeError::ErrorMessage(e)));
+                }
+                NlMsg::NfGenMsg(..) => {
+                    let op = get_operation_from_nlmsghdr_type(hdr.nlmsg_type) as u32;
+                    if op == T::MSG_TYPE_ADD || op == T::MSG_TYPE_DEL {
+                        return Some(T::deserialize(raw).map(|(obj, _)| (hdr, obj)));
+                    }
+                }
+                NlMsg::Error(_) | NlMsg::Noop => {}
+            }
+        }
+    }
+}
+
+/// Decodes the objects of type `T` held by `buf`, a sequence of netlink messages such as the
+/// responses to a dump, or a batch as sent to the kernel. Each object is yielded with the header
+/// of its message, e.g. to match it with a request by its sequence number:
+///
+/// ```ignore
+/// for res in parse_response_stream::<Rule>(&buf) {
+///     let (header, rule) = res?;
+///     println!("{}: {:?}", header.nlmsg_seq, rule.get_handle());
+/// }
+/// ```
+///
+/// The messages about other types of objects, the acknowledgements and the batch delimiters are
+/// skipped, and the iteration stops at the end of a dump. An error reported by the kernel is
+/// yielded as [`DecodeError::ErrorMessage`], and ends the iteration, like any other error.
+pub fn parse_response_stream<T: NfNetlinkObject>(buf: &[u8]) -> ResponseStream<'_, T> {
+    ResponseStream {
+        msgs: parse_nlmsgs(buf),
+        _phantom: PhantomData,
+    }
+}
+
+/// Decodes the content that follows a `nlmsgerr`: the payload of the offending message (unless
+/// the kernel capped it), then the extended ACK attributes (if any).
+fn parse_error_report(hdr: &nlmsghdr, err: nlmsgerr, extra: &[u8]) -> NetlinkErrorReport {
+    let mut report = NetlinkErrorReport {
+        err,
+        message: None,
+        bad_attr_offset: None,
+        bad_attr_name: None,
+    };
+
+    let size_of_hdr = pad_netlink_object::<nlmsghdr>();
+    let mut orig_payload = None;
+    let mut tlvs = extra;
+    if hdr.nlmsg_flags & NLM_F_CAPPED as u16 == 0 {
+        let payload_len = (err.msg.nlmsg_len as usize).saturating_sub(size_of_hdr);
+        if payload_len > extra.len() {
+            return report;
+        }
+        orig_payload = Some(&extra[..payload_len]);
+        tlvs = &extra[pad_netlink_object_with_variable_size(payload_len).min(extra.len())..];
+    }
+    if hdr.nlmsg_flags & NLM_F_ACK_TLVS as u16 == 0 {
+        return report;
+    }
+
+    // the padding of the last attribute may be missing, so `pos` can go past the end of `tlvs`
+    let mut pos = 0;
+    while let Some(rest) = tlvs.get(pos..) {
+        if rest.len() < pad_netlink_object::<nlattr>() {
+            break;
+        }
+        let nlattr = unsafe { std::ptr::read_unaligned(rest.as_ptr() as *const nlattr) };
+        let len = nlattr.nla_len as usize;
+        if len < pad_netlink_object::<nlattr>() || len > rest.len() {
+            break;
+        }
+        let value = &rest[pad_netlink_object::<nlattr>()..len];
+        match (nlattr.nla_type & NLA_TYPE_MASK as u16) as u32 {
+            x if x == NLMSGERR_ATTR_MSG as u32 => {
+                let value = value.split(|c| *c == 0).next().unwrap_or(value);
+                report.message = Some(String::from_utf8_lossy(value).into_owned());
+            }
+            x if x == NLMSGERR_ATTR_OFFS as u32 && value.len() >= size_of::<u32>() => {
+                report.bad_attr_offset =
+                    Some(u32::from_ne_bytes([value[0], value[1], value[2], value[3]]));
+            }
+            _ => {}
+        }
+        pos += pad_netlink_object_with_variable_size(len);
+    }
+
+    if let (Some(offset), Some(payload)) = (report.bad_attr_offset, orig_payload) {
+        report.bad_attr_name = find_attribute_at_offset(err.msg.nlmsg_type, payload, offset);
+    }
+    report
+}
+
+/// Finds the name of the top-level attribute of a nftables message that contains the byte at
+/// `offset` (from the start of the message header).
+fn find_attribute_at_offset(msg_type: u16, payload: &[u8], offset: u32) -> Option<&'static str> {
+    let attrs_start = pad_netlink_object::<nfgenmsg>();
+    let offset = (offset as usize).checked_sub(pad_netlink_object::<nlmsghdr>() + attrs_start)?;
+    let attrs = payload.get(attrs_start..)?;
+
+    let mut pos = 0;
+    while let Some(rest) = attrs.get(pos..) {
+        if rest.len() < pad_netlink_object::<nlattr>() {
+            break;
+        }
+        let nlattr = unsafe { std::ptr::read_unaligned(rest.as_ptr() as *const nlattr) };
+        let len = nlattr.nla_len as usize;
+        if len < pad_netlink_object::<nlattr>() || len > rest.len() {
+            return None;
+        }
+        if offset < pos + len {
+            return attribute_name(
+                get_operation_from_nlmsghdr_type(msg_type) as u32,
+                nlattr.nla_type & NLA_TYPE_MASK as u16,
+            );
+        }
+        pos += pad_netlink_object_with_variable_size(len);
+    }
+    None
+}
+
+macro_rules! match_attribute_names {
+    ($attr:expr, $($name:ident),*) => {
+        match $attr {
+            $(x if x == sys::$name => Some(stringify!($name)),)*
+            _ => None,
+        }
+    };
+}
+
+/// Returns the name of the top-level attribute `attr` in a nftables message of type `operation`.
+fn attribute_name(operation: u32, attr: u16) -> Option<&'static str> {
+    match operation {
+        sys::NFT_MSG_NEWTABLE | sys::NFT_MSG_GETTABLE | sys::NFT_MSG_DELTABLE => {
+            match_attribute_names!(attr, NFTA_TABLE_NAME, NFTA_TABLE_FLAGS, NFTA_TABLE_USE)
+        }
+        sys::NFT_MSG_NEWCHAIN | sys::NFT_MSG_GETCHAIN | sys::NFT_MSG_DELCHAIN => {
+            match_attribute_names!(
+                attr,
+                NFTA_CHAIN_TABLE,
+                NFTA_CHAIN_HANDLE,
+                NFTA_CHAIN_NAME,
+                NFTA_CHAIN_HOOK,
+                NFTA_CHAIN_POLICY,
+                NFTA_CHAIN_USE,
+                NFTA_CHAIN_TYPE,
+                NFTA_CHAIN_COUNTERS,
+                NFTA_CHAIN_FLAGS
+            )
+        }
+        sys::NFT_MSG_NEWRULE | sys::NFT_MSG_GETRULE | sys::NFT_MSG_DELRULE => {
+            match_attribute_names!(
+                attr,
+                NFTA_RULE_TABLE,
+                NFTA_RULE_CHAIN,
+                NFTA_RULE_HANDLE,
+                NFTA_RULE_EXPRESSIONS,
+                NFTA_RULE_COMPAT,
+                NFTA_RULE_POSITION,
+                NFTA_RULE_USERDATA,
+                NFTA_RULE_ID
+            )
+        }
+        sys::NFT_MSG_NEWSET | sys::NFT_MSG_GETSET | sys::NFT_MSG_DELSET => {
+            match_attribute_names!(
+                attr,
+                NFTA_SET_TABLE,
+                NFTA_SET_NAME,
+                NFTA_SET_FLAGS,
+                NFTA_SET_KEY_TYPE,
+                NFTA_SET_KEY_LEN,
+                NFTA_SET_DATA_TYPE,
+                NFTA_SET_DATA_LEN,
+                NFTA_SET_POLICY,
+                NFTA_SET_DESC,
+                NFTA_SET_ID,
+                NFTA_SET_TIMEOUT,
+                NFTA_SET_GC_INTERVAL,
+                NFTA_SET_USERDATA
+            )
+        }
+        sys::NFT_MSG_NEWSETELEM | sys::NFT_MSG_GETSETELEM | sys::NFT_MSG_DELSETELEM => {
+            match_attribute_names!(
+                attr,
+                NFTA_SET_ELEM_LIST_TABLE,
+                NFTA_SET_ELEM_LIST_SET,
+                NFTA_SET_ELEM_LIST_ELEMENTS,
+                NFTA_SET_ELEM_LIST_SET_ID
+            )
+        }
+        sys::NFT_MSG_NEWOBJ | sys::NFT_MSG_GETOBJ | sys::NFT_MSG_DELOBJ => {
+            match_attribute_names!(
+                attr,
+                NFTA_OBJ_TABLE,
+                NFTA_OBJ_NAME,
+                NFTA_OBJ_TYPE,
+                NFTA_OBJ_DATA,
+                NFTA_OBJ_USE,
+                NFTA_OBJ_HANDLE
+            )
+        }
+        sys::NFT_MSG_NEWFLOWTABLE | sys::NFT_MSG_GETFLOWTABLE | sys::NFT_MSG_DELFLOWTABLE => {
+            match_attribute_names!(
+                attr,
+                NFTA_FLOWTABLE_TABLE,
+                NFTA_FLOWTABLE_NAME,
+                NFTA_FLOWTABLE_HOOK,
+                NFTA_FLOWTABLE_USE,
+                NFTA_FLOWTABLE_HANDLE,
+                NFTA_FLOWTABLE_FLAGS
+            )
+        }
+        _ => None,
+    }
+}
+
 /// Write the attribute, preceded by a `libc::nlattr`
 // rewrite of `mnl_attr_put`
 pub fn write_attribute<'a>(ty: NetlinkType, obj: &impl NfNetlinkAttribute, mut buf: &mut [u8]) {
@@ -146,11 +544,16 @@ pub(crate) fn read_attributes<T: AttributeDecoder + Debug + Default>(
         let attr_remaining_size = nlattr.nla_len as usize - pad_netlink_object::<nlattr>();
         match T::decode_attribute(&mut res, nla_type, &buf[pos..pos + attr_remaining_size]) {
             Ok(()) => {}
-            Err(DecodeError::UnsupportedAttributeType(t)) => info!(
-                "Ignoring unsupported attribute type {} for type {}",
-                t,
-                std::any::type_name::<T>()
-            ),
+            Err(DecodeError::UnsupportedAttributeType(t)) => {
+                if decode_mode() == DecodeMode::Strict {
+                    return Err(DecodeError::UnknownAttribute(t, std::any::type_name::<T>()));
+                }
+                info!(
+                    "Ignoring unsupported attribute type {} for type {}",
+                    t,
+                    std::any::type_name::<T>()
+                )
+            }
             Err(e) => return Err(e),
         }
         pos += pad_netlink_object_with_variable_size(attr_remaining_size);
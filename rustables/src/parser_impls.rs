@@ -1,5 +1,5 @@
 use std::{
-    fmt::Debug,
+    fmt::{self, Debug, Display, Formatter},
     mem::{size_of, transmute},
 };
 
@@ -25,68 +25,43 @@ impl NfNetlinkAttribute for u8 {
 
 impl NfNetlinkDeserializable for u8 {
     fn deserialize(buf: &[u8]) -> Result<(Self, &[u8]), DecodeError> {
-        Ok((buf[0], &buf[1..]))
+        let ([value], remaining) = split_bytes(buf)?;
+        Ok((value, remaining))
     }
 }
 
-impl NfNetlinkAttribute for u16 {
-    fn write_payload(&self, addr: &mut [u8]) {
-        addr[0..size_of::<Self>()].copy_from_slice(&self.to_be_bytes());
-    }
-}
-
-impl NfNetlinkDeserializable for u16 {
-    fn deserialize(buf: &[u8]) -> Result<(Self, &[u8]), DecodeError> {
-        Ok((u16::from_be_bytes([buf[0], buf[1]]), &buf[2..]))
-    }
-}
-
-impl NfNetlinkAttribute for i32 {
-    fn write_payload(&self, addr: &mut [u8]) {
-        addr[0..size_of::<Self>()].copy_from_slice(&self.to_be_bytes());
-    }
-}
-
-impl NfNetlinkDeserializable for i32 {
-    fn deserialize(buf: &[u8]) -> Result<(Self, &[u8]), DecodeError> {
-        Ok((
-            i32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]]),
-            &buf[4..],
-        ))
+/// Reads the `N` bytes of `buf`, or fails if `buf` holds more or less than that, e.g. when the
+/// kernel sends an attribute of a different size than expected.
+fn split_bytes<const N: usize>(buf: &[u8]) -> Result<([u8; N], &[u8]), DecodeError> {
+    if buf.len() != N {
+        return Err(DecodeError::InvalidDataSize);
     }
+    let mut res = [0; N];
+    res.copy_from_slice(buf);
+    Ok((res, &[]))
 }
 
-impl NfNetlinkAttribute for u32 {
-    fn write_payload(&self, addr: &mut [u8]) {
-        addr[0..size_of::<Self>()].copy_from_slice(&self.to_be_bytes());
-    }
-}
-
-impl NfNetlinkDeserializable for u32 {
-    fn deserialize(buf: &[u8]) -> Result<(Self, &[u8]), DecodeError> {
-        Ok((
-            u32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]]),
-            &buf[4..],
-        ))
-    }
-}
+// the integer attributes are in network byte order, both ways
+macro_rules! impl_integer_attribute {
+    ($($ty:ty),*) => {
+        $(
+            impl NfNetlinkAttribute for $ty {
+                fn write_payload(&self, addr: &mut [u8]) {
+                    addr[0..size_of::<Self>()].copy_from_slice(&self.to_be_bytes());
+                }
+            }
 
-impl NfNetlinkAttribute for u64 {
-    fn write_payload(&self, addr: &mut [u8]) {
-        addr[0..size_of::<Self>()].copy_from_slice(&self.to_be_bytes());
-    }
+            impl NfNetlinkDeserializable for $ty {
+                fn deserialize(buf: &[u8]) -> Result<(Self, &[u8]), DecodeError> {
+                    let (bytes, remaining) = split_bytes(buf)?;
+                    Ok((<$ty>::from_be_bytes(bytes), remaining))
+                }
+            }
+        )*
+    };
 }
 
-impl NfNetlinkDeserializable for u64 {
-    fn deserialize(buf: &[u8]) -> Result<(Self, &[u8]), DecodeError> {
-        Ok((
-            u64::from_be_bytes([
-                buf[0], buf[1], buf[2], buf[3], buf[4], buf[5], buf[6], buf[7],
-            ]),
-            &buf[8..],
-        ))
-    }
-}
+impl_integer_attribute!(u16, i32, u32, u64);
 
 impl NfNetlinkAttribute for String {
     fn get_size(&self) -> usize {
@@ -125,14 +100,55 @@ impl NfNetlinkDeserializable for Vec<u8> {
 }
 #[derive(Clone, PartialEq, Eq, Default, Debug)]
 #[nfnetlink_struct(nested = true)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NfNetlinkData {
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, with = "crate::serde_helpers::option_hex")
+    )]
     #[field(NFTA_DATA_VALUE)]
     value: Vec<u8>,
     #[field(NFTA_DATA_VERDICT)]
     verdict: Verdict,
 }
 
+impl Display for NfNetlinkData {
+    /// Displays the verdict, or the value. Values are shown as quoted strings when they look like
+    /// a NUL-terminated string (e.g. an interface name), as numbers when they are one or two
+    /// bytes long (e.g. ports and protocols, in network byte order), and in hexadecimal otherwise.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if let Some(verdict) = &self.verdict {
+            return write!(f, "{}", verdict);
+        }
+        let value = match &self.value {
+            Some(value) => value,
+            None => return f.write_str("?"),
+        };
+        let text_len = value.iter().position(|b| *b == 0).unwrap_or(value.len());
+        let (text, padding) = value.split_at(text_len);
+        if !text.is_empty()
+            && !padding.is_empty()
+            && text.iter().all(|b| b.is_ascii_graphic())
+            && padding.iter().all(|b| *b == 0)
+        {
+            return write!(f, "\"{}\"", String::from_utf8_lossy(text));
+        }
+        match value.len() {
+            1 => write!(f, "{}", value[0]),
+            2 => write!(f, "{}", u16::from_be_bytes([value[0], value[1]])),
+            _ => {
+                f.write_str("0x")?;
+                for b in value {
+                    write!(f, "{:02x}", b)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NfNetlinkList<T>
 where
     T: NfNetlinkDeserializable + NfNetlinkAttribute + Debug + Clone + Eq + Default,
@@ -234,6 +250,28 @@ where
     }
 }
 
+impl<O, T> FromIterator<O> for NfNetlinkList<T>
+where
+    T: From<O>,
+    T: NfNetlinkDeserializable + NfNetlinkAttribute + Clone + Eq + Default,
+{
+    fn from_iter<I: IntoIterator<Item = O>>(iter: I) -> Self {
+        NfNetlinkList {
+            objs: iter.into_iter().map(T::from).collect(),
+        }
+    }
+}
+
+impl<O, T> Extend<O> for NfNetlinkList<T>
+where
+    T: From<O>,
+    T: NfNetlinkDeserializable + NfNetlinkAttribute + Clone + Eq + Default,
+{
+    fn extend<I: IntoIterator<Item = O>>(&mut self, iter: I) {
+        self.objs.extend(iter.into_iter().map(T::from));
+    }
+}
+
 impl<T> NfNetlinkDeserializable for T
 where
     T: NfNetlinkObject + AttributeDecoder + Default + Sized,
@@ -244,7 +282,7 @@ where
             <T as NfNetlinkObject>::MSG_TYPE_ADD,
             <T as NfNetlinkObject>::MSG_TYPE_DEL,
         )?;
-        obj.set_family(ProtocolFamily::try_from(nfgenmsg.nfgen_family as i32)?);
+        obj.set_family(ProtocolFamily::from(nfgenmsg.nfgen_family as i32));
 
         Ok((obj, remaining_data))
     }
@@ -0,0 +1,299 @@
+//! Human-readable plans of the changes held by a [`Batch`], for the operators of interactive tools
+//! to review them before the batch is sent.
+//!
+//! ```ignore
+//! let plan = batch.plan()?;
+//! println!("{}", plan);
+//! if confirm()? {
+//!     batch.send()?;
+//! }
+//! ```
+//!
+//! The plan lists the changes grouped by table, in the order in which the tables first appear in
+//! the batch. The changes of a table keep the order in which the kernel applies them, but those of
+//! different tables may be interleaved in the batch: [`Plan::changes`] holds the exact order.
+//!
+//! ```text
+//! table inet filter
+//!   + chain input
+//!   + rule input: immediate accept -> verdict
+//!   ~ rule input handle 4: immediate drop -> verdict
+//!   - set blocked
+//! 2 to add, 1 to replace, 1 to delete
+//! ```
+//!
+//! The expressions of the rules are written with their `Display` implementation, separated by
+//! semicolons.
+
+use std::fmt::{self, Display, Formatter};
+
+use crate::error::DecodeError;
+use crate::nlmsg::NfNetlinkObject;
+use crate::nlmsg::{get_operation_from_nlmsghdr_type, get_subsystem_from_nlmsghdr_type};
+use crate::parser::{parse_nlmsgs, NlMsg};
+use crate::set::SetElementList;
+use crate::sys::{
+    NFNL_SUBSYS_NFTABLES, NFT_MSG_DELCHAIN, NFT_MSG_DELFLOWTABLE, NFT_MSG_DELOBJ, NFT_MSG_DELRULE,
+    NFT_MSG_DELSET, NFT_MSG_DELSETELEM, NFT_MSG_DELTABLE, NFT_MSG_NEWCHAIN, NFT_MSG_NEWFLOWTABLE,
+    NFT_MSG_NEWOBJ, NFT_MSG_NEWRULE, NFT_MSG_NEWSET, NFT_MSG_NEWSETELEM, NFT_MSG_NEWTABLE,
+    NLM_F_REPLACE,
+};
+use crate::zeroize::ZeroOnDrop;
+use crate::{Batch, Chain, Flowtable, Obj, ProtocolFamily, Rule, Set, Table};
+
+/// What a [`PlannedChange`] does to its object.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum PlanAction {
+    Add,
+    /// The object replaces an existing one, e.g. a rule designated by its handle.
+    Replace,
+    Delete,
+}
+
+impl Display for PlanAction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PlanAction::Add => "+",
+            PlanAction::Replace => "~",
+            PlanAction::Delete => "-",
+        })
+    }
+}
+
+/// The object affected by a [`PlannedChange`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlannedObject {
+    Table,
+    Chain(Option<String>),
+    Rule {
+        chain: Option<String>,
+        handle: Option<u64>,
+        /// The expressions of the rule, written with their `Display` implementation and separated
+        /// by semicolons, and empty when the rule has none (e.g. when it is deleted by its
+        /// handle).
+        expressions: String,
+    },
+    Set(Option<String>),
+    /// Elements added to or deleted from a set.
+    Elements {
+        set: Option<String>,
+        count: usize,
+    },
+    Obj(Option<String>),
+    Flowtable(Option<String>),
+    /// A message of a type unknown to this crate, counted as an addition.
+    Unknown(u8),
+}
+
+impl Display for PlannedObject {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let name = |name: &Option<String>| name.clone().unwrap_or_else(|| "?".to_string());
+        match self {
+            PlannedObject::Table => f.write_str("table"),
+            PlannedObject::Chain(chain) => write!(f, "chain {}", name(chain)),
+            PlannedObject::Rule {
+                chain,
+                handle,
+                expressions,
+            } => {
+                write!(f, "rule {}", name(chain))?;
+                if let Some(handle) = handle {
+                    write!(f, " handle {}", handle)?;
+                }
+                if !expressions.is_empty() {
+                    write!(f, ":{}", expressions)?;
+                }
+                Ok(())
+            }
+            PlannedObject::Set(set) => write!(f, "set {}", name(set)),
+            PlannedObject::Elements { set, count } => {
+                let plural = if *count == 1 { "" } else { "s" };
+                write!(f, "{} element{} of set {}", count, plural, name(set))
+            }
+            PlannedObject::Obj(obj) => write!(f, "object {}", name(obj)),
+            PlannedObject::Flowtable(flowtable) => write!(f, "flowtable {}", name(flowtable)),
+            PlannedObject::Unknown(msg_type) => write!(f, "unknown message {:#x}", msg_type),
+        }
+    }
+}
+
+/// A change held by a batch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedChange {
+    pub action: PlanAction,
+    pub family: ProtocolFamily,
+    pub table: Option<String>,
+    pub object: PlannedObject,
+}
+
+/// The changes held by a batch, see [`Batch::plan`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Plan {
+    /// The changes, in the order of the messages of the batch.
+    pub changes: Vec<PlannedChange>,
+}
+
+impl Plan {
+    /// Decodes the changes of `buf`, a batch as sent to the kernel (e.g. by
+    /// [`Batch::finalize`]).
+    pub fn from_messages(buf: &[u8]) -> Result<Plan, DecodeError> {
+        let mut changes = Vec::new();
+        for msg in parse_nlmsgs(buf) {
+            let (hdr, msg, raw) = msg?;
+            let NlMsg::NfGenMsg(genmsg, _) = msg else {
+                continue;
+            };
+            // the batch delimiters
+            if get_subsystem_from_nlmsghdr_type(hdr.nlmsg_type) != NFNL_SUBSYS_NFTABLES as u8 {
+                continue;
+            }
+            let op = get_operation_from_nlmsghdr_type(hdr.nlmsg_type);
+            let replace = hdr.nlmsg_flags & NLM_F_REPLACE as u16 != 0;
+            let family = ProtocolFamily::from(genmsg.nfgen_family as i32);
+            changes.push(planned_change(op, family, replace, raw)?);
+        }
+        Ok(Plan { changes })
+    }
+
+    /// The number of changes of each action, i.e. the objects to add, replace and delete.
+    pub fn counts(&self) -> (usize, usize, usize) {
+        let count = |action| self.changes.iter().filter(|c| c.action == action).count();
+        (
+            count(PlanAction::Add),
+            count(PlanAction::Replace),
+            count(PlanAction::Delete),
+        )
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+/// Decodes the change of the message `raw`, whose operation is `op`.
+/// Appends the expressions of `rule` to `expressions`, each one preceded by a space and separated
+/// by semicolons. Control characters (e.g. in log prefixes) are escaped, so that each change
+/// fits on a single line.
+fn push_expressions(rule: &Rule, expressions: &mut String) {
+    for (i, expr) in rule
+        .get_expressions()
+        .into_iter()
+        .flat_map(|e| e.iter())
+        .enumerate()
+    {
+        expressions.push_str(if i == 0 { " " } else { "; " });
+        for c in expr.to_string().chars() {
+            if c.is_control() {
+                expressions.extend(c.escape_default());
+            } else {
+                expressions.push(c);
+            }
+        }
+    }
+}
+
+fn planned_change(
+    op: u8,
+    family: ProtocolFamily,
+    replace: bool,
+    raw: &[u8],
+) -> Result<PlannedChange, DecodeError> {
+    fn describe<T: NfNetlinkObject>(
+        raw: &[u8],
+        f: impl FnOnce(&T) -> (Option<String>, PlannedObject),
+    ) -> Result<(Option<String>, PlannedObject), DecodeError> {
+        Ok(f(&T::deserialize(raw)?.0))
+    }
+
+    let (table, object) = match op as u32 {
+        NFT_MSG_NEWTABLE | NFT_MSG_DELTABLE => describe(raw, |t: &Table| {
+            (t.get_name().cloned(), PlannedObject::Table)
+        })?,
+        NFT_MSG_NEWCHAIN | NFT_MSG_DELCHAIN => describe(raw, |c: &Chain| {
+            let chain = PlannedObject::Chain(c.get_name().cloned());
+            (c.get_table().cloned(), chain)
+        })?,
+        NFT_MSG_NEWRULE | NFT_MSG_DELRULE => describe(raw, |r: &Rule| {
+            let mut expressions = String::new();
+            push_expressions(r, &mut expressions);
+            let rule = PlannedObject::Rule {
+                chain: r.get_chain().cloned(),
+                handle: r.get_handle().copied(),
+                expressions,
+            };
+            (r.get_table().cloned(), rule)
+        })?,
+        NFT_MSG_NEWSET | NFT_MSG_DELSET => describe(raw, |s: &Set| {
+            let set = PlannedObject::Set(s.get_name().cloned());
+            (s.get_table().cloned(), set)
+        })?,
+        NFT_MSG_NEWSETELEM | NFT_MSG_DELSETELEM => describe(raw, |l: &SetElementList| {
+            let elements = PlannedObject::Elements {
+                set: l.get_set().cloned(),
+                count: l.get_elements().map_or(0, |e| e.iter().count()),
+            };
+            (l.get_table().cloned(), elements)
+        })?,
+        NFT_MSG_NEWOBJ | NFT_MSG_DELOBJ => describe(raw, |o: &Obj| {
+            let obj = PlannedObject::Obj(o.get_name().cloned());
+            (o.get_table().cloned(), obj)
+        })?,
+        NFT_MSG_NEWFLOWTABLE | NFT_MSG_DELFLOWTABLE => describe(raw, |f: &Flowtable| {
+            let flowtable = PlannedObject::Flowtable(f.get_name().cloned());
+            (f.get_table().cloned(), flowtable)
+        })?,
+        _ => (None, PlannedObject::Unknown(op)),
+    };
+    let action = match op as u32 {
+        NFT_MSG_DELTABLE | NFT_MSG_DELCHAIN | NFT_MSG_DELRULE | NFT_MSG_DELSET
+        | NFT_MSG_DELSETELEM | NFT_MSG_DELOBJ | NFT_MSG_DELFLOWTABLE => PlanAction::Delete,
+        _ if replace => PlanAction::Replace,
+        _ => PlanAction::Add,
+    };
+    Ok(PlannedChange {
+        action,
+        family,
+        table,
+        object,
+    })
+}
+
+impl Display for Plan {
+    /// Writes the changes grouped by table, in the order in which the tables first appear in the
+    /// batch, followed by the number of changes of each action.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut tables: Vec<(ProtocolFamily, Option<&String>)> = Vec::new();
+        for change in &self.changes {
+            let table = (change.family, change.table.as_ref());
+            if !tables.contains(&table) {
+                tables.push(table);
+            }
+        }
+        for (family, table) in tables {
+            match table {
+                Some(table) => writeln!(f, "table {} {}", family, table)?,
+                None => writeln!(f, "table {} ?", family)?,
+            }
+            for change in self
+                .changes
+                .iter()
+                .filter(|c| c.family == family && c.table.as_ref() == table)
+            {
+                writeln!(f, "  {} {}", change.action, change.object)?;
+            }
+        }
+        let (add, replace, delete) = self.counts();
+        write!(
+            f,
+            "{} to add, {} to replace, {} to delete",
+            add, replace, delete
+        )
+    }
+}
+
+impl Batch {
+    /// Returns the changes held by the batch, to be reviewed before the batch is sent.
+    pub fn plan(&self) -> Result<Plan, DecodeError> {
+        Plan::from_messages(&ZeroOnDrop(self.finalized_copy()))
+    }
+}
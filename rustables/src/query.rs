@@ -1,34 +1,384 @@
-use std::os::unix::prelude::RawFd;
+use std::os::unix::prelude::{AsRawFd, RawFd};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
-use nix::sys::socket::{self, AddressFamily, MsgFlags, SockFlag, SockProtocol, SockType};
+use nix::errno::Errno;
+use nix::sys::socket::{
+    self, AddressFamily, MsgFlags, NetlinkAddr, SockAddr, SockFlag, SockProtocol, SockType,
+};
+#[cfg(feature = "tokio")]
+use tokio::io::unix::AsyncFd;
 
 use crate::{
-    error::QueryError,
+    error::{DecodeError, QueryError},
+    metrics,
     nlmsg::{
         nft_nlmsg_maxsize, pad_netlink_object_with_variable_size, NfNetlinkAttribute,
         NfNetlinkObject, NfNetlinkWriter,
     },
-    parser::{parse_nlmsg, NlMsg},
-    sys::{NLM_F_DUMP, NLM_F_MULTI},
+    parser::{get_nlmsghdr, parse_nlmsg, parse_nlmsgs, with_decode_mode, DecodeMode, NlMsg},
+    sys::{NETLINK_EXT_ACK, NLM_F_DUMP, NLM_F_MULTI},
+    zeroize::{zeroize, zeroize_if_enabled},
     ProtocolFamily,
 };
 
-pub(crate) fn recv_and_process<'a, T>(
-    sock: RawFd,
+/// A netlink socket bound to the netfilter subsystem.
+///
+/// The kernel assigns a port ID to the socket when it is bound, and every message the kernel
+/// sends in response to our requests is addressed to that port ID.
+#[derive(Debug)]
+pub struct NfNetlinkSocket {
+    fd: RawFd,
+    portid: u32,
+    groups: u32,
+}
+
+impl NfNetlinkSocket {
+    /// Opens a new netlink socket to netfilter, without subscribing to any multicast group.
+    pub fn new() -> Result<Self, QueryError> {
+        Self::with_groups(0)
+    }
+
+    /// Opens a new netlink socket to netfilter and subscribes to the multicast groups in the
+    /// `groups` bitmask. The bit `1 << (group - 1)` must be set to subscribe to the group
+    /// `group`, e.g. `1 << (libc::NFNLGRP_NFTABLES - 1)` to receive nftables events.
+    pub fn with_groups(groups: u32) -> Result<Self, QueryError> {
+        let fd = socket::socket(
+            AddressFamily::Netlink,
+            SockType::Raw,
+            SockFlag::empty(),
+            SockProtocol::NetlinkNetFilter,
+        )
+        .map_err(QueryError::NetlinkOpenError)?;
+        // from now on, the socket is closed on error when `sock` is dropped
+        let mut sock = NfNetlinkSocket {
+            fd,
+            portid: 0,
+            groups,
+        };
+
+        // while binding is not strictly necessary when we do not subscribe to any group, strace
+        // have trouble decoding the messages if we don't
+        let addr = SockAddr::Netlink(NetlinkAddr::new(0, groups));
+        socket::bind(fd, &addr).map_err(|_| QueryError::BindFailed)?;
+
+        // ask for extended ACKs, that describe the errors in more detail. Failures are ignored,
+        // as this is not supported by older kernels.
+        let enable: libc::c_int = 1;
+        unsafe {
+            libc::setsockopt(
+                fd,
+                libc::SOL_NETLINK,
+                NETLINK_EXT_ACK as libc::c_int,
+                &enable as *const libc::c_int as *const libc::c_void,
+                std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+            );
+        }
+
+        sock.portid = match socket::getsockname(fd) {
+            Ok(SockAddr::Netlink(addr)) => addr.pid(),
+            Ok(_) => return Err(QueryError::NotNetlinkSocket),
+            Err(_) => return Err(QueryError::RetrievingSocketInfoFailed),
+        };
+
+        Ok(sock)
+    }
+
+    /// Wraps a socket opened by the tests, e.g. one end of a socketpair standing for the kernel.
+    #[cfg(test)]
+    pub(crate) fn from_raw_fd(fd: RawFd, portid: u32, groups: u32) -> Self {
+        NfNetlinkSocket { fd, portid, groups }
+    }
+
+    /// Registers the socket with the tokio runtime of the caller, for the async queries (e.g.
+    /// [`list_objects_with_socket_async`]).
+    #[cfg(feature = "tokio")]
+    pub fn into_async(self) -> Result<AsyncFd<Self>, QueryError> {
+        AsyncFd::new(self).map_err(|e| QueryError::NetlinkOpenError(io_errno(e)))
+    }
+
+    /// The port ID assigned by the kernel to this socket.
+    pub fn portid(&self) -> u32 {
+        self.portid
+    }
+
+    /// The multicast groups this socket is subscribed to.
+    pub fn groups(&self) -> u32 {
+        self.groups
+    }
+
+    /// Sends the entire content of `buf` on the socket.
+    pub fn send(&self, buf: &[u8]) -> Result<(), QueryError> {
+        let mut sent = 0;
+        while sent != buf.len() {
+            sent += socket::send(self.fd, &buf[sent..], MsgFlags::empty())
+                .map_err(QueryError::NetlinkSendError)?;
+        }
+
+        let mut count = 0;
+        let mut remaining = buf;
+        while let Ok(hdr) = get_nlmsghdr(remaining) {
+            count += 1;
+            let len = pad_netlink_object_with_variable_size(hdr.nlmsg_len as usize);
+            remaining = remaining.get(len..).unwrap_or_default();
+        }
+        metrics::record_messages_sent(count);
+        Ok(())
+    }
+
+    /// Closes the socket, reporting any error that happened in the process (unlike dropping
+    /// the socket, which closes it silently).
+    pub fn close(self) -> Result<(), QueryError> {
+        let fd = self.fd;
+        std::mem::forget(self);
+        // we don't need to shutdown the socket (in fact, Linux doesn't support that operation;
+        // and return EOPNOTSUPP if we try)
+        nix::unistd::close(fd).map_err(QueryError::CloseFailed)
+    }
+}
+
+impl AsRawFd for NfNetlinkSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+
+impl Drop for NfNetlinkSocket {
+    fn drop(&mut self) {
+        let _ = nix::unistd::close(self.fd);
+    }
+}
+
+/// A buffer receiving the responses of the kernel.
+///
+/// The buffer must be able to hold two of the largest netlink messages, which weighs more than
+/// 128KB. Callers querying the kernel periodically (e.g. to read counters every second) can keep
+/// a buffer around and pass it to [`list_objects_with_socket`], instead of allocating a new one
+/// for every query.
+///
+/// The buffer also holds the [`DecodeMode`] of the objects received in it, which is lenient by
+/// default, and the [`CancellationToken`] of the queries, if any.
+///
+/// The responses stay in the buffer after the queries, until they are overwritten by the next
+/// ones. With the `zeroize` feature, the buffer is wiped after every query and when it is
+/// dropped, see [`QueryBuffer::wipe`].
+#[derive(Debug, Clone)]
+pub struct QueryBuffer {
+    buf: Vec<u8>,
+    decode_mode: DecodeMode,
+    cancellation: Option<CancellationToken>,
+    echo: bool,
+}
+
+impl QueryBuffer {
+    /// Creates a buffer large enough for the largest netlink message of nf_tables, decoding the
+    /// objects leniently and without cancellation.
+    pub fn new() -> Self {
+        QueryBuffer {
+            buf: vec![0; 2 * nft_nlmsg_maxsize() as usize],
+            decode_mode: DecodeMode::Lenient,
+            cancellation: None,
+            echo: false,
+        }
+    }
+
+    /// Receives the response to a batch sent with `NLM_F_ECHO`: the kernel echoes the objects
+    /// under the sequence numbers of their messages before acknowledging them, so the response
+    /// only ends with the acknowledgement of the last message.
+    pub(crate) fn with_echo(mut self) -> Self {
+        self.echo = true;
+        self
+    }
+
+    /// Aborts the queries receiving their response in this buffer once `token` is cancelled.
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    /// The token cancelling the queries of this buffer, if any.
+    pub fn cancellation(&self) -> Option<&CancellationToken> {
+        self.cancellation.as_ref()
+    }
+
+    /// Decodes the objects received in this buffer in the mode `mode`.
+    pub fn with_decode_mode(mut self, mode: DecodeMode) -> Self {
+        self.decode_mode = mode;
+        self
+    }
+
+    /// The mode decoding the objects received in this buffer.
+    pub fn decode_mode(&self) -> DecodeMode {
+        self.decode_mode
+    }
+
+    /// Overwrites the responses received in the buffer with zeros, e.g. once the objects listed
+    /// in it are processed, so that the ruleset does not linger in memory until the next query.
+    pub fn wipe(&mut self) {
+        zeroize(&mut self.buf);
+    }
+}
+
+impl Drop for QueryBuffer {
+    fn drop(&mut self) {
+        zeroize_if_enabled(&mut self.buf);
+    }
+}
+
+impl Default for QueryBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Cancels the queries of a [`QueryBuffer`], e.g. to abort a large dump from another thread or
+/// from a signal handler of an interactive tool.
+///
+/// The token is checked between the messages of the response. Once it is cancelled, the rest of
+/// the response is still received, but it is discarded without being decoded, and the query
+/// fails with [`QueryError::Cancelled`]. This leaves the socket ready for the next query.
+///
+/// Cancelling the token is permanent: the token can be shared by several queries, which are all
+/// aborted at once.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// Creates a token that is not cancelled yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels the queries of the buffers holding this token or one of its clones.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether the token was cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Processes a message received from the kernel, see [`recv_and_process`].
+type Callback<'c, T> = dyn Fn(&[u8], &mut T) -> Result<(), QueryError> + 'c;
+
+pub(crate) fn recv_and_process<T>(
+    sock: &NfNetlinkSocket,
+    buffer: &mut QueryBuffer,
     max_seq: Option<u32>,
-    cb: Option<&dyn Fn(&[u8], &mut T) -> Result<(), QueryError>>,
-    working_data: &'a mut T,
+    cb: Option<&Callback<'_, T>>,
+    working_data: &mut T,
 ) -> Result<(), QueryError> {
-    let mut msg_buffer = vec![0; 2 * nft_nlmsg_maxsize() as usize];
+    let res = recv_and_process_messages(sock, buffer, max_seq, cb, working_data);
+    record_failure(&res);
+    if cfg!(feature = "zeroize") {
+        buffer.wipe();
+    }
+    res
+}
+
+fn record_failure<T>(res: &Result<T, QueryError>) {
+    match res {
+        Err(QueryError::ProcessNetlinkError(_)) => metrics::record_decode_error(),
+        Err(QueryError::NetlinkError(e)) if e.err.error == libc::EBUSY => {
+            metrics::record_busy_error()
+        }
+        _ => {}
+    }
+}
+
+/// Processes the messages already queued on `sock`, without waiting for further messages. Returns
+/// whether the end of the response was reached.
+///
+/// Netlink sockets deliver whole datagrams, which only hold complete messages, so the messages
+/// never span two calls.
+pub(crate) fn recv_available(
+    sock: &NfNetlinkSocket,
+    buffer: &mut QueryBuffer,
+    max_seq: Option<u32>,
+    cb: &mut dyn FnMut(&[u8]) -> Result<(), QueryError>,
+) -> Result<bool, QueryError> {
+    let res = recv_available_messages(sock, buffer, max_seq, cb);
+    record_failure(&res);
+    if cfg!(feature = "zeroize") {
+        buffer.wipe();
+    }
+    res
+}
+
+fn recv_available_messages(
+    sock: &NfNetlinkSocket,
+    buffer: &mut QueryBuffer,
+    max_seq: Option<u32>,
+    cb: &mut dyn FnMut(&[u8]) -> Result<(), QueryError>,
+) -> Result<bool, QueryError> {
+    let decode_mode = buffer.decode_mode;
+    loop {
+        let nb_recv = match socket::recv(sock.fd, &mut buffer.buf, MsgFlags::MSG_DONTWAIT) {
+            Err(Errno::EAGAIN) => return Ok(false),
+            Err(Errno::EINTR) => continue,
+            Err(e) => return Err(QueryError::NetlinkRecvError(e)),
+            Ok(0) => return Ok(true),
+            Ok(n) => n,
+        };
+        #[cfg(feature = "capture")]
+        crate::capture::record(&buffer.buf[..nb_recv]);
+
+        for res in parse_nlmsgs(&buffer.buf[..nb_recv]) {
+            let (nlmsghdr, msg, raw) = res?;
+            if nlmsghdr.nlmsg_pid != sock.portid() && sock.groups() == 0 {
+                return Err(DecodeError::InvalidPortId(nlmsghdr.nlmsg_pid).into());
+            }
+            match msg {
+                NlMsg::Done => return Ok(true),
+                NlMsg::Error(e) if e.err.error != 0 => return Err(QueryError::NetlinkError(e)),
+                NlMsg::Error(_) | NlMsg::Noop => {}
+                NlMsg::NfGenMsg(..) => with_decode_mode(decode_mode, || cb(raw))?,
+            }
+            match max_seq {
+                Some(max_seq) if nlmsghdr.nlmsg_seq >= max_seq => return Ok(true),
+                Some(_) => {}
+                None if nlmsghdr.nlmsg_flags & NLM_F_MULTI as u16 == 0 => {
+                    return Err(QueryError::UndecidableMessageTermination)
+                }
+                None => {}
+            }
+        }
+    }
+}
+
+fn recv_and_process_messages<T>(
+    sock: &NfNetlinkSocket,
+    buffer: &mut QueryBuffer,
+    max_seq: Option<u32>,
+    cb: Option<&Callback<'_, T>>,
+    working_data: &mut T,
+) -> Result<(), QueryError> {
+    let decode_mode = buffer.decode_mode;
+    let cancellation = buffer.cancellation.clone();
+    let echo = buffer.echo;
+    let msg_buffer = &mut buffer.buf;
     let mut buf_start = 0;
     let mut end_pos = 0;
+    let mut cancelled = false;
+    let done = |cancelled: bool| match cancelled {
+        true => Err(QueryError::Cancelled),
+        false => Ok(()),
+    };
 
     loop {
-        let nb_recv = socket::recv(sock, &mut msg_buffer[end_pos..], MsgFlags::empty())
-            .map_err(QueryError::NetlinkRecvError)?;
+        let nb_recv = socket::recv(
+            sock.as_raw_fd(),
+            &mut msg_buffer[end_pos..],
+            MsgFlags::empty(),
+        )
+        .map_err(QueryError::NetlinkRecvError)?;
         if nb_recv <= 0 {
-            return Ok(());
+            return done(cancelled);
         }
+        #[cfg(feature = "capture")]
+        crate::capture::record(&msg_buffer[end_pos..end_pos + nb_recv]);
         end_pos += nb_recv;
         loop {
             let buf = &msg_buffer.as_slice()[buf_start..end_pos];
@@ -40,20 +390,33 @@ pub(crate) fn recv_and_process<'a, T>(
             debug!("Calling parse_nlmsg");
             let (nlmsghdr, msg) = parse_nlmsg(&buf)?;
             debug!("Got a valid netlink message: {:?} {:?}", nlmsghdr, msg);
+            let echoed = echo && matches!(msg, NlMsg::NfGenMsg(..));
+
+            // unless we subscribed to multicast groups (whose notifications carry the port ID of
+            // the socket that caused them), every message must be addressed to our socket
+            if nlmsghdr.nlmsg_pid != sock.portid() && sock.groups() == 0 {
+                return Err(DecodeError::InvalidPortId(nlmsghdr.nlmsg_pid).into());
+            }
 
             match msg {
                 NlMsg::Done => {
-                    return Ok(());
+                    return done(cancelled);
                 }
                 NlMsg::Error(e) => {
-                    if e.error != 0 {
+                    if e.err.error != 0 {
                         return Err(QueryError::NetlinkError(e));
                     }
                 }
                 NlMsg::Noop => {}
                 NlMsg::NfGenMsg(_genmsg, _data) => {
-                    if let Some(cb) = cb {
-                        cb(&buf[0..nlmsghdr.nlmsg_len as usize], working_data)?;
+                    // the remaining messages are still drained once the query is cancelled, so
+                    // that the next query on the socket doesn't receive them
+                    cancelled =
+                        cancelled || cancellation.as_ref().is_some_and(|t| t.is_cancelled());
+                    if let (Some(cb), false) = (cb, cancelled) {
+                        with_decode_mode(decode_mode, || {
+                            cb(&buf[0..nlmsghdr.nlmsg_len as usize], working_data)
+                        })?;
                     }
                 }
             }
@@ -66,8 +429,8 @@ pub(crate) fn recv_and_process<'a, T>(
 
             // retrieve the next message
             if let Some(max_seq) = max_seq {
-                if nlmsghdr.nlmsg_seq >= max_seq {
-                    return Ok(());
+                if nlmsghdr.nlmsg_seq >= max_seq && !echoed {
+                    return done(cancelled);
                 }
             }
 
@@ -88,17 +451,15 @@ pub(crate) fn recv_and_process<'a, T>(
 }
 
 pub(crate) fn socket_close_wrapper<E>(
-    sock: RawFd,
-    cb: impl FnOnce(RawFd) -> Result<(), E>,
+    sock: NfNetlinkSocket,
+    cb: impl FnOnce(&NfNetlinkSocket) -> Result<(), E>,
 ) -> Result<(), QueryError>
 where
     QueryError: From<E>,
 {
-    let ret = cb(sock);
+    let ret = cb(&sock);
 
-    // we don't need to shutdown the socket (in fact, Linux doesn't support that operation;
-    // and return EOPNOTSUPP if we try)
-    nix::unistd::close(sock).map_err(QueryError::CloseFailed)?;
+    sock.close()?;
 
     Ok(ret?)
 }
@@ -110,16 +471,42 @@ pub fn get_list_of_objects<T: NfNetlinkAttribute>(
     msg_type: u16,
     seq: u32,
     filter: Option<&T>,
+) -> Result<Vec<u8>, QueryError> {
+    get_list_of_objects_for_family(msg_type, ProtocolFamily::Unspec, seq, filter)
+}
+
+/// Same as [`get_list_of_objects`], but only requests the objects of the protocol family
+/// `family`. Some object types (like set elements) can only be listed when the family of their
+/// table is known.
+pub(crate) fn get_list_of_objects_for_family<T: NfNetlinkAttribute>(
+    msg_type: u16,
+    family: ProtocolFamily,
+    seq: u32,
+    filter: Option<&T>,
+) -> Result<Vec<u8>, QueryError> {
+    build_request(msg_type, family, NLM_F_DUMP as u16, seq, filter)
+}
+
+/// Returns a buffer containing a netlink message which requests the single object identified by
+/// `filter` (e.g. a rule identified by its table, chain and handle).
+pub(crate) fn get_object_request<T: NfNetlinkObject>(
+    msg_type: u16,
+    seq: u32,
+    filter: &T,
+) -> Result<Vec<u8>, QueryError> {
+    build_request(msg_type, filter.get_family(), 0, seq, Some(filter))
+}
+
+fn build_request<T: NfNetlinkAttribute>(
+    msg_type: u16,
+    family: ProtocolFamily,
+    flags: u16,
+    seq: u32,
+    filter: Option<&T>,
 ) -> Result<Vec<u8>, QueryError> {
     let mut buffer = Vec::new();
     let mut writer = NfNetlinkWriter::new(&mut buffer);
-    writer.write_header(
-        msg_type,
-        ProtocolFamily::Unspec,
-        NLM_F_DUMP as u16,
-        seq,
-        None,
-    );
+    writer.write_header(msg_type, family, flags, seq, None);
     if let Some(filter) = filter {
         let buf = writer.add_data_zeroed(filter.get_size());
         filter.write_payload(buf);
@@ -128,6 +515,35 @@ pub fn get_list_of_objects<T: NfNetlinkAttribute>(
     Ok(buffer)
 }
 
+/// Fetches the single object identified by `filter` (e.g. a rule identified by its table, chain
+/// and handle) on `sock`, which is cheaper than listing all the objects of that type.
+pub fn get_object<Object>(
+    sock: &NfNetlinkSocket,
+    buffer: &mut QueryBuffer,
+    msg_type: u16,
+    filter: &Object,
+) -> Result<Object, QueryError>
+where
+    Object: NfNetlinkObject + NfNetlinkAttribute,
+{
+    let seq = 0;
+    sock.send(&get_object_request(msg_type, seq, filter)?)?;
+
+    // the kernel replies with a single message, carrying the same sequence number
+    let mut result = None;
+    recv_and_process(
+        sock,
+        buffer,
+        Some(seq),
+        Some(&|buf: &[u8], result: &mut Option<Object>| {
+            *result = Some(Object::deserialize(buf)?.0);
+            Ok(())
+        }),
+        &mut result,
+    )?;
+    result.ok_or(QueryError::MissingObject)
+}
+
 /// Lists objects of a certain type (e.g. libc::NFT_MSG_GETTABLE) with the help of a helper
 /// function called by mnl::cb_run2.
 /// The callback expects a tuple of additional data (supplied as an argument to this function)
@@ -138,33 +554,204 @@ pub fn list_objects_with_data<'a, Object, Accumulator>(
     filter: Option<&Object>,
     working_data: &'a mut Accumulator,
 ) -> Result<(), QueryError>
+where
+    Object: NfNetlinkObject + NfNetlinkAttribute,
+{
+    let sock = NfNetlinkSocket::new()?;
+    let mut buffer = QueryBuffer::new();
+    socket_close_wrapper(sock, move |sock| {
+        list_objects_with_socket(sock, &mut buffer, data_type, cb, filter, working_data)
+    })
+}
+
+/// Same as [`list_objects_with_data`], but sends the request on `sock` and receives the response
+/// in `buffer`, which can both be reused across queries.
+pub fn list_objects_with_socket<'a, Object, Accumulator>(
+    sock: &NfNetlinkSocket,
+    buffer: &mut QueryBuffer,
+    data_type: u16,
+    cb: &dyn Fn(Object, &mut Accumulator) -> Result<(), QueryError>,
+    filter: Option<&Object>,
+    working_data: &'a mut Accumulator,
+) -> Result<(), QueryError>
 where
     Object: NfNetlinkObject + NfNetlinkAttribute,
 {
     debug!("Listing objects of kind {}", data_type);
-    let sock = socket::socket(
-        AddressFamily::Netlink,
-        SockType::Raw,
-        SockFlag::empty(),
-        SockProtocol::NetlinkNetFilter,
+    let seq = 0;
+
+    let family = filter
+        .map(|f| f.get_family())
+        .unwrap_or(ProtocolFamily::Unspec);
+    let chains_buf = get_list_of_objects_for_family(data_type, family, seq, filter)?;
+    sock.send(&chains_buf)?;
+
+    // the kernel should return NLM_F_MULTI objects
+    recv_and_process(
+        sock,
+        buffer,
+        None,
+        Some(&|buf: &[u8], working_data: &mut Accumulator| {
+            debug!("Calling Object::deserialize()");
+            cb(Object::deserialize(buf)?.0, working_data)
+        }),
+        working_data,
     )
-    .map_err(QueryError::NetlinkOpenError)?;
+}
 
-    let seq = 0;
+/// A listing of objects whose responses are received without blocking, to run queries from an
+/// event loop (e.g. an async runtime) instead of dedicating a thread to them.
+///
+/// [`PendingDump::start`] sends the request, then [`PendingDump::try_complete`] must be called
+/// every time the socket becomes readable, until it returns the objects. The socket must not be
+/// used for other queries in the meantime. The `tokio` feature provides
+/// `list_objects_with_data_async`, which does this on the tokio runtime. Other event loops can
+/// await the readiness of the socket the same way as tokio's `AsyncFd`:
+///
+/// ```ignore
+/// let sock = NfNetlinkSocket::new()?;
+/// let fd = AsyncFd::new(sock.as_raw_fd())?;
+/// let request = libc::NFT_MSG_GETTABLE as u16;
+/// let mut dump = PendingDump::<Table>::start(&sock, QueryBuffer::new(), request, None)?;
+/// let tables = loop {
+///     let mut guard = fd.readable().await?;
+///     if let Some(tables) = dump.try_complete(&sock)? {
+///         break tables;
+///     }
+///     guard.clear_ready();
+/// };
+/// ```
+///
+/// The [`CancellationToken`] of the buffer is not used: dropping the query cancels it, but the
+/// rest of the response must then be drained before reusing the socket.
+pub struct PendingDump<Object> {
+    buffer: QueryBuffer,
+    objects: Vec<Object>,
+}
+
+impl<Object> PendingDump<Object>
+where
+    Object: NfNetlinkObject + NfNetlinkAttribute,
+{
+    /// Sends on `sock` the request listing the objects of type `data_type` (e.g.
+    /// `NFT_MSG_GETTABLE`) that match `filter`. The responses will be received in `buffer`.
+    pub fn start(
+        sock: &NfNetlinkSocket,
+        buffer: QueryBuffer,
+        data_type: u16,
+        filter: Option<&Object>,
+    ) -> Result<Self, QueryError> {
+        let family = filter
+            .map(|f| f.get_family())
+            .unwrap_or(ProtocolFamily::Unspec);
+        sock.send(&get_list_of_objects_for_family(
+            data_type, family, 0, filter,
+        )?)?;
+        Ok(PendingDump {
+            buffer,
+            objects: Vec::new(),
+        })
+    }
 
-    let chains_buf = get_list_of_objects(data_type, seq, filter)?;
-    socket::send(sock, &chains_buf, MsgFlags::empty()).map_err(QueryError::NetlinkSendError)?;
+    /// Decodes the responses already received on `sock`. Returns the objects once the whole
+    /// response was received, or `None` if more responses must be awaited.
+    pub fn try_complete(
+        &mut self,
+        sock: &NfNetlinkSocket,
+    ) -> Result<Option<Vec<Object>>, QueryError> {
+        let objects = &mut self.objects;
+        let done = recv_available(sock, &mut self.buffer, None, &mut |buf| {
+            objects.push(Object::deserialize(buf)?.0);
+            Ok(())
+        })?;
+        Ok(done.then(|| std::mem::take(objects)))
+    }
+}
 
-    socket_close_wrapper(sock, move |sock| {
-        // the kernel should return NLM_F_MULTI objects
-        recv_and_process(
-            sock,
-            None,
-            Some(&|buf: &[u8], working_data: &mut Accumulator| {
-                debug!("Calling Object::deserialize()");
-                cb(Object::deserialize(buf)?.0, working_data)
-            }),
-            working_data,
-        )
+/// Same as [`recv_available`], but awaits the messages until the end of the response is reached.
+#[cfg(feature = "tokio")]
+pub(crate) async fn recv_async(
+    sock: &AsyncFd<NfNetlinkSocket>,
+    buffer: &mut QueryBuffer,
+    max_seq: Option<u32>,
+    mut cb: impl FnMut(&[u8]) -> Result<(), QueryError>,
+) -> Result<(), QueryError> {
+    loop {
+        let mut guard = sock
+            .readable()
+            .await
+            .map_err(|e| QueryError::NetlinkRecvError(io_errno(e)))?;
+        if recv_available(sock.get_ref(), buffer, max_seq, &mut cb)? {
+            return Ok(());
+        }
+        guard.clear_ready();
+    }
+}
+
+#[cfg(feature = "tokio")]
+fn io_errno(error: std::io::Error) -> Errno {
+    Errno::from_i32(error.raw_os_error().unwrap_or(libc::EIO))
+}
+
+/// Same as [`list_objects_with_data`], but awaits the responses of the kernel instead of blocking
+/// the thread, so that async daemons don't need to spawn a blocking task for every listing.
+///
+/// ```ignore
+/// let mut tables = Vec::new();
+/// list_objects_with_data_async(
+///     libc::NFT_MSG_GETTABLE as u16,
+///     &|table: Table, tables: &mut Vec<Table>| {
+///         tables.push(table);
+///         Ok(())
+///     },
+///     None,
+///     &mut tables,
+/// )
+/// .await?;
+/// ```
+#[cfg(feature = "tokio")]
+pub async fn list_objects_with_data_async<Object, Accumulator>(
+    data_type: u16,
+    cb: &(dyn Fn(Object, &mut Accumulator) -> Result<(), QueryError> + Sync),
+    filter: Option<&Object>,
+    working_data: &mut Accumulator,
+) -> Result<(), QueryError>
+where
+    Object: NfNetlinkObject + NfNetlinkAttribute,
+{
+    let sock = NfNetlinkSocket::new()?.into_async()?;
+    let mut buffer = QueryBuffer::new();
+    let res =
+        list_objects_with_socket_async(&sock, &mut buffer, data_type, cb, filter, working_data)
+            .await;
+    sock.into_inner().close()?;
+    res
+}
+
+/// Same as [`list_objects_with_data_async`], but sends the request on `sock` and receives the
+/// response in `buffer`, which can both be reused across queries.
+#[cfg(feature = "tokio")]
+pub async fn list_objects_with_socket_async<Object, Accumulator>(
+    sock: &AsyncFd<NfNetlinkSocket>,
+    buffer: &mut QueryBuffer,
+    data_type: u16,
+    cb: &(dyn Fn(Object, &mut Accumulator) -> Result<(), QueryError> + Sync),
+    filter: Option<&Object>,
+    working_data: &mut Accumulator,
+) -> Result<(), QueryError>
+where
+    Object: NfNetlinkObject + NfNetlinkAttribute,
+{
+    debug!("Listing objects of kind {}", data_type);
+    let family = filter
+        .map(|f| f.get_family())
+        .unwrap_or(ProtocolFamily::Unspec);
+    sock.get_ref().send(&get_list_of_objects_for_family(
+        data_type, family, 0, filter,
+    )?)?;
+
+    recv_async(sock, buffer, None, |buf| {
+        cb(Object::deserialize(buf)?.0, working_data)
     })
+    .await
 }
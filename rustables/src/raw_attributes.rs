@@ -0,0 +1,178 @@
+use std::fmt::{self, Display, Formatter};
+
+use crate::error::DecodeError;
+use crate::nlmsg::{
+    pad_netlink_object, pad_netlink_object_with_variable_size, NetlinkType, NfNetlinkAttribute,
+    NfNetlinkDeserializable,
+};
+use crate::parser::{parse_nlmsg, NlMsg};
+use crate::sys::{nlattr, NLA_F_NESTED, NLA_TYPE_MASK};
+
+/// The attributes of a netlink payload, decoded without knowing what they mean, e.g. to inspect
+/// the attributes of a kernel more recent than this crate, that its types drop:
+///
+/// ```ignore
+/// for message in read_capture("dump.pcap")? {
+///     println!("{}", RawAttributeTree::from_message(&message.payload)?);
+/// }
+/// ```
+///
+/// The attributes flagged with `NLA_F_NESTED` are decoded recursively, the others are kept as
+/// bytes (see [`RawAttribute::nested`] for the nested attributes the kernel does not flag). The
+/// tree is displayed with one attribute per line, indented by nesting level.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RawAttributeTree {
+    pub attributes: Vec<RawAttribute>,
+}
+
+/// An attribute of a [`RawAttributeTree`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawAttribute {
+    /// The type of the attribute, without its flags.
+    pub attr_type: NetlinkType,
+    pub value: RawAttributeValue,
+}
+
+/// The payload of a [`RawAttribute`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RawAttributeValue {
+    Bytes(Vec<u8>),
+    Nested(RawAttributeTree),
+}
+
+impl RawAttributeTree {
+    /// Decodes the attributes of `buf`, which must hold whole attributes and their padding.
+    pub fn parse(buf: &[u8]) -> Result<Self, DecodeError> {
+        let header_len = pad_netlink_object::<nlattr>();
+        let mut attributes = Vec::new();
+        let mut pos = 0;
+        while pos < buf.len() {
+            if buf.len() - pos < header_len {
+                return Err(DecodeError::InvalidDataSize);
+            }
+            let header = unsafe { std::ptr::read_unaligned(buf[pos..].as_ptr() as *const nlattr) };
+            let len = header.nla_len as usize;
+            if len < header_len || len > buf.len() - pos {
+                return Err(DecodeError::InvalidDataSize);
+            }
+            let payload = &buf[pos + header_len..pos + len];
+            let value = if header.nla_type & NLA_F_NESTED as u16 != 0 {
+                RawAttributeValue::Nested(RawAttributeTree::parse(payload)?)
+            } else {
+                RawAttributeValue::Bytes(payload.to_vec())
+            };
+            attributes.push(RawAttribute {
+                attr_type: header.nla_type & NLA_TYPE_MASK as u16,
+                value,
+            });
+            pos = (pos + pad_netlink_object_with_variable_size(len)).min(buf.len());
+        }
+        Ok(RawAttributeTree { attributes })
+    }
+
+    /// Decodes the attributes of the nf_tables message at the beginning of `buf`, e.g. a message
+    /// received from the kernel or written by [`NfNetlinkObject::to_batch_message`].
+    ///
+    /// [`NfNetlinkObject::to_batch_message`]: crate::NfNetlinkObject::to_batch_message
+    pub fn from_message(buf: &[u8]) -> Result<Self, DecodeError> {
+        match parse_nlmsg(buf)? {
+            (_, NlMsg::NfGenMsg(_, payload)) => RawAttributeTree::parse(payload),
+            (hdr, _) => Err(DecodeError::UnexpectedType(hdr.nlmsg_type)),
+        }
+    }
+
+    /// The first attribute of type `attr_type`.
+    pub fn get(&self, attr_type: NetlinkType) -> Option<&RawAttribute> {
+        self.attributes
+            .iter()
+            .find(|attr| attr.attr_type == attr_type)
+    }
+
+    fn fmt_indented(&self, f: &mut Formatter<'_>, depth: usize) -> fmt::Result {
+        for attr in &self.attributes {
+            write!(f, "{:1$}{2}", "", depth * 2, attr.attr_type)?;
+            match &attr.value {
+                RawAttributeValue::Bytes(bytes) => {
+                    f.write_str(": 0x")?;
+                    for b in bytes {
+                        write!(f, "{:02x}", b)?;
+                    }
+                    writeln!(f)?;
+                }
+                RawAttributeValue::Nested(tree) => {
+                    writeln!(f, ":")?;
+                    tree.fmt_indented(f, depth + 1)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl RawAttribute {
+    /// The payload of the attribute, decoded as nested attributes. This is needed for the nested
+    /// attributes written without the `NLA_F_NESTED` flag, which older kernels omit.
+    pub fn nested(&self) -> Result<RawAttributeTree, DecodeError> {
+        match &self.value {
+            RawAttributeValue::Bytes(bytes) => RawAttributeTree::parse(bytes),
+            RawAttributeValue::Nested(tree) => Ok(tree.clone()),
+        }
+    }
+}
+
+impl Display for RawAttributeTree {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.fmt_indented(f, 0)
+    }
+}
+
+impl NfNetlinkAttribute for RawAttributeValue {
+    fn is_nested(&self) -> bool {
+        matches!(self, RawAttributeValue::Nested(_))
+    }
+
+    fn get_size(&self) -> usize {
+        match self {
+            RawAttributeValue::Bytes(bytes) => bytes.len(),
+            RawAttributeValue::Nested(tree) => tree.get_size(),
+        }
+    }
+
+    fn write_payload(&self, addr: &mut [u8]) {
+        match self {
+            RawAttributeValue::Bytes(bytes) => bytes.write_payload(addr),
+            RawAttributeValue::Nested(tree) => tree.write_payload(addr),
+        }
+    }
+}
+
+impl NfNetlinkAttribute for RawAttributeTree {
+    fn is_nested(&self) -> bool {
+        true
+    }
+
+    fn get_size(&self) -> usize {
+        self.attributes
+            .iter()
+            .map(|attr| {
+                pad_netlink_object::<nlattr>()
+                    + pad_netlink_object_with_variable_size(attr.value.get_size())
+            })
+            .sum()
+    }
+
+    fn write_payload(&self, mut addr: &mut [u8]) {
+        for attr in &self.attributes {
+            crate::parser::write_attribute(attr.attr_type, &attr.value, addr);
+            let len = pad_netlink_object::<nlattr>()
+                + pad_netlink_object_with_variable_size(attr.value.get_size());
+            addr = &mut addr[len..];
+        }
+    }
+}
+
+impl NfNetlinkDeserializable for RawAttributeTree {
+    fn deserialize(buf: &[u8]) -> Result<(Self, &[u8]), DecodeError> {
+        Ok((RawAttributeTree::parse(buf)?, &[]))
+    }
+}
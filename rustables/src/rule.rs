@@ -1,22 +1,33 @@
-use std::fmt::Debug;
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::fmt::{self, Debug, Display, Formatter};
 
-use rustables_macros::nfnetlink_struct;
+use rustables_macros::{nfnetlink_object, nfnetlink_struct};
 
 use crate::chain::Chain;
-use crate::error::{BuilderError, QueryError};
-use crate::expr::{ExpressionList, RawExpression};
-use crate::nlmsg::NfNetlinkObject;
-use crate::query::list_objects_with_data;
+use crate::compat::RuleCompat;
+use crate::error::BuilderError;
+#[cfg(feature = "socket")]
+use crate::error::QueryError;
+use crate::expr::{Counter, ExpressionList, ExpressionVariant, RawExpression, Register};
+use crate::nlmsg::{NfNetlinkAttribute, NfNetlinkObject};
+#[cfg(feature = "socket")]
+use crate::query::{get_object, list_objects_with_data, NfNetlinkSocket, QueryBuffer};
+use crate::raw_attributes::{RawAttributeTree, RawAttributeValue};
+#[cfg(feature = "socket")]
+use crate::sys::NFT_MSG_GETRULE_RESET;
 use crate::sys::{
-    NFTA_RULE_CHAIN, NFTA_RULE_EXPRESSIONS, NFTA_RULE_HANDLE, NFTA_RULE_ID, NFTA_RULE_POSITION,
-    NFTA_RULE_TABLE, NFTA_RULE_USERDATA, NFT_MSG_DELRULE, NFT_MSG_NEWRULE, NLM_F_APPEND,
-    NLM_F_CREATE,
+    NFTA_RULE_CHAIN, NFTA_RULE_COMPAT, NFTA_RULE_EXPRESSIONS, NFTA_RULE_HANDLE, NFTA_RULE_ID,
+    NFTA_RULE_POSITION, NFTA_RULE_POSITION_ID, NFTA_RULE_TABLE, NFTA_RULE_USERDATA,
+    NFT_MSG_DELRULE, NFT_MSG_NEWRULE, NFT_PAYLOAD_NETWORK_HEADER, NLM_F_APPEND, NLM_F_CREATE,
+    NLM_F_REPLACE,
 };
-use crate::{Batch, ProtocolFamily};
+use crate::{Batch, MsgType, ProtocolFamily};
 
 /// A nftables firewall rule.
 #[derive(Clone, PartialEq, Eq, Default, Debug)]
 #[nfnetlink_struct(derive_deserialize = false)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Rule {
     family: ProtocolFamily,
     #[field(NFTA_RULE_TABLE)]
@@ -29,10 +40,19 @@ pub struct Rule {
     expressions: ExpressionList,
     #[field(NFTA_RULE_POSITION)]
     position: u64,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, with = "crate::serde_helpers::option_hex")
+    )]
     #[field(NFTA_RULE_USERDATA)]
     userdata: Vec<u8>,
     #[field(NFTA_RULE_ID)]
     id: u32,
+    #[field(NFTA_RULE_POSITION_ID)]
+    position_id: u32,
+    /// The protocol information iptables-nft attaches to its rules, see [`crate::compat`].
+    #[field(NFTA_RULE_COMPAT)]
+    compat: RuleCompat,
 }
 
 impl Rule {
@@ -70,6 +90,151 @@ impl Rule {
         self
     }
 
+    /// Sums the counters of the rule, returning a `(bytes, packets)` tuple, or `None` if the rule
+    /// has no counter. The values are only filled in the rules listed from the kernel.
+    pub fn counters(&self) -> Option<(u64, u64)> {
+        let mut res: Option<Counter> = None;
+        for expr in self.get_expressions()?.iter() {
+            if let Some(ExpressionVariant::Counter(counter)) = expr.get_data() {
+                res = Some(match res {
+                    Some(total) => total.merge(counter),
+                    None => counter.clone(),
+                });
+            }
+        }
+        res.map(|total| (total.bytes(), total.packets()))
+    }
+
+    /// The chains the rule jumps (or goes) to with its verdicts. The verdicts designating their
+    /// chain by its id in a batch (see [`Batch::jump_to_added`](crate::Batch::jump_to_added)) are
+    /// skipped, as the kernel always reports the chains by their name.
+    pub fn jump_targets(&self) -> impl Iterator<Item = &str> {
+        self.get_expressions()
+            .into_iter()
+            .flat_map(|exprs| exprs.iter())
+            .filter_map(|expr| match expr.get_data() {
+                Some(ExpressionVariant::Immediate(immediate)) => immediate
+                    .get_data()?
+                    .get_verdict()?
+                    .get_chain()
+                    .map(|chain| chain.as_str()),
+                _ => None,
+            })
+    }
+
+    /// Checks the sequence of the expressions of the rule against the constraints of the kernel,
+    /// which otherwise refuses the rule with a bare `EINVAL`:
+    /// - an expression can only read a register loaded by a previous expression (e.g. a [`Cmp`]
+    ///   must follow the [`Payload`] or [`Meta`] expression loading the compared value, and a
+    ///   [`Nat`] the expressions loading its address and port);
+    /// - nothing can follow the verdict of the rule.
+    ///
+    /// The error holds the index of the offending expression. The register checks stop at the
+    /// first expression unknown to this crate, as its registers cannot be known.
+    ///
+    /// [`Cmp`]: crate::expr::Cmp
+    /// [`Payload`]: crate::expr::Payload
+    /// [`Meta`]: crate::expr::Meta
+    /// [`Nat`]: crate::expr::Nat
+    pub fn validate(&self) -> Result<(), BuilderError> {
+        let mut loaded = HashSet::new();
+        // the registers loaded by unknown expressions cannot be tracked
+        let mut registers_known = true;
+        let mut has_verdict = false;
+        let exprs = self
+            .get_expressions()
+            .into_iter()
+            .flat_map(|exprs| exprs.iter());
+        for (index, expr) in exprs.enumerate() {
+            if has_verdict {
+                return Err(BuilderError::InvalidRuleExpression(
+                    index,
+                    "the expression follows the verdict of the rule",
+                ));
+            }
+            let Some((reads, loads)) = expr.get_data().and_then(|data| data.registers()) else {
+                registers_known = false;
+                continue;
+            };
+            if registers_known && reads.iter().any(|reg| !loaded.contains(reg)) {
+                return Err(BuilderError::InvalidRuleExpression(
+                    index,
+                    "the expression reads a register that no previous expression loaded",
+                ));
+            }
+            for reg in loads {
+                if reg == Register::Verdict {
+                    has_verdict = true;
+                } else {
+                    loaded.insert(reg);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Fetches this rule again from the kernel on `sock`, to update its counters and
+    /// expressions. The rule is identified by its table, chain and handle, so this is much
+    /// cheaper than listing the whole chain when only a few rules are monitored.
+    ///
+    /// The response is received in `buffer`, which can be reused across the refreshes of the
+    /// monitored rules.
+    #[cfg(feature = "socket")]
+    pub fn refresh(
+        &mut self,
+        sock: &NfNetlinkSocket,
+        buffer: &mut QueryBuffer,
+    ) -> Result<(), QueryError> {
+        let filter = self.refresh_filter()?;
+        *self = get_object(sock, buffer, libc::NFT_MSG_GETRULE as u16, &filter)?;
+        Ok(())
+    }
+
+    /// The rule identifying this rule in the requests of [`Rule::refresh`].
+    #[cfg(feature = "socket")]
+    pub(crate) fn refresh_filter(&self) -> Result<Rule, BuilderError> {
+        Ok(Rule::default()
+            .with_family(self.family)
+            .with_table(
+                self.get_table()
+                    .ok_or(BuilderError::MissingChainInformationError)?,
+            )
+            .with_chain(
+                self.get_chain()
+                    .ok_or(BuilderError::MissingChainInformationError)?,
+            )
+            .with_handle(*self.get_handle().ok_or(BuilderError::MissingRuleHandle)?))
+    }
+
+    /// Copies this rule into `chain`, e.g. to mirror a policy from an `ip` table to an `ip6`
+    /// table, or from a staging chain to a production chain.
+    ///
+    /// The copy keeps the expressions and userdata of the rule, but belongs to the family,
+    /// table and chain of `chain`, and has neither a handle nor a position, so that it is appended
+    /// to `chain` when added to a batch. The sets referenced by the expressions must exist in
+    /// the table of `chain`, and the counters keep the values of this rule.
+    ///
+    /// Fails if the family changes while the rule matches the network header (e.g. `ip saddr`),
+    /// whose layout depends on the family: the copy would read other fields of the packets.
+    pub fn clone_into(&self, chain: &Chain) -> Result<Rule, BuilderError> {
+        let mut rule = Rule::new(chain)?;
+        let reads_network_header = self
+            .get_expressions()
+            .into_iter()
+            .flat_map(|e| e.iter())
+            .any(|expr| {
+                matches!(expr.get_data(), Some(ExpressionVariant::Payload(payload))
+                    if payload.get_base() == Some(&NFT_PAYLOAD_NETWORK_HEADER))
+            });
+        if rule.family != self.family && reads_network_header {
+            return Err(BuilderError::FamilyDependentRule);
+        }
+        rule.expressions = self.expressions.clone();
+        rule.userdata = self.userdata.clone();
+        rule.compat = self.compat.clone();
+        Ok(rule)
+    }
+
     /// Appends this rule to `batch`
     pub fn add_to_batch(self, batch: &mut Batch) -> Self {
         batch.add(&self, crate::MsgType::Add);
@@ -77,28 +242,202 @@ impl Rule {
     }
 }
 
+#[nfnetlink_object(add = NFT_MSG_NEWRULE, del = NFT_MSG_DELRULE, family_field = family)]
 impl NfNetlinkObject for Rule {
-    const MSG_TYPE_ADD: u32 = NFT_MSG_NEWRULE;
-    const MSG_TYPE_DEL: u32 = NFT_MSG_DELRULE;
-
-    fn get_family(&self) -> ProtocolFamily {
-        self.family
+    fn missing_attributes(&self, msg_type: MsgType) -> Vec<&'static str> {
+        let mut missing = Vec::new();
+        if self.table.is_none() {
+            missing.push("NFTA_RULE_TABLE");
+        }
+        // deleting rules without a chain flushes the whole table
+        if self.chain.is_none() && msg_type != MsgType::Del {
+            missing.push("NFTA_RULE_CHAIN");
+        }
+        if self.handle.is_none() && msg_type == MsgType::Replace {
+            missing.push("NFTA_RULE_HANDLE");
+        }
+        missing
     }
 
-    fn set_family(&mut self, family: ProtocolFamily) {
-        self.family = family;
+    // a handle designates the rule to replace, which requires NLM_F_REPLACE
+    fn without_read_only_attributes(&self, msg_type: MsgType, flags: u16) -> Option<Self> {
+        let is_addition = msg_type == MsgType::Add || msg_type == MsgType::Insert;
+        if !is_addition || self.handle.is_none() || flags & NLM_F_REPLACE as u16 != 0 {
+            return None;
+        }
+        let mut rule = self.clone();
+        rule.handle = None;
+        Some(rule)
     }
 
     // append at the end of the chain, instead of the beginning
     fn get_add_flags(&self) -> u32 {
         NLM_F_CREATE | NLM_F_APPEND
     }
+
+    // without NLM_F_APPEND, the rule goes before its position, or at the start of the chain
+    fn get_insert_flags(&self) -> u32 {
+        NLM_F_CREATE
+    }
+}
+
+/// A canonical one-line description of a [`Rule`], meant for audit logs.
+///
+/// The summary follows the grammar below (in ABNF, where `SP` is a space). It only depends on the
+/// attributes the rule is encoded with, not on the `Display` implementations of the expressions,
+/// so that it stays the same across versions of this crate:
+///
+/// ```text
+/// summary    = family SP name SP name [SP "handle" SP 1*DIGIT] ":"
+///              [SP expression *(";" SP expression)]
+/// family     = "unspec" / "inet" / "ip" / "arp" / "netdev" / "bridge" / "ip6" / "decnet"
+///              / 1*DIGIT
+/// expression = name [SP value]
+/// value      = "0x" *(2HEXDIG) / "(" [attribute *(SP attribute)] ")"
+/// attribute  = 1*DIGIT "=" value
+/// name       = 1*(ALPHA / DIGIT / "_" / "-" / ".") / DQUOTE *CHAR DQUOTE
+/// ```
+///
+/// The family is the one of the rule, or its `NFPROTO_*` value when nft has no name for it. The
+/// table, chain and expression names are written as is when they only hold ASCII letters, digits,
+/// `_`, `-` and `.`, and otherwise between double quotes, escaped with
+/// [`char::escape_default`] so that the summary fits on a single line. The value of an
+/// expression is its `NFTA_EXPR_DATA` attribute: nested attributes are written between
+/// parentheses by increasing type, with their types in decimal, and the others as their bytes in
+/// lowercase hexadecimal (i.e. integers in network byte order). For instance:
+///
+/// ```text
+/// inet filter input handle 4: meta (1=0x00000001 2=0x00000010); cmp (1=0x00000001
+/// 2=0x00000000 3=(1=0x06)); immediate (1=0x00000000 2=(2=(1=0x00000001)))
+/// ```
+///
+/// (on a single line). This format is part of the API: changing it is a breaking change.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RuleSummary(String);
+
+impl RuleSummary {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl TryFrom<&Rule> for RuleSummary {
+    type Error = BuilderError;
+
+    /// Fails with [`BuilderError::MissingChainInformationError`] if the rule is not bound to a
+    /// chain.
+    fn try_from(rule: &Rule) -> Result<Self, Self::Error> {
+        let table = rule
+            .get_table()
+            .ok_or(BuilderError::MissingChainInformationError)?;
+        let chain = rule
+            .get_chain()
+            .ok_or(BuilderError::MissingChainInformationError)?;
+
+        let mut summary = String::new();
+        push_summary_family(rule.get_family(), &mut summary);
+        summary.push(' ');
+        push_summary_name(table, &mut summary);
+        summary.push(' ');
+        push_summary_name(chain, &mut summary);
+        if let Some(handle) = rule.get_handle() {
+            summary.push_str(&format!(" handle {}", handle));
+        }
+        summary.push(':');
+        for (i, expr) in rule
+            .get_expressions()
+            .into_iter()
+            .flat_map(|e| e.iter())
+            .enumerate()
+        {
+            summary.push_str(if i == 0 { " " } else { "; " });
+            push_summary_name(expr.get_name().map_or("", String::as_str), &mut summary);
+            if let Some(data) = expr.get_data() {
+                let mut buf = vec![0; data.get_size()];
+                data.write_payload(&mut buf);
+                // the data of the expressions unknown to this crate may not be attributes
+                let value = match RawAttributeTree::parse(&buf) {
+                    Ok(tree) if data.is_nested() => RawAttributeValue::Nested(tree),
+                    _ => RawAttributeValue::Bytes(buf),
+                };
+                summary.push(' ');
+                push_summary_value(&value, &mut summary);
+            }
+        }
+        Ok(RuleSummary(summary))
+    }
 }
 
+fn push_summary_family(family: ProtocolFamily, summary: &mut String) {
+    match family {
+        ProtocolFamily::Other(value) => summary.push_str(&value.to_string()),
+        family => summary.push_str(&family.to_string()),
+    }
+}
+
+fn push_summary_name(name: &str, summary: &mut String) {
+    let is_bare = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.';
+    if !name.is_empty() && name.chars().all(is_bare) {
+        summary.push_str(name);
+    } else {
+        summary.push('"');
+        summary.extend(name.chars().flat_map(char::escape_default));
+        summary.push('"');
+    }
+}
+
+fn push_summary_value(value: &RawAttributeValue, summary: &mut String) {
+    match value {
+        RawAttributeValue::Bytes(bytes) => {
+            summary.push_str("0x");
+            for b in bytes {
+                summary.push_str(&format!("{:02x}", b));
+            }
+        }
+        RawAttributeValue::Nested(tree) => {
+            // independent of the order in which the attributes are written
+            let mut attributes: Vec<_> = tree.attributes.iter().collect();
+            attributes.sort_by_key(|attr| attr.attr_type);
+            summary.push('(');
+            for (i, attr) in attributes.into_iter().enumerate() {
+                if i != 0 {
+                    summary.push(' ');
+                }
+                summary.push_str(&format!("{}=", attr.attr_type));
+                push_summary_value(&attr.value, summary);
+            }
+            summary.push(')');
+        }
+    }
+}
+
+impl Display for RuleSummary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[cfg(feature = "socket")]
 pub fn list_rules_for_chain(chain: &Chain) -> Result<Vec<Rule>, QueryError> {
+    list_rules_for_chain_with_reset(chain, false)
+}
+
+/// Same as [`list_rules_for_chain`], but if `reset` is set, the kernel also resets the counters
+/// (and other stateful expressions) of the rules after reporting them, which allows reading the
+/// traffic accounted since the last call with [`Rule::counters`]. Resetting requires Linux 6.3.
+#[cfg(feature = "socket")]
+pub fn list_rules_for_chain_with_reset(
+    chain: &Chain,
+    reset: bool,
+) -> Result<Vec<Rule>, QueryError> {
+    let msg_type = if reset {
+        NFT_MSG_GETRULE_RESET
+    } else {
+        libc::NFT_MSG_GETRULE as u32
+    };
     let mut result = Vec::new();
     list_objects_with_data(
-        libc::NFT_MSG_GETRULE as u16,
+        msg_type as u16,
         &|rule: Rule, rules: &mut Vec<Rule>| {
             rules.push(rule);
             Ok(())
@@ -109,3 +448,48 @@ pub fn list_rules_for_chain(chain: &Chain) -> Result<Vec<Rule>, QueryError> {
     )?;
     Ok(result)
 }
+
+/// Lists the rules of all the chains of `family`, or of all the families with
+/// [`ProtocolFamily::Unspec`].
+#[cfg(feature = "socket")]
+pub fn list_rules_for_family(family: ProtocolFamily) -> Result<Vec<Rule>, QueryError> {
+    let mut result = Vec::new();
+    list_objects_with_data(
+        libc::NFT_MSG_GETRULE as u16,
+        &|rule: Rule, rules: &mut Vec<Rule>| {
+            rules.push(rule);
+            Ok(())
+        },
+        Some(&Rule::default().with_family(family)),
+        &mut result,
+    )?;
+    Ok(result)
+}
+
+/// Lists the rules of the table of `chain` that jump (or go) to `chain`, and thus prevent its
+/// deletion. The references from verdict maps are not reported.
+#[cfg(feature = "socket")]
+pub fn list_rules_jumping_to(chain: &Chain) -> Result<Vec<Rule>, QueryError> {
+    let name = chain
+        .get_name()
+        .ok_or(BuilderError::MissingChainInformationError)?;
+    let filter = Rule::default().with_family(chain.get_family()).with_table(
+        chain
+            .get_table()
+            .ok_or(BuilderError::MissingChainInformationError)?,
+    );
+    let mut result = Vec::new();
+    list_objects_with_data(
+        libc::NFT_MSG_GETRULE as u16,
+        &|rule: Rule, rules: &mut Vec<Rule>| {
+            if rule.jump_targets().any(|target| target == name) {
+                rules.push(rule);
+            }
+            Ok(())
+        },
+        // retrieve the rules of every chain of the table
+        Some(&filter),
+        &mut result,
+    )?;
+    Ok(result)
+}
@@ -1,28 +1,80 @@
 use std::ffi::CString;
 use std::net::IpAddr;
+use std::ops::RangeInclusive;
 
 use ipnetwork::IpNetwork;
 
-use crate::data_type::ip_to_vec;
+use crate::data_type::{DataType, IpOperand, Port, TcHandle};
 use crate::error::BuilderError;
 use crate::expr::ct::{ConnTrackState, Conntrack, ConntrackKey};
 use crate::expr::{
-    Bitwise, Cmp, CmpOp, HighLevelPayload, IPv4HeaderField, IPv6HeaderField, Immediate, Masquerade,
-    Meta, MetaType, NetworkHeaderField, TCPHeaderField, TransportHeaderField, UDPHeaderField,
-    VerdictKind,
+    Bitwise, Byteorder, ByteorderOp, Cmp, CmpOp, Exthdr, HighLevelPayload, ICMPHeaderField,
+    ICMPv6HeaderField, IPv4HeaderField, IPv6HeaderField, Immediate, Limit, Log, LogPrefix, Lookup,
+    Masquerade, Meta, MetaType, Nat, NatType, NetworkHeaderField, Objref, RawExpression, Register,
+    Rt, RtKey, Socket, TCPHeaderField, Tproxy, TransportHeaderField, UDPHeaderField, VerdictKind,
+    TCPOPT_MAXSEG,
 };
-use crate::Rule;
+use crate::nlmsg::NfNetlinkObject;
+use crate::set::SetBuilder;
+use crate::{Batch, Chain, MsgType, ProtocolFamily, Rule, Set, Table};
+
+/// Maximum number of values excluded with a chain of [`Cmp`] expressions by [`Rule::match_any`],
+/// above which an anonymous set is used.
+const MAX_CMP_CHAIN_LEN: usize = 4;
+
+/// The SYN bit of the flags of the TCP header.
+const TCP_FLAG_SYN: u8 = 0x02;
 
 /// Simple protocol description. Note that it does not implement other layer 4 protocols as
 /// IGMP et al. See [`Rule::igmp`] for a workaround.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum Protocol {
     TCP,
     UDP,
 }
 
+/// The types of the ICMP messages, see [`Rule::icmp_type_code`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+#[repr(u8)]
+pub enum IcmpType {
+    EchoReply = 0,
+    DestinationUnreachable = 3,
+    SourceQuench = 4,
+    Redirect = 5,
+    EchoRequest = 8,
+    RouterAdvertisement = 9,
+    RouterSolicitation = 10,
+    TimeExceeded = 11,
+    ParameterProblem = 12,
+    TimestampRequest = 13,
+    TimestampReply = 14,
+}
+
+/// The types of the ICMPv6 messages, see [`Rule::icmpv6_type_code`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+#[repr(u8)]
+pub enum Icmpv6Type {
+    DestinationUnreachable = 1,
+    PacketTooBig = 2,
+    TimeExceeded = 3,
+    ParameterProblem = 4,
+    EchoRequest = 128,
+    EchoReply = 129,
+    RouterSolicitation = 133,
+    RouterAdvertisement = 134,
+    NeighborSolicitation = 135,
+    NeighborAdvertisement = 136,
+    Redirect = 137,
+}
+
 impl Rule {
-    fn match_port(mut self, port: u16, protocol: Protocol, source: bool) -> Self {
+    fn match_port(mut self, port: Port, protocol: Protocol, source: bool, op: CmpOp) -> Self {
         self = self.protocol(protocol);
         self.add_expr(
             HighLevelPayload::Transport(match protocol {
@@ -39,14 +91,29 @@ impl Rule {
             })
             .build(),
         );
-        self.add_expr(Cmp::new(CmpOp::Eq, port.to_be_bytes()));
+        // ports are compared in network byte order, which orders them like numbers
+        self.add_expr(Cmp::new_port(op, port));
         self
     }
 
-    pub fn match_ip(mut self, ip: IpAddr, source: bool) -> Self {
+    fn match_iface_name(mut self, key: MetaType, iface_name: &str) -> Result<Self, BuilderError> {
+        if iface_name.len() >= libc::IFNAMSIZ {
+            return Err(BuilderError::InterfaceNameTooLong);
+        }
+        let mut iface_vec = iface_name.as_bytes().to_vec();
+        // null terminator
+        iface_vec.push(0u8);
+
+        self.add_expr(Meta::new(key));
+        self.add_expr(Cmp::new(CmpOp::Eq, iface_vec));
+        Ok(self)
+    }
+
+    pub fn match_ip(mut self, ip: impl Into<IpOperand>, source: bool) -> Self {
+        let ip = ip.into();
         self.add_expr(Meta::new(MetaType::NfProto));
-        match ip {
-            IpAddr::V4(addr) => {
+        match ip.addr() {
+            IpAddr::V4(_) => {
                 self.add_expr(Cmp::new(CmpOp::Eq, [libc::NFPROTO_IPV4 as u8]));
                 self.add_expr(
                     HighLevelPayload::Network(NetworkHeaderField::IPv4(if source {
@@ -56,9 +123,8 @@ impl Rule {
                     }))
                     .build(),
                 );
-                self.add_expr(Cmp::new(CmpOp::Eq, addr.octets()));
             }
-            IpAddr::V6(addr) => {
+            IpAddr::V6(_) => {
                 self.add_expr(Cmp::new(CmpOp::Eq, [libc::NFPROTO_IPV6 as u8]));
                 self.add_expr(
                     HighLevelPayload::Network(NetworkHeaderField::IPv6(if source {
@@ -68,9 +134,9 @@ impl Rule {
                     }))
                     .build(),
                 );
-                self.add_expr(Cmp::new(CmpOp::Eq, addr.octets()));
             }
         }
+        self.add_expr(Cmp::new_ip(CmpOp::Eq, ip));
         self
     }
 
@@ -87,7 +153,10 @@ impl Rule {
                     }))
                     .build(),
                 );
-                self.add_expr(Bitwise::new(ip_to_vec(net.mask()), 0u32.to_be_bytes())?);
+                self.add_expr(Bitwise::new(
+                    IpOperand::from(net.mask()),
+                    0u32.to_be_bytes(),
+                )?);
             }
             IpNetwork::V6(_) => {
                 self.add_expr(Cmp::new(CmpOp::Eq, [libc::NFPROTO_IPV6 as u8]));
@@ -99,10 +168,13 @@ impl Rule {
                     }))
                     .build(),
                 );
-                self.add_expr(Bitwise::new(ip_to_vec(net.mask()), 0u128.to_be_bytes())?);
+                self.add_expr(Bitwise::new(
+                    IpOperand::from(net.mask()),
+                    0u128.to_be_bytes(),
+                )?);
             }
         }
-        self.add_expr(Cmp::new(CmpOp::Eq, ip_to_vec(net.network())));
+        self.add_expr(Cmp::new_ip(CmpOp::Eq, net.network()));
         Ok(self)
     }
 }
@@ -110,11 +182,50 @@ impl Rule {
 impl Rule {
     /// Matches ICMP packets.
     pub fn icmp(mut self) -> Self {
-        // quid of icmpv6?
         self.add_expr(Meta::new(MetaType::L4Proto));
         self.add_expr(Cmp::new(CmpOp::Eq, [libc::IPPROTO_ICMP as u8]));
         self
     }
+    /// Matches ICMPv6 packets.
+    pub fn icmpv6(mut self) -> Self {
+        self.add_expr(Meta::new(MetaType::L4Proto));
+        self.add_expr(Cmp::new(CmpOp::Eq, [libc::IPPROTO_ICMPV6 as u8]));
+        self
+    }
+    /// Matches the ICMP packets of type `icmp_type` and code `code`, like `icmp type
+    /// destination-unreachable icmp code port-unreachable` in nft. The meaning of the codes
+    /// depends on the type, e.g. 3 is `port-unreachable` for [`IcmpType::DestinationUnreachable`].
+    pub fn icmp_type_code(self, icmp_type: IcmpType, code: u8) -> Self {
+        self.icmp().match_icmp_header(
+            TransportHeaderField::ICMP(ICMPHeaderField::Type),
+            TransportHeaderField::ICMP(ICMPHeaderField::Code),
+            icmp_type as u8,
+            code,
+        )
+    }
+    /// Same as [`Rule::icmp_type_code`], for the ICMPv6 packets, e.g. the router solicitations
+    /// (of code 0) to accept for IPv6 autoconfiguration.
+    pub fn icmpv6_type_code(self, icmp_type: Icmpv6Type, code: u8) -> Self {
+        self.icmpv6().match_icmp_header(
+            TransportHeaderField::ICMPv6(ICMPv6HeaderField::Type),
+            TransportHeaderField::ICMPv6(ICMPv6HeaderField::Code),
+            icmp_type as u8,
+            code,
+        )
+    }
+    fn match_icmp_header(
+        mut self,
+        type_field: TransportHeaderField,
+        code_field: TransportHeaderField,
+        icmp_type: u8,
+        code: u8,
+    ) -> Self {
+        self.add_expr(HighLevelPayload::Transport(type_field).build());
+        self.add_expr(Cmp::new(CmpOp::Eq, [icmp_type]));
+        self.add_expr(HighLevelPayload::Transport(code_field).build());
+        self.add_expr(Cmp::new(CmpOp::Eq, [code]));
+        self
+    }
     /// Matches IGMP packets.
     pub fn igmp(mut self) -> Self {
         self.add_expr(Meta::new(MetaType::L4Proto));
@@ -122,12 +233,21 @@ impl Rule {
         self
     }
     /// Matches packets from source `port` and `protocol`.
-    pub fn sport(self, port: u16, protocol: Protocol) -> Self {
-        self.match_port(port, protocol, true)
+    pub fn sport(self, port: impl Into<Port>, protocol: Protocol) -> Self {
+        self.match_port(port.into(), protocol, true, CmpOp::Eq)
     }
     /// Matches packets to destination `port` and `protocol`.
-    pub fn dport(self, port: u16, protocol: Protocol) -> Self {
-        self.match_port(port, protocol, false)
+    pub fn dport(self, port: impl Into<Port>, protocol: Protocol) -> Self {
+        self.match_port(port.into(), protocol, false, CmpOp::Eq)
+    }
+    /// Matches packets to a destination port greater than or equal to `port`, with `protocol`.
+    /// For open-ended bounds, a single comparison is cheaper than matching a range of ports.
+    pub fn dport_at_least(self, port: impl Into<Port>, protocol: Protocol) -> Self {
+        self.match_port(port.into(), protocol, false, CmpOp::Gte)
+    }
+    /// Matches packets to a destination port lower than or equal to `port`, with `protocol`.
+    pub fn dport_at_most(self, port: impl Into<Port>, protocol: Protocol) -> Self {
+        self.match_port(port.into(), protocol, false, CmpOp::Lte)
     }
     /// Matches packets on `protocol`.
     pub fn protocol(mut self, protocol: Protocol) -> Self {
@@ -142,14 +262,28 @@ impl Rule {
         self
     }
     /// Matches packets in an already established connection.
-    pub fn established(mut self) -> Result<Self, BuilderError> {
-        let allowed_states = ConnTrackState::ESTABLISHED.bits();
+    pub fn established(self) -> Result<Self, BuilderError> {
+        self.ct_states(ConnTrackState::ESTABLISHED, false)
+    }
+    /// Matches packets in an already established connection, or starting a connection related to
+    /// one (e.g. ICMP errors). This is what a stateful `forward` chain usually accepts first.
+    pub fn established_or_related(self) -> Result<Self, BuilderError> {
+        self.ct_states(ConnTrackState::ESTABLISHED | ConnTrackState::RELATED, false)
+    }
+    /// Matches packets whose connection tracking state is one of `states`, e.g.
+    /// `ConnTrackState::ESTABLISHED | ConnTrackState::RELATED`. If `invert` is set, matches the
+    /// packets whose state is none of `states` instead.
+    pub fn ct_states(mut self, states: ConnTrackState, invert: bool) -> Result<Self, BuilderError> {
+        // the conntrack state is stored in host byte order
         self.add_expr(Conntrack::new(ConntrackKey::State));
         self.add_expr(Bitwise::new(
-            allowed_states.to_le_bytes(),
+            states.bits().to_ne_bytes(),
             0u32.to_be_bytes(),
         )?);
-        self.add_expr(Cmp::new(CmpOp::Neq, 0u32.to_be_bytes()));
+        self.add_expr(Cmp::new(
+            if invert { CmpOp::Eq } else { CmpOp::Neq },
+            0u32.to_be_bytes(),
+        ));
         Ok(self)
     }
     /// Deprecated. Please use [Rule::iiface_id] instead, which has the same interface.
@@ -170,17 +304,8 @@ impl Rule {
         self.iiface(iface_name)
     }
     /// Matches packets received through `iface_name` (an interface name, as in "wlan0" or "lo").
-    pub fn iiface(mut self, iface_name: &str) -> Result<Self, BuilderError> {
-        if iface_name.len() >= libc::IFNAMSIZ {
-            return Err(BuilderError::InterfaceNameTooLong);
-        }
-        let mut iface_vec = iface_name.as_bytes().to_vec();
-        // null terminator
-        iface_vec.push(0u8);
-
-        self.add_expr(Meta::new(MetaType::IifName));
-        self.add_expr(Cmp::new(CmpOp::Eq, iface_vec));
-        Ok(self)
+    pub fn iiface(self, iface_name: &str) -> Result<Self, BuilderError> {
+        self.match_iface_name(MetaType::IifName, iface_name)
     }
     /// Matches packets sent through `iface_index`. Interface indexes can be queried with
     /// `iface_index()`.
@@ -190,24 +315,31 @@ impl Rule {
         self
     }
     /// Matches packets sent through `iface_name` (an interface name, as in "wlan0" or "lo").
-    pub fn oiface(mut self, iface_name: &str) -> Result<Self, BuilderError> {
-        if iface_name.len() >= libc::IFNAMSIZ {
-            return Err(BuilderError::InterfaceNameTooLong);
-        }
-        let mut iface_vec = iface_name.as_bytes().to_vec();
-        // null terminator
-        iface_vec.push(0u8);
-
-        self.add_expr(Meta::new(MetaType::OifName));
-        self.add_expr(Cmp::new(CmpOp::Eq, iface_vec));
-        Ok(self)
+    pub fn oiface(self, iface_name: &str) -> Result<Self, BuilderError> {
+        self.match_iface_name(MetaType::OifName, iface_name)
+    }
+    /// Matches packets received on the bridge `bridge_name`. Only available in bridge tables.
+    pub fn ibrname(self, bridge_name: &str) -> Result<Self, BuilderError> {
+        self.match_iface_name(MetaType::BriIifName, bridge_name)
+    }
+    /// Matches packets sent through the bridge `bridge_name`. Only available in bridge tables.
+    pub fn obrname(self, bridge_name: &str) -> Result<Self, BuilderError> {
+        self.match_iface_name(MetaType::BriOifName, bridge_name)
+    }
+    /// Matches packets whose ethertype is `ether_type` (e.g. `libc::ETH_P_IP`). In bridge
+    /// tables, this is the way to dispatch the packets to different chains depending on their
+    /// network protocol, by following the match with a jump verdict.
+    pub fn ether_type(mut self, ether_type: u16) -> Self {
+        self.add_expr(Meta::new(MetaType::Protocol));
+        self.add_expr(Cmp::new(CmpOp::Eq, ether_type.to_be_bytes()));
+        self
     }
     /// Matches packets whose source IP address is `saddr`.
-    pub fn saddr(self, ip: IpAddr) -> Self {
+    pub fn saddr(self, ip: impl Into<IpOperand>) -> Self {
         self.match_ip(ip, true)
     }
     /// Matches packets whose destination IP address is `saddr`.
-    pub fn daddr(self, ip: IpAddr) -> Self {
+    pub fn daddr(self, ip: impl Into<IpOperand>) -> Self {
         self.match_ip(ip, false)
     }
     /// Matches packets whose source network is `net`.
@@ -228,13 +360,322 @@ impl Rule {
         self.add_expr(Immediate::new_verdict(VerdictKind::Drop));
         self
     }
+    /// Jumps to `chain`, whose rules are evaluated before coming back to this chain (unless one
+    /// of them issues a final verdict). Fails if `chain` does not belong to the table of the rule.
+    pub fn jump(self, chain: &Chain) -> Result<Self, BuilderError> {
+        let verdict = VerdictKind::jump_to(chain)?;
+        self.with_verdict_to(chain, verdict)
+    }
+    /// Goes to `chain`, which does not come back to this chain once its rules are evaluated.
+    /// Fails if `chain` does not belong to the table of the rule.
+    pub fn goto(self, chain: &Chain) -> Result<Self, BuilderError> {
+        let verdict = VerdictKind::goto_to(chain)?;
+        self.with_verdict_to(chain, verdict)
+    }
+    fn with_verdict_to(
+        mut self,
+        chain: &Chain,
+        verdict: VerdictKind,
+    ) -> Result<Self, BuilderError> {
+        if chain.get_table() != self.get_table() || chain.get_family() != self.get_family() {
+            return Err(BuilderError::ChainTableMismatch);
+        }
+        self.add_expr(Immediate::new_verdict(verdict));
+        Ok(self)
+    }
+    /// Logs the packets with the given prefix, at most at the pace allowed by `rate`. The limit is
+    /// placed before the log statement, so that packets over the rate are not logged.
+    ///
+    /// Packets over the rate stop the evaluation of the rule: expressions added after this one
+    /// (e.g. a verdict) do not apply to them, so this is best used in a dedicated logging rule.
+    ///
+    /// Prefer this over a bare [`Log`] expression on rules that can match an unbounded number of
+    /// packets, as flooding the kernel log is an easy way to take down a machine.
+    pub fn log_limited<P>(mut self, prefix: P, rate: Limit) -> Result<Self, BuilderError>
+    where
+        P: TryInto<LogPrefix>,
+        BuilderError: From<P::Error>,
+    {
+        let log = Log::new(None, Some(prefix))?;
+        self.add_expr(rate);
+        self.add_expr(log);
+        Ok(self)
+    }
+    /// Matches the packets for which the value loaded in register 1 by `load` (e.g. a payload
+    /// expression) is one of `values`, or none of them if `invert` is set. This is the equivalent
+    /// of `tcp dport { 80, 443 }` (or `tcp dport != { 80, 443 }`) in nft.
+    ///
+    /// The cheapest encoding is picked: a single [`Cmp`] for a single value, a chain of [`Cmp`]
+    /// for a few values to exclude (rules being conjunctions, a chain cannot match one value
+    /// among several), and a lookup in an anonymous set otherwise. In that last case, the set and
+    /// its elements are appended to `batch`, where the rule must then be added too.
+    pub fn match_any<K: DataType>(
+        mut self,
+        load: impl Into<RawExpression>,
+        values: &[K],
+        invert: bool,
+        batch: &mut Batch,
+    ) -> Result<Self, BuilderError> {
+        if values.is_empty() {
+            return Err(BuilderError::EmptyValueList);
+        }
+        self.add_expr(load);
+
+        let op = if invert { CmpOp::Neq } else { CmpOp::Eq };
+        if values.len() == 1 || (invert && values.len() <= MAX_CMP_CHAIN_LEN) {
+            for value in values {
+                self.add_expr(Cmp::new(op, value.data()));
+            }
+            return Ok(self);
+        }
+
+        let table = Table::new(self.get_family()).with_name(
+            self.get_table()
+                .ok_or(BuilderError::MissingChainInformationError)?,
+        );
+        let mut builder = SetBuilder::<K>::new_anonymous(&table)?;
+        for value in values {
+            builder.add(value);
+        }
+        let (set, elements) = builder.finish();
+        batch.add(&set, MsgType::Add);
+        batch.add(&elements, MsgType::Add);
+
+        let lookup = Lookup::new(&set)?;
+        self.add_expr(if invert { lookup.inverted() } else { lookup });
+        Ok(self)
+    }
+
+    /// Sets the mark of the packets to the value associated, in the map `map`, to the key loaded
+    /// in register 1 by `key` (e.g. the source address of the packet). This is the equivalent of
+    /// `meta mark set ip saddr map @map` in nft, and is commonly used to select a routing table.
+    ///
+    /// `map` must be a map with 4-bytes long values (see [`SetBuilder::map_to`]), holding the
+    /// marks in host byte order. Packets whose key is not in the map do not match the rule.
+    ///
+    /// [`SetBuilder::map_to`]: crate::set::SetBuilder::map_to
+    pub fn set_mark_from_map(
+        mut self,
+        key: impl Into<RawExpression>,
+        map: &Set,
+    ) -> Result<Self, BuilderError> {
+        if !map.is_map() || map.get_data_len() != Some(&4) {
+            return Err(BuilderError::InvalidMarkMap);
+        }
+        self.add_expr(key);
+        self.add_expr(Lookup::new(map)?.with_dreg(Register::Reg1));
+        self.add_expr(
+            Meta::default()
+                .with_key(MetaType::Mark)
+                .with_sreg(Register::Reg1),
+        );
+        Ok(self)
+    }
+    /// Applies to the packet the verdict associated, in the verdict map `map`, to the key loaded
+    /// in register 1 by `key` (e.g. the destination port of the packet). This is the equivalent of
+    /// `tcp dport vmap @map` in nft, and replaces a long list of rules with a single lookup.
+    ///
+    /// `map` must be a verdict map (see [`SetBuilder::map_to_verdicts`]). Packets whose key is
+    /// not in the map continue to the next rule.
+    pub fn verdict_map(
+        mut self,
+        key: impl Into<RawExpression>,
+        map: &Set,
+    ) -> Result<Self, BuilderError> {
+        if !map.is_verdict_map() {
+            return Err(BuilderError::NotAVerdictMap);
+        }
+        self.add_expr(key);
+        self.add_expr(Lookup::new(map)?.with_dreg(Register::Verdict));
+        Ok(self)
+    }
+    /// Sets the priority of the packets to `handle`, which classifies them into the tc class
+    /// `handle` of their output interface. This is the equivalent of `meta priority set 1:20` in
+    /// nft.
+    pub fn set_priority(mut self, handle: TcHandle) -> Self {
+        self.add_expr(Immediate::new_data(handle.data(), Register::Reg1));
+        self.add_expr(
+            Meta::default()
+                .with_key(MetaType::Priority)
+                .with_sreg(Register::Reg1),
+        );
+        self
+    }
+    /// Matches packets whose local socket belongs to a process in the cgroup v2 `cgroup_id`, or in
+    /// one of its descendants, where `level` is the level of that cgroup in the hierarchy (see
+    /// [`Socket::cgroupv2`] and [`cgroupv2_id`]). This is the equivalent of
+    /// `socket cgroupv2 level 1 "system.slice"` in nft.
+    ///
+    /// This needs Linux 5.13, see [`is_socket_key_supported`] to check it beforehand.
+    ///
+    /// [`cgroupv2_id`]: crate::expr::cgroupv2_id
+    /// [`is_socket_key_supported`]: crate::expr::is_socket_key_supported
+    pub fn socket_cgroupv2(mut self, level: u8, cgroup_id: u64) -> Self {
+        self.add_expr(Socket::cgroupv2(level));
+        self.add_expr(Cmp::new(CmpOp::Eq, cgroup_id.to_ne_bytes()));
+        self
+    }
+    /// Applies to the packet the stateful object associated, in the object map `map`, to the key
+    /// loaded in register 1 by `key`. This is the equivalent of `counter name ip saddr map @map`
+    /// in nft, and gives every client its own named counter (or quota, ...) with a single rule.
+    ///
+    /// Packets whose key is not in the map do not match the rule.
+    pub fn object_from_map(
+        mut self,
+        key: impl Into<RawExpression>,
+        map: &Set,
+    ) -> Result<Self, BuilderError> {
+        let objref = Objref::from_map(map)?;
+        self.add_expr(key);
+        self.add_expr(objref);
+        Ok(self)
+    }
     /// Forwards the packet to its destination by replacing its source IP address
     /// with that of the output interface and creating a NAT binding.
     /// Note that masquerade operations only make sense in the `postrouting` chain
     /// of a NAT table. See more information on masquerading at
     /// [https://wiki.nftables.org/wiki-nftables/index.php/Performing_Network_Address_Translation_(NAT)](https://wiki.nftables.org/wiki-nftables/index.php/Performing_Network_Address_Translation_(NAT))
     pub fn masquerade(mut self) -> Self {
-        self.add_expr(Masquerade {});
+        self.add_expr(Masquerade::default());
+        self
+    }
+    /// Same as [`Rule::masquerade`], with the source ports of the translated connections picked
+    /// in `ports`.
+    pub fn masquerade_to_ports(mut self, ports: RangeInclusive<u16>) -> Result<Self, BuilderError> {
+        if ports.is_empty() {
+            return Err(BuilderError::EmptyPortRange);
+        }
+        self.add_expr(Immediate::new_data(
+            ports.start().to_be_bytes().to_vec(),
+            Register::Reg1,
+        ));
+        self.add_expr(Immediate::new_data(
+            ports.end().to_be_bytes().to_vec(),
+            Register::Reg2,
+        ));
+        self.add_expr(Masquerade::default().with_port_range(Register::Reg1, Register::Reg2));
+        Ok(self)
+    }
+    /// Lowers the maximum segment size announced by the TCP SYN packets to the one allowed by the
+    /// MTU of their route, like `tcp flags syn tcp option maxseg size set rt mtu` in nft.
+    ///
+    /// This works around the broken path MTU discovery of some networks, e.g. behind a PPPoE
+    /// uplink. It only makes sense after the routing decision, usually in the `forward` chain.
+    pub fn clamp_mss_to_pmtu(mut self) -> Result<Self, BuilderError> {
+        self = self.protocol(Protocol::TCP);
+        self.add_expr(
+            HighLevelPayload::Transport(TransportHeaderField::Tcp(TCPHeaderField::Flags)).build(),
+        );
+        self.add_expr(Bitwise::new([TCP_FLAG_SYN], [0u8])?);
+        self.add_expr(Cmp::new(CmpOp::Neq, [0u8]));
+        self.add_expr(Rt::new(RtKey::TcpMss));
+        self.add_expr(Byteorder::new(ByteorderOp::Hton, 2));
+        self.add_expr(Exthdr::tcp_option(TCPOPT_MAXSEG, 2, 2).with_value_from(Register::Reg1));
+        Ok(self)
+    }
+    /// Rewrites the source address of the packets to `ip`. Only makes sense in the `postrouting`
+    /// chain of a NAT table.
+    pub fn snat(self, ip: impl Into<IpOperand>) -> Self {
+        self.nat(NatType::SNat, ip.into())
+    }
+    /// Rewrites the destination address of the packets to `ip`. Only makes sense in the
+    /// `prerouting` chain of a NAT table.
+    pub fn dnat(self, ip: impl Into<IpOperand>) -> Self {
+        self.nat(NatType::DNat, ip.into())
+    }
+    /// Same as [`Rule::snat`], with the source ports of the translated connections picked in
+    /// `ports`.
+    pub fn snat_to_ports(
+        self,
+        ip: impl Into<IpOperand>,
+        ports: RangeInclusive<u16>,
+    ) -> Result<Self, BuilderError> {
+        self.nat_to_ports(NatType::SNat, ip.into(), ports)
+    }
+    /// Same as [`Rule::dnat`], with the destination ports of the translated connections picked
+    /// in `ports`.
+    pub fn dnat_to_ports(
+        self,
+        ip: impl Into<IpOperand>,
+        ports: RangeInclusive<u16>,
+    ) -> Result<Self, BuilderError> {
+        self.nat_to_ports(NatType::DNat, ip.into(), ports)
+    }
+    /// Diverts the packets to the local socket listening on `port`, without changing their
+    /// destination, like `tproxy to :port` in nft. Only makes sense in the `prerouting` chain, on
+    /// packets whose transport protocol was matched (e.g. with [`Rule::protocol`]).
+    ///
+    /// The proxy must listen with the `IP_TRANSPARENT` option, and the replies must be routed
+    /// back to the host, which is usually done by marking the packets in the same rule and
+    /// routing the marked packets to the loopback interface.
+    pub fn tproxy(mut self, port: u16) -> Self {
+        let family = match self.get_family() {
+            ProtocolFamily::Ipv4 | ProtocolFamily::Ipv6 => self.get_family(),
+            _ => ProtocolFamily::Unspec,
+        };
+        self.add_expr(Immediate::new_data(
+            port.to_be_bytes().to_vec(),
+            Register::Reg1,
+        ));
+        self.add_expr(
+            Tproxy::default()
+                .with_family(family)
+                .with_port_register(Register::Reg1),
+        );
+        self
+    }
+    /// Same as [`Rule::tproxy`], with the proxy listening on `ip` rather than on the original
+    /// destination address of the packets.
+    pub fn tproxy_to(mut self, ip: impl Into<IpOperand>, port: u16) -> Self {
+        let ip = ip.into();
+        self.add_expr(Immediate::new_ip(ip, Register::Reg1));
+        self.add_expr(Immediate::new_data(
+            port.to_be_bytes().to_vec(),
+            Register::Reg2,
+        ));
+        self.add_expr(
+            Tproxy::default()
+                .with_family(ip.family())
+                .with_addr_register(Register::Reg1)
+                .with_port_register(Register::Reg2),
+        );
+        self
+    }
+    fn nat_to_ports(
+        mut self,
+        nat_type: NatType,
+        ip: IpOperand,
+        ports: RangeInclusive<u16>,
+    ) -> Result<Self, BuilderError> {
+        if ports.is_empty() {
+            return Err(BuilderError::EmptyPortRange);
+        }
+        self.add_expr(Immediate::new_ip(ip, Register::Reg1));
+        self.add_expr(Immediate::new_data(
+            ports.start().to_be_bytes().to_vec(),
+            Register::Reg2,
+        ));
+        self.add_expr(Immediate::new_data(
+            ports.end().to_be_bytes().to_vec(),
+            Register::Reg3,
+        ));
+        self.add_expr(
+            Nat::default()
+                .with_nat_type(nat_type)
+                .with_family(ip.family())
+                .with_ip_register(Register::Reg1)
+                .with_port_range(Register::Reg2, Register::Reg3),
+        );
+        Ok(self)
+    }
+    fn nat(mut self, nat_type: NatType, ip: IpOperand) -> Self {
+        self.add_expr(Immediate::new_ip(ip, Register::Reg1));
+        self.add_expr(
+            Nat::default()
+                .with_nat_type(nat_type)
+                .with_family(ip.family())
+                .with_ip_register(Register::Reg1),
+        );
         self
     }
 }
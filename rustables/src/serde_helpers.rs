@@ -0,0 +1,62 @@
+//! Custom (de)serialization of the byte buffers of the model types: they are represented as
+//! hexadecimal strings, which is both more compact and more readable than arrays of integers in
+//! text formats.
+
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serializer};
+
+fn to_hex(buf: &[u8]) -> String {
+    buf.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex<E: Error>(s: &str) -> Result<Vec<u8>, E> {
+    if s.len() % 2 == 1 {
+        return Err(E::custom("odd number of hexadecimal digits"));
+    }
+    // from_str_radix alone would also accept a leading sign
+    if !s.bytes().all(|c| c.is_ascii_hexdigit()) {
+        return Err(E::custom("invalid hexadecimal string"));
+    }
+    s.as_bytes()
+        .chunks(2)
+        .map(|digits| {
+            let digits = std::str::from_utf8(digits).map_err(E::custom)?;
+            u8::from_str_radix(digits, 16).map_err(E::custom)
+        })
+        .collect()
+}
+
+pub(crate) mod hex {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(buf: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&to_hex(buf))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        from_hex(&String::deserialize(deserializer)?)
+    }
+}
+
+/// Same as [`hex`], for the optional attributes of the netlink structures.
+pub(crate) mod option_hex {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        buf: &Option<Vec<u8>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match buf {
+            Some(buf) => serializer.serialize_some(&to_hex(buf)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Vec<u8>>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|s| from_hex(&s))
+            .transpose()
+    }
+}
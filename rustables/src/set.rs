@@ -1,50 +1,244 @@
-use rustables_macros::nfnetlink_struct;
+use rustables_macros::{nfnetlink_object, nfnetlink_struct};
 
-use crate::data_type::DataType;
-use crate::error::BuilderError;
+use crate::data_type::{DataType, DataTypeId, IpOperand};
+#[cfg(feature = "socket")]
+use crate::error::QueryError;
+use crate::error::{BuilderError, DecodeError};
+use crate::expr::{Counter, ExpressionList, ExpressionVariant, RawExpression, VerdictKind};
 use crate::nlmsg::NfNetlinkObject;
+use crate::obj::ObjectType;
 use crate::parser_impls::{NfNetlinkData, NfNetlinkList};
+#[cfg(feature = "socket")]
+use crate::query::list_objects_with_data;
+pub use crate::set_userdata::{Endianness, SetUserdata, TypeofExpr};
 use crate::sys::{
-    NFTA_SET_ELEM_KEY, NFTA_SET_ELEM_LIST_ELEMENTS, NFTA_SET_ELEM_LIST_SET,
-    NFTA_SET_ELEM_LIST_TABLE, NFTA_SET_FLAGS, NFTA_SET_ID, NFTA_SET_KEY_LEN, NFTA_SET_KEY_TYPE,
-    NFTA_SET_NAME, NFTA_SET_TABLE, NFTA_SET_USERDATA, NFT_MSG_DELSET, NFT_MSG_DELSETELEM,
-    NFT_MSG_NEWSET, NFT_MSG_NEWSETELEM,
+    NFTA_SET_DATA_LEN, NFTA_SET_DATA_TYPE, NFTA_SET_ELEM_DATA, NFTA_SET_ELEM_EXPIRATION,
+    NFTA_SET_ELEM_EXPR, NFTA_SET_ELEM_FLAGS, NFTA_SET_ELEM_KEY, NFTA_SET_ELEM_LIST_ELEMENTS,
+    NFTA_SET_ELEM_LIST_SET, NFTA_SET_ELEM_LIST_SET_ID, NFTA_SET_ELEM_LIST_TABLE,
+    NFTA_SET_ELEM_OBJREF, NFTA_SET_ELEM_TIMEOUT, NFTA_SET_FLAGS, NFTA_SET_ID, NFTA_SET_KEY_LEN,
+    NFTA_SET_KEY_TYPE, NFTA_SET_NAME, NFTA_SET_OBJ_TYPE, NFTA_SET_TABLE, NFTA_SET_TIMEOUT,
+    NFTA_SET_USERDATA, NFT_DATA_VERDICT, NFT_MSG_DELSET, NFT_MSG_DELSETELEM, NFT_MSG_NEWSET,
+    NFT_MSG_NEWSETELEM, NFT_SET_ANONYMOUS, NFT_SET_CONCAT, NFT_SET_CONSTANT,
+    NFT_SET_ELEM_INTERVAL_END, NFT_SET_EVAL, NFT_SET_EXPR, NFT_SET_INTERVAL, NFT_SET_MAP,
+    NFT_SET_OBJECT, NFT_SET_TIMEOUT,
 };
 use crate::table::Table;
-use crate::ProtocolFamily;
+use crate::{Batch, MsgType, ProtocolFamily};
 use std::fmt::Debug;
 use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
 
+bitflags::bitflags! {
+    /// The flags of a set, see [`Set::get_set_flags`].
+    pub struct SetFlags: u32 {
+        /// The set has no name of its own, and is deleted with the last rule using it.
+        const ANONYMOUS = NFT_SET_ANONYMOUS;
+        /// The elements of the set cannot change once it is created.
+        const CONSTANT = NFT_SET_CONSTANT;
+        /// The keys of the set are ranges (e.g. networks).
+        const INTERVAL = NFT_SET_INTERVAL;
+        /// The elements of the set associate a value to their key.
+        const MAP = NFT_SET_MAP;
+        /// The elements of the set can expire.
+        const TIMEOUT = NFT_SET_TIMEOUT;
+        /// The set can be updated from the packet path, with a dynset expression (the `dynamic`
+        /// flag of nft).
+        const EVAL = NFT_SET_EVAL;
+        /// The elements of the set associate a stateful object to their key.
+        const OBJECT = NFT_SET_OBJECT;
+        /// The keys of the set are concatenations of several values.
+        const CONCAT = NFT_SET_CONCAT;
+        /// An expression is attached to the elements of the set.
+        const EXPR = NFT_SET_EXPR;
+    }
+}
+
+#[nfnetlink_struct(derive_deserialize = false, builder = "RawSetBuilder")]
 #[derive(Default, Debug, Clone, PartialEq, Eq)]
-#[nfnetlink_struct(derive_deserialize = false)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Set {
     pub family: ProtocolFamily,
-    #[field(NFTA_SET_TABLE)]
+    #[field(NFTA_SET_TABLE, required = true)]
     pub table: String,
-    #[field(NFTA_SET_NAME)]
+    #[field(NFTA_SET_NAME, required = true)]
     pub name: String,
     #[field(NFTA_SET_FLAGS)]
     pub flags: u32,
-    #[field(NFTA_SET_KEY_TYPE)]
+    #[field(NFTA_SET_KEY_TYPE, required = true)]
     pub key_type: u32,
-    #[field(NFTA_SET_KEY_LEN)]
+    #[field(NFTA_SET_KEY_LEN, required = true)]
     pub key_len: u32,
+    /// For maps, the nft type of the values associated to the keys.
+    #[field(NFTA_SET_DATA_TYPE)]
+    pub data_type: u32,
+    /// For maps, the length of the values associated to the keys.
+    #[field(NFTA_SET_DATA_LEN)]
+    pub data_len: u32,
     #[field(NFTA_SET_ID)]
     pub id: u32,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, with = "crate::serde_helpers::option_hex")
+    )]
     #[field(NFTA_SET_USERDATA)]
     pub userdata: Vec<u8>,
+    /// Expression attached to every element of the set, e.g. a [`Counter`].
+    #[field(optional = true, stub_if_missing = true, crate::sys::NFTA_SET_EXPR)]
+    pub expr: RawExpression,
+    /// For object maps, the type of the objects associated to the keys.
+    #[field(NFTA_SET_OBJ_TYPE)]
+    pub obj_type: ObjectType,
+    /// The timeout of the elements added without one, in milliseconds.
+    #[field(NFTA_SET_TIMEOUT)]
+    pub timeout: u64,
 }
 
-impl NfNetlinkObject for Set {
-    const MSG_TYPE_ADD: u32 = NFT_MSG_NEWSET;
-    const MSG_TYPE_DEL: u32 = NFT_MSG_DELSET;
+impl<TableState, NameState, KeyTypeState, KeyLenState>
+    RawSetBuilder<TableState, NameState, KeyTypeState, KeyLenState>
+{
+    /// Sets the protocol family of the set, which must be the one of its table.
+    pub fn with_family(mut self, family: ProtocolFamily) -> Self {
+        self.inner.family = family;
+        self
+    }
+}
+
+impl Set {
+    /// Creates a named set of the table `table`, holding keys of type `K`. This is the equivalent
+    /// of `nft add set inet t s { type ipv4_addr; }`. The elements of the set can then be added or
+    /// removed in later batches, see [`SetElementList::for_set`].
+    ///
+    /// [`SetBuilder`] creates a set along with its initial elements.
+    pub fn new_named<K: DataType>(
+        name: impl Into<String>,
+        table: &Table,
+    ) -> Result<Self, BuilderError> {
+        Ok(SetBuilder::<K>::new(name, table)?.finish().0)
+    }
+
+    /// Whether the set is a map, i.e. its elements associate a value to their key.
+    pub fn is_map(&self) -> bool {
+        self.flags.unwrap_or(0) & NFT_SET_MAP != 0
+    }
+
+    /// Whether the set is a verdict map, i.e. its elements associate a verdict to their key.
+    pub fn is_verdict_map(&self) -> bool {
+        self.is_map() && self.data_type == Some(NFT_DATA_VERDICT)
+    }
+
+    /// Whether the set is an object map, i.e. its elements associate a stateful object of the
+    /// table to their key.
+    pub fn is_object_map(&self) -> bool {
+        self.flags.unwrap_or(0) & NFT_SET_OBJECT != 0
+    }
 
-    fn get_family(&self) -> ProtocolFamily {
-        self.family
+    /// The flags of the set, or `None` if they are not set.
+    pub fn get_set_flags(&self) -> Option<SetFlags> {
+        self.flags.map(SetFlags::from_bits_truncate)
     }
 
-    fn set_family(&mut self, family: ProtocolFamily) {
-        self.family = family;
+    /// Replaces the flags of the set.
+    pub fn with_set_flags(self, flags: SetFlags) -> Self {
+        self.with_flags(flags.bits())
+    }
+
+    /// Checks that the flags of the set can be accepted by the kernel, which otherwise refuses
+    /// the set with `EOPNOTSUPP`:
+    /// - a set cannot be both a map and an object map;
+    /// - an object map cannot be updated from the packet path;
+    /// - the elements of anonymous sets can only expire in sets updated from the packet path;
+    /// - the elements of constant sets cannot expire.
+    pub fn validate(&self) -> Result<(), BuilderError> {
+        let flags = self.get_set_flags().unwrap_or_else(SetFlags::empty);
+        for incompatible in [
+            SetFlags::MAP | SetFlags::OBJECT,
+            SetFlags::EVAL | SetFlags::OBJECT,
+            SetFlags::CONSTANT | SetFlags::TIMEOUT,
+        ] {
+            if flags.contains(incompatible) {
+                return Err(BuilderError::IncompatibleSetFlags(incompatible));
+            }
+        }
+        if flags.contains(SetFlags::ANONYMOUS | SetFlags::TIMEOUT)
+            && !flags.contains(SetFlags::EVAL)
+        {
+            return Err(BuilderError::IncompatibleSetFlags(
+                SetFlags::ANONYMOUS | SetFlags::TIMEOUT,
+            ));
+        }
+        Ok(())
+    }
+
+    /// Decodes the userdata of the set, in which nft stores how to display the set. Returns the
+    /// default value if the set has no userdata.
+    pub fn get_userdata_tlvs(&self) -> Result<SetUserdata, DecodeError> {
+        match &self.userdata {
+            Some(userdata) => SetUserdata::decode(userdata),
+            None => Ok(SetUserdata::default()),
+        }
+    }
+
+    /// Replaces the userdata of the set with the encoding of `userdata`. Fails if a value of
+    /// `userdata` is longer than 255 bytes, leaving the set untouched.
+    pub fn set_userdata_tlvs(&mut self, userdata: &SetUserdata) -> Result<(), BuilderError> {
+        self.set_userdata(userdata.encode()?);
+        Ok(())
+    }
+
+    /// The nft types of the keys of the set, with one entry per member for concatenations.
+    /// Returns `None` if the key type is not set, or if it is not known to this library.
+    pub fn get_key_data_types(&self) -> Option<Vec<DataTypeId>> {
+        DataTypeId::split(self.key_type?).ok()
+    }
+
+    /// A human-readable description of the type of the keys, as displayed by nft (e.g.
+    /// `ipv4_addr . inet_service`). Falls back to the raw value when the type is unknown.
+    pub fn key_type_name(&self) -> Option<String> {
+        let key_type = self.key_type?;
+        Some(match DataTypeId::split(key_type) {
+            Ok(types) => types
+                .iter()
+                .map(|ty| ty.name())
+                .collect::<Vec<_>>()
+                .join(" . "),
+            Err(_) => format!("{:#x}", key_type),
+        })
+    }
+}
+
+#[nfnetlink_object(add = NFT_MSG_NEWSET, del = NFT_MSG_DELSET, family_field = family)]
+impl NfNetlinkObject for Set {
+    fn object_name(&self) -> Option<String> {
+        self.name.clone()
+    }
+
+    fn missing_attributes(&self, msg_type: MsgType) -> Vec<&'static str> {
+        let mut missing = Vec::new();
+        if self.table.is_none() {
+            missing.push("NFTA_SET_TABLE");
+        }
+        if self.name.is_none() {
+            missing.push("NFTA_SET_NAME");
+        }
+        if msg_type != MsgType::Del && self.key_type.is_none() {
+            missing.push("NFTA_SET_KEY_TYPE");
+        }
+        if msg_type != MsgType::Del && self.key_len.is_none() {
+            missing.push("NFTA_SET_KEY_LEN");
+        }
+        // the kernel knows the length of verdicts
+        if msg_type != MsgType::Del
+            && self.is_map()
+            && !self.is_verdict_map()
+            && self.data_len.is_none()
+        {
+            missing.push("NFTA_SET_DATA_LEN");
+        }
+        if msg_type != MsgType::Del && self.is_object_map() && self.obj_type.is_none() {
+            missing.push("NFTA_SET_OBJ_TYPE");
+        }
+        missing
     }
 }
 
@@ -62,23 +256,197 @@ impl<K: DataType> SetBuilder<K> {
             .with_key_type(K::TYPE)
             .with_key_len(K::LEN)
             .with_table(table_name)
-            .with_name(&set_name);
+            .with_name(&set_name)
+            .with_family(table.get_family());
 
         Ok(SetBuilder {
             inner: set,
             list: SetElementList {
+                family: table.get_family(),
                 table: Some(table_name.clone()),
                 set: Some(set_name),
+                set_id: None,
                 elements: Some(SetElementListElements::default()),
             },
             _phantom: PhantomData,
         })
     }
 
+    /// Creates an anonymous and constant set, like the ones nft creates for the inline sets of
+    /// rules (e.g. `tcp dport { 80, 443 }`). The set is referenced by its ID, which is unique in
+    /// the process, as the kernel picks its name. It is deleted with the last rule using it.
+    pub fn new_anonymous(table: &Table) -> Result<Self, BuilderError> {
+        static NEXT_ID: AtomicU32 = AtomicU32::new(0);
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+
+        let mut builder = Self::new("__set%d", table)?;
+        builder
+            .inner
+            .set_flags(NFT_SET_ANONYMOUS | NFT_SET_CONSTANT);
+        builder.inner.set_id(id);
+        builder.list.set_id = Some(id);
+        Ok(builder)
+    }
+
+    /// Adds `flags` to the flags of the set. See [`Set::validate`] for the combinations the
+    /// kernel accepts.
+    pub fn add_flags(mut self, flags: SetFlags) -> Self {
+        let current = self.inner.get_set_flags().unwrap_or_else(SetFlags::empty);
+        self.inner = self.inner.with_set_flags(current | flags);
+        self
+    }
+
+    /// Lets the rules update the set from the packet path (e.g. to add the source address of the
+    /// packets to a blacklist), like the `dynamic` flag of nft.
+    pub fn dynamic(self) -> Self {
+        self.add_flags(SetFlags::EVAL)
+    }
+
+    /// Removes the elements from the set `timeout` after they were added (or refreshed, see
+    /// [`SetElementList::refresh`]), unless they are added with their own timeout (see
+    /// [`SetElement::with_ttl`]). This is the equivalent of `flags timeout; timeout 1h;` in nft.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.inner.set_timeout(timeout.as_millis() as u64);
+        self.add_flags(SetFlags::TIMEOUT)
+    }
+
+    /// Overrides the nft type of the keys, which is otherwise derived from `K`. This lets nft
+    /// display the elements properly when `K` is a raw byte array, e.g. with
+    /// [`DataTypeId::InetService`] for ports stored as `[u8; 2]`.
+    pub fn with_key_type(mut self, key_type: DataTypeId) -> Self {
+        self.inner.set_key_type(key_type as u32);
+        self
+    }
+
+    /// Sets the userdata of the set, e.g. to let nft display integer keys in host byte order.
+    /// Fails if a value of `userdata` is longer than 255 bytes.
+    pub fn with_userdata_tlvs(mut self, userdata: &SetUserdata) -> Result<Self, BuilderError> {
+        self.inner.set_userdata_tlvs(userdata)?;
+        Ok(self)
+    }
+
     pub fn add(&mut self, key: &K) {
-        self.list.elements.as_mut().unwrap().add_value(SetElement {
-            key: Some(NfNetlinkData::default().with_value(key.data())),
-        });
+        self.list.elements.as_mut().unwrap().add_value(
+            SetElement::default().with_key(NfNetlinkData::default().with_value(key.data())),
+        );
+    }
+
+    /// Adds the IPv4 or IPv6 address `ip` to the set. Fails if the length of the address doesn't
+    /// match the length of the keys of the set (e.g. when adding an IPv6 address to a set of
+    /// [`Ipv4Addr`]).
+    ///
+    /// [`Ipv4Addr`]: std::net::Ipv4Addr
+    pub fn add_ip(&mut self, ip: impl Into<IpOperand>) -> Result<(), BuilderError> {
+        let data = ip.into().to_vec();
+        if data.len() != K::LEN as usize {
+            return Err(BuilderError::IncompatibleLength);
+        }
+        self.list
+            .elements
+            .as_mut()
+            .unwrap()
+            .add_value(SetElement::default().with_key(NfNetlinkData::default().with_value(data)));
+        Ok(())
+    }
+
+    /// Turns the set into a map, whose elements associate a value of type `data_type`, that is
+    /// `data_len` bytes long, to their key. The elements are then added with
+    /// [`SetBuilder::add_mapping`]. Verdict maps are created with
+    /// [`SetBuilder::map_to_verdicts`] instead.
+    pub fn map_to(mut self, data_type: DataTypeId, data_len: u32) -> Self {
+        let flags = self.inner.get_flags().copied().unwrap_or(0);
+        self.inner.set_flags(flags | NFT_SET_MAP);
+        self.inner.set_data_type(data_type as u32);
+        self.inner.set_data_len(data_len);
+        self
+    }
+
+    /// Adds the element `key` to the map, associated to the value `data`. Fails if the length of
+    /// `data` doesn't match the one given to [`SetBuilder::map_to`].
+    pub fn add_mapping(&mut self, key: &K, data: impl Into<Vec<u8>>) -> Result<(), BuilderError> {
+        let data = data.into();
+        if self.inner.get_data_len() != Some(&(data.len() as u32)) {
+            return Err(BuilderError::IncompatibleLength);
+        }
+        self.list.elements.as_mut().unwrap().add_value(
+            SetElement::default()
+                .with_key(NfNetlinkData::default().with_value(key.data()))
+                .with_data(NfNetlinkData::default().with_value(data)),
+        );
+        Ok(())
+    }
+
+    /// Turns the set into a verdict map, whose elements associate a verdict to their key. The
+    /// elements are then added with [`SetBuilder::add_verdict_mapping`], and the verdicts are
+    /// applied to the packets with [`Rule::verdict_map`]. This is the equivalent of
+    /// `map m { type inet_service : verdict }` in nft.
+    ///
+    /// [`Rule::verdict_map`]: crate::Rule::verdict_map
+    pub fn map_to_verdicts(mut self) -> Self {
+        let flags = self.inner.get_flags().copied().unwrap_or(0);
+        self.inner.set_flags(flags | NFT_SET_MAP);
+        self.inner.set_data_type(NFT_DATA_VERDICT);
+        self
+    }
+
+    /// Adds the element `key` to the verdict map, associated to the verdict `verdict`. Fails if
+    /// the set is not a verdict map (see [`SetBuilder::map_to_verdicts`]).
+    pub fn add_verdict_mapping(
+        &mut self,
+        key: &K,
+        verdict: VerdictKind,
+    ) -> Result<(), BuilderError> {
+        if !self.inner.is_verdict_map() {
+            return Err(BuilderError::NotAVerdictMap);
+        }
+        self.list
+            .elements
+            .as_mut()
+            .unwrap()
+            .add_value(SetElement::verdict_mapping(key, verdict));
+        Ok(())
+    }
+
+    /// Turns the set into an object map, whose elements associate a stateful object of type
+    /// `obj_type` to their key. The elements are then added with
+    /// [`SetBuilder::add_object_mapping`], and the objects are applied to the packets with an
+    /// [`Objref`] expression.
+    ///
+    /// [`Objref`]: crate::expr::Objref
+    pub fn map_to_objects(mut self, obj_type: ObjectType) -> Self {
+        let flags = self.inner.get_flags().copied().unwrap_or(0);
+        self.inner.set_flags(flags | NFT_SET_OBJECT);
+        self.inner.set_obj_type(obj_type);
+        self
+    }
+
+    /// Adds the element `key` to the object map, associated to the object named `obj_name`, which
+    /// must exist in the table when the element is added. Fails if the set is not an object map
+    /// (see [`SetBuilder::map_to_objects`]).
+    pub fn add_object_mapping(
+        &mut self,
+        key: &K,
+        obj_name: impl Into<String>,
+    ) -> Result<(), BuilderError> {
+        if !self.inner.is_object_map() {
+            return Err(BuilderError::NotAnObjectMap);
+        }
+        self.list.elements.as_mut().unwrap().add_value(
+            SetElement::default()
+                .with_key(NfNetlinkData::default().with_value(key.data()))
+                .with_objref(obj_name.into()),
+        );
+        Ok(())
+    }
+
+    /// Adds an element to the set, with a stateful expression (e.g. a [`Counter`]) attached to
+    /// that element.
+    pub fn add_with_expr(&mut self, key: &K, expr: impl Into<RawExpression>) {
+        self.list.elements.as_mut().unwrap().add_value(
+            SetElement::default()
+                .with_key(NfNetlinkData::default().with_value(key.data()))
+                .with_expr(expr),
+        );
     }
 
     pub fn finish(self) -> (Set, SetElementList) {
@@ -88,29 +456,291 @@ impl<K: DataType> SetBuilder<K> {
 
 #[derive(Default, Debug, Clone, PartialEq, Eq)]
 #[nfnetlink_struct(nested = true, derive_deserialize = false)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SetElementList {
+    pub family: ProtocolFamily,
     #[field(NFTA_SET_ELEM_LIST_TABLE)]
     pub table: String,
     #[field(NFTA_SET_ELEM_LIST_SET)]
     pub set: String,
+    /// Identifies the set when it is created in the same batch, which is required for anonymous
+    /// sets, whose name is picked by the kernel.
+    #[field(NFTA_SET_ELEM_LIST_SET_ID)]
+    pub set_id: u32,
     #[field(NFTA_SET_ELEM_LIST_ELEMENTS)]
     pub elements: SetElementListElements,
 }
 
+impl SetElementList {
+    /// Creates an empty list of elements of the existing set `set`. Once filled, the list adds
+    /// the elements to the set when it is added to a batch with [`MsgType::Add`], and removes
+    /// them from the set with [`MsgType::Del`].
+    pub fn for_set(set: &Set) -> Result<Self, BuilderError> {
+        Ok(SetElementList {
+            family: set.family,
+            table: Some(set.table.clone().ok_or(BuilderError::MissingTableName)?),
+            set: Some(set.name.clone().ok_or(BuilderError::MissingSetName)?),
+            set_id: None,
+            elements: Some(SetElementListElements::default()),
+        })
+    }
+
+    /// Appends to the list the element whose key is `key`.
+    pub fn add_key<K: DataType>(&mut self, key: &K) {
+        self.add_element(
+            SetElement::default().with_key(NfNetlinkData::default().with_value(key.data())),
+        );
+    }
+
+    /// Appends to the list the element of a map associating `data` to `key`.
+    pub fn add_mapping<K: DataType>(&mut self, key: &K, data: impl Into<Vec<u8>>) {
+        self.add_element(
+            SetElement::default()
+                .with_key(NfNetlinkData::default().with_value(key.data()))
+                .with_data(NfNetlinkData::default().with_value(data.into())),
+        );
+    }
+
+    /// Appends to the list the element of a verdict map associating `verdict` to `key`.
+    pub fn add_verdict_mapping<K: DataType>(&mut self, key: &K, verdict: VerdictKind) {
+        self.add_element(SetElement::verdict_mapping(key, verdict));
+    }
+
+    /// Appends to the list the element whose key is `key`, which the kernel removes from the set
+    /// `ttl` after it is added. The set must have the [`SetFlags::TIMEOUT`] flag.
+    pub fn add_key_with_ttl<K: DataType>(&mut self, key: &K, ttl: Duration) {
+        self.add_element(
+            SetElement::default()
+                .with_key(NfNetlinkData::default().with_value(key.data()))
+                .with_ttl(ttl),
+        );
+    }
+
+    /// Appends to the list of an interval set (see [`SetFlags::INTERVAL`]) the range of keys from
+    /// `first` to `last`, both included. The kernel stores the range as two elements: the one
+    /// starting it, and the one ending it (flagged with `NFT_SET_ELEM_INTERVAL_END`) whose key
+    /// follows `last`. The keys are ordered as big-endian byte strings, like the kernel does, so
+    /// the ranges of addresses or ports are straightforward.
+    ///
+    /// The ranges of a set must not overlap, which the kernel refuses: they must be merged
+    /// beforehand, as nft does for the sets with `auto-merge`.
+    pub fn add_range<K: DataType>(&mut self, first: &K, last: &K) {
+        self.add_element(
+            SetElement::default().with_key(NfNetlinkData::default().with_value(first.data())),
+        );
+        // the range that reaches the largest key is left open
+        let mut end = last.data();
+        if let Some(pos) = end.iter().rposition(|b| *b != u8::MAX) {
+            end[pos] += 1;
+            end[pos + 1..].fill(0);
+            self.add_element(SetElement::interval_end(end));
+        }
+    }
+
+    /// Appends `element` to the list, e.g. the element of a map with its value.
+    pub fn add_element(&mut self, element: SetElement) {
+        self.elements
+            .get_or_insert_with(SetElementListElements::default)
+            .add_value(element);
+    }
+}
+
+impl SetElementList {
+    /// Adds the elements of the list to `batch`, restarting the expiration of the elements
+    /// already in the set, e.g. to implement sliding expirations in a ban list: the timeout of
+    /// an element (or the one of the set) then runs from the moment the batch is committed.
+    ///
+    /// Adding an element that is already in the set does not change its expiration, and deleting
+    /// an element that is not in the set fails the batch. So the elements are added, deleted and
+    /// added again, which works whether the elements are in the set or not, and is atomic since
+    /// the batch is.
+    pub fn refresh(&self, batch: &mut Batch) {
+        batch.add(self, MsgType::Add);
+        batch.add(self, MsgType::Del);
+        batch.add(self, MsgType::Add);
+    }
+}
+
+#[nfnetlink_object(add = NFT_MSG_NEWSETELEM, del = NFT_MSG_DELSETELEM, family_field = family)]
 impl NfNetlinkObject for SetElementList {
-    const MSG_TYPE_ADD: u32 = NFT_MSG_NEWSETELEM;
-    const MSG_TYPE_DEL: u32 = NFT_MSG_DELSETELEM;
+    // the elements are designated by the set that holds them
+    fn object_name(&self) -> Option<String> {
+        self.set.clone()
+    }
+
+    fn missing_attributes(&self, _msg_type: MsgType) -> Vec<&'static str> {
+        let mut missing = Vec::new();
+        if self.table.is_none() {
+            missing.push("NFTA_SET_ELEM_LIST_TABLE");
+        }
+        if self.set.is_none() && self.set_id.is_none() {
+            missing.push("NFTA_SET_ELEM_LIST_SET");
+        }
+        missing
+    }
 
-    fn get_family(&self) -> ProtocolFamily {
-        ProtocolFamily::Unspec
+    // sending back the time left would set it as the new expiration of listed elements, or fail
+    // on sets without timeouts
+    fn without_read_only_attributes(&self, _msg_type: MsgType, _flags: u16) -> Option<Self> {
+        let elements = self.elements.as_ref()?;
+        if !elements.iter().any(|elem| elem.expiration.is_some()) {
+            return None;
+        }
+        let mut list = self.clone();
+        for elem in list
+            .elements
+            .iter_mut()
+            .flat_map(|elements| elements.iter_mut())
+        {
+            elem.expiration = None;
+        }
+        Some(list)
     }
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Eq)]
 #[nfnetlink_struct(nested = true)]
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SetElement {
     #[field(NFTA_SET_ELEM_KEY)]
     pub key: NfNetlinkData,
+    /// For maps, the value associated to the key.
+    #[field(NFTA_SET_ELEM_DATA)]
+    pub data: NfNetlinkData,
+    #[field(NFTA_SET_ELEM_EXPR)]
+    pub expr: RawExpression,
+    #[field(
+        optional = true,
+        stub_if_missing = true,
+        crate::sys::NFTA_SET_ELEM_EXPRESSIONS
+    )]
+    pub expressions: ExpressionList,
+    /// For object maps, the name of the object associated to the key.
+    #[field(NFTA_SET_ELEM_OBJREF)]
+    pub objref: String,
+    /// The time after which the element is removed from the set, in milliseconds.
+    #[field(NFTA_SET_ELEM_TIMEOUT)]
+    pub timeout: u64,
+    /// The time left before the element is removed from the set, in milliseconds, as reported
+    /// by the kernel when the elements are listed. It is not written in the messages of a
+    /// [`SetElementList`], so that listed elements can be sent back as is.
+    #[field(NFTA_SET_ELEM_EXPIRATION)]
+    pub expiration: u64,
+    /// The flags of the element, e.g. `NFT_SET_ELEM_INTERVAL_END`.
+    #[field(NFTA_SET_ELEM_FLAGS)]
+    pub flags: u32,
+}
+
+impl SetElement {
+    /// Sets the time after which the kernel removes the element from the set, which overrides
+    /// the timeout of the set (see [`SetBuilder::with_timeout`]).
+    pub fn with_ttl(self, ttl: Duration) -> Self {
+        self.with_timeout(ttl.as_millis() as u64)
+    }
+
+    /// The time left before the element expires, if the element was listed from a set with
+    /// timeouts (see [`list_set_elements`]).
+    pub fn expires_in(&self) -> Option<Duration> {
+        self.expiration.map(Duration::from_millis)
+    }
+
+    /// The element ending a range of keys in an interval set, see [`SetElementList::add_range`].
+    /// `key` is the first key after the range.
+    pub fn interval_end(key: impl Into<Vec<u8>>) -> Self {
+        SetElement::default()
+            .with_key(NfNetlinkData::default().with_value(key.into()))
+            .with_flags(NFT_SET_ELEM_INTERVAL_END)
+    }
+
+    /// Whether the element ends a range of keys rather than starting one.
+    pub fn is_interval_end(&self) -> bool {
+        self.flags
+            .is_some_and(|flags| flags & NFT_SET_ELEM_INTERVAL_END != 0)
+    }
+
+    /// The element of a verdict map associating `verdict` to `key`.
+    pub fn verdict_mapping<K: DataType>(key: &K, verdict: VerdictKind) -> Self {
+        SetElement::default()
+            .with_key(NfNetlinkData::default().with_value(key.data()))
+            .with_data(NfNetlinkData::default().with_verdict(verdict))
+    }
+
+    /// Returns the counter attached to this element, if any.
+    ///
+    /// The kernel reports the expression of elements holding a single expression in
+    /// `NFTA_SET_ELEM_EXPR`, which is the only attribute inspected here.
+    pub fn counter(&self) -> Option<&Counter> {
+        match self.get_expr()?.get_data() {
+            Some(ExpressionVariant::Counter(counter)) => Some(counter),
+            _ => None,
+        }
+    }
 }
 
 type SetElementListElements = NfNetlinkList<SetElement>;
+
+/// Lists the named sets (and maps) of `table`. The anonymous sets of the rules are left out.
+#[cfg(feature = "socket")]
+pub fn list_sets_for_table(table: &Table) -> Result<Vec<Set>, QueryError> {
+    let table_name = table.get_name().ok_or(BuilderError::MissingTableName)?;
+    let filter = Set::default()
+        .with_family(table.get_family())
+        .with_table(table_name);
+    let mut result = Vec::new();
+    list_objects_with_data(
+        crate::sys::NFT_MSG_GETSET as u16,
+        &|set: Set, sets: &mut Vec<Set>| {
+            let anonymous = set.flags.unwrap_or(0) & NFT_SET_ANONYMOUS != 0;
+            if set.table.as_ref() == Some(table_name) && !anonymous {
+                sets.push(set);
+            }
+            Ok(())
+        },
+        Some(&filter),
+        &mut result,
+    )?;
+    Ok(result)
+}
+
+/// Lists the elements of `set`, along with the expressions attached to them.
+#[cfg(feature = "socket")]
+pub fn list_set_elements(set: &Set) -> Result<Vec<SetElement>, QueryError> {
+    let filter = SetElementList {
+        elements: None,
+        ..SetElementList::for_set(set)?
+    };
+    let mut result = Vec::new();
+    list_objects_with_data(
+        crate::sys::NFT_MSG_GETSETELEM as u16,
+        &|list: SetElementList, elements: &mut Vec<SetElement>| {
+            if let Some(list_elements) = list.elements {
+                elements.extend(list_elements.iter().cloned());
+            }
+            Ok(())
+        },
+        Some(&filter),
+        &mut result,
+    )?;
+    Ok(result)
+}
+
+/// Reads the per-element counters of `set`, returning a `(key, packets, bytes)` tuple for every
+/// element that holds a [`Counter`].
+///
+/// This requires the elements to have been created with a counter attached to them, either with
+/// [`SetBuilder::add_with_expr`] or through a counter in the [`Set`] expression.
+#[cfg(feature = "socket")]
+pub fn read_set_counters(set: &Set) -> Result<Vec<(Vec<u8>, u64, u64)>, QueryError> {
+    Ok(list_set_elements(set)?
+        .iter()
+        .filter_map(|elem| {
+            let key = elem.get_key()?.get_value()?.clone();
+            let counter = elem.counter()?;
+            Some((
+                key,
+                counter.nb_packets.unwrap_or(0),
+                counter.nb_bytes.unwrap_or(0),
+            ))
+        })
+        .collect())
+}
@@ -0,0 +1,194 @@
+//! Codec of the userdata nft attaches to sets.
+//!
+//! The kernel stores `NFTA_SET_USERDATA` opaquely, and nft uses it to remember how to display
+//! the set: the byte order of the keys and values, whether adjacent intervals are merged, and
+//! the expressions of `typeof` declarations. The userdata is a sequence of TLVs, each made of a
+//! one-byte type, a one-byte length and the value, without padding.
+
+use std::convert::TryInto;
+
+use crate::error::{BuilderError, DecodeError};
+
+// Types of the TLVs, as found in `enum udata_set_type` in libnftnl's
+// `include/libnftnl/udata.h`.
+const UDATA_SET_KEYBYTEORDER: u8 = 0;
+const UDATA_SET_DATABYTEORDER: u8 = 1;
+const UDATA_SET_MERGE_ELEMENTS: u8 = 2;
+const UDATA_SET_KEY_TYPEOF: u8 = 3;
+const UDATA_SET_DATA_TYPEOF: u8 = 4;
+const UDATA_SET_COMMENT: u8 = 7;
+
+// Types of the TLVs nested in the `typeof` TLVs.
+const UDATA_SET_TYPEOF_EXPR: u8 = 0;
+const UDATA_SET_TYPEOF_DATA: u8 = 1;
+
+// Values of `enum byteorder` in nftables' `include/expression.h`.
+const BYTEORDER_HOST_ENDIAN: u32 = 1;
+const BYTEORDER_BIG_ENDIAN: u32 = 2;
+
+/// The byte order of the keys or of the values of a set.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Endianness {
+    /// The byte order of the host, e.g. for packet marks.
+    Host,
+    /// Network byte order, e.g. for addresses and ports.
+    Big,
+}
+
+/// An expression of a `typeof` declaration (e.g. `typeof ip saddr`), which nft uses to display
+/// the keys or the values of the set.
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TypeofExpr {
+    /// The name of the expression, e.g. `payload`.
+    pub name: String,
+    /// The description of the expression, which depends on its type. It is kept undecoded.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_helpers::hex"))]
+    pub data: Vec<u8>,
+}
+
+/// The content of the userdata nft attaches to sets, see [`Set::get_userdata_tlvs`].
+///
+/// The TLVs this library doesn't know about are kept in `unknown`, and written back as is. The
+/// length of each TLV is stored on a single byte, so their values hold at most 255 bytes.
+///
+/// [`Set::get_userdata_tlvs`]: crate::Set::get_userdata_tlvs
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SetUserdata {
+    pub key_byteorder: Option<Endianness>,
+    pub data_byteorder: Option<Endianness>,
+    /// Whether nft merges the adjacent and overlapping intervals added to the set.
+    pub merge_elements: bool,
+    pub key_typeof: Option<TypeofExpr>,
+    pub data_typeof: Option<TypeofExpr>,
+    pub comment: Option<String>,
+    pub unknown: Vec<(u8, Vec<u8>)>,
+}
+
+impl SetUserdata {
+    /// Encodes the TLVs. Fails if a value is longer than 255 bytes, e.g. a long comment.
+    pub fn encode(&self) -> Result<Vec<u8>, BuilderError> {
+        let mut buf = Vec::new();
+        if let Some(byteorder) = self.key_byteorder {
+            put_byteorder(&mut buf, UDATA_SET_KEYBYTEORDER, byteorder)?;
+        }
+        if let Some(byteorder) = self.data_byteorder {
+            put_byteorder(&mut buf, UDATA_SET_DATABYTEORDER, byteorder)?;
+        }
+        if self.merge_elements {
+            put(&mut buf, UDATA_SET_MERGE_ELEMENTS, &1u32.to_ne_bytes())?;
+        }
+        if let Some(expr) = &self.key_typeof {
+            put_typeof(&mut buf, UDATA_SET_KEY_TYPEOF, expr)?;
+        }
+        if let Some(expr) = &self.data_typeof {
+            put_typeof(&mut buf, UDATA_SET_DATA_TYPEOF, expr)?;
+        }
+        if let Some(comment) = &self.comment {
+            put_str(&mut buf, UDATA_SET_COMMENT, comment)?;
+        }
+        for (ty, value) in &self.unknown {
+            put(&mut buf, *ty, value)?;
+        }
+        Ok(buf)
+    }
+
+    pub fn decode(buf: &[u8]) -> Result<Self, DecodeError> {
+        let mut res = SetUserdata::default();
+        for (ty, value) in tlvs(buf) {
+            let value = value?;
+            match ty {
+                UDATA_SET_KEYBYTEORDER => res.key_byteorder = Some(get_byteorder(value)?),
+                UDATA_SET_DATABYTEORDER => res.data_byteorder = Some(get_byteorder(value)?),
+                UDATA_SET_MERGE_ELEMENTS => res.merge_elements = get_u32(value)? != 0,
+                UDATA_SET_KEY_TYPEOF => res.key_typeof = Some(get_typeof(value)?),
+                UDATA_SET_DATA_TYPEOF => res.data_typeof = Some(get_typeof(value)?),
+                UDATA_SET_COMMENT => res.comment = Some(get_str(value)?),
+                _ => res.unknown.push((ty, value.to_vec())),
+            }
+        }
+        Ok(res)
+    }
+}
+
+/// Iterates over the TLVs of `buf`, as `(type, value)` pairs.
+fn tlvs(mut buf: &[u8]) -> impl Iterator<Item = (u8, Result<&[u8], DecodeError>)> {
+    std::iter::from_fn(move || {
+        let (&ty, rest) = buf.split_first()?;
+        let value = match rest.split_first() {
+            Some((&len, rest)) if rest.len() >= len as usize => {
+                let (value, rest) = rest.split_at(len as usize);
+                buf = rest;
+                Ok(value)
+            }
+            _ => {
+                buf = &[];
+                Err(DecodeError::InvalidDataSize)
+            }
+        };
+        Some((ty, value))
+    })
+}
+
+fn put(buf: &mut Vec<u8>, ty: u8, value: &[u8]) -> Result<(), BuilderError> {
+    // the length is written on a single byte, longer values cannot be stored by nft either
+    let len = u8::try_from(value.len()).map_err(|_| BuilderError::TooLongUserdataValue(ty))?;
+    buf.push(ty);
+    buf.push(len);
+    buf.extend_from_slice(value);
+    Ok(())
+}
+
+fn put_str(buf: &mut Vec<u8>, ty: u8, value: &str) -> Result<(), BuilderError> {
+    let mut bytes = value.as_bytes().to_vec();
+    bytes.push(0);
+    put(buf, ty, &bytes)
+}
+
+fn put_byteorder(buf: &mut Vec<u8>, ty: u8, byteorder: Endianness) -> Result<(), BuilderError> {
+    let value = match byteorder {
+        Endianness::Host => BYTEORDER_HOST_ENDIAN,
+        Endianness::Big => BYTEORDER_BIG_ENDIAN,
+    };
+    put(buf, ty, &value.to_ne_bytes())
+}
+
+fn put_typeof(buf: &mut Vec<u8>, ty: u8, expr: &TypeofExpr) -> Result<(), BuilderError> {
+    let mut nested = Vec::new();
+    put_str(&mut nested, UDATA_SET_TYPEOF_EXPR, &expr.name)?;
+    put(&mut nested, UDATA_SET_TYPEOF_DATA, &expr.data)?;
+    put(buf, ty, &nested)
+}
+
+fn get_u32(value: &[u8]) -> Result<u32, DecodeError> {
+    let bytes = value.try_into().map_err(|_| DecodeError::InvalidDataSize)?;
+    Ok(u32::from_ne_bytes(bytes))
+}
+
+fn get_str(value: &[u8]) -> Result<String, DecodeError> {
+    let value = value.strip_suffix(&[0]).unwrap_or(value);
+    Ok(String::from_utf8(value.to_vec())?)
+}
+
+fn get_byteorder(value: &[u8]) -> Result<Endianness, DecodeError> {
+    match get_u32(value)? {
+        BYTEORDER_HOST_ENDIAN => Ok(Endianness::Host),
+        BYTEORDER_BIG_ENDIAN => Ok(Endianness::Big),
+        v => Err(DecodeError::UnknownSetByteorder(v)),
+    }
+}
+
+fn get_typeof(value: &[u8]) -> Result<TypeofExpr, DecodeError> {
+    let mut expr = TypeofExpr::default();
+    for (ty, value) in tlvs(value) {
+        match ty {
+            UDATA_SET_TYPEOF_EXPR => expr.name = get_str(value?)?,
+            UDATA_SET_TYPEOF_DATA => expr.data = value?.to_vec(),
+            _ => {}
+        }
+    }
+    Ok(expr)
+}
@@ -1,3 +1,15 @@
 #![allow(non_camel_case_types, dead_code)]
 
 include!(concat!(env!("OUT_DIR"), "/sys.rs"));
+include!(concat!(env!("OUT_DIR"), "/constants.rs"));
+include!(concat!(env!("OUT_DIR"), "/fallback.rs"));
+
+/// Lists the `NFT_*` and `NFTA_*` constants of the kernel headers this crate was compiled
+/// against, along with their values. This tells which attributes, expressions and message types
+/// the compiled library can express, although the running kernel may support a different set.
+///
+/// The same list is written by the build script to `constants.txt` in the `OUT_DIR` of the
+/// crate, with one `NAME VALUE` pair per line.
+pub fn supported_attributes() -> &'static [(&'static str, i64)] {
+    SUPPORTED_ATTRIBUTES
+}
@@ -1,27 +1,36 @@
 use std::fmt::Debug;
 
-use rustables_macros::nfnetlink_struct;
+use rustables_macros::{nfnetlink_object, nfnetlink_struct};
 
+use crate::error::BuilderError;
+#[cfg(feature = "socket")]
 use crate::error::QueryError;
 use crate::nlmsg::NfNetlinkObject;
-use crate::sys::{
-    NFTA_TABLE_FLAGS, NFTA_TABLE_NAME, NFT_MSG_DELTABLE, NFT_MSG_GETTABLE, NFT_MSG_NEWTABLE,
-};
-use crate::{Batch, ProtocolFamily};
+use crate::sys::{NFTA_TABLE_FLAGS, NFTA_TABLE_NAME, NFT_MSG_DELTABLE, NFT_MSG_NEWTABLE};
+use crate::{Batch, MsgType, ProtocolFamily, Rule};
 
 /// Abstraction of a `nftnl_table`, the top level container in netfilter. A table has a protocol
 /// family and contains [`Chain`]s that in turn hold the rules.
 ///
 /// [`Chain`]: struct.Chain.html
 #[nfnetlink_struct(derive_deserialize = false)]
-#[derive(Default, PartialEq, Eq, Debug)]
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Table {
     family: ProtocolFamily,
     #[field(NFTA_TABLE_NAME)]
     name: String,
     #[field(NFTA_TABLE_FLAGS)]
     flags: u32,
-    #[field(optional = true, crate::sys::NFTA_TABLE_USERDATA)]
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, with = "crate::serde_helpers::option_hex")
+    )]
+    #[field(
+        optional = true,
+        stub_if_missing = true,
+        crate::sys::NFTA_TABLE_USERDATA
+    )]
     userdata: Vec<u8>,
 }
 
@@ -37,30 +46,53 @@ impl Table {
         batch.add(&self, crate::MsgType::Add);
         self
     }
+
+    /// Adds to `batch` a message deleting every rule of every chain of the table, while keeping
+    /// the table and its chains (the equivalent of `nft flush table`). The sets of the table are
+    /// left untouched.
+    pub fn flush(&self, batch: &mut Batch) -> Result<(), BuilderError> {
+        let rule = Rule::default()
+            .with_family(self.family)
+            .with_table(self.get_name().ok_or(BuilderError::MissingTableName)?);
+        batch.add(&rule, MsgType::Del);
+        Ok(())
+    }
 }
 
+#[nfnetlink_object(add = NFT_MSG_NEWTABLE, del = NFT_MSG_DELTABLE, family_field = family)]
 impl NfNetlinkObject for Table {
-    const MSG_TYPE_ADD: u32 = NFT_MSG_NEWTABLE;
-    const MSG_TYPE_DEL: u32 = NFT_MSG_DELTABLE;
-
-    fn get_family(&self) -> ProtocolFamily {
-        self.family
+    fn object_name(&self) -> Option<String> {
+        self.name.clone()
     }
 
-    fn set_family(&mut self, family: ProtocolFamily) {
-        self.family = family;
+    fn missing_attributes(&self, _msg_type: MsgType) -> Vec<&'static str> {
+        let mut missing = Vec::new();
+        // deleting a table without a name deletes all the tables of its family, which is only
+        // done on purpose by Batch::flush_ruleset, with an unchecked message
+        if self.name.is_none() {
+            missing.push("NFTA_TABLE_NAME");
+        }
+        missing
     }
 }
 
+#[cfg(feature = "socket")]
 pub fn list_tables() -> Result<Vec<Table>, QueryError> {
+    list_tables_for_family(ProtocolFamily::Unspec)
+}
+
+/// Lists the tables of `family` only, or of all the families with [`ProtocolFamily::Unspec`].
+/// The kernel skips the tables of the other families, rather than sending them to be discarded.
+#[cfg(feature = "socket")]
+pub fn list_tables_for_family(family: ProtocolFamily) -> Result<Vec<Table>, QueryError> {
     let mut result = Vec::new();
     crate::query::list_objects_with_data(
-        NFT_MSG_GETTABLE as u16,
+        crate::sys::NFT_MSG_GETTABLE as u16,
         &|table: Table, tables: &mut Vec<Table>| {
             tables.push(table);
             Ok(())
         },
-        None,
+        Some(&Table::new(family)),
         &mut result,
     )?;
     Ok(result)
@@ -0,0 +1,186 @@
+use crate::error::DecodeError;
+use crate::parser::{parse_nlmsg, NlMsg};
+use crate::{Chain, Obj, Rule, Set, Table};
+
+#[cfg(feature = "socket")]
+use crate::{
+    error::{BuilderError, QueryError},
+    nlmsg::{NfNetlinkAttribute, NfNetlinkObject},
+    query::{NfNetlinkSocket, QueryBuffer},
+    sys::{NFT_MSG_GETCHAIN, NFT_MSG_GETOBJ, NFT_MSG_GETRULE, NFT_MSG_GETSET},
+};
+
+/// How many times [`Table::dump_contents`] lists the table again when the ruleset changes while
+/// it is being listed.
+#[cfg(feature = "socket")]
+const MAX_DUMP_ATTEMPTS: usize = 10;
+
+/// The objects of a table, as listed by [`Table::dump_contents`] from a single generation of the
+/// ruleset.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct TableContents {
+    pub table: Table,
+    pub chains: Vec<Chain>,
+    pub sets: Vec<Set>,
+    pub objects: Vec<Obj>,
+    /// The rules of every chain, grouped by chain, in their order of evaluation.
+    pub rules: Vec<Rule>,
+}
+
+impl TableContents {
+    /// Returns the rules of `chain`, in their order of evaluation.
+    pub fn rules_of<'a>(&'a self, chain: &'a Chain) -> impl Iterator<Item = &'a Rule> + 'a {
+        self.rules
+            .iter()
+            .filter(move |rule| rule.get_chain().is_some() && rule.get_chain() == chain.get_name())
+    }
+
+    /// Returns the chain holding `rule`.
+    pub fn chain_of(&self, rule: &Rule) -> Option<&Chain> {
+        let name = rule.get_chain()?;
+        self.chains
+            .iter()
+            .find(|chain| chain.get_name() == Some(name))
+    }
+}
+
+/// Checks that the messages of one or more dumps were all produced from the same generation of
+/// the ruleset.
+///
+/// The kernel stores the 16 lower bits of the generation in the resource id of the messages of a
+/// dump. A change in the middle of a single dump is reported by the kernel with `NLM_F_DUMP_INTR`,
+/// which fails the decoding of the message with [`DecodeError::ConcurrentGenerationUpdate`].
+#[derive(Debug, Default)]
+#[cfg_attr(not(feature = "socket"), allow(dead_code))]
+pub(crate) struct GenerationCheck {
+    generation: Option<u16>,
+    changed: bool,
+}
+
+#[cfg_attr(not(feature = "socket"), allow(dead_code))]
+impl GenerationCheck {
+    /// Records the generation of the message at the start of `buf`.
+    pub(crate) fn record(&mut self, buf: &[u8]) -> Result<(), DecodeError> {
+        let (_, msg) = parse_nlmsg(buf)?;
+        if let NlMsg::NfGenMsg(genmsg, _) = msg {
+            let generation = u16::from_be(genmsg.res_id);
+            if *self.generation.get_or_insert(generation) != generation {
+                self.changed = true;
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether all the recorded messages came from the same generation.
+    pub(crate) fn is_consistent(&self) -> bool {
+        !self.changed
+    }
+}
+
+#[cfg(feature = "socket")]
+impl Table {
+    /// Lists the chains, sets, stateful objects and rules of this table on `sock`.
+    ///
+    /// Every type of object is listed with a single dump request. If the ruleset is modified
+    /// between two dumps, the objects are listed again, so the contents always come from a single
+    /// generation of the ruleset, unlike successive calls to [`list_chains_for_table`] and
+    /// [`list_rules_for_chain`]. This fails with [`DecodeError::ConcurrentGenerationUpdate`] if
+    /// the ruleset is modified in the middle of a dump, or after 10 attempts.
+    ///
+    /// [`list_chains_for_table`]: crate::list_chains_for_table
+    /// [`list_rules_for_chain`]: crate::list_rules_for_chain
+    pub fn dump_contents(&self, sock: &NfNetlinkSocket) -> Result<TableContents, QueryError> {
+        let name = self.get_name().ok_or(BuilderError::MissingTableName)?;
+        let mut buffer = QueryBuffer::new();
+        for _ in 0..MAX_DUMP_ATTEMPTS {
+            let mut check = GenerationCheck::default();
+            let chains = dump_with_check(
+                sock,
+                &mut buffer,
+                NFT_MSG_GETCHAIN,
+                &Chain::new(self),
+                &mut check,
+            )?;
+            let set_filter = Set::default()
+                .with_family(self.get_family())
+                .with_table(name);
+            let sets = dump_with_check(sock, &mut buffer, NFT_MSG_GETSET, &set_filter, &mut check)?;
+            let objects = dump_with_check(
+                sock,
+                &mut buffer,
+                NFT_MSG_GETOBJ,
+                &Obj::new(self)?,
+                &mut check,
+            )?;
+            let rule_filter = Rule::default()
+                .with_family(self.get_family())
+                .with_table(name);
+            let rules =
+                dump_with_check(sock, &mut buffer, NFT_MSG_GETRULE, &rule_filter, &mut check)?;
+            if !check.is_consistent() {
+                debug!("The ruleset changed while listing table {}, retrying", name);
+                continue;
+            }
+            // older kernels ignore the table filter of some dumps
+            let in_table = |table: Option<&String>| table == Some(name);
+            return Ok(TableContents {
+                table: self.clone(),
+                chains: chains
+                    .into_iter()
+                    .filter(|c| in_table(c.get_table()))
+                    .collect(),
+                sets: sets
+                    .into_iter()
+                    .filter(|s| in_table(s.get_table()))
+                    .collect(),
+                objects: objects
+                    .into_iter()
+                    .filter(|o| in_table(o.get_table()))
+                    .collect(),
+                rules: rules
+                    .into_iter()
+                    .filter(|r| in_table(r.get_table()))
+                    .collect(),
+            });
+        }
+        Err(DecodeError::ConcurrentGenerationUpdate.into())
+    }
+}
+
+/// Dumps the objects matching `filter` on `sock`, and records their generation in `check`.
+#[cfg(feature = "socket")]
+fn dump_with_check<T>(
+    sock: &NfNetlinkSocket,
+    buffer: &mut QueryBuffer,
+    msg_type: u32,
+    filter: &T,
+    check: &mut GenerationCheck,
+) -> Result<Vec<T>, QueryError>
+where
+    T: NfNetlinkObject + NfNetlinkAttribute,
+{
+    use crate::query::{get_list_of_objects_for_family, recv_and_process};
+
+    let seq = 0;
+    sock.send(&get_list_of_objects_for_family(
+        msg_type as u16,
+        filter.get_family(),
+        seq,
+        Some(filter),
+    )?)?;
+    let mut result = (Vec::new(), check);
+    recv_and_process(
+        sock,
+        buffer,
+        None,
+        Some(
+            &|buf: &[u8], (objects, check): &mut (Vec<T>, &mut GenerationCheck)| {
+                check.record(buf)?;
+                objects.push(T::deserialize(buf)?.0);
+                Ok(())
+            },
+        ),
+        &mut result,
+    )?;
+    Ok(result.0)
+}
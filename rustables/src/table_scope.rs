@@ -0,0 +1,82 @@
+use crate::error::BuilderError;
+use crate::nlmsg::NfNetlinkObject;
+use crate::{Batch, Chain, Flowtable, MsgType, Obj, Rule, Set, Table};
+
+impl Batch {
+    /// Adds objects to the table `table`, through the [`TableScope`] handed to `f`.
+    ///
+    /// The scope fills the family and the table of the objects it adds (and the chain of the
+    /// rules), so they cannot be forgotten or mismatched:
+    ///
+    /// ```ignore
+    /// batch.in_table(&table, |t| {
+    ///     let chain = t.add_chain(Chain::default().with_name("input"))?;
+    ///     t.add_rule(&chain, Rule::default().dport(22, Protocol::TCP).accept())?;
+    ///     Ok(())
+    /// })?;
+    /// ```
+    ///
+    /// The table itself is not added to the batch.
+    pub fn in_table<R>(
+        &mut self,
+        table: &Table,
+        f: impl FnOnce(&mut TableScope<'_>) -> Result<R, BuilderError>,
+    ) -> Result<R, BuilderError> {
+        let mut scope = TableScope {
+            batch: self,
+            table: table.get_name().ok_or(BuilderError::MissingTableName)?,
+            family: table.get_family(),
+        };
+        f(&mut scope)
+    }
+}
+
+/// Adds objects to a batch on behalf of a table, see [`Batch::in_table`].
+///
+/// The objects are checked with [`Batch::add_checked`], and are returned with their table
+/// filled, to be used as the parent of other objects.
+pub struct TableScope<'a> {
+    batch: &'a mut Batch,
+    table: &'a String,
+    family: crate::ProtocolFamily,
+}
+
+impl TableScope<'_> {
+    fn add<T: NfNetlinkObject>(&mut self, mut obj: T) -> Result<T, BuilderError> {
+        obj.set_family(self.family);
+        self.batch.add_checked(&obj, MsgType::Add)?;
+        Ok(obj)
+    }
+
+    pub fn add_chain(&mut self, chain: Chain) -> Result<Chain, BuilderError> {
+        let chain = chain.with_table(self.table);
+        chain.validate()?;
+        self.add(chain)
+    }
+
+    /// Adds `rule` to the end of `chain`, which must belong to the table of the scope.
+    pub fn add_rule(&mut self, chain: &Chain, rule: Rule) -> Result<Rule, BuilderError> {
+        if chain.get_table().is_some_and(|table| table != self.table) {
+            return Err(BuilderError::ChainTableMismatch);
+        }
+        let name = chain
+            .get_name()
+            .ok_or(BuilderError::MissingChainInformationError)?;
+        self.add(rule.with_table(self.table).with_chain(name))
+    }
+
+    pub fn add_set(&mut self, set: Set) -> Result<Set, BuilderError> {
+        set.validate()?;
+        self.add(set.with_table(self.table))
+    }
+
+    pub fn add_obj(&mut self, obj: Obj) -> Result<Obj, BuilderError> {
+        self.add(obj.with_table(self.table))
+    }
+
+    pub fn add_flowtable(&mut self, flowtable: Flowtable) -> Result<Flowtable, BuilderError> {
+        let flowtable = flowtable.with_table(self.table);
+        flowtable.validate()?;
+        self.add(flowtable)
+    }
+}
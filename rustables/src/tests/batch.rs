@@ -1,14 +1,27 @@
 use std::mem::size_of;
 
+use libc::NFNL_MSG_BATCH_END;
 use libc::{AF_UNSPEC, NFNL_MSG_BATCH_BEGIN, NLM_F_REQUEST};
-use nix::libc::NFNL_MSG_BATCH_END;
+use rustables_macros::{nfnetlink_object, nfnetlink_struct};
 
-use crate::nlmsg::{pad_netlink_object_with_variable_size, NfNetlinkDeserializable};
-use crate::parser::{parse_nlmsg, NlMsg};
-use crate::sys::{nfgenmsg, nlmsghdr, NFNETLINK_V0, NFNL_SUBSYS_NFTABLES, NLM_F_ACK};
-use crate::{Batch, MsgType, Table};
+use crate::error::{BuilderError, DecodeError};
+use crate::expr::Immediate;
+use crate::nlmsg::{
+    get_operation_from_nlmsghdr_type, pad_netlink_object_with_variable_size,
+    NfNetlinkDeserializable, NfNetlinkObject,
+};
+use crate::parser::{get_nlmsghdr, parse_nlmsg, parse_response_stream, NlMsg};
+use crate::sys::{
+    nfgenmsg, nlmsghdr, NFNETLINK_V0, NFNL_SUBSYS_NFTABLES, NFTA_TABLE_NAME, NFT_MSG_DELCHAIN,
+    NFT_MSG_DELRULE, NFT_MSG_DELTABLE, NFT_MSG_NEWCHAIN, NFT_MSG_NEWRULE, NFT_MSG_NEWTABLE,
+    NLMSG_DONE, NLM_F_ACK, NLM_F_MULTI,
+};
+use crate::{
+    AckMode, Batch, BatchMarker, Chain, Hook, MsgType, ProtocolFamily, Rule, SequenceProgress,
+    Table, Transaction, TransactionSequence,
+};
 
-use super::get_test_table;
+use super::{get_test_chain, get_test_rule, get_test_table, CHAIN_NAME, TABLE_NAME};
 
 const HEADER_SIZE: u32 =
     pad_netlink_object_with_variable_size(size_of::<nlmsghdr>() + size_of::<nfgenmsg>()) as u32;
@@ -24,7 +37,7 @@ const DEFAULT_BATCH_MSG: NlMsg = NlMsg::NfGenMsg(
     nfgenmsg {
         nfgen_family: AF_UNSPEC as u8,
         version: NFNETLINK_V0 as u8,
-        res_id: NFNL_SUBSYS_NFTABLES as u16,
+        res_id: (NFNL_SUBSYS_NFTABLES as u16).to_be(),
     },
     &[],
 );
@@ -94,3 +107,711 @@ fn batch_with_objects() {
     assert_eq!(hdr, end_hdr);
     assert_eq!(msg, DEFAULT_BATCH_MSG);
 }
+
+#[test]
+fn transaction_rollback_is_reversed() {
+    let table = get_test_table();
+    let chain = get_test_chain();
+
+    let mut transaction = Transaction::new();
+    transaction.add(&table, MsgType::Add).unwrap();
+    transaction.add(&chain, MsgType::Del).unwrap();
+    let buf = transaction.rollback().to_batch().finalize();
+
+    let (hdr, _msg) = parse_nlmsg(&buf).expect("Invalid nlmsg message");
+    assert_eq!(hdr, DEFAULT_BATCH_BEGIN_HDR);
+    let mut remaining_data = &buf[pad_netlink_object_with_variable_size(hdr.nlmsg_len as usize)..];
+
+    // the chain deletion is reverted first
+    let (hdr, _msg) = parse_nlmsg(remaining_data).expect("Invalid nlmsg message");
+    assert_eq!(
+        get_operation_from_nlmsghdr_type(hdr.nlmsg_type),
+        NFT_MSG_NEWCHAIN as u8
+    );
+    let (deserialized_chain, rest) =
+        Chain::deserialize(remaining_data).expect("could not deserialize a chain");
+    assert_eq!(deserialized_chain, chain);
+    remaining_data = rest;
+
+    let (hdr, _msg) = parse_nlmsg(remaining_data).expect("Invalid nlmsg message");
+    assert_eq!(
+        get_operation_from_nlmsghdr_type(hdr.nlmsg_type),
+        NFT_MSG_DELTABLE as u8
+    );
+    let (deserialized_table, rest) =
+        Table::deserialize(remaining_data).expect("could not deserialize a table");
+    assert_eq!(deserialized_table, table);
+    remaining_data = rest;
+
+    let (hdr, _msg) = parse_nlmsg(remaining_data).expect("Invalid nlmsg message");
+    let mut end_hdr = DEFAULT_BATCH_END_HDR;
+    end_hdr.nlmsg_seq = 3;
+    assert_eq!(hdr, end_hdr);
+}
+
+#[test]
+fn transaction_rule_rollback() {
+    // the inverse of a rule addition would delete every rule of the chain
+    let mut transaction = Transaction::new();
+    for msg_type in [MsgType::Add, MsgType::Insert] {
+        assert!(matches!(
+            transaction.add(&get_test_rule(), msg_type),
+            Err(BuilderError::IrreversibleOperation(t, "rule")) if t == msg_type
+        ));
+    }
+    transaction.add_without_rollback(&get_test_rule(), MsgType::Add);
+    assert!(transaction.rollback().to_batch().is_empty());
+
+    // a deleted rule is added back, without its former handle
+    let rule = get_test_rule().with_handle(42u64);
+    transaction.add(&rule, MsgType::Del).unwrap();
+    let buf = transaction.rollback().to_batch().finalize();
+    let hdr = get_nlmsghdr(&buf).unwrap();
+    let inverse = &buf[pad_netlink_object_with_variable_size(hdr.nlmsg_len as usize)..];
+    let hdr = get_nlmsghdr(inverse).unwrap();
+    assert_eq!(
+        get_operation_from_nlmsghdr_type(hdr.nlmsg_type),
+        NFT_MSG_NEWRULE as u8
+    );
+    let (inverse, _) = Rule::deserialize(inverse).unwrap();
+    assert_eq!(inverse, get_test_rule());
+}
+
+#[test]
+fn transaction_replace_rollback() {
+    let previous = get_test_rule().with_handle(42u64).accept();
+    let replacement = get_test_rule().with_handle(42u64).drop();
+
+    // the replaced rule must be known
+    let mut transaction = Transaction::new();
+    assert!(matches!(
+        transaction.add(&replacement, MsgType::Replace),
+        Err(BuilderError::IrreversibleOperation(
+            MsgType::Replace,
+            "rule"
+        ))
+    ));
+    assert!(matches!(
+        transaction.replace(&get_test_rule(), &replacement),
+        Err(BuilderError::MissingAttributes(missing)) if missing == ["NFTA_RULE_HANDLE"]
+    ));
+    assert!(transaction.rollback().to_batch().is_empty());
+
+    // the previous rule replaces the new one back
+    transaction.replace(&previous, &replacement).unwrap();
+    let mut expected = Batch::new();
+    expected.add(&previous, MsgType::Replace);
+    assert_eq!(
+        transaction.rollback().to_batch().finalize(),
+        expected.finalize()
+    );
+}
+
+#[test]
+fn transaction_sequence_before_sending() {
+    let mut first = Transaction::new();
+    first.add(&get_test_table(), MsgType::Add).unwrap();
+    let mut second = Transaction::new();
+    second.add(&get_test_chain(), MsgType::Add).unwrap();
+
+    let mut sequence = TransactionSequence::new();
+    assert!(sequence.is_empty() && sequence.is_complete());
+    sequence.push(first);
+    sequence.push(second);
+    assert_eq!(sequence.len(), 2);
+    assert_eq!(
+        sequence.progress(),
+        SequenceProgress {
+            committed: 0,
+            total: 2
+        }
+    );
+    assert!(!sequence.is_complete());
+    // nothing was committed, so there is nothing to compensate
+    assert!(sequence.rollback().to_batch().is_empty());
+
+    // the sequence sends a copy of the finalized batches, which stay available for a retry
+    let mut batch = Batch::new();
+    batch.add(&get_test_table(), MsgType::Add);
+    let copy = batch.finalized_copy();
+    assert_eq!(copy, batch.finalized_copy());
+    assert_eq!(copy, batch.finalize());
+}
+
+#[test]
+fn append_batches_built_in_parallel() {
+    let table = get_test_table();
+    let chain = get_test_chain();
+
+    let mut expected = Batch::new();
+    expected.add(&table, MsgType::Add);
+    expected.add(&chain, MsgType::Add);
+    expected.add(&chain, MsgType::Del);
+
+    let tables = {
+        let table = table.clone();
+        std::thread::spawn(move || {
+            let mut batch = Batch::new();
+            batch.add(&table, MsgType::Add);
+            batch
+        })
+    };
+    let chains = std::thread::spawn(move || {
+        let mut batch = Batch::new();
+        batch.add(&chain, MsgType::Add);
+        batch.add(&chain, MsgType::Del);
+        batch
+    });
+
+    let mut batch = Batch::new();
+    batch.append(tables.join().unwrap()).unwrap();
+    batch.append(chains.join().unwrap()).unwrap();
+    assert_eq!(batch.finalize(), expected.finalize());
+}
+
+#[test]
+fn append_batches_with_ids() {
+    let mut rules = Batch::new();
+    let first = rules.add_rule(get_test_rule());
+    let mut chains = Batch::new();
+    chains.add_chain(get_test_chain()).unwrap();
+
+    // the ids of the rules follow the ones of the appended batch
+    let mut batch = Batch::new();
+    batch.append(rules).unwrap();
+    assert_eq!(batch.add_rule(get_test_rule()).get_id(), Some(&2));
+    batch.append(chains).unwrap();
+    let chain = batch
+        .add_chain(get_test_chain().with_name("other"))
+        .unwrap();
+    assert_eq!(chain.get_id(), Some(&2));
+    assert!(batch.insert_after(&first, get_test_rule()).is_ok());
+
+    let mut other = Batch::new();
+    other.add_rule(get_test_rule());
+    let len = batch.objects().len();
+    assert!(matches!(
+        batch.append(other),
+        Err(BuilderError::ConflictingBatchIds)
+    ));
+    assert_eq!(batch.objects().len(), len);
+}
+
+#[test]
+fn batch_objects_identify_messages() {
+    let mut rules = Batch::new();
+    rules.add(&get_test_rule(), MsgType::Add);
+
+    let mut batch = Batch::new();
+    batch.add(&get_test_table(), MsgType::Add);
+    batch.add(&get_test_chain(), MsgType::Del);
+    batch.append(rules).unwrap();
+
+    let objects = batch.objects();
+    assert_eq!(objects.len(), 3);
+    assert_eq!(
+        objects.iter().map(|o| (o.index, o.seq)).collect::<Vec<_>>(),
+        [(0, 1), (1, 2), (2, 3)]
+    );
+    assert_eq!(objects[0].kind(), "table");
+    assert_eq!(objects[0].name.as_deref(), Some(TABLE_NAME));
+    assert_eq!(objects[1].msg_type, NFT_MSG_DELCHAIN as u16);
+    assert_eq!(
+        objects[1].to_string(),
+        format!("deletion of chain {:?} (object 1 of the batch)", CHAIN_NAME)
+    );
+    assert_eq!(objects[2].kind(), "rule");
+    assert_eq!(objects[2].name, None);
+
+    // the errors of the kernel designate the messages by their sequence number
+    #[cfg(feature = "socket")]
+    {
+        use crate::batch::identify_refused_object;
+        use crate::error::{NetlinkErrorReport, QueryError};
+        use crate::sys::nlmsgerr;
+
+        let mut msg = DEFAULT_BATCH_BEGIN_HDR;
+        msg.nlmsg_seq = 2;
+        let report = NetlinkErrorReport {
+            err: nlmsgerr {
+                error: libc::EBUSY,
+                msg,
+            },
+            message: None,
+            bad_attr_offset: None,
+            bad_attr_name: None,
+        };
+        let error = identify_refused_object(QueryError::NetlinkError(report.clone()), objects);
+        assert!(matches!(
+            &error,
+            QueryError::BatchObjectRefused { object, .. } if *object == objects[1]
+        ));
+        assert_eq!(error.netlink_report(), Some(&report));
+
+        msg.nlmsg_seq = 10;
+        let report = NetlinkErrorReport {
+            err: nlmsgerr {
+                error: libc::EBUSY,
+                msg,
+            },
+            ..report
+        };
+        let error = identify_refused_object(QueryError::NetlinkError(report), objects);
+        assert!(matches!(error, QueryError::NetlinkError(_)));
+    }
+}
+
+#[test]
+#[cfg(feature = "socket")]
+fn echoed_objects() {
+    use crate::batch::{set_echo_flags, CreatedObject};
+    use crate::sys::NLM_F_ECHO;
+
+    let mut batch = Batch::new();
+    batch.add(&get_test_table(), MsgType::Add);
+    batch.add(&get_test_rule(), MsgType::Add);
+    batch.add(&get_test_chain(), MsgType::Del);
+    let objects = batch.objects().to_vec();
+
+    let mut buf = batch.finalize();
+    set_echo_flags(&mut buf);
+    let mut echoed = Vec::new();
+    let mut remaining = &buf[..];
+    while let Ok((hdr, _)) = parse_nlmsg(remaining) {
+        echoed.push(hdr.nlmsg_flags & NLM_F_ECHO as u16 != 0);
+        remaining = &remaining[pad_netlink_object_with_variable_size(hdr.nlmsg_len as usize)..];
+    }
+    assert_eq!(echoed, [false, true, true, true, false]);
+
+    // the kernel echoes the rule with its handle, under the sequence number of its message
+    let rule = get_test_rule().with_handle(7u64);
+    let echo = rule.to_message_with_flags(MsgType::Replace, objects[1].seq, 0);
+    let created = CreatedObject::from_echo(&echo, &objects).unwrap().unwrap();
+    assert_eq!(created.object, objects[1]);
+    assert_eq!(created.handle, Some(7));
+    assert_eq!(Rule::deserialize(&created.message).unwrap().0, rule);
+
+    let echo = get_test_table().to_batch_message(MsgType::Add, objects[0].seq);
+    let created = CreatedObject::from_echo(&echo, &objects).unwrap().unwrap();
+    assert_eq!(created.handle, None);
+
+    let echo = get_test_chain().to_batch_message(MsgType::Del, objects[2].seq);
+    assert_eq!(CreatedObject::from_echo(&echo, &objects).unwrap(), None);
+}
+
+#[test]
+fn batch_res_id() {
+    let batch = Batch::new();
+    assert_eq!(batch.res_id(), NFNL_SUBSYS_NFTABLES as u16);
+
+    // batches of other subsystems are rejected when decoding
+    let mut buf = Batch::with_res_id(NFNL_SUBSYS_NFTABLES as u16 + 1).finalize();
+    assert!(matches!(
+        parse_nlmsg(&buf),
+        Err(DecodeError::InvalidResourceId(res_id)) if res_id == NFNL_SUBSYS_NFTABLES as u16 + 1
+    ));
+
+    // the host byte order used by older versions of nft is still accepted
+    let offset = size_of::<nlmsghdr>() + 2;
+    buf[offset..offset + 2].copy_from_slice(&(NFNL_SUBSYS_NFTABLES as u16).to_ne_bytes());
+    assert!(parse_nlmsg(&buf).is_ok());
+}
+
+#[test]
+fn flush_table_and_chain() {
+    let table = get_test_table();
+    let chain = get_test_chain();
+
+    let mut batch = Batch::new();
+    table.flush(&mut batch).unwrap();
+    chain.flush(&mut batch).unwrap();
+    let buf = batch.finalize();
+
+    let mut flushed = Vec::new();
+    for res in parse_response_stream::<Rule>(&buf) {
+        let (hdr, rule) = res.expect("could not deserialize a rule");
+        assert_eq!(
+            get_operation_from_nlmsghdr_type(hdr.nlmsg_type),
+            NFT_MSG_DELRULE as u8
+        );
+        flushed.push(rule);
+    }
+    assert_eq!(flushed.len(), 2);
+
+    // a rule deletion without a chain nor a handle targets the whole table
+    assert_eq!(flushed[0].get_table(), table.get_name());
+    assert_eq!(flushed[0].get_chain(), None);
+    assert_eq!(flushed[0].get_handle(), None);
+    assert_eq!(flushed[1], Rule::new(&chain).unwrap());
+
+    assert!(matches!(
+        Table::new(table.get_family()).flush(&mut Batch::new()),
+        Err(BuilderError::MissingTableName)
+    ));
+}
+
+#[test]
+fn batch_add_checked() {
+    let mut batch = Batch::new();
+    batch.add_checked(&get_test_chain(), MsgType::Add).unwrap();
+
+    let chain = Chain::new(&Table::new(ProtocolFamily::Inet)).with_hook(Hook::default());
+    match batch.add_checked(&chain, MsgType::Add) {
+        Err(BuilderError::MissingAttributes(missing)) => assert_eq!(
+            missing,
+            [
+                "NFTA_CHAIN_TABLE",
+                "NFTA_CHAIN_NAME",
+                "NFTA_HOOK_HOOKNUM",
+                "NFTA_HOOK_PRIORITY"
+            ]
+        ),
+        res => panic!("unexpected result: {:?}", res),
+    }
+
+    // rules can be deleted without a chain, but not added
+    let rule = Rule::default().with_table(TABLE_NAME);
+    batch.add_checked(&rule, MsgType::Del).unwrap();
+    assert!(matches!(
+        batch.add_checked(&rule, MsgType::Add),
+        Err(BuilderError::MissingAttributes(missing)) if missing == ["NFTA_RULE_CHAIN"]
+    ));
+
+    // only rules can be replaced
+    assert!(matches!(
+        batch.add_checked(&get_test_table(), MsgType::Replace),
+        Err(BuilderError::UnsupportedOperation(
+            MsgType::Replace,
+            "table"
+        ))
+    ));
+
+    // the objects that failed the check are not added
+    let mut expected = Batch::new();
+    expected.add(&get_test_chain(), MsgType::Add);
+    expected.add(&rule, MsgType::Del);
+    assert_eq!(batch.finalize(), expected.finalize());
+}
+
+#[test]
+fn batch_with_small_capacity_grows() {
+    let mut batch = Batch::with_capacity(0);
+    let mut expected = Batch::new();
+    assert!(batch.is_empty());
+    for _ in 0..100 {
+        batch.add(&get_test_chain(), MsgType::Add);
+        expected.add(&get_test_chain(), MsgType::Add);
+    }
+    assert!(!batch.is_empty());
+    assert_eq!(batch.len(), 100);
+    assert!(batch.capacity() >= batch.size());
+    assert_eq!(batch.size(), expected.size());
+    assert_eq!(batch.finalize(), expected.finalize());
+}
+
+#[test]
+fn batch_in_table_scope() {
+    let table = get_test_table();
+    let mut batch = Batch::new();
+    let rule = batch
+        .in_table(&table, |t| {
+            let chain = t.add_chain(Chain::default().with_name(CHAIN_NAME))?;
+            t.add_rule(&chain, Rule::default().accept())
+        })
+        .unwrap();
+    assert_eq!(rule, get_test_rule().accept());
+
+    let mut expected = Batch::new();
+    expected.add(&get_test_chain(), MsgType::Add);
+    expected.add(&get_test_rule().accept(), MsgType::Add);
+    assert_eq!(batch.finalize(), expected.finalize());
+
+    // the chains of other tables are rejected
+    let other_chain =
+        Chain::new(&Table::new(ProtocolFamily::Inet).with_name("other")).with_name(CHAIN_NAME);
+    let mut batch = Batch::new();
+    assert!(matches!(
+        batch.in_table(&table, |t| t.add_rule(&other_chain, Rule::default())),
+        Err(BuilderError::ChainTableMismatch)
+    ));
+    assert!(matches!(
+        batch.in_table(&Table::new(ProtocolFamily::Inet), |_| Ok(())),
+        Err(BuilderError::MissingTableName)
+    ));
+    assert!(batch.is_empty());
+}
+
+#[test]
+fn single_object_messages() {
+    let table = get_test_table();
+    let mut batch = Batch::new();
+    batch.add(&table, MsgType::Add);
+    let buf = batch.finalize();
+
+    let msg = table.to_batch_message(MsgType::Add, 1);
+    let start = HEADER_SIZE as usize;
+    assert_eq!(&buf[start..start + msg.len()], msg.as_slice());
+
+    let msg = table.to_message_with_flags(MsgType::Del, 7, 0);
+    let (hdr, _) = parse_nlmsg(&msg).expect("Invalid nlmsg message");
+    assert_eq!(hdr.nlmsg_flags, NLM_F_REQUEST as u16);
+    assert_eq!(hdr.nlmsg_seq, 7);
+    assert_eq!(
+        get_operation_from_nlmsghdr_type(hdr.nlmsg_type),
+        NFT_MSG_DELTABLE as u8
+    );
+    assert_eq!(hdr.nlmsg_len as usize, msg.len());
+}
+
+#[test]
+fn insert_after_uncommitted_rules() {
+    let chain = get_test_chain();
+    let mut batch = Batch::new();
+    let first = batch.add_rule(Rule::new(&chain).unwrap().accept());
+    let second = batch
+        .insert_after(&first, Rule::new(&chain).unwrap().drop())
+        .unwrap();
+    assert_eq!(first.get_id(), Some(&1));
+    assert_eq!(second.get_id(), Some(&2));
+    assert_eq!(second.get_position_id(), first.get_id());
+
+    // committed rules are referenced by their handle
+    let listed = Rule::new(&chain).unwrap().with_handle(42u64);
+    let third = batch
+        .insert_after(&listed, Rule::new(&chain).unwrap())
+        .unwrap();
+    assert_eq!(third.get_position(), Some(&42));
+    assert_eq!(third.get_position_id(), None);
+
+    assert!(matches!(
+        batch.insert_after(&Rule::new(&chain).unwrap(), Rule::new(&chain).unwrap()),
+        Err(BuilderError::UnknownRulePosition)
+    ));
+    let other_chain = Chain::new(&get_test_table()).with_name("other");
+    assert!(matches!(
+        batch.insert_after(&first, Rule::new(&other_chain).unwrap()),
+        Err(BuilderError::RuleChainMismatch)
+    ));
+
+    let mut expected = Batch::new();
+    expected.add(&first, MsgType::Add);
+    expected.add(&second, MsgType::Add);
+    expected.add(&third, MsgType::Add);
+    assert_eq!(batch.finalize(), expected.finalize());
+}
+
+#[test]
+fn insert_before_rules() {
+    let chain = get_test_chain();
+    let mut batch = Batch::new();
+    let last = batch.add_rule(Rule::new(&chain).unwrap().drop());
+    let first = batch
+        .insert_before(&last, Rule::new(&chain).unwrap().accept())
+        .unwrap();
+    assert_eq!(first.get_id(), Some(&2));
+    assert_eq!(first.get_position_id(), last.get_id());
+
+    let listed = Rule::new(&chain).unwrap().with_handle(42u64);
+    batch.add(&listed, MsgType::Replace);
+
+    let operations: Vec<MsgType> = batch.objects().iter().map(|o| o.operation()).collect();
+    assert_eq!(
+        operations,
+        [MsgType::Add, MsgType::Insert, MsgType::Replace]
+    );
+    assert_eq!(
+        batch.objects()[1].to_string(),
+        "insertion of rule (object 1 of the batch)"
+    );
+    assert!(matches!(
+        batch.insert_before(&Rule::new(&chain).unwrap(), Rule::new(&chain).unwrap()),
+        Err(BuilderError::UnknownRulePosition)
+    ));
+}
+
+#[test]
+fn flush_ruleset_before_new_tables() {
+    let mut batch = Batch::new();
+    batch.flush_ruleset(ProtocolFamily::Unspec);
+    batch
+        .add_checked(&get_test_table(), MsgType::Add)
+        .expect("the table is complete");
+    let buf = batch.finalize();
+
+    let mut messages = Vec::new();
+    for res in parse_response_stream::<Table>(&buf) {
+        let (hdr, table) = res.expect("could not deserialize a table");
+        messages.push((get_operation_from_nlmsghdr_type(hdr.nlmsg_type), table));
+    }
+    assert_eq!(messages.len(), 2);
+    assert_eq!(messages[0].0, NFT_MSG_DELTABLE as u8);
+    assert_eq!(messages[0].1, Table::new(ProtocolFamily::Unspec));
+    assert_eq!(messages[1].0, NFT_MSG_NEWTABLE as u8);
+    assert_eq!(messages[1].1, get_test_table());
+
+    // the checked deletions of unnamed tables would delete all the tables of their family
+    let unnamed = Table::new(ProtocolFamily::Inet);
+    for msg_type in [MsgType::Add, MsgType::Del] {
+        assert_eq!(
+            unnamed.missing_attributes(msg_type),
+            vec!["NFTA_TABLE_NAME"]
+        );
+    }
+    assert!(Batch::new().add_checked(&unnamed, MsgType::Del).is_err());
+}
+
+/// A table described declaratively, without any handwritten method.
+#[derive(Debug, Default)]
+#[nfnetlink_object(add = NFT_MSG_NEWTABLE, del = NFT_MSG_DELTABLE, family_field = family)]
+#[nfnetlink_struct(derive_deserialize = false)]
+struct DeclarativeTable {
+    family: ProtocolFamily,
+    #[field(NFTA_TABLE_NAME)]
+    name: String,
+}
+
+#[test]
+fn declarative_object() {
+    let table = DeclarativeTable::default()
+        .with_family(ProtocolFamily::Inet)
+        .with_name(TABLE_NAME);
+    let expected = Table::new(ProtocolFamily::Inet).with_name(TABLE_NAME);
+    assert_eq!(table.get_family(), ProtocolFamily::Inet);
+    assert_eq!(
+        table.to_batch_message(MsgType::Add, 1),
+        expected.to_batch_message(MsgType::Add, 1)
+    );
+    assert_eq!(
+        table.to_batch_message(MsgType::Del, 1),
+        expected.to_batch_message(MsgType::Del, 1)
+    );
+}
+
+#[test]
+fn batch_markers() {
+    let mut batch = Batch::new();
+    batch.add(&get_test_table(), MsgType::Add);
+    let buf = batch.finalize();
+
+    let begin = BatchMarker::begin(0, NFNL_SUBSYS_NFTABLES as u16);
+    assert_eq!(BatchMarker::parse(&buf).unwrap(), Some(begin));
+    let begin_len = HEADER_SIZE as usize;
+    assert_eq!(&buf[..begin_len], begin.to_message().as_slice());
+
+    // the table message between them is not a marker
+    assert_eq!(BatchMarker::parse(&buf[begin_len..]).unwrap(), None);
+
+    let end = BatchMarker::end(2, NFNL_SUBSYS_NFTABLES as u16);
+    assert_eq!(&buf[buf.len() - begin_len..], end.to_message().as_slice());
+    assert_eq!(
+        BatchMarker::parse(&buf[buf.len() - begin_len..]).unwrap(),
+        Some(end)
+    );
+
+    // the batches of the other subsystems are recognized as well
+    let other = BatchMarker::begin(7, NFNL_SUBSYS_NFTABLES as u16 + 1);
+    assert_eq!(
+        BatchMarker::parse(&other.to_message()).unwrap(),
+        Some(other)
+    );
+    assert!(BatchMarker::parse(&other.to_message()[..begin_len - 1]).is_err());
+}
+
+#[test]
+fn parse_dump_response_stream() {
+    let rule = get_test_rule().with_userdata(b"dumped".to_vec());
+    let mut buf = get_test_table().to_message_with_flags(MsgType::Add, 1, NLM_F_MULTI as u16);
+    buf.extend(rule.to_message_with_flags(MsgType::Add, 2, NLM_F_MULTI as u16));
+    // the end of the dump, holding a 32 bits payload
+    let done_len = size_of::<nlmsghdr>() as u32 + 4;
+    buf.extend(done_len.to_ne_bytes());
+    buf.extend((NLMSG_DONE as u16).to_ne_bytes());
+    buf.extend((NLM_F_MULTI as u16).to_ne_bytes());
+    buf.extend([3u32.to_ne_bytes(), 0u32.to_ne_bytes(), 0u32.to_ne_bytes()].concat());
+    // never reached
+    buf.extend(rule.to_message_with_flags(MsgType::Add, 4, NLM_F_MULTI as u16));
+
+    let rules: Vec<_> = parse_response_stream::<Rule>(&buf)
+        .map(|res| res.unwrap())
+        .collect();
+    assert_eq!(rules.len(), 1);
+    assert_eq!(rules[0].0.nlmsg_seq, 2);
+    assert_eq!(rules[0].1, rule);
+
+    let tables: Vec<_> = parse_response_stream::<Table>(&buf)
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(tables.len(), 1);
+
+    // a truncated message ends the iteration with an error
+    let mut stream = parse_response_stream::<Rule>(&buf[..HEADER_SIZE as usize - 1]);
+    assert!(stream.next().unwrap().is_err());
+    assert!(stream.next().is_none());
+}
+
+#[test]
+fn ack_modes() {
+    let acked = |batch: Batch| {
+        let buf = batch.finalize();
+        let mut acked = Vec::new();
+        let mut remaining = &buf[..];
+        while let Ok((hdr, _)) = parse_nlmsg(remaining) {
+            acked.push(hdr.nlmsg_flags & NLM_F_ACK as u16 != 0);
+            remaining = &remaining[pad_netlink_object_with_variable_size(hdr.nlmsg_len as usize)..];
+        }
+        acked
+    };
+
+    let mut batch = Batch::new();
+    assert_eq!(batch.get_ack_mode(), AckMode::Strict);
+    batch.add(&get_test_table(), MsgType::Add);
+    batch.add(&get_test_chain(), MsgType::Add);
+    assert_eq!(acked(batch), [true, true, true, false]);
+
+    // the kernels before 6.10 never acknowledge the end message
+    let mut batch = Batch::new().with_ack_mode(AckMode::LastMessage);
+    batch.add(&get_test_table(), MsgType::Add);
+    batch.add(&get_test_chain(), MsgType::Add);
+    assert_eq!(acked(batch), [false, false, true, false]);
+}
+
+#[test]
+fn chain_ids_in_verdicts() {
+    let mut batch = Batch::new();
+    let first = batch
+        .add_chain(Chain::new(&get_test_table()).with_name("first"))
+        .unwrap();
+    let second = batch
+        .add_chain(Chain::new(&get_test_table()).with_name("second"))
+        .unwrap();
+    assert_eq!(first.get_id(), Some(&1));
+    assert_eq!(second.get_id(), Some(&2));
+    assert_eq!(batch.objects()[1].msg_type, NFT_MSG_NEWCHAIN as u16);
+
+    let message = second.to_message_with_flags(MsgType::Add, 0, 0);
+    assert_eq!(Chain::deserialize(&message).unwrap().0, second);
+
+    let verdict = batch.goto_to_added(&second).unwrap();
+    assert_eq!(verdict.get_chain(), None);
+    assert_eq!(verdict.get_chain_id(), Some(&2));
+    assert_eq!(verdict.to_string(), "goto chain id 2");
+
+    let rule = get_test_rule().with_expr(Immediate::new_verdict(verdict));
+    assert_eq!(rule.jump_targets().count(), 0);
+    let message = rule.to_message_with_flags(MsgType::Add, 0, 0);
+    assert_eq!(Rule::deserialize(&message).unwrap().0, rule);
+
+    // the rules jump to the chains added by the batch by their name, unless asked otherwise
+    let rule = get_test_rule().jump(&second).unwrap();
+    assert_eq!(rule.jump_targets().collect::<Vec<_>>(), ["second"]);
+
+    // the chains that were not added by the batch have no id in it
+    assert!(matches!(
+        batch.jump_to_added(&get_test_chain()),
+        Err(BuilderError::ChainNotInBatch)
+    ));
+    assert!(matches!(
+        Batch::new().jump_to_added(&first),
+        Err(BuilderError::ChainNotInBatch)
+    ));
+}
@@ -0,0 +1,107 @@
+use std::net::{IpAddr, Ipv4Addr};
+
+use crate::blocklist::{merge_networks, AddressRange, Blocklist, IPV4_SET};
+use crate::set::SetElementList;
+
+fn range(first: &str, last: &str) -> AddressRange {
+    AddressRange {
+        first: first.parse().unwrap(),
+        last: last.parse().unwrap(),
+    }
+}
+
+#[test]
+fn merge_overlapping_networks() {
+    let networks = [
+        "2001:db8::/48",
+        "10.0.1.0/24",
+        "192.0.2.0/24",
+        "10.0.0.0/24",
+        "10.0.0.128/25",
+        "2001:db8::/32",
+        "255.255.255.0/24",
+        "255.255.0.0/16",
+    ];
+    let ranges = merge_networks(networks.iter().map(|n| n.parse().unwrap()));
+    assert_eq!(
+        ranges,
+        [
+            range("10.0.0.0", "10.0.1.255"),
+            range("192.0.2.0", "192.0.2.255"),
+            range("255.255.0.0", "255.255.255.255"),
+            range("2001:db8::", "2001:db8:ffff:ffff:ffff:ffff:ffff:ffff"),
+        ]
+    );
+}
+
+#[test]
+fn interval_elements() {
+    let set = Blocklist::new("geoip").sets().unwrap().0;
+    let mut elements = SetElementList::for_set(&set).unwrap();
+    elements.add_range(&Ipv4Addr::new(10, 0, 0, 0), &Ipv4Addr::new(10, 0, 0, 255));
+    elements.add_range(&Ipv4Addr::new(192, 0, 2, 1), &Ipv4Addr::new(192, 0, 2, 1));
+    elements.add_range(&Ipv4Addr::new(255, 0, 0, 0), &Ipv4Addr::BROADCAST);
+    let keys: Vec<(Vec<u8>, bool)> = elements
+        .elements
+        .as_ref()
+        .unwrap()
+        .iter()
+        .map(|e| {
+            let key = e.get_key().unwrap().get_value().unwrap().clone();
+            (key, e.is_interval_end())
+        })
+        .collect();
+    assert_eq!(
+        keys,
+        [
+            (vec![10, 0, 0, 0], false),
+            (vec![10, 0, 1, 0], true),
+            (vec![192, 0, 2, 1], false),
+            (vec![192, 0, 2, 2], true),
+            // the range reaching the last address is left open
+            (vec![255, 0, 0, 0], false),
+        ]
+    );
+}
+
+#[test]
+fn blocklist_sequence() {
+    let blocklist = Blocklist::new("geoip").with_networks(
+        [
+            "10.0.0.0/8",
+            "172.16.0.0/12",
+            "192.0.2.0/24",
+            "198.51.100.0/24",
+            "203.0.113.0/24",
+            "2001:db8::/32",
+        ]
+        .iter()
+        .map(|n| n.parse().unwrap()),
+    );
+    assert_eq!(blocklist.ranges().len(), 6);
+    assert!(blocklist.ranges()[0].first == IpAddr::from([10, 0, 0, 0]));
+
+    let (ipv4, ipv6) = blocklist.sets().unwrap();
+    assert_eq!(ipv4.get_name().unwrap(), IPV4_SET);
+    assert!(ipv4.validate().is_ok());
+    assert!(ipv6.get_userdata_tlvs().unwrap().merge_elements);
+
+    let rules: Vec<String> = blocklist
+        .rules()
+        .unwrap()
+        .iter()
+        .map(|r| r.to_nft_syntax())
+        .collect();
+    assert_eq!(
+        rules,
+        [
+            "meta nfproto ipv4 ip saddr @ipv4_networks counter packets 0 bytes 0 drop",
+            "meta nfproto ipv6 ip6 saddr @ipv6_networks counter packets 0 bytes 0 drop",
+        ]
+    );
+
+    // the setup, then 3 transactions for the 5 IPv4 ranges and one for the IPv6 range
+    assert_eq!(blocklist.to_sequence(2).unwrap().len(), 5);
+    assert_eq!(blocklist.to_sequence(0).unwrap().len(), 7);
+    assert_eq!(Blocklist::new("empty").to_sequence(10).unwrap().len(), 1);
+}
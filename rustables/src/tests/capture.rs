@@ -0,0 +1,35 @@
+use crate::capture::{is_capturing, read_capture, record, start_capture, stop_capture};
+use crate::nlmsg::NfNetlinkDeserializable;
+use crate::Table;
+
+use super::{get_test_nlmsg, get_test_table};
+
+#[test]
+fn capture_roundtrip() {
+    let path = std::env::temp_dir().join(format!("rustables-capture-{}.pcap", std::process::id()));
+
+    let mut table = get_test_table();
+    let mut buf = Vec::new();
+    get_test_nlmsg(&mut buf, &mut table);
+
+    // nothing is recorded outside of a capture
+    record(&buf);
+    start_capture(&path).unwrap();
+    assert!(is_capturing());
+    record(&buf);
+    record(&buf[..16]);
+    stop_capture().unwrap();
+    assert!(!is_capturing());
+    record(&buf);
+
+    let messages = read_capture(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(messages.len(), 2);
+    assert_eq!(messages[0].payload, buf);
+    assert_eq!(messages[1].payload, &buf[..16]);
+    assert!(messages[0].timestamp <= messages[1].timestamp);
+
+    // the captured messages can be fed back to the parser
+    let (parsed, _) = Table::deserialize(&messages[0].payload).unwrap();
+    assert_eq!(parsed, table);
+}
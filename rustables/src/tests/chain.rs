@@ -1,10 +1,14 @@
 use crate::{
-    nlmsg::get_operation_from_nlmsghdr_type,
+    error::BuilderError,
+    expr::{Counter, Limit, LogPrefix},
+    nlmsg::{get_operation_from_nlmsghdr_type, NfNetlinkDeserializable, NfNetlinkObject},
     sys::{
-        NFTA_CHAIN_HOOK, NFTA_CHAIN_NAME, NFTA_CHAIN_TABLE, NFTA_CHAIN_TYPE, NFTA_CHAIN_USERDATA,
-        NFTA_HOOK_HOOKNUM, NFTA_HOOK_PRIORITY, NFT_MSG_DELCHAIN, NFT_MSG_NEWCHAIN,
+        NFTA_CHAIN_HANDLE, NFTA_CHAIN_HOOK, NFTA_CHAIN_NAME, NFTA_CHAIN_TABLE, NFTA_CHAIN_TYPE,
+        NFTA_CHAIN_USERDATA, NFTA_DEVICE_NAME, NFTA_HOOK_DEVS, NFTA_HOOK_HOOKNUM,
+        NFTA_HOOK_PRIORITY, NFT_MSG_DELCHAIN, NFT_MSG_NEWCHAIN,
     },
-    ChainType, Hook, HookClass, MsgType,
+    Batch, Chain, ChainFlags, ChainPolicy, ChainType, DeviceList, Hook, HookClass, MsgType,
+    ProtocolFamily, Rule, StandardPriority, SymbolicPriority, Table,
 };
 
 use super::{
@@ -118,3 +122,327 @@ fn delete_empty_chain() {
         .to_raw()
     );
 }
+
+#[test]
+fn dumped_chain_can_be_resubmitted() {
+    let mut chain = get_test_chain().with_use_count(3u32).with_handle(5u64);
+
+    let mut buf = Vec::new();
+    let (_nlmsghdr, _nfgenmsg, raw_expr) = get_test_nlmsg(&mut buf, &mut chain);
+    assert_eq!(
+        raw_expr,
+        NetlinkExpr::List(vec![
+            NetlinkExpr::Final(NFTA_CHAIN_TABLE, TABLE_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Final(NFTA_CHAIN_NAME, CHAIN_NAME.as_bytes().to_vec()),
+        ])
+        .to_raw()
+    );
+
+    let mut buf = Vec::new();
+    let (_nlmsghdr, _nfgenmsg, raw_expr) =
+        get_test_nlmsg_with_msg_type(&mut buf, &mut chain, MsgType::Del);
+    assert_eq!(
+        raw_expr,
+        NetlinkExpr::List(vec![
+            NetlinkExpr::Final(NFTA_CHAIN_TABLE, TABLE_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Final(NFTA_CHAIN_HANDLE, 5u64.to_be_bytes().to_vec()),
+            NetlinkExpr::Final(NFTA_CHAIN_NAME, CHAIN_NAME.as_bytes().to_vec()),
+        ])
+        .to_raw()
+    );
+}
+
+#[test]
+fn parse_chain_with_drop_policy() {
+    let mut chain = get_test_chain()
+        .with_hook(Hook::new(HookClass::In, 0))
+        .with_policy(ChainPolicy::Drop);
+
+    let mut buf = Vec::new();
+    get_test_nlmsg(&mut buf, &mut chain);
+
+    let (deserialized_chain, remaining) =
+        Chain::deserialize(&buf).expect("Couldn't deserialize the object");
+    assert_eq!(deserialized_chain.get_policy(), Some(&ChainPolicy::Drop));
+    assert_eq!(chain, deserialized_chain);
+    assert_eq!(remaining.len(), 0);
+}
+
+#[test]
+fn parse_chain_flags() {
+    let mut chain =
+        get_test_chain().with_flags((ChainFlags::BASE | ChainFlags::BINDING).bits() | 0x80000000);
+
+    let mut buf = Vec::new();
+    get_test_nlmsg(&mut buf, &mut chain);
+
+    let (deserialized_chain, _) =
+        Chain::deserialize(&buf).expect("Couldn't deserialize the object");
+    assert_eq!(
+        deserialized_chain.get_chain_flags(),
+        Some(ChainFlags::BASE | ChainFlags::BINDING)
+    );
+    assert!(deserialized_chain.is_bound());
+    assert!(!deserialized_chain.is_offloaded());
+    assert!(!get_test_chain().is_bound());
+}
+
+#[test]
+fn chain_policy_requires_hook() {
+    let chain = get_test_chain();
+    assert!(chain.validate().is_ok());
+
+    let chain = chain.with_policy(ChainPolicy::Accept);
+    assert!(matches!(
+        chain.validate(),
+        Err(BuilderError::ChainPolicyWithoutHook)
+    ));
+
+    let chain = chain.with_hook(Hook::new(HookClass::In, 0));
+    assert!(chain.validate().is_ok());
+}
+
+#[test]
+fn bridge_chain_validation() {
+    let table = Table::new(ProtocolFamily::Bridge).with_name(TABLE_NAME);
+    let chain = Chain::new(&table)
+        .with_name(CHAIN_NAME)
+        .with_hook(Hook::new(HookClass::Forward, 0))
+        .with_type(ChainType::Filter);
+    assert!(chain.validate().is_ok());
+
+    let nat_chain = chain.clone().with_type(ChainType::Nat);
+    assert!(matches!(
+        nat_chain.validate(),
+        Err(BuilderError::UnsupportedChainTypeForFamily)
+    ));
+
+    let brouting_chain = chain.with_hook(Hook::default().with_class(libc::NF_BR_BROUTING as u32));
+    assert!(matches!(
+        brouting_chain.validate(),
+        Err(BuilderError::UnsupportedHookForFamily(5))
+    ));
+}
+
+#[test]
+fn device_list_validation() {
+    let devices = DeviceList::new(["eth0", "eth1"]).unwrap();
+    assert_eq!(devices.iter().collect::<Vec<_>>(), ["eth0", "eth1"]);
+    assert_eq!(devices.len(), 2);
+
+    assert!(matches!(
+        devices.clone().with_device("eth0"),
+        Err(BuilderError::DuplicateDevice(name)) if name == "eth0"
+    ));
+    assert!(matches!(
+        devices.clone().with_device("a-very-long-name0"),
+        Err(BuilderError::InterfaceNameTooLong)
+    ));
+    assert!(matches!(
+        devices.with_device(""),
+        Err(BuilderError::InvalidDeviceName(_))
+    ));
+}
+
+#[test]
+fn netdev_chain_with_devices() {
+    let table = Table::new(ProtocolFamily::NetDev).with_name(TABLE_NAME);
+    let chain = Chain::new(&table)
+        .with_name(CHAIN_NAME)
+        .with_type(ChainType::Filter);
+
+    let no_devices = chain
+        .clone()
+        .with_hook(Hook::ingress(0, DeviceList::default()));
+    assert!(matches!(
+        no_devices.validate(),
+        Err(BuilderError::MissingChainDevices)
+    ));
+
+    let devices = DeviceList::new(["eth0", "eth1"]).unwrap();
+    let mut chain = chain.with_hook(Hook::ingress(-10, devices));
+    assert!(chain.validate().is_ok());
+
+    let mut buf = Vec::new();
+    let (_nlmsghdr, _nfgenmsg, raw_expr) = get_test_nlmsg(&mut buf, &mut chain);
+    assert_eq!(
+        raw_expr,
+        NetlinkExpr::List(vec![
+            NetlinkExpr::Final(NFTA_CHAIN_TABLE, TABLE_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Final(NFTA_CHAIN_NAME, CHAIN_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Final(NFTA_CHAIN_TYPE, "filter".as_bytes().to_vec()),
+            NetlinkExpr::Nested(
+                NFTA_CHAIN_HOOK,
+                vec![
+                    NetlinkExpr::Final(NFTA_HOOK_HOOKNUM, vec![0, 0, 0, 0]),
+                    NetlinkExpr::Final(NFTA_HOOK_PRIORITY, (-10i32).to_be_bytes().to_vec()),
+                    NetlinkExpr::Nested(
+                        NFTA_HOOK_DEVS,
+                        vec![
+                            NetlinkExpr::Final(NFTA_DEVICE_NAME, b"eth0".to_vec()),
+                            NetlinkExpr::Final(NFTA_DEVICE_NAME, b"eth1".to_vec()),
+                        ]
+                    ),
+                ]
+            ),
+        ])
+        .to_raw()
+    );
+
+    let (deserialized_chain, _) = Chain::deserialize(&buf).unwrap();
+    assert_eq!(chain, deserialized_chain);
+}
+
+#[test]
+fn netdev_chain_validation() {
+    let table = Table::new(ProtocolFamily::NetDev).with_name(TABLE_NAME);
+    let devices = DeviceList::new(["eth0"]).unwrap();
+    let chain = Chain::new(&table)
+        .with_name(CHAIN_NAME)
+        .with_type(ChainType::Filter)
+        .with_hook(Hook::egress(0, devices.clone()));
+    assert!(chain.validate().is_ok());
+
+    let nat_chain = chain.clone().with_type(ChainType::Nat);
+    assert!(matches!(
+        nat_chain.validate(),
+        Err(BuilderError::UnsupportedChainTypeForFamily)
+    ));
+
+    let forward_chain =
+        chain.with_hook(Hook::new(HookClass::Forward, 0).with_devices(devices.clone()));
+    assert!(matches!(
+        forward_chain.validate(),
+        Err(BuilderError::UnsupportedHookForFamily(2))
+    ));
+
+    // devices are only allowed on the ingress hook of the other families
+    let inet_table = Table::new(ProtocolFamily::Inet).with_name(TABLE_NAME);
+    let inet_chain = Chain::new(&inet_table)
+        .with_name(CHAIN_NAME)
+        .with_hook(Hook::new(HookClass::In, 0).with_devices(devices.clone()));
+    assert!(matches!(
+        inet_chain.validate(),
+        Err(BuilderError::UnexpectedChainDevices)
+    ));
+    let inet_chain = inet_chain.with_hook(
+        Hook::default()
+            .with_class(libc::NF_INET_INGRESS as u32)
+            .with_devices(devices),
+    );
+    assert!(inet_chain.validate().is_ok());
+}
+
+#[test]
+fn symbolic_chain_priorities() {
+    let priority: SymbolicPriority = "mangle + 10".parse().unwrap();
+    assert_eq!(
+        priority,
+        SymbolicPriority::new(StandardPriority::Mangle, 10)
+    );
+    assert_eq!(priority.to_string(), "mangle + 10");
+    assert_eq!(priority.resolve(ProtocolFamily::Inet, None).unwrap(), -140);
+    for (s, expected) in [
+        ("filter", "filter"),
+        ("dstnat-5", "dstnat - 5"),
+        (" srcnat +  3 ", "srcnat + 3"),
+        ("-42", "-42"),
+    ] {
+        assert_eq!(s.parse::<SymbolicPriority>().unwrap().to_string(), expected);
+    }
+    for s in [
+        "",
+        "nat",
+        "filter +",
+        "filter + -5",
+        "filter 10",
+        "filter + 1x",
+    ] {
+        assert!(matches!(
+            s.parse::<SymbolicPriority>(),
+            Err(BuilderError::InvalidPriority(_))
+        ));
+    }
+
+    // the standard priorities depend on the family, and some are tied to a hook
+    let filter = SymbolicPriority::new(StandardPriority::Filter, 0);
+    assert_eq!(filter.resolve(ProtocolFamily::Bridge, None).unwrap(), -200);
+    assert!(matches!(
+        SymbolicPriority::new(StandardPriority::Raw, 0).resolve(ProtocolFamily::NetDev, None),
+        Err(BuilderError::UnsupportedPriority(_))
+    ));
+    assert!(matches!(
+        Hook::new(HookClass::PostRouting, 0)
+            .with_symbolic_priority("dstnat".parse().unwrap(), ProtocolFamily::Ipv4),
+        Err(BuilderError::UnsupportedPriority(_))
+    ));
+    let hook = Hook::new(HookClass::PreRouting, 0)
+        .with_symbolic_priority("dstnat".parse().unwrap(), ProtocolFamily::Ipv4)
+        .unwrap();
+    assert_eq!(hook.get_priority(), Some(&(-100i32 as u32)));
+
+    let chain = Chain::new(&Table::new(ProtocolFamily::Inet)).with_hook(hook);
+    assert_eq!(
+        chain.symbolic_priority().unwrap().to_string(),
+        "dstnat".to_string()
+    );
+    let chain = chain.with_hook(Hook::new(HookClass::In, 55));
+    assert_eq!(
+        chain.symbolic_priority().unwrap().to_string(),
+        "security + 5"
+    );
+    let chain = chain.with_hook(Hook::new(HookClass::In, -120));
+    assert_eq!(chain.symbolic_priority().unwrap().to_string(), "-120");
+    assert_eq!(get_test_chain().symbolic_priority(), None);
+}
+
+#[test]
+fn chain_drop_by_default() {
+    let chain = get_test_chain();
+    let mut batch = Batch::new();
+    assert!(matches!(
+        chain.clone().drop_by_default(&mut batch, None),
+        Err(BuilderError::ChainPolicyWithoutHook)
+    ));
+
+    let chain = chain.with_hook(Hook::new(HookClass::In, 0));
+    let prefix = LogPrefix::new("dropped: ").unwrap();
+    let mut batch = Batch::new();
+    let dropping = chain
+        .clone()
+        .drop_by_default(&mut batch, Some((prefix.clone(), Limit::new(5, 60))))
+        .unwrap();
+    assert_eq!(dropping.get_policy(), Some(&ChainPolicy::Drop));
+
+    let mut expected = Batch::new();
+    expected.add(&chain.with_policy(ChainPolicy::Drop), MsgType::Add);
+    let log_rule = Rule::new(&dropping)
+        .unwrap()
+        .with_expr(Counter::default())
+        .log_limited(prefix, Limit::new(5, 60))
+        .unwrap()
+        .drop();
+    expected.add(&log_rule, MsgType::Add);
+    assert_eq!(batch.finalize(), expected.finalize());
+}
+
+#[test]
+fn chain_builder() {
+    let chain = Chain::builder()
+        .with_name(CHAIN_NAME)
+        .with_family(ProtocolFamily::Inet)
+        .with_table(TABLE_NAME)
+        .build();
+    assert_eq!(chain, get_test_chain());
+
+    // the other attributes can be set before or after the required ones
+    let chain = Chain::builder()
+        .with_policy(ChainPolicy::Drop)
+        .with_table(TABLE_NAME)
+        .with_hook(Hook::new(HookClass::In, 0))
+        .with_name(CHAIN_NAME)
+        .with_type(ChainType::Filter)
+        .build();
+    assert_eq!(chain.get_policy(), Some(&ChainPolicy::Drop));
+    assert!(chain.missing_attributes(MsgType::Add).is_empty());
+}
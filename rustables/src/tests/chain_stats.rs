@@ -0,0 +1,50 @@
+use std::time::{Duration, Instant};
+
+use crate::expr::Counter;
+use crate::{ChainSample, Rule};
+
+use super::get_test_rule;
+
+fn counted_rule(handle: u64, packets: u64, bytes: u64) -> Rule {
+    get_test_rule()
+        .with_handle(handle)
+        .with_expr(Counter::new(packets, bytes))
+}
+
+#[test]
+fn chain_sample_rates() {
+    let start = Instant::now();
+    let rules = [
+        counted_rule(1, 10, 1000),
+        counted_rule(2, 5, 500),
+        get_test_rule(),
+    ];
+    let first = ChainSample::new(&rules, start, None, false);
+    // the rule without a counter is left out
+    assert_eq!(first.rules.len(), 2);
+    assert_eq!(first.elapsed, None);
+    assert_eq!(first.rates(), None);
+    assert_eq!(first.delta(), Counter::new(15, 1500));
+
+    // the second rule was recreated in between, so its counter went down
+    let rules = [counted_rule(1, 30, 3000), counted_rule(2, 1, 100)];
+    let second = ChainSample::new(&rules, start + Duration::from_secs(2), Some(&first), false);
+    assert_eq!(second.elapsed, Some(Duration::from_secs(2)));
+    let rule = second.rule(1).unwrap();
+    assert_eq!(rule.delta, Counter::new(20, 2000));
+    assert_eq!(rule.rates, Some((10., 1000.)));
+    assert_eq!(second.rule(2).unwrap().delta, Counter::new(1, 100));
+    assert_eq!(second.rates(), Some((10.5, 1050.)));
+}
+
+#[test]
+fn chain_sample_with_reset() {
+    let start = Instant::now();
+    let first = ChainSample::new(&[counted_rule(1, 10, 1000)], start, None, true);
+    let rules = [counted_rule(1, 4, 400)];
+    let second = ChainSample::new(&rules, start + Duration::from_secs(4), Some(&first), true);
+    let rule = second.rule(1).unwrap();
+    assert_eq!(rule.counter, Counter::new(14, 1400));
+    assert_eq!(rule.delta, Counter::new(4, 400));
+    assert_eq!(rule.rates, Some((1., 100.)));
+}
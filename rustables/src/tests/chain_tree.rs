@@ -0,0 +1,66 @@
+use crate::{
+    error::BuilderError,
+    nlmsg::get_operation_from_nlmsghdr_type,
+    parser::parse_nlmsgs,
+    sys::{NFT_MSG_NEWCHAIN, NFT_MSG_NEWRULE},
+    Batch, ChainTree,
+};
+
+use super::get_test_chain;
+
+#[test]
+fn chain_tree_adds_chains_before_jumps() {
+    let lan = ChainTree::new(get_test_chain().with_name("lan"))
+        .branch("lan_ssh", |rule| Ok(rule.dport(22, crate::Protocol::TCP)))
+        .unwrap();
+    let tree = ChainTree::new(get_test_chain())
+        .subtree(lan, |rule| rule.iiface("eth0"))
+        .unwrap()
+        .branch("wan", |rule| rule.iiface("eth1"))
+        .unwrap();
+
+    let names: Vec<_> = tree
+        .chains()
+        .iter()
+        .map(|chain| chain.get_name().unwrap().as_str())
+        .collect();
+    assert_eq!(names, ["mockchain", "lan", "lan_ssh", "wan"]);
+    let rule_chains: Vec<_> = tree
+        .rules()
+        .iter()
+        .map(|rule| rule.get_chain().unwrap().as_str())
+        .collect();
+    assert_eq!(rule_chains, ["mockchain", "lan", "mockchain"]);
+
+    let mut batch = Batch::new();
+    tree.add_to_batch(&mut batch);
+    let buf = batch.finalize();
+
+    let ops: Vec<_> = parse_nlmsgs(&buf)
+        .map(|res| get_operation_from_nlmsghdr_type(res.unwrap().0.nlmsg_type))
+        .collect();
+    // skip the batch begin and end messages
+    assert_eq!(
+        ops[1..ops.len() - 1],
+        [
+            NFT_MSG_NEWCHAIN as u8,
+            NFT_MSG_NEWCHAIN as u8,
+            NFT_MSG_NEWCHAIN as u8,
+            NFT_MSG_NEWCHAIN as u8,
+            NFT_MSG_NEWRULE as u8,
+            NFT_MSG_NEWRULE as u8,
+            NFT_MSG_NEWRULE as u8,
+        ]
+    );
+}
+
+#[test]
+fn chain_tree_rejects_duplicate_chains() {
+    let tree = ChainTree::new(get_test_chain())
+        .branch("zone", |rule| rule.iiface("eth0"))
+        .unwrap();
+    assert!(matches!(
+        tree.branch("zone", |rule| rule.iiface("eth1")),
+        Err(BuilderError::DuplicateChainName(name)) if name == "zone"
+    ));
+}
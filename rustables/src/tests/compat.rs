@@ -0,0 +1,55 @@
+use crate::{
+    compat::{find_compat_usage, CompatConstruct, RuleCompat},
+    expr::RawExpression,
+    nlmsg::NfNetlinkDeserializable,
+    sys::{NFTA_EXPR_DATA, NFTA_EXPR_NAME},
+};
+
+use super::{get_test_rule, NetlinkExpr, CHAIN_NAME, TABLE_NAME};
+
+/// A `match` expression running the xtables `conntrack` match.
+fn xt_match() -> RawExpression {
+    let raw = NetlinkExpr::List(vec![
+        NetlinkExpr::Final(NFTA_EXPR_NAME, b"match\0".to_vec()),
+        NetlinkExpr::Nested(
+            NFTA_EXPR_DATA,
+            vec![
+                NetlinkExpr::Final(1, b"conntrack\0".to_vec()),
+                NetlinkExpr::Final(2, 3u32.to_be_bytes().to_vec()),
+                NetlinkExpr::Final(3, vec![0; 8]),
+            ],
+        ),
+    ])
+    .to_raw();
+    RawExpression::deserialize(&raw).unwrap().0
+}
+
+#[test]
+fn detect_compat_rules() {
+    let native = get_test_rule().accept();
+    let legacy = get_test_rule()
+        .with_handle(4u64)
+        .with_compat(RuleCompat::default().with_proto(libc::IPPROTO_TCP as u32))
+        .with_expr(xt_match())
+        .accept();
+    assert!(find_compat_usage([&native]).is_empty());
+
+    let usages = find_compat_usage([&native, &legacy]);
+    assert_eq!(usages.len(), 2);
+    assert_eq!(usages[0].construct, CompatConstruct::RuleAttribute);
+    assert_eq!(usages[0].expression, None);
+    assert_eq!(
+        usages[1].construct,
+        CompatConstruct::Match(Some("conntrack".to_string()))
+    );
+    assert_eq!(usages[1].expression, Some(0));
+    assert_eq!(usages[1].table.as_deref(), Some(TABLE_NAME));
+    assert_eq!(usages[1].chain.as_deref(), Some(CHAIN_NAME));
+    assert_eq!(
+        usages[1].to_string(),
+        format!(
+            "inet {} {} handle 4: xtables match conntrack (expression 0)",
+            TABLE_NAME, CHAIN_NAME
+        )
+    );
+}
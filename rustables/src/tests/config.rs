@@ -0,0 +1,84 @@
+use std::net::{IpAddr, Ipv4Addr};
+
+use crate::{
+    config::{ChainConfig, Config, HookConfig, RuleConfig, TableConfig},
+    error::BuilderError,
+    expr::VerdictKind,
+    nlmsg::{pad_netlink_object_with_variable_size, NfNetlinkDeserializable, NfNetlinkObject},
+    parser::parse_nlmsg,
+    Chain, ChainPolicy, ChainType, HookClass, Protocol, ProtocolFamily, Rule, Table,
+};
+
+use super::{get_test_chain, CHAIN_NAME, TABLE_NAME};
+
+fn get_test_config() -> Config {
+    Config {
+        tables: vec![TableConfig {
+            name: TABLE_NAME.to_string(),
+            family: ProtocolFamily::Inet,
+            chains: vec![ChainConfig {
+                name: CHAIN_NAME.to_string(),
+                hook: Some(HookConfig {
+                    class: HookClass::In,
+                    priority: 0.into(),
+                }),
+                chain_type: Some(ChainType::Filter),
+                policy: Some(ChainPolicy::Drop),
+                rules: vec![
+                    RuleConfig {
+                        established: true,
+                        verdict: Some(VerdictKind::Accept),
+                        ..Default::default()
+                    },
+                    RuleConfig {
+                        protocol: Some(Protocol::TCP),
+                        dport: Some(22),
+                        saddr: Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))),
+                        verdict: Some(VerdictKind::Accept),
+                        ..Default::default()
+                    },
+                ],
+            }],
+        }],
+    }
+}
+
+#[test]
+fn config_to_batch() {
+    let config = get_test_config();
+    let buf = config
+        .to_batch()
+        .expect("Couldn't build the batch")
+        .finalize();
+
+    // skip the batch begin message
+    let (hdr, _msg) = parse_nlmsg(&buf).expect("Invalid nlmsg message");
+    let remaining = &buf[pad_netlink_object_with_variable_size(hdr.nlmsg_len as usize)..];
+
+    let (table, remaining) = Table::deserialize(remaining).expect("Couldn't parse the table");
+    assert_eq!(table.get_name(), Some(&TABLE_NAME.to_string()));
+    assert_eq!(table.get_family(), ProtocolFamily::Inet);
+
+    let (chain, mut remaining) = Chain::deserialize(remaining).expect("Couldn't parse the chain");
+    assert_eq!(chain.get_name(), Some(&CHAIN_NAME.to_string()));
+    assert_eq!(chain.get_policy(), Some(&ChainPolicy::Drop));
+
+    let chain_config = &config.tables[0].chains[0];
+    for rule_config in &chain_config.rules {
+        let (rule, rest) = Rule::deserialize(remaining).expect("Couldn't parse the rule");
+        remaining = rest;
+        assert_eq!(rule, rule_config.to_rule(&chain).unwrap());
+    }
+}
+
+#[test]
+fn config_port_without_protocol() {
+    let rule = RuleConfig {
+        dport: Some(22),
+        ..Default::default()
+    };
+    assert!(matches!(
+        rule.to_rule(&get_test_chain()),
+        Err(BuilderError::MissingPortProtocol)
+    ));
+}
@@ -0,0 +1,118 @@
+use std::any::type_name;
+use std::fmt::Debug;
+
+use crate::expr::{Meta, MetaType, Verdict, VerdictType};
+use crate::nlmsg::{
+    pad_netlink_object, AttributeDecoder, NfNetlinkAttribute, NfNetlinkDeserializable,
+};
+use crate::parser::read_attributes;
+use crate::sys::nlattr;
+use crate::{Chain, ChainPolicy};
+
+/// An integer type of the attributes, with a sample value whose bytes all differ, so that
+/// writing or reading them in the wrong byte order shows.
+pub trait SampleInteger:
+    NfNetlinkAttribute + NfNetlinkDeserializable + Copy + PartialEq + Debug
+{
+    const SAMPLE: Self;
+
+    fn to_network_bytes(self) -> Vec<u8>;
+}
+
+macro_rules! impl_sample_integer {
+    ($($ty:ty => $sample:expr),*) => {
+        $(
+            impl SampleInteger for $ty {
+                const SAMPLE: Self = $sample;
+
+                fn to_network_bytes(self) -> Vec<u8> {
+                    self.to_be_bytes().to_vec()
+                }
+            }
+        )*
+    };
+}
+
+impl_sample_integer!(
+    u8 => 0x2a,
+    u16 => 0x0102,
+    u32 => 0x0102_0304,
+    i32 => -0x0102_0304,
+    u64 => 0x0102_0304_0506_0708
+);
+
+/// Checks that the field `field` of `T` holding the integer type `I`, in the object returned by
+/// `with`, is written in network byte order and decoded back to the same value by `get`.
+///
+/// `nfnetlink_struct` generates a call to this function for every integer field of the
+/// structures deriving their decoder, so that all of them are covered.
+pub fn check_integer_field<T, I>(field: &str, with: impl Fn(I) -> T, get: impl Fn(&T) -> Option<I>)
+where
+    T: NfNetlinkAttribute + AttributeDecoder + Debug + Default,
+    I: SampleInteger,
+{
+    let obj = with(I::SAMPLE);
+    let mut buf = vec![0u8; obj.get_size()];
+    obj.write_payload(&mut buf);
+    let expected = I::SAMPLE.to_network_bytes();
+    let payload = &buf[pad_netlink_object::<nlattr>()..];
+    assert_eq!(
+        &payload[..expected.len()],
+        &expected[..],
+        "{}::{}",
+        type_name::<T>(),
+        field
+    );
+
+    let decoded: T = read_attributes(&buf)
+        .unwrap_or_else(|e| panic!("could not decode {}::{}: {:?}", type_name::<T>(), field, e));
+    assert_eq!(
+        get(&decoded),
+        Some(I::SAMPLE),
+        "{}::{}",
+        type_name::<T>(),
+        field
+    );
+}
+
+/// Generates a test per listed field holding an enum stored as an integer, checking that the
+/// value set with `$with` is written in network byte order as `$bytes`, and that decoding the
+/// attribute gives back the same value through `$get`. The integer fields themselves are covered
+/// by the tests generated by `nfnetlink_struct`, see [`check_integer_field`].
+macro_rules! round_trip_tests {
+    ($($test:ident: $ty:ty, $with:ident, $get:ident, $value:expr => $bytes:expr;)*) => {
+        $(
+            #[test]
+            fn $test() {
+                let obj = <$ty>::default().$with($value);
+                let mut buf = vec![0u8; obj.get_size()];
+                obj.write_payload(&mut buf);
+                let expected: &[u8] = &$bytes;
+                let payload = &buf[pad_netlink_object::<nlattr>()..];
+                assert_eq!(&payload[..expected.len()], expected);
+
+                let decoded: $ty = read_attributes(&buf).expect("could not decode the field");
+                assert_eq!(decoded.$get(), obj.$get());
+            }
+        )*
+    };
+}
+
+round_trip_tests! {
+    meta_key: Meta, with_key, get_key, MetaType::Mark => [0, 0, 0, 3];
+    verdict_code: Verdict, with_code, get_code, VerdictType::Jump => (-3i32).to_be_bytes();
+    chain_policy: Chain, with_policy, get_policy, ChainPolicy::Drop => [0, 0, 0, 0];
+}
+
+#[test]
+fn integer_sizes() {
+    assert!(u8::deserialize(&[]).is_err());
+    assert!(u16::deserialize(&[1]).is_err());
+    assert!(u32::deserialize(&[1, 2, 3]).is_err());
+    assert!(u64::deserialize(&[1, 2, 3, 4]).is_err());
+    assert!(u8::deserialize(&[1, 2]).is_err());
+    assert!(u16::deserialize(&[1, 2, 3]).is_err());
+    assert!(i32::deserialize(&[1, 2, 3, 4, 5]).is_err());
+    assert!(u64::deserialize(&[0; 12]).is_err());
+    assert_eq!(u16::deserialize(&[1, 2]).unwrap(), (0x0102, &[][..]));
+}
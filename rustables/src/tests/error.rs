@@ -0,0 +1,124 @@
+use std::mem::size_of;
+
+use crate::nlmsg::pad_netlink_object_with_variable_size;
+use crate::parser::{get_nlmsghdr, parse_nlmsg, NlMsg};
+use crate::sys::{
+    nlmsgerr, nlmsghdr, NLMSGERR_ATTR_MSG, NLMSGERR_ATTR_OFFS, NLMSG_ERROR, NLM_F_ACK_TLVS,
+};
+
+use super::{get_test_nlmsg, get_test_rule, NetlinkExpr};
+
+#[test]
+fn parse_error_with_extended_ack() {
+    let mut rule = get_test_rule();
+    let mut orig_msg = Vec::new();
+    get_test_nlmsg(&mut orig_msg, &mut rule);
+    let orig_hdr = get_nlmsghdr(&orig_msg).unwrap();
+
+    // the second attribute of the rule (after the table name) is the chain name
+    let table_attr_len = pad_netlink_object_with_variable_size(4 + super::TABLE_NAME.len());
+    let bad_offset = (size_of::<nlmsghdr>() + 4 + table_attr_len) as u32;
+
+    let tlvs = NetlinkExpr::List(vec![
+        NetlinkExpr::Final(NLMSGERR_ATTR_MSG as u16, b"Chain not found\0".to_vec()),
+        NetlinkExpr::Final(NLMSGERR_ATTR_OFFS as u16, bad_offset.to_ne_bytes().to_vec()),
+    ])
+    .to_raw();
+
+    let err = nlmsgerr {
+        error: -libc::ENOENT,
+        msg: orig_hdr,
+    };
+    let err_len = size_of::<nlmsghdr>() + size_of::<nlmsgerr>() + orig_msg.len()
+        - size_of::<nlmsghdr>()
+        + tlvs.len();
+    let hdr = nlmsghdr {
+        nlmsg_len: err_len as u32,
+        nlmsg_type: NLMSG_ERROR as u16,
+        nlmsg_flags: NLM_F_ACK_TLVS as u16,
+        nlmsg_seq: 0,
+        nlmsg_pid: 0,
+    };
+    let mut buf = Vec::new();
+    buf.extend_from_slice(unsafe {
+        std::slice::from_raw_parts(&hdr as *const nlmsghdr as *const u8, size_of::<nlmsghdr>())
+    });
+    buf.extend_from_slice(unsafe {
+        std::slice::from_raw_parts(&err as *const nlmsgerr as *const u8, size_of::<nlmsgerr>())
+    });
+    buf.extend_from_slice(&orig_msg[size_of::<nlmsghdr>()..]);
+    buf.extend_from_slice(&tlvs);
+
+    let (_hdr, msg) = parse_nlmsg(&buf).expect("Couldn't parse the error message");
+    let report = match msg {
+        NlMsg::Error(report) => report,
+        _ => panic!("Invalid return value type, expected an error"),
+    };
+    assert_eq!(report.err.error, libc::ENOENT);
+    #[cfg(feature = "socket")]
+    assert_eq!(report.errno(), nix::errno::Errno::ENOENT);
+    assert_eq!(report.msg_type(), orig_hdr.nlmsg_type);
+    assert_eq!(report.seq(), orig_hdr.nlmsg_seq);
+    assert_eq!(report.message.as_deref(), Some("Chain not found"));
+    assert_eq!(report.bad_attr_offset, Some(bad_offset));
+    assert_eq!(report.bad_attr_name, Some("NFTA_RULE_CHAIN"));
+}
+
+#[test]
+fn parse_error_with_unpadded_attributes() {
+    // the offending message holds a single attribute, whose padding is missing
+    let orig_payload = [[0; 4].as_slice(), &[5, 0, 1, 0, b'x']].concat();
+    let orig_hdr = nlmsghdr {
+        nlmsg_len: (size_of::<nlmsghdr>() + orig_payload.len()) as u32,
+        nlmsg_type: (libc::NFNL_SUBSYS_NFTABLES << 8 | libc::NFT_MSG_NEWRULE) as u16,
+        nlmsg_flags: 0,
+        nlmsg_seq: 1,
+        nlmsg_pid: 0,
+    };
+    let err = nlmsgerr {
+        error: -libc::EINVAL,
+        msg: orig_hdr,
+    };
+    // the offset designates the bytes following that attribute
+    let bad_offset = orig_hdr.nlmsg_len + 4;
+    for last_tlv in [vec![5, 0, 0xff, 0, 0], vec![12, 0, 0xff, 0, 0]] {
+        let mut extra = orig_payload.clone();
+        extra.resize(pad_netlink_object_with_variable_size(extra.len()), 0);
+        extra.extend(
+            NetlinkExpr::Final(NLMSGERR_ATTR_OFFS as u16, bad_offset.to_ne_bytes().to_vec())
+                .to_raw(),
+        );
+        extra.extend(&last_tlv);
+        let hdr = nlmsghdr {
+            nlmsg_len: (size_of::<nlmsghdr>() + size_of::<nlmsgerr>() + extra.len()) as u32,
+            nlmsg_type: NLMSG_ERROR as u16,
+            nlmsg_flags: NLM_F_ACK_TLVS as u16,
+            nlmsg_seq: 1,
+            nlmsg_pid: 0,
+        };
+        let buf = [
+            unsafe {
+                std::slice::from_raw_parts(
+                    &hdr as *const nlmsghdr as *const u8,
+                    size_of::<nlmsghdr>(),
+                )
+            },
+            unsafe {
+                std::slice::from_raw_parts(
+                    &err as *const nlmsgerr as *const u8,
+                    size_of::<nlmsgerr>(),
+                )
+            },
+            &extra,
+        ]
+        .concat();
+
+        let report = match parse_nlmsg(&buf) {
+            Ok((_, NlMsg::Error(report))) => report,
+            _ => panic!("Invalid return value type, expected an error"),
+        };
+        assert_eq!(report.err.error, libc::EINVAL);
+        assert_eq!(report.bad_attr_offset, Some(bad_offset));
+        assert_eq!(report.bad_attr_name, None);
+    }
+}
@@ -3,26 +3,32 @@ use std::net::Ipv4Addr;
 use libc::NF_DROP;
 
 use crate::{
+    error::{BuilderError, DecodeError},
     expr::{
-        Bitwise, Cmp, CmpOp, Conntrack, ConntrackKey, Counter, ExpressionList, HeaderField,
-        HighLevelPayload, IcmpCode, Immediate, Log, Lookup, Masquerade, Meta, MetaType, Nat,
-        NatType, Register, Reject, RejectType, TCPHeaderField, TransportHeaderField, VerdictKind,
+        Bitwise, Cmp, CmpOp, Conntrack, ConntrackKey, Counter, ExpressionList, ExpressionVariant,
+        HeaderField, HighLevelPayload, IcmpCode, Immediate, Limit, Log, LogPrefix, Lookup,
+        Masquerade, Meta, MetaType, Nat, NatType, Payload, Queue, QueueFlags, RawExpression,
+        Register, Reject, RejectType, TCPHeaderField, Tproxy, TransportHeaderField, VerdictKind,
     },
+    groups::{LogGroup, QueueNum},
+    nlmsg::NfNetlinkDeserializable,
     set::SetBuilder,
     sys::{
         NFTA_BITWISE_DREG, NFTA_BITWISE_LEN, NFTA_BITWISE_MASK, NFTA_BITWISE_SREG,
         NFTA_BITWISE_XOR, NFTA_CMP_DATA, NFTA_CMP_OP, NFTA_CMP_SREG, NFTA_COUNTER_BYTES,
         NFTA_COUNTER_PACKETS, NFTA_CT_DREG, NFTA_CT_KEY, NFTA_DATA_VALUE, NFTA_DATA_VERDICT,
-        NFTA_EXPR_DATA, NFTA_EXPR_NAME, NFTA_IMMEDIATE_DATA, NFTA_IMMEDIATE_DREG, NFTA_LIST_ELEM,
-        NFTA_LOG_GROUP, NFTA_LOG_PREFIX, NFTA_LOOKUP_SET, NFTA_LOOKUP_SREG, NFTA_META_DREG,
-        NFTA_META_KEY, NFTA_NAT_FAMILY, NFTA_NAT_REG_ADDR_MIN, NFTA_NAT_TYPE, NFTA_PAYLOAD_BASE,
-        NFTA_PAYLOAD_DREG, NFTA_PAYLOAD_LEN, NFTA_PAYLOAD_OFFSET, NFTA_REJECT_ICMP_CODE,
+        NFTA_EXPR_DATA, NFTA_EXPR_NAME, NFTA_IMMEDIATE_DATA, NFTA_IMMEDIATE_DREG, NFTA_LIMIT_RATE,
+        NFTA_LIMIT_UNIT, NFTA_LIST_ELEM, NFTA_LOG_GROUP, NFTA_LOG_PREFIX, NFTA_LOOKUP_SET,
+        NFTA_LOOKUP_SREG, NFTA_META_DREG, NFTA_META_KEY, NFTA_NAT_FAMILY, NFTA_NAT_REG_ADDR_MIN,
+        NFTA_NAT_TYPE, NFTA_PAYLOAD_BASE, NFTA_PAYLOAD_DREG, NFTA_PAYLOAD_LEN, NFTA_PAYLOAD_OFFSET,
+        NFTA_QUEUE_FLAGS, NFTA_QUEUE_NUM, NFTA_QUEUE_TOTAL, NFTA_REJECT_ICMP_CODE,
         NFTA_REJECT_TYPE, NFTA_RULE_CHAIN, NFTA_RULE_EXPRESSIONS, NFTA_RULE_TABLE,
-        NFTA_VERDICT_CODE, NFT_CMP_EQ, NFT_CT_STATE, NFT_META_PROTOCOL, NFT_NAT_SNAT,
+        NFTA_TPROXY_FAMILY, NFTA_TPROXY_REG_PORT, NFTA_VERDICT_CODE, NFT_CMP_EQ, NFT_CT_STATE,
+        NFT_LIMIT_PKTS, NFT_META_L4PROTO, NFT_META_PROTOCOL, NFT_NAT_SNAT,
         NFT_PAYLOAD_TRANSPORT_HEADER, NFT_REG_1, NFT_REG_VERDICT, NFT_REJECT_ICMPX_UNREACH,
     },
     tests::{get_test_table, SET_NAME},
-    ProtocolFamily,
+    with_decode_mode, DecodeMode, Protocol, ProtocolFamily, Rule,
 };
 
 use super::{get_test_nlmsg, get_test_rule, NetlinkExpr, CHAIN_NAME, TABLE_NAME};
@@ -285,6 +291,168 @@ fn log_expr_is_valid() {
     );
 }
 
+#[test]
+fn log_prefix_is_checked() {
+    assert_eq!(LogPrefix::new("ssh: ").unwrap().as_str(), "ssh: ");
+    assert!(LogPrefix::try_from("a".repeat(127).as_str()).is_ok());
+    assert!(matches!(
+        LogPrefix::try_from("a".repeat(128)),
+        Err(BuilderError::TooLongLogPrefix)
+    ));
+    assert!(matches!(
+        LogPrefix::new("ssh\0"),
+        Err(BuilderError::NulInLogPrefix)
+    ));
+
+    let prefix = LogPrefix::new("mockprefix").unwrap();
+    assert_eq!(
+        Log::new(None, Some(prefix.clone())).unwrap(),
+        Log::new(None, Some("mockprefix")).unwrap()
+    );
+    assert!(get_test_rule()
+        .log_limited("ssh\0", Limit::new(10, 1))
+        .is_err());
+}
+
+#[test]
+fn group_numbers_are_checked() {
+    assert_eq!(LogGroup::new(5u32).unwrap(), LogGroup::from(5));
+    assert_eq!(LogGroup::new(65535u64).unwrap().number(), u16::MAX);
+    assert!(matches!(
+        LogGroup::new(65536u32),
+        Err(BuilderError::InvalidLogGroup)
+    ));
+    assert!(matches!(
+        LogGroup::new(-1i32),
+        Err(BuilderError::InvalidLogGroup)
+    ));
+    assert!(matches!(
+        QueueNum::new(1usize << 16),
+        Err(BuilderError::InvalidQueueNumber)
+    ));
+    assert_eq!(QueueNum::new(3u32).unwrap().number(), 3);
+
+    let log = Log::default().with_group(LogGroup::new(1337u32).unwrap());
+    assert_eq!(log, Log::new(Some(1337), None::<String>).unwrap());
+    assert_eq!(log.get_group(), Some(&LogGroup::from(1337)));
+}
+
+#[test]
+fn queue_expr_is_valid() {
+    let queue = Queue::new(QueueNum::new(3u32).unwrap())
+        .spread(4)
+        .with_queue_flags(QueueFlags::BYPASS);
+    assert_eq!(queue.to_string(), "queue flags bypass to 3-6");
+    assert_eq!(Queue::new(0u16).to_string(), "queue to 0");
+    let mut rule = get_test_rule().with_expressions(ExpressionList::default().with_value(queue));
+
+    let mut buf = Vec::new();
+    let (_nlmsghdr, _nfgenmsg, raw_expr) = get_test_nlmsg(&mut buf, &mut rule);
+    assert_eq!(
+        raw_expr,
+        NetlinkExpr::List(vec![
+            NetlinkExpr::Final(NFTA_RULE_TABLE, TABLE_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Final(NFTA_RULE_CHAIN, CHAIN_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Nested(
+                NFTA_RULE_EXPRESSIONS,
+                vec![NetlinkExpr::Nested(
+                    NFTA_LIST_ELEM,
+                    vec![
+                        NetlinkExpr::Final(NFTA_EXPR_NAME, b"queue".to_vec()),
+                        NetlinkExpr::Nested(
+                            NFTA_EXPR_DATA,
+                            vec![
+                                NetlinkExpr::Final(NFTA_QUEUE_NUM, 3u16.to_be_bytes().to_vec()),
+                                NetlinkExpr::Final(NFTA_QUEUE_TOTAL, 4u16.to_be_bytes().to_vec()),
+                                NetlinkExpr::Final(NFTA_QUEUE_FLAGS, 1u16.to_be_bytes().to_vec()),
+                            ]
+                        )
+                    ]
+                )]
+            )
+        ])
+        .to_raw()
+    );
+    assert_eq!(Rule::deserialize(&buf).unwrap().0, rule);
+}
+
+#[test]
+fn log_limited_rule_is_valid() {
+    let mut rule = get_test_rule()
+        .log_limited("mockprefix", Limit::new(10, 1))
+        .expect("Could not build a rate-limited log rule");
+
+    let mut buf = Vec::new();
+    let (nlmsghdr, _nfgenmsg, raw_expr) = get_test_nlmsg(&mut buf, &mut rule);
+    assert_eq!(nlmsghdr.nlmsg_len, 132);
+
+    assert_eq!(
+        raw_expr,
+        NetlinkExpr::List(vec![
+            NetlinkExpr::Final(NFTA_RULE_TABLE, TABLE_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Final(NFTA_RULE_CHAIN, CHAIN_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Nested(
+                NFTA_RULE_EXPRESSIONS,
+                vec![
+                    NetlinkExpr::Nested(
+                        NFTA_LIST_ELEM,
+                        vec![
+                            NetlinkExpr::Final(NFTA_EXPR_NAME, b"limit".to_vec()),
+                            NetlinkExpr::Nested(
+                                NFTA_EXPR_DATA,
+                                vec![
+                                    NetlinkExpr::Final(
+                                        NFTA_LIMIT_RATE,
+                                        10u64.to_be_bytes().to_vec()
+                                    ),
+                                    NetlinkExpr::Final(
+                                        NFTA_LIMIT_UNIT,
+                                        1u64.to_be_bytes().to_vec()
+                                    ),
+                                ]
+                            )
+                        ]
+                    ),
+                    NetlinkExpr::Nested(
+                        NFTA_LIST_ELEM,
+                        vec![
+                            NetlinkExpr::Final(NFTA_EXPR_NAME, b"log".to_vec()),
+                            NetlinkExpr::Nested(
+                                NFTA_EXPR_DATA,
+                                vec![NetlinkExpr::Final(NFTA_LOG_PREFIX, b"mockprefix".to_vec())]
+                            )
+                        ]
+                    )
+                ]
+            )
+        ])
+        .to_raw()
+    );
+}
+
+#[test]
+fn limit_constructors() {
+    assert_eq!(
+        Limit::per_second(10).burst(5),
+        Limit::default()
+            .with_rate(10u64)
+            .with_unit(1u64)
+            .with_limit_type(NFT_LIMIT_PKTS)
+            .with_burst(5u32)
+    );
+    assert_eq!(Limit::per_minute(3).get_unit(), Some(&60));
+    assert_eq!(Limit::per_hour(3).get_unit(), Some(&3600));
+    assert_eq!(Limit::per_day(3).get_unit(), Some(&86400));
+    assert_eq!(
+        Limit::per_second(10).over().to_string(),
+        "limit rate over 10/1s"
+    );
+    assert_eq!(
+        Limit::bytes_per_second(10240).burst(2048).to_string(),
+        "limit rate 10240/1s bytes burst 2048"
+    );
+}
+
 #[test]
 fn lookup_expr_is_valid() {
     let table = get_test_table();
@@ -433,7 +601,7 @@ fn nat_expr_is_valid() {
                                 ),
                                 NetlinkExpr::Final(
                                     NFTA_NAT_FAMILY,
-                                    (ProtocolFamily::Ipv4 as u32).to_be_bytes().to_vec(),
+                                    (libc::NFPROTO_IPV4 as u32).to_be_bytes().to_vec(),
                                 ),
                                 NetlinkExpr::Final(
                                     NFTA_NAT_REG_ADDR_MIN,
@@ -543,6 +711,49 @@ fn reject_expr_is_valid() {
     );
 }
 
+#[test]
+fn tproxy_expr_is_valid() {
+    let tproxy = Tproxy::default()
+        .with_family(ProtocolFamily::Ipv4)
+        .with_port_register(Register::Reg1);
+    let mut rule = get_test_rule().with_expressions(vec![tproxy]);
+
+    let mut buf = Vec::new();
+    let (nlmsghdr, _nfgenmsg, raw_expr) = get_test_nlmsg(&mut buf, &mut rule);
+    assert_eq!(nlmsghdr.nlmsg_len, 92);
+
+    assert_eq!(
+        raw_expr,
+        NetlinkExpr::List(vec![
+            NetlinkExpr::Final(NFTA_RULE_TABLE, TABLE_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Final(NFTA_RULE_CHAIN, CHAIN_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Nested(
+                NFTA_RULE_EXPRESSIONS,
+                vec![NetlinkExpr::Nested(
+                    NFTA_LIST_ELEM,
+                    vec![
+                        NetlinkExpr::Final(NFTA_EXPR_NAME, b"tproxy".to_vec()),
+                        NetlinkExpr::Nested(
+                            NFTA_EXPR_DATA,
+                            vec![
+                                NetlinkExpr::Final(
+                                    NFTA_TPROXY_FAMILY,
+                                    (libc::NFPROTO_IPV4 as u32).to_be_bytes().to_vec(),
+                                ),
+                                NetlinkExpr::Final(
+                                    NFTA_TPROXY_REG_PORT,
+                                    NFT_REG_1.to_be_bytes().to_vec()
+                                )
+                            ]
+                        )
+                    ]
+                )]
+            )
+        ])
+        .to_raw()
+    );
+}
+
 #[test]
 fn verdict_expr_is_valid() {
     let verdict = Immediate::new_verdict(VerdictKind::Drop);
@@ -589,3 +800,189 @@ fn verdict_expr_is_valid() {
         .to_raw()
     );
 }
+
+#[test]
+fn dport_rule_matches_nft_output() {
+    // `nft --debug=netlink add rule inet mocktable mockchain tcp dport 22` prints:
+    //   [ meta load l4proto => reg 1 ]
+    //   [ cmp eq reg 1 0x00000006 ]
+    //   [ payload load 2b @ transport header + 2 => reg 1 ]
+    //   [ cmp eq reg 1 0x00001600 ]
+    // where the registers are dumped as 32-bit words in host (little endian) byte order.
+    let mut rule = get_test_rule().dport(22, Protocol::TCP);
+
+    let mut buf = Vec::new();
+    let (_nlmsghdr, _nfgenmsg, raw_expr) = get_test_nlmsg(&mut buf, &mut rule);
+
+    let expr = |name: &[u8], data| {
+        NetlinkExpr::Nested(
+            NFTA_LIST_ELEM,
+            vec![
+                NetlinkExpr::Final(NFTA_EXPR_NAME, name.to_vec()),
+                NetlinkExpr::Nested(NFTA_EXPR_DATA, data),
+            ],
+        )
+    };
+    let cmp_eq = |data: Vec<u8>| {
+        expr(
+            b"cmp",
+            vec![
+                NetlinkExpr::Final(NFTA_CMP_SREG, NFT_REG_1.to_be_bytes().to_vec()),
+                NetlinkExpr::Final(NFTA_CMP_OP, NFT_CMP_EQ.to_be_bytes().to_vec()),
+                NetlinkExpr::Nested(
+                    NFTA_CMP_DATA,
+                    vec![NetlinkExpr::Final(NFTA_DATA_VALUE, data)],
+                ),
+            ],
+        )
+    };
+    assert_eq!(
+        raw_expr,
+        NetlinkExpr::List(vec![
+            NetlinkExpr::Final(NFTA_RULE_TABLE, TABLE_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Final(NFTA_RULE_CHAIN, CHAIN_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Nested(
+                NFTA_RULE_EXPRESSIONS,
+                vec![
+                    expr(
+                        b"meta",
+                        vec![
+                            NetlinkExpr::Final(NFTA_META_DREG, NFT_REG_1.to_be_bytes().to_vec()),
+                            NetlinkExpr::Final(
+                                NFTA_META_KEY,
+                                NFT_META_L4PROTO.to_be_bytes().to_vec()
+                            ),
+                        ]
+                    ),
+                    cmp_eq(vec![0x06]),
+                    expr(
+                        b"payload",
+                        vec![
+                            NetlinkExpr::Final(NFTA_PAYLOAD_DREG, NFT_REG_1.to_be_bytes().to_vec()),
+                            NetlinkExpr::Final(
+                                NFTA_PAYLOAD_BASE,
+                                NFT_PAYLOAD_TRANSPORT_HEADER.to_be_bytes().to_vec()
+                            ),
+                            NetlinkExpr::Final(NFTA_PAYLOAD_OFFSET, 2u32.to_be_bytes().to_vec()),
+                            NetlinkExpr::Final(NFTA_PAYLOAD_LEN, 2u32.to_be_bytes().to_vec()),
+                        ]
+                    ),
+                    cmp_eq(vec![0x00, 0x16]),
+                ]
+            )
+        ])
+        .to_raw()
+    );
+}
+
+#[test]
+fn expressions_display() {
+    let dport = HighLevelPayload::Transport(TransportHeaderField::Tcp(TCPHeaderField::Dport));
+    let rule = get_test_rule()
+        .with_expr(Meta::new(MetaType::IifName))
+        .with_expr(Cmp::new(CmpOp::Eq, *b"eth0\0"))
+        .with_expr(dport.build())
+        .with_expr(Cmp::new(CmpOp::Neq, 22u16.to_be_bytes()))
+        .with_expr(Bitwise::new([255, 255, 255, 0], [0, 0, 0, 0]).unwrap())
+        .with_expr(Counter::default().with_nb_packets(3u64))
+        .with_expr(Log::new(None, Some("ssh")).unwrap())
+        .with_expr(Immediate::new_verdict(VerdictKind::Jump {
+            chain: "zone".to_string(),
+        }));
+    let displayed: Vec<String> = rule
+        .get_expressions()
+        .unwrap()
+        .iter()
+        .map(|expr| expr.to_string())
+        .collect();
+    assert_eq!(
+        displayed,
+        [
+            "meta iifname -> reg1",
+            "cmp reg1 == \"eth0\"",
+            "payload @transport,16,16 -> reg1",
+            "cmp reg1 != 22",
+            "bitwise reg1 = (reg1 & 0xffffff00) ^ 0x00000000",
+            "counter packets 3 bytes ?",
+            "log prefix \"ssh\"",
+            "immediate jump zone -> verdict",
+        ]
+    );
+
+    // the offsets in bits do not fit in the u32 offsets in bytes
+    let payload = Payload::default()
+        .with_base(NFT_PAYLOAD_TRANSPORT_HEADER)
+        .with_offset(u32::MAX)
+        .with_len(u32::MAX);
+    assert_eq!(
+        payload.to_string(),
+        "payload @transport,34359738360,34359738360"
+    );
+}
+
+#[test]
+fn counter_arithmetic() {
+    use crate::expr::CounterSnapshot;
+    use std::time::Duration;
+
+    let a = Counter::new(2, 120);
+    let b = Counter::default().with_nb_packets(1u64);
+    assert_eq!(a.merge(&b), Counter::new(3, 120));
+    assert_eq!(a.clone() + b.clone(), Counter::new(3, 120));
+    assert_eq!([a.clone(), b].iter().sum::<Counter>(), Counter::new(3, 120));
+    assert_eq!(
+        Counter::new(u64::MAX, 1).merge(&Counter::new(1, 1)),
+        Counter::new(u64::MAX, 2)
+    );
+
+    // a counter lower than its previous reading was reset in between
+    assert_eq!(Counter::new(5, 300).delta_since(&a), Counter::new(3, 180));
+    assert_eq!(Counter::new(1, 60).delta_since(&a), Counter::new(1, 60));
+
+    let previous = CounterSnapshot::now(a);
+    let current = CounterSnapshot {
+        counter: Counter::new(12, 520),
+        taken_at: previous.taken_at + Duration::from_secs(2),
+    };
+    assert_eq!(current.delta_since(&previous), Counter::new(10, 400));
+    assert_eq!(current.rates_since(&previous), Some((5.0, 200.0)));
+    assert_eq!(previous.rates_since(&current), None);
+    assert_eq!(current.rates_since(&current), None);
+}
+
+#[test]
+fn strict_decode_mode() {
+    let counter = NetlinkExpr::List(vec![
+        NetlinkExpr::Final(NFTA_COUNTER_BYTES, 60u64.to_be_bytes().to_vec()),
+        NetlinkExpr::Final(42, vec![0; 4]),
+    ])
+    .to_raw();
+    let (decoded, _) = Counter::deserialize(&counter).unwrap();
+    assert_eq!(decoded, Counter::default().with_nb_bytes(60u64));
+    assert!(matches!(
+        with_decode_mode(DecodeMode::Strict, || Counter::deserialize(&counter)),
+        Err(DecodeError::UnknownAttribute(42, _))
+    ));
+
+    let expr = NetlinkExpr::List(vec![
+        NetlinkExpr::Final(NFTA_EXPR_NAME, b"synproxy".to_vec()),
+        NetlinkExpr::Nested(NFTA_EXPR_DATA, vec![NetlinkExpr::Final(1, vec![0; 4])]),
+    ])
+    .to_raw();
+    let (decoded, _) = RawExpression::deserialize(&expr).unwrap();
+    assert!(matches!(
+        decoded.get_data(),
+        Some(ExpressionVariant::ExpressionRaw(_))
+    ));
+    assert!(matches!(
+        with_decode_mode(DecodeMode::Strict, || RawExpression::deserialize(&expr)),
+        Err(DecodeError::UnknownExpression(name)) if name == "synproxy"
+    ));
+
+    // the previous mode is restored, including in nested sections
+    with_decode_mode(DecodeMode::Strict, || {
+        assert!(with_decode_mode(DecodeMode::Lenient, || Counter::deserialize(&counter)).is_ok());
+        assert_eq!(crate::decode_mode(), DecodeMode::Strict);
+    });
+    assert_eq!(crate::decode_mode(), DecodeMode::Lenient);
+}
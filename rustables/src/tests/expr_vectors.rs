@@ -0,0 +1,287 @@
+//! Test vectors shared by the serialization and the deserialization of the expressions: every
+//! expression listed in [`vectors`] must be written as its attributes, and be decoded back from
+//! them.
+
+use crate::{
+    expr::{
+        Cmp, CmpOp, Counter, Hash, IcmpCode, Limit, Log, Masquerade, Meta, MetaType, Nat, NatType,
+        Numgen, Payload, RawExpression, Register, Reject, RejectType, Socket,
+    },
+    nlmsg::{NfNetlinkAttribute, NfNetlinkDeserializable},
+    sys::{
+        NFTA_CMP_DATA, NFTA_CMP_OP, NFTA_CMP_SREG, NFTA_COUNTER_BYTES, NFTA_COUNTER_PACKETS,
+        NFTA_DATA_VALUE, NFTA_EXPR_DATA, NFTA_EXPR_NAME, NFTA_HASH_DREG, NFTA_HASH_LEN,
+        NFTA_HASH_MODULUS, NFTA_HASH_OFFSET, NFTA_HASH_SEED, NFTA_HASH_SREG, NFTA_HASH_TYPE,
+        NFTA_LIMIT_BURST, NFTA_LIMIT_FLAGS, NFTA_LIMIT_RATE, NFTA_LIMIT_TYPE, NFTA_LIMIT_UNIT,
+        NFTA_LOG_GROUP, NFTA_LOG_PREFIX, NFTA_MASQ_REG_PROTO_MAX, NFTA_MASQ_REG_PROTO_MIN,
+        NFTA_META_DREG, NFTA_META_KEY, NFTA_META_SREG, NFTA_NAT_FAMILY, NFTA_NAT_REG_ADDR_MIN,
+        NFTA_NAT_REG_PROTO_MIN, NFTA_NAT_TYPE, NFTA_NG_DREG, NFTA_NG_MODULUS, NFTA_NG_OFFSET,
+        NFTA_NG_TYPE, NFTA_PAYLOAD_BASE, NFTA_PAYLOAD_DREG, NFTA_PAYLOAD_LEN, NFTA_PAYLOAD_OFFSET,
+        NFTA_PAYLOAD_SREG, NFTA_REJECT_ICMP_CODE, NFTA_REJECT_TYPE, NFTA_SOCKET_DREG,
+        NFTA_SOCKET_KEY, NFTA_SOCKET_LEVEL, NFT_CMP_EQ, NFT_CMP_GT, NFT_CMP_GTE, NFT_CMP_LT,
+        NFT_CMP_LTE, NFT_CMP_NEQ, NFT_HASH_JENKINS, NFT_HASH_SYM, NFT_LIMIT_PKTS, NFT_META_IIFNAME,
+        NFT_META_MARK, NFT_NAT_DNAT, NFT_NG_INCREMENTAL, NFT_PAYLOAD_NETWORK_HEADER, NFT_REG_1,
+        NFT_REG_2, NFT_REJECT_ICMPX_PORT_UNREACH, NFT_REJECT_ICMPX_UNREACH, NFT_REJECT_TCP_RST,
+        NFT_SOCKET_CGROUPV2,
+    },
+    ProtocolFamily,
+};
+
+use super::NetlinkExpr;
+
+/// An expression, with the name and the attributes it is written as.
+struct Vector {
+    expr: RawExpression,
+    name: &'static str,
+    attributes: Vec<NetlinkExpr>,
+}
+
+impl Vector {
+    fn new(
+        expr: impl Into<RawExpression>,
+        name: &'static str,
+        attributes: Vec<NetlinkExpr>,
+    ) -> Self {
+        Vector {
+            expr: expr.into(),
+            name,
+            attributes,
+        }
+    }
+
+    fn to_raw(&self) -> Vec<u8> {
+        NetlinkExpr::List(vec![
+            NetlinkExpr::Final(NFTA_EXPR_NAME, self.name.as_bytes().to_vec()),
+            NetlinkExpr::Nested(NFTA_EXPR_DATA, self.attributes.clone()),
+        ])
+        .to_raw()
+    }
+}
+
+fn u32_attr(ty: u16, val: u32) -> NetlinkExpr {
+    NetlinkExpr::Final(ty, val.to_be_bytes().to_vec())
+}
+
+fn u64_attr(ty: u16, val: u64) -> NetlinkExpr {
+    NetlinkExpr::Final(ty, val.to_be_bytes().to_vec())
+}
+
+fn vectors() -> Vec<Vector> {
+    let mut v = vec![
+        Vector::new(
+            Nat::default()
+                .with_nat_type(NatType::DNat)
+                .with_family(ProtocolFamily::Ipv4)
+                .with_ip_register(Register::Reg1)
+                .with_port_register(Register::Reg2),
+            "nat",
+            vec![
+                u32_attr(NFTA_NAT_TYPE, NFT_NAT_DNAT),
+                u32_attr(NFTA_NAT_FAMILY, libc::NFPROTO_IPV4 as u32),
+                u32_attr(NFTA_NAT_REG_ADDR_MIN, NFT_REG_1),
+                u32_attr(NFTA_NAT_REG_PROTO_MIN, NFT_REG_2),
+            ],
+        ),
+        Vector::new(
+            Reject::default()
+                .with_type(RejectType::IcmpxUnreach)
+                .with_icmp_code(IcmpCode::PortUnreach),
+            "reject",
+            vec![
+                u32_attr(NFTA_REJECT_TYPE, NFT_REJECT_ICMPX_UNREACH),
+                NetlinkExpr::Final(
+                    NFTA_REJECT_ICMP_CODE,
+                    vec![NFT_REJECT_ICMPX_PORT_UNREACH as u8],
+                ),
+            ],
+        ),
+        Vector::new(
+            Reject::default().with_type(RejectType::TcpRst),
+            "reject",
+            vec![u32_attr(NFTA_REJECT_TYPE, NFT_REJECT_TCP_RST)],
+        ),
+        // a payload loaded in a register
+        Vector::new(
+            Payload::default()
+                .with_dreg(Register::Reg1)
+                .with_base(NFT_PAYLOAD_NETWORK_HEADER)
+                .with_offset(12u32)
+                .with_len(4u32),
+            "payload",
+            vec![
+                u32_attr(NFTA_PAYLOAD_DREG, NFT_REG_1),
+                u32_attr(NFTA_PAYLOAD_BASE, NFT_PAYLOAD_NETWORK_HEADER),
+                u32_attr(NFTA_PAYLOAD_OFFSET, 12),
+                u32_attr(NFTA_PAYLOAD_LEN, 4),
+            ],
+        ),
+        // a payload written from a register
+        Vector::new(
+            Payload::default()
+                .with_base(NFT_PAYLOAD_NETWORK_HEADER)
+                .with_offset(16u32)
+                .with_len(4u32)
+                .with_sreg(Register::Reg2),
+            "payload",
+            vec![
+                u32_attr(NFTA_PAYLOAD_BASE, NFT_PAYLOAD_NETWORK_HEADER),
+                u32_attr(NFTA_PAYLOAD_OFFSET, 16),
+                u32_attr(NFTA_PAYLOAD_LEN, 4),
+                u32_attr(NFTA_PAYLOAD_SREG, NFT_REG_2),
+            ],
+        ),
+        Vector::new(
+            Meta::default()
+                .with_dreg(Register::Reg1)
+                .with_key(MetaType::IifName),
+            "meta",
+            vec![
+                u32_attr(NFTA_META_DREG, NFT_REG_1),
+                u32_attr(NFTA_META_KEY, NFT_META_IIFNAME),
+            ],
+        ),
+        Vector::new(
+            Meta::default()
+                .with_key(MetaType::Mark)
+                .with_sreg(Register::Reg1),
+            "meta",
+            vec![
+                u32_attr(NFTA_META_KEY, NFT_META_MARK),
+                u32_attr(NFTA_META_SREG, NFT_REG_1),
+            ],
+        ),
+        Vector::new(
+            Socket::cgroupv2(2),
+            "socket",
+            vec![
+                u32_attr(NFTA_SOCKET_KEY, NFT_SOCKET_CGROUPV2),
+                u32_attr(NFTA_SOCKET_DREG, NFT_REG_1),
+                u32_attr(NFTA_SOCKET_LEVEL, 2),
+            ],
+        ),
+        Vector::new(
+            Hash::jhash(4, 3)
+                .with_seed(0xdeadbeef_u32)
+                .with_offset(100u32),
+            "hash",
+            vec![
+                u32_attr(NFTA_HASH_SREG, NFT_REG_1),
+                u32_attr(NFTA_HASH_DREG, NFT_REG_1),
+                u32_attr(NFTA_HASH_LEN, 4),
+                u32_attr(NFTA_HASH_MODULUS, 3),
+                u32_attr(NFTA_HASH_SEED, 0xdeadbeef),
+                u32_attr(NFTA_HASH_OFFSET, 100),
+                u32_attr(NFTA_HASH_TYPE, NFT_HASH_JENKINS),
+            ],
+        ),
+        Vector::new(
+            Hash::symhash(2),
+            "hash",
+            vec![
+                u32_attr(NFTA_HASH_DREG, NFT_REG_1),
+                u32_attr(NFTA_HASH_MODULUS, 2),
+                u32_attr(NFTA_HASH_TYPE, NFT_HASH_SYM),
+            ],
+        ),
+        Vector::new(
+            Numgen::incremental(2).with_offset(1u32),
+            "numgen",
+            vec![
+                u32_attr(NFTA_NG_DREG, NFT_REG_1),
+                u32_attr(NFTA_NG_MODULUS, 2),
+                u32_attr(NFTA_NG_TYPE, NFT_NG_INCREMENTAL),
+                u32_attr(NFTA_NG_OFFSET, 1),
+            ],
+        ),
+        Vector::new(
+            Masquerade::default()
+                .with_port_min_register(Register::Reg1)
+                .with_port_max_register(Register::Reg2),
+            "masq",
+            vec![
+                u32_attr(NFTA_MASQ_REG_PROTO_MIN, NFT_REG_1),
+                u32_attr(NFTA_MASQ_REG_PROTO_MAX, NFT_REG_2),
+            ],
+        ),
+        Vector::new(
+            Counter::default()
+                .with_nb_bytes(1500u64)
+                .with_nb_packets(3u64),
+            "counter",
+            vec![
+                u64_attr(NFTA_COUNTER_BYTES, 1500),
+                u64_attr(NFTA_COUNTER_PACKETS, 3),
+            ],
+        ),
+        Vector::new(
+            Limit::default()
+                .with_rate(10u64)
+                .with_unit(60u64)
+                .with_burst(5u32)
+                .with_limit_type(NFT_LIMIT_PKTS)
+                .with_flags(0u32),
+            "limit",
+            vec![
+                u64_attr(NFTA_LIMIT_RATE, 10),
+                u64_attr(NFTA_LIMIT_UNIT, 60),
+                u32_attr(NFTA_LIMIT_BURST, 5),
+                u32_attr(NFTA_LIMIT_TYPE, NFT_LIMIT_PKTS),
+                u32_attr(NFTA_LIMIT_FLAGS, 0),
+            ],
+        ),
+        Vector::new(
+            Log::new(Some(2), Some("ssh: ")).unwrap(),
+            "log",
+            vec![
+                NetlinkExpr::Final(NFTA_LOG_GROUP, 2u16.to_be_bytes().to_vec()),
+                NetlinkExpr::Final(NFTA_LOG_PREFIX, b"ssh: ".to_vec()),
+            ],
+        ),
+    ];
+    let cmp_ops = [
+        (CmpOp::Eq, NFT_CMP_EQ),
+        (CmpOp::Neq, NFT_CMP_NEQ),
+        (CmpOp::Lt, NFT_CMP_LT),
+        (CmpOp::Lte, NFT_CMP_LTE),
+        (CmpOp::Gt, NFT_CMP_GT),
+        (CmpOp::Gte, NFT_CMP_GTE),
+    ];
+    v.extend(cmp_ops.into_iter().map(|(op, value)| {
+        Vector::new(
+            Cmp::new(op, 1024u16.to_be_bytes()),
+            "cmp",
+            vec![
+                u32_attr(NFTA_CMP_SREG, NFT_REG_1),
+                u32_attr(NFTA_CMP_OP, value),
+                NetlinkExpr::Nested(
+                    NFTA_CMP_DATA,
+                    vec![NetlinkExpr::Final(
+                        NFTA_DATA_VALUE,
+                        1024u16.to_be_bytes().to_vec(),
+                    )],
+                ),
+            ],
+        )
+    }));
+    v
+}
+
+#[test]
+fn expression_vectors_are_written() {
+    for vector in vectors() {
+        let mut buf = vec![0; vector.expr.get_size()];
+        vector.expr.write_payload(&mut buf);
+        assert_eq!(buf, vector.to_raw(), "while writing {}", vector.expr);
+    }
+}
+
+#[test]
+fn expression_vectors_are_decoded() {
+    for vector in vectors() {
+        let raw = vector.to_raw();
+        let (expr, remaining) = RawExpression::deserialize(&raw)
+            .unwrap_or_else(|e| panic!("while decoding {}: {:?}", vector.expr, e));
+        assert!(remaining.is_empty());
+        assert_eq!(expr, vector.expr, "while decoding {}", vector.expr);
+    }
+}
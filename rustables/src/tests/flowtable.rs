@@ -0,0 +1,103 @@
+use crate::{
+    error::BuilderError,
+    nlmsg::{get_operation_from_nlmsghdr_type, NfNetlinkDeserializable},
+    sys::{
+        NFTA_DEVICE_NAME, NFTA_FLOWTABLE_FLAGS, NFTA_FLOWTABLE_HOOK, NFTA_FLOWTABLE_HOOK_DEVS,
+        NFTA_FLOWTABLE_HOOK_NUM, NFTA_FLOWTABLE_HOOK_PRIORITY, NFTA_FLOWTABLE_NAME,
+        NFTA_FLOWTABLE_TABLE, NFT_MSG_NEWFLOWTABLE,
+    },
+    Flowtable, FlowtableFlags, FlowtableHook,
+};
+
+use super::{get_test_nlmsg, get_test_table, NetlinkExpr, TABLE_NAME};
+
+const FLOWTABLE_NAME: &str = "mockflowtable";
+
+fn get_test_flowtable() -> Flowtable {
+    Flowtable::new(&get_test_table())
+        .with_name(FLOWTABLE_NAME)
+        .with_hook(FlowtableHook::new(-100, ["eth0", "eth1"]))
+        .with_flags(FlowtableFlags::HW_OFFLOAD.bits())
+}
+
+#[test]
+fn new_flowtable() {
+    let mut flowtable = get_test_flowtable();
+    let mut buf = Vec::new();
+    let (nlmsghdr, _nfgenmsg, raw_expr) = get_test_nlmsg(&mut buf, &mut flowtable);
+    assert_eq!(
+        get_operation_from_nlmsghdr_type(nlmsghdr.nlmsg_type),
+        NFT_MSG_NEWFLOWTABLE as u8
+    );
+
+    assert_eq!(
+        raw_expr,
+        NetlinkExpr::List(vec![
+            NetlinkExpr::Final(NFTA_FLOWTABLE_TABLE, TABLE_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Final(NFTA_FLOWTABLE_NAME, FLOWTABLE_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Nested(
+                NFTA_FLOWTABLE_HOOK,
+                vec![
+                    NetlinkExpr::Final(
+                        NFTA_FLOWTABLE_HOOK_NUM,
+                        (libc::NF_NETDEV_INGRESS as u32).to_be_bytes().to_vec()
+                    ),
+                    NetlinkExpr::Final(
+                        NFTA_FLOWTABLE_HOOK_PRIORITY,
+                        (-100i32).to_be_bytes().to_vec()
+                    ),
+                    NetlinkExpr::Nested(
+                        NFTA_FLOWTABLE_HOOK_DEVS,
+                        vec![
+                            NetlinkExpr::Final(NFTA_DEVICE_NAME, b"eth0".to_vec()),
+                            NetlinkExpr::Final(NFTA_DEVICE_NAME, b"eth1".to_vec()),
+                        ]
+                    ),
+                ]
+            ),
+            NetlinkExpr::Final(
+                NFTA_FLOWTABLE_FLAGS,
+                FlowtableFlags::HW_OFFLOAD.bits().to_be_bytes().to_vec()
+            ),
+        ])
+        .to_raw()
+    );
+}
+
+#[test]
+fn parse_flowtable() {
+    let mut flowtable = get_test_flowtable();
+    let mut buf = Vec::new();
+    get_test_nlmsg(&mut buf, &mut flowtable);
+
+    let (deserialized_flowtable, remaining) =
+        Flowtable::deserialize(&buf).expect("Couldn't deserialize the object");
+    assert_eq!(flowtable, deserialized_flowtable);
+    assert_eq!(remaining.len(), 0);
+    assert_eq!(
+        deserialized_flowtable.get_flowtable_flags(),
+        Some(FlowtableFlags::HW_OFFLOAD)
+    );
+    // the priorities before the one of the filter chains are negative
+    assert_eq!(
+        deserialized_flowtable.get_hook().unwrap().get_priority(),
+        Some(&-100)
+    );
+}
+
+#[test]
+fn flowtable_requires_devices() {
+    assert!(get_test_flowtable().validate().is_ok());
+
+    let flowtable = Flowtable::new(&get_test_table()).with_name(FLOWTABLE_NAME);
+    assert!(matches!(
+        flowtable.validate(),
+        Err(BuilderError::MissingFlowtableDevices)
+    ));
+
+    let flowtable = flowtable.with_hook(FlowtableHook::new(0, Vec::<String>::new()));
+    assert!(matches!(
+        flowtable.validate(),
+        Err(BuilderError::MissingFlowtableDevices)
+    ));
+}
@@ -0,0 +1,205 @@
+use crate::error::IptablesError;
+use crate::expr::{ExpressionVariant, Reject};
+use crate::iptables::{parse_rule, IptablesRuleset};
+use crate::{ChainPolicy, ChainType, ProtocolFamily, Table};
+
+const IPTABLES_SAVE: &str = r#"# Generated by iptables-save v1.8.9 on Thu Oct 15 10:12:30 2026
+*nat
+:PREROUTING ACCEPT [0:0]
+:INPUT ACCEPT [0:0]
+:OUTPUT ACCEPT [0:0]
+:POSTROUTING ACCEPT [0:0]
+-A POSTROUTING -s 10.0.0.0/8 -o eth0 -j MASQUERADE
+COMMIT
+# Completed on Thu Oct 15 10:12:30 2026
+*filter
+:INPUT DROP [12:3456]
+:FORWARD DROP [0:0]
+:OUTPUT ACCEPT [0:0]
+:SSH - [0:0]
+[120:8400] -A INPUT -i lo -j ACCEPT
+-A INPUT -m conntrack --ctstate RELATED,ESTABLISHED -j ACCEPT
+-A INPUT -p tcp -m tcp --dport 22 -m comment --comment "remote access" -j SSH
+-A INPUT -p icmp -j ACCEPT
+-A SSH -s 192.0.2.1/32 -j ACCEPT
+-A SSH -j REJECT --reject-with tcp-reset
+COMMIT
+"#;
+
+#[test]
+fn parse_iptables_save() {
+    let ruleset = IptablesRuleset::from_save(IPTABLES_SAVE, ProtocolFamily::Ipv4).unwrap();
+    let tables: Vec<_> = ruleset
+        .tables
+        .iter()
+        .map(|t| t.get_name().unwrap())
+        .collect();
+    assert_eq!(tables, ["nat", "filter"]);
+
+    let postrouting = &ruleset.chains[3];
+    assert_eq!(postrouting.get_type(), Some(&ChainType::Nat));
+    assert_eq!(postrouting.get_hook().unwrap().get_priority(), Some(&100));
+    let input = &ruleset.chains[4];
+    assert_eq!(input.get_table().unwrap(), "filter");
+    assert_eq!(input.get_policy(), Some(&ChainPolicy::Drop));
+    let ssh = &ruleset.chains[7];
+    assert_eq!(ssh.get_name().unwrap(), "SSH");
+    assert_eq!(ssh.get_hook(), None);
+
+    let rules: Vec<String> = ruleset.rules.iter().map(|r| r.to_nft_syntax()).collect();
+    assert_eq!(
+        rules,
+        [
+            "meta oifname \"eth0\" meta nfproto ipv4 ip saddr 10.0.0.0/8 counter packets 0 bytes 0 masquerade",
+            "meta iifname \"lo\" counter packets 120 bytes 8400 accept",
+            "ct state established,related counter packets 0 bytes 0 accept",
+            "meta l4proto tcp tcp dport 22 counter packets 0 bytes 0 jump SSH",
+            "meta l4proto icmp counter packets 0 bytes 0 accept",
+            "meta nfproto ipv4 ip saddr 192.0.2.1 counter packets 0 bytes 0 accept",
+            "counter packets 0 bytes 0 reject with tcp reset",
+        ]
+    );
+}
+
+#[test]
+fn parse_rule_specs() {
+    let table = Table::new(ProtocolFamily::Ipv4).with_name("filter");
+    let cases = [
+        (
+            "-A INPUT -p udp --sport 53 -j ACCEPT",
+            "meta l4proto udp udp sport 53 counter packets 0 bytes 0 accept",
+        ),
+        (
+            "-A FORWARD -i eth1 -o eth0 -d 198.51.100.0/24 -j DROP",
+            "meta iifname \"eth1\" meta oifname \"eth0\" meta nfproto ipv4 ip daddr 198.51.100.0/24 counter packets 0 bytes 0 drop",
+        ),
+        (
+            "-A INPUT -m state --state INVALID -j DROP",
+            "ct state invalid counter packets 0 bytes 0 drop",
+        ),
+        (
+            "-A INPUT -p tcp --dport 80 -j LOG --log-prefix \"web: \"",
+            "meta l4proto tcp tcp dport 80 counter packets 0 bytes 0 log prefix \"web: \"",
+        ),
+        (
+            "-A INPUT -j REJECT --reject-with icmp-admin-prohibited",
+            "counter packets 0 bytes 0 reject with icmp admin-prohibited",
+        ),
+        (
+            "-A INPUT -j REJECT --reject-with icmp-host-prohibited",
+            "counter packets 0 bytes 0 reject with icmp host-prohibited",
+        ),
+        (
+            "-A INPUT -j REJECT",
+            "counter packets 0 bytes 0 reject with icmp port-unreachable",
+        ),
+        ("-A INPUT -g OTHER", "counter packets 0 bytes 0 goto OTHER"),
+        ("-A INPUT -j RETURN", "counter packets 0 bytes 0 return"),
+        (
+            "-A INPUT -s 192.0.2.0/24",
+            "meta nfproto ipv4 ip saddr 192.0.2.0/24 counter packets 0 bytes 0",
+        ),
+    ];
+    for (spec, expected) in cases {
+        assert_eq!(
+            parse_rule(spec, &table).unwrap().to_nft_syntax(),
+            expected,
+            "{}",
+            spec
+        );
+    }
+
+    // the ip and ip6 families ignore the ICMPX rejections, and have their own codes
+    let rule = parse_rule("-A INPUT -j REJECT", &table).unwrap();
+    let reject = rule.get_expressions().unwrap().iter().last().unwrap();
+    assert_eq!(
+        reject.get_data(),
+        Some(&ExpressionVariant::Reject(Reject::icmp(3)))
+    );
+    let table6 = Table::new(ProtocolFamily::Ipv6).with_name("filter");
+    for (spec, expected) in [
+        (
+            "-A INPUT -j REJECT",
+            "counter packets 0 bytes 0 reject with icmpv6 port-unreachable",
+        ),
+        (
+            "-A INPUT -j REJECT --reject-with icmp6-adm-prohibited",
+            "counter packets 0 bytes 0 reject with icmpv6 admin-prohibited",
+        ),
+    ] {
+        assert_eq!(
+            parse_rule(spec, &table6).unwrap().to_nft_syntax(),
+            expected,
+            "{}",
+            spec
+        );
+    }
+    assert!(parse_rule(
+        "-A INPUT -j REJECT --reject-with icmp-host-prohibited",
+        &table6
+    )
+    .is_err());
+    assert!(parse_rule("-A INPUT -j REJECT --reject-with icmp6-no-route", &table).is_err());
+
+    let nat = Table::new(ProtocolFamily::Ipv4).with_name("nat");
+    let cases = [
+        (
+            "-A PREROUTING -p tcp --dport 8080 -j DNAT --to-destination 10.0.0.2",
+            "meta l4proto tcp tcp dport 8080 counter packets 0 bytes 0 dnat to 10.0.0.2",
+        ),
+        (
+            "-A POSTROUTING -o eth0 -j SNAT --to-source 203.0.113.1",
+            "meta oifname \"eth0\" counter packets 0 bytes 0 snat to 203.0.113.1",
+        ),
+    ];
+    for (spec, expected) in cases {
+        assert_eq!(
+            parse_rule(spec, &nat).unwrap().to_nft_syntax(),
+            expected,
+            "{}",
+            spec
+        );
+    }
+    assert!(parse_rule("-A POSTROUTING -j MASQUERADE --to-ports 1024-65535", &nat).is_ok());
+}
+
+#[test]
+fn unsupported_specs() {
+    let table = Table::new(ProtocolFamily::Ipv4).with_name("filter");
+    for spec in [
+        "-A INPUT ! -s 192.0.2.1 -j DROP",
+        "-A INPUT -p tcp -m multiport --dports 80,443 -j ACCEPT",
+        "-A INPUT -p tcp --dport 1024:65535 -j ACCEPT",
+        "-A INPUT -i eth+ -j ACCEPT",
+        "-A INPUT -j ACCEPT --log-prefix x",
+    ] {
+        assert!(
+            matches!(parse_rule(spec, &table), Err(IptablesError::Unsupported(_))),
+            "{}",
+            spec
+        );
+    }
+    assert!(matches!(
+        parse_rule("-A INPUT -s 2001:db8::1 -j DROP", &table),
+        Err(IptablesError::InvalidValue { .. })
+    ));
+    assert!(matches!(
+        parse_rule("-A INPUT --dport 22 -j ACCEPT", &table),
+        Err(IptablesError::Unsupported(_))
+    ));
+
+    let undeclared = "*filter\n:INPUT ACCEPT [0:0]\n-A INPUT -j MISSING\nCOMMIT\n";
+    assert!(matches!(
+        IptablesRuleset::from_save(undeclared, ProtocolFamily::Ipv4),
+        Err(IptablesError::AtLine { line: 3, error }) if matches!(*error, IptablesError::Unsupported(_))
+    ));
+    let unterminated = "*filter\n:INPUT ACCEPT [0:0]\n-A INPUT -j ACCEPT\n";
+    assert!(matches!(
+        IptablesRuleset::from_save(unterminated, ProtocolFamily::Ipv4),
+        Err(IptablesError::MissingCommit(table)) if table == "filter"
+    ));
+    assert!(matches!(
+        IptablesRuleset::from_save("-A INPUT -j ACCEPT\n", ProtocolFamily::Ipv4),
+        Err(IptablesError::AtLine { line: 1, error }) if matches!(*error, IptablesError::OutsideTable)
+    ));
+}
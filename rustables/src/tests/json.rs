@@ -0,0 +1,165 @@
+use std::net::Ipv4Addr;
+
+use ipnetwork::IpNetwork;
+use serde_json::json;
+
+use super::{get_test_chain, get_test_rule, get_test_table};
+use crate::error::JsonError;
+use crate::expr::{Cmp, CmpOp, IcmpCode, Reject};
+use crate::json::JsonRuleset;
+use crate::nlmsg::NfNetlinkObject;
+use crate::set::SetBuilder;
+use crate::{ChainPolicy, ChainType, Hook, HookClass, Protocol, ProtocolFamily, SetFlags};
+
+const NFT_OUTPUT: &str = r#"{"nftables": [
+    {"metainfo": {"version": "1.0.9", "release_name": "Old Doc Yak #3", "json_schema_version": 1}},
+    {"table": {"family": "inet", "name": "filter", "handle": 1}},
+    {"chain": {"family": "inet", "table": "filter", "name": "input", "handle": 1,
+        "type": "filter", "hook": "input", "prio": 0, "policy": "drop"}},
+    {"set": {"family": "inet", "name": "blocked", "table": "filter", "type": "ipv4_addr",
+        "handle": 2, "flags": ["interval"]}},
+    {"rule": {"family": "inet", "table": "filter", "chain": "input", "handle": 3, "expr": [
+        {"match": {"op": "in", "left": {"ct": {"key": "state"}}, "right": ["established", "related"]}},
+        {"accept": null}]}},
+    {"rule": {"family": "inet", "table": "filter", "chain": "input", "handle": 4, "expr": [
+        {"match": {"op": "==", "left": {"payload": {"protocol": "ip", "field": "saddr"}},
+            "right": "@blocked"}},
+        {"counter": {"packets": 0, "bytes": 0}},
+        {"drop": null}]}},
+    {"rule": {"family": "inet", "table": "filter", "chain": "input", "handle": 5, "expr": [
+        {"match": {"op": "==", "left": {"payload": {"protocol": "tcp", "field": "dport"}},
+            "right": 22}},
+        {"accept": null}]}}
+]}"#;
+
+#[test]
+fn parse_nft_output() {
+    let ruleset = JsonRuleset::from_json(NFT_OUTPUT).unwrap();
+    assert_eq!(ruleset.tables.len(), 1);
+    assert_eq!(ruleset.tables[0].get_name().unwrap(), "filter");
+
+    assert_eq!(
+        ruleset.chains[0].to_nft_syntax(),
+        "chain input {\n\ttype filter hook input priority filter; policy drop;\n}\n"
+    );
+
+    let set = &ruleset.sets[0];
+    assert_eq!(set.get_name().unwrap(), "blocked");
+    assert_eq!(set.get_key_len(), Some(&4));
+    assert_eq!(set.get_set_flags(), Some(SetFlags::INTERVAL));
+
+    let rules: Vec<String> = ruleset.rules.iter().map(|r| r.to_nft_syntax()).collect();
+    assert_eq!(
+        rules,
+        [
+            "ct state established,related accept",
+            "meta nfproto ipv4 ip saddr @blocked counter packets 0 bytes 0 drop",
+            "meta l4proto tcp tcp dport 22 accept",
+        ]
+    );
+    assert_eq!(ruleset.rules[2].get_handle(), Some(&5));
+}
+
+#[test]
+fn round_trip() {
+    let table = get_test_table();
+    let chain = get_test_chain()
+        .with_hook(Hook::new(HookClass::In, 0))
+        .with_type(ChainType::Filter)
+        .with_policy(ChainPolicy::Accept);
+    let (set, _) = SetBuilder::<Ipv4Addr>::new("allowed", &table)
+        .unwrap()
+        .add_flags(SetFlags::INTERVAL)
+        .finish();
+    let net: IpNetwork = "10.0.0.0/8".parse().unwrap();
+    let rules = vec![
+        get_test_rule().established_or_related().unwrap().accept(),
+        get_test_rule().dport(22, Protocol::TCP).accept(),
+        get_test_rule().snetwork(net).unwrap().drop(),
+    ];
+    let ruleset = JsonRuleset {
+        tables: vec![table],
+        chains: vec![chain],
+        sets: vec![set],
+        rules,
+    };
+
+    let json = ruleset.to_value().unwrap();
+    let parsed = JsonRuleset::from_value(&json).unwrap();
+    assert_eq!(parsed.rules, ruleset.rules);
+    assert_eq!(parsed.chains, ruleset.chains);
+    assert_eq!(parsed.to_value().unwrap(), json);
+}
+
+#[test]
+fn unsupported_statements() {
+    let rule = |statement: &str| {
+        format!(
+            r#"{{"nftables": [{{"rule": {{"family": "inet", "table": "filter", "chain": "input",
+                "expr": [{}]}}}}]}}"#,
+            statement
+        )
+    };
+    assert!(matches!(
+        JsonRuleset::from_json(&rule(r#"{"quota": {"val": 25, "val_unit": "mbytes"}}"#)),
+        Err(JsonError::Unsupported(_))
+    ));
+    assert!(matches!(
+        JsonRuleset::from_json(&rule(r#"{"accept": null}"#)),
+        Ok(ruleset) if ruleset.rules.len() == 1
+    ));
+
+    // a comparison without any loaded value
+    let mut rule = get_test_rule();
+    rule.add_expr(Cmp::new(CmpOp::Eq, [1u8]));
+    let ruleset = JsonRuleset {
+        rules: vec![rule],
+        ..Default::default()
+    };
+    assert!(matches!(
+        ruleset.to_json(),
+        Err(JsonError::UntranslatedExpression(_))
+    ));
+}
+
+#[test]
+fn reject_statements() {
+    let ip6_rule = get_test_rule().with_family(ProtocolFamily::Ipv6);
+    let cases = [
+        (
+            get_test_rule().with_expr(Reject::icmpx(IcmpCode::HostUnreach)),
+            json!({ "type": "icmpx", "expr": "host-unreachable" }),
+        ),
+        (
+            ip6_rule.clone().with_expr(Reject::icmp(4)),
+            json!({ "type": "icmpv6", "expr": "port-unreachable" }),
+        ),
+        (
+            get_test_rule()
+                .with_family(ProtocolFamily::Ipv4)
+                .with_expr(Reject::icmp(10)),
+            json!({ "type": "icmp", "expr": "host-prohibited" }),
+        ),
+        (get_test_rule().with_expr(Reject::default()), json!(null)),
+    ];
+    for (rule, expected) in cases {
+        let ruleset = JsonRuleset {
+            rules: vec![rule],
+            ..Default::default()
+        };
+        let json = ruleset.to_value().unwrap();
+        let rule = &json["nftables"][1]["rule"];
+        assert_eq!(rule["expr"][0]["reject"], expected, "{}", rule);
+        assert_eq!(JsonRuleset::from_value(&json).unwrap().rules, ruleset.rules);
+    }
+
+    // the ICMP codes depend on the network protocol, unknown in inet rules
+    let ruleset = JsonRuleset {
+        rules: vec![get_test_rule().with_expr(Reject::icmp(3))],
+        ..Default::default()
+    };
+    assert!(matches!(
+        ruleset.to_json(),
+        Err(JsonError::UntranslatedExpression(_))
+    ));
+}
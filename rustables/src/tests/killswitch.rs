@@ -0,0 +1,57 @@
+use std::net::{IpAddr, Ipv4Addr};
+
+use crate::killswitch::{Killswitch, Template};
+use crate::{
+    Batch, Chain, ChainPolicy, ChainType, Hook, HookClass, MsgType, Protocol, ProtocolFamily, Rule,
+    Table,
+};
+
+#[test]
+fn killswitch_batch() {
+    let dns = IpAddr::V4(Ipv4Addr::new(10, 64, 0, 1));
+    let killswitch = Killswitch::new("killswitch")
+        .allow_loopback()
+        .allow_dns([dns]);
+    assert_eq!(
+        killswitch.get_templates(),
+        [Template::Loopback, Template::Dns { servers: vec![dns] }]
+    );
+
+    let table = Table::new(ProtocolFamily::Inet).with_name("killswitch");
+    let base_chain = |name: &str, class: HookClass| {
+        Chain::new(&table)
+            .with_name(name)
+            .with_hook(Hook::new(class, 0))
+            .with_type(ChainType::Filter)
+            .with_policy(ChainPolicy::Drop)
+    };
+    let input = base_chain("input", HookClass::In);
+    let output = base_chain("output", HookClass::Out);
+
+    let mut expected = Batch::new();
+    expected.add(&table, MsgType::Add);
+    table.flush(&mut expected).unwrap();
+    expected.add(&input, MsgType::Add);
+    expected.add(&output, MsgType::Add);
+    for rule in [
+        Rule::new(&input).unwrap().iiface("lo").unwrap().accept(),
+        Rule::new(&output).unwrap().oiface("lo").unwrap().accept(),
+        Rule::new(&output)
+            .unwrap()
+            .daddr(dns)
+            .dport(53, Protocol::UDP)
+            .accept(),
+        Rule::new(&output)
+            .unwrap()
+            .daddr(dns)
+            .dport(53, Protocol::TCP)
+            .accept(),
+    ] {
+        expected.add(&rule, MsgType::Add);
+    }
+
+    assert_eq!(
+        killswitch.to_batch().unwrap().finalize(),
+        expected.finalize()
+    );
+}
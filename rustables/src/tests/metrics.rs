@@ -0,0 +1,15 @@
+use crate::metrics::{metrics, record_busy_error, record_decode_error, record_messages_sent};
+
+#[test]
+fn metrics_are_monotonic() {
+    // the counters are shared with the other tests, which may run concurrently
+    let before = metrics();
+    record_messages_sent(3);
+    record_decode_error();
+    record_busy_error();
+    let after = metrics();
+    assert!(after.messages_sent >= before.messages_sent + 3);
+    assert!(after.decode_errors > before.decode_errors);
+    assert!(after.busy_errors > before.busy_errors);
+    assert!(after.batches_committed >= before.batches_committed);
+}
@@ -4,12 +4,43 @@ use crate::parser::{parse_nlmsg, NlMsg};
 use crate::set::{Set, SetBuilder};
 use crate::{sys::*, Chain, MsgType, ProtocolFamily, Rule, Table};
 
+pub use endianness::check_integer_field;
+
 mod batch;
+mod blocklist;
+#[cfg(feature = "capture")]
+mod capture;
 mod chain;
+mod chain_stats;
+mod chain_tree;
+mod compat;
+mod config;
+mod endianness;
+mod error;
 mod expr;
+mod expr_vectors;
+mod flowtable;
+#[cfg(feature = "iptables")]
+mod iptables;
+#[cfg(feature = "json")]
+mod json;
+mod killswitch;
+mod metrics;
+#[cfg(feature = "socket")]
+mod monitor;
+mod nft_syntax;
+mod obj;
+mod plan;
+#[cfg(feature = "socket")]
+mod query;
+mod raw_attributes;
 mod rule;
+#[cfg(feature = "serde")]
+mod serde_helpers;
 mod set;
+mod sys;
 mod table;
+mod zeroize;
 
 pub const TABLE_NAME: &'static str = "mocktable";
 pub const CHAIN_NAME: &'static str = "mockchain";
@@ -0,0 +1,77 @@
+use crate::monitor::{Event, MonitorShutdown, RawEvent};
+use crate::nlmsg::NfNetlinkObject;
+use crate::parser::{parse_nlmsg, NlMsg};
+use crate::{Generation, MsgType};
+
+use super::{get_test_rule, get_test_table};
+
+/// Returns the notification of the kernel holding `obj`.
+fn notification<T: NfNetlinkObject>(obj: &T) -> Vec<u8> {
+    obj.to_message_with_flags(MsgType::Add, 0, 0)
+}
+
+fn as_event(buf: &[u8]) -> RawEvent<'_> {
+    match parse_nlmsg(buf).unwrap() {
+        (header, NlMsg::NfGenMsg(genmsg, _)) => RawEvent::new(header, genmsg, buf),
+        (_, msg) => panic!("not a notification: {:?}", msg),
+    }
+}
+
+#[test]
+fn monitor_shutdown_is_shared_by_clones() {
+    let shutdown = MonitorShutdown::new().expect("Couldn't create the shutdown handle");
+    let clone = shutdown.clone();
+    assert!(!shutdown.is_stopped());
+    assert!(!clone.is_stopped());
+
+    let stopper = clone.clone();
+    std::thread::spawn(move || stopper.stop())
+        .join()
+        .unwrap()
+        .expect("Couldn't stop the monitor");
+    assert!(shutdown.is_stopped());
+    assert!(clone.is_stopped());
+}
+
+#[test]
+fn generation_events() {
+    let generation = Generation::default()
+        .with_id(42u32)
+        .with_proc_pid(1234u32)
+        .with_proc_name("nft");
+    let buf = notification(&generation);
+    let event = as_event(&buf);
+    assert_eq!(event.msg_type() as u32, crate::sys::NFT_MSG_NEWGEN);
+    assert_eq!(event.generation().unwrap().unwrap(), generation);
+    assert_eq!(generation.to_string(), "generation 42 by nft (pid 1234)");
+
+    // the changes of the batch are not generations
+    let buf = notification(&get_test_table());
+    assert!(as_event(&buf).generation().is_none());
+}
+
+#[test]
+fn typed_events() {
+    let table = get_test_table();
+    let buf = notification(&table);
+    assert_eq!(
+        as_event(&buf).to_event().unwrap(),
+        Event::TableAdded(table.clone())
+    );
+    let buf = table.to_message_with_flags(MsgType::Del, 0, 0);
+    assert_eq!(
+        as_event(&buf).to_event().unwrap(),
+        Event::TableDeleted(table)
+    );
+
+    let rule = get_test_rule().with_handle(7u64);
+    let buf = rule.to_message_with_flags(MsgType::Del, 0, 0);
+    assert_eq!(as_event(&buf).to_event().unwrap(), Event::RuleDeleted(rule));
+
+    let generation = Generation::default().with_id(3u32);
+    let buf = notification(&generation);
+    assert_eq!(
+        as_event(&buf).to_event().unwrap(),
+        Event::Generation(generation)
+    );
+}
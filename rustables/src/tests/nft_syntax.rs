@@ -0,0 +1,94 @@
+use std::net::Ipv4Addr;
+
+use ipnetwork::IpNetwork;
+
+use super::{get_test_chain, get_test_rule, get_test_table, CHAIN_NAME};
+use crate::expr::{Cmp, CmpOp, Payload, Register};
+use crate::set::SetBuilder;
+use crate::sys::NFT_PAYLOAD_TRANSPORT_HEADER;
+use crate::{ChainPolicy, ChainType, Hook, HookClass, Protocol, SetFlags, TableContents};
+
+#[test]
+fn rule_statements() {
+    let rule = get_test_rule().established_or_related().unwrap().accept();
+    assert_eq!(rule.to_nft_syntax(), "ct state established,related accept");
+
+    let rule = get_test_rule().dport(22, Protocol::TCP).accept();
+    assert_eq!(rule.to_nft_syntax(), "meta l4proto tcp tcp dport 22 accept");
+
+    let rule = get_test_rule()
+        .iiface("lo")
+        .unwrap()
+        .saddr(Ipv4Addr::new(10, 0, 0, 1))
+        .drop();
+    assert_eq!(
+        rule.to_nft_syntax(),
+        "meta iifname \"lo\" meta nfproto ipv4 ip saddr 10.0.0.1 drop"
+    );
+
+    let net: IpNetwork = "192.168.0.0/16".parse().unwrap();
+    let rule = get_test_rule().dnetwork(net).unwrap().accept();
+    assert_eq!(
+        rule.to_nft_syntax(),
+        "meta nfproto ipv4 ip daddr 192.168.0.0/16 accept"
+    );
+
+    // the offsets in bits of the raw payloads do not fit in the u32 offsets in bytes
+    let rule = get_test_rule()
+        .with_expr(
+            Payload::default()
+                .with_base(NFT_PAYLOAD_TRANSPORT_HEADER)
+                .with_offset(u32::MAX)
+                .with_len(1u32)
+                .with_dreg(Register::Reg1),
+        )
+        .with_expr(Cmp::new(CmpOp::Eq, [1u8]))
+        .accept();
+    assert_eq!(rule.to_nft_syntax(), "@th,34359738360,8 1 accept");
+}
+
+#[test]
+fn chain_with_hook() {
+    let chain = get_test_chain()
+        .with_hook(Hook::new(HookClass::In, 0))
+        .with_type(ChainType::Filter)
+        .with_policy(ChainPolicy::Drop);
+    assert_eq!(
+        chain.to_nft_syntax(),
+        format!(
+            "chain {} {{\n\ttype filter hook input priority filter; policy drop;\n}}\n",
+            CHAIN_NAME
+        )
+    );
+}
+
+#[test]
+fn table_with_contents() {
+    let table = get_test_table();
+    let (set, _) = SetBuilder::<Ipv4Addr>::new("blocked", &table)
+        .unwrap()
+        .add_flags(SetFlags::INTERVAL)
+        .finish();
+    let chain = get_test_chain();
+    let rule = get_test_rule().accept();
+    let contents = TableContents {
+        table,
+        chains: vec![chain],
+        sets: vec![set],
+        objects: Vec::new(),
+        rules: vec![rule],
+    };
+    assert_eq!(
+        contents.to_nft_syntax(),
+        "table inet mocktable {\n\
+         \tset blocked {\n\
+         \t\ttype ipv4_addr\n\
+         \t\tflags interval\n\
+         \t}\n\
+         \n\
+         \tchain mockchain {\n\
+         \t\taccept\n\
+         \t}\n\
+         }\n"
+    );
+}
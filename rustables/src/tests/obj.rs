@@ -0,0 +1,130 @@
+use crate::{
+    nlmsg::{get_operation_from_nlmsghdr_type, nft_nlmsg_maxsize, NfNetlinkDeserializable},
+    obj::{Obj, ObjectType},
+    sys::{
+        NFTA_COUNTER_BYTES, NFTA_COUNTER_PACKETS, NFTA_OBJ_DATA, NFTA_OBJ_HANDLE, NFTA_OBJ_NAME,
+        NFTA_OBJ_TABLE, NFTA_OBJ_TYPE, NFT_MSG_NEWOBJ, NFT_OBJECT_COUNTER,
+    },
+    MsgType,
+};
+
+use super::{
+    get_test_nlmsg, get_test_nlmsg_with_msg_type, get_test_table, NetlinkExpr, TABLE_NAME,
+};
+
+const OBJ_NAME: &str = "mockcounter";
+
+#[test]
+#[cfg(feature = "socket")]
+fn list_objects_filtered_by_type() {
+    use crate::query::get_list_of_objects_for_family;
+    use crate::sys::NFT_MSG_GETOBJ;
+    use crate::ProtocolFamily;
+
+    let mut filter = Obj::new(&get_test_table()).unwrap();
+    filter.set_obj_type(ObjectType::Counter);
+
+    let buf = get_list_of_objects_for_family(
+        NFT_MSG_GETOBJ as u16,
+        ProtocolFamily::Inet,
+        0,
+        Some(&filter),
+    )
+    .unwrap();
+    let (nlmsghdr, msg) = crate::parser::parse_nlmsg(&buf).unwrap();
+    assert_eq!(
+        get_operation_from_nlmsghdr_type(nlmsghdr.nlmsg_type),
+        NFT_MSG_GETOBJ as u8
+    );
+    let raw_expr = match msg {
+        crate::parser::NlMsg::NfGenMsg(nfgenmsg, raw_expr) => {
+            assert_eq!(nfgenmsg.nfgen_family, libc::NFPROTO_INET as u8);
+            raw_expr
+        }
+        _ => panic!("Invalid return value type, expected a valid message"),
+    };
+
+    assert_eq!(
+        raw_expr,
+        NetlinkExpr::List(vec![
+            NetlinkExpr::Final(NFTA_OBJ_TABLE, TABLE_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Final(NFTA_OBJ_TYPE, NFT_OBJECT_COUNTER.to_be_bytes().to_vec()),
+        ])
+        .to_raw()
+    );
+}
+
+#[test]
+fn parse_counter_object() {
+    let data = NetlinkExpr::List(vec![
+        NetlinkExpr::Final(NFTA_COUNTER_BYTES, 1500u64.to_be_bytes().to_vec()),
+        NetlinkExpr::Final(NFTA_COUNTER_PACKETS, 3u64.to_be_bytes().to_vec()),
+    ])
+    .to_raw();
+    let mut obj = Obj::new(&get_test_table())
+        .unwrap()
+        .with_name(OBJ_NAME)
+        .with_obj_type(ObjectType::Counter)
+        .with_data(data.clone());
+    let mut buf = Vec::with_capacity(nft_nlmsg_maxsize() as usize);
+    let (nlmsghdr, _nfgenmsg, raw_expr) = get_test_nlmsg(&mut buf, &mut obj);
+    assert_eq!(
+        get_operation_from_nlmsghdr_type(nlmsghdr.nlmsg_type),
+        NFT_MSG_NEWOBJ as u8
+    );
+    assert_eq!(
+        raw_expr,
+        NetlinkExpr::List(vec![
+            NetlinkExpr::Final(NFTA_OBJ_TABLE, TABLE_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Final(NFTA_OBJ_NAME, OBJ_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Final(NFTA_OBJ_TYPE, NFT_OBJECT_COUNTER.to_be_bytes().to_vec()),
+            NetlinkExpr::Final(NFTA_OBJ_DATA, data),
+        ])
+        .to_raw()
+    );
+
+    let (deserialized_obj, remaining) =
+        Obj::deserialize(&buf).expect("Couldn't deserialize the object");
+    assert_eq!(obj, deserialized_obj);
+    assert_eq!(remaining.len(), 0);
+
+    let counter = deserialized_obj.counter().expect("Missing counter data");
+    assert_eq!(counter.nb_bytes, Some(1500));
+    assert_eq!(counter.nb_packets, Some(3));
+}
+
+#[test]
+fn dumped_object_can_be_resubmitted() {
+    let mut obj = Obj::new(&get_test_table())
+        .unwrap()
+        .with_name(OBJ_NAME)
+        .with_obj_type(ObjectType::Counter)
+        .with_use_count(2u32)
+        .with_handle(7u64);
+
+    let mut buf = Vec::new();
+    let (_nlmsghdr, _nfgenmsg, raw_expr) = get_test_nlmsg(&mut buf, &mut obj);
+    assert_eq!(
+        raw_expr,
+        NetlinkExpr::List(vec![
+            NetlinkExpr::Final(NFTA_OBJ_TABLE, TABLE_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Final(NFTA_OBJ_NAME, OBJ_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Final(NFTA_OBJ_TYPE, NFT_OBJECT_COUNTER.to_be_bytes().to_vec()),
+        ])
+        .to_raw()
+    );
+
+    let mut buf = Vec::new();
+    let (_nlmsghdr, _nfgenmsg, raw_expr) =
+        get_test_nlmsg_with_msg_type(&mut buf, &mut obj, MsgType::Del);
+    assert_eq!(
+        raw_expr,
+        NetlinkExpr::List(vec![
+            NetlinkExpr::Final(NFTA_OBJ_TABLE, TABLE_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Final(NFTA_OBJ_NAME, OBJ_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Final(NFTA_OBJ_TYPE, NFT_OBJECT_COUNTER.to_be_bytes().to_vec()),
+            NetlinkExpr::Final(NFTA_OBJ_HANDLE, 7u64.to_be_bytes().to_vec()),
+        ])
+        .to_raw()
+    );
+}
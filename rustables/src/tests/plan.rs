@@ -0,0 +1,75 @@
+use std::net::Ipv4Addr;
+
+use crate::{
+    expr::{Immediate, VerdictKind},
+    nlmsg::NfNetlinkObject,
+    plan::{Plan, PlanAction, PlannedChange, PlannedObject},
+    set::SetBuilder,
+    sys::{NLM_F_ACK, NLM_F_REPLACE},
+    Batch, MsgType, ProtocolFamily,
+};
+
+use super::{get_test_chain, get_test_rule, get_test_table, CHAIN_NAME, SET_NAME, TABLE_NAME};
+
+#[test]
+fn batch_plan() {
+    let mut batch = Batch::new();
+    batch.add(&get_test_table(), MsgType::Add);
+    batch.add(&get_test_chain(), MsgType::Add);
+    get_test_rule().accept().add_to_batch(&mut batch);
+    let mut set_builder = SetBuilder::<Ipv4Addr>::new(SET_NAME, &get_test_table()).unwrap();
+    set_builder.add(&Ipv4Addr::new(192, 0, 2, 1));
+    set_builder.add(&Ipv4Addr::new(192, 0, 2, 2));
+    let (set, elements) = set_builder.finish();
+    batch.add(&elements, MsgType::Add);
+    batch.add(&set, MsgType::Del);
+    batch.add(&get_test_rule().with_handle(4u64), MsgType::Del);
+
+    let plan = batch.plan().unwrap();
+    assert_eq!(plan.changes.len(), 6);
+    assert_eq!(
+        plan.changes[3],
+        PlannedChange {
+            action: PlanAction::Add,
+            family: ProtocolFamily::Inet,
+            table: Some(TABLE_NAME.to_string()),
+            object: PlannedObject::Elements {
+                set: Some(SET_NAME.to_string()),
+                count: 2,
+            },
+        }
+    );
+    assert_eq!(plan.counts(), (4, 0, 2));
+    assert_eq!(
+        plan.to_string(),
+        format!(
+            "table inet {table}\n  \
+             + table\n  \
+             + chain {chain}\n  \
+             + rule {chain}: immediate accept -> verdict\n  \
+             + 2 elements of set {set}\n  \
+             - set {set}\n  \
+             - rule {chain} handle 4\n\
+             4 to add, 0 to replace, 2 to delete",
+            table = TABLE_NAME,
+            chain = CHAIN_NAME,
+            set = SET_NAME,
+        )
+    );
+    assert!(Batch::new().plan().unwrap().is_empty());
+}
+
+#[test]
+fn replaced_rule_plan() {
+    let rule = get_test_rule()
+        .with_handle(4u64)
+        .with_expr(Immediate::new_verdict(VerdictKind::Drop));
+    let msg = rule.to_message_with_flags(MsgType::Add, 0, (NLM_F_REPLACE | NLM_F_ACK) as u16);
+
+    let plan = Plan::from_messages(&msg).unwrap();
+    assert_eq!(plan.counts(), (0, 1, 0));
+    assert_eq!(
+        plan.changes[0].object.to_string(),
+        format!("rule {} handle 4: immediate drop -> verdict", CHAIN_NAME)
+    );
+}
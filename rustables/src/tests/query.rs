@@ -0,0 +1,399 @@
+use std::mem::size_of;
+use std::os::unix::prelude::RawFd;
+use std::thread::JoinHandle;
+
+use nix::sys::socket::{
+    self, sockopt::ReceiveTimeout, AddressFamily, MsgFlags, SockFlag, SockType,
+};
+use nix::sys::time::{TimeVal, TimeValLike};
+
+use crate::nlmsg::pad_netlink_object_with_variable_size;
+use crate::parser::get_nlmsghdr;
+use crate::query::NfNetlinkSocket;
+use crate::sys::{
+    nlmsgerr, nlmsghdr, NFNL_MSG_BATCH_BEGIN, NFNL_MSG_BATCH_END, NFT_MSG_GETTABLE, NLMSG_DONE,
+    NLMSG_ERROR, NLM_F_ACK, NLM_F_MULTI,
+};
+use crate::{AckMode, Batch, MsgType, NfNetlinkObject, Table};
+
+use super::get_test_table;
+
+/// Returns a socket whose peer stands for the kernel, and the file descriptor of that peer.
+/// Both ends deliver whole datagrams, like netlink sockets.
+pub fn fake_kernel_socket() -> (NfNetlinkSocket, RawFd) {
+    let (sock, peer) = socket::socketpair(
+        AddressFamily::Unix,
+        SockType::Datagram,
+        None,
+        SockFlag::SOCK_CLOEXEC,
+    )
+    .expect("Couldn't create a socketpair");
+    // a query waiting for a response that never comes fails instead of blocking the tests
+    socket::setsockopt(sock, ReceiveTimeout, &TimeVal::seconds(5)).unwrap();
+    (NfNetlinkSocket::from_raw_fd(sock, 0, 0), peer)
+}
+
+fn as_bytes<T>(value: &T) -> &[u8] {
+    unsafe { std::slice::from_raw_parts(value as *const T as *const u8, size_of::<T>()) }
+}
+
+/// The acknowledgement of the message whose header is `hdr`, or the error `error` about it.
+pub fn ack(hdr: &nlmsghdr, error: i32) -> Vec<u8> {
+    let reply = nlmsghdr {
+        nlmsg_len: (size_of::<nlmsghdr>() + size_of::<nlmsgerr>()) as u32,
+        nlmsg_type: NLMSG_ERROR as u16,
+        nlmsg_flags: 0,
+        nlmsg_seq: hdr.nlmsg_seq,
+        nlmsg_pid: 0,
+    };
+    let err = nlmsgerr { error, msg: *hdr };
+    [as_bytes(&reply), as_bytes(&err)].concat()
+}
+
+/// The message ending a dump.
+pub fn done() -> Vec<u8> {
+    let hdr = nlmsghdr {
+        nlmsg_len: (size_of::<nlmsghdr>() + 4) as u32,
+        nlmsg_type: NLMSG_DONE as u16,
+        nlmsg_flags: NLM_F_MULTI as u16,
+        nlmsg_seq: 0,
+        nlmsg_pid: 0,
+    };
+    [as_bytes(&hdr), &[0; 4]].concat()
+}
+
+/// The headers of the messages of `buf`.
+pub fn headers(buf: &[u8]) -> Vec<nlmsghdr> {
+    let mut headers = Vec::new();
+    let mut offset = 0;
+    while let Ok(hdr) = get_nlmsghdr(&buf[offset..]) {
+        offset += pad_netlink_object_with_variable_size(hdr.nlmsg_len as usize);
+        headers.push(hdr);
+    }
+    headers
+}
+
+/// Receives a request on `peer`, then sends the datagrams returned by `reply` for it.
+pub fn reply_once(
+    peer: RawFd,
+    reply: impl FnOnce(&[u8]) -> Vec<Vec<u8>> + Send + 'static,
+) -> JoinHandle<Vec<u8>> {
+    std::thread::spawn(move || {
+        let mut buf = vec![0; 1 << 16];
+        let len = socket::recv(peer, &mut buf, MsgFlags::empty()).expect("Couldn't receive");
+        buf.truncate(len);
+        for datagram in reply(&buf) {
+            socket::send(peer, &datagram, MsgFlags::empty()).expect("Couldn't reply");
+        }
+        buf
+    })
+}
+
+/// Acknowledges the messages of a batch flagged with `NLM_F_ACK`, like the kernels before 6.10,
+/// which ignore the flags of the begin and end messages.
+pub fn ack_flagged(request: &[u8]) -> Vec<Vec<u8>> {
+    headers(request)
+        .iter()
+        .filter(|hdr| hdr.nlmsg_flags & NLM_F_ACK as u16 != 0)
+        .filter(|hdr| {
+            hdr.nlmsg_type != NFNL_MSG_BATCH_BEGIN as u16
+                && hdr.nlmsg_type != NFNL_MSG_BATCH_END as u16
+        })
+        .map(|hdr| ack(hdr, 0))
+        .collect()
+}
+
+fn test_tables() -> Vec<Table> {
+    vec![get_test_table(), get_test_table().with_name("other")]
+}
+
+/// The messages of the kernel listing `objects`.
+fn dump_replies<T: NfNetlinkObject>(objects: &[T]) -> Vec<Vec<u8>> {
+    let mut replies: Vec<_> = objects
+        .iter()
+        .map(|obj| obj.to_message_with_flags(MsgType::Add, 0, NLM_F_MULTI as u16))
+        .collect();
+    replies.push(done());
+    replies
+}
+
+#[test]
+fn list_objects() {
+    use crate::query::{list_objects_with_socket, QueryBuffer};
+
+    let (sock, peer) = fake_kernel_socket();
+    let kernel = reply_once(peer, |_| dump_replies(&test_tables()));
+    let mut listed = Vec::new();
+    list_objects_with_socket(
+        &sock,
+        &mut QueryBuffer::new(),
+        NFT_MSG_GETTABLE as u16,
+        &|table: Table, listed: &mut Vec<Table>| {
+            listed.push(table);
+            Ok(())
+        },
+        None,
+        &mut listed,
+    )
+    .unwrap();
+    assert_eq!(listed, test_tables());
+    kernel.join().unwrap();
+    nix::unistd::close(peer).unwrap();
+}
+
+/// `replies`, with the first message addressed to another socket than the one of the query.
+fn to_foreign_port(mut replies: Vec<Vec<u8>>) -> Vec<Vec<u8>> {
+    let offset = std::mem::offset_of!(nlmsghdr, nlmsg_pid);
+    replies[0][offset..offset + 4].copy_from_slice(&4242u32.to_ne_bytes());
+    replies
+}
+
+#[test]
+fn foreign_port_id() {
+    use crate::error::{DecodeError, QueryError};
+    use crate::query::{list_objects_with_socket, QueryBuffer};
+
+    let (sock, peer) = fake_kernel_socket();
+    let kernel = reply_once(peer, |_| to_foreign_port(dump_replies(&test_tables())));
+    let mut listed = Vec::new();
+    let error = list_objects_with_socket(
+        &sock,
+        &mut QueryBuffer::new(),
+        NFT_MSG_GETTABLE as u16,
+        &|table: Table, listed: &mut Vec<Table>| {
+            listed.push(table);
+            Ok(())
+        },
+        None,
+        &mut listed,
+    )
+    .unwrap_err();
+    assert!(matches!(
+        error,
+        QueryError::ProcessNetlinkError(DecodeError::InvalidPortId(4242))
+    ));
+    assert!(listed.is_empty());
+    kernel.join().unwrap();
+    nix::unistd::close(peer).unwrap();
+}
+
+#[test]
+fn refresh_rules() {
+    use crate::expr::Counter;
+    use crate::nlmsg::{NfNetlinkAttribute, NfNetlinkWriter};
+    use crate::query::QueryBuffer;
+    use crate::sys::NFT_MSG_NEWRULE;
+
+    use super::get_test_rule;
+
+    let (sock, peer) = fake_kernel_socket();
+    let mut buffer = QueryBuffer::new();
+    let mut rule = get_test_rule().with_handle(42u64);
+    for packets in [1, 2] {
+        let counted = rule.clone().with_expr(Counter::new(packets, 0));
+        let expected = counted.clone();
+        // unlike the messages of the batches, the listed rules carry their handle
+        let kernel = reply_once(peer, move |_| {
+            let mut buf = Vec::new();
+            let mut writer = NfNetlinkWriter::new(&mut buf);
+            writer.write_header(NFT_MSG_NEWRULE as u16, counted.get_family(), 0, 0, None);
+            counted.write_payload(writer.add_data_zeroed(counted.get_size()));
+            writer.finalize_writing_object();
+            vec![buf]
+        });
+        // the same buffer receives the responses of the successive refreshes
+        rule.refresh(&sock, &mut buffer).unwrap();
+        assert_eq!(headers(&kernel.join().unwrap()).len(), 1);
+        assert_eq!(rule, expected);
+    }
+    nix::unistd::close(peer).unwrap();
+}
+
+#[test]
+fn socket_key_probe() {
+    use crate::expr::{is_socket_key_supported_with_socket, SocketKey};
+
+    let (sock, peer) = fake_kernel_socket();
+    let kernel = reply_once(peer, ack_flagged);
+    assert!(is_socket_key_supported_with_socket(&sock, SocketKey::Cgroupv2).unwrap());
+    kernel.join().unwrap();
+
+    // the kernels without the key refuse the rule of the probe, the third message of the batch
+    for errno in [libc::EOPNOTSUPP, libc::ENOENT] {
+        let kernel = reply_once(peer, move |request| vec![ack(&headers(request)[3], -errno)]);
+        assert!(!is_socket_key_supported_with_socket(&sock, SocketKey::Cgroupv2).unwrap());
+        kernel.join().unwrap();
+    }
+    let kernel = reply_once(peer, |request| {
+        vec![ack(&headers(request)[1], -libc::EPERM)]
+    });
+    assert!(is_socket_key_supported_with_socket(&sock, SocketKey::Cgroupv2).is_err());
+    kernel.join().unwrap();
+    nix::unistd::close(peer).unwrap();
+}
+
+#[test]
+fn cancelled_dump() {
+    use crate::error::QueryError;
+    use crate::query::{list_objects_with_socket, CancellationToken, QueryBuffer};
+
+    let (sock, peer) = fake_kernel_socket();
+    let token = CancellationToken::new();
+    let mut buffer = QueryBuffer::new().with_cancellation(token.clone());
+    let kernel = reply_once(peer, |_| dump_replies(&test_tables()));
+    let mut listed = Vec::new();
+    // the dump is cancelled while the first table is processed
+    let error = list_objects_with_socket(
+        &sock,
+        &mut buffer,
+        NFT_MSG_GETTABLE as u16,
+        &|table: Table, listed: &mut Vec<Table>| {
+            token.cancel();
+            listed.push(table);
+            Ok(())
+        },
+        None,
+        &mut listed,
+    )
+    .unwrap_err();
+    assert!(matches!(error, QueryError::Cancelled));
+    assert_eq!(listed, test_tables()[..1]);
+    kernel.join().unwrap();
+
+    // the rest of the dump was drained, so the next query only receives its own response
+    let kernel = reply_once(peer, |_| dump_replies(&test_tables()[1..]));
+    let mut listed = Vec::new();
+    list_objects_with_socket(
+        &sock,
+        &mut QueryBuffer::new(),
+        NFT_MSG_GETTABLE as u16,
+        &|table: Table, listed: &mut Vec<Table>| {
+            listed.push(table);
+            Ok(())
+        },
+        None,
+        &mut listed,
+    )
+    .unwrap();
+    assert_eq!(listed, test_tables()[1..]);
+    kernel.join().unwrap();
+    nix::unistd::close(peer).unwrap();
+}
+
+#[test]
+fn send_batch() {
+    let (sock, peer) = fake_kernel_socket();
+    for ack_mode in [AckMode::Strict, AckMode::LastMessage] {
+        let kernel = reply_once(peer, ack_flagged);
+        let mut batch = Batch::new().with_ack_mode(ack_mode);
+        batch.add(&get_test_table(), MsgType::Add);
+        batch.add(&get_test_table().with_name("other"), MsgType::Add);
+        // the batch is committed once the last message is acknowledged
+        batch.send_with_socket(&sock).unwrap();
+        let acked = headers(&kernel.join().unwrap())
+            .iter()
+            .filter(|hdr| hdr.nlmsg_flags & NLM_F_ACK as u16 != 0)
+            .count();
+        assert_eq!(acked, if ack_mode == AckMode::Strict { 3 } else { 1 });
+    }
+    nix::unistd::close(peer).unwrap();
+}
+
+#[cfg(feature = "tokio")]
+fn assert_send<T: Send>(_: &T) {}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn send_batch_async() {
+    use super::get_test_rule;
+
+    let (sock, peer) = fake_kernel_socket();
+    let sock = sock.into_async().unwrap();
+    let kernel = reply_once(peer, ack_flagged);
+
+    let mut batch = Batch::new();
+    batch.add(&get_test_table(), MsgType::Add);
+    batch.add(&get_test_rule(), MsgType::Add);
+    let send = batch.send_async_with_socket(&sock);
+    assert_send(&send);
+    send.await.unwrap();
+    assert_eq!(headers(&kernel.join().unwrap()).len(), 4);
+
+    // the errors of the kernel designate the refused object
+    let kernel = reply_once(peer, |request| {
+        vec![ack(&headers(request)[2], -libc::ENOENT)]
+    });
+    let mut batch = Batch::new();
+    batch.add(&get_test_table(), MsgType::Add);
+    batch.add(&get_test_rule(), MsgType::Add);
+    let error = batch.send_async_with_socket(&sock).await.unwrap_err();
+    assert!(matches!(
+        error,
+        crate::error::QueryError::BatchObjectRefused { object, .. } if object.index == 1
+    ));
+    kernel.join().unwrap();
+    nix::unistd::close(peer).unwrap();
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn list_objects_async() {
+    use crate::query::{list_objects_with_socket_async, QueryBuffer};
+
+    let (sock, peer) = fake_kernel_socket();
+    let sock = sock.into_async().unwrap();
+    let tables = test_tables();
+    let kernel = reply_once(peer, |_| dump_replies(&test_tables()));
+
+    let mut listed = Vec::new();
+    let mut buffer = QueryBuffer::new();
+    let query = list_objects_with_socket_async(
+        &sock,
+        &mut buffer,
+        NFT_MSG_GETTABLE as u16,
+        &|table: Table, listed: &mut Vec<Table>| {
+            listed.push(table);
+            Ok(())
+        },
+        None,
+        &mut listed,
+    );
+    // the queries can be spawned on multi-threaded runtimes
+    assert_send(&query);
+    query.await.unwrap();
+    assert_eq!(listed, tables);
+    kernel.join().unwrap();
+    nix::unistd::close(peer).unwrap();
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn foreign_port_id_async() {
+    use crate::error::{DecodeError, QueryError};
+    use crate::query::{list_objects_with_socket_async, QueryBuffer};
+
+    let (sock, peer) = fake_kernel_socket();
+    let sock = sock.into_async().unwrap();
+    let kernel = reply_once(peer, |_| to_foreign_port(dump_replies(&test_tables())));
+    let mut listed = Vec::new();
+    let error = list_objects_with_socket_async(
+        &sock,
+        &mut QueryBuffer::new(),
+        NFT_MSG_GETTABLE as u16,
+        &|table: Table, listed: &mut Vec<Table>| {
+            listed.push(table);
+            Ok(())
+        },
+        None,
+        &mut listed,
+    )
+    .await
+    .unwrap_err();
+    assert!(matches!(
+        error,
+        QueryError::ProcessNetlinkError(DecodeError::InvalidPortId(4242))
+    ));
+    assert!(listed.is_empty());
+    kernel.join().unwrap();
+    nix::unistd::close(peer).unwrap();
+}
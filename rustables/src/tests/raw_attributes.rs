@@ -0,0 +1,101 @@
+use crate::error::DecodeError;
+use crate::expr::{Counter, ExpressionRaw, RawExpression};
+use crate::sys::{
+    NFTA_COUNTER_BYTES, NFTA_EXPR_DATA, NFTA_EXPR_NAME, NFTA_LIST_ELEM, NFTA_RULE_EXPRESSIONS,
+    NFTA_RULE_TABLE, NFTA_TABLE_NAME,
+};
+use crate::{
+    MsgType, NfNetlinkAttribute, NfNetlinkDeserializable, NfNetlinkObject, RawAttributeTree,
+    RawAttributeValue, Rule,
+};
+
+use super::{get_test_chain, get_test_rule, get_test_table, TABLE_NAME};
+
+#[test]
+fn objects_are_decoded_as_trees() {
+    let rule = Rule::new(&get_test_chain())
+        .unwrap()
+        .with_expr(Counter::new(1, 2));
+    let tree = rule.to_attribute_tree().unwrap();
+    assert_eq!(
+        tree.get(NFTA_RULE_TABLE).unwrap().value,
+        RawAttributeValue::Bytes(TABLE_NAME.as_bytes().to_vec())
+    );
+    let RawAttributeValue::Nested(exprs) = &tree.get(NFTA_RULE_EXPRESSIONS).unwrap().value else {
+        panic!("the expressions are not nested");
+    };
+    let RawAttributeValue::Nested(expr) = &exprs.get(NFTA_LIST_ELEM).unwrap().value else {
+        panic!("the expression is not nested");
+    };
+    assert_eq!(
+        expr.get(NFTA_EXPR_NAME).unwrap().value,
+        RawAttributeValue::Bytes(b"counter".to_vec())
+    );
+    let counter = expr.get(NFTA_EXPR_DATA).unwrap().nested().unwrap();
+    assert_eq!(
+        counter.get(NFTA_COUNTER_BYTES).unwrap().value,
+        RawAttributeValue::Bytes(2u64.to_be_bytes().to_vec())
+    );
+
+    // the tree is written back as it was read
+    let mut buf = vec![0; rule.get_size()];
+    rule.write_payload(&mut buf);
+    let mut written = vec![0; tree.get_size()];
+    tree.write_payload(&mut written);
+    assert_eq!(written, buf);
+
+    assert_eq!(
+        tree.to_string(),
+        concat!(
+            "1: 0x6d6f636b7461626c65\n",
+            "2: 0x6d6f636b636861696e\n",
+            "4:\n",
+            "  1:\n",
+            "    1: 0x636f756e746572\n",
+            "    2:\n",
+            "      1: 0x0000000000000002\n",
+            "      2: 0x0000000000000001\n",
+        )
+    );
+}
+
+#[test]
+fn messages_are_decoded_as_trees() {
+    let table = get_test_table();
+    let tree = RawAttributeTree::from_message(&table.to_batch_message(MsgType::Add, 1)).unwrap();
+    assert_eq!(tree, table.to_attribute_tree().unwrap());
+    assert_eq!(
+        tree.get(NFTA_TABLE_NAME).unwrap().attr_type,
+        NFTA_TABLE_NAME
+    );
+}
+
+#[test]
+fn unknown_expressions_are_decoded_as_trees() {
+    let (expr, _) = ExpressionRaw::deserialize(&[8, 0, 1, 0, 0, 0, 0, 42]).unwrap();
+    let tree = expr.to_attribute_tree().unwrap();
+    assert_eq!(tree.to_string(), "1: 0x0000002a\n");
+
+    // the raw bytes are written as is, so they can hold malformed attributes
+    let (expr, _) = ExpressionRaw::deserialize(&[12, 0, 1, 0, 0, 0, 0, 42]).unwrap();
+    let rule =
+        get_test_rule().with_expr(RawExpression::default().with_name("mock").with_data(expr));
+    assert!(matches!(
+        rule.to_attribute_tree(),
+        Err(DecodeError::InvalidDataSize)
+    ));
+}
+
+#[test]
+fn invalid_lengths_are_refused() {
+    // the attribute claims to be longer than the buffer
+    assert!(matches!(
+        RawAttributeTree::parse(&[12, 0, 1, 0, 0, 0, 0, 42]),
+        Err(DecodeError::InvalidDataSize)
+    ));
+    // the buffer ends in the middle of a header
+    assert!(matches!(
+        RawAttributeTree::parse(&[8, 0, 1, 0, 0, 0, 0, 42, 4, 0]),
+        Err(DecodeError::InvalidDataSize)
+    ));
+}
@@ -1,15 +1,36 @@
+use std::convert::TryFrom;
+use std::net::Ipv4Addr;
+use std::ops::RangeInclusive;
+
 use crate::{
-    nlmsg::get_operation_from_nlmsghdr_type,
+    attribute_order,
+    compat::RuleCompat,
+    data_type::{DataTypeId, Port, TcHandle},
+    error::BuilderError,
+    expr::{
+        Bitwise, Cmp, CmpOp, ConnTrackState, Conntrack, ConntrackKey, Counter, ExpressionList,
+        ExpressionVariant, Hash, HighLevelPayload, IPv4HeaderField, Immediate, Lookup, Meta,
+        MetaType, Nat, NatType, NetworkHeaderField, Numgen, Register, Socket, TCPHeaderField,
+        TransportHeaderField, VerdictKind,
+    },
+    nlmsg::{
+        get_operation_from_nlmsghdr_type, pad_netlink_object_with_variable_size,
+        NfNetlinkAttribute, NfNetlinkDeserializable, NfNetlinkObject,
+    },
+    parser::parse_nlmsg,
+    set::SetBuilder,
     sys::{
-        NFTA_RULE_CHAIN, NFTA_RULE_HANDLE, NFTA_RULE_POSITION, NFTA_RULE_TABLE, NFTA_RULE_USERDATA,
-        NFT_MSG_DELRULE, NFT_MSG_NEWRULE,
+        NFTA_RULE_CHAIN, NFTA_RULE_COMPAT, NFTA_RULE_COMPAT_PROTO, NFTA_RULE_HANDLE,
+        NFTA_RULE_POSITION, NFTA_RULE_TABLE, NFTA_RULE_USERDATA, NFT_MSG_DELRULE, NFT_MSG_NEWRULE,
+        NFT_SET_ANONYMOUS, NFT_SET_CONSTANT, NLM_F_ACK, NLM_F_CREATE, NLM_F_REPLACE,
     },
-    MsgType,
+    with_attribute_order, AttributeOrder, Batch, Chain, IcmpType, Icmpv6Type, MsgType, Protocol,
+    ProtocolFamily, Rule, RuleSummary, Set, Table,
 };
 
 use super::{
-    get_test_nlmsg, get_test_nlmsg_with_msg_type, get_test_rule, NetlinkExpr, CHAIN_NAME,
-    RULE_USERDATA, TABLE_NAME,
+    get_test_nlmsg, get_test_nlmsg_with_msg_type, get_test_rule, get_test_set, get_test_table,
+    NetlinkExpr, CHAIN_NAME, RULE_USERDATA, SET_NAME, TABLE_NAME,
 };
 
 #[test]
@@ -34,6 +55,41 @@ fn new_empty_rule() {
     );
 }
 
+#[test]
+fn rule_attributes_sorted_by_type() {
+    let rule = get_test_rule()
+        .with_position(5u64)
+        .with_compat(RuleCompat::default().with_proto(libc::IPPROTO_TCP as u32));
+    let header = NetlinkExpr::List(vec![
+        NetlinkExpr::Final(NFTA_RULE_TABLE, TABLE_NAME.as_bytes().to_vec()),
+        NetlinkExpr::Final(NFTA_RULE_CHAIN, CHAIN_NAME.as_bytes().to_vec()),
+    ])
+    .to_raw();
+    let position = NetlinkExpr::Final(NFTA_RULE_POSITION, 5u64.to_be_bytes().to_vec()).to_raw();
+    let compat = NetlinkExpr::Nested(
+        NFTA_RULE_COMPAT,
+        vec![NetlinkExpr::Final(
+            NFTA_RULE_COMPAT_PROTO,
+            (libc::IPPROTO_TCP as u32).to_be_bytes().to_vec(),
+        )],
+    )
+    .to_raw();
+    let write = || {
+        let mut buf = vec![0; rule.get_size()];
+        rule.write_payload(&mut buf);
+        buf
+    };
+
+    // the compat attribute is declared after the position
+    assert_eq!(write(), [&header[..], &position, &compat].concat());
+    assert_eq!(
+        with_attribute_order(AttributeOrder::ByType, write),
+        [&header[..], &compat, &position].concat()
+    );
+    // the order is restored afterwards
+    assert_eq!(attribute_order(), AttributeOrder::Declaration);
+}
+
 #[test]
 fn new_empty_rule_with_userdata() {
     let mut rule = get_test_rule().with_userdata(RULE_USERDATA);
@@ -69,8 +125,38 @@ fn new_empty_rule_with_position_and_handle() {
         get_operation_from_nlmsghdr_type(nlmsghdr.nlmsg_type),
         NFT_MSG_NEWRULE as u8
     );
-    assert_eq!(nlmsghdr.nlmsg_len, 76);
+    assert_eq!(nlmsghdr.nlmsg_len, 64);
 
+    // the kernel refuses handles in the messages adding rules, unless they replace the rule
+    assert_eq!(
+        raw_expr,
+        NetlinkExpr::List(vec![
+            NetlinkExpr::Final(NFTA_RULE_TABLE, TABLE_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Final(NFTA_RULE_CHAIN, CHAIN_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Final(NFTA_RULE_POSITION, position.to_be_bytes().to_vec()),
+        ])
+        .to_raw()
+    );
+}
+
+#[test]
+fn replace_and_insert_rules() {
+    let handle: u64 = 1337;
+    let position: u64 = 42;
+    let mut rule = get_test_rule().with_handle(handle).with_position(position);
+
+    // the handle designates the rule to replace
+    let mut buf = Vec::new();
+    let (nlmsghdr, _nfgenmsg, raw_expr) =
+        get_test_nlmsg_with_msg_type(&mut buf, &mut rule, MsgType::Replace);
+    assert_eq!(
+        get_operation_from_nlmsghdr_type(nlmsghdr.nlmsg_type),
+        NFT_MSG_NEWRULE as u8
+    );
+    assert_eq!(
+        nlmsghdr.nlmsg_flags,
+        libc::NLM_F_REQUEST as u16 | (NLM_F_REPLACE | NLM_F_ACK) as u16
+    );
     assert_eq!(
         raw_expr,
         NetlinkExpr::List(vec![
@@ -81,6 +167,63 @@ fn new_empty_rule_with_position_and_handle() {
         ])
         .to_raw()
     );
+    assert_eq!(
+        get_test_rule().missing_attributes(MsgType::Replace),
+        ["NFTA_RULE_HANDLE"]
+    );
+
+    // inserted rules go before their position, without NLM_F_APPEND
+    let mut buf = Vec::new();
+    let (nlmsghdr, _nfgenmsg, raw_expr) =
+        get_test_nlmsg_with_msg_type(&mut buf, &mut rule, MsgType::Insert);
+    assert_eq!(
+        nlmsghdr.nlmsg_flags,
+        libc::NLM_F_REQUEST as u16 | (NLM_F_CREATE | NLM_F_ACK) as u16
+    );
+    assert_eq!(
+        raw_expr,
+        NetlinkExpr::List(vec![
+            NetlinkExpr::Final(NFTA_RULE_TABLE, TABLE_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Final(NFTA_RULE_CHAIN, CHAIN_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Final(NFTA_RULE_POSITION, position.to_be_bytes().to_vec()),
+        ])
+        .to_raw()
+    );
+    assert!(get_test_rule()
+        .missing_attributes(MsgType::Insert)
+        .is_empty());
+}
+
+#[test]
+fn dumped_rule_can_be_resubmitted() {
+    // a rule as dumped by the kernel, with its handle
+    let dumped = get_test_rule().with_handle(42u64).accept();
+    let msg = dumped.to_message_with_flags(MsgType::Add, 0, NLM_F_REPLACE as u16);
+    let (mut rule, _) = Rule::deserialize(&msg).unwrap();
+    assert_eq!(rule, dumped);
+
+    rule.add_expr(Counter::default());
+    let mut batch = Batch::new();
+    batch.add(&rule, MsgType::Add);
+    batch.add(&rule, MsgType::Del);
+    let buf = batch.finalize();
+
+    let mut msgs = Vec::new();
+    let mut remaining = &buf[..];
+    while let Ok((hdr, _)) = parse_nlmsg(remaining) {
+        let len = pad_netlink_object_with_variable_size(hdr.nlmsg_len as usize);
+        if get_operation_from_nlmsghdr_type(hdr.nlmsg_type) == NFT_MSG_NEWRULE as u8
+            || get_operation_from_nlmsghdr_type(hdr.nlmsg_type) == NFT_MSG_DELRULE as u8
+        {
+            msgs.push(Rule::deserialize(&remaining[..len]).unwrap().0);
+        }
+        remaining = &remaining[len..];
+    }
+    let [added, deleted]: [Rule; 2] = msgs.try_into().unwrap();
+    assert_eq!(added.get_handle(), None);
+    assert_eq!(added.get_expressions(), rule.get_expressions());
+    // the handle still designates the rule to delete
+    assert_eq!(deleted.get_handle(), Some(&42));
 }
 
 #[test]
@@ -130,3 +273,631 @@ fn delete_empty_rule_with_handle() {
         .to_raw()
     );
 }
+
+#[test]
+fn rule_with_ct_states() {
+    let states = ConnTrackState::ESTABLISHED | ConnTrackState::RELATED;
+    let expected = |op| {
+        get_test_rule()
+            .with_expr(Conntrack::new(ConntrackKey::State))
+            .with_expr(Bitwise::new(states.bits().to_ne_bytes(), 0u32.to_be_bytes()).unwrap())
+            .with_expr(Cmp::new(op, 0u32.to_be_bytes()))
+    };
+
+    let rule = get_test_rule().ct_states(states, false).unwrap();
+    assert_eq!(rule, expected(CmpOp::Neq));
+
+    let rule = get_test_rule().ct_states(states, true).unwrap();
+    assert_eq!(rule, expected(CmpOp::Eq));
+}
+
+#[test]
+fn rule_with_mark_from_map() {
+    let mut builder = SetBuilder::<Ipv4Addr>::new(SET_NAME, &get_test_table())
+        .unwrap()
+        .map_to(DataTypeId::Mark, 4);
+    builder
+        .add_mapping(&Ipv4Addr::new(10, 0, 0, 1), 42u32.to_ne_bytes())
+        .unwrap();
+    assert!(matches!(
+        builder.add_mapping(&Ipv4Addr::new(10, 0, 0, 2), [0u8; 2]),
+        Err(BuilderError::IncompatibleLength)
+    ));
+    let (map, elements) = builder.finish();
+    assert!(map.is_map());
+    assert_eq!(map.get_data_type(), Some(&(DataTypeId::Mark as u32)));
+    let elements = elements.elements.unwrap();
+    let element = elements.iter().next().unwrap();
+    assert_eq!(
+        element.get_data().and_then(|d| d.get_value()),
+        Some(&42u32.to_ne_bytes().to_vec())
+    );
+
+    let saddr = HighLevelPayload::Network(NetworkHeaderField::IPv4(IPv4HeaderField::Saddr));
+    let rule = get_test_rule()
+        .set_mark_from_map(saddr.build(), &map)
+        .unwrap();
+    let expected = get_test_rule()
+        .with_expr(saddr.build())
+        .with_expr(Lookup::new(&map).unwrap().with_dreg(Register::Reg1))
+        .with_expr(
+            Meta::default()
+                .with_key(MetaType::Mark)
+                .with_sreg(Register::Reg1),
+        );
+    assert_eq!(rule, expected);
+
+    assert!(matches!(
+        get_test_rule().set_mark_from_map(saddr.build(), &get_test_set::<Ipv4Addr>()),
+        Err(BuilderError::InvalidMarkMap)
+    ));
+}
+
+#[test]
+fn rule_counters() {
+    let rule = get_test_rule().with_expr(Cmp::new(CmpOp::Eq, [1u8]));
+    assert_eq!(rule.counters(), None);
+
+    let rule = rule
+        .with_expr(
+            Counter::default()
+                .with_nb_bytes(120u64)
+                .with_nb_packets(2u64),
+        )
+        .with_expr(
+            Counter::default()
+                .with_nb_bytes(60u64)
+                .with_nb_packets(1u64),
+        );
+    assert_eq!(rule.counters(), Some((180, 3)));
+}
+
+#[test]
+fn rule_matching_any_value() {
+    let dport = HighLevelPayload::Transport(TransportHeaderField::Tcp(TCPHeaderField::Dport));
+    let ports = [Port::new(80), Port::new(443), Port::new(8080)];
+    let empty_batch = Batch::new().finalize();
+
+    // a single value is matched with a comparison
+    let mut batch = Batch::new();
+    let rule = get_test_rule()
+        .match_any(dport.build(), &ports[..1], false, &mut batch)
+        .unwrap();
+    let expected = get_test_rule()
+        .with_expr(dport.build())
+        .with_expr(Cmp::new(CmpOp::Eq, 80u16.to_be_bytes()));
+    assert_eq!(rule, expected);
+    assert_eq!(batch.finalize(), empty_batch);
+
+    // a few values are excluded with a chain of comparisons
+    let mut batch = Batch::new();
+    let rule = get_test_rule()
+        .match_any(dport.build(), &ports, true, &mut batch)
+        .unwrap();
+    let mut expected = get_test_rule().with_expr(dport.build());
+    for port in [80u16, 443, 8080] {
+        expected.add_expr(Cmp::new(CmpOp::Neq, port.to_be_bytes()));
+    }
+    assert_eq!(rule, expected);
+    assert_eq!(batch.finalize(), empty_batch);
+
+    // otherwise, the values are looked up in an anonymous set added to the batch
+    let mut batch = Batch::new();
+    let rule = get_test_rule()
+        .match_any(dport.build(), &ports, false, &mut batch)
+        .unwrap();
+    let lookup = match rule
+        .get_expressions()
+        .unwrap()
+        .iter()
+        .last()
+        .unwrap()
+        .get_data()
+    {
+        Some(ExpressionVariant::Lookup(lookup)) => lookup.clone(),
+        _ => panic!("the last expression should be a lookup"),
+    };
+    let buf = batch.finalize();
+    let (hdr, _msg) = parse_nlmsg(&buf).unwrap();
+    let (set, _) =
+        Set::deserialize(&buf[pad_netlink_object_with_variable_size(hdr.nlmsg_len as usize)..])
+            .unwrap();
+    assert_eq!(set.flags, Some(NFT_SET_ANONYMOUS | NFT_SET_CONSTANT));
+    assert_eq!(set.id, lookup.get_set_id().copied());
+    assert_eq!(lookup.get_flags(), None);
+
+    let empty: [Port; 0] = [];
+    assert!(matches!(
+        get_test_rule().match_any(dport.build(), &empty, false, &mut Batch::new()),
+        Err(BuilderError::EmptyValueList)
+    ));
+}
+
+#[test]
+#[cfg(feature = "socket")]
+fn rule_refresh_request() {
+    use crate::query::get_object_request;
+    use crate::sys::NFT_MSG_GETRULE;
+
+    let rule = get_test_rule().with_expr(Counter::default());
+    assert!(matches!(
+        rule.refresh_filter(),
+        Err(BuilderError::MissingRuleHandle)
+    ));
+
+    let rule = rule.with_handle(42u64);
+    let filter = rule.refresh_filter().unwrap();
+    assert_eq!(filter, get_test_rule().with_handle(42u64));
+
+    // a single rule is requested, instead of a dump of the chain
+    let buf = get_object_request(NFT_MSG_GETRULE as u16, 0, &filter).unwrap();
+    let (hdr, _msg) = parse_nlmsg(&buf).unwrap();
+    assert_eq!(
+        get_operation_from_nlmsghdr_type(hdr.nlmsg_type),
+        NFT_MSG_GETRULE as u8
+    );
+    assert_eq!(hdr.nlmsg_flags, libc::NLM_F_REQUEST as u16);
+}
+
+#[test]
+fn nat_gateway_rules() {
+    let display = |rule: &Rule| {
+        rule.get_expressions()
+            .unwrap()
+            .iter()
+            .map(|expr| expr.to_string())
+            .collect::<Vec<_>>()
+    };
+
+    let mut rule = get_test_rule().clamp_mss_to_pmtu().unwrap();
+    assert_eq!(
+        display(&rule),
+        [
+            "meta l4proto -> reg1",
+            "cmp reg1 == 6",
+            "payload @transport,104,8 -> reg1",
+            "bitwise reg1 = (reg1 & 2) ^ 0",
+            "cmp reg1 != 0",
+            "rt tcpmss -> reg1",
+            "byteorder reg1 = hton(reg1, 2, 2)",
+            "exthdr tcpopt 2 2b @ 2 <- reg1",
+        ]
+    );
+    let mut buf = Vec::new();
+    get_test_nlmsg(&mut buf, &mut rule);
+    let (deserialized_rule, _) = Rule::deserialize(&buf).unwrap();
+    assert_eq!(rule, deserialized_rule);
+
+    let mut rule = get_test_rule().masquerade_to_ports(1024..=65535).unwrap();
+    assert_eq!(
+        display(&rule),
+        [
+            "immediate 1024 -> reg1",
+            "immediate 65535 -> reg2",
+            "masq to :reg1-reg2",
+        ]
+    );
+    let mut buf = Vec::new();
+    get_test_nlmsg(&mut buf, &mut rule);
+    let (deserialized_rule, _) = Rule::deserialize(&buf).unwrap();
+    assert_eq!(rule, deserialized_rule);
+
+    assert!(matches!(
+        get_test_rule().masquerade_to_ports(RangeInclusive::new(2000, 1000)),
+        Err(BuilderError::EmptyPortRange)
+    ));
+
+    let mut rule = get_test_rule()
+        .snat_to_ports(Ipv4Addr::new(203, 0, 113, 1), 1024..=2047)
+        .unwrap();
+    assert_eq!(
+        display(&rule),
+        [
+            "immediate 0xcb007101 -> reg1",
+            "immediate 1024 -> reg2",
+            "immediate 2047 -> reg3",
+            "snat Ipv4 addr reg1 proto reg2-reg3 flags PROTO_SPECIFIED",
+        ]
+    );
+    let mut buf = Vec::new();
+    get_test_nlmsg(&mut buf, &mut rule);
+    let (deserialized_rule, _) = Rule::deserialize(&buf).unwrap();
+    assert_eq!(rule, deserialized_rule);
+    // the ranges are not translated to the nft syntax yet
+    assert!(rule.to_nft_syntax().contains("snat Ipv4"));
+}
+
+#[test]
+fn icmp_type_code_rules() {
+    let display = |rule: &Rule| {
+        rule.get_expressions()
+            .unwrap()
+            .iter()
+            .map(|expr| expr.to_string())
+            .collect::<Vec<_>>()
+    };
+
+    let mut rule = get_test_rule().icmp_type_code(IcmpType::DestinationUnreachable, 3);
+    assert_eq!(
+        display(&rule),
+        [
+            "meta l4proto -> reg1",
+            "cmp reg1 == 1",
+            "payload @transport,0,8 -> reg1",
+            "cmp reg1 == 3",
+            "payload @transport,8,8 -> reg1",
+            "cmp reg1 == 3",
+        ]
+    );
+    assert_eq!(
+        rule.to_nft_syntax(),
+        "meta l4proto icmp icmp type 3 icmp code 3"
+    );
+    let mut buf = Vec::new();
+    get_test_nlmsg(&mut buf, &mut rule);
+    let (deserialized_rule, _) = Rule::deserialize(&buf).unwrap();
+    assert_eq!(rule, deserialized_rule);
+
+    let rule = get_test_rule()
+        .icmpv6_type_code(Icmpv6Type::RouterSolicitation, 0)
+        .accept();
+    assert_eq!(display(&rule)[1], "cmp reg1 == 58");
+    assert_eq!(
+        rule.to_nft_syntax(),
+        "meta l4proto icmpv6 icmpv6 type 133 icmpv6 code 0 accept"
+    );
+}
+
+#[test]
+fn load_balancing_rules() {
+    let (backends, mut elements) = SetBuilder::<[u8; 4]>::new_anonymous(&get_test_table())
+        .unwrap()
+        .with_key_type(DataTypeId::Integer)
+        .map_to(DataTypeId::IpAddr, 4)
+        .finish();
+    elements.add_mapping(&0u32.to_ne_bytes(), Ipv4Addr::new(10, 0, 0, 1).octets());
+    elements.add_mapping(&1u32.to_ne_bytes(), Ipv4Addr::new(10, 0, 0, 2).octets());
+
+    let dnat = Nat::default()
+        .with_nat_type(NatType::DNat)
+        .with_family(ProtocolFamily::Ipv4)
+        .with_ip_register(Register::Reg1);
+    let mut rule = get_test_rule()
+        .with_expr(Numgen::incremental(2))
+        .with_expr(Lookup::new(&backends).unwrap().with_dreg(Register::Reg1))
+        .with_expr(dnat.clone());
+    assert!(rule.validate().is_ok());
+    assert_eq!(
+        rule.get_expressions()
+            .unwrap()
+            .iter()
+            .next()
+            .unwrap()
+            .to_string(),
+        "numgen inc mod 2 -> reg1"
+    );
+    let mut buf = Vec::new();
+    get_test_nlmsg(&mut buf, &mut rule);
+    let (deserialized_rule, _) = Rule::deserialize(&buf).unwrap();
+    assert_eq!(rule, deserialized_rule);
+
+    // the clients stick to a backend, by hashing their address
+    let rule = get_test_rule()
+        .with_expr(
+            HighLevelPayload::Network(NetworkHeaderField::IPv4(IPv4HeaderField::Saddr)).build(),
+        )
+        .with_expr(Hash::jhash(4, 2).with_seed(0xdeadbeef_u32))
+        .with_expr(Lookup::new(&backends).unwrap().with_dreg(Register::Reg1))
+        .with_expr(dnat);
+    assert!(rule.validate().is_ok());
+    assert_eq!(
+        rule.get_expressions()
+            .unwrap()
+            .iter()
+            .nth(1)
+            .unwrap()
+            .to_string(),
+        "jhash reg1 len 4 mod 2 seed 0xdeadbeef -> reg1"
+    );
+}
+
+#[test]
+fn tproxy_rules() {
+    let display = |rule: &Rule| {
+        rule.get_expressions()
+            .unwrap()
+            .iter()
+            .map(|expr| expr.to_string())
+            .collect::<Vec<_>>()
+    };
+
+    let mut rule = get_test_rule().protocol(Protocol::TCP).tproxy(8080);
+    assert_eq!(
+        display(&rule)[2..],
+        ["immediate 8080 -> reg1", "tproxy Unspec port reg1"]
+    );
+    assert_eq!(rule.to_nft_syntax(), "meta l4proto tcp tproxy to :8080");
+    let mut buf = Vec::new();
+    get_test_nlmsg(&mut buf, &mut rule);
+    let (deserialized_rule, _) = Rule::deserialize(&buf).unwrap();
+    assert_eq!(rule, deserialized_rule);
+
+    let rule = get_test_rule()
+        .protocol(Protocol::UDP)
+        .tproxy_to(Ipv4Addr::LOCALHOST, 50080);
+    assert_eq!(display(&rule)[4], "tproxy Ipv4 addr reg1 port reg2");
+    assert_eq!(
+        rule.to_nft_syntax(),
+        "meta l4proto udp tproxy ip to 127.0.0.1:50080"
+    );
+    assert!(rule.validate().is_ok());
+
+    // the family is implied by the tables of a single family
+    let table = Table::new(ProtocolFamily::Ipv6).with_name("proxy");
+    let chain = Chain::new(&table).with_name("prerouting");
+    let rule = Rule::new(&chain).unwrap().tproxy(3128);
+    assert_eq!(display(&rule)[1], "tproxy Ipv6 port reg1");
+    assert_eq!(rule.to_nft_syntax(), "tproxy to :3128");
+}
+
+#[test]
+fn rule_summary() {
+    let rule = get_test_rule()
+        .with_handle(7u64)
+        .dport(22, crate::Protocol::TCP)
+        .with_expr(crate::expr::Log::new(None, Some("ssh\n")).unwrap())
+        .accept();
+    assert_eq!(
+        RuleSummary::try_from(&rule).unwrap().as_str(),
+        "inet mocktable mockchain handle 7: meta (1=0x00000001 2=0x00000010); \
+         cmp (1=0x00000001 2=0x00000000 3=(1=0x06)); \
+         payload (1=0x00000001 2=0x00000002 3=0x00000002 4=0x00000002); \
+         cmp (1=0x00000001 2=0x00000000 3=(1=0x0016)); log (2=0x7373680a); \
+         immediate (1=0x00000000 2=(2=(1=0x00000001)))"
+    );
+
+    // the summary does not depend on the order in which the attributes are written
+    let by_type = crate::with_attribute_order(crate::AttributeOrder::ByType, || {
+        RuleSummary::try_from(&rule).unwrap()
+    });
+    assert_eq!(by_type, RuleSummary::try_from(&rule).unwrap());
+
+    // the names that are not plain identifiers are quoted
+    let rule = Rule::default()
+        .with_family(ProtocolFamily::Other(42))
+        .with_table("my table")
+        .with_chain("in\"put\n");
+    assert_eq!(
+        RuleSummary::try_from(&rule).unwrap().as_str(),
+        "42 \"my table\" \"in\\\"put\\n\":"
+    );
+
+    let empty = RuleSummary::try_from(&get_test_rule()).unwrap();
+    assert_eq!(empty.to_string(), "inet mocktable mockchain:");
+
+    assert!(matches!(
+        RuleSummary::try_from(&Rule::default()),
+        Err(BuilderError::MissingChainInformationError)
+    ));
+}
+
+#[test]
+fn rule_jump_targets() {
+    let rule = get_test_rule()
+        .with_expr(Cmp::new(CmpOp::Eq, [1u8]))
+        .accept();
+    assert_eq!(rule.jump_targets().count(), 0);
+    assert_eq!(get_test_rule().jump_targets().count(), 0);
+
+    let rule = get_test_rule()
+        .with_expr(Immediate::new_verdict(VerdictKind::Jump {
+            chain: "tcp-in".to_string(),
+        }))
+        .with_expr(Immediate::new_verdict(VerdictKind::Goto {
+            chain: "udp-in".to_string(),
+        }));
+    assert_eq!(
+        rule.jump_targets().collect::<Vec<_>>(),
+        vec!["tcp-in", "udp-in"]
+    );
+}
+
+#[test]
+fn dport_bounds() {
+    let dport = HighLevelPayload::Transport(TransportHeaderField::Tcp(TCPHeaderField::Dport));
+    let expected = |op| {
+        get_test_rule()
+            .protocol(Protocol::TCP)
+            .with_expr(dport.build())
+            .with_expr(Cmp::new(op, 1024u16.to_be_bytes()))
+    };
+    assert_eq!(
+        get_test_rule().dport_at_least(1024, Protocol::TCP),
+        expected(CmpOp::Gte)
+    );
+    assert_eq!(
+        get_test_rule().dport_at_most(1024, Protocol::TCP),
+        expected(CmpOp::Lte)
+    );
+}
+
+#[test]
+fn tc_handle_parsing() {
+    let handle: TcHandle = "1:20".parse().unwrap();
+    assert_eq!(handle, TcHandle::new(1, 0x20));
+    assert_eq!(handle.value(), 0x10020);
+    assert_eq!(handle.to_string(), "1:20");
+    assert_eq!(
+        "ffff:".parse::<TcHandle>().unwrap(),
+        TcHandle::new(0xffff, 0)
+    );
+    assert_eq!("root".parse::<TcHandle>().unwrap().value(), u32::MAX);
+    assert_eq!(TcHandle::from(0).to_string(), "none");
+    for invalid in ["1", "1:2:3", "10000:1", "+1:2", "x:1", ""] {
+        assert!(matches!(
+            invalid.parse::<TcHandle>(),
+            Err(BuilderError::InvalidTcHandle(_))
+        ));
+    }
+
+    let rule = get_test_rule().set_priority(handle);
+    let expected = get_test_rule()
+        .with_expr(Immediate::new_data(
+            0x10020u32.to_ne_bytes().to_vec(),
+            Register::Reg1,
+        ))
+        .with_expr(
+            Meta::default()
+                .with_key(MetaType::Priority)
+                .with_sreg(Register::Reg1),
+        );
+    assert_eq!(rule, expected);
+    assert!(rule.validate().is_ok());
+}
+
+#[test]
+fn socket_cgroupv2() {
+    let rule = get_test_rule().socket_cgroupv2(1, 0x1234);
+    let expected = get_test_rule()
+        .with_expr(Socket::cgroupv2(1))
+        .with_expr(Cmp::new(CmpOp::Eq, 0x1234u64.to_ne_bytes()));
+    assert_eq!(rule, expected);
+    assert!(rule.validate().is_ok());
+    assert_eq!(
+        Socket::cgroupv2(1).to_string(),
+        "socket cgroupv2 level 1 -> reg1"
+    );
+}
+
+#[test]
+fn rule_jump_to_chain() {
+    let table = get_test_table();
+    let target = Chain::new(&table).with_name("tcp-in");
+    let rule = get_test_rule()
+        .jump(&target)
+        .unwrap()
+        .goto(&target)
+        .unwrap();
+    assert_eq!(
+        rule.jump_targets().collect::<Vec<_>>(),
+        vec!["tcp-in", "tcp-in"]
+    );
+
+    let other_table = Table::new(ProtocolFamily::Inet).with_name("other-table");
+    let foreign = Chain::new(&other_table).with_name("tcp-in");
+    assert!(matches!(
+        get_test_rule().jump(&foreign),
+        Err(BuilderError::ChainTableMismatch)
+    ));
+    assert!(matches!(
+        VerdictKind::goto_to(&Chain::new(&table)),
+        Err(BuilderError::MissingChainInformationError)
+    ));
+}
+
+#[test]
+fn rule_expression_validation() {
+    let ip = Ipv4Addr::new(10, 0, 0, 1);
+    for rule in [
+        get_test_rule()
+            .saddr(ip)
+            .dport(22, Protocol::TCP)
+            .established()
+            .unwrap()
+            .accept(),
+        get_test_rule().snat(ip),
+        get_test_rule().masquerade_to_ports(10000..=20000).unwrap(),
+        get_test_rule().clamp_mss_to_pmtu().unwrap(),
+    ] {
+        assert!(rule.validate().is_ok(), "{:?}", rule);
+    }
+
+    let unloaded_cmp = get_test_rule()
+        .with_expr(Counter::default())
+        .with_expr(Cmp::new(CmpOp::Eq, [1u8]));
+    assert!(matches!(
+        unloaded_cmp.validate(),
+        Err(BuilderError::InvalidRuleExpression(1, _))
+    ));
+
+    let nat = Nat::default()
+        .with_nat_type(NatType::SNat)
+        .with_family(ProtocolFamily::Ipv4)
+        .with_ip_register(Register::Reg1);
+    assert!(matches!(
+        get_test_rule().with_expr(nat.clone()).validate(),
+        Err(BuilderError::InvalidRuleExpression(0, _))
+    ));
+    assert!(get_test_rule()
+        .with_expr(Immediate::new_ip(ip, Register::Reg1))
+        .with_expr(nat)
+        .validate()
+        .is_ok());
+
+    let after_verdict = get_test_rule().accept().with_expr(Counter::default());
+    assert!(matches!(
+        after_verdict.validate(),
+        Err(BuilderError::InvalidRuleExpression(1, _))
+    ));
+}
+
+#[test]
+fn mixed_expression_list() {
+    let rule = get_test_rule().with_expressions(crate::exprs![
+        Meta::new(MetaType::L4Proto),
+        Cmp::new(CmpOp::Eq, [libc::IPPROTO_TCP as u8]),
+        Counter::default(),
+        Immediate::new_verdict(VerdictKind::Accept),
+    ]);
+    let expected = get_test_rule()
+        .with_expr(Meta::new(MetaType::L4Proto))
+        .with_expr(Cmp::new(CmpOp::Eq, [libc::IPPROTO_TCP as u8]))
+        .with_expr(Counter::default())
+        .with_expr(Immediate::new_verdict(VerdictKind::Accept));
+    assert_eq!(rule, expected);
+
+    let mut exprs: ExpressionList = [80u16, 443]
+        .iter()
+        .map(|port| Cmp::new(CmpOp::Neq, port.to_be_bytes()))
+        .collect();
+    exprs.extend([Immediate::new_verdict(VerdictKind::Drop)]);
+    assert_eq!(exprs.iter().count(), 3);
+}
+
+#[test]
+fn clone_rule_into_other_chain() {
+    let rule = get_test_rule()
+        .with_handle(12u64)
+        .with_position(3u64)
+        .with_userdata(RULE_USERDATA)
+        .with_expr(Counter::new(5, 500))
+        .with_expr(Immediate::new_verdict(VerdictKind::Accept));
+    let table = Table::new(ProtocolFamily::Ipv6).with_name("mirror");
+    let chain = Chain::new(&table).with_name("input");
+
+    let copy = rule.clone_into(&chain).unwrap();
+    assert_eq!(copy.get_family(), ProtocolFamily::Ipv6);
+    assert_eq!(copy.get_table().unwrap(), "mirror");
+    assert_eq!(copy.get_chain().unwrap(), "input");
+    assert_eq!(copy.get_handle(), None);
+    assert_eq!(copy.get_position(), None);
+    assert_eq!(copy.get_expressions(), rule.get_expressions());
+    assert_eq!(copy.get_userdata(), rule.get_userdata());
+
+    assert!(matches!(
+        rule.clone_into(&Chain::new(&table)),
+        Err(BuilderError::MissingChainInformationError)
+    ));
+
+    // the offsets of the fields of the network header depend on the family
+    let rule = get_test_rule().saddr(Ipv4Addr::new(192, 0, 2, 1)).drop();
+    assert!(matches!(
+        rule.clone_into(&chain),
+        Err(BuilderError::FamilyDependentRule)
+    ));
+    let staging = Chain::new(&get_test_table()).with_name("staging");
+    assert_eq!(
+        rule.clone_into(&staging).unwrap().get_expressions(),
+        rule.get_expressions()
+    );
+}
@@ -0,0 +1,66 @@
+use serde_json::json;
+
+use crate::expr::ExpressionRaw;
+use crate::nlmsg::NfNetlinkDeserializable;
+use crate::set::TypeofExpr;
+use crate::Table;
+
+use super::get_test_table;
+
+#[test]
+fn hex_round_trip() {
+    let expr = TypeofExpr {
+        name: "payload".to_string(),
+        data: vec![0x00, 0x2a, 0xff],
+    };
+    let value = serde_json::to_value(&expr).unwrap();
+    assert_eq!(value, json!({ "name": "payload", "data": "002aff" }));
+    assert_eq!(serde_json::from_value::<TypeofExpr>(value).unwrap(), expr);
+
+    let (raw, _) = ExpressionRaw::deserialize(&[8, 0, 1, 0, 0, 0, 0, 42]).unwrap();
+    let value = serde_json::to_value(&raw).unwrap();
+    assert_eq!(value, json!("080001000000002a"));
+    assert_eq!(serde_json::from_value::<ExpressionRaw>(value).unwrap(), raw);
+
+    // the digits are case insensitive, and the empty buffer is the empty string
+    let expr: TypeofExpr = serde_json::from_value(json!({ "name": "", "data": "0A0b" })).unwrap();
+    assert_eq!(expr.data, [0x0a, 0x0b]);
+    let expr: TypeofExpr = serde_json::from_value(json!({ "name": "", "data": "" })).unwrap();
+    assert!(expr.data.is_empty());
+}
+
+#[test]
+fn invalid_hex() {
+    for data in ["abc", "0g", "+1", "é0", "0x2a"] {
+        let res = serde_json::from_value::<TypeofExpr>(json!({ "name": "", "data": data }));
+        assert!(res.is_err(), "{:?}", data);
+    }
+    assert!(serde_json::from_value::<TypeofExpr>(json!({ "name": "", "data": [1, 2] })).is_err());
+}
+
+#[test]
+fn option_hex_round_trip() {
+    let table = get_test_table().with_userdata(b"\x01mock".to_vec());
+    let value = serde_json::to_value(&table).unwrap();
+    assert_eq!(value["userdata"], json!("016d6f636b"));
+    assert_eq!(serde_json::from_value::<Table>(value).unwrap(), table);
+
+    let table = get_test_table();
+    let mut value = serde_json::to_value(&table).unwrap();
+    assert_eq!(value["userdata"], json!(null));
+    assert_eq!(
+        serde_json::from_value::<Table>(value.clone()).unwrap(),
+        table
+    );
+    // the attribute can be left out
+    value.as_object_mut().unwrap().remove("userdata");
+    assert_eq!(
+        serde_json::from_value::<Table>(value.clone()).unwrap(),
+        table
+    );
+
+    value["userdata"] = json!("016d6f636");
+    assert!(serde_json::from_value::<Table>(value.clone()).is_err());
+    value["userdata"] = json!("zz");
+    assert!(serde_json::from_value::<Table>(value).is_err());
+}
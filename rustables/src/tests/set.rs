@@ -1,22 +1,33 @@
-use std::net::{Ipv4Addr, Ipv6Addr};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::Duration;
 
 use crate::{
-    data_type::DataType,
-    error::DecodeError,
-    nlmsg::{get_operation_from_nlmsghdr_type, NfNetlinkDeserializable},
-    set::SetBuilder,
+    data_type::{DataType, DataTypeId, Port},
+    error::{BuilderError, DecodeError},
+    expr::{
+        Counter, HighLevelPayload, IPv4HeaderField, Lookup, NetworkHeaderField, Objref, Register,
+        TCPHeaderField, TransportHeaderField, Verdict, VerdictKind,
+    },
+    nlmsg::{
+        get_operation_from_nlmsghdr_type, NfNetlinkAttribute, NfNetlinkDeserializable,
+        NfNetlinkObject, NfNetlinkWriter,
+    },
+    obj::ObjectType,
+    parser::parse_response_stream,
+    plan::PlanAction,
+    set::{Endianness, SetBuilder, SetElement, SetElementList, SetUserdata, TypeofExpr},
     sys::{
         NFTA_DATA_VALUE, NFTA_LIST_ELEM, NFTA_SET_ELEM_KEY, NFTA_SET_ELEM_LIST_ELEMENTS,
         NFTA_SET_ELEM_LIST_SET, NFTA_SET_ELEM_LIST_TABLE, NFTA_SET_KEY_LEN, NFTA_SET_KEY_TYPE,
-        NFTA_SET_NAME, NFTA_SET_TABLE, NFTA_SET_USERDATA, NFT_MSG_DELSET, NFT_MSG_NEWSET,
-        NFT_MSG_NEWSETELEM,
+        NFTA_SET_NAME, NFTA_SET_TABLE, NFTA_SET_USERDATA, NFT_DATA_VERDICT, NFT_MSG_DELSET,
+        NFT_MSG_DELSETELEM, NFT_MSG_NEWSET, NFT_MSG_NEWSETELEM, NFT_SET_EVAL, NFT_SET_TIMEOUT,
     },
-    MsgType, Set,
+    Batch, MsgType, ProtocolFamily, Set, SetFlags,
 };
 
 use super::{
-    get_test_nlmsg, get_test_nlmsg_with_msg_type, get_test_set, get_test_table, NetlinkExpr,
-    SET_NAME, SET_USERDATA, TABLE_NAME,
+    get_test_nlmsg, get_test_nlmsg_with_msg_type, get_test_rule, get_test_set, get_test_table,
+    NetlinkExpr, SET_NAME, SET_USERDATA, TABLE_NAME,
 };
 
 #[test]
@@ -133,3 +144,443 @@ fn new_set_with_data() {
         .to_raw()
     );
 }
+
+#[test]
+fn parse_set_elements_with_counters() {
+    let ip1 = Ipv4Addr::new(127, 0, 0, 1);
+    let ip2 = Ipv4Addr::new(1, 1, 1, 1);
+    let mut set_builder = SetBuilder::<Ipv4Addr>::new(SET_NAME.to_string(), &get_test_table())
+        .expect("Couldn't create a set");
+
+    set_builder.add_with_expr(
+        &ip1,
+        Counter::default()
+            .with_nb_packets(3u64)
+            .with_nb_bytes(180u64),
+    );
+    set_builder.add(&ip2);
+    let (_set, mut elem_list) = set_builder.finish();
+
+    let mut buf = Vec::new();
+    get_test_nlmsg(&mut buf, &mut elem_list);
+
+    let (deserialized_list, remaining) =
+        SetElementList::deserialize(&buf).expect("Couldn't deserialize the object");
+    assert_eq!(remaining.len(), 0);
+    assert_eq!(deserialized_list.family, ProtocolFamily::Inet);
+    assert_eq!(deserialized_list, elem_list);
+
+    let elements: Vec<_> = deserialized_list
+        .elements
+        .unwrap()
+        .iter()
+        .cloned()
+        .collect();
+    let counter = elements[0].counter().expect("Missing counter");
+    assert_eq!(counter.nb_packets, Some(3));
+    assert_eq!(counter.nb_bytes, Some(180));
+    assert!(elements[1].counter().is_none());
+}
+
+#[test]
+fn add_ip_to_set() {
+    let ip = Ipv4Addr::new(127, 0, 0, 1);
+    let mut expected = SetBuilder::<Ipv4Addr>::new(SET_NAME, &get_test_table()).unwrap();
+    expected.add(&ip);
+
+    let mut set_builder = SetBuilder::<Ipv4Addr>::new(SET_NAME, &get_test_table()).unwrap();
+    set_builder
+        .add_ip(IpAddr::V4(ip))
+        .expect("Couldn't add an IPv4 address to the set");
+    assert!(matches!(
+        set_builder.add_ip(Ipv6Addr::LOCALHOST),
+        Err(BuilderError::IncompatibleLength)
+    ));
+
+    assert_eq!(set_builder.finish().1, expected.finish().1);
+}
+
+#[test]
+fn named_set_lifecycle() {
+    let table = get_test_table();
+    let set = Set::new_named::<Ipv4Addr>(SET_NAME, &table).unwrap();
+    assert_eq!(
+        set.clone().with_userdata(SET_USERDATA),
+        get_test_set::<Ipv4Addr>()
+    );
+
+    // elements added in a later batch target the set by its name
+    let ip = Ipv4Addr::new(192, 0, 2, 1);
+    let mut builder = SetBuilder::<Ipv4Addr>::new(SET_NAME, &table).unwrap();
+    builder.add(&ip);
+    let mut elements = SetElementList::for_set(&set).unwrap();
+    elements.add_key(&ip);
+    assert_eq!(elements, builder.finish().1);
+
+    let mut batch = Batch::new();
+    batch.add(&elements, MsgType::Del);
+    let buf = batch.finalize();
+    let (elements_batch, _) = parse_response_stream::<SetElementList>(&buf)
+        .next()
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        get_operation_from_nlmsghdr_type(elements_batch.nlmsg_type),
+        NFT_MSG_DELSETELEM as u8
+    );
+
+    assert!(matches!(
+        SetElementList::for_set(&Set::default().with_table(TABLE_NAME)),
+        Err(BuilderError::MissingSetName)
+    ));
+}
+
+#[test]
+fn set_key_data_types() {
+    let set = get_test_set::<Ipv4Addr>();
+    assert_eq!(set.get_key_data_types(), Some(vec![DataTypeId::IpAddr]));
+    assert_eq!(set.key_type_name().as_deref(), Some("ipv4_addr"));
+
+    let (set, _) = SetBuilder::<[u8; 2]>::new(SET_NAME, &get_test_table())
+        .unwrap()
+        .with_key_type(DataTypeId::InetService)
+        .finish();
+    assert_eq!(set.key_type_name().as_deref(), Some("inet_service"));
+
+    let concat = DataTypeId::concat(&[DataTypeId::IpAddr, DataTypeId::InetService]);
+    let set = Set::default().with_key_type(concat);
+    assert_eq!(
+        set.get_key_data_types(),
+        Some(vec![DataTypeId::IpAddr, DataTypeId::InetService])
+    );
+    assert_eq!(
+        set.key_type_name().as_deref(),
+        Some("ipv4_addr . inet_service")
+    );
+
+    let set = Set::default().with_key_type(63u32);
+    assert_eq!(set.get_key_data_types(), None);
+    assert_eq!(set.key_type_name().as_deref(), Some("0x3f"));
+}
+
+#[test]
+fn verdict_map_elements() {
+    let (ssh, http) = (Port::from(22), Port::from(80));
+    let web = VerdictKind::Jump {
+        chain: "web".to_string(),
+    };
+    let mut set_builder = SetBuilder::<Port>::new(SET_NAME, &get_test_table()).unwrap();
+    assert!(matches!(
+        set_builder.add_verdict_mapping(&ssh, VerdictKind::Accept),
+        Err(BuilderError::NotAVerdictMap)
+    ));
+
+    let mut set_builder = set_builder.map_to_verdicts();
+    set_builder
+        .add_verdict_mapping(&ssh, VerdictKind::Accept)
+        .unwrap();
+    set_builder.add_verdict_mapping(&http, web.clone()).unwrap();
+    let (set, mut elem_list) = set_builder.finish();
+    assert!(set.is_map() && set.is_verdict_map());
+    assert_eq!(set.get_data_type(), Some(&NFT_DATA_VERDICT));
+    assert_eq!(set.get_data_len(), None);
+    assert!(set.missing_attributes(MsgType::Add).is_empty());
+
+    // the same elements, added to the existing map
+    let mut elements = SetElementList::for_set(&set).unwrap();
+    elements.add_verdict_mapping(&ssh, VerdictKind::Accept);
+    elements.add_verdict_mapping(&http, web.clone());
+    assert_eq!(elements, elem_list);
+
+    let mut buf = Vec::new();
+    get_test_nlmsg(&mut buf, &mut elem_list);
+    let (deserialized_list, _) =
+        SetElementList::deserialize(&buf).expect("Couldn't deserialize the object");
+    assert_eq!(deserialized_list, elem_list);
+    let element = deserialized_list.elements.unwrap().iter().nth(1).cloned();
+    assert_eq!(
+        element
+            .unwrap()
+            .get_data()
+            .and_then(|data| data.get_verdict()),
+        Some(&Verdict::from(web))
+    );
+
+    let dport = HighLevelPayload::Transport(TransportHeaderField::Tcp(TCPHeaderField::Dport));
+    let rule = get_test_rule().verdict_map(dport.build(), &set).unwrap();
+    assert_eq!(
+        rule,
+        get_test_rule()
+            .with_expr(dport.build())
+            .with_expr(Lookup::new(&set).unwrap().with_dreg(Register::Verdict))
+    );
+    assert!(rule.validate().is_ok());
+    assert!(matches!(
+        get_test_rule().verdict_map(dport.build(), &get_test_set::<Port>()),
+        Err(BuilderError::NotAVerdictMap)
+    ));
+}
+
+#[test]
+fn element_timeouts() {
+    let table = get_test_table();
+    let (set, _) = SetBuilder::<Ipv4Addr>::new(SET_NAME, &table)
+        .unwrap()
+        .with_timeout(Duration::from_secs(3600))
+        .finish();
+    assert!(set.get_set_flags().unwrap().contains(SetFlags::TIMEOUT));
+    assert_eq!(set.get_timeout(), Some(&3_600_000));
+
+    let ip = Ipv4Addr::new(192, 0, 2, 1);
+    let mut elements = SetElementList::for_set(&set).unwrap();
+    elements.add_key_with_ttl(&ip, Duration::from_secs(60));
+    let mut buf = Vec::new();
+    get_test_nlmsg(&mut buf, &mut elements);
+    let (deserialized_list, _) =
+        SetElementList::deserialize(&buf).expect("Couldn't deserialize the object");
+    assert_eq!(deserialized_list, elements);
+    let element = deserialized_list.elements.unwrap().iter().next().cloned();
+    assert_eq!(element.unwrap().get_timeout(), Some(&60_000));
+
+    let listed = SetElement::default().with_expiration(1500u64);
+    assert_eq!(listed.expires_in(), Some(Duration::from_millis(1500)));
+    assert_eq!(SetElement::default().expires_in(), None);
+
+    let mut batch = Batch::new();
+    elements.refresh(&mut batch);
+    let actions: Vec<_> = batch
+        .plan()
+        .unwrap()
+        .changes
+        .into_iter()
+        .map(|change| change.action)
+        .collect();
+    assert_eq!(
+        actions,
+        [PlanAction::Add, PlanAction::Delete, PlanAction::Add]
+    );
+
+    // the kernel reports the time left of the listed elements, which is not sent back
+    let mut listed = elements.clone();
+    for element in listed.elements.as_mut().unwrap().iter_mut() {
+        element.set_expiration(1500u64);
+    }
+    let mut buf = Vec::new();
+    let mut writer = NfNetlinkWriter::new(&mut buf);
+    writer.write_header(NFT_MSG_NEWSETELEM as u16, set.family, 0, 0, None);
+    listed.write_payload(writer.add_data_zeroed(listed.get_size()));
+    writer.finalize_writing_object();
+    let (listed, _) = SetElementList::deserialize(&buf).expect("Couldn't deserialize the object");
+    let element = listed.elements.as_ref().unwrap().iter().next().cloned();
+    assert_eq!(
+        element.unwrap().expires_in(),
+        Some(Duration::from_millis(1500))
+    );
+
+    let mut listed_batch = Batch::new();
+    listed.refresh(&mut listed_batch);
+    let mut batch = Batch::new();
+    elements.refresh(&mut batch);
+    assert_eq!(listed_batch.finalize(), batch.finalize());
+}
+
+#[test]
+fn object_map_elements() {
+    let ip = Ipv4Addr::new(192, 168, 1, 10);
+    let mut set_builder = SetBuilder::<Ipv4Addr>::new(SET_NAME, &get_test_table()).unwrap();
+    assert!(matches!(
+        set_builder.add_object_mapping(&ip, "client-10"),
+        Err(BuilderError::NotAnObjectMap)
+    ));
+
+    let mut set_builder = set_builder.map_to_objects(ObjectType::Counter);
+    set_builder.add_object_mapping(&ip, "client-10").unwrap();
+    let (set, mut elem_list) = set_builder.finish();
+    assert!(set.is_object_map());
+    assert_eq!(set.get_obj_type(), Some(&ObjectType::Counter));
+    assert!(set.missing_attributes(MsgType::Add).is_empty());
+
+    let mut buf = Vec::new();
+    get_test_nlmsg(&mut buf, &mut elem_list);
+    let (deserialized_list, _) =
+        SetElementList::deserialize(&buf).expect("Couldn't deserialize the object");
+    assert_eq!(deserialized_list, elem_list);
+    let element = deserialized_list.elements.unwrap().iter().next().cloned();
+    assert_eq!(
+        element.unwrap().get_objref().map(String::as_str),
+        Some("client-10")
+    );
+
+    let saddr = HighLevelPayload::Network(NetworkHeaderField::IPv4(IPv4HeaderField::Saddr));
+    let rule = get_test_rule()
+        .object_from_map(saddr.build(), &set)
+        .unwrap();
+    let objref = Objref::from_map(&set).unwrap();
+    assert_eq!(objref.get_set_sreg(), Some(&Register::Reg1));
+    assert_eq!(objref.to_string(), format!("objref reg1 map @{}", SET_NAME));
+    assert_eq!(
+        rule,
+        get_test_rule().with_expr(saddr.build()).with_expr(objref)
+    );
+
+    assert!(matches!(
+        get_test_rule().object_from_map(saddr.build(), &get_test_set::<Ipv4Addr>()),
+        Err(BuilderError::NotAnObjectMap)
+    ));
+    assert_eq!(
+        Objref::named(ObjectType::CtHelper, "ftp").to_string(),
+        "objref ct helper \"ftp\""
+    );
+}
+
+#[test]
+fn set_userdata_tlvs() {
+    // userdata of `set s { typeof meta mark; auto-merge; comment "marks" }`, as written by nft
+    let mut raw = Vec::new();
+    raw.extend([0, 4]);
+    raw.extend(1u32.to_ne_bytes());
+    raw.extend([2, 4]);
+    raw.extend(1u32.to_ne_bytes());
+    raw.extend([3, 13, 0, 5]);
+    raw.extend(b"meta\0");
+    raw.extend([1, 4, 0, 4, 3, 0]);
+    raw.extend([7, 6]);
+    raw.extend(b"marks\0");
+
+    let set = get_test_set::<Ipv4Addr>().with_userdata(raw.clone());
+    let userdata = set.get_userdata_tlvs().unwrap();
+    assert_eq!(
+        userdata,
+        SetUserdata {
+            key_byteorder: Some(Endianness::Host),
+            merge_elements: true,
+            key_typeof: Some(TypeofExpr {
+                name: "meta".to_string(),
+                data: vec![0, 4, 3, 0],
+            }),
+            comment: Some("marks".to_string()),
+            ..Default::default()
+        }
+    );
+    assert_eq!(userdata.encode().unwrap(), raw);
+
+    // unknown TLVs are preserved
+    let userdata = SetUserdata {
+        data_byteorder: Some(Endianness::Big),
+        unknown: vec![(42, vec![1, 2, 3])],
+        ..Default::default()
+    };
+    let (set, _) = SetBuilder::<Ipv4Addr>::new(SET_NAME, &get_test_table())
+        .unwrap()
+        .with_userdata_tlvs(&userdata)
+        .unwrap()
+        .finish();
+    assert_eq!(set.get_userdata_tlvs().unwrap(), userdata);
+
+    // the values are not truncated to the 255 bytes their length can describe
+    let mut set = get_test_set::<Ipv4Addr>();
+    let previous = set.clone();
+    let userdata = SetUserdata {
+        comment: Some("a".repeat(255)),
+        ..Default::default()
+    };
+    assert!(matches!(
+        set.set_userdata_tlvs(&userdata),
+        Err(BuilderError::TooLongUserdataValue(7))
+    ));
+    assert_eq!(set, previous);
+    let userdata = SetUserdata {
+        comment: Some("a".repeat(254)),
+        ..Default::default()
+    };
+    set.set_userdata_tlvs(&userdata).unwrap();
+    assert_eq!(set.get_userdata_tlvs().unwrap(), userdata);
+
+    assert_eq!(
+        Set::default().get_userdata_tlvs().unwrap(),
+        SetUserdata::default()
+    );
+    assert!(matches!(
+        SetUserdata::decode(&[0, 4, 1, 0]),
+        Err(DecodeError::InvalidDataSize)
+    ));
+    assert!(matches!(
+        SetUserdata::decode(&[1, 4, 9, 0, 0, 0]),
+        Err(DecodeError::UnknownSetByteorder(_))
+    ));
+}
+
+#[test]
+fn set_flags_validation() {
+    let (set, _) = SetBuilder::<Ipv4Addr>::new(SET_NAME, &get_test_table())
+        .unwrap()
+        .add_flags(SetFlags::TIMEOUT)
+        .dynamic()
+        .finish();
+    assert_eq!(
+        set.get_set_flags(),
+        Some(SetFlags::TIMEOUT | SetFlags::EVAL)
+    );
+    assert_eq!(set.get_flags(), Some(&(NFT_SET_TIMEOUT | NFT_SET_EVAL)));
+    assert!(set.validate().is_ok());
+
+    // the constant sets can be updated from the packet path, but their elements cannot expire
+    let constant = set
+        .clone()
+        .with_set_flags(SetFlags::CONSTANT | SetFlags::EVAL);
+    assert!(constant.validate().is_ok());
+    let constant = set
+        .clone()
+        .with_set_flags(SetFlags::CONSTANT | SetFlags::TIMEOUT);
+    assert!(matches!(
+        constant.validate(),
+        Err(BuilderError::IncompatibleSetFlags(flags)) if flags == SetFlags::CONSTANT | SetFlags::TIMEOUT
+    ));
+
+    let (anonymous, _) = SetBuilder::<Ipv4Addr>::new_anonymous(&get_test_table())
+        .unwrap()
+        .add_flags(SetFlags::TIMEOUT)
+        .finish();
+    assert!(matches!(
+        anonymous.validate(),
+        Err(BuilderError::IncompatibleSetFlags(_))
+    ));
+    assert!(anonymous
+        .with_set_flags(SetFlags::ANONYMOUS | SetFlags::TIMEOUT | SetFlags::EVAL)
+        .validate()
+        .is_ok());
+
+    let object_map = SetBuilder::<Ipv4Addr>::new(SET_NAME, &get_test_table())
+        .unwrap()
+        .map_to_objects(ObjectType::Counter)
+        .dynamic();
+    assert!(object_map.finish().0.validate().is_err());
+}
+
+#[test]
+fn raw_set_builder() {
+    let set = Set::builder()
+        .with_family(ProtocolFamily::Inet)
+        .with_key_type(Ipv4Addr::TYPE)
+        .with_table(TABLE_NAME)
+        .with_name(SET_NAME)
+        .with_userdata(SET_USERDATA)
+        .with_key_len(Ipv4Addr::LEN)
+        .build();
+    assert!(set.missing_attributes(MsgType::Add).is_empty());
+    assert_eq!(set.get_family(), ProtocolFamily::Inet);
+    assert_eq!(set.get_key_data_types(), Some(vec![DataTypeId::IpAddr]));
+    assert_eq!(
+        set.get_userdata(),
+        get_test_set::<Ipv4Addr>().get_userdata()
+    );
+
+    // the sets are added with the type and length of their keys, but deleted by name
+    let keyless = Set::default().with_table(TABLE_NAME).with_name(SET_NAME);
+    assert_eq!(
+        keyless.missing_attributes(MsgType::Add),
+        ["NFTA_SET_KEY_TYPE", "NFTA_SET_KEY_LEN"]
+    );
+    assert!(keyless.missing_attributes(MsgType::Del).is_empty());
+    assert!(Batch::new().add_checked(&keyless, MsgType::Add).is_err());
+}
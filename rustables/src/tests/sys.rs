@@ -0,0 +1,10 @@
+use crate::sys::{self, NFTA_TABLE_NAME, NFT_JUMP, NFT_MSG_NEWRULE};
+
+#[test]
+fn supported_attributes_match_sys_constants() {
+    let attributes = sys::supported_attributes();
+    assert!(attributes.contains(&("NFTA_TABLE_NAME", NFTA_TABLE_NAME as i64)));
+    assert!(attributes.contains(&("NFT_MSG_NEWRULE", NFT_MSG_NEWRULE as i64)));
+    assert!(attributes.contains(&("NFT_JUMP", NFT_JUMP as i64)));
+    assert!(attributes.iter().all(|(name, _)| name.starts_with("NFT")));
+}
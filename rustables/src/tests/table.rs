@@ -1,12 +1,21 @@
+use std::mem::size_of;
+
 use crate::{
-    nlmsg::{get_operation_from_nlmsghdr_type, nft_nlmsg_maxsize, NfNetlinkDeserializable},
-    sys::{NFT_MSG_DELTABLE, NFT_MSG_NEWTABLE},
-    MsgType, Table,
+    error::{BuilderError, DecodeError},
+    nlmsg::{
+        get_operation_from_nlmsghdr_type, nft_nlmsg_maxsize, NfNetlinkDeserializable,
+        NfNetlinkObject,
+    },
+    sys::{
+        nlmsghdr, NFTA_TABLE_NAME, NFT_MSG_DELTABLE, NFT_MSG_NEWTABLE, NLM_F_DUMP_INTR, NLM_F_MULTI,
+    },
+    table_contents::GenerationCheck,
+    Chain, MsgType, ProtocolFamily, Rule, Table, TableContents,
 };
 
 use super::{
-    get_test_nlmsg, get_test_nlmsg_with_msg_type, get_test_table, get_test_table_raw_expr,
-    get_test_table_with_userdata_raw_expr, TABLE_USERDATA,
+    get_test_chain, get_test_nlmsg, get_test_nlmsg_with_msg_type, get_test_rule, get_test_table,
+    get_test_table_raw_expr, get_test_table_with_userdata_raw_expr, TABLE_NAME, TABLE_USERDATA,
 };
 
 #[test]
@@ -65,3 +74,130 @@ fn parse_table() {
     assert_eq!(table, deserialized_table);
     assert_eq!(remaining.len(), 0);
 }
+
+#[test]
+fn parse_table_of_unknown_family() {
+    let mut table = get_test_table().with_family(ProtocolFamily::Other(42));
+    let mut buf = Vec::with_capacity(nft_nlmsg_maxsize() as usize);
+    let (_nlmsghdr, nfgenmsg, _raw_expr) = get_test_nlmsg(&mut buf, &mut table);
+    assert_eq!(nfgenmsg.nfgen_family, 42);
+
+    let (deserialized_table, _) =
+        Table::deserialize(&buf).expect("Couldn't deserialize the object");
+    assert_eq!(deserialized_table.get_family(), ProtocolFamily::Other(42));
+    assert_eq!(table, deserialized_table);
+
+    assert_eq!(
+        ProtocolFamily::from(libc::NFPROTO_BRIDGE),
+        ProtocolFamily::Bridge
+    );
+    assert_eq!(i32::from(ProtocolFamily::Other(42)), 42);
+    assert_eq!(ProtocolFamily::Other(42).to_string(), "family 42");
+}
+
+/// Returns a dump message holding `table`, read from the generation `generation` of the ruleset.
+fn dump_message(table: &Table, generation: u16, flags: u32) -> Vec<u8> {
+    let mut msg = table.to_message_with_flags(MsgType::Add, 1, (NLM_F_MULTI | flags) as u16);
+    // the generation is stored in the resource id of the nfgenmsg header
+    let res_id = size_of::<nlmsghdr>() + 2;
+    msg[res_id..res_id + 2].copy_from_slice(&generation.to_be_bytes());
+    msg
+}
+
+#[test]
+fn dump_generation_check() {
+    let table = get_test_table();
+
+    let mut check = GenerationCheck::default();
+    check.record(&dump_message(&table, 7, 0)).unwrap();
+    check.record(&dump_message(&table, 7, 0)).unwrap();
+    assert!(check.is_consistent());
+    check.record(&dump_message(&table, 8, 0)).unwrap();
+    assert!(!check.is_consistent());
+
+    // the dumps interrupted by a change are refused by the parser
+    let mut check = GenerationCheck::default();
+    assert!(matches!(
+        check.record(&dump_message(&table, 7, NLM_F_DUMP_INTR)),
+        Err(DecodeError::ConcurrentGenerationUpdate)
+    ));
+}
+
+#[test]
+#[cfg(feature = "socket")]
+fn list_tables_filtered_by_family() {
+    use crate::query::get_list_of_objects_for_family;
+    use crate::sys::NFT_MSG_GETTABLE;
+
+    let filter = Table::new(ProtocolFamily::Ipv6);
+    let buf = get_list_of_objects_for_family(
+        NFT_MSG_GETTABLE as u16,
+        filter.get_family(),
+        0,
+        Some(&filter),
+    )
+    .unwrap();
+    let (_, msg) = crate::parser::parse_nlmsg(&buf).unwrap();
+    match msg {
+        crate::parser::NlMsg::NfGenMsg(nfgenmsg, raw_expr) => {
+            assert_eq!(nfgenmsg.nfgen_family, libc::NFPROTO_IPV6 as u8);
+            // the kernel only needs the family to filter the tables
+            assert!(raw_expr.is_empty());
+        }
+        _ => panic!("Invalid return value type, expected a valid message"),
+    }
+}
+
+#[test]
+fn table_contents_links_rules_to_chains() {
+    let chain = get_test_chain();
+    let other_chain = Chain::new(&get_test_table()).with_name("other");
+    let rule = get_test_rule().with_userdata(b"first".to_vec());
+    let other_rule = Rule::new(&other_chain).unwrap();
+    let contents = TableContents {
+        table: get_test_table(),
+        chains: vec![chain.clone(), other_chain.clone()],
+        sets: Vec::new(),
+        objects: Vec::new(),
+        rules: vec![rule.clone(), other_rule.clone()],
+    };
+
+    assert_eq!(contents.rules_of(&chain).collect::<Vec<_>>(), [&rule]);
+    assert_eq!(
+        contents.rules_of(&other_chain).collect::<Vec<_>>(),
+        [&other_rule]
+    );
+    assert_eq!(contents.chain_of(&other_rule), Some(&other_chain));
+    assert_eq!(contents.chain_of(&Rule::default()), None);
+}
+
+/// A table with an attribute that no kernel headers declare.
+// the struct is rewritten before deriving, as the derives must not see the missing field
+#[rustables_macros::nfnetlink_struct(derive_deserialize = false)]
+#[derive(Debug, Default)]
+struct FutureTable {
+    #[field(NFTA_TABLE_NAME)]
+    name: String,
+    #[field(
+        optional = true,
+        stub_if_missing = true,
+        crate::sys::NFTA_TABLE_FROM_THE_FUTURE
+    )]
+    future: u32,
+}
+
+#[test]
+fn unsupported_attribute_stubs() {
+    let table = get_test_table().try_with_userdata(TABLE_USERDATA).unwrap();
+    assert_eq!(table.get_userdata(), Some(&TABLE_USERDATA.into()));
+
+    let mut table = FutureTable::default().with_name(TABLE_NAME);
+    assert!(matches!(
+        table.try_set_future(1u32),
+        Err(BuilderError::UnsupportedOnThisBuild(
+            "NFTA_TABLE_FROM_THE_FUTURE"
+        ))
+    ));
+    assert!(table.try_with_future(1u32).is_err());
+    assert_eq!(FutureTable::default().get_future(), None);
+}
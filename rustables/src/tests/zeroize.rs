@@ -0,0 +1,23 @@
+use crate::zeroize::{zeroize, zeroize_allocation};
+
+#[test]
+fn buffers_are_zeroized() {
+    let mut buf = b"table inet filter".to_vec();
+    zeroize(&mut buf[6..]);
+    assert_eq!(buf, b"table \0\0\0\0\0\0\0\0\0\0\0");
+}
+
+#[test]
+fn allocations_are_zeroized() {
+    let mut buf = Vec::with_capacity(64);
+    buf.extend_from_slice(b"table inet filter");
+    // the bytes past the length are left over from a previous use
+    buf.truncate(5);
+    zeroize_allocation(&mut buf);
+    assert!(buf.is_empty());
+    assert!(buf.capacity() >= 64);
+    assert!(buf
+        .spare_capacity_mut()
+        .iter()
+        .all(|b| unsafe { b.assume_init() } == 0));
+}
@@ -0,0 +1,57 @@
+//! Overwriting of the buffers holding netlink messages, for the `zeroize` feature.
+
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{compiler_fence, Ordering};
+
+/// Overwrites `buf` with zeros. Unlike `fill(0)`, the writes cannot be optimized out when `buf`
+/// is not read afterwards, e.g. right before it is freed.
+#[cfg_attr(not(feature = "socket"), allow(dead_code))]
+pub(crate) fn zeroize(buf: &mut [u8]) {
+    for b in buf.iter_mut() {
+        unsafe { std::ptr::write_volatile(b, 0) };
+    }
+    compiler_fence(Ordering::SeqCst);
+}
+
+/// Overwrites the whole allocation of `buf` with zeros, including the bytes past its length,
+/// which may hold the messages of a previous use, and leaves it empty.
+pub(crate) fn zeroize_allocation(buf: &mut Vec<u8>) {
+    buf.clear();
+    for b in buf.spare_capacity_mut() {
+        unsafe { std::ptr::write_volatile(b.as_mut_ptr(), 0) };
+    }
+    compiler_fence(Ordering::SeqCst);
+}
+
+/// Calls [`zeroize_allocation`] on `buf` when the `zeroize` feature is enabled, before `buf`
+/// is dropped or reused.
+pub(crate) fn zeroize_if_enabled(buf: &mut Vec<u8>) {
+    if cfg!(feature = "zeroize") {
+        zeroize_allocation(buf);
+    }
+}
+
+/// A buffer that is overwritten with zeros when it is dropped, if the `zeroize` feature is
+/// enabled, e.g. the finalized messages of a batch once they are sent.
+#[derive(Debug, Default)]
+pub(crate) struct ZeroOnDrop(pub(crate) Vec<u8>);
+
+impl Deref for ZeroOnDrop {
+    type Target = Vec<u8>;
+
+    fn deref(&self) -> &Vec<u8> {
+        &self.0
+    }
+}
+
+impl DerefMut for ZeroOnDrop {
+    fn deref_mut(&mut self) -> &mut Vec<u8> {
+        &mut self.0
+    }
+}
+
+impl Drop for ZeroOnDrop {
+    fn drop(&mut self) {
+        zeroize_if_enabled(&mut self.0);
+    }
+}